@@ -1,34 +1,70 @@
-const CACHE = "kandil-cache-v1";
-const OFFLINE_URLS = ["/", "/pwa", "/manifest.webmanifest"];
+// Generated by `kandil pwa`. The app shell is precached on install and served
+// cache-first so the app opens offline; API calls go to the network first and
+// fall back to the last cached response.
+const CACHE = "kandil-__CACHE_VERSION__";
+const APP_SHELL = __APP_SHELL__;
 
 self.addEventListener("install", (event) => {
   event.waitUntil(
-    caches.open(CACHE).then((cache) => {
-      return cache.addAll(OFFLINE_URLS);
-    })
+    caches
+      .open(CACHE)
+      .then((cache) => cache.addAll(APP_SHELL))
+      .then(() => self.skipWaiting())
   );
 });
 
 self.addEventListener("activate", (event) => {
   event.waitUntil(
-    caches.keys().then((keys) =>
-      Promise.all(keys.map((key) => key !== CACHE && caches.delete(key)))
-    )
+    caches
+      .keys()
+      .then((keys) =>
+        Promise.all(keys.filter((key) => key !== CACHE).map((key) => caches.delete(key)))
+      )
+      .then(() => self.clients.claim())
   );
 });
 
+async function networkFirst(request) {
+  const cache = await caches.open(CACHE);
+  try {
+    const response = await fetch(request);
+    if (request.method === "GET" && response.ok) {
+      cache.put(request, response.clone());
+    }
+    return response;
+  } catch (err) {
+    const cached = await cache.match(request);
+    if (cached) return cached;
+    return new Response(JSON.stringify({ error: "offline" }), {
+      status: 503,
+      headers: { "Content-Type": "application/json" },
+    });
+  }
+}
+
+async function cacheFirst(request) {
+  const cached = await caches.match(request);
+  if (cached) return cached;
+  const response = await fetch(request);
+  if (response.ok) {
+    const cache = await caches.open(CACHE);
+    cache.put(request, response.clone());
+  }
+  return response;
+}
+
 self.addEventListener("fetch", (event) => {
-  event.respondWith(
-    caches.match(event.request).then((cached) => {
-      return (
-        cached ||
-        fetch(event.request).then((response) => {
-          const copy = response.clone();
-          caches.open(CACHE).then((cache) => cache.put(event.request, copy));
-          return response;
-        })
-      );
-    })
-  );
-});
+  const url = new URL(event.request.url);
+  if (url.origin !== self.location.origin) return;
 
+  if (url.pathname.startsWith("/api/")) {
+    event.respondWith(networkFirst(event.request));
+  } else if (event.request.mode === "navigate") {
+    // Client-side routes all load the shell.
+    event.respondWith(
+      fetch(event.request).catch(() => caches.match("/index.html"))
+    );
+  } else if (event.request.method === "GET") {
+    event.respondWith(cacheFirst(event.request));
+  }
+});
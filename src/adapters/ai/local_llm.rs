@@ -24,10 +24,7 @@ impl LocalLLMAdapter {
             Some(path) => path.join(&spec.filename),
             None => {
                 // Default to a models directory in user's home
-                let mut path = dirs::data_dir()
-                    .unwrap_or_else(|| std::env::current_dir().unwrap())
-                    .join("kandil")
-                    .join("models");
+                let mut path = crate::utils::data_dir::path().join("models");
                 std::fs::create_dir_all(&path)
                     .map_err(|e| LocalModelError::IoError { source: e })?;
                 path.join(&spec.filename)
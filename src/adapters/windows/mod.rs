@@ -1,3 +1,5 @@
+use crate::benchmark::{local_endpoint, local_runtimes, probe_url};
+use crate::utils::config::Config;
 use reqwest::Client;
 use serde::Serialize;
 use std::process::Command;
@@ -7,10 +9,20 @@ use tracing::warn;
 #[derive(Debug, Serialize)]
 pub struct WindowsAdapterStatus {
     pub wsl_ip: Option<String>,
-    pub ollama_reachable: bool,
-    pub lmstudio_reachable: bool,
-    pub gpt4all_reachable: bool,
-    pub foundry_reachable: bool,
+    /// Local runtimes, probed at their `[endpoints]` entry or default port.
+    pub runtimes: Vec<RuntimeProbe>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuntimeProbe {
+    /// Provider name as used in `ai_provider`, e.g. `lmstudio`.
+    pub provider: String,
+    pub name: String,
+    /// Base URL of the runtime's API.
+    pub endpoint: String,
+    /// The URL that was requested.
+    pub probe_url: String,
+    pub reachable: bool,
 }
 
 impl WindowsAdapterStatus {
@@ -19,21 +31,40 @@ impl WindowsAdapterStatus {
             .timeout(Duration::from_secs(2))
             .build()
             .unwrap_or_else(|_| Client::new());
+        let config = Config::load().unwrap_or_default();
 
-        let wsl_ip = detect_wsl_ip();
-        let ollama_url = wsl_ip
-            .as_ref()
-            .map(|ip| format!("http://{}:11434/api/tags", ip))
-            .unwrap_or_else(|| "http://localhost:11434/api/tags".to_string());
+        let runtimes = futures_util::future::join_all(local_runtimes().into_iter().map(
+            |(provider, name)| {
+                let endpoint = local_endpoint(&provider, &config);
+                let probe_url = probe_url(&provider, &endpoint);
+                let client = &client;
+                async move {
+                    RuntimeProbe {
+                        provider,
+                        name,
+                        reachable: http_ok(client, &probe_url).await,
+                        endpoint,
+                        probe_url,
+                    }
+                }
+            },
+        ))
+        .await;
 
         Self {
-            wsl_ip,
-            ollama_reachable: http_ok(&client, &ollama_url).await,
-            lmstudio_reachable: http_ok(&client, "http://localhost:1234/v1/models").await,
-            gpt4all_reachable: http_ok(&client, "http://localhost:4891/v1/models").await,
-            foundry_reachable: http_ok(&client, "http://localhost:5001/v1/models").await,
+            wsl_ip: detect_wsl_ip(),
+            runtimes,
         }
     }
+
+    /// The runtimes that answered.
+    pub fn reachable(&self) -> impl Iterator<Item = &RuntimeProbe> {
+        self.runtimes.iter().filter(|runtime| runtime.reachable)
+    }
+
+    pub fn is_reachable(&self, provider: &str) -> bool {
+        self.reachable().any(|runtime| runtime.provider == provider)
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,356 @@
+//! Side-by-side runtime comparison
+//!
+//! `kandil local-model benchmark --all-runtimes --compare` turns a
+//! [`BenchmarkReport`] into one table: a column per runtime and a row per
+//! metric, with the best value in each row marked. Latency percentiles come
+//! from the per-prompt samples. Rows where only one runtime has a value, such
+//! as streaming metrics for a runtime that doesn't stream, mark nothing.
+//! Memory figures measured different ways (see [`MemorySource`]) can't be
+//! compared, so each cell names its source and the best is only marked among
+//! runtimes measured the same way.
+
+use super::{BenchmarkReport, MemorySource, RuntimeBenchmark};
+use std::fmt::Write as _;
+
+/// Marks the best value in the text table.
+const BEST: &str = "★";
+/// Explains the source in memory cells, for under the tables.
+const MEMORY_NOTE: &str = "Memory: kandil = kandil process RSS, runtime = RSS of the runtime's \
+                           processes, system = system-wide growth; compared within a source only";
+
+/// The metrics of every runtime in a report, one row per metric.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub runtimes: Vec<String>,
+    pub rows: Vec<ComparisonRow>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparisonRow {
+    pub metric: &'static str,
+    /// One cell per runtime; `None` when the runtime has no value.
+    pub cells: Vec<Option<String>>,
+    /// Indexes of the runtimes with the best value; ties share it.
+    pub best: Vec<usize>,
+}
+
+#[derive(Clone, Copy)]
+enum Better {
+    Lower,
+    Higher,
+}
+
+impl Comparison {
+    pub fn new(report: &BenchmarkReport) -> Self {
+        let results = &report.results;
+        let same = |_: &RuntimeBenchmark| ();
+        let tokens_prefix = |runtime: &RuntimeBenchmark| {
+            if runtime.tokens_approximate {
+                "~"
+            } else {
+                ""
+            }
+        };
+        let rows = vec![
+            row(results, "p50 latency", Better::Lower, same, |runtime| {
+                percentile(runtime, 50).map(|ms| (ms as f64, format!("{} ms", ms)))
+            }),
+            row(results, "p95 latency", Better::Lower, same, |runtime| {
+                percentile(runtime, 95).map(|ms| (ms as f64, format!("{} ms", ms)))
+            }),
+            row(
+                results,
+                "Time to first token",
+                Better::Lower,
+                same,
+                |runtime| runtime.ttft_ms.map(|ms| (ms as f64, format!("{} ms", ms))),
+            ),
+            row(
+                results,
+                "Tokens/s (end to end)",
+                Better::Higher,
+                same,
+                |runtime| {
+                    let speed = runtime.average_tokens_per_sec;
+                    Some((speed as f64, format!("{}{}", tokens_prefix(runtime), speed)))
+                },
+            ),
+            row(
+                results,
+                "Tokens/s (generation)",
+                Better::Higher,
+                same,
+                |runtime| {
+                    runtime
+                        .generation_tokens_per_sec
+                        .map(|speed| (speed as f64, format!("{}{}", tokens_prefix(runtime), speed)))
+                },
+            ),
+            row(
+                results,
+                "Memory peak",
+                Better::Lower,
+                |runtime| std::mem::discriminant(&runtime.memory_source),
+                |runtime| {
+                    let mb = runtime.memory_peak_mb;
+                    let source = runtime.memory_source.short_label();
+                    Some((mb as f64, format!("{} MB ({})", mb, source)))
+                },
+            ),
+        ];
+        Self {
+            runtimes: results
+                .iter()
+                .map(|runtime| runtime.runtime.clone())
+                .collect(),
+            rows,
+        }
+    }
+
+    /// A plain-text table for terminals, best values marked with ★.
+    pub fn to_text(&self) -> String {
+        let cells: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| {
+                (0..self.runtimes.len())
+                    .map(|index| {
+                        let value = row.cells[index].as_deref().unwrap_or("—");
+                        if row.best.contains(&index) {
+                            format!("{} {}", value, BEST)
+                        } else {
+                            value.to_string()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let metric_width = self
+            .rows
+            .iter()
+            .map(|row| row.metric.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("Metric".len());
+        let widths: Vec<usize> = self
+            .runtimes
+            .iter()
+            .enumerate()
+            .map(|(index, runtime)| {
+                cells
+                    .iter()
+                    .map(|row| row[index].chars().count())
+                    .chain([runtime.chars().count()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let mut out = String::new();
+        let mut line = |first: &str, rest: &[String]| {
+            let _ = write!(out, "{:<width$}", first, width = metric_width);
+            for (value, width) in rest.iter().zip(&widths) {
+                let pad = width.saturating_sub(value.chars().count());
+                let _ = write!(out, "  {}{}", " ".repeat(pad), value);
+            }
+            out.push('\n');
+        };
+        line("Metric", &self.runtimes);
+        let rule: Vec<String> = widths.iter().map(|width| "─".repeat(*width)).collect();
+        line(&"─".repeat(metric_width), &rule);
+        for (row, cells) in self.rows.iter().zip(&cells) {
+            line(row.metric, cells);
+        }
+        out.push_str(&format!("{} best in row\n", BEST));
+        out.push_str(MEMORY_NOTE);
+        out.push('\n');
+        out
+    }
+
+    /// The same table in Markdown, best values in bold.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "| Metric | {} |", self.runtimes.join(" | "));
+        let _ = writeln!(out, "|---|{}", "---:|".repeat(self.runtimes.len()));
+        for row in &self.rows {
+            let cells: Vec<String> = (0..self.runtimes.len())
+                .map(|index| match row.cells[index].as_deref() {
+                    Some(value) if row.best.contains(&index) => format!("**{}**", value),
+                    Some(value) => value.to_string(),
+                    None => "—".to_string(),
+                })
+                .collect();
+            let _ = writeln!(out, "| {} | {} |", row.metric, cells.join(" | "));
+        }
+        let _ = writeln!(out, "\n{}", MEMORY_NOTE);
+        out
+    }
+}
+
+/// One metric across `results`. A value is marked best against the others
+/// in its `group`, and only when at least two of them have one.
+fn row<K: PartialEq>(
+    results: &[RuntimeBenchmark],
+    metric: &'static str,
+    better: Better,
+    group: impl Fn(&RuntimeBenchmark) -> K,
+    value: impl Fn(&RuntimeBenchmark) -> Option<(f64, String)>,
+) -> ComparisonRow {
+    let values: Vec<Option<(f64, String)>> = results.iter().map(value).collect();
+    let groups: Vec<K> = results.iter().map(group).collect();
+    let best = values
+        .iter()
+        .enumerate()
+        .filter_map(|(index, value)| {
+            let (number, _) = value.as_ref()?;
+            let peers: Vec<f64> = values
+                .iter()
+                .zip(&groups)
+                .filter(|(_, key)| **key == groups[index])
+                .filter_map(|(value, _)| value.as_ref().map(|(number, _)| *number))
+                .collect();
+            let best_value = match better {
+                Better::Lower => peers.iter().copied().reduce(f64::min),
+                Better::Higher => peers.iter().copied().reduce(f64::max),
+            }?;
+            (peers.len() > 1 && *number == best_value).then_some(index)
+        })
+        .collect();
+    ComparisonRow {
+        metric,
+        cells: values
+            .into_iter()
+            .map(|value| value.map(|(_, text)| text))
+            .collect(),
+        best,
+    }
+}
+
+/// The `percent`th percentile of the runtime's sample latencies, by nearest
+/// rank.
+fn percentile(runtime: &RuntimeBenchmark, percent: usize) -> Option<u64> {
+    let mut latencies: Vec<u64> = runtime
+        .samples
+        .iter()
+        .map(|sample| sample.latency_ms)
+        .collect();
+    if latencies.is_empty() {
+        return None;
+    }
+    latencies.sort_unstable();
+    let rank = (percent * latencies.len()).div_ceil(100).max(1);
+    latencies.get(rank - 1).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::{MemorySource, PromptSample};
+
+    fn runtime(name: &str, latencies: &[u64], ttft: Option<u64>, memory: u64) -> RuntimeBenchmark {
+        RuntimeBenchmark {
+            runtime: name.to_string(),
+            provider: name.to_string(),
+            average_latency_ms: latencies.iter().sum::<u64>() / latencies.len() as u64,
+            first_call_latency_ms: latencies[0],
+            repeat_call_latency_ms: None,
+            average_tokens_per_sec: 40,
+            tokenizer: "cl100k_base".to_string(),
+            tokens_approximate: true,
+            ttft_ms: ttft,
+            generation_tokens_per_sec: None,
+            memory_peak_mb: memory,
+            memory_source: MemorySource::CurrentProcess,
+            battery_impact: None,
+            samples: latencies
+                .iter()
+                .map(|latency| PromptSample {
+                    prompt: "p".to_string(),
+                    latency_ms: *latency,
+                    output_tokens: 10,
+                    ttft_ms: ttft,
+                    generation_tokens_per_sec: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn report(results: Vec<RuntimeBenchmark>) -> BenchmarkReport {
+        BenchmarkReport {
+            model: "m".to_string(),
+            requested_runtime: None,
+            hardware: crate::core::hardware::detect_hardware(),
+            prompts: vec!["p".to_string()],
+            timestamp: chrono::Utc::now(),
+            results,
+            warnings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn best_values_are_marked_per_row() {
+        let comparison = Comparison::new(&report(vec![
+            runtime("ollama", &[100, 200, 300, 400, 1000], Some(50), 900),
+            runtime("lmstudio", &[150, 160, 170, 180, 190], None, 700),
+        ]));
+        assert_eq!(comparison.runtimes, ["ollama", "lmstudio"]);
+
+        let p50 = &comparison.rows[0];
+        assert_eq!(p50.cells[0].as_deref(), Some("300 ms"));
+        assert_eq!(p50.cells[1].as_deref(), Some("170 ms"));
+        assert_eq!(p50.best, [1]);
+        let p95 = &comparison.rows[1];
+        assert_eq!(p95.cells[0].as_deref(), Some("1000 ms"));
+        // Only one runtime streams, so there is nothing to compare.
+        assert_eq!(comparison.rows[2].cells[1], None);
+        assert!(comparison.rows[2].best.is_empty());
+        // Equal throughput: both are best.
+        assert_eq!(comparison.rows[3].best, [0, 1]);
+        assert_eq!(comparison.rows[3].cells[0].as_deref(), Some("~40"));
+        assert_eq!(comparison.rows[5].best, [1]);
+
+        let text = comparison.to_text();
+        assert!(text.starts_with("Metric "));
+        assert!(text.contains("170 ms ★"));
+        assert!(text.contains("—"));
+        let markdown = comparison.to_markdown();
+        assert!(markdown.starts_with("| Metric | ollama | lmstudio |\n|---|---:|---:|\n"));
+        assert!(markdown.contains("| p50 latency | 300 ms | **170 ms** |"));
+    }
+
+    #[test]
+    fn memory_is_only_compared_within_a_source() {
+        let mut ollama = runtime("ollama", &[100], None, 4000);
+        ollama.memory_source = MemorySource::RuntimeProcess {
+            processes: vec!["ollama".to_string()],
+        };
+        let mut llamacpp = runtime("llamacpp", &[100], None, 3000);
+        llamacpp.memory_source = MemorySource::RuntimeProcess {
+            processes: vec!["llama-server".to_string()],
+        };
+        let cloud = runtime("openai", &[100], None, 50);
+        let mut lmstudio = runtime("lmstudio", &[100], None, 200);
+        lmstudio.memory_source = MemorySource::SystemDelta;
+
+        let comparison = Comparison::new(&report(vec![ollama, llamacpp, cloud, lmstudio]));
+        let memory = &comparison.rows[5];
+        assert_eq!(memory.metric, "Memory peak");
+        // The kandil process and the system delta have nothing to compare with.
+        assert_eq!(memory.best, [1]);
+        assert_eq!(memory.cells[1].as_deref(), Some("3000 MB (runtime)"));
+        assert_eq!(memory.cells[2].as_deref(), Some("50 MB (kandil)"));
+        assert_eq!(memory.cells[3].as_deref(), Some("200 MB (system)"));
+        assert!(comparison.to_text().contains(MEMORY_NOTE));
+        assert!(comparison.to_markdown().contains(MEMORY_NOTE));
+    }
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let single = runtime("r", &[42], None, 1);
+        assert_eq!(percentile(&single, 50), Some(42));
+        assert_eq!(percentile(&single, 95), Some(42));
+        let ten = runtime("r", &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10], None, 1);
+        assert_eq!(percentile(&ten, 50), Some(5));
+        assert_eq!(percentile(&ten, 95), Some(10));
+    }
+}
@@ -0,0 +1,199 @@
+//! Benchmark history
+//!
+//! Every benchmark report is appended to `benchmark_history.jsonl` in the
+//! kandil data directory, one JSON object per line, so gradual regressions
+//! across driver and model updates show up as a trend. Once the file passes
+//! [`MAX_HISTORY_BYTES`] it is rotated to `benchmark_history.jsonl.1`, which
+//! replaces the previous rotation; reads cover both files.
+
+use super::BenchmarkReport;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Size at which the history file is rotated.
+pub const MAX_HISTORY_BYTES: u64 = 5 * 1024 * 1024;
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The parts of a recorded report the trend needs.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HistoryEntry {
+    pub model: String,
+    pub timestamp: DateTime<Utc>,
+    pub results: Vec<HistoryResult>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HistoryResult {
+    pub runtime: String,
+    pub average_latency_ms: u64,
+    pub average_tokens_per_sec: u32,
+    /// Runs recorded before this field existed counted words, so they are
+    /// approximate.
+    #[serde(default = "counted_words")]
+    pub tokens_approximate: bool,
+    #[serde(default)]
+    pub ttft_ms: Option<u64>,
+    #[serde(default)]
+    pub generation_tokens_per_sec: Option<u32>,
+}
+
+fn counted_words() -> bool {
+    true
+}
+
+pub fn history_path() -> PathBuf {
+    crate::utils::data_dir::path().join("benchmark_history.jsonl")
+}
+
+fn rotated_path(path: &Path) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+    PathBuf::from(rotated)
+}
+
+/// Appends `report` as one line, rotating first if the line would take the
+/// file past `max_bytes`.
+pub fn append(path: &Path, report: &BenchmarkReport, max_bytes: u64) -> Result<()> {
+    let line = format!("{}\n", serde_json::to_string(report)?);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let size = std::fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+    if size > 0 && size + line.len() as u64 > max_bytes {
+        std::fs::rename(path, rotated_path(path))
+            .with_context(|| format!("Failed to rotate {}", path.display()))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// The last `last` recorded runs, oldest first, limited to `model` if given.
+/// Lines that can't be read are skipped with a warning.
+pub fn load(path: &Path, model: Option<&str>, last: usize) -> Result<Vec<HistoryEntry>> {
+    let mut entries = Vec::new();
+    for file in [rotated_path(path), path.to_path_buf()] {
+        let contents = match std::fs::read_to_string(&file) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err).context(format!("Failed to read {}", file.display())),
+        };
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<HistoryEntry>(line) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => {
+                    log::warn!("Skipping line {} of {}: {}", index + 1, file.display(), err)
+                }
+            }
+        }
+    }
+    if let Some(model) = model {
+        entries.retain(|entry| entry.model == model);
+    }
+    entries.sort_by_key(|entry| entry.timestamp);
+    let skip = entries.len().saturating_sub(last);
+    Ok(entries.split_off(skip))
+}
+
+/// One block character per value, scaled between the smallest and largest.
+pub fn sparkline(values: &[f64]) -> String {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|&value| {
+            if max - min <= f64::EPSILON {
+                return SPARK_LEVELS[SPARK_LEVELS.len() / 2];
+            }
+            let level = ((value - min) / (max - min) * (SPARK_LEVELS.len() - 1) as f64).round();
+            SPARK_LEVELS[level as usize]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::hardware::detect_hardware;
+
+    fn report(model: &str, latency_ms: u64) -> BenchmarkReport {
+        BenchmarkReport {
+            model: model.to_string(),
+            requested_runtime: None,
+            hardware: detect_hardware(),
+            prompts: Vec::new(),
+            timestamp: Utc::now(),
+            results: vec![super::super::RuntimeBenchmark {
+                runtime: "ollama".to_string(),
+                provider: "ollama".to_string(),
+                average_latency_ms: latency_ms,
+                first_call_latency_ms: latency_ms,
+                repeat_call_latency_ms: None,
+                average_tokens_per_sec: 30,
+                tokenizer: "llama3 (reported by ollama)".to_string(),
+                tokens_approximate: false,
+                ttft_ms: Some(200),
+                generation_tokens_per_sec: Some(40),
+                memory_peak_mb: 0,
+                memory_source: super::super::MemorySource::CurrentProcess,
+                battery_impact: None,
+                samples: Vec::new(),
+            }],
+            warnings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn history_is_filtered_limited_and_survives_rotation() {
+        let dir = std::env::temp_dir().join(format!("kandil_bench_{}", uuid::Uuid::new_v4()));
+        let path = dir.join("benchmark_history.jsonl");
+        // Room for two runs but not three
+        let line_len = serde_json::to_string(&report("a", 1000)).unwrap().len() as u64 + 1;
+        let max_bytes = line_len * 5 / 2;
+
+        for latency in [1000, 1100, 1200] {
+            append(&path, &report("a", latency), max_bytes).unwrap();
+        }
+        append(&path, &report("b", 500), max_bytes).unwrap();
+        append(&path, &report("a", 1300), max_bytes).unwrap();
+        std::fs::write(rotated_path(&path), {
+            let mut contents = std::fs::read_to_string(rotated_path(&path)).unwrap();
+            contents.push_str("not json\n");
+            contents
+        })
+        .unwrap();
+
+        let all = load(&path, None, usize::MAX).unwrap();
+        assert_eq!(all.len(), 3, "the second rotation drops the first two runs");
+        let a = load(&path, Some("a"), 1).unwrap();
+        assert_eq!(a.len(), 1);
+        assert_eq!(a[0].results[0].average_latency_ms, 1300);
+        assert_eq!(a[0].results[0].ttft_ms, Some(200));
+        assert!(!a[0].results[0].tokens_approximate);
+
+        let legacy: HistoryResult = serde_json::from_str(
+            r#"{"runtime":"ollama","average_latency_ms":900,"average_tokens_per_sec":25}"#,
+        )
+        .unwrap();
+        assert!(legacy.tokens_approximate, "older runs counted words");
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn sparklines_span_the_range() {
+        assert_eq!(sparkline(&[1.0, 4.5, 8.0]), "▁▅█");
+        assert_eq!(sparkline(&[3.0, 3.0]), "▅▅");
+        assert_eq!(sparkline(&[]), "");
+    }
+}
@@ -0,0 +1,194 @@
+//! Memory attribution for benchmarks
+//!
+//! System-wide used memory includes everything else running on the machine,
+//! so a runtime's footprint is measured from its own processes instead: this
+//! process for in-process and cloud providers, and the runtime's server
+//! processes for local runtimes such as Ollama. When no runtime process can be
+//! found, the growth in system-wide usage since the baseline is reported and
+//! labelled as such.
+
+use serde::Serialize;
+use sysinfo::{Pid, ProcessExt, RefreshKind, System, SystemExt};
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+/// Where a memory figure came from.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MemorySource {
+    /// Resident memory of the kandil process itself.
+    CurrentProcess,
+    /// Resident memory summed over the runtime's processes.
+    RuntimeProcess { processes: Vec<String> },
+    /// Growth of system-wide used memory; not attributable to one process.
+    SystemDelta,
+}
+
+impl MemorySource {
+    /// A word for table cells: `kandil`, `runtime` or `system`.
+    pub fn short_label(&self) -> &'static str {
+        match self {
+            MemorySource::CurrentProcess => "kandil",
+            MemorySource::RuntimeProcess { .. } => "runtime",
+            MemorySource::SystemDelta => "system",
+        }
+    }
+}
+
+impl std::fmt::Display for MemorySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MemorySource::CurrentProcess => f.write_str("kandil process RSS"),
+            MemorySource::RuntimeProcess { processes } => {
+                write!(f, "RSS of {}", processes.join(", "))
+            }
+            MemorySource::SystemDelta => {
+                f.write_str("system-wide delta; runtime process not found")
+            }
+        }
+    }
+}
+
+/// Process name prefixes (lowercase) for runtimes that serve models out of
+/// process, from the provider's backend.
+fn runtime_process_names(provider: &str) -> Option<&'static [&'static str]> {
+    let names = crate::core::adapters::ai::backend::get(provider)?.process_names();
+    (!names.is_empty()).then_some(names)
+}
+
+/// Samples the memory attributable to one provider during a benchmark.
+pub struct MemoryProbe {
+    sys: System,
+    target: Target,
+    peak_mb: u64,
+}
+
+enum Target {
+    Current(Pid),
+    Runtime(&'static [&'static str]),
+    SystemDelta { baseline_mb: u64 },
+}
+
+impl MemoryProbe {
+    pub fn for_provider(provider: &str) -> Self {
+        let mut sys = System::new_with_specifics(RefreshKind::new());
+        let target = match runtime_process_names(provider) {
+            Some(names) => {
+                sys.refresh_processes();
+                if matching_processes(&sys, names).next().is_some() {
+                    Target::Runtime(names)
+                } else {
+                    sys.refresh_memory();
+                    Target::SystemDelta {
+                        baseline_mb: sys.used_memory() / BYTES_PER_MB,
+                    }
+                }
+            }
+            None => match sysinfo::get_current_pid() {
+                Ok(pid) => Target::Current(pid),
+                Err(_) => {
+                    sys.refresh_memory();
+                    Target::SystemDelta {
+                        baseline_mb: sys.used_memory() / BYTES_PER_MB,
+                    }
+                }
+            },
+        };
+
+        let mut probe = Self {
+            sys,
+            target,
+            peak_mb: 0,
+        };
+        probe.sample();
+        probe
+    }
+
+    /// Takes a reading and returns it in MB.
+    pub fn sample(&mut self) -> u64 {
+        let current = match &self.target {
+            Target::Current(pid) => {
+                self.sys.refresh_process(*pid);
+                self.sys
+                    .process(*pid)
+                    .map(|process| process.memory() / BYTES_PER_MB)
+                    .unwrap_or(0)
+            }
+            Target::Runtime(names) => {
+                self.sys.refresh_processes();
+                matching_processes(&self.sys, names)
+                    .map(|process| process.memory())
+                    .sum::<u64>()
+                    / BYTES_PER_MB
+            }
+            Target::SystemDelta { baseline_mb } => {
+                self.sys.refresh_memory();
+                (self.sys.used_memory() / BYTES_PER_MB).saturating_sub(*baseline_mb)
+            }
+        };
+        self.peak_mb = self.peak_mb.max(current);
+        current
+    }
+
+    pub fn peak_mb(&self) -> u64 {
+        self.peak_mb
+    }
+
+    /// Labels the readings, naming the processes that were measured.
+    pub fn source(&mut self) -> MemorySource {
+        match &self.target {
+            Target::Current(_) => MemorySource::CurrentProcess,
+            Target::Runtime(names) => {
+                self.sys.refresh_processes();
+                let mut processes: Vec<String> = matching_processes(&self.sys, names)
+                    .map(|process| process.name().to_string())
+                    .collect();
+                processes.sort();
+                processes.dedup();
+                MemorySource::RuntimeProcess { processes }
+            }
+            Target::SystemDelta { .. } => MemorySource::SystemDelta,
+        }
+    }
+}
+
+fn matching_processes<'a>(
+    sys: &'a System,
+    names: &'static [&'static str],
+) -> impl Iterator<Item = &'a sysinfo::Process> {
+    sys.processes().values().filter(move |process| {
+        let name = process.name().to_lowercase();
+        names.iter().any(|prefix| name.starts_with(prefix))
+    })
+}
+
+/// Resident memory of the current process in MB, or 0 if it can't be read.
+pub fn current_process_mb() -> u64 {
+    let Ok(pid) = sysinfo::get_current_pid() else {
+        return 0;
+    };
+    let mut sys = System::new_with_specifics(RefreshKind::new());
+    sys.refresh_process(pid);
+    sys.process(pid)
+        .map(|process| process.memory() / BYTES_PER_MB)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cloud_providers_measure_this_process() {
+        let mut probe = MemoryProbe::for_provider("openai");
+        assert_eq!(probe.source(), MemorySource::CurrentProcess);
+        assert!(probe.sample() > 0);
+    }
+
+    #[test]
+    fn only_local_runtimes_are_matched_by_process_name() {
+        assert!(runtime_process_names("claude").is_none());
+        let names = runtime_process_names("ollama").unwrap();
+        assert!(names.iter().all(|name| *name == name.to_lowercase()));
+    }
+}
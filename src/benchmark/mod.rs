@@ -1,16 +1,27 @@
-#[cfg(target_os = "windows")]
-use crate::adapters::windows;
-use crate::core::adapters::ai::KandilAI;
+use crate::core::adapters::ai::http::{self, ProxySettings};
+use crate::core::adapters::ai::stream::StreamedReply;
+use crate::core::adapters::ai::{backend, KandilAI};
 use crate::core::hardware::{detect_hardware, HardwareProfile};
+use crate::security::file_permissions::PermissionCheck;
+use crate::utils::config::{Config, TlsConfig};
+use crate::utils::tokens::TokenCount;
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use reqwest::Client;
 use serde::Serialize;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use sysinfo::{RefreshKind, System, SystemExt};
 use tokio::time::{timeout, Duration as TokioDuration};
 
+pub mod compare;
+pub mod history;
+mod memory;
+mod timing;
+
+pub use memory::MemorySource;
+use memory::{current_process_mb, MemoryProbe};
+use timing::StreamTiming;
+
 const DEFAULT_PROMPTS: [&str; 5] = [
     "Explain the Kandil Code architecture in two sentences.",
     "Write a Rust function that reverses a linked list.",
@@ -36,14 +47,27 @@ impl CrossPlatformBenchmark {
             .iter()
             .map(|p| p.to_string())
             .collect::<Vec<_>>();
-        let http = Client::builder()
-            .timeout(Duration::from_secs(4))
-            .build()
+        let http = http::client_builder()
+            .and_then(|builder| Ok(builder.timeout(Duration::from_secs(4)).build()?))
             .unwrap_or_else(|_| Client::new());
         Self { prompts, http }
     }
 
     pub async fn run(&self, options: BenchmarkOptions) -> Result<BenchmarkReport> {
+        self.run_with_progress(options, |_| {}).await
+    }
+
+    /// Same as [`run`](Self::run), reporting each runtime and prompt to
+    /// `on_progress` as the benchmark goes.
+    pub async fn run_with_progress<F>(
+        &self,
+        options: BenchmarkOptions,
+        mut on_progress: F,
+    ) -> Result<BenchmarkReport>
+    where
+        F: FnMut(BenchmarkProgress<'_>),
+    {
+        let _span = crate::performance::Profiler::start("benchmark.run");
         let hardware = detect_hardware();
         let prompts = options
             .prompts
@@ -59,7 +83,18 @@ impl CrossPlatformBenchmark {
 
         let mut results = Vec::new();
         for target in targets {
-            match self.benchmark_runtime(&target, &prompts).await {
+            on_progress(BenchmarkProgress::RuntimeStarted {
+                runtime: &target.display_name,
+                prompts: prompts.len(),
+            });
+            let outcome = self
+                .benchmark_runtime(&target, &prompts, &mut on_progress)
+                .await;
+            on_progress(BenchmarkProgress::RuntimeFinished {
+                runtime: &target.display_name,
+                error: outcome.as_ref().err().map(|err| err.to_string()),
+            });
+            match outcome {
                 Ok(report) => results.push(report),
                 Err(err) => warnings.push(format!(
                     "Runtime '{}' failed during benchmark: {}",
@@ -119,8 +154,8 @@ impl CrossPlatformBenchmark {
         }
 
         if options.include_all_runtimes {
-            for runtime in ["ollama", "lmstudio", "gpt4all", "foundry"] {
-                match self.build_runtime(runtime, options).await {
+            for (runtime, _) in local_runtimes() {
+                match self.build_runtime(&runtime, options).await {
                     Ok(Some(target)) => runtimes.push(target),
                     Ok(None) => warnings.push(format!("Runtime '{}' not detected", runtime)),
                     Err(err) => {
@@ -163,84 +198,60 @@ impl CrossPlatformBenchmark {
         runtime_id: &str,
         options: &BenchmarkOptions,
     ) -> Result<Option<RuntimeTarget>> {
-        match runtime_id {
-            "default" => {
-                let ai = KandilAI::new(options.default_provider.clone(), options.model.clone())?;
-                Ok(Some(RuntimeTarget::new(
-                    "default",
-                    format!("{} (default)", options.default_provider),
-                    options.default_provider.clone(),
-                    ai,
-                )))
-            }
-            "ollama" => {
-                if !self.runtime_ready(&self.ollama_probe_url()).await {
-                    return Ok(None);
-                }
-                let ai = KandilAI::new("ollama".into(), options.model.clone())?;
-                Ok(Some(RuntimeTarget::new(
-                    "ollama",
-                    "Ollama".to_string(),
-                    "ollama".into(),
-                    ai,
-                )))
-            }
-            "lmstudio" => {
-                if !self.runtime_ready("http://localhost:1234/v1/models").await {
-                    return Ok(None);
-                }
-                let ai = KandilAI::new("lmstudio".into(), options.model.clone())?;
-                Ok(Some(RuntimeTarget::new(
-                    "lmstudio",
-                    "LM Studio".to_string(),
-                    "lmstudio".into(),
-                    ai,
-                )))
-            }
-            "gpt4all" => {
-                if !self.runtime_ready("http://localhost:4891/v1/models").await {
-                    return Ok(None);
-                }
-                let ai = KandilAI::new("gpt4all".into(), options.model.clone())?;
-                Ok(Some(RuntimeTarget::new(
-                    "gpt4all",
-                    "GPT4All".to_string(),
-                    "gpt4all".into(),
-                    ai,
-                )))
+        let Some((provider, display_name)) =
+            runtime_provider(runtime_id, &options.default_provider)
+        else {
+            return Ok(None);
+        };
+        let ai = KandilAI::new(provider.clone(), options.model.clone())?;
+        if runtime_id != "default" {
+            if !self.runtime_ready(&probe_url(&provider, ai.base_url())).await {
+                return Ok(None);
             }
-            "foundry" => {
-                if !self.runtime_ready("http://localhost:5001/v1/models").await {
-                    return Ok(None);
-                }
-                let ai = KandilAI::new("foundry".into(), options.model.clone())?;
-                Ok(Some(RuntimeTarget::new(
-                    "foundry",
-                    "Foundry Local".to_string(),
-                    "foundry".into(),
-                    ai,
-                )))
-            }
-            _ => Ok(None),
         }
+        Ok(Some(RuntimeTarget::new(runtime_id, display_name, provider, ai)))
     }
 
-    async fn benchmark_runtime(
+    async fn benchmark_runtime<F>(
         &self,
         runtime: &RuntimeTarget,
         prompts: &[String],
-    ) -> Result<RuntimeBenchmark> {
+        on_progress: &mut F,
+    ) -> Result<RuntimeBenchmark>
+    where
+        F: FnMut(BenchmarkProgress<'_>),
+    {
+        let _span = crate::performance::Profiler::start("benchmark.runtime");
         let mut samples = Vec::new();
-        let base_memory = memory_usage_mb();
+        let mut memory = MemoryProbe::for_provider(&runtime.provider);
         let mut total_latency_ms = 0u128;
         let mut total_tokens_per_sec = 0f64;
+        let mut tokenizer = String::new();
+        let mut tokens_approximate = false;
 
         for prompt in prompts {
+            on_progress(BenchmarkProgress::PromptStarted {
+                runtime: &runtime.display_name,
+                prompt,
+            });
             let start = Instant::now();
-            let response = runtime.ai.chat(prompt).await?;
+            let mut timing = StreamTiming::start();
+            let reply = runtime
+                .ai
+                .chat_stream(prompt, false, |_| timing.fragment())
+                .await?;
             let elapsed = start.elapsed();
             let latency_ms = elapsed.as_millis() as u64;
-            let token_count = tokens_from_response(&response);
+            on_progress(BenchmarkProgress::PromptFinished {
+                runtime: &runtime.display_name,
+                prompt,
+                latency_ms,
+            });
+            memory.sample();
+            let count = output_tokens(runtime, &reply).await;
+            let token_count = count.tokens.max(1);
+            tokenizer = count.tokenizer;
+            tokens_approximate |= !count.exact;
             total_latency_ms += elapsed.as_millis();
 
             let tokens_per_sec = if elapsed.as_secs_f64() > 0.0 {
@@ -254,6 +265,8 @@ impl CrossPlatformBenchmark {
                 prompt: prompt.clone(),
                 latency_ms,
                 output_tokens: token_count,
+                ttft_ms: timing.ttft_ms(),
+                generation_tokens_per_sec: timing.generation_tokens_per_sec(),
             });
         }
 
@@ -267,7 +280,29 @@ impl CrossPlatformBenchmark {
         let avg_tokens_per_sec = (total_tokens_per_sec / samples.len() as f64)
             .round()
             .max(1.0) as u32;
-        let memory_peak_mb = memory_usage_mb().max(base_memory);
+        // Only streamed samples have these; average over those that do
+        let ttft: Vec<u64> = samples.iter().filter_map(|s| s.ttft_ms).collect();
+        let ttft_ms = (!ttft.is_empty()).then(|| ttft.iter().sum::<u64>() / ttft.len() as u64);
+        let speeds: Vec<f64> = samples
+            .iter()
+            .filter_map(|s| s.generation_tokens_per_sec)
+            .collect();
+        let generation_tokens_per_sec = (!speeds.is_empty())
+            .then(|| (speeds.iter().sum::<f64>() / speeds.len() as f64).round() as u32);
+        let memory_peak_mb = memory.peak_mb();
+        let memory_source = memory.source();
+
+        // The first call pays for connecting to the runtime; later calls reuse
+        // the pooled connection from the shared client.
+        let first_call_latency_ms = samples[0].latency_ms;
+        let repeat_call_latency_ms = if samples.len() > 1 {
+            Some(
+                samples[1..].iter().map(|s| s.latency_ms).sum::<u64>()
+                    / (samples.len() - 1) as u64,
+            )
+        } else {
+            None
+        };
 
         // Attempt to measure battery impact (placeholder - would need real implementation)
         let battery_impact = self.estimate_battery_impact(&samples).await;
@@ -276,8 +311,15 @@ impl CrossPlatformBenchmark {
             runtime: runtime.display_name.clone(),
             provider: runtime.provider.clone(),
             average_latency_ms: avg_latency_ms,
+            first_call_latency_ms,
+            repeat_call_latency_ms,
             average_tokens_per_sec: avg_tokens_per_sec,
+            tokenizer,
+            tokens_approximate,
+            ttft_ms,
+            generation_tokens_per_sec,
             memory_peak_mb,
+            memory_source,
             battery_impact,
             samples,
         })
@@ -290,20 +332,6 @@ impl CrossPlatformBenchmark {
         }
     }
 
-    fn ollama_probe_url(&self) -> String {
-        format!("{}/api/tags", self.ollama_base_endpoint())
-    }
-
-    fn ollama_base_endpoint(&self) -> String {
-        #[cfg(target_os = "windows")]
-        {
-            windows::preferred_ollama_endpoint()
-        }
-        #[cfg(not(target_os = "windows"))]
-        {
-            "http://localhost:11434".to_string()
-        }
-    }
 
     async fn estimate_battery_impact(&self, samples: &[PromptSample]) -> Option<f32> {
         // This is a placeholder implementation - in a real implementation,
@@ -342,31 +370,35 @@ impl CrossPlatformBenchmark {
             connectivity,
             performance,
             security,
+            data_dir: crate::utils::data_dir::data_dir().clone(),
         }
     }
 
-    async fn check_connectivity(&self) -> ConnectivityReport {
-        let mut endpoints = Vec::new();
-
-        // Check various endpoints
-        let endpoints_to_check = [
-            ("localhost:11434", "Ollama API"), // Default Ollama
-            ("localhost:1234", "LM Studio API"),
-            ("localhost:4891", "GPT4All API"),
-            ("localhost:5001", "Foundry API"),
-        ];
-
-        for (endpoint, name) in &endpoints_to_check {
-            let reachable = self.runtime_ready(&format!("http://{}", endpoint)).await;
-            endpoints.push(EndpointStatus {
-                name: name.to_string(),
-                endpoint: endpoint.to_string(),
-                reachable,
-            });
-        }
+    /// Probes the local runtime APIs concurrently, at the endpoints chat
+    /// would use: `[endpoints]` from the config, else each runtime's default.
+    pub async fn check_connectivity(&self) -> ConnectivityReport {
+        let config = Config::load().unwrap_or_default();
+
+        let endpoints = futures_util::future::join_all(local_runtimes().into_iter().map(
+            |(provider, name)| {
+                let endpoint = local_endpoint(&provider, &config);
+                let probe_url = probe_url(&provider, &endpoint);
+                async move {
+                    EndpointStatus {
+                        name: format!("{} API", name),
+                        provider,
+                        reachable: self.runtime_ready(&probe_url).await,
+                        endpoint,
+                    }
+                }
+            },
+        ))
+        .await;
 
         ConnectivityReport {
             endpoints,
+            proxy: ProxySettings::current().redacted(),
+            tls: config.tls,
             timestamp: Utc::now(),
         }
     }
@@ -374,19 +406,20 @@ impl CrossPlatformBenchmark {
     async fn check_performance(&self) -> PerformanceReport {
         // Baseline performance test
         let start_time = Instant::now();
-        let base_memory = memory_usage_mb();
+        let base_memory = current_process_mb();
 
         // Simple CPU test
-        let mut sum = 0;
-        for i in 0..1000000 {
-            sum += i % 100;
+        let mut sum = 0u64;
+        for i in 0..1_000_000u64 {
+            sum = std::hint::black_box(sum + i % 100);
         }
 
         let elapsed = start_time.elapsed().as_millis() as u64;
-        let peak_memory = memory_usage_mb().max(base_memory);
+        let peak_memory = current_process_mb().max(base_memory);
 
         PerformanceReport {
             cpu_test_duration_ms: elapsed,
+            cpu_test_note: CPU_TEST_NOTE.to_string(),
             memory_baseline_mb: base_memory,
             memory_peak_mb: peak_memory,
             timestamp: Utc::now(),
@@ -395,20 +428,26 @@ impl CrossPlatformBenchmark {
 
     async fn check_security(&self) -> SecurityReport {
         // Basic security checks
-        let has_api_key = self.check_api_key_storage().await;
+        let insecure_files = self.check_api_key_storage().await;
         let is_network_secure = self.check_network_security().await;
 
         SecurityReport {
-            api_key_secure: has_api_key,
+            api_key_secure: insecure_files.is_empty(),
+            insecure_files,
             network_secure: is_network_secure,
             timestamp: Utc::now(),
         }
     }
 
-    async fn check_api_key_storage(&self) -> bool {
-        // This would check if API keys are properly stored in OS keyring
-        // For now, return true as a placeholder
-        true
+    /// Config and credential files that other users can read.
+    async fn check_api_key_storage(&self) -> Vec<PermissionCheck> {
+        match crate::security::file_permissions::check_secret_files() {
+            Ok(checks) => checks.into_iter().filter(|check| !check.is_secure()).collect(),
+            Err(err) => {
+                log::warn!("Could not check secret file permissions: {}", err);
+                Vec::new()
+            }
+        }
     }
 
     async fn check_network_security(&self) -> bool {
@@ -418,8 +457,24 @@ impl CrossPlatformBenchmark {
     }
 }
 
-fn tokens_from_response(response: &str) -> usize {
-    response.split_whitespace().count().max(1)
+/// Output tokens in `reply`: the runtime's own count when the stream reports
+/// one, otherwise the text counted with the model's tokenizer (see
+/// [`crate::utils::tokens::count`]).
+async fn output_tokens(runtime: &RuntimeTarget, reply: &StreamedReply) -> TokenCount {
+    if reply.tokens_exact {
+        return TokenCount {
+            tokens: reply.tokens_received,
+            tokenizer: format!("{} (reported by {})", runtime.ai.model_name(), runtime.provider),
+            exact: true,
+        };
+    }
+    crate::utils::tokens::count(
+        &runtime.provider,
+        runtime.ai.model_name(),
+        runtime.ai.base_url(),
+        &reply.text,
+    )
+    .await
 }
 
 // Diagnostic report structures
@@ -430,24 +485,34 @@ pub struct DiagnosticReport {
     pub connectivity: ConnectivityReport,
     pub performance: PerformanceReport,
     pub security: SecurityReport,
+    /// Where models and state are written.
+    pub data_dir: crate::utils::data_dir::DataDir,
 }
 
 #[derive(Debug, Serialize, Clone)]
 pub struct ConnectivityReport {
     pub endpoints: Vec<EndpointStatus>,
+    /// Proxy used for outbound requests, with credentials hidden.
+    pub proxy: ProxySettings,
+    /// The `[tls]` settings applied to HTTPS connections.
+    pub tls: TlsConfig,
     pub timestamp: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, Clone)]
 pub struct EndpointStatus {
     pub name: String,
+    /// Provider name as used in `ai_provider`, e.g. `ollama`.
+    pub provider: String,
     pub endpoint: String,
     pub reachable: bool,
 }
 
 #[derive(Debug, Serialize, Clone)]
 pub struct PerformanceReport {
+    /// Time for a fixed integer loop; only useful to compare runs on one machine.
     pub cpu_test_duration_ms: u64,
+    pub cpu_test_note: String,
     pub memory_baseline_mb: u64,
     pub memory_peak_mb: u64,
     pub timestamp: DateTime<Utc>,
@@ -456,16 +521,12 @@ pub struct PerformanceReport {
 #[derive(Debug, Serialize, Clone)]
 pub struct SecurityReport {
     pub api_key_secure: bool,
+    /// Secret-bearing files with permissions that are too open.
+    pub insecure_files: Vec<PermissionCheck>,
     pub network_secure: bool,
     pub timestamp: DateTime<Utc>,
 }
 
-fn memory_usage_mb() -> u64 {
-    let mut sys = System::new_with_specifics(RefreshKind::new().with_memory());
-    sys.refresh_memory();
-    (sys.used_memory() / 1024) as u64
-}
-
 #[derive(Debug, Serialize, Clone)]
 pub struct BenchmarkReport {
     pub model: String,
@@ -482,8 +543,25 @@ pub struct RuntimeBenchmark {
     pub runtime: String,
     pub provider: String,
     pub average_latency_ms: u64,
+    pub first_call_latency_ms: u64,
+    /// Average over the calls after the first, once a connection is pooled.
+    pub repeat_call_latency_ms: Option<u64>,
+    /// End to end: output tokens over wall-clock time, network and queueing
+    /// included.
     pub average_tokens_per_sec: u32,
+    /// What the output tokens were counted with.
+    pub tokenizer: String,
+    /// The counts come from a stand-in tokenizer, not the model's own.
+    pub tokens_approximate: bool,
+    /// Average time to the first streamed token; `None` when the runtime
+    /// doesn't stream.
+    pub ttft_ms: Option<u64>,
+    /// Generation speed once tokens are flowing, from the streaming interval
+    /// alone; `None` when the runtime doesn't stream.
+    pub generation_tokens_per_sec: Option<u32>,
     pub memory_peak_mb: u64,
+    /// What `memory_peak_mb` measured.
+    pub memory_source: MemorySource,
     pub battery_impact: Option<f32>,
     pub samples: Vec<PromptSample>,
 }
@@ -493,6 +571,24 @@ pub struct PromptSample {
     pub prompt: String,
     pub latency_ms: u64,
     pub output_tokens: usize,
+    pub ttft_ms: Option<u64>,
+    pub generation_tokens_per_sec: Option<f64>,
+}
+
+/// Progress events emitted by [`CrossPlatformBenchmark::run_with_progress`].
+#[derive(Debug, Clone)]
+pub enum BenchmarkProgress<'a> {
+    RuntimeStarted { runtime: &'a str, prompts: usize },
+    PromptStarted { runtime: &'a str, prompt: &'a str },
+    PromptFinished {
+        runtime: &'a str,
+        prompt: &'a str,
+        latency_ms: u64,
+    },
+    RuntimeFinished {
+        runtime: &'a str,
+        error: Option<String>,
+    },
 }
 
 pub struct BenchmarkOptions {
@@ -512,6 +608,58 @@ impl BenchmarkOptions {
     }
 }
 
+/// Local runtimes as `(provider, display name)`, in detection order: every
+/// registered backend that isn't a cloud service.
+pub fn local_runtimes() -> Vec<(String, String)> {
+    backend::all()
+        .iter()
+        .filter(|backend| !backend.is_cloud())
+        .map(|backend| (backend.name().to_string(), backend.display_name().to_string()))
+        .collect()
+}
+
+/// How to read `PerformanceReport::cpu_test_duration_ms`.
+const CPU_TEST_NOTE: &str = "indicative only: a tight loop, not model inference speed";
+
+/// Base URL of a local runtime: its `[endpoints]` entry, else the default
+/// chat would use.
+pub fn local_endpoint(provider: &str, config: &Config) -> String {
+    match config.endpoints.get(provider) {
+        Some(url) if !url.trim().is_empty() => url.trim().trim_end_matches('/').to_string(),
+        _ => KandilAI::new(provider.to_string(), String::new())
+            .map(|ai| ai.base_url().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// The URL that answers when a runtime at `endpoint` is up: the backend's
+/// model listing.
+pub fn probe_url(provider: &str, endpoint: &str) -> String {
+    let path = backend::get(provider)
+        .map(|backend| backend.models_path().to_string())
+        .unwrap_or_else(|| "/v1/models".to_string());
+    format!("{}{}", endpoint, path)
+}
+
+/// Resolves a `--runtime` value to its provider and display name. `default`
+/// is the configured provider, and cloud provider names are accepted as-is.
+pub fn runtime_provider(runtime_id: &str, default_provider: &str) -> Option<(String, String)> {
+    if runtime_id == "default" {
+        return Some((
+            default_provider.to_string(),
+            format!("{} (default)", default_provider),
+        ));
+    }
+    if let Some(runtime) = local_runtimes()
+        .into_iter()
+        .find(|(provider, _)| provider == runtime_id)
+    {
+        return Some(runtime);
+    }
+    backend::is_registered(runtime_id)
+        .then(|| (runtime_id.to_string(), runtime_id.to_string()))
+}
+
 struct RuntimeTarget {
     id: String,
     provider: String,
@@ -0,0 +1,91 @@
+//! Streaming timings for benchmarks
+//!
+//! Wall-clock latency includes connecting, network round trips and time spent
+//! queued at the provider, so it understates how fast a model generates. When
+//! a reply is streamed, the wait for the first fragment (time to first token)
+//! is measured separately from the interval over which the remaining fragments
+//! arrive, and generation speed is taken from that interval alone. Replies
+//! that arrive as a single fragment have no such interval.
+
+use std::time::Instant;
+
+/// Arrival times of one reply's fragments.
+pub struct StreamTiming {
+    started: Instant,
+    first: Option<Instant>,
+    last: Option<Instant>,
+    fragments: usize,
+}
+
+impl StreamTiming {
+    pub fn start() -> Self {
+        Self::started_at(Instant::now())
+    }
+
+    fn started_at(started: Instant) -> Self {
+        Self {
+            started,
+            first: None,
+            last: None,
+            fragments: 0,
+        }
+    }
+
+    /// Records a fragment arriving now.
+    pub fn fragment(&mut self) {
+        self.fragment_at(Instant::now());
+    }
+
+    fn fragment_at(&mut self, at: Instant) {
+        self.first.get_or_insert(at);
+        self.last = Some(at);
+        self.fragments += 1;
+    }
+
+    fn streamed(&self) -> bool {
+        self.fragments > 1
+    }
+
+    /// Milliseconds until the first fragment, for streamed replies.
+    pub fn ttft_ms(&self) -> Option<u64> {
+        let first = self.first.filter(|_| self.streamed())?;
+        Some(first.duration_since(self.started).as_millis() as u64)
+    }
+
+    /// Fragments per second after the first one arrived, for streamed
+    /// replies. Streaming runtimes send about one token per fragment.
+    pub fn generation_tokens_per_sec(&self) -> Option<f64> {
+        if !self.streamed() {
+            return None;
+        }
+        let interval = self.last?.duration_since(self.first?).as_secs_f64();
+        (interval > 0.0).then(|| (self.fragments - 1) as f64 / interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn generation_speed_excludes_the_wait_for_the_first_token() {
+        let started = Instant::now();
+        let mut timing = StreamTiming::started_at(started);
+        // 2 s queued and on the wire, then 20 more tokens over 1 s
+        for index in 0..21u64 {
+            timing.fragment_at(started + Duration::from_millis(2000 + index * 50));
+        }
+        assert_eq!(timing.ttft_ms(), Some(2000));
+        assert_eq!(timing.generation_tokens_per_sec(), Some(20.0));
+    }
+
+    #[test]
+    fn single_fragment_replies_have_no_streaming_figures() {
+        let started = Instant::now();
+        let mut timing = StreamTiming::started_at(started);
+        timing.fragment_at(started + Duration::from_millis(800));
+        assert_eq!(timing.ttft_ms(), None);
+        assert_eq!(timing.generation_tokens_per_sec(), None);
+    }
+}
@@ -1,18 +1,29 @@
 use crate::adapters::{edge, linux, macos, mobile, windows};
-use crate::benchmark::{BenchmarkOptions, CrossPlatformBenchmark};
+use crate::benchmark::compare::Comparison;
+use crate::benchmark::{history, BenchmarkOptions, BenchmarkProgress, CrossPlatformBenchmark};
 use crate::core::adapters::ai::factory::AIProviderFactory;
+use crate::core::agents::progress::{OpenStages, ProgressEvent};
 use crate::core::hardware::{detect_hardware, PlatformKind};
-use crate::core::prompting::{PromptIntent, PromptRouter};
+use crate::core::performance::TokenEstimator;
+use crate::core::prompting::{infer_intent, PromptIntent, PromptRouter, RoutedPrompt};
+use crate::core::trust::{static_check, ChangeEvidence, TrustScore};
 use crate::enhanced_ui;
 use crate::pwa;
 use crate::security::mobile as mobile_security;
 use crate::security::platform::PlatformHardener;
+use crate::utils::cloud_sync;
 use crate::utils::config::{Config, SecureKey};
+use crate::utils::coverage::CoverageRunner;
+use crate::utils::language;
+use crate::utils::offline;
+use crate::utils::preflight;
 use crate::utils::plugins::PluginManager;
-use crate::utils::project_manager::ProjectManager;
+use crate::utils::project_manager::{ProjectManager, ReplyCost};
+use crate::utils::redact;
 use crate::utils::refactoring::{RefactorEngine, RefactorParams};
 use crate::utils::templates::TemplateEngine;
 use crate::utils::test_generation::TestGenerator;
+use crate::utils::verbosity;
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use futures_util::stream::StreamExt;
@@ -26,27 +37,142 @@ pub struct Cli {
     #[command(subcommand)]
     pub command: Option<Commands>,
 
-    #[arg(short, long, global = true, help = "Verbose output")]
-    pub verbose: bool,
+    #[arg(
+        short,
+        long,
+        global = true,
+        action = clap::ArgAction::Count,
+        help = "Explain routing, retries, cache use and timings on stderr; -vv adds debug logs"
+    )]
+    pub verbose: u8,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Only use local AI runtimes and never contact the network (also KANDIL_OFFLINE=1)"
+    )]
+    pub offline: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Skip the provider reachability check before AI commands"
+    )]
+    pub no_preflight: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "U64",
+        help = "Seed sampling and internal randomness for reproducible runs (also KANDIL_SEED)"
+    )]
+    pub seed: Option<u64>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        help = "Max AI requests for this command, retries included (also KANDIL_CALL_BUDGET)"
+    )]
+    pub call_budget: Option<u32>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        help = "Use this config file instead of the default locations (also KANDIL_CONFIG)"
+    )]
+    pub config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Show AI responses and record memory without masking secrets"
+    )]
+    pub no_redact: bool,
+
+    /// Format of the timing report printed when KANDIL_PROFILE=1
+    #[arg(
+        long,
+        global = true,
+        default_value = "text",
+        value_parser = ["text", "json"]
+    )]
+    pub profile_format: String,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
-    /// Initialize a new Kandil project
-    Init,
+    /// Initialize a new Kandil project and scaffold .kandil/config.toml
+    Init {
+        /// AI provider for this project (prompted for when omitted)
+        #[arg(long)]
+        provider: Option<String>,
+        /// AI model for this project (prompted for when omitted)
+        #[arg(long)]
+        model: Option<String>,
+    },
     /// Chat with the AI assistant
     Chat {
+        /// Message to send; `-` reads it from stdin
         #[arg(value_parser)]
         message: Option<String>,
+        /// Read the message from a file
+        #[arg(long, conflicts_with = "message")]
+        file: Option<PathBuf>,
+        /// Attach the files matching this glob that fit the context window,
+        /// most relevant to the message first (repeatable)
+        #[arg(long = "files", value_name = "GLOB", conflicts_with = "consensus")]
+        files: Vec<String>,
+        /// Ask this many models and synthesize a consensus answer
+        #[arg(long)]
+        consensus: Option<usize>,
+        /// Models for --consensus as [provider:]model, comma separated
+        #[arg(long, value_delimiter = ',')]
+        models: Vec<String>,
+        /// Per-model timeout in seconds for --consensus
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+        /// Refuse to run --consensus if its estimated cost exceeds this many USD
+        #[arg(long)]
+        max_cost: Option<f64>,
+        /// Keep a streamed answer that was cut off mid-stream instead of failing
+        #[arg(long)]
+        allow_partial: bool,
+        /// Also write the response to this file (parent directories are created)
+        #[arg(long, conflicts_with = "consensus")]
+        output: Option<PathBuf>,
+        /// Emit the response as structured JSON
+        #[arg(long, conflicts_with = "consensus")]
+        json: bool,
+        /// With --output, don't print the response to stdout
+        #[arg(long, requires = "output")]
+        quiet: bool,
+        #[command(flatten)]
+        choice: ModelOverride,
     },
     /// Create a new project from template
     Create {
-        /// Template name (flutter, python, javascript, rust)
+        /// Template name (flutter, python, javascript, rust, or a custom template)
         #[arg(value_parser)]
         template: String,
         /// Project name
         #[arg(value_parser)]
         name: String,
+        /// Run the template's setup commands (e.g. git init, cargo build) afterwards
+        #[arg(long)]
+        setup: bool,
+        /// List the files and project record that would be created, without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Create the project even if the directory isn't empty, overwriting clashing files
+        #[arg(long)]
+        force: bool,
+    },
+    /// Manage custom project templates for `create`
+    Template {
+        #[command(subcommand)]
+        sub: TemplateSub,
     },
     /// Launch the TUI studio
     Tui,
@@ -57,6 +183,12 @@ pub enum Commands {
     },
     /// Agent commands for requirements, design, etc.
     Agent {
+        #[command(flatten)]
+        choice: ModelOverride,
+        /// Give up on the whole agent run after this many seconds, however
+        /// many model calls it makes
+        #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+        timeout: Option<u64>,
         #[command(subcommand)]
         sub: AgentSub,
     },
@@ -70,8 +202,14 @@ pub enum Commands {
         #[command(subcommand)]
         sub: TestSub,
     },
-    /// Model switching commands
-    SwitchModel { provider: String, model: String },
+    /// Switch the configured provider and model; without arguments, pick one
+    /// interactively
+    SwitchModel {
+        #[arg(requires = "model")]
+        provider: Option<String>,
+        #[arg(requires = "provider")]
+        model: Option<String>,
+    },
     /// Plugin management commands
     Plugin {
         #[command(subcommand)]
@@ -94,11 +232,19 @@ pub enum Commands {
     },
     /// System diagnostics and health checks
     Doctor {
-        #[arg(long)]
-        verbose: bool,
         #[arg(long, default_value = "json")]
         format: String,
     },
+    /// Plan how to tackle a task with the execution strategy
+    Strategy {
+        #[command(subcommand)]
+        sub: StrategySub,
+    },
+    /// Count tokens before sending text to a model
+    Tokens {
+        #[command(subcommand)]
+        sub: TokensSub,
+    },
     /// Windows-specific local runtime helpers
     Windows {
         #[command(subcommand)]
@@ -123,6 +269,14 @@ pub enum Commands {
     Pwa {
         #[arg(long, default_value = "dist/pwa")]
         output: PathBuf,
+        /// Name shown when the app is installed
+        #[arg(long, global = true, default_value = "Kandil Companion")]
+        app_name: String,
+        /// Theme colour for the manifest and icons, e.g. #06b6d4
+        #[arg(long, global = true, default_value = "#06b6d4")]
+        theme_color: String,
+        #[command(subcommand)]
+        sub: Option<PwaSub>,
     },
     /// Launch the Axum-based web companion dashboard
     Web {
@@ -132,6 +286,49 @@ pub enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+pub enum PwaSub {
+    /// Export the assets if needed and serve them over HTTP
+    Serve {
+        #[arg(long, default_value = "dist/pwa")]
+        output: PathBuf,
+        /// Address to bind, e.g. 127.0.0.1:8080
+        #[arg(short, long, default_value = "127.0.0.1:8080")]
+        address: String,
+        /// Web companion server that /api/* requests are proxied to
+        #[arg(long, default_value = "http://127.0.0.1:7878")]
+        api: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TokensSub {
+    /// Count the tokens in text, a file or stdin with the model's tokenizer,
+    /// and estimate what sending it would cost
+    Count {
+        /// Text to count; reads stdin when neither this nor --file is given
+        #[arg(conflicts_with = "file")]
+        text: Option<String>,
+        /// File to count
+        #[arg(long)]
+        file: Option<PathBuf>,
+        #[command(flatten)]
+        choice: ModelOverride,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum StrategySub {
+    /// Recommend agents, models and commands for a task
+    Suggest {
+        /// Description of the task
+        task: String,
+        /// Output format (text, json)
+        #[arg(long, default_value = "text")]
+        format: String,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum AgentSub {
     /// Generate requirements document
@@ -146,16 +343,38 @@ pub enum AgentSub {
     },
     /// Generate code from design
     Code {
-        /// Path to design document
-        design_path: String,
-        /// Target language
+        /// Path to design document, or `-` to read it from stdin
+        #[arg(required_unless_present = "design_text")]
+        design_path: Option<String>,
+        /// Target language; `auto` detects it from the design or the project
+        #[arg(long, default_value = "auto")]
         language: String,
+        /// The design itself, instead of a file
+        #[arg(long, conflicts_with = "design_path")]
+        design_text: Option<String>,
     },
     /// Generate or execute tests
     Test {
         #[command(subcommand)]
         sub: TestSubCommand,
     },
+    /// Run requirements → design → code → test in sequence
+    Pipeline {
+        /// Description of the project to build
+        description: String,
+        /// Target language for generated code
+        #[arg(long, default_value = "rust")]
+        language: String,
+        /// Directory that receives every stage's artifacts
+        #[arg(long, default_value = "kandil-pipeline")]
+        output_dir: String,
+        /// Resume from a stage (requirements, design, code, test) using earlier artifacts
+        #[arg(long, default_value = "requirements")]
+        from: String,
+        /// Stop before the next stage once estimated spend reaches this many USD
+        #[arg(long)]
+        max_cost: Option<f64>,
+    },
     /// Generate documentation
     Documentation {
         #[command(subcommand)]
@@ -205,6 +424,31 @@ pub enum MaintenanceSubCommand {
         /// Name of the system to check
         system_name: String,
     },
+    /// Show when the next maintenance window opens, in local time
+    NextWindow,
+    /// List the saved maintenance windows
+    Windows,
+    /// Save a maintenance window that opens on a cron schedule
+    AddWindow {
+        /// Cron expression: minute hour day month weekday, e.g. "0 2 * * Sun"
+        cron: String,
+        /// IANA timezone the expression is evaluated in, e.g. Europe/Berlin
+        #[arg(long, default_value = "UTC")]
+        timezone: String,
+        /// How long the window stays open, in minutes
+        #[arg(long, default_value_t = 120)]
+        duration: i64,
+        /// Update types allowed in the window
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_value = "security-patch,bug-fix",
+            value_parser = ["security-patch", "bug-fix", "feature", "enhancement", "major"]
+        )]
+        allow: Vec<String>,
+    },
+    /// Remove a saved maintenance window by its number in `windows`
+    RemoveWindow { index: usize },
 }
 
 #[derive(Subcommand)]
@@ -213,6 +457,18 @@ pub enum QaSubCommand {
     FullSuite {
         /// Path to the project to run QA on
         project_path: String,
+        /// Re-run phases with failing tests up to N times; tests that pass on
+        /// a retry are reported as flaky instead of failed
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        retry_failed: u32,
+        /// Report format: md, or junit (JUnit XML for CI; needs --output)
+        #[arg(long, default_value = "md", value_parser = ["md", "junit"])]
+        format: String,
+        /// File to write the report to; printed if not specified
+        #[arg(long)]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        notify: NotifyArgs,
     },
 }
 
@@ -232,6 +488,14 @@ pub enum DocumentationSubCommand {
         /// Path to the project to document
         path: String,
     },
+    /// Find docs that reference missing or changed functions and types
+    Sync {
+        /// Path to the project to check
+        path: String,
+        /// Rewrite stale lines with AI-generated fixes
+        #[arg(long)]
+        apply: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -274,6 +538,12 @@ pub enum DevOpsSubCommand {
     Terraform {
         /// Infrastructure specification
         spec: String,
+        /// Only generate this cloud's resources
+        #[arg(long, value_parser = ["aws", "gcp", "azure"])]
+        cloud: Option<String>,
+        /// Run `terraform fmt -check` and `terraform validate` on the result
+        #[arg(long)]
+        validate: bool,
     },
     /// Run incident response drill
     Drill {
@@ -284,6 +554,15 @@ pub enum DevOpsSubCommand {
     Pipeline {
         /// Project type
         project_type: String,
+        /// CI system to generate for; its syntax is validated
+        #[arg(long, value_parser = ["github", "gitlab", "circleci", "jenkins"])]
+        ci: Option<String>,
+        /// Write the pipeline to the CI system's conventional path
+        #[arg(long, requires = "ci")]
+        write: bool,
+        /// Overwrite an existing pipeline file
+        #[arg(long, requires = "write")]
+        force: bool,
     },
 }
 
@@ -323,6 +602,24 @@ pub enum I18nSubCommand {
         /// Source language code
         source: String,
     },
+    /// Translate every value of a .json, .arb or .po resource file
+    TranslateFile {
+        /// Resource file to translate
+        path: String,
+        /// Target language code (en, es, fr, etc.)
+        #[arg(long)]
+        target: String,
+        /// Source language code
+        #[arg(long, default_value = "en")]
+        source: String,
+        /// Where to write the translated file (defaults to a sibling named
+        /// for the target language, e.g. en.json -> es.json)
+        #[arg(long)]
+        output: Option<String>,
+        /// Keep values already in the output file and translate only the gaps
+        #[arg(long)]
+        only_missing: bool,
+    },
     /// Audit translations in a directory
     Audit {
         /// Path to resource directory
@@ -337,6 +634,18 @@ pub enum I18nSubCommand {
         /// Target language code
         target: String,
     },
+    /// Format numbers, currency and dates for a locale (CLDR conventions)
+    Format {
+        /// Locale tag, e.g. de-DE, fr, pt_BR
+        #[arg(long)]
+        locale: String,
+        /// Number to format as a plain number and as the locale's currency
+        #[arg(long, allow_hyphen_values = true)]
+        number: Option<f64>,
+        /// ISO 8601 date to format, e.g. 2024-03-05
+        #[arg(long)]
+        date: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -345,6 +654,13 @@ pub enum A11ySubCommand {
     Audit {
         /// Content to audit
         content: String,
+        /// Output format: text, json or axe (axe-core result shape)
+        #[arg(long, default_value = "text")]
+        format: String,
+        /// Exit non-zero when violations at or above this impact are found
+        /// (minor, moderate, serious, critical)
+        #[arg(long)]
+        fail_on: Option<String>,
     },
     /// Generate accessibility guidelines for a component
     Guidelines {
@@ -393,6 +709,13 @@ pub enum CollabSubCommand {
         /// Language
         language: String,
     },
+    /// List saved collaboration sessions
+    List,
+    /// Show participants and documents of a session
+    Info {
+        /// Session ID
+        session_id: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -427,6 +750,20 @@ pub enum IdeSubCommand {
         /// Language of the code
         language: String,
     },
+    /// Serve the IDE features over HTTP and WebSocket for editor plugins
+    Serve {
+        /// Port to listen on
+        #[arg(long, default_value_t = 8765)]
+        port: u16,
+        /// Address to bind; keep the default unless the editor runs elsewhere.
+        /// Anything but loopback needs `[web].ide_token`
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Browser origin allowed to call the server, in addition to
+        /// `[web].allowed_origins` (repeatable)
+        #[arg(long = "allow-origin")]
+        allow_origins: Vec<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -523,8 +860,20 @@ pub enum CollaborateSubCommand {
 pub enum AdvancedSubCommand {
     /// Code review and quality analysis
     Review {
-        /// Path to source file
+        /// Path to a source file, or a directory to review all its source files
         file: String,
+        /// In directory mode, also review test files
+        #[arg(long)]
+        include_tests: bool,
+        /// In directory mode, skip files matching this `*` pattern (repeatable)
+        #[arg(long)]
+        exclude: Vec<String>,
+        /// Output format: text, or json for the full report
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+        /// List every issue by severity, with the code around it (text format)
+        #[arg(long)]
+        detailed: bool,
     },
     /// Security and ethics scanning
     Security {
@@ -542,6 +891,19 @@ pub enum AdvancedSubCommand {
     SelfImprove {
         /// Path to codebase to analyze
         path: String,
+        /// Turn each plan into file changes and apply the ones you approve
+        #[arg(long)]
+        apply: bool,
+        /// Where to write the plans when not applying
+        /// (default: .kandil/self-improve-report.json)
+        #[arg(long, conflicts_with = "apply")]
+        report: Option<PathBuf>,
+        /// Offer changes whose trust score is below the threshold
+        #[arg(long, requires = "apply")]
+        force: bool,
+        /// Test command to run with each change applied, e.g. "cargo test"
+        #[arg(long, requires = "apply")]
+        test_cmd: Option<String>,
     },
 }
 
@@ -553,12 +915,33 @@ pub enum DeploySubCommand {
         environment: String,
         /// Application name
         app: String,
+        /// Save the plan as JSON for `execute` and `rollback`
+        #[arg(long)]
+        output: Option<PathBuf>,
     },
     /// Execute a deployment
     Execute {
         /// Path to deployment plan
         plan: String,
     },
+    /// Run the rollback steps of a previously executed plan
+    Rollback {
+        /// Path to deployment plan
+        plan: String,
+        /// List the rollback steps without running them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Work on a deployment task with the model running approved tool calls
+    Assist {
+        /// Environment to work in
+        environment: String,
+        /// What to do, e.g. "check why the web pods keep restarting"
+        task: String,
+        /// Run every tool call without asking
+        #[arg(long)]
+        yes: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -567,15 +950,22 @@ pub enum TestSubCommand {
     Generate {
         /// Path to source file
         source: String,
-        /// Target language
+        /// Target language; `auto` detects it from the file or the project
+        #[arg(default_value = "auto")]
         language: String,
+        /// Test framework to write for; inferred from the language and project
+        #[arg(long)]
+        framework: Option<String>,
     },
-    /// Execute tests
+    /// Execute tests with the framework's own runner
     Execute {
         /// Path to test file
         test: String,
-        /// Test framework to use
+        /// Test framework to use (rust, pytest, jest, flutter)
         framework: String,
+        /// Seconds to wait for the runner before stopping it
+        #[arg(long, default_value_t = 600)]
+        timeout: u64,
     },
     /// Analyze test coverage
     Coverage {
@@ -583,6 +973,12 @@ pub enum TestSubCommand {
         source: String,
         /// Path to test file
         test: String,
+        /// text (an AI analysis), or lcov (measured coverage as an LCOV tracefile)
+        #[arg(long, default_value = "text", value_parser = ["text", "lcov"])]
+        format: String,
+        /// File to write the LCOV report to; printed if not specified
+        #[arg(long)]
+        output: Option<PathBuf>,
     },
 }
 
@@ -623,10 +1019,16 @@ pub enum BaSubCommand {
         /// Requirements document path
         requirements: String,
     },
-    /// Create a user story
+    /// Create a user story with Given/When/Then acceptance criteria
     UserStory {
         /// Feature description
         feature: String,
+        /// Output format
+        #[arg(long, default_value = "text", value_parser = ["text", "gherkin", "json", "md"])]
+        format: String,
+        /// Write the story to this file instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
     },
 }
 
@@ -643,11 +1045,38 @@ pub enum ProjectSub {
     Sync {
         /// Project ID to sync (current if not specified)
         id: Option<String>,
+        /// How to settle entries changed on both sides (local-wins, remote-wins, newest-wins, merge)
+        #[arg(long, default_value = "newest-wins")]
+        conflict: String,
+        /// Sync project memory with a JSON file instead, e.g. on a shared drive
+        #[arg(long)]
+        remote: Option<PathBuf>,
     },
     /// Show project information
     Info {
         /// Project ID to show info for (current if not specified)
         id: Option<String>,
+        /// Print the details and memory stats as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Export a chat session as a Markdown or HTML transcript
+    ExportChat {
+        /// Project ID (current if not specified)
+        #[arg(long)]
+        id: Option<String>,
+        /// Session to export (the most recent if not specified)
+        #[arg(long, conflicts_with = "all_sessions")]
+        session: Option<String>,
+        /// Export every session of the project
+        #[arg(long)]
+        all_sessions: bool,
+        /// Transcript format (md or html)
+        #[arg(long, default_value = "md")]
+        format: String,
+        /// File to write; prints to stdout if not specified
+        #[arg(long)]
+        output: Option<PathBuf>,
     },
 }
 
@@ -664,7 +1093,17 @@ pub enum RefactorSub {
         params: Vec<String>,
     },
     /// Apply all pending refactor operations
-    Apply,
+    Apply {
+        /// Apply even when the trust score is below the configured threshold
+        #[arg(long)]
+        force: bool,
+        /// Test command to run with the changes applied, e.g. "cargo test"
+        #[arg(long)]
+        test_cmd: Option<String>,
+        /// Ask the review agent to score the changed files
+        #[arg(long)]
+        review: bool,
+    },
     /// Cancel pending refactor operations
     Cancel,
 }
@@ -675,7 +1114,8 @@ pub enum TestSub {
     Generate {
         /// Source file to generate tests for
         file: String,
-        /// Test framework to use (rust, pytest, jest, flutter)
+        /// Test framework to use (rust, pytest, jest, flutter); `auto` or
+        /// empty infers it from the file's language and the project
         #[arg(short, long, default_value = "")]
         framework: String,
     },
@@ -690,6 +1130,15 @@ pub enum TestSub {
         source: String,
         /// Test file
         test: String,
+        /// Minimum line coverage percentage; exits non-zero when coverage is lower
+        #[arg(long)]
+        threshold: Option<f64>,
+        /// text, or lcov (the measured coverage as an LCOV tracefile)
+        #[arg(long, default_value = "text", value_parser = ["text", "lcov"])]
+        format: String,
+        /// File to write the LCOV report to; printed if not specified
+        #[arg(long)]
+        output: Option<PathBuf>,
     },
 }
 
@@ -698,8 +1147,12 @@ pub enum PluginSub {
     /// Install a plugin from URL or file
     Install { source: String },
     /// List installed plugins
-    List,
-    /// Execute a plugin
+    List {
+        /// List the slash commands plugins provide instead
+        #[arg(long)]
+        commands: bool,
+    },
+    /// Run a plugin command, e.g. `jira:issue` or `/issue`
     Run { name: String, args: Vec<String> },
 }
 
@@ -707,15 +1160,83 @@ pub enum PluginSub {
 pub enum ConfigSub {
     /// Set API key for a provider
     SetKey { provider: String, key: String },
+    /// Replace a provider's API key after verifying the new one works
+    RotateKey {
+        provider: String,
+        /// Restore the key that the last rotation replaced
+        #[arg(long)]
+        undo: bool,
+    },
     /// List configured API keys
     ListKeys,
-    /// Show cost statistics
+    /// Show the recorded cost of AI calls
     Costs {
         /// Provider to show costs for (all if not specified)
         provider: Option<String>,
+        #[arg(long, default_value = "text", value_parser = ["text", "json", "csv"])]
+        format: String,
+        /// File to write; prints to stdout if not specified
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Total the calls per provider and month instead of listing each
+        #[arg(long)]
+        summary: bool,
     },
     /// Validate production configuration
     Validate,
+    /// Check that config and credential files are readable by you only
+    Doctor {
+        /// Restrict the files that aren't to their owner
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Close a provider's open circuit breaker in running sessions, so their
+    /// next request is tried instead of failing fast
+    ResetBreaker { provider: String },
+    /// Print the effective configuration after all layers are merged
+    Show {
+        /// Print one section's values and where each came from
+        #[arg(long, value_parser = ["resilience"])]
+        section: Option<String>,
+    },
+    /// Detect the best model, runtime and routing for this machine
+    Auto {
+        /// Write the recommendation without asking for confirmation
+        #[arg(long)]
+        apply: bool,
+    },
+    /// Inspect or customize agent prompt templates
+    Prompts {
+        #[command(subcommand)]
+        sub: PromptsSub,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TemplateSub {
+    /// Install a template from a local directory or git URL
+    Add {
+        /// Name used with `kandil create <name>`
+        name: String,
+        /// Directory or git URL holding the template files
+        source: String,
+        /// Replace a built-in or already installed template with this name
+        #[arg(long = "override")]
+        replace: bool,
+    },
+    /// List built-in and custom templates
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum PromptsSub {
+    /// List agent prompts and whether they are customized
+    List,
+    /// Open an agent's prompt in $EDITOR, starting from the built-in one
+    Edit {
+        /// Agent name (code, design, requirements, review, test)
+        agent: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -740,16 +1261,35 @@ pub enum LocalModelSub {
         #[arg(value_parser)]
         model: String,
     },
+    /// Remove installed models that haven't been used recently, or the
+    /// largest ones until enough disk is free
+    Prune {
+        /// Remove models not used in this many days
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u64>,
+        /// Remove the largest unused models until this many GB are free
+        #[arg(long, value_name = "GB")]
+        target_free: Option<f64>,
+        /// Show what would be removed without removing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Remove without asking
+        #[arg(long)]
+        yes: bool,
+    },
     /// Verify model integrity
     Verify {
         #[arg(value_parser)]
         model: String,
     },
     /// Benchmark installed model
+    #[command(args_conflicts_with_subcommands = true)]
     Benchmark {
+        #[command(subcommand)]
+        sub: Option<BenchmarkSub>,
         #[arg(value_parser)]
         model: Option<String>,
-        /// Output format
+        /// Output format: table, json, or md (the --compare table in Markdown)
         #[arg(long, default_value = "table")]
         format: String,
         /// Force a specific runtime (ollama, lmstudio, gpt4all, foundry, default)
@@ -758,6 +1298,9 @@ pub enum LocalModelSub {
         /// Benchmark every detected runtime
         #[arg(long)]
         all_runtimes: bool,
+        /// Show runtimes side by side, one metric per row, best values marked
+        #[arg(long)]
+        compare: bool,
     },
     /// Use a local model and persist selection
     Use {
@@ -766,6 +1309,60 @@ pub enum LocalModelSub {
     },
     /// Show local model system status
     Status,
+    /// List every model profile in the registry
+    Registry {
+        /// Output format (text or json)
+        #[arg(long, default_value = "text")]
+        format: String,
+    },
+    /// List the models a runtime or provider endpoint serves
+    ListRemote {
+        /// Runtime or provider to query (ollama, lmstudio, gpt4all, foundry,
+        /// openai, claude, qwen); defaults to the configured provider
+        #[arg(long, default_value = "default")]
+        runtime: String,
+        /// Output format (text or json)
+        #[arg(long, default_value = "text")]
+        format: String,
+    },
+    /// Check that local runtimes respond, and report hardware, performance
+    /// and security
+    Diagnostics {
+        /// Output format (text or json)
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+    },
+    /// Manage the external model catalog
+    Catalog {
+        #[command(subcommand)]
+        sub: CatalogSub,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CatalogSub {
+    /// Download a catalog, validate it and save it as the external catalog
+    Update {
+        /// URL of a catalog JSON file
+        #[arg(long)]
+        url: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BenchmarkSub {
+    /// Show the latency and throughput trend of past benchmark runs
+    History {
+        /// Only runs of this model
+        #[arg(long)]
+        model: Option<String>,
+        /// How many of the most recent runs to show
+        #[arg(long, default_value_t = 20)]
+        last: usize,
+        /// Add a sparkline of each runtime's trend
+        #[arg(long)]
+        sparkline: bool,
+    },
 }
 
 struct BenchmarkCliOptions {
@@ -773,6 +1370,7 @@ struct BenchmarkCliOptions {
     format: String,
     runtime: Option<String>,
     all_runtimes: bool,
+    compare: bool,
 }
 
 #[derive(Subcommand)]
@@ -820,16 +1418,101 @@ pub enum MobileSub {
     AndroidSync,
     /// Export an ONNX-ready manifest for edge devices
     EdgeSnapshot,
+    /// Retry queued push notifications now
+    FlushNotifications,
 }
 
 pub async fn run(cli: Cli) -> Result<()> {
+    let profile_format = cli.profile_format.clone();
+    if let Some(path) = &cli.config {
+        crate::utils::config::set_config_path(path.clone());
+    }
+    // The TUI runs its own watchdog and shows pressure in its status line.
+    let watchdog = match (&cli.command, Config::load()) {
+        (Some(Commands::Tui), _) | (_, Err(_)) => None,
+        (_, Ok(config)) => crate::performance::Watchdog::from_config(&config.watchdog, true),
+    };
+    let started = std::time::Instant::now();
+    let result = run_command(cli).await;
+    verbosity::note(format!("Finished in {:.2?}", started.elapsed()));
+    drop(watchdog);
+    crate::performance::emit_profile_report(&profile_format);
+    result
+}
+
+async fn run_command(cli: Cli) -> Result<()> {
+    if cli.offline {
+        offline::enable();
+    }
+    if let Some(seed) = cli.seed {
+        crate::utils::seed::set(seed);
+    }
+    if let Some(budget) = cli.call_budget {
+        crate::utils::budget::set(budget);
+    }
+    verbosity::set(cli.verbose);
+    if cli.no_redact {
+        redact::disable();
+    }
+
+    if !cli.no_preflight && !offline::is_enabled() && needs_ai(&cli.command) {
+        preflight_ai(command_override(&cli.command)).await;
+    }
+
     match cli.command {
-        Some(Commands::Init) => init_project().await?,
-        Some(Commands::Chat { message }) => chat(message.unwrap_or_default()).await?,
-        Some(Commands::Create { template, name }) => create_project(&template, &name).await?,
+        Some(Commands::Init { provider, model }) => init_project(provider, model).await?,
+        Some(Commands::Chat {
+            message,
+            file,
+            consensus: Some(count),
+            models,
+            timeout,
+            max_cost,
+            choice,
+            ..
+        }) => {
+            if choice.is_set() {
+                anyhow::bail!(
+                    "--provider and --model cannot be used with --consensus; use --models"
+                );
+            }
+            let message = read_chat_message(message, file)?;
+            consensus_chat(message, count, models, timeout, max_cost).await?
+        }
+        Some(Commands::Chat {
+            message,
+            file,
+            files,
+            allow_partial,
+            output,
+            json,
+            quiet,
+            choice,
+            ..
+        }) => {
+            let sink = ChatSink {
+                path: output,
+                json,
+                quiet,
+            };
+            let message = read_chat_message(message, file)?;
+            chat(message, files, allow_partial, sink, choice).await?
+        }
+        Some(Commands::Create {
+            template,
+            name,
+            setup,
+            dry_run,
+            force,
+        }) => create_project(&template, &name, setup, dry_run, force).await?,
+        Some(Commands::Template { sub }) => handle_template(sub)?,
         Some(Commands::Tui) => launch_tui().await?,
         Some(Commands::Projects { sub }) => handle_projects(sub).await?,
-        Some(Commands::Agent { sub }) => handle_agent(sub).await?,
+        Some(Commands::Agent {
+            choice,
+            timeout,
+            sub,
+        }) => handle_agent(sub, choice, timeout).await?,
         Some(Commands::Refactor { sub }) => handle_refactor(sub).await?,
         Some(Commands::Test { sub }) => handle_test(sub).await?,
         Some(Commands::SwitchModel { provider, model }) => switch_model(provider, model).await?,
@@ -841,12 +1524,24 @@ pub async fn run(cli: Cli) -> Result<()> {
         Some(Commands::Macos { sub }) => handle_macos(sub).await?,
         Some(Commands::Linux { sub }) => handle_linux(sub).await?,
         Some(Commands::Mobile { sub }) => handle_mobile(sub).await?,
-        Some(Commands::Pwa { output }) => handle_pwa(output).await?,
-        Some(Commands::Web { address }) => {
-            eprintln!("Web module is temporarily unavailable in this build");
-            return Ok(());
-        },
-        Some(Commands::Doctor { verbose, format }) => handle_doctor(verbose, &format).await?,
+        Some(Commands::Pwa {
+            output,
+            app_name,
+            theme_color,
+            sub,
+        }) => {
+            let options = pwa::PwaOptions {
+                app_name,
+                theme_color,
+            };
+            handle_pwa(output, options, sub).await?
+        }
+        Some(Commands::Web { address }) => crate::web::start(&address).await?,
+        Some(Commands::Doctor { format }) => handle_doctor(verbosity::enabled(), &format).await?,
+        Some(Commands::Strategy { sub }) => handle_strategy(sub).await?,
+        Some(Commands::Tokens {
+            sub: TokensSub::Count { text, file, choice },
+        }) => count_tokens(text, file, &choice).await?,
         None => {
             println!("Kandil Code - Intelligent Development Platform");
             println!("Use --help for commands");
@@ -855,11 +1550,73 @@ pub async fn run(cli: Cli) -> Result<()> {
     Ok(())
 }
 
-async fn init_project() -> Result<()> {
+fn needs_ai(command: &Option<Commands>) -> bool {
+    matches!(
+        command,
+        Some(Commands::Chat { .. }) | Some(Commands::Agent { .. }) | Some(Commands::Test { .. })
+    )
+}
+
+fn command_override(command: &Option<Commands>) -> Option<&ModelOverride> {
+    match command {
+        Some(Commands::Chat { choice, .. }) | Some(Commands::Agent { choice, .. }) => Some(choice),
+        _ => None,
+    }
+}
+
+/// Warns early when the configured provider, or the one chosen with
+/// `--provider`/`--model`, cannot be reached. The command still runs; this
+/// only replaces a confusing network error with next steps.
+async fn preflight_ai(choice: Option<&ModelOverride>) {
+    let mut config = match Config::load() {
+        Ok(config) => config,
+        Err(_) => return,
+    };
+    if let Some(choice) = choice {
+        // An invalid override is reported by the command itself
+        let _ = choice.apply(&mut config);
+    }
+    if let Some(advice) = preflight::check_provider(&config.ai_provider, &config.ai_model).await
+    {
+        eprintln!("⚠️  {}", advice);
+    }
+}
+
+async fn init_project(provider: Option<String>, model: Option<String>) -> Result<()> {
     println!("Initializing new Kandil project...");
 
     let project_manager = ProjectManager::new()?;
-    let current_dir = std::env::current_dir()?.to_string_lossy().to_string();
+    let root = std::env::current_dir()?;
+    let current_dir = root.to_string_lossy().to_string();
+
+    let mut config = Config::load()?;
+    config.ai_provider = match provider {
+        Some(provider) => provider,
+        None => prompt_with_default("AI provider", &config.ai_provider)?,
+    };
+    config.ai_model = match model {
+        Some(model) => model,
+        None => prompt_with_default("AI model", &config.ai_model)?,
+    };
+    if config.ignore_patterns.is_empty() {
+        config.ignore_patterns = DEFAULT_IGNORE_PATTERNS
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .collect();
+    }
+
+    let config_path = root
+        .join(crate::utils::config::PROJECT_CONFIG_DIR)
+        .join("config.toml");
+    if config_path.exists() {
+        println!(
+            "Project config already exists at {}; leaving it unchanged",
+            config_path.display()
+        );
+    } else {
+        let written = config.write_project(&root)?;
+        println!("Wrote project config to {}", written.display());
+    }
 
     // Try to get project name from directory or use a default
     let project_name = std::path::Path::new(&current_dir)
@@ -867,75 +1624,556 @@ async fn init_project() -> Result<()> {
         .and_then(|name| name.to_str())
         .unwrap_or("unnamed_project");
 
-    let project =
-        project_manager.create_project(project_name, &current_dir, "ollama", "llama3:70b")?;
+    let project = project_manager.create_project(
+        project_name,
+        &current_dir,
+        &config.ai_provider,
+        &config.ai_model,
+    )?;
 
     println!("Created project: {} with ID: {}", project.name, project.id);
     Ok(())
 }
 
-async fn chat(message: String) -> Result<()> {
-    if message.trim().is_empty() {
-        return enhanced_ui::repl::run_repl().await;
-    }
+const DEFAULT_IGNORE_PATTERNS: [&str; 6] =
+    ["target", "node_modules", ".git", "dist", "build", "*.lock"];
 
-    let config = Config::load()?;
-    let factory = AIProviderFactory::new(config.clone());
-    
-    // Use prompt router to intelligently route the message
-    let router = PromptRouter::new();
-    let routed = router.route_message(
-        &message,
-        &config.ai_provider,
-        &config.ai_model,
-    );
-    
-    // Create AI instance based on routed prompt
-    let ai = Arc::new(factory.create_ai(&routed.provider, &routed.model)?);
-    let tracked_ai = crate::core::adapters::TrackedAI::new(ai.clone(), factory.get_cost_tracker());
+/// Asks for a value on an interactive terminal, falling back to `default`
+/// when input is empty or stdin is not a terminal.
+fn prompt_with_default(label: &str, default: &str) -> Result<String> {
+    use std::io::{BufRead, IsTerminal, Write};
 
-    // Show routing info if it differs from default or if verbose
-    if routed.provider != config.ai_provider || routed.model != config.ai_model {
-        println!("🎯 Routed to {} ({}) for {:?} intent", 
-            routed.provider, routed.model, routed.intent);
-        println!("   {}", routed.explanation);
+    if !std::io::stdin().is_terminal() {
+        return Ok(default.to_string());
     }
 
-    let response = tracked_ai.chat(&message).await?;
-    println!("{}", response);
-
-    // Save to project memory if project manager is available
-    if let Ok(project_manager) = ProjectManager::new() {
-        if let Ok(current_project) = project_manager.ensure_active_project(None) {
-            // For this example, we'll use a simple session ID
-            let session_id = uuid::Uuid::new_v4().to_string();
-
-            // Save user message
-            let _ = project_manager.save_project_memory(
-                &current_project.id,
-                &session_id,
-                "user",
-                &message,
-                None, // Token count not available without proper parsing
-            );
+    print!("{} [{}]: ", label, default);
+    std::io::stdout().flush()?;
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    let value = line.trim();
+    Ok(if value.is_empty() { default } else { value }.to_string())
+}
 
-            // Save AI response
-            let _ = project_manager.save_project_memory(
-                &current_project.id,
-                &session_id,
-                "ai",
-                &response,
-                None, // Token count not available without proper parsing
-            );
+/// Resolves the chat message from `--file`, stdin (`-`) or the argument.
+/// An empty result starts the interactive REPL.
+fn read_chat_message(message: Option<String>, file: Option<PathBuf>) -> Result<String> {
+    if let Some(path) = file {
+        return std::fs::read_to_string(&path)
+            .map_err(|err| anyhow::anyhow!("Cannot read {}: {}", path.display(), err));
+    }
+    match message {
+        Some(message) if message == "-" => {
+            use std::io::Read;
+            let mut buf = String::new();
+            std::io::stdin().read_to_string(&mut buf)?;
+            Ok(buf)
+        }
+        message => Ok(message.unwrap_or_default()),
+    }
+}
+
+/// Where a long-running command reports that it finished.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct NotifyArgs {
+    /// POST a notification to this URL when the command finishes, even if it
+    /// fails; defaults to `[notify] webhook_url`
+    #[arg(long, value_name = "URL")]
+    notify_webhook: Option<String>,
+    /// Payload shape: json (command, status, duration, summary), or a Slack
+    /// or Discord message
+    #[arg(long, value_parser = ["json", "slack", "discord"])]
+    notify_format: Option<String>,
+}
+
+impl NotifyArgs {
+    /// Sends the notification if a webhook is configured. Delivery problems
+    /// are printed, never returned, so they can't mask the command's result.
+    async fn send<T>(
+        &self,
+        config: &Config,
+        command: &str,
+        started: std::time::Instant,
+        result: &Result<T>,
+        summary: impl FnOnce(&T) -> String,
+    ) {
+        let Some(url) = self.notify_webhook.as_ref().or(config.notify.webhook_url.as_ref())
+        else {
+            return;
+        };
+        let format = match &self.notify_format {
+            Some(format) => format.parse().unwrap_or_default(),
+            None => config.notify.format.unwrap_or_default(),
+        };
+        let completion = crate::mobile::completion::Completion {
+            command,
+            duration: started.elapsed(),
+            outcome: match result {
+                Ok(value) => Ok(summary(value)),
+                Err(err) => Err(format!("{:#}", err)),
+            },
+        };
+        // Webhook URLs embed their secret, so only the host is shown
+        let parsed = reqwest::Url::parse(url).ok();
+        let host = parsed
+            .as_ref()
+            .and_then(|parsed| parsed.host_str())
+            .unwrap_or("the webhook");
+        match crate::mobile::completion::notify(url, format, &completion).await {
+            Ok(()) => println!("🔔 Notified {}", host),
+            Err(err) => {
+                let mut message = format!("{:#}", err).replace(url.as_str(), host);
+                if let Some(parsed) = &parsed {
+                    message = message.replace(parsed.as_str(), host);
+                }
+                eprintln!(
+                    "⚠️  Completion notification to {} failed: {}",
+                    host, message
+                );
+            }
+        }
+    }
+}
+
+/// `--provider`/`--model` for a single invocation, leaving the config alone.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct ModelOverride {
+    /// Provider for this invocation only; the router still picks the model
+    /// unless --model is given too
+    #[arg(long, global = true)]
+    provider: Option<String>,
+    /// Model for this invocation only; with --provider, routing is skipped
+    #[arg(long, global = true)]
+    model: Option<String>,
+}
+
+impl ModelOverride {
+    fn is_set(&self) -> bool {
+        self.provider.is_some() || self.model.is_some()
+    }
+
+    /// Validates the override and makes it the config's defaults. Returns
+    /// `true` when both parts were given, so routing should be skipped.
+    fn apply(&self, config: &mut Config) -> Result<bool> {
+        if !self.is_set() {
+            return Ok(false);
+        }
+        let (provider, model) = config
+            .resolve_selection(self.provider.as_deref(), self.model.as_deref())
+            .map_err(anyhow::Error::msg)?;
+        config.ai_provider = provider;
+        config.ai_model = model;
+        Ok(self.provider.is_some() && self.model.is_some())
+    }
+
+    /// Routes `intent` through `router` unless the override pins the route.
+    fn route(&self, config: &Config, intent: PromptIntent) -> Result<RoutedPrompt> {
+        let mut config = config.clone();
+        if self.apply(&mut config)? {
+            return Ok(RoutedPrompt::pinned(intent, &config.ai_provider, &config.ai_model));
+        }
+        let router = PromptRouter::new().with_overrides(config.routing.clone());
+        Ok(router.route_for_intent(intent, &config.ai_provider, &config.ai_model))
+    }
+}
+
+/// Where `kandil chat` sends its answer.
+struct ChatSink {
+    path: Option<PathBuf>,
+    json: bool,
+    quiet: bool,
+}
+
+impl ChatSink {
+    fn prints_text(&self) -> bool {
+        !self.json && !self.quiet
+    }
+
+    /// Opens the `--output` file for plain-text answers. JSON results are
+    /// written whole once the answer is complete.
+    fn open_text_file(&self) -> Result<Option<std::io::BufWriter<std::fs::File>>> {
+        match &self.path {
+            Some(path) if !self.json => {
+                Ok(Some(std::io::BufWriter::new(create_output_file(path)?)))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+fn create_output_file(path: &std::path::Path) -> Result<std::fs::File> {
+    if let Some(parent) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::File::create(path)
+        .map_err(|err| anyhow::anyhow!("Cannot write {}: {}", path.display(), err))
+}
+
+/// In `--json` mode, prints and/or saves the structured chat result.
+fn write_chat_json(
+    sink: &ChatSink,
+    routed: &crate::core::prompting::RoutedPrompt,
+    response: &str,
+    truncated: bool,
+    cancelled: bool,
+) -> Result<()> {
+    use std::io::Write;
+
+    if !sink.json {
+        return Ok(());
+    }
+    let result = serde_json::json!({
+        "provider": routed.provider,
+        "model": routed.model,
+        "intent": routed.intent,
+        "response": response,
+        "truncated": truncated,
+        "cancelled": cancelled,
+    });
+    let rendered = serde_json::to_string_pretty(&result)?;
+    if let Some(path) = &sink.path {
+        create_output_file(path)?.write_all(rendered.as_bytes())?;
+    }
+    if !sink.quiet {
+        println!("{}", rendered);
+    }
+    Ok(())
+}
+
+async fn chat(
+    message: String,
+    files: Vec<String>,
+    allow_partial: bool,
+    sink: ChatSink,
+    choice: ModelOverride,
+) -> Result<()> {
+    use std::io::Write;
+
+    if message.trim().is_empty() {
+        if choice.is_set() {
+            anyhow::bail!(
+                "--provider and --model need a message; the REPL uses the configured model"
+            );
+        }
+        return enhanced_ui::repl::run_repl().await;
+    }
+
+    let config = Config::load()?;
+    let factory = AIProviderFactory::new(config.clone());
+    
+    // Use prompt router to intelligently route the message, unless
+    // --provider and --model pin the route
+    let routed = choice.route(&config, infer_intent(&message))?;
+    
+    // Files mentioned as @path, then those matching --files, go in verbatim
+    // ahead of the message
+    let prompt = attach_files(&message, &files, &routed.model);
+
+    // Create AI instance based on routed prompt
+    let ai = Arc::new(factory.create_ai(&routed.provider, &routed.model)?);
+    let tracked_ai = crate::core::adapters::TrackedAI::new(ai.clone(), factory.get_cost_tracker());
+
+    // Show routing info if it differs from default or if verbose
+    if verbosity::enabled() {
+        verbosity::note(format!(
+            "Routed to {} ({}) for {:?} intent: {}",
+            routed.provider, routed.model, routed.intent, routed.explanation
+        ));
+        verbosity::note(format!(
+            "Context: ~{} tokens",
+            TokenEstimator.estimate_tokens(&prompt)
+        ));
+    } else if sink.prints_text()
+        && (routed.provider != config.ai_provider || routed.model != config.ai_model)
+    {
+        println!("🎯 Routed to {} ({}) for {:?} intent", 
+            routed.provider, routed.model, routed.intent);
+        println!("   {}", routed.explanation);
+    }
+
+    // Ctrl+C stops generation instead of killing the process, so partial
+    // output is kept. Provider timeouts still apply underneath.
+    let shutdown = crate::shutdown::ShutdownHandler::new();
+    shutdown
+        .setup_signal_handlers()
+        .await
+        .map_err(|err| anyhow::anyhow!("Cannot install Ctrl+C handler: {}", err))?;
+
+    let mut file = sink.open_text_file()?;
+    let started = std::time::Instant::now();
+    let (response, truncated) = if ai.capabilities().supports_streaming {
+        // Stream answers so long generations show up as they are produced,
+        // and reach the --output file as they arrive.
+        // Secrets are masked a line at a time (a PEM block at a time for keys),
+        // so output lags by up to a line.
+        let mut write_error = None;
+        let mut received = String::new();
+        let mut fragments = 0usize;
+        let mut redactor = redact::LineRedactor::default();
+        let mut emit = |text: &str| {
+            if sink.prints_text() {
+                print!("{}", text);
+                let _ = std::io::stdout().flush();
+            }
+            if let Some(file) = file.as_mut() {
+                let written = file.write_all(text.as_bytes());
+                if let Err(err) = written.and_then(|_| file.flush()) {
+                    write_error.get_or_insert(err);
+                }
+            }
+        };
+        let reply = tokio::select! {
+            reply = tracked_ai.chat_stream(&prompt, allow_partial, |fragment| {
+                received.push_str(fragment);
+                fragments += 1;
+                emit(&redactor.push(fragment));
+            }) => Some(reply),
+            _ = shutdown.wait_for_shutdown() => None,
+        };
+        emit(&redactor.finish());
+        if sink.prints_text() {
+            println!();
+        }
+        let Some(reply) = reply else {
+            eprintln!("⏹  Cancelled after {} tokens; kept the partial answer.", fragments);
+            write_chat_json(&sink, &routed, &redact::redact(&received), true, true)?;
+            std::process::exit(130);
+        };
+        let reply = reply?;
+        if let Some(err) = write_error {
+            return Err(err.into());
+        }
+        if reply.truncated {
+            eprintln!(
+                "⚠️  The answer is truncated: the stream ended after {} tokens before completing.",
+                reply.tokens_received
+            );
+        }
+        (redact::redact(&reply.text), reply.truncated)
+    } else {
+        let response = tokio::select! {
+            response = tracked_ai.chat(&prompt) => response?,
+            _ = shutdown.wait_for_shutdown() => {
+                eprintln!("⏹  Cancelled before the answer arrived.");
+                std::process::exit(130);
+            }
+        };
+        let response = redact::redact(&response);
+        if sink.prints_text() {
+            println!("{}", response);
+        }
+        if let Some(file) = file.as_mut() {
+            file.write_all(response.as_bytes())?;
+            file.flush()?;
+        }
+        (response, false)
+    };
+
+    verbosity::note(format!(
+        "{} replied in {:.2?}",
+        ai.provider_name(),
+        started.elapsed()
+    ));
+    if !offline::is_cloud_provider(ai.provider_name()) {
+        // Off the answer's path; the runtime waits for it before exiting
+        let model = ai.model_name().to_string();
+        task::spawn_blocking(move || crate::models::usage::record(&model));
+    }
+    write_chat_json(&sink, &routed, &response, truncated, false)?;
+
+    // Save to project memory if project manager is available
+    if let Ok(project_manager) = ProjectManager::new() {
+        if let Ok(current_project) = project_manager.ensure_active_project(None) {
+            // For this example, we'll use a simple session ID
+            let session_id = uuid::Uuid::new_v4().to_string();
+
+            let usage = tracked_ai.last_usage();
+
+            // Save user message
+            let _ = project_manager.save_project_memory(
+                &current_project.id,
+                &session_id,
+                "user",
+                &message,
+                usage.map(|u| i64::from(u.prompt_tokens)),
+                None,
+            );
+
+            // Save AI response with who answered and what it cost
+            let _ = project_manager.save_project_memory(
+                &current_project.id,
+                &session_id,
+                "ai",
+                &response,
+                usage.map(|u| i64::from(u.completion_tokens)),
+                usage.map(|u| ReplyCost {
+                    provider: ai.provider_name(),
+                    model: ai.model_name(),
+                    cost_usd: u.cost_usd,
+                }),
+            );
         }
     }
 
     Ok(())
 }
 
-async fn create_project(template: &str, name: &str) -> Result<()> {
-    let engine = TemplateEngine::new();
-    engine.create_project(template, name, name)?;
+/// Output tokens assumed per model when estimating consensus cost up front.
+const CONSENSUS_EXPECTED_OUTPUT_TOKENS: u32 = 1_000;
+
+async fn consensus_chat(
+    message: String,
+    count: usize,
+    models: Vec<String>,
+    timeout: u64,
+    max_cost: Option<f64>,
+) -> Result<()> {
+    if message.trim().is_empty() {
+        anyhow::bail!("--consensus needs a message");
+    }
+    if count < 2 {
+        anyhow::bail!("--consensus needs at least 2 models");
+    }
+
+    let config = Config::load()?;
+    let factory = AIProviderFactory::new(config.clone());
+
+    // Candidates: explicit --models, else the configured model plus routing overrides
+    let mut candidates: Vec<(String, String)> = models
+        .iter()
+        .map(|spec| crate::core::agents::consensus::parse_model_spec(spec, &config.ai_provider))
+        .collect();
+    if candidates.is_empty() {
+        candidates.push((config.ai_provider.clone(), config.ai_model.clone()));
+        let mut routed: Vec<&String> = config.routing.values().collect();
+        routed.sort();
+        for model in routed {
+            candidates.push((config.ai_provider.clone(), model.clone()));
+        }
+    }
+    let mut seen = std::collections::HashSet::new();
+    candidates.retain(|candidate| seen.insert(candidate.clone()));
+    // Models whose context window can't hold the prompt would only fail
+    let prompt_tokens = (message.len() / 4) as u32;
+    candidates.retain(|(provider, model)| {
+        let capabilities = crate::models::Capabilities::for_model(provider, model);
+        let fits = capabilities.fits(prompt_tokens as usize);
+        if !fits {
+            eprintln!(
+                "⚠️  Skipping {}:{}: the prompt (~{} tokens) exceeds its {}-token context",
+                provider,
+                model,
+                prompt_tokens,
+                capabilities.max_context.unwrap_or_default()
+            );
+        }
+        fits
+    });
+    if candidates.len() < count {
+        anyhow::bail!(
+            "--consensus {} needs {} models but only {} are configured. Pass --models provider:model,...",
+            count,
+            count,
+            candidates.len()
+        );
+    }
+    candidates.truncate(count);
+
+    // Judge call included: it reads the prompt plus every answer
+    let tracker = factory.get_cost_tracker();
+    let estimated: f64 = candidates
+        .iter()
+        .map(|(provider, model)| {
+            tracker.estimate_cost(provider, model, prompt_tokens, CONSENSUS_EXPECTED_OUTPUT_TOKENS)
+        })
+        .sum::<f64>()
+        + tracker.estimate_cost(
+            &candidates[0].0,
+            &candidates[0].1,
+            prompt_tokens + CONSENSUS_EXPECTED_OUTPUT_TOKENS * count as u32,
+            CONSENSUS_EXPECTED_OUTPUT_TOKENS,
+        );
+    if let Some(limit) = max_cost {
+        if estimated > limit {
+            anyhow::bail!(
+                "Consensus across {} models is estimated at ${:.4}, above --max-cost ${:.4}",
+                count,
+                estimated,
+                limit
+            );
+        }
+    }
+
+    let mut participants: Vec<(String, Arc<dyn crate::core::adapters::ai::AIProviderTrait>)> =
+        Vec::new();
+    for (provider, model) in &candidates {
+        let ai = factory.create_ai(provider, model)?;
+        participants.push((format!("{}::{}", provider, model), Arc::new(ai)));
+    }
+    let judge = Arc::new(factory.create_ai(&candidates[0].0, &candidates[0].1)?);
+
+    println!(
+        "🗳️  Asking {} models (estimated ${:.4})...",
+        count, estimated
+    );
+    let report = crate::core::agents::consensus::ModelConsensus::new(
+        participants,
+        std::time::Duration::from_secs(timeout),
+    )
+    .with_judge(judge)
+    .run(&message)
+    .await?;
+
+    println!("{}", redact::redact(&report.answer));
+    println!();
+    println!("Agreed: {}", report.agreeing.join(", "));
+    for answer in &report.dissenting {
+        let content = answer.content.as_deref().unwrap_or_default();
+        let preview: String = redact::redact(content).chars().take(200).collect();
+        println!("Disagreed: {}\n  {}", answer.label, preview);
+    }
+    for answer in &report.failed {
+        println!(
+            "Failed: {} ({})",
+            answer.label,
+            answer.error.as_deref().unwrap_or("unknown error")
+        );
+    }
+    Ok(())
+}
+
+async fn create_project(
+    template: &str,
+    name: &str,
+    setup: bool,
+    dry_run: bool,
+    force: bool,
+) -> Result<()> {
+    let engine = TemplateEngine::with_user_templates()?;
+    let Some(found) = engine.get(template) else {
+        let mut names: Vec<_> = engine
+            .list_templates()
+            .into_iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort();
+        anyhow::bail!(
+            "Template '{}' not found. Available: {}",
+            template,
+            names.join(", ")
+        );
+    };
+
+    let mut variables = crate::utils::templates::standard_variables(name);
+    for variable in found.variables() {
+        let label = variable.prompt.as_deref().unwrap_or(&variable.name);
+        let value = prompt_with_default(label, &variable.default)?;
+        variables.insert(variable.name.clone(), value);
+    }
+    if dry_run {
+        return preview_project(&engine, template, name, setup, force, &variables);
+    }
+    engine.create_project_with(template, name, &variables, force)?;
 
     // Create a project entry in the database
     let project_manager = ProjectManager::new()?;
@@ -948,30 +2186,389 @@ async fn create_project(template: &str, name: &str) -> Result<()> {
 
     println!("Created project '{}' using template '{}'", name, template);
     println!("Project ID: {}", project.id);
+
+    let steps = found.post_create();
+    if setup {
+        crate::utils::templates::run_post_create(found, std::path::Path::new(name), &variables)?;
+    } else if !steps.is_empty() {
+        println!("Skipped setup steps (pass --setup to run them): {}", steps.join(", "));
+    }
+    Ok(())
+}
+
+fn print_step(step: &crate::core::agents::deployment::StepOutcome) {
+    use crate::core::agents::deployment::StepStatus;
+
+    let mark = match step.status {
+        StepStatus::Succeeded => "✅",
+        StepStatus::Failed => "❌",
+        StepStatus::Skipped => "⏭️ ",
+    };
+    println!("  {} {} ({})", mark, step.name, step.command);
+    if step.status == StepStatus::Failed {
+        for line in step.output.lines().take(20) {
+            println!("      {}", line);
+        }
+    }
+}
+
+fn print_directory_review(root: &str, review: &crate::core::agents::review::DirectoryReview) {
+    let lines: usize = review.files.iter().map(|file| file.lines).sum();
+    println!("Code review for: {}", root);
+    println!(
+        "  Score: {}/100 (weighted by {} lines of production code)",
+        review.score, lines
+    );
+    for file in &review.files {
+        println!(
+            "  {:>3}/100  {:>6} lines  {}  ({} issue(s))",
+            file.report.score,
+            file.lines,
+            file.path.display(),
+            file.report.issues.len()
+        );
+    }
+    let skipped = review.plan.skipped_by_reason();
+    if !skipped.is_empty() {
+        println!("  Skipped {} file(s):", review.plan.skipped.len());
+        for (reason, count) in skipped {
+            println!("    {:>4}  {}", count, reason);
+        }
+    }
+}
+
+/// Lists `report`'s issues by severity, quoting two lines either side of
+/// each one from `file` (or the issue's own file).
+fn print_review_issues(file: &str, report: &crate::core::agents::review::ReviewReport) {
+    use crate::core::agents::review::code_context;
+    for (severity, issues) in report.issues_by_severity() {
+        println!("\n{} ({}):", severity, issues.len());
+        for issue in issues {
+            let path = issue.file.as_deref().unwrap_or(file);
+            let location = match issue.line_number {
+                Some(line) => format!("{}:{}", path, line),
+                None => path.to_string(),
+            };
+            println!("  [{}] {} — {}", issue.id, issue.title, location);
+            println!("    {}", issue.description);
+            if let Some(line) = issue.line_number {
+                let content = std::fs::read_to_string(path).unwrap_or_default();
+                for (number, text) in code_context(&content, line, 2) {
+                    let marker = if number == line as usize { ">" } else { " " };
+                    println!("    {} {:>4} | {}", marker, number, text);
+                }
+            }
+            if !issue.suggestion.is_empty() {
+                println!("    Suggestion: {}", issue.suggestion);
+            }
+        }
+    }
+}
+
+/// Reports config and credential files other users can read, restricting
+/// them to their owner with `fix`.
+fn check_secret_file_permissions(fix: bool) -> Result<()> {
+    use crate::security::file_permissions;
+    let checks = file_permissions::check_secret_files()?;
+    if checks.is_empty() {
+        println!("No config or credential files to check");
+        return Ok(());
+    }
+    let mut open = 0;
+    for check in &checks {
+        match &check.problem {
+            None => println!("✅ {} {}", check.kind, check.path.display()),
+            Some(_) if fix => {
+                file_permissions::fix(&check.path)?;
+                println!("🔒 {} {}: restricted to you", check.kind, check.path.display());
+            }
+            Some(problem) => {
+                open += 1;
+                println!("⚠️  {} {}: {}", check.kind, check.path.display(), problem);
+            }
+        }
+    }
+    if open > 0 {
+        println!("Run `kandil config doctor --fix` to restrict {} file(s)", open);
+    }
+    Ok(())
+}
+
+/// Prints or writes the cost ledger's calls, or their totals per provider
+/// and month with `summary`, as a table, JSON or CSV.
+fn show_costs(
+    provider: Option<&str>,
+    format: &str,
+    output: Option<&std::path::Path>,
+    summary: bool,
+) -> Result<()> {
+    use crate::utils::cost_ledger;
+    use std::fmt::Write as _;
+    let entries = ProjectManager::new()?.cost_entries(provider)?;
+    let summaries = cost_ledger::summarize(&entries);
+    let mut rendered = Vec::new();
+    match (format, summary) {
+        ("csv", false) => cost_ledger::write_csv(&entries, &mut rendered)?,
+        ("csv", true) => cost_ledger::write_summary_csv(&summaries, &mut rendered)?,
+        ("json", false) => rendered = serde_json::to_vec_pretty(&entries)?,
+        ("json", true) => rendered = serde_json::to_vec_pretty(&summaries)?,
+        _ => {
+            let mut text = String::new();
+            if entries.is_empty() {
+                writeln!(text, "No AI calls have been recorded yet")?;
+            } else if summary {
+                writeln!(
+                    text,
+                    "{:<8} {:<10} {:>6} {:>10} {:>10} {:>10}",
+                    "Month", "Provider", "Calls", "Prompt", "Completion", "Cost"
+                )?;
+                for row in &summaries {
+                    writeln!(
+                        text,
+                        "{:<8} {:<10} {:>6} {:>10} {:>10} {:>10.4}",
+                        row.month,
+                        row.provider,
+                        row.calls,
+                        row.prompt_tokens,
+                        row.completion_tokens,
+                        row.cost_usd
+                    )?;
+                }
+            } else {
+                for entry in &entries {
+                    writeln!(
+                        text,
+                        "{}  {}/{}  {} + {} tokens  ${:.4}  {}",
+                        entry.timestamp.format("%Y-%m-%d %H:%M"),
+                        entry.provider,
+                        entry.model,
+                        entry.prompt_tokens,
+                        entry.completion_tokens,
+                        entry.cost_usd,
+                        entry.project
+                    )?;
+                }
+            }
+            if !entries.is_empty() {
+                let total: f64 = entries.iter().map(|entry| entry.cost_usd).sum();
+                writeln!(text, "Total: ${:.4} over {} call(s)", total, entries.len())?;
+            }
+            rendered = text.into_bytes();
+        }
+    }
+    match output {
+        Some(path) => {
+            std::fs::write(path, &rendered)?;
+            println!("Wrote costs to {}", path.display());
+        }
+        None => {
+            use std::io::Write as _;
+            std::io::stdout().write_all(&rendered)?;
+            if format == "json" {
+                println!();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints each `terraform` check on `dir`, failing if any of them did.
+fn check_terraform(dir: &std::path::Path) -> Result<()> {
+    let Some(checks) = crate::core::agents::devops::validate_terraform(dir)? else {
+        println!("ℹ️  terraform is not installed; skipped validation");
+        return Ok(());
+    };
+    for check in &checks {
+        let mark = if check.passed { "✅" } else { "❌" };
+        println!("{} {}", mark, check.command);
+        if !check.passed && !check.output.is_empty() {
+            for line in check.output.lines() {
+                println!("    {}", line);
+            }
+        }
+    }
+    let failed = checks.iter().filter(|check| !check.passed).count();
+    if failed > 0 {
+        anyhow::bail!(
+            "{} of the Terraform checks failed; fix {} before applying",
+            failed,
+            dir.display()
+        );
+    }
     Ok(())
 }
 
-async fn handle_agent(sub: AgentSub) -> Result<()> {
+/// Stages of the agent `print_progress` is showing that haven't finished,
+/// so an `agent --timeout` error can name the step that hung.
+static OPEN_STAGES: std::sync::Mutex<OpenStages> = std::sync::Mutex::new(OpenStages::new());
+
+/// Runs `work`, printing the progress events it sends as they arrive.
+async fn print_progress<T>(
+    mut events: tokio::sync::mpsc::UnboundedReceiver<ProgressEvent>,
+    work: impl std::future::Future<Output = Result<T>>,
+) -> Result<T> {
+    let show = |event: ProgressEvent| {
+        if let Ok(mut open) = OPEN_STAGES.lock() {
+            open.observe(&event);
+        }
+        if let Some(message) = event.message() {
+            println!("{}", message);
+        }
+    };
+    tokio::pin!(work);
+    let result = loop {
+        tokio::select! {
+            biased;
+            Some(event) = events.recv() => show(event),
+            result = &mut work => break result,
+        }
+    };
+    while let Ok(event) = events.try_recv() {
+        show(event);
+    }
+    if let Ok(mut open) = OPEN_STAGES.lock() {
+        open.clear();
+    }
+    result
+}
+
+/// What `create --dry-run` would do.
+fn preview_project(
+    engine: &TemplateEngine,
+    template: &str,
+    name: &str,
+    setup: bool,
+    force: bool,
+    variables: &std::collections::HashMap<String, String>,
+) -> Result<()> {
+    let plan = engine.plan_project(template, name, variables)?;
+    let root = std::env::current_dir()?.join(name);
+    println!("Dry run: nothing will be written.");
+    println!("Files ({}):", plan.len());
+    for file in &plan {
+        let note = if file.exists() { "  (overwrite)" } else { "" };
+        println!("  {}{}", file.path.display(), note);
+    }
+    println!("Project record:");
+    println!("  name:     {}", name);
+    println!("  root:     {}", root.display());
+    println!("  provider: ollama");
+    println!("  model:    llama3:70b");
+
+    let steps = engine.get(template).map(|t| t.post_create()).unwrap_or_default();
+    if !steps.is_empty() {
+        let verb = if setup { "Would run" } else { "Would skip" };
+        println!("{} setup steps: {}", verb, steps.join(", "));
+    }
+    if force {
+        let overwritten = plan.iter().filter(|file| file.exists()).count();
+        if overwritten > 0 {
+            println!("⚠️  --force will overwrite {} existing file(s)", overwritten);
+        }
+    } else if let Err(err) =
+        crate::utils::templates::check_target(std::path::Path::new(name), &plan)
+    {
+        println!("❌ Creating it would fail: {}", err);
+    }
+    Ok(())
+}
+
+fn handle_template(sub: TemplateSub) -> Result<()> {
+    use crate::utils::templates::user_templates_dir;
+
+    match sub {
+        TemplateSub::Add {
+            name,
+            source,
+            replace,
+        } => {
+            let path = TemplateEngine::add_custom_template(
+                &user_templates_dir(),
+                &name,
+                &source,
+                replace,
+            )?;
+            println!("✅ Installed template '{}' at {}", name, path.display());
+            println!("Create a project with: kandil create {} <project-name>", name);
+        }
+        TemplateSub::List => {
+            let engine = TemplateEngine::with_user_templates()?;
+            let mut names: Vec<_> = engine
+                .list_templates()
+                .into_iter()
+                .map(|(name, _)| name.clone())
+                .collect();
+            names.sort();
+            for name in names {
+                let Some(template) = engine.get(&name) else {
+                    continue;
+                };
+                let kind = if template.is_custom() {
+                    "custom"
+                } else {
+                    "built-in"
+                };
+                println!("  {:<14} {:<9} {}", name, kind, template.description());
+            }
+            println!("Custom templates directory: {}", user_templates_dir().display());
+        }
+    }
+    Ok(())
+}
+
+/// Runs the agent, bounded by `timeout` seconds when given. The bound covers
+/// every model call the agent makes, on top of each request's own timeout.
+async fn handle_agent(sub: AgentSub, choice: ModelOverride, timeout: Option<u64>) -> Result<()> {
+    let Some(secs) = timeout else {
+        return run_agent(sub, choice).await;
+    };
+    match tokio::time::timeout(std::time::Duration::from_secs(secs), run_agent(sub, choice)).await
+    {
+        Ok(result) => result,
+        Err(_) => {
+            let step = OPEN_STAGES
+                .lock()
+                .ok()
+                .and_then(|open| open.current().map(str::to_string));
+            match step {
+                Some(step) => anyhow::bail!(
+                    "Agent timed out after {}s during the '{}' step",
+                    secs,
+                    step
+                ),
+                None => anyhow::bail!("Agent timed out after {}s", secs),
+            }
+        }
+    }
+}
+
+async fn run_agent(sub: AgentSub, choice: ModelOverride) -> Result<()> {
     let config = Config::load()?;
     let factory = AIProviderFactory::new(config.clone());
-    let router = PromptRouter::new();
     
     // Route based on agent type
     let (intent, task_description) = match &sub {
         AgentSub::Requirements { description } => (PromptIntent::Planning, description.clone()),
         AgentSub::Design { requirements } => (PromptIntent::Architecture, requirements.clone()),
-        AgentSub::Code { design_path, .. } => (PromptIntent::Coding, format!("Generate code from {}", design_path)),
+        AgentSub::Code { design_path, .. } => (
+            PromptIntent::Coding,
+            format!("Generate code from {}", design_path.as_deref().unwrap_or("an inline design")),
+        ),
         AgentSub::Test { .. } => (PromptIntent::Testing, "Generate tests".to_string()),
+        AgentSub::Pipeline { description, .. } => (PromptIntent::Coding, description.clone()),
         AgentSub::Documentation { .. } => (PromptIntent::Analysis, "Generate documentation".to_string()),
         _ => (PromptIntent::Conversation, "Agent task".to_string()),
     };
     
-    let routed = router.route_for_intent(intent, &config.ai_provider, &config.ai_model);
+    let routed = choice.route(&config, intent)?;
     let ai = Arc::new(factory.create_ai(&routed.provider, &routed.model)?);
     
+    // On stderr, so `--format json` and LCOV on stdout stay parseable
     if routed.provider != config.ai_provider || routed.model != config.ai_model {
-        println!("🎯 Agent using {} ({}) for {:?} intent", 
-            routed.provider, routed.model, routed.intent);
+        eprintln!(
+            "🎯 Agent using {} ({}) for {:?} intent",
+            routed.provider, routed.model, routed.intent
+        );
     }
 
     match sub {
@@ -997,11 +2594,16 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
         AgentSub::Code {
             design_path,
             language,
+            design_text,
         } => {
-            // Read the design document
-            let design_content = std::fs::read_to_string(&design_path)
-                .unwrap_or_else(|_| "Design document content would be read from file".to_string());
-
+            let design_content = read_design(design_path.as_deref(), design_text)?;
+            let language = resolve_language(&language, || match design_path.as_deref() {
+                Some(path) if path != "-" => detect_language(path),
+                _ => {
+                    let project = enhanced_ui::context::ProjectContext::detect().project_type;
+                    language::detect_text(&design_content, "the design", &project)
+                }
+            })?;
             let code_agent = crate::core::agents::CodeAgent::new(ai)?;
             let output = code_agent.generate_code(&design_content, &language).await?;
             println!(
@@ -1015,27 +2617,111 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
         AgentSub::Test { sub: test_cmd } => {
             let test_agent = crate::core::agents::TestAgent::new(ai);
             match test_cmd {
-                TestSubCommand::Generate { source, language } => {
-                    let tests = test_agent.generate_tests(&source, &language).await?;
+                TestSubCommand::Generate {
+                    source,
+                    language,
+                    framework,
+                } => {
+                    let language = resolve_language(&language, || detect_language(&source))?;
+                    let framework = match framework {
+                        Some(framework) => Some(framework),
+                        None => {
+                            let framework =
+                                language::test_framework(&language, &std::env::current_dir()?);
+                            if let Some(framework) = framework {
+                                println!(
+                                    "🔎 Writing {} tests; pass --framework to override",
+                                    framework
+                                );
+                            }
+                            framework.map(str::to_string)
+                        }
+                    };
+                    let tests = test_agent
+                        .generate_tests(&source, &language, framework.as_deref())
+                        .await?;
                     println!("Generated tests for: {}", source);
                     println!("{}", tests);
                 }
-                TestSubCommand::Execute { test, framework } => {
-                    let results = test_agent.execute_tests(&test, &framework).await?;
+                TestSubCommand::Execute {
+                    test,
+                    framework,
+                    timeout,
+                } => {
+                    let results = test_agent
+                        .execute_tests(&test, &framework, std::time::Duration::from_secs(timeout))
+                        .await?;
                     println!("Test execution results:");
                     println!(
                         "  Passed: {}, Failed: {}, Skipped: {}",
                         results.passed, results.failed, results.skipped
                     );
                     println!("  Duration: {}ms", results.duration_ms);
+                    for failure in results.failures() {
+                        println!("\n❌ {}", failure.name);
+                        if let Some(message) = &failure.message {
+                            for line in message.lines() {
+                                println!("   {}", line);
+                            }
+                        }
+                    }
+                    if results.failed > 0 {
+                        anyhow::bail!("{} test(s) failed", results.failed);
+                    }
                 }
-                TestSubCommand::Coverage { source, test } => {
+                TestSubCommand::Coverage {
+                    source,
+                    test,
+                    format,
+                    output,
+                } => {
+                    if format == "lcov" {
+                        write_lcov(&source, &test, output.as_deref()).await?;
+                        return Ok(());
+                    }
                     let analysis = test_agent.analyze_test_coverage(&source, &test).await?;
                     println!("Test coverage analysis for {} and {}:", source, test);
                     println!("{}", analysis);
                 }
             }
         }
+        AgentSub::Pipeline {
+            description,
+            language,
+            output_dir,
+            from,
+            max_cost,
+        } => {
+            let from: crate::core::agents::PipelineStage = from.parse()?;
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+            let mut pipeline = crate::core::agents::AgentPipeline::new(
+                ai,
+                factory.get_cost_tracker(),
+                &output_dir,
+                &language,
+            )
+            .with_max_cost(max_cost)
+            .with_progress(tx);
+
+            println!("🚀 Running agent pipeline from '{}' into {}", from.as_str(), output_dir);
+            let run = pipeline.run(&description, from, |report| {
+                println!(
+                    "  ✅ {:<12} {} artifact(s), ~${:.4}",
+                    report.stage.as_str(),
+                    report.artifacts.len(),
+                    report.estimated_cost
+                );
+                for artifact in &report.artifacts {
+                    println!("       {}", artifact.display());
+                }
+            });
+            let reports = print_progress(rx, run).await?;
+            println!(
+                "Pipeline finished: {} stage(s), estimated cost ${:.4}",
+                reports.len(),
+                pipeline.spent()
+            );
+        }
         AgentSub::Documentation { sub: doc_cmd } => {
             let doc_agent = crate::core::agents::documentation::DocumentationGenerator::new(ai);
             match doc_cmd {
@@ -1044,15 +2730,78 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
                     println!("Generated documentation for project at {}:", path);
                     println!("{}", report);
                 }
+                DocumentationSubCommand::Sync { path, apply } => {
+                    let stale = crate::core::doc_sync::find_stale_references(std::path::Path::new(&path))?;
+                    if stale.is_empty() {
+                        println!("✅ Documentation in {} matches the code", path);
+                        return Ok(());
+                    }
+
+                    println!("Found {} stale reference(s):", stale.len());
+                    for item in &stale {
+                        println!(
+                            "  {}:{} `{}` - {}",
+                            item.doc_file.display(),
+                            item.line,
+                            item.reference,
+                            item.reason
+                        );
+                        if let Some(suggestion) = &item.suggestion {
+                            println!("      did you mean `{}`?", suggestion);
+                        }
+                    }
+
+                    if !apply {
+                        println!("\nRe-run with --apply to rewrite these lines with AI-generated fixes.");
+                        return Ok(());
+                    }
+
+                    // Group problems per file and line so each line is rewritten once
+                    let mut by_file: std::collections::BTreeMap<
+                        PathBuf,
+                        std::collections::BTreeMap<usize, Vec<String>>,
+                    > = std::collections::BTreeMap::new();
+                    for item in &stale {
+                        let mut problem = item.reason.clone();
+                        if let Some(suggestion) = &item.suggestion {
+                            problem.push_str(&format!(" (closest match: `{}`)", suggestion));
+                        }
+                        by_file
+                            .entry(item.doc_file.clone())
+                            .or_default()
+                            .entry(item.line)
+                            .or_default()
+                            .push(problem);
+                    }
+
+                    for (file, lines) in by_file {
+                        let content = std::fs::read_to_string(&file)?;
+                        let mut updated: Vec<String> = content.lines().map(String::from).collect();
+                        for (line, problems) in lines {
+                            let fixed = doc_agent.propose_doc_fix(&updated[line - 1], &problems).await?;
+                            println!("  {}:{}\n    - {}\n    + {}", file.display(), line, updated[line - 1], fixed);
+                            updated[line - 1] = fixed;
+                        }
+                        let mut output = updated.join("\n");
+                        if content.ends_with('\n') {
+                            output.push('\n');
+                        }
+                        std::fs::write(&file, output)?;
+                    }
+                    println!("✅ Applied documentation fixes");
+                }
             }
         }
         AgentSub::Release { sub: release_cmd } => {
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+            // Placeholder version
             let mut release_manager =
-                crate::core::agents::release_manager::ReleaseManager::new(ai, "0.1.0".to_string()); // Placeholder version
+                crate::core::agents::release_manager::ReleaseManager::new(ai, "0.1.0".to_string())
+                    .with_progress(tx);
             match release_cmd {
                 ReleaseSubCommand::FullProcess { version } => {
                     release_manager.version = version; // Update version from CLI arg
-                    release_manager.run_full_release_process().await?;
+                    print_progress(rx, release_manager.run_full_release_process()).await?;
                     println!(
                         "Full release process completed for version: {}",
                         release_manager.version
@@ -1061,26 +2810,149 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
             }
         }
         AgentSub::Qa { sub: qa_cmd } => {
-            let mut qa_system =
+            let qa_system =
                 crate::core::agents::quality_assurance::QualityAssuranceSystem::new(ai);
             match qa_cmd {
-                QaSubCommand::FullSuite { project_path } => {
-                    let _report = qa_system.run_full_qa_suite(&project_path).await?;
-                    println!("Full QA suite completed for project at {}:", project_path);
-                    println!("{}", qa_system.generate_qa_report_md());
+                QaSubCommand::FullSuite {
+                    project_path,
+                    retry_failed,
+                    format,
+                    output,
+                    notify,
+                } => {
+                    if format == "junit" && output.is_none() {
+                        // Progress lines go to stdout, so the XML needs its own file
+                        anyhow::bail!("--format junit needs --output <path>");
+                    }
+                    let started = std::time::Instant::now();
+                    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+                    let mut qa_system = qa_system.with_retry_failed(retry_failed).with_progress(tx);
+                    let result =
+                        print_progress(rx, qa_system.run_full_qa_suite(&project_path)).await;
+                    notify
+                        .send(&config, "agent qa full-suite", started, &result, |report| {
+                            let tests = &report.test_results;
+                            format!(
+                                "{}: {}/{} tests passed, {} failed; quality score {:.1}, {:?}",
+                                project_path,
+                                tests.passed_tests,
+                                tests.total_tests,
+                                tests.failed_tests,
+                                report.overall_quality_score,
+                                report.readiness_level
+                            )
+                        })
+                        .await;
+                    result?;
+                    let report = match format.as_str() {
+                        "junit" => qa_system.generate_qa_report_junit()?,
+                        _ => qa_system.generate_qa_report_md(),
+                    };
+                    match output {
+                        Some(path) => {
+                            fs::write(&path, report).await?;
+                            println!(
+                                "Full QA suite completed for project at {}; report written to {}",
+                                project_path,
+                                path.display()
+                            );
+                        }
+                        None => {
+                            println!("Full QA suite completed for project at {}:", project_path);
+                            println!("{}", report);
+                        }
+                    }
                 }
             }
         }
         AgentSub::Maintenance {
             sub: maintenance_cmd,
         } => {
-            let mut maintenance_manager =
-                crate::core::agents::maintenance::MaintenanceManager::new(ai);
+            use crate::core::agents::maintenance::{self, MaintenanceWindow, UpdateType};
+
+            let mut maintenance_manager = maintenance::MaintenanceManager::new(ai);
+            let saved = maintenance::load_windows()?;
+            if !saved.is_empty() {
+                maintenance_manager.update_scheduler.maintenance_windows = saved.clone();
+            }
             match maintenance_cmd {
                 MaintenanceSubCommand::HealthCheck { system_name } => {
                     maintenance_manager.run_health_checks(&system_name).await?;
                     println!("Health check completed for system: {}", system_name);
                 }
+                MaintenanceSubCommand::NextWindow => {
+                    let scheduler = &maintenance_manager.update_scheduler;
+                    let Some((start, window)) = scheduler.next_window(chrono::Utc::now())? else {
+                        println!("No maintenance windows configured");
+                        return Ok(());
+                    };
+                    let end = start + window.duration()?;
+                    let local = start.with_timezone(&chrono::Local);
+                    println!(
+                        "Next maintenance window: {} to {} (local time)",
+                        local.format("%a %Y-%m-%d %H:%M %Z"),
+                        end.with_timezone(&chrono::Local).format("%H:%M")
+                    );
+                    println!(
+                        "  {} in {}; allows {:?}",
+                        start.format("%a %Y-%m-%d %H:%M"),
+                        window.timezone,
+                        window.allowed_updates
+                    );
+                }
+                MaintenanceSubCommand::Windows => {
+                    if saved.is_empty() {
+                        println!("No saved windows; using the default weekly window.");
+                        println!(
+                            "Add one with: kandil agent maintenance add-window \"0 2 * * Sun\""
+                        );
+                    }
+                    for (index, window) in saved.iter().enumerate() {
+                        let schedule = window.schedule()?;
+                        println!(
+                            "  {}. {} ({}) for {} min, allows {:?}",
+                            index + 1,
+                            window.cron_expression.as_deref().unwrap_or(&schedule.to_string()),
+                            window.timezone,
+                            window.duration()?.num_minutes(),
+                            window.allowed_updates
+                        );
+                    }
+                }
+                MaintenanceSubCommand::AddWindow {
+                    cron,
+                    timezone,
+                    duration,
+                    allow,
+                } => {
+                    let allowed = allow
+                        .iter()
+                        .map(|kind| match kind.as_str() {
+                            "security-patch" => UpdateType::SecurityPatch,
+                            "bug-fix" => UpdateType::BugFix,
+                            "feature" => UpdateType::Feature,
+                            "enhancement" => UpdateType::Enhancement,
+                            _ => UpdateType::Major,
+                        })
+                        .collect();
+                    let window = MaintenanceWindow::from_cron(
+                        &cron,
+                        &timezone,
+                        chrono::Duration::minutes(duration),
+                        allowed,
+                    )?;
+                    let first = window.start_time.clone();
+                    let index = maintenance::save_window(window)?;
+                    println!("✅ Saved maintenance window {}; it first opens {}", index, first);
+                }
+                MaintenanceSubCommand::RemoveWindow { index } => {
+                    let removed = maintenance::remove_window(index)?;
+                    println!(
+                        "Removed maintenance window {} ({})",
+                        index,
+                        removed.cron_expression.unwrap_or_default()
+                    );
+                }
             }
         }
         AgentSub::Simulate { sub: sim_cmd } => match sim_cmd {
@@ -1112,25 +2984,95 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
                         println!("Requirements validation for: {}", requirements);
                         println!("{}", validation);
                     }
-                    BaSubCommand::UserStory { feature } => {
+                    BaSubCommand::UserStory {
+                        feature,
+                        format,
+                        output,
+                    } => {
                         let story = ba_sim.create_user_story(&feature).await?;
-                        println!("Created user story for feature: {}", feature);
-                        println!("  ID: {}, Title: {}", story.id, story.title);
-                        println!("  Priority: {:?}", story.priority);
-                        println!("  Story Points: {}", story.story_points);
+                        let rendered = match format.as_str() {
+                            "gherkin" => story.to_gherkin(),
+                            "json" => serde_json::to_string_pretty(&story)? + "\n",
+                            "md" => story.to_markdown(),
+                            _ => {
+                                let mut text = format!(
+                                    "Created user story for feature: {}\n",
+                                    feature
+                                );
+                                text += &format!(
+                                    "  ID: {}, Title: {}\n  Priority: {:?}\n",
+                                    story.id, story.title, story.priority
+                                );
+                                text += &format!(
+                                    "  Story Points: {}\n  {}\n  Acceptance criteria:\n",
+                                    story.story_points, story.description
+                                );
+                                for criterion in &story.acceptance_criteria {
+                                    text += &format!(
+                                        "    - {}: given {}, when {}, then {}\n",
+                                        criterion.scenario,
+                                        criterion.given.join(" and "),
+                                        criterion.when.join(" and "),
+                                        criterion.then.join(" and ")
+                                    );
+                                }
+                                text
+                            }
+                        };
+                        match output {
+                            Some(path) => {
+                                std::fs::write(&path, rendered)?;
+                                println!("Wrote user story to {}", path.display());
+                            }
+                            None => print!("{}", rendered),
+                        }
                     }
                 }
             }
         },
         AgentSub::Advanced { sub: advanced_cmd } => {
             match advanced_cmd {
-                AdvancedSubCommand::Review { file } => {
+                AdvancedSubCommand::Review {
+                    file,
+                    include_tests,
+                    exclude,
+                    format,
+                    detailed,
+                } => {
                     let review_agent = crate::core::agents::ReviewAgent::new(ai);
-                    let report = review_agent.code_review(&file).await?;
-                    println!("Code review for: {}", file);
-                    println!("  Score: {}/100", report.score);
-                    println!("  Issues found: {}", report.issues.len());
-                    println!("  Summary: {}", report.summary);
+                    if std::path::Path::new(&file).is_dir() {
+                        let options = crate::core::agents::review::DirectoryReviewOptions {
+                            include_tests,
+                            exclude,
+                        };
+                        let review = review_agent
+                            .directory_review(std::path::Path::new(&file), &options)
+                            .await?;
+                        if format == "json" {
+                            println!("{}", serde_json::to_string_pretty(&review)?);
+                        } else {
+                            print_directory_review(&file, &review);
+                            if detailed {
+                                for reviewed in &review.files {
+                                    let path = reviewed.path.to_string_lossy();
+                                    print_review_issues(&path, &reviewed.report);
+                                }
+                            }
+                        }
+                    } else {
+                        let report = review_agent.code_review(&file).await?;
+                        if format == "json" {
+                            println!("{}", serde_json::to_string_pretty(&report)?);
+                        } else {
+                            println!("Code review for: {}", file);
+                            println!("  Score: {}/100", report.score);
+                            println!("  Issues found: {}", report.issues.len());
+                            println!("  Summary: {}", report.summary);
+                            if detailed {
+                                print_review_issues(&file, &report);
+                            }
+                        }
+                    }
                 }
                 AdvancedSubCommand::Security { file, description } => {
                     let security_agent = crate::core::agents::EthicsSecurityAgent::new(ai);
@@ -1153,7 +3095,11 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
                 AdvancedSubCommand::Deploy { sub: deploy_cmd } => {
                     let deploy_agent = crate::core::agents::DeploymentAgent::new(ai)?;
                     match deploy_cmd {
-                        DeploySubCommand::Plan { environment, app } => {
+                        DeploySubCommand::Plan {
+                            environment,
+                            app,
+                            output,
+                        } => {
                             let plan = deploy_agent
                                 .create_deployment_plan(&environment, &app)
                                 .await?;
@@ -1168,15 +3114,85 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
                                     "Yes"
                                 }
                             );
+                            if let Some(path) = output {
+                                fs::write(&path, serde_json::to_string_pretty(&plan)?).await?;
+                                println!("Saved the plan to {}", path.display());
+                            }
                         }
                         DeploySubCommand::Execute { plan } => {
-                            // In a real implementation, this would load and execute the plan
                             println!("Executing deployment plan from: {}", plan);
-                            println!("Deployment execution would happen in full implementation");
+                            let record = deploy_agent
+                                .execute_deployment(plan.as_ref(), &mut print_step)
+                                .await?;
+                            if !record.succeeded() {
+                                anyhow::bail!(
+                                    "Deployment to {} failed; roll it back with \
+                                     `kandil agent advanced deploy rollback {}`",
+                                    record.environment,
+                                    plan
+                                );
+                            }
+                            println!("✅ Deployed to {}", record.environment);
+                        }
+                        DeploySubCommand::Rollback { plan, dry_run } => {
+                            use crate::core::agents::deployment::{self, RollbackReadiness};
+
+                            let plan_path = std::path::Path::new(&plan);
+                            let loaded = deployment::load_plan(plan_path)?;
+                            let history = deployment::plan_history(plan_path)?;
+                            match deployment::rollback_readiness(&history) {
+                                RollbackReadiness::Ready => {}
+                                RollbackReadiness::NeverExecuted => {
+                                    println!(
+                                        "⚠️  {} was never executed; there is nothing to roll back",
+                                        plan
+                                    );
+                                    return Ok(());
+                                }
+                                RollbackReadiness::RolledBack(at) => anyhow::bail!(
+                                    "{} was already rolled back at {}; execute it again before another rollback",
+                                    plan,
+                                    at.to_rfc3339()
+                                ),
+                            }
+                            if dry_run {
+                                println!("Dry run: rollback steps for {}:", loaded.environment);
+                                for (index, step) in loaded.rollback_plan.steps.iter().enumerate() {
+                                    println!("  {}. {}: {}", index + 1, step.action, step.command);
+                                }
+                                return Ok(());
+                            }
+                            let record = deploy_agent
+                                .rollback(plan_path, &mut print_step)
+                                .await?;
+                            if !record.succeeded() {
+                                anyhow::bail!("Rollback of {} failed", record.environment);
+                            }
+                            println!("✅ Rolled back {}", record.environment);
+                        }
+                        DeploySubCommand::Assist {
+                            environment,
+                            task,
+                            yes,
+                        } => {
+                            let mut runner = deploy_agent.tool_runner(&environment, |call| {
+                                println!("🔧 {} {}", call.name, call.arguments);
+                                yes || confirm("Allow this tool call?").unwrap_or(false)
+                            })?;
+                            let answer = deploy_agent
+                                .assist(&environment, &task, &mut runner)
+                                .await?;
+                            println!("{}", answer);
                         }
                     }
                 }
-                AdvancedSubCommand::SelfImprove { path } => {
+                AdvancedSubCommand::SelfImprove {
+                    path,
+                    apply,
+                    report,
+                    force,
+                    test_cmd,
+                } => {
                     let meta_agent = crate::core::agents::MetaAgent::new(ai);
                     let analysis = meta_agent.analyze_system(&path).await?;
                     println!("System analysis for: {}", path);
@@ -1193,6 +3209,35 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
                     let improvement_plans = meta_agent.generate_improvement_plan(&analysis).await?;
                     println!("  Suggested improvements: {}", improvement_plans.len());
 
+                    if apply {
+                        apply_improvement_plans(
+                            &meta_agent,
+                            &improvement_plans,
+                            &path,
+                            force,
+                            test_cmd,
+                        )
+                        .await?;
+                    } else {
+                        let report = match report {
+                            Some(report) => report,
+                            None => std::env::current_dir()?
+                                .join(crate::utils::config::PROJECT_CONFIG_DIR)
+                                .join("self-improve-report.json"),
+                        };
+                        if let Some(parent) = report.parent() {
+                            std::fs::create_dir_all(parent)?;
+                        }
+                        let contents = serde_json::json!({
+                            "path": path,
+                            "analysis": analysis,
+                            "plans": improvement_plans,
+                        });
+                        std::fs::write(&report, serde_json::to_string_pretty(&contents)?)?;
+                        println!("  Plans written to {}", report.display());
+                        println!("  Re-run with --apply to review and apply them as file changes");
+                    }
+
                     // Also run the self-evolution capability
                     let evolution_result = meta_agent.evolve_agent_capabilities().await?;
                     println!("  Self-improvement analysis: {}", evolution_result);
@@ -1258,7 +3303,8 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
                 }
 
                 TechRoleSubCommand::Collaborate { sub: collab_cmd } => {
-                    let mut collab_manager = crate::core::agents::CollaborationManager::new();
+                    let mut collab_manager =
+                        crate::core::agents::CollaborationManager::open_default()?;
                     match collab_cmd {
                         CollaborateSubCommand::Session {
                             title,
@@ -1282,7 +3328,7 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
 
                             let session_id = collab_manager
                                 .start_collaboration_session(&title, role_list, agenda)
-                                .await;
+                                .await?;
                             println!("Collaboration session started: {}", session_id);
                         }
                         CollaborateSubCommand::Decision {
@@ -1322,9 +3368,21 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
                 AdvancedFeaturesSubCommand::DevOps { sub: devops_cmd } => {
                     let devops_agent = crate::core::agents::DevOpsSimulation::new(ai);
                     match devops_cmd {
-                        DevOpsSubCommand::Terraform { spec } => {
-                            let tf_path = devops_agent.generate_terraform(&spec).await?;
+                        DevOpsSubCommand::Terraform {
+                            spec,
+                            cloud,
+                            validate,
+                        } => {
+                            let cloud = cloud
+                                .as_deref()
+                                .map(str::parse::<crate::core::agents::devops::CloudProvider>)
+                                .transpose()?;
+                            let tf_path = devops_agent.generate_terraform(&spec, cloud).await?;
                             println!("Generated Terraform configuration: {:?}", tf_path);
+                            if validate {
+                                let dir = tf_path.parent().unwrap_or(std::path::Path::new("."));
+                                check_terraform(dir)?;
+                            }
                         }
                         DevOpsSubCommand::Drill { scenario } => {
                             let report = devops_agent.run_drill(&scenario).await?;
@@ -1333,11 +3391,49 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
                             println!("  Duration: {} seconds", report.duration_seconds);
                             println!("  Effectiveness: {}/100", report.effectiveness_score);
                         }
-                        DevOpsSubCommand::Pipeline { project_type } => {
-                            let pipeline =
-                                devops_agent.generate_ci_cd_pipeline(&project_type).await?;
-                            println!("Generated CI/CD pipeline for {} projects", project_type);
-                            println!("{}", pipeline);
+                        DevOpsSubCommand::Pipeline {
+                            project_type,
+                            ci,
+                            write,
+                            force,
+                        } => {
+                            let provider = ci
+                                .as_deref()
+                                .map(str::parse::<crate::core::agents::devops::CiProvider>)
+                                .transpose()?;
+                            let target = provider.map(|provider| PathBuf::from(provider.path()));
+                            if let Some(target) = target.as_ref().filter(|_| write && !force) {
+                                if target.exists() {
+                                    anyhow::bail!(
+                                        "{} already exists. Pass --force to overwrite it",
+                                        target.display()
+                                    );
+                                }
+                            }
+                            let pipeline = devops_agent
+                                .generate_ci_cd_pipeline(&project_type, provider)
+                                .await?;
+                            match (provider, target) {
+                                (Some(provider), Some(target)) if write => {
+                                    if let Some(parent) = target.parent() {
+                                        std::fs::create_dir_all(parent)?;
+                                    }
+                                    std::fs::write(&target, &pipeline)?;
+                                    println!(
+                                        "✅ Wrote {} pipeline for {} projects to {}",
+                                        provider.name(),
+                                        project_type,
+                                        target.display()
+                                    );
+                                }
+                                _ => {
+                                    println!(
+                                        "Generated CI/CD pipeline for {} projects",
+                                        project_type
+                                    );
+                                    println!("{}", pipeline);
+                                }
+                            }
                         }
                     }
                 }
@@ -1398,11 +3494,64 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
                             println!("Translation from {} to {}:", source, target);
                             println!("{}", translation);
                         }
+                        I18nSubCommand::TranslateFile {
+                            path,
+                            target,
+                            source,
+                            output,
+                            only_missing,
+                        } => {
+                            use crate::core::agents::resource_file::default_output_path;
+                            let source_path = std::path::Path::new(&path);
+                            let output = output
+                                .map(std::path::PathBuf::from)
+                                .unwrap_or_else(|| default_output_path(source_path, &target));
+                            let report = i18n_agent
+                                .translate_resource_file(
+                                    source_path,
+                                    &output,
+                                    &target,
+                                    &source,
+                                    only_missing,
+                                )
+                                .await?;
+                            for key in &report.translated {
+                                println!("  ✓ {}", key);
+                            }
+                            for (key, reason) in &report.failed {
+                                println!("  ✗ {}: {}", key, reason);
+                            }
+                            println!(
+                                "Wrote {}: {} translated, {} kept, {} failed",
+                                report.output.display(),
+                                report.translated.len(),
+                                report.kept.len(),
+                                report.failed.len()
+                            );
+                            if !report.failed.is_empty() {
+                                anyhow::bail!(
+                                    "{} key(s) could not be translated; re-run with --only-missing to retry them",
+                                    report.failed.len()
+                                );
+                            }
+                        }
                         I18nSubCommand::Audit { path } => {
                             let report = i18n_agent.audit_translations(&path).await?;
                             println!("Translation audit completed:");
                             println!("  Languages: {}", report.completeness_by_language.len());
                             println!("  Recommendations: {}", report.recommendations.len());
+                            if !report.hardcoded_formats.is_empty() {
+                                println!(
+                                    "  Hardcoded formats: {}",
+                                    report.hardcoded_formats.len()
+                                );
+                                for format in &report.hardcoded_formats {
+                                    println!(
+                                        "    {}:{} `{}` - {}",
+                                        format.file, format.line, format.snippet, format.reason
+                                    );
+                                }
+                            }
                         }
                         I18nSubCommand::Review {
                             original,
@@ -1416,18 +3565,96 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
                             println!("  Quality score: {}/100", report.quality_score);
                             println!("  Issues found: {}", report.issues_found.len());
                         }
+                        I18nSubCommand::Format {
+                            locale,
+                            number,
+                            date,
+                        } => {
+                            use crate::core::agents::locale_format::{
+                                parse_iso_date, LocaleFormatter,
+                            };
+                            if number.is_none() && date.is_none() {
+                                anyhow::bail!("Pass --number and/or --date to format");
+                            }
+                            let formatter = LocaleFormatter::for_locale(&locale)?;
+                            println!("Locale: {}", formatter.tag());
+                            if let Some(number) = number {
+                                println!("  Number: {}", formatter.format_number(number));
+                                println!(
+                                    "  Currency ({}): {}",
+                                    formatter.currency_code(),
+                                    formatter.format_currency(number)
+                                );
+                            }
+                            if let Some(date) = date {
+                                let date = parse_iso_date(&date)?;
+                                println!("  Date: {}", formatter.format_date(date));
+                            }
+                        }
                     }
                 }
                 AdvancedFeaturesSubCommand::A11y { sub: a11y_cmd } => {
-                    let a11y_agent = crate::core::agents::A11yAssistant::new(ai);
+                    let a11y_agent = crate::core::agents::A11yAssistant::new(ai.clone());
                     match a11y_cmd {
-                        A11ySubCommand::Audit { content } => {
-                            // Default to WCAG AA level
-                            use crate::core::agents::a11y::WcagLevel;
-                            let report = a11y_agent.wcag_audit(&content, WcagLevel::AA).await?;
-                            println!("Accessibility audit completed:");
-                            println!("  Score: {}/100", report.accessibility_score);
-                            println!("  Issues found: {}", report.issues_found.len());
+                        A11ySubCommand::Audit {
+                            content,
+                            format,
+                            fail_on,
+                        } => {
+                            use crate::core::agents::enhanced_a11y::{
+                                AxeImpact, EnhancedA11yAssistant,
+                            };
+                            let fail_on = fail_on
+                                .as_deref()
+                                .map(str::parse::<AxeImpact>)
+                                .transpose()?;
+
+                            if format == "text" && fail_on.is_none() {
+                                // Default to WCAG AA level
+                                use crate::core::agents::a11y::WcagLevel;
+                                let report =
+                                    a11y_agent.wcag_audit(&content, WcagLevel::AA).await?;
+                                println!("Accessibility audit completed:");
+                                println!("  Score: {}/100", report.accessibility_score);
+                                println!("  Issues found: {}", report.issues_found.len());
+                                return Ok(());
+                            }
+
+                            let enhanced = EnhancedA11yAssistant::new(ai);
+                            let report = enhanced.comprehensive_wcag_audit(&content).await?;
+                            let axe = report.to_axe(&enhanced.wcag_standards, "");
+                            match format.as_str() {
+                                "json" => println!("{}", serde_json::to_string_pretty(&report)?),
+                                "axe" => println!("{}", serde_json::to_string_pretty(&axe)?),
+                                "text" => {
+                                    println!("Accessibility audit completed:");
+                                    println!(
+                                        "  Score: {}/100",
+                                        report.compliance_summary.overall_score
+                                    );
+                                    for violation in &axe.violations {
+                                        println!(
+                                            "  [{:?}] {} - {}",
+                                            violation.impact, violation.help, violation.description
+                                        );
+                                    }
+                                }
+                                other => anyhow::bail!(
+                                    "Unknown format '{}'. Expected one of: text, json, axe",
+                                    other
+                                ),
+                            }
+
+                            if let Some(threshold) = fail_on {
+                                let failing = axe.violations_at_or_above(threshold);
+                                if !failing.is_empty() {
+                                    anyhow::bail!(
+                                        "{} accessibility violation(s) at or above {:?} impact",
+                                        failing.len(),
+                                        threshold
+                                    );
+                                }
+                            }
                         }
                         A11ySubCommand::Guidelines { component } => {
                             let guidelines =
@@ -1443,7 +3670,7 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
                     }
                 }
                 AdvancedFeaturesSubCommand::Collab { sub: collab_cmd } => {
-                    let mut collab = crate::core::agents::RealTimeCollaboration::new();
+                    let mut collab = crate::core::agents::RealTimeCollaboration::open_default()?;
                     match collab_cmd {
                         CollabSubCommand::Session {
                             name,
@@ -1487,6 +3714,44 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
                             )?;
                             println!("Added document {} to session {}", name, session_id);
                         }
+                        CollabSubCommand::List => {
+                            let sessions = collab.list_sessions();
+                            if sessions.is_empty() {
+                                println!("No collaboration sessions yet.");
+                            }
+                            for session in sessions {
+                                println!(
+                                    "{}  {}  ({} participants, {} documents, last active {})",
+                                    session.id,
+                                    session.name,
+                                    session.participants.len(),
+                                    session.documents.len(),
+                                    session.last_activity
+                                );
+                            }
+                        }
+                        CollabSubCommand::Info { session_id } => {
+                            let session = collab.get_session(&session_id)?;
+                            println!("Session {}: {}", session.id, session.name);
+                            println!("  Created: {}", session.created_at);
+                            println!("  Last activity: {}", session.last_activity);
+                            println!("  Participants:");
+                            for participant in &session.participants {
+                                println!(
+                                    "    - {} ({}) {:?}",
+                                    participant.name, participant.id, participant.role
+                                );
+                            }
+                            println!("  Documents:");
+                            let mut documents: Vec<_> = session.documents.values().collect();
+                            documents.sort_by(|a, b| a.id.cmp(&b.id));
+                            for doc in documents {
+                                println!(
+                                    "    - {} [{}] {} v{}",
+                                    doc.name, doc.id, doc.language, doc.version
+                                );
+                            }
+                        }
                     }
                 }
                 AdvancedFeaturesSubCommand::Ide { sub: ide_cmd } => {
@@ -1532,6 +3797,16 @@ async fn handle_agent(sub: AgentSub) -> Result<()> {
                                 );
                             }
                         }
+                        IdeSubCommand::Serve {
+                            port,
+                            host,
+                            allow_origins,
+                        } => {
+                            let mut web = Config::load()?.web;
+                            web.allowed_origins.extend(allow_origins);
+                            let address = format!("{}:{}", host, port);
+                            crate::web::ide::serve(&address, ide_ext, web).await?;
+                        }
                     }
                 }
             }
@@ -1569,7 +3844,56 @@ async fn handle_projects(sub: ProjectSub) -> Result<()> {
                 project.name, project.root_path
             );
         }
-        ProjectSub::Sync { id } => {
+        ProjectSub::Sync {
+            id,
+            conflict,
+            remote: Some(remote),
+        } => {
+            let strategy: cloud_sync::ConflictStrategy = conflict.parse()?;
+            let project = match id {
+                Some(project_id) => project_manager.get_project(&project_id)?,
+                None => project_manager.list_projects()?.into_iter().next(),
+            }
+            .ok_or_else(|| anyhow::anyhow!("Project not found"))?;
+
+            let local = project_manager.get_project_memory(&project.id, None)?;
+            let theirs = cloud_sync::read_remote_file(&remote)?;
+            let plan = cloud_sync::reconcile(&local, &theirs, strategy);
+            project_manager.apply_sync(&project.id, &plan)?;
+            cloud_sync::write_remote_file(&remote, &plan.remote)?;
+
+            let report = &plan.report;
+            println!("Synced {} with {}", project.name, remote.display());
+            println!("  unchanged:                {}", report.unchanged);
+            println!("  fast-forward (pushed):    {}", report.pushed);
+            println!("  fast-forward (pulled):    {}", report.pulled);
+            println!("  local-wins:               {}", report.local_wins);
+            println!("  remote-wins:              {}", report.remote_wins);
+            println!("  newest-wins:              {}", report.newest_wins);
+            println!("  merged:                   {}", report.merged);
+            if !report.unresolved.is_empty() {
+                let path = project_manager.save_sync_conflicts(&project.id, &report.unresolved)?;
+                println!(
+                    "⚠️  {} conflict(s) left unchanged on both sides for review: {}",
+                    report.unresolved.len(),
+                    path.display()
+                );
+                for conflict in &report.unresolved {
+                    println!(
+                        "  {} | local: {:?} | remote: {:?}",
+                        conflict.entry_id,
+                        truncate_for_display(&conflict.local.content, 40),
+                        truncate_for_display(&conflict.remote.content, 40)
+                    );
+                }
+                println!(
+                    "Re-run with --conflict local-wins, remote-wins or merge to settle them."
+                );
+            }
+        }
+        ProjectSub::Sync { id, conflict, .. } => {
+            let _: cloud_sync::ConflictStrategy = conflict.parse()?;
+            offline::ensure_online("Cloud sync")?;
             // For now, just show that sync would happen
             // In a real implementation, we would use the CloudSync module
             match id {
@@ -1578,33 +3902,102 @@ async fn handle_projects(sub: ProjectSub) -> Result<()> {
             }
             println!("Note: Cloud sync functionality would be implemented with Supabase in a full implementation");
         }
-        ProjectSub::Info { id } => {
-            let project = if let Some(project_id) = id {
-                project_manager.get_project(&project_id)?
+        ProjectSub::Info { id, json } => {
+            let (p, inferred) = match id {
+                Some(project_id) => match project_manager.get_project(&project_id)? {
+                    Some(project) => (project, false),
+                    None => anyhow::bail!("Project {} not found", project_id),
+                },
+                None => match project_manager.current_project()? {
+                    Some(current) => current,
+                    None => anyhow::bail!("No projects yet; one is created the first time you chat"),
+                },
+            };
+            let stats = project_manager.project_stats(&p)?;
+
+            if json {
+                let info = serde_json::json!({
+                    "id": p.id,
+                    "name": p.name,
+                    "root_path": p.root_path,
+                    "ai_provider": p.ai_provider,
+                    "ai_model": p.ai_model,
+                    "memory_enabled": p.memory_enabled,
+                    "created_at": p.created_at.to_rfc3339(),
+                    "last_opened": p.last_opened.map(|d| d.to_rfc3339()),
+                    "inferred": inferred,
+                    "stats": stats,
+                });
+                println!("{}", serde_json::to_string_pretty(&info)?);
+                return Ok(());
+            }
+
+            if inferred {
+                println!("No project at the current directory; showing the most recently opened.");
+            }
+            println!("Project Details:");
+            println!("  ID: {}", p.id);
+            println!("  Name: {}", p.name);
+            println!("  Path: {}", p.root_path);
+            println!("  AI Provider: {}", p.ai_provider);
+            println!("  AI Model: {}", p.ai_model);
+            println!("  Memory Enabled: {}", p.memory_enabled);
+            println!("  Created: {}", p.created_at.format("%Y-%m-%d %H:%M:%S"));
+            println!(
+                "  Last Opened: {}",
+                p.last_opened
+                    .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_else(|| "Never".to_string())
+            );
+            println!("  Memory Turns: {}", stats.memory_turns);
+            println!("  Tokens Used: {}", stats.tokens_used);
+            println!("  Estimated Cost: ${:.4}", stats.estimated_cost_usd);
+            println!(
+                "  Last Session: {}",
+                stats.last_session_id.as_deref().unwrap_or("None")
+            );
+            println!("  Memory Size: {} bytes", stats.memory_bytes);
+        }
+        ProjectSub::ExportChat {
+            id,
+            session,
+            all_sessions,
+            format,
+            output,
+        } => {
+            use crate::utils::chat_export::{self, ExportFormat};
+
+            let format = ExportFormat::parse(&format)?;
+            let project = match id {
+                Some(project_id) => project_manager.get_project(&project_id)?,
+                None => project_manager.list_projects()?.first().cloned(),
+            }
+            .ok_or_else(|| anyhow::anyhow!("Project not found"))?;
+
+            let sessions =
+                chat_export::group_sessions(project_manager.get_project_memory(&project.id, None)?);
+            let sessions = if all_sessions {
+                if sessions.is_empty() {
+                    anyhow::bail!("Project {} has no stored chat sessions", project.name);
+                }
+                sessions
             } else {
-                // Get current project (for now, just get the most recently used)
-                let projects = project_manager.list_projects()?;
-                projects.first().cloned()
+                vec![chat_export::select_session(sessions, session.as_deref())?]
             };
 
-            match project {
-                Some(p) => {
-                    println!("Project Details:");
-                    println!("  ID: {}", p.id);
-                    println!("  Name: {}", p.name);
-                    println!("  Path: {}", p.root_path);
-                    println!("  AI Provider: {}", p.ai_provider);
-                    println!("  AI Model: {}", p.ai_model);
-                    println!("  Memory Enabled: {}", p.memory_enabled);
-                    println!("  Created: {}", p.created_at.format("%Y-%m-%d %H:%M:%S"));
+            let transcript = chat_export::render(&project, &sessions, format);
+            match output {
+                Some(path) => {
+                    fs::write(&path, transcript).await?;
+                    let turns: usize = sessions.iter().map(|session| session.turns.len()).sum();
                     println!(
-                        "  Last Opened: {}",
-                        p.last_opened
-                            .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string())
-                            .unwrap_or_else(|| "Never".to_string())
+                        "📝 Exported {} session(s), {} turns, to {}",
+                        sessions.len(),
+                        turns,
+                        path.display()
                     );
                 }
-                None => println!("Project not found"),
+                None => print!("{}", transcript),
             }
         }
     }
@@ -1626,7 +4019,7 @@ async fn launch_tui() -> Result<()> {
 }
 
 async fn handle_refactor(sub: RefactorSub) -> Result<()> {
-    let mut engine = RefactorEngine::new();
+    let mut engine = RefactorEngine::load_pending()?;
     match sub {
         RefactorSub::Preview {
             file,
@@ -1655,21 +4048,221 @@ async fn handle_refactor(sub: RefactorSub) -> Result<()> {
             }
 
             let result = engine.preview_refactor(&file, &refactor_type, &refactor_params)?;
+            engine.save_pending()?;
             println!("Refactoring preview for '{}':", file);
             println!("{}", result);
+
+            let original = std::fs::read_to_string(&file)?;
+            let trust = TrustScore::evaluate(&ChangeEvidence {
+                static_issues: static_check(&original, &result),
+                ..Default::default()
+            });
+            print_trust(&trust, Config::load()?.trust_threshold);
         }
-        RefactorSub::Apply => {
+        RefactorSub::Apply {
+            force,
+            test_cmd,
+            review,
+        } => {
+            let operations = engine.get_pending_operations().clone();
+            if operations.is_empty() {
+                println!("No pending refactor operations. Run `kandil refactor preview` first.");
+                return Ok(());
+            }
+            let config = Config::load()?;
+
+            let mut evidence = ChangeEvidence::default();
+            for op in &operations {
+                for issue in static_check(op.original_code(), &op.refactored_code) {
+                    evidence.static_issues.push(format!("{}: {}", op.file_path, issue));
+                }
+            }
+
+            if review {
+                let factory = AIProviderFactory::new(config.clone());
+                let ai = Arc::new(factory.create_ai(&config.ai_provider, &config.ai_model)?);
+                let reviewer = crate::core::agents::ReviewAgent::new(ai);
+                let mut scores = Vec::new();
+                for op in &operations {
+                    let extension = std::path::Path::new(&op.file_path)
+                        .extension()
+                        .map(|ext| format!(".{}", ext.to_string_lossy()))
+                        .unwrap_or_default();
+                    let temp = std::env::temp_dir()
+                        .join(format!("kandil-review-{}{}", uuid::Uuid::new_v4(), extension));
+                    std::fs::write(&temp, &op.refactored_code)?;
+                    let report = reviewer.code_review(&temp.to_string_lossy()).await;
+                    let _ = std::fs::remove_file(&temp);
+                    scores.push(report?.score as u32);
+                }
+                evidence.review_score = Some((scores.iter().sum::<u32>() / scores.len() as u32) as u8);
+            }
+
+            if let Some(command) = &test_cmd {
+                let changes: Vec<(&str, &str)> = operations
+                    .iter()
+                    .map(|op| (op.file_path.as_str(), op.refactored_code.as_str()))
+                    .collect();
+                evidence.tests = Some(test_in_place(&changes, command)?);
+            }
+
+            let trust = TrustScore::evaluate(&evidence);
+            print_trust(&trust, config.trust_threshold);
+            if !trust.meets(config.trust_threshold)
+                && !force
+                && !confirm("Apply these low-trust changes anyway?")?
+            {
+                anyhow::bail!(
+                    "Trust score {:.2} is below the threshold {:.2}; re-run with --force to apply anyway",
+                    trust.score,
+                    config.trust_threshold
+                );
+            }
+
             engine.apply_pending_operations()?;
+            engine.save_pending()?;
             println!("Applied all pending refactor operations");
         }
         RefactorSub::Cancel => {
             engine.cancel_pending_operations();
+            engine.save_pending()?;
             println!("Cancelled all pending refactor operations");
         }
     }
     Ok(())
 }
 
+/// Writes each `(path, contents)` change, runs `command` against them, then
+/// puts every written file back as it was just before it was written. The
+/// files are restored whether a write or the command fails.
+fn test_in_place(changes: &[(&str, &str)], command: &str) -> Result<(usize, usize)> {
+    let mut written: Vec<(&str, Option<Vec<u8>>)> = Vec::new();
+    let mut result = Ok((0, 0));
+    for (path, contents) in changes {
+        let snapshot = match std::fs::read(path) {
+            Ok(bytes) => Some(bytes),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => {
+                result = Err(anyhow::Error::new(err).context(format!("Cannot read {}", path)));
+                break;
+            }
+        };
+        written.push((path, snapshot));
+        if let Err(err) = std::fs::write(path, contents) {
+            result = Err(anyhow::Error::new(err).context(format!("Cannot write {}", path)));
+            break;
+        }
+    }
+    if result.is_ok() {
+        result = crate::core::trust::run_test_command(command);
+    }
+
+    let mut restore_failures = Vec::new();
+    for (path, snapshot) in written.into_iter().rev() {
+        let restored = match snapshot {
+            Some(bytes) => std::fs::write(path, bytes),
+            None => std::fs::remove_file(path),
+        };
+        if let Err(err) = restored {
+            restore_failures.push(format!("{}: {}", path, err));
+        }
+    }
+    if !restore_failures.is_empty() {
+        anyhow::bail!(
+            "Could not restore files after testing: {}",
+            restore_failures.join("; ")
+        );
+    }
+    result
+}
+
+fn print_trust(trust: &TrustScore, threshold: f64) {
+    let badge = if trust.meets(threshold) { "✅" } else { "⚠️" };
+    println!(
+        "\n{} Trust score: {:.2} (threshold {:.2})",
+        badge, trust.score, threshold
+    );
+    for reason in &trust.rationale {
+        println!("   • {}", reason);
+    }
+}
+
+/// Walks the improvement plans, showing each proposed file change with its
+/// trust score and applying the ones the user approves. Each change gets its
+/// own checkpoint and is rolled back if `test_cmd` reports failures.
+async fn apply_improvement_plans(
+    meta_agent: &crate::core::agents::MetaAgent,
+    plans: &[crate::core::agents::meta::ImprovementPlan],
+    path: &str,
+    force: bool,
+    test_cmd: Option<String>,
+) -> Result<()> {
+    use crate::enhanced_ui::smart_prompt::SmartPrompt;
+
+    let threshold = Config::load()?.trust_threshold;
+    let root = std::path::Path::new(path).canonicalize()?;
+    let session = crate::core::rollback::SessionManager::new(&root.to_string_lossy());
+    let (mut applied, mut skipped) = (0, 0);
+
+    for plan in plans {
+        println!("\n📋 {} ({:?} priority)", plan.title, plan.priority);
+        let changes = meta_agent.propose_changes(plan, &root).await?;
+        if changes.is_empty() {
+            println!("   No concrete file change proposed");
+            continue;
+        }
+
+        for change in changes {
+            let file = change.path.to_string_lossy().to_string();
+            let mut engine = RefactorEngine::new();
+            let operation =
+                engine.stage_rewrite(&file, change.content, "self_improve", &change.summary)?;
+            let original = operation.original_code().to_string();
+            let rewritten = operation.refactored_code.clone();
+            let display = change.path.strip_prefix(&root).unwrap_or(&change.path).display();
+            println!("\n{} — {}", display, change.summary);
+            SmartPrompt::show_diff(&SmartPrompt::diff_preview(&original, &rewritten));
+
+            let checkpoint = session
+                .create_file_checkpoint(&plan.title, &[change.path.as_path()])
+                .await?;
+            let mut evidence = ChangeEvidence {
+                static_issues: static_check(&original, &rewritten),
+                ..Default::default()
+            };
+            if let Some(command) = &test_cmd {
+                // Measure the change in place, then restore the checkpoint
+                std::fs::write(&change.path, &rewritten)?;
+                let result = crate::core::trust::run_test_command(command);
+                session.rollback_to_checkpoint(&checkpoint).await?;
+                evidence.tests = Some(result?);
+            }
+
+            let trust = TrustScore::evaluate(&evidence);
+            print_trust(&trust, threshold);
+            if !trust.meets(threshold) && !force {
+                println!("   Skipped: below the trust threshold (use --force to offer it anyway)");
+                skipped += 1;
+                continue;
+            }
+            if !confirm(&format!("Apply this change to {}?", display))? {
+                skipped += 1;
+                continue;
+            }
+
+            if let Err(err) = engine.apply_pending_operations() {
+                session.rollback_to_checkpoint(&checkpoint).await?;
+                return Err(err.context(format!("Failed to write {}; restored it", display)));
+            }
+            println!("   ✅ Applied (checkpoint {})", checkpoint);
+            applied += 1;
+        }
+    }
+
+    println!("\nSelf-improvement: {} change(s) applied, {} skipped", applied, skipped);
+    Ok(())
+}
+
 async fn handle_test(sub: TestSub) -> Result<()> {
     let config = Config::load()?;
     let factory = AIProviderFactory::new(config.clone());
@@ -1679,6 +4272,27 @@ async fn handle_test(sub: TestSub) -> Result<()> {
 
     match sub {
         TestSub::Generate { file, framework } => {
+            let framework = if language::is_auto(&framework) {
+                let detected = detect_language(&file)?;
+                match language::test_framework(detected.language, &std::env::current_dir()?) {
+                    Some(framework) => {
+                        println!(
+                            "🔎 Detected {} from {}; writing {} tests",
+                            detected.language, detected.reason, framework
+                        );
+                        framework.to_string()
+                    }
+                    None => {
+                        println!(
+                            "🔎 Detected {} from {}; tests follow its own conventions",
+                            detected.language, detected.reason
+                        );
+                        String::new()
+                    }
+                }
+            } else {
+                framework
+            };
             let tests = generator.generate_tests_for_file(&file, &framework).await?;
             println!("Generated tests for '{}':", file);
             println!("{}", tests);
@@ -1688,27 +4302,217 @@ async fn handle_test(sub: TestSub) -> Result<()> {
             println!("Generated integration tests for feature:");
             println!("{}", tests);
         }
-        TestSub::Coverage { source, test } => {
-            let analysis = generator.analyze_test_coverage(&source, &test).await?;
-            println!("Test coverage analysis for '{}' and '{}':", source, test);
-            println!("{}", analysis);
+        TestSub::Coverage {
+            source,
+            test,
+            threshold,
+            format,
+            output,
+        } if format == "lcov" => {
+            let report = write_lcov(&source, &test, output.as_deref()).await?;
+            enforce_threshold(&report, threshold)?;
         }
+        TestSub::Coverage {
+            source,
+            test,
+            threshold,
+            ..
+        } => match CoverageRunner::measure(&source, &test).await? {
+            Some(report) => {
+                println!(
+                    "Measured coverage for '{}' ({}):",
+                    source,
+                    report.tool.as_str()
+                );
+                println!(
+                    "  Lines: {:.1}% ({}/{})",
+                    report.line_percent(),
+                    report.lines_hit,
+                    report.lines_found
+                );
+                if let Some(branches) = report.branch_percent() {
+                    println!(
+                        "  Branches: {:.1}% ({}/{})",
+                        branches, report.branches_hit, report.branches_found
+                    );
+                }
+                if report.uncovered_lines.is_empty() {
+                    println!("  Uncovered lines: none");
+                } else {
+                    println!("  Uncovered lines: {}", report.uncovered_ranges());
+                }
+                enforce_threshold(&report, threshold)?;
+            }
+            None => {
+                println!("⚠️  No coverage tool found for '{}'; falling back to an AI estimate.", source);
+                println!("   Install cargo-llvm-cov, cargo-tarpaulin or coverage.py for measured results.");
+                let analysis = generator.analyze_test_coverage(&source, &test).await?;
+                println!(
+                    "AI-estimated test coverage analysis for '{}' and '{}':",
+                    source, test
+                );
+                println!("{}", analysis);
+
+                if let Some(threshold) = threshold {
+                    anyhow::bail!(
+                        "Cannot enforce --threshold {:.1}%: coverage was estimated, not measured",
+                        threshold
+                    );
+                }
+            }
+        },
     }
     Ok(())
 }
 
-async fn switch_model(provider: String, model: String) -> Result<()> {
-    // In a real implementation, this would update the configuration
-    // For now, we'll just verify the provider is valid
-    match provider.as_str() {
-        "ollama" | "claude" | "qwen" | "openai" => {
-            println!("Switched to provider: {}, model: {}", provider, model);
+/// Measures coverage and writes it as LCOV to `output`, or stdout. An AI
+/// estimate has no line data to export, so this fails without a coverage tool.
+async fn write_lcov(
+    source: &str,
+    test: &str,
+    output: Option<&std::path::Path>,
+) -> Result<crate::utils::coverage::CoverageReport> {
+    let Some(report) = CoverageRunner::measure(source, test).await? else {
+        anyhow::bail!(
+            "No coverage tool found for '{}', so there is no measured coverage to export as \
+             LCOV (an AI estimate has no line data). Install cargo-llvm-cov, cargo-tarpaulin \
+             or coverage.py",
+            source
+        );
+    };
+    let lcov = report.to_lcov();
+    match output {
+        Some(path) => {
+            std::fs::write(path, lcov)?;
+            println!(
+                "Wrote LCOV coverage for '{}' ({:.1}% of lines, {}) to {}",
+                source,
+                report.line_percent(),
+                report.tool.as_str(),
+                path.display()
+            );
+        }
+        None => print!("{}", lcov),
+    }
+    Ok(report)
+}
+
+fn enforce_threshold(
+    report: &crate::utils::coverage::CoverageReport,
+    threshold: Option<f64>,
+) -> Result<()> {
+    if let Some(threshold) = threshold {
+        if report.line_percent() < threshold {
+            anyhow::bail!(
+                "Line coverage {:.1}% is below the {:.1}% threshold",
+                report.line_percent(),
+                threshold
+            );
         }
-        _ => return Err(anyhow::anyhow!("Invalid provider: {}", provider)),
     }
     Ok(())
 }
 
+/// Saves `provider` and `model` as the configured choice, picking them
+/// interactively when they aren't given.
+async fn switch_model(provider: Option<String>, model: Option<String>) -> Result<()> {
+    let mut config = Config::load()?;
+    let (provider, model) = match (provider, model) {
+        (Some(provider), Some(model)) => (provider, model),
+        _ => match pick_model(&config).await? {
+            Some(choice) => choice,
+            None => {
+                println!("No model selected; the configuration is unchanged");
+                return Ok(());
+            }
+        },
+    };
+    use crate::core::adapters::ai::backend;
+    if !backend::is_registered(&provider) {
+        anyhow::bail!(
+            "Invalid provider: {}. Supported: {}",
+            provider,
+            backend::names().join(", ")
+        );
+    }
+
+    config.ai_provider = provider;
+    config.ai_model = model;
+    if let Err(warning) = config.validate_model() {
+        eprintln!("Warning: {}", warning);
+    }
+    let target = match config
+        .source
+        .clone()
+        .or_else(crate::utils::config::config_path_override)
+        .or_else(Config::default_global_path)
+    {
+        Some(path) => path,
+        None => std::env::current_dir()?.join("kandil.toml"),
+    };
+    config.save_to(&target)?;
+    println!(
+        "Switched to provider: {}, model: {} (saved to {})",
+        config.ai_provider,
+        config.ai_model,
+        target.display()
+    );
+    Ok(())
+}
+
+/// Lets the user pick a registry model in a full-screen list. `None` if
+/// they cancel.
+#[cfg(feature = "tui")]
+async fn pick_model(config: &Config) -> Result<Option<(String, String)>> {
+    use crate::tui::model_picker::{self, ModelPicker};
+    use std::io::IsTerminal;
+
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        anyhow::bail!(
+            "Pass a provider and model, e.g. `kandil switch-model ollama qwen2.5-coder-7b-q4`; \
+             the picker needs an interactive terminal"
+        );
+    }
+    let tags = crate::utils::ollama::list_models().await.unwrap_or_default();
+    let models_dir = models_root().await?;
+    let installed: Vec<String> = crate::models::catalog::MODEL_CATALOG
+        .iter()
+        .filter(|spec| {
+            crate::core::auto_config::installed_tag(spec.name, &tags).is_some()
+                || models_dir.join(spec.filename).exists()
+        })
+        .map(|spec| spec.name.to_string())
+        .collect();
+    let entries = model_picker::entries(
+        &crate::models::UniversalModelRegistry::global().list_profiles(),
+        &detect_hardware(),
+        &installed,
+    );
+    if entries.is_empty() {
+        anyhow::bail!("The model registry has no models to pick from");
+    }
+    let current = (config.ai_provider.as_str(), config.ai_model.as_str());
+    let picker = ModelPicker::new(entries, Some(current));
+    let Some(choice) = task::spawn_blocking(move || picker.run()).await?? else {
+        return Ok(None);
+    };
+    // Ollama knows installed catalog models by their own tag
+    let model = match choice.provider.as_str() {
+        "ollama" => crate::core::auto_config::installed_tag(&choice.model, &tags)
+            .unwrap_or(choice.model),
+        _ => choice.model,
+    };
+    Ok(Some((choice.provider, model)))
+}
+
+#[cfg(not(feature = "tui"))]
+async fn pick_model(_config: &Config) -> Result<Option<(String, String)>> {
+    anyhow::bail!(
+        "Pass a provider and model, e.g. `kandil switch-model ollama qwen2.5-coder-7b-q4`; \
+         this build has no TUI for picking one"
+    )
+}
+
 async fn handle_plugin(sub: PluginSub) -> Result<()> {
     let manager = PluginManager::new();
     match sub {
@@ -1716,7 +4520,22 @@ async fn handle_plugin(sub: PluginSub) -> Result<()> {
             manager.install_plugin(&source)?;
             println!("Plugin installed from: {}", source);
         }
-        PluginSub::List => {
+        PluginSub::List { commands: true } => {
+            let commands = manager.commands(&enhanced_ui::splash::builtin_triggers())?;
+            if commands.is_empty() {
+                println!("No plugin commands (plugins live in {})", manager.dir().display());
+            } else {
+                println!("Plugin commands:");
+                for command in commands {
+                    let triggers = match &command.alias {
+                        Some(alias) => format!("{} ({})", command.qualified(), alias),
+                        None => command.qualified(),
+                    };
+                    println!("  {:<32} {}", triggers, command.description);
+                }
+            }
+        }
+        PluginSub::List { commands: false } => {
             let plugins = manager.list_plugins()?;
             if plugins.is_empty() {
                 println!("No plugins installed");
@@ -1728,9 +4547,20 @@ async fn handle_plugin(sub: PluginSub) -> Result<()> {
             }
         }
         PluginSub::Run { name, args } => {
-            // In a real implementation, we'd look up the actual plugin path
-            println!("Running plugin: {} with args: {:?}", name, args);
-            // This would execute the plugin with proper sandboxing
+            let trigger = format!("/{}", name.trim_start_matches('/'));
+            let command = manager
+                .resolve(&trigger, &enhanced_ui::splash::builtin_triggers())?
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "No plugin command {}. See `kandil plugin list --commands`",
+                        trigger
+                    )
+                })?;
+            let input = std::iter::once(trigger)
+                .chain(args.iter().cloned())
+                .collect::<Vec<_>>()
+                .join(" ");
+            println!("{}", manager.invoke(&command, &args, &input).await?);
         }
     }
     Ok(())
@@ -1739,41 +4569,326 @@ async fn handle_plugin(sub: PluginSub) -> Result<()> {
 async fn handle_config(sub: ConfigSub) -> Result<()> {
     match sub {
         ConfigSub::SetKey { provider, key } => {
-            SecureKey::save(&provider, &key)?;
-            println!("API key saved securely for provider: {}", provider);
+            let backend = SecureKey::save_with_backend(&provider, &key)?;
+            match backend {
+                crate::security::CredentialBackend::Keyring => {
+                    println!("API key saved securely for provider: {}", provider)
+                }
+                crate::security::CredentialBackend::EncryptedFile => println!(
+                    "API key saved for provider: {} (OS keyring unavailable; stored in the encrypted credential file)",
+                    provider
+                ),
+            }
         }
         ConfigSub::ListKeys => {
             println!("Currently implemented as a placeholder - key listing will be implemented in future versions");
             // Note: Actual key listing may not be implemented for security reasons
         }
-        ConfigSub::Costs { provider } => {
-            // For now, showing a placeholder - in a real implementation we would access the cost tracker
-            match provider {
-                Some(provider_str) => {
-                    println!(
-                        "Cost tracking for provider: {} is not available in this context",
-                        provider_str
-                    );
-                    println!("Cost tracking is available when using AI features");
-                }
-                None => {
-                    println!("Cost tracking summary is not available in this context");
-                    println!("Cost tracking is available when using AI features");
-                }
+        ConfigSub::Costs {
+            provider,
+            format,
+            output,
+            summary,
+        } => show_costs(provider.as_deref(), &format, output.as_deref(), summary)?,
+        ConfigSub::Show { section } => {
+            let cfg = Config::load()?;
+            match section.as_deref() {
+                Some("resilience") => {
+                    println!("[resilience]");
+                    for setting in cfg.resilience_sources()? {
+                        println!(
+                            "{:<26} {:<8} {}",
+                            setting.key,
+                            setting.value.to_string(),
+                            setting.source
+                        );
+                    }
+                }
+                _ => {
+                    if let Some(source) = &cfg.source {
+                        println!("# Highest-precedence file: {}", source.display());
+                    }
+                    print!("{}", cfg.render(std::path::Path::new("kandil.toml"))?);
+                }
+            }
+        }
+        ConfigSub::ResetBreaker { provider } => {
+            use crate::core::adapters::ai::backend;
+            // Breakers are keyed by the provider's canonical name
+            let Some(registered) = backend::get(&provider) else {
+                anyhow::bail!(
+                    "Unknown provider '{}'. Supported: {}",
+                    provider,
+                    backend::names().join(", ")
+                );
+            };
+            let provider = registered.name();
+            crate::monitoring::circuit_breaker::request_reset(provider)?;
+            log::info!("Circuit breaker for {} reset manually", provider);
+            println!(
+                "🔌 Reset the circuit breaker for {}. Running sessions try it again on their next request; one more failure opens it again.",
+                provider
+            );
+        }
+        ConfigSub::Doctor { fix } => check_secret_file_permissions(fix)?,
+        ConfigSub::Validate => {
+            let cfg = Config::load()?;
+            if let Err(warning) = cfg.validate_model() {
+                eprintln!("Warning: {}", warning);
+            }
+            match cfg.validate_production().await {
+                Ok(()) => println!("Configuration validation: ok"),
+                Err(e) => {
+                    eprintln!("Configuration validation failed: {}", e);
+                    return Err(e);
+                }
+            }
+        }
+        ConfigSub::Auto { apply } => auto_configure(apply).await?,
+        ConfigSub::RotateKey { provider, undo } => rotate_key(&provider, undo).await?,
+        ConfigSub::Prompts { sub } => handle_prompts(sub)?,
+    }
+    Ok(())
+}
+
+fn handle_prompts(sub: PromptsSub) -> Result<()> {
+    use crate::utils::prompt_templates::{render_template, PromptData, PromptTemplates};
+    use anyhow::Context;
+
+    let templates = PromptTemplates::user_default();
+    match sub {
+        PromptsSub::List => {
+            println!("Prompt overrides directory: {}", templates.dir().display());
+            for info in templates.list() {
+                if info.overridden {
+                    println!("  {:<14} custom   {}", info.agent, info.path.display());
+                } else {
+                    println!("  {:<14} built-in", info.agent);
+                }
+            }
+            println!("Template variables: {{{{language}}}}, {{{{context}}}}, {{{{constraints}}}}");
+        }
+        PromptsSub::Edit { agent } => {
+            let path = templates.materialize(&agent)?;
+            let editor = std::env::var("VISUAL")
+                .or_else(|_| std::env::var("EDITOR"))
+                .unwrap_or_else(|_| {
+                    if cfg!(windows) {
+                        "notepad".to_string()
+                    } else {
+                        "vi".to_string()
+                    }
+                });
+            let status = std::process::Command::new(&editor)
+                .arg(&path)
+                .status()
+                .with_context(|| format!("Failed to launch editor '{}'", editor))?;
+            if !status.success() {
+                anyhow::bail!("Editor '{}' exited with {}", editor, status);
+            }
+
+            let sample = PromptData {
+                language: "rust".to_string(),
+                context: "<context>".to_string(),
+                constraints: vec!["<constraint>".to_string()],
+            };
+            let template = std::fs::read_to_string(&path)?;
+            match render_template(&template, &sample) {
+                Ok(_) => println!("✅ Saved prompt for {}: {}", agent, path.display()),
+                Err(err) => println!(
+                    "⚠️  {} does not render ({}); the built-in prompt will be used until it is fixed",
+                    path.display(),
+                    err
+                ),
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn rotate_key(provider: &str, undo: bool) -> Result<()> {
+    use crate::security::CredentialManager;
+    use secrecy::{ExposeSecret, SecretString};
+    use std::io::{IsTerminal, Read};
+
+    if !offline::is_cloud_provider(provider) {
+        anyhow::bail!("{} does not use an API key", provider);
+    }
+    if undo {
+        CredentialManager::undo_rotation(provider)?;
+        println!("✅ Restored the previous API key for {}", provider);
+        return Ok(());
+    }
+
+    let new_key = if std::io::stdin().is_terminal() {
+        rpassword::prompt_password(format!("New API key for {}: ", provider))?
+    } else {
+        let mut buf = String::new();
+        std::io::stdin().read_to_string(&mut buf)?;
+        buf
+    };
+    let new_key = SecretString::new(new_key.trim().to_string());
+    if new_key.expose_secret().is_empty() {
+        anyhow::bail!("Empty API key");
+    }
+
+    let config = Config::load()?;
+    let ai = crate::core::adapters::ai::KandilAI::new(
+        provider.to_string(),
+        config.ai_model.clone(),
+    )?
+    .with_base_url(config.endpoints.get(provider).cloned());
+    println!("Verifying the new key against {}...", ai.base_url());
+    if let Err(err) = ai.verify_key(new_key.expose_secret()).await {
+        anyhow::bail!("❌ Verification failed, the stored key was not changed: {}", err);
+    }
+    println!("✅ Verification succeeded");
+
+    if CredentialManager::rotate(provider, &new_key)? {
+        println!(
+            "API key rotated for {}. Roll back with `kandil config rotate-key {} --undo`.",
+            provider, provider
+        );
+    } else {
+        println!(
+            "API key saved for {} (there was no previous key to back up)",
+            provider
+        );
+    }
+    Ok(())
+}
+
+async fn auto_configure(apply: bool) -> Result<()> {
+    println!("🔍 Inspecting hardware, runtimes and installed models...");
+    let current = Config::load()?;
+    let hardware = detect_hardware();
+
+    let status = windows::WindowsAdapterStatus::gather().await;
+    let runtimes: Vec<(String, String)> = status
+        .reachable()
+        .map(|runtime| (runtime.provider.clone(), runtime.endpoint.clone()))
+        .collect();
+    let installed = if status.is_reachable("ollama") {
+        crate::utils::ollama::list_models().await.unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+
+    let recommendation = crate::core::auto_config::AutoConfig::recommend_cli_config(
+        &current, &hardware, &runtimes, &installed,
+    );
+    for note in &recommendation.notes {
+        println!("  • {}", note);
+    }
+
+    let target = match current.source.clone().or_else(Config::default_global_path) {
+        Some(path) => path,
+        None => std::env::current_dir()?.join("kandil.toml"),
+    };
+    let before = if target.exists() {
+        std::fs::read_to_string(&target)?
+    } else {
+        String::new()
+    };
+    let after = recommendation.config.render_update(&target)?;
+
+    let before_lines: Vec<&str> = before.lines().collect();
+    let after_lines: Vec<&str> = after.lines().collect();
+    let removed: Vec<&&str> = before_lines.iter().filter(|l| !after_lines.contains(l)).collect();
+    let added: Vec<&&str> = after_lines.iter().filter(|l| !before_lines.contains(l)).collect();
+    if removed.is_empty() && added.is_empty() {
+        println!("✅ {} already matches the recommended settings", target.display());
+        return Ok(());
+    }
+
+    println!("\nProposed changes to {}:", target.display());
+    for line in removed {
+        println!("- {}", line);
+    }
+    for line in added {
+        println!("+ {}", line);
+    }
+
+    if !apply && !confirm("\nWrite these settings?")? {
+        println!("No changes written. Re-run with --apply to write without prompting.");
+        return Ok(());
+    }
+
+    recommendation.config.save_to(&target)?;
+    println!("✅ Wrote {}", target.display());
+    Ok(())
+}
+
+/// `message` with the files it mentions as `@path` attached, then the files
+/// matching `globs` ranked by relevance, within what's left of `model`'s
+/// context window.
+fn attach_files(message: &str, globs: &[String], model: &str) -> String {
+    use enhanced_ui::mentions;
+    let mentioned = mentions::mentioned_files(message);
+    if mentioned.is_empty() && globs.is_empty() {
+        return message.to_string();
+    }
+    let budget = enhanced_ui::conversation::Conversation::for_model(model).spare_tokens(message);
+    let (mut attachments, warnings) = mentions::attach(&mentioned, budget, mentions::offer_slice);
+    for warning in warnings {
+        eprintln!("⚠️  {}", warning);
+    }
+    if globs.is_empty() {
+        return mentions::with_attachments(&attachments, message);
+    }
+
+    let mut matched: Vec<PathBuf> = Vec::new();
+    for glob in globs {
+        let files = mentions::glob_files(glob);
+        if files.is_empty() {
+            eprintln!("⚠️  --files {} matched no files", glob);
+        }
+        for file in files {
+            if !matched.contains(&file) && !mentioned.contains(&file) {
+                matched.push(file);
             }
         }
-        ConfigSub::Validate => {
-            let cfg = Config::load()?;
-            match cfg.validate_production().await {
-                Ok(()) => println!("Configuration validation: ok"),
-                Err(e) => {
-                    eprintln!("Configuration validation failed: {}", e);
-                    return Err(e);
-                }
+    }
+    let used: usize = attachments
+        .iter()
+        .map(|attachment| TokenEstimator.estimate_tokens(&attachment.content))
+        .sum();
+    let ranked = mentions::rank_by_relevance(matched, message);
+    // Oversized files are left out rather than asking about each one
+    let (globbed, skipped) =
+        mentions::attach(&ranked, budget.saturating_sub(used), |_, _| false);
+    for reason in &skipped {
+        verbosity::note(reason);
+    }
+    if !ranked.is_empty() {
+        eprintln!(
+            "📎 --files matched {} file(s); included {}{}",
+            ranked.len(),
+            globbed.len(),
+            if skipped.is_empty() {
+                ""
+            } else {
+                " (the rest are over the size cap or the token budget; -v lists them)"
             }
-        }
+        );
     }
-    Ok(())
+    attachments.extend(globbed);
+    mentions::with_attachments(&attachments, message)
+}
+
+/// Asks a yes/no question; answers "no" when stdin is not a terminal.
+fn confirm(question: &str) -> Result<bool> {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    if !std::io::stdin().is_terminal() {
+        return Ok(false);
+    }
+
+    print!("{} [y/N]: ", question);
+    std::io::stdout().flush()?;
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(matches!(line.trim().to_lowercase().as_str(), "y" | "yes"))
 }
 
 async fn handle_local_model(sub: LocalModelSub) -> Result<()> {
@@ -1782,6 +4897,9 @@ async fn handle_local_model(sub: LocalModelSub) -> Result<()> {
             let hardware = detect_hardware();
             let catalog = &crate::models::catalog::MODEL_CATALOG;
 
+            let installed = installed_models(&models_root().await?)?;
+            let now = chrono::Utc::now();
+
             println!("Available Models:");
             for model in catalog.iter() {
                 // Check compatibility if requested
@@ -1789,7 +4907,12 @@ async fn handle_local_model(sub: LocalModelSub) -> Result<()> {
                     continue;
                 }
 
-                println!("  {}", model.name);
+                match installed.iter().find(|local| local.name == model.name) {
+                    Some(local) => {
+                        println!("  {} (installed, {})", model.name, local.describe_use(now))
+                    }
+                    None => println!("  {}", model.name),
+                }
                 println!(
                     "    Size: {}GB, RAM: {}GB, GPU: {:?}GB",
                     model.size_gb, model.ram_required_gb, model.gpu_vram_min
@@ -1859,6 +4982,12 @@ async fn handle_local_model(sub: LocalModelSub) -> Result<()> {
                 println!("Model {} not found at {:?}", model, path);
             }
         }
+        LocalModelSub::Prune {
+            older_than,
+            target_free,
+            dry_run,
+            yes,
+        } => prune_models(older_than, target_free, dry_run, yes).await?,
         LocalModelSub::Verify { model } => {
             let model_spec = crate::models::catalog::MODEL_CATALOG
                 .iter()
@@ -1873,16 +5002,27 @@ async fn handle_local_model(sub: LocalModelSub) -> Result<()> {
             }
         }
         LocalModelSub::Benchmark {
+            sub: Some(BenchmarkSub::History {
+                model,
+                last,
+                sparkline,
+            }),
+            ..
+        } => benchmark_history(model.as_deref(), last, sparkline)?,
+        LocalModelSub::Benchmark {
+            sub: None,
             model,
             format,
             runtime,
             all_runtimes,
+            compare,
         } => {
             let opts = BenchmarkCliOptions {
                 model,
                 format,
                 runtime,
                 all_runtimes,
+                compare,
             };
             benchmark_model(opts).await?;
         }
@@ -1894,18 +5034,94 @@ async fn handle_local_model(sub: LocalModelSub) -> Result<()> {
             // For now, just print a message
             println!("Note: This would normally update the default model in your config file.");
         }
+        LocalModelSub::ListRemote { runtime, format } => {
+            list_remote_models(&runtime.trim().to_lowercase(), &format).await?;
+        }
+        LocalModelSub::Catalog {
+            sub: CatalogSub::Update { url },
+        } => update_catalog(&url).await?,
+        LocalModelSub::Diagnostics { format } => {
+            let report = crate::benchmark::CrossPlatformBenchmark::new()
+                .run_diagnostics()
+                .await;
+            if format == "json" {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print_diagnostic_report(&report);
+            }
+        }
+        LocalModelSub::Registry { format } => {
+            let profiles = crate::models::UniversalModelRegistry::global().list_profiles();
+            if format == "json" {
+                println!("{}", serde_json::to_string_pretty(&profiles)?);
+                return Ok(());
+            }
+
+            let config = Config::load()?;
+            println!("Registered models ({}):", profiles.len());
+            for profile in &profiles {
+                let marker = if profile.name == config.ai_model {
+                    " (configured)"
+                } else {
+                    ""
+                };
+                println!(
+                    "  {}{} [{}, {}]",
+                    profile.name,
+                    marker,
+                    profile.provider,
+                    if profile.is_local { "local" } else { "remote" }
+                );
+                println!(
+                    "    Resources: {:.1}GB, RAM: {}GB, GPU: {}",
+                    profile.resources.size_gb,
+                    profile.resources.min_system_ram_gb,
+                    profile
+                        .resources
+                        .min_vram_gb
+                        .map(|gb| format!("{}GB", gb))
+                        .unwrap_or_else(|| "not required".to_string())
+                );
+                println!(
+                    "    Capabilities: {} token context, {}, tags: {}",
+                    profile.context_window,
+                    if profile.api_key_required {
+                        "API key required"
+                    } else {
+                        "no API key"
+                    },
+                    profile.tags.join(", ")
+                );
+                if let Some(endpoint) = &profile.default_endpoint {
+                    println!("    Endpoint: {}", endpoint);
+                }
+                for issue in profile.dimension_issues() {
+                    println!("    ⚠️  {}", issue);
+                }
+            }
+
+            if let Err(warning) = config.validate_model() {
+                println!("\n⚠️  {}", warning);
+            }
+        }
         LocalModelSub::Status => {
             let hardware = detect_hardware();
-            let catalog = &crate::models::catalog::MODEL_CATALOG;
 
             println!("Hardware Profile:");
             println!(
                 "  RAM: {}GB total, {}GB available",
                 hardware.total_ram_gb, hardware.available_ram_gb
             );
+            println!("  CPU: {}", hardware.describe_cores());
+            let threads = hardware.recommended_threads();
             println!(
-                "  CPU: {} physical cores, {} logical cores",
-                hardware.cpu_physical_cores, hardware.cpu_logical_cores
+                "  Threads for local runtimes: {} ({}; e.g. Ollama's num_thread)",
+                threads,
+                if hardware.cpu_performance_cores.is_some() {
+                    "performance cores only"
+                } else {
+                    "physical cores, up to 8"
+                }
             );
             println!("  Disk free: {}GB", hardware.free_disk_gb);
             if let Some(gpu) = &hardware.gpu {
@@ -1920,14 +5136,12 @@ async fn handle_local_model(sub: LocalModelSub) -> Result<()> {
 
             let models_dir = models_root().await?;
 
-            let mut installed_count = 0usize;
-            for model in catalog.iter() {
-                let path = models_dir.join(&model.filename);
-                if fs::metadata(&path).await.is_ok() {
-                    installed_count += 1;
-                }
+            let installed = installed_models(&models_dir)?;
+            println!("Installed models: {}", installed.len());
+            let now = chrono::Utc::now();
+            for model in &installed {
+                println!("  {}: {}", model.name, model.describe_use(now));
             }
-            println!("Installed models: {}", installed_count);
 
             match hardware.platform {
                 PlatformKind::Windows | PlatformKind::WindowsWsl => {
@@ -1936,13 +5150,14 @@ async fn handle_local_model(sub: LocalModelSub) -> Result<()> {
                         "WSL IP: {}",
                         status.wsl_ip.unwrap_or_else(|| "not detected".to_string())
                     );
-                    println!("  Ollama (WSL2): {}", bool_icon(status.ollama_reachable));
-                    println!("  LM Studio API: {}", bool_icon(status.lmstudio_reachable));
-                    println!("  GPT4All API: {}", bool_icon(status.gpt4all_reachable));
-                    println!(
-                        "  Foundry Local API: {}",
-                        bool_icon(status.foundry_reachable)
-                    );
+                    for runtime in &status.runtimes {
+                        println!(
+                            "  {} ({}): {}",
+                            runtime.name,
+                            runtime.probe_url,
+                            bool_icon(runtime.reachable)
+                        );
+                    }
                     let gpu_report = windows::check_wsl_gpu();
                     println!("  GPU passthrough: {}", gpu_report.message);
                 }
@@ -1977,13 +5192,145 @@ async fn handle_local_model(sub: LocalModelSub) -> Result<()> {
     Ok(())
 }
 
+/// Fetches the catalog at `url` and saves it where the next run will load it.
+/// Nothing is written unless every entry is valid.
+async fn update_catalog(url: &str) -> Result<()> {
+    use crate::models::catalog::{builtin_catalog, external_catalog_path, parse_catalog};
+
+    offline::ensure_online("Updating the model catalog")?;
+    let path = external_catalog_path()
+        .ok_or_else(|| anyhow::anyhow!("No config directory; set KANDIL_CATALOG"))?;
+    let client = crate::core::adapters::ai::http::client_builder()?.build()?;
+    let text = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| anyhow::anyhow!("Cannot fetch {}: {}", url, err))?
+        .text()
+        .await?;
+    let entries = parse_catalog(&text)
+        .map_err(|err| anyhow::anyhow!("{} is not a valid model catalog: {:#}", url, err))?;
+
+    let builtin = builtin_catalog();
+    let replacing = entries
+        .iter()
+        .filter(|entry| builtin.iter().any(|spec| spec.name == entry.name))
+        .count();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let catalog = crate::models::catalog::CatalogFile { models: entries };
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(&catalog)?).await?;
+    fs::rename(&tmp, &path).await?;
+
+    println!(
+        "✅ Saved {} model(s) to {} ({} new, {} replacing built-in entries)",
+        catalog.models.len(),
+        path.display(),
+        catalog.models.len() - replacing,
+        replacing
+    );
+    Ok(())
+}
+
+/// Lists installed models with their size and last use, then removes those
+/// selected by `--older-than` and `--target-free` after confirmation. The
+/// configured model is always kept.
+async fn prune_models(
+    older_than: Option<u64>,
+    target_free_gb: Option<f64>,
+    dry_run: bool,
+    yes: bool,
+) -> Result<()> {
+    use crate::models::usage;
+    const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+    if older_than.is_none() && target_free_gb.is_none() {
+        anyhow::bail!("Choose what to prune with --older-than <days> or --target-free <gb>");
+    }
+    let dir = models_root().await?;
+    let models = installed_models(&dir)?;
+    if models.is_empty() {
+        println!("No local models are installed in {}", dir.display());
+        return Ok(());
+    }
+
+    let now = chrono::Utc::now();
+    println!("MODEL                                     SIZE  USE");
+    for model in &models {
+        println!(
+            "{:<36} {:>7.1}GB  {}",
+            model.name,
+            model.size_bytes as f64 / GB,
+            model.describe_use(now)
+        );
+    }
+
+    let config = Config::load().unwrap_or_default();
+    let current = usage::catalog_name(&config.ai_model);
+    let free = usage::free_space(&dir);
+    let target = target_free_gb.map(|gb| (gb * GB) as u64);
+    let chosen = usage::select(&models, older_than, target, free, &[current.as_str()], now);
+    if chosen.is_empty() {
+        println!("\nNothing to prune.");
+        return Ok(());
+    }
+    let reclaimed: u64 = chosen.iter().map(|model| model.size_bytes).sum();
+    println!(
+        "\n{} {} model(s), reclaiming {:.1}GB:",
+        if dry_run { "Would remove" } else { "Removing" },
+        chosen.len(),
+        reclaimed as f64 / GB
+    );
+    for model in &chosen {
+        println!("  {}", model.name);
+    }
+    if let Some(target) = target {
+        if free + reclaimed < target {
+            eprintln!(
+                "⚠️  Only {:.1}GB will be free, short of the {:.1}GB target",
+                (free + reclaimed) as f64 / GB,
+                target as f64 / GB
+            );
+        }
+    }
+    if dry_run {
+        return Ok(());
+    }
+    if !yes && !confirm("Remove these models?")? {
+        println!("Nothing was removed");
+        return Ok(());
+    }
+    for model in chosen {
+        fs::remove_file(&model.path).await.map_err(|err| {
+            anyhow::anyhow!("Failed to remove {}: {}", model.path.display(), err)
+        })?;
+        println!("✅ Removed {}", model.name);
+    }
+    Ok(())
+}
+
+/// Model files in `dir` with their recorded use; see [`crate::models::usage`].
+fn installed_models(dir: &std::path::Path) -> Result<Vec<crate::models::usage::InstalledModel>> {
+    let usage = crate::models::usage::load().unwrap_or_else(|err| {
+        log::warn!("Cannot read model usage: {:#}", err);
+        Default::default()
+    });
+    crate::models::usage::installed(dir, &usage)
+}
+
 async fn models_root() -> Result<std::path::PathBuf> {
-    let path = dirs::data_dir()
-        .unwrap_or_else(|| std::env::current_dir().unwrap())
-        .join("kandil")
-        .join("models");
-    fs::create_dir_all(&path).await?;
-    Ok(path)
+    // Refuse up front rather than failing partway through a download.
+    let data_dir = crate::utils::data_dir::data_dir();
+    if !data_dir.writable {
+        anyhow::bail!(
+            "{} is not writable; set KANDIL_DATA_DIR to a writable directory",
+            data_dir.path.display()
+        );
+    }
+    crate::utils::data_dir::subdir("models")
 }
 
 fn bool_icon(value: bool) -> &'static str {
@@ -2002,13 +5349,15 @@ async fn download_model(
     model: &crate::models::catalog::ModelSpec,
     path: &std::path::PathBuf,
 ) -> Result<()> {
+    offline::ensure_online("Downloading models")?;
+
     // Construct the Hugging Face download URL
     let url = format!(
         "https://huggingface.co/{}/resolve/main/{}",
         model.huggingface_repo, model.filename
     );
 
-    let client = reqwest::Client::new();
+    let client = crate::core::adapters::ai::http::client_builder()?.build()?;
     let response = client.get(&url).send().await?;
     let total_size = response.content_length().unwrap_or(0);
 
@@ -2031,26 +5380,101 @@ async fn download_model(
     Ok(())
 }
 
+/// Shortens `text` to at most `max` characters for one-line status output.
+fn truncate_for_display(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(max.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
+
 async fn benchmark_model(opts: BenchmarkCliOptions) -> Result<()> {
     let cfg = Config::load()?;
     let model = opts.model.clone().unwrap_or_else(|| cfg.ai_model.clone());
 
     let suite = CrossPlatformBenchmark::new();
 
+    // Bars go to stderr; JSON output hides them so stdout stays machine-readable.
+    let progress = if opts.format.eq_ignore_ascii_case("json") {
+        indicatif::MultiProgress::with_draw_target(indicatif::ProgressDrawTarget::hidden())
+    } else {
+        indicatif::MultiProgress::new()
+    };
+    let style = indicatif::ProgressStyle::default_bar()
+        .template("{spinner:.green} {prefix} [{bar:30.cyan/blue}] {pos}/{len} {msg}")
+        .unwrap();
+    let mut bars: std::collections::HashMap<String, indicatif::ProgressBar> =
+        std::collections::HashMap::new();
+
     let report = suite
-        .run(BenchmarkOptions {
-            model: model.clone(),
-            default_provider: cfg.ai_provider.clone(),
-            runtime: opts.runtime.clone(),
-            include_all_runtimes: opts.all_runtimes,
-            prompts: None,
-        })
+        .run_with_progress(
+            BenchmarkOptions {
+                model: model.clone(),
+                default_provider: cfg.ai_provider.clone(),
+                runtime: opts.runtime.clone(),
+                include_all_runtimes: opts.all_runtimes,
+                prompts: None,
+            },
+            |event| match event {
+                BenchmarkProgress::RuntimeStarted { runtime, prompts } => {
+                    let bar = progress.add(indicatif::ProgressBar::new(prompts as u64));
+                    bar.set_style(style.clone());
+                    bar.set_prefix(runtime.to_string());
+                    bar.enable_steady_tick(std::time::Duration::from_millis(120));
+                    bars.insert(runtime.to_string(), bar);
+                }
+                BenchmarkProgress::PromptStarted { runtime, prompt } => {
+                    if let Some(bar) = bars.get(runtime) {
+                        bar.set_message(format!("{}…", truncate_for_display(prompt, 40)));
+                    }
+                }
+                BenchmarkProgress::PromptFinished {
+                    runtime,
+                    prompt,
+                    latency_ms,
+                } => {
+                    if let Some(bar) = bars.get(runtime) {
+                        bar.inc(1);
+                        bar.set_message(format!(
+                            "{} → {} ms",
+                            truncate_for_display(prompt, 40),
+                            latency_ms
+                        ));
+                    }
+                }
+                BenchmarkProgress::RuntimeFinished { runtime, error } => {
+                    if let Some(bar) = bars.get(runtime) {
+                        match error {
+                            Some(err) => bar.abandon_with_message(format!("failed: {}", err)),
+                            None => bar.finish_with_message("done"),
+                        }
+                    }
+                }
+            },
+        )
         .await?;
 
+    if let Err(err) = history::append(&history::history_path(), &report, history::MAX_HISTORY_BYTES)
+    {
+        log::warn!("Could not record the benchmark in its history: {:#}", err);
+    }
+
     match opts.format.to_lowercase().as_str() {
         "json" => {
             println!("{}", serde_json::to_string_pretty(&report)?);
         }
+        "md" | "markdown" => {
+            println!("## Benchmark: {}\n", report.model);
+            print!("{}", Comparison::new(&report).to_markdown());
+            if !report.warnings.is_empty() {
+                println!();
+                for warning in &report.warnings {
+                    println!("- ⚠️ {}", warning);
+                }
+            }
+        }
         _ => {
             println!("📊 Cross-Platform Benchmark");
             println!("Model: {}", report.model);
@@ -2069,24 +5493,55 @@ async fn benchmark_model(opts: BenchmarkCliOptions) -> Result<()> {
             println!("Prompts: {}", report.prompts.len());
             println!("Timestamp: {}", report.timestamp);
 
-            for runtime in &report.results {
-                println!("\nRuntime: {}", runtime.runtime);
-                println!("  Provider: {}", runtime.provider);
-                println!("  Avg latency: {} ms", runtime.average_latency_ms);
-                println!(
-                    "  Avg throughput: {} tokens/s",
-                    runtime.average_tokens_per_sec
-                );
-                println!("  Memory peak: {} MB", runtime.memory_peak_mb);
-                if let Some(impact) = runtime.battery_impact {
-                    println!("  Battery impact: {:.1}%/min", impact);
-                }
-                println!("  Samples:");
-                for sample in &runtime.samples {
+            if opts.compare {
+                println!();
+                print!("{}", Comparison::new(&report).to_text());
+            } else {
+                for runtime in &report.results {
+                    println!("\nRuntime: {}", runtime.runtime);
+                    println!("  Provider: {}", runtime.provider);
+                    println!("  Avg latency: {} ms", runtime.average_latency_ms);
+                    if let Some(repeat) = runtime.repeat_call_latency_ms {
+                        println!(
+                            "  First call: {} ms, later calls: {} ms avg",
+                            runtime.first_call_latency_ms, repeat
+                        );
+                    }
                     println!(
-                        "    • {} → {} ms, {} tokens",
-                        sample.prompt, sample.latency_ms, sample.output_tokens
+                        "  Avg throughput: {}{} tokens/s end to end (counted with {}{})",
+                        if runtime.tokens_approximate { "~" } else { "" },
+                        runtime.average_tokens_per_sec,
+                        runtime.tokenizer,
+                        if runtime.tokens_approximate { ", approximate" } else { "" }
                     );
+                    match (runtime.ttft_ms, runtime.generation_tokens_per_sec) {
+                        (Some(ttft), Some(speed)) => println!(
+                            "  Streaming: {} ms to first token, then {} tokens/s",
+                            ttft, speed
+                        ),
+                        (Some(ttft), None) => println!("  Streaming: {} ms to first token", ttft),
+                        _ => println!("  Streaming: not available for this runtime"),
+                    }
+                    println!(
+                        "  Memory peak: {} MB ({})",
+                        runtime.memory_peak_mb, runtime.memory_source
+                    );
+                    if let Some(impact) = runtime.battery_impact {
+                        println!("  Battery impact: {:.1}%/min", impact);
+                    }
+                    println!("  Samples:");
+                    for sample in &runtime.samples {
+                        let streaming = match (sample.ttft_ms, sample.generation_tokens_per_sec) {
+                            (Some(ttft), Some(speed)) => {
+                                format!(" (first token {} ms, {:.1} tokens/s)", ttft, speed)
+                            }
+                            _ => String::new(),
+                        };
+                        println!(
+                            "    • {} → {} ms, {} tokens{}",
+                            sample.prompt, sample.latency_ms, sample.output_tokens, streaming
+                        );
+                    }
                 }
             }
 
@@ -2102,6 +5557,128 @@ async fn benchmark_model(opts: BenchmarkCliOptions) -> Result<()> {
     Ok(())
 }
 
+/// Prints each runtime's latency and throughput over the recorded runs,
+/// oldest first.
+fn benchmark_history(model: Option<&str>, last: usize, sparkline: bool) -> Result<()> {
+    let path = history::history_path();
+    let entries = history::load(&path, model, last)?;
+    if entries.is_empty() {
+        println!(
+            "No benchmark history{} yet. Run `kandil local-model benchmark` to start one.",
+            model.map(|model| format!(" for {}", model)).unwrap_or_default()
+        );
+        return Ok(());
+    }
+
+    let mut runtimes: Vec<&str> = Vec::new();
+    for entry in &entries {
+        for result in &entry.results {
+            if !runtimes.contains(&result.runtime.as_str()) {
+                runtimes.push(&result.runtime);
+            }
+        }
+    }
+
+    println!("📈 Benchmark history ({} runs from {})", entries.len(), path.display());
+    for runtime in runtimes {
+        println!("\nRuntime: {}", runtime);
+        let runs: Vec<(&history::HistoryEntry, &history::HistoryResult)> = entries
+            .iter()
+            .flat_map(|entry| {
+                entry
+                    .results
+                    .iter()
+                    .filter(move |result| result.runtime == runtime)
+                    .map(move |result| (entry, result))
+            })
+            .collect();
+        for (entry, result) in &runs {
+            let streaming = match (result.ttft_ms, result.generation_tokens_per_sec) {
+                (Some(ttft), Some(speed)) => {
+                    format!(", first token {} ms, {} tokens/s streaming", ttft, speed)
+                }
+                _ => String::new(),
+            };
+            println!(
+                "  {}  {}  {} ms, {}{} tokens/s{}",
+                entry.timestamp.format("%Y-%m-%d %H:%M"),
+                entry.model,
+                result.average_latency_ms,
+                if result.tokens_approximate { "~" } else { "" },
+                result.average_tokens_per_sec,
+                streaming
+            );
+        }
+        if sparkline && runs.len() > 1 {
+            let latency: Vec<f64> = runs
+                .iter()
+                .map(|(_, result)| result.average_latency_ms as f64)
+                .collect();
+            let throughput: Vec<f64> = runs
+                .iter()
+                .map(|(_, result)| result.average_tokens_per_sec as f64)
+                .collect();
+            println!(
+                "  Latency:    {}  {} → {} ms",
+                history::sparkline(&latency),
+                latency[0],
+                latency[latency.len() - 1]
+            );
+            println!(
+                "  Throughput: {}  {} → {} tokens/s",
+                history::sparkline(&throughput),
+                throughput[0],
+                throughput[throughput.len() - 1]
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The language of `path`, from the file itself or the project in the
+/// current directory.
+fn detect_language(path: &str) -> Result<language::Detection> {
+    let project = enhanced_ui::context::ProjectContext::detect().project_type;
+    language::detect(std::path::Path::new(path), &project)
+}
+
+/// The design for `agent code`: `text` if given, else stdin for `-`, else the
+/// file at `path`. An unreadable file or an empty design is an error.
+fn read_design(path: Option<&str>, text: Option<String>) -> Result<String> {
+    let design = match (text, path) {
+        (Some(text), _) => text,
+        (None, Some("-")) => {
+            use std::io::Read;
+            let mut buf = String::new();
+            std::io::stdin().read_to_string(&mut buf)?;
+            buf
+        }
+        (None, Some(path)) => std::fs::read_to_string(path)
+            .map_err(|err| anyhow::anyhow!("Cannot read design document {}: {}", path, err))?,
+        (None, None) => anyhow::bail!("Pass a design document path, `-` or --design-text"),
+    };
+    if design.trim().is_empty() {
+        anyhow::bail!("The design is empty");
+    }
+    Ok(design)
+}
+
+/// `language`, or the one `detect` finds when it is `auto` or empty.
+fn resolve_language(
+    language: &str,
+    detect: impl FnOnce() -> Result<language::Detection>,
+) -> Result<String> {
+    if !language::is_auto(language) {
+        return Ok(language.to_string());
+    }
+    let detected = detect()?;
+    println!(
+        "🔎 Detected {} from {}; pass a language to override",
+        detected.language, detected.reason
+    );
+    Ok(detected.language.to_string())
+}
+
 async fn handle_auth(sub: AuthSub) -> Result<()> {
     match sub {
         AuthSub::Login { provider } => {
@@ -2121,9 +5698,123 @@ async fn handle_auth(sub: AuthSub) -> Result<()> {
     Ok(())
 }
 
-async fn handle_pwa(output: PathBuf) -> Result<()> {
-    pwa::write_assets(&output)?;
-    println!("📦 PWA assets exported to {}", output.display());
+/// Counts tokens with the tokenizer of the configured (or chosen) model and
+/// estimates the input cost; see [`crate::utils::tokens`].
+async fn count_tokens(
+    text: Option<String>,
+    file: Option<PathBuf>,
+    choice: &ModelOverride,
+) -> Result<()> {
+    use std::io::{IsTerminal, Read};
+
+    let text = match (text, file) {
+        (Some(text), _) => text,
+        (None, Some(file)) => fs::read_to_string(&file)
+            .await
+            .map_err(|err| anyhow::anyhow!("Cannot read {}: {}", file.display(), err))?,
+        (None, None) if !std::io::stdin().is_terminal() => {
+            let mut buf = String::new();
+            std::io::stdin().read_to_string(&mut buf)?;
+            buf
+        }
+        (None, None) => anyhow::bail!("Pass the text to count, --file, or pipe it in"),
+    };
+    let mut config = Config::load()?;
+    choice.apply(&mut config)?;
+    let (provider, model) = (&config.ai_provider, &config.ai_model);
+    let ollama_endpoint = config
+        .endpoints
+        .get("ollama")
+        .map(|url| url.trim_end_matches('/').to_string())
+        .or_else(|| crate::core::adapters::ai::backend::get("ollama").map(|b| b.default_endpoint()))
+        .unwrap_or_default();
+
+    let count = crate::utils::tokens::count(provider, model, &ollama_endpoint, &text).await;
+    println!(
+        "{} tokens ({}{})",
+        count.tokens,
+        count.tokenizer,
+        if count.exact { "" } else { ", approximate" }
+    );
+    if !offline::is_cloud_provider(provider) {
+        println!("Cost: none, {} runs locally", provider);
+        return Ok(());
+    }
+    let cost = crate::utils::cost_tracking::CostTracker::new().estimate_cost(
+        provider,
+        model,
+        count.tokens as u32,
+        0,
+    );
+    println!(
+        "Estimated input cost on {}:{}: ${:.6} (the answer is billed separately)",
+        provider, model, cost
+    );
+    Ok(())
+}
+
+async fn handle_strategy(sub: StrategySub) -> Result<()> {
+    match sub {
+        StrategySub::Suggest { task, format } => {
+            let config = Config::load()?;
+            let strategy_config = crate::config::layered::Config::load()?;
+            let plan = crate::core::strategy::suggest_plan(
+                &task,
+                &strategy_config,
+                &config.ai_provider,
+                &config.ai_model,
+            );
+
+            match format.to_lowercase().as_str() {
+                "json" => println!("{}", serde_json::to_string_pretty(&plan)?),
+                _ => {
+                    println!("🧭 Strategy for: {}", plan.task);
+                    println!("  Complexity: {}", plan.complexity);
+                    println!("  Mode: {:?}", plan.mode);
+                    println!("  Model: {} ({})", plan.model, plan.provider);
+                    if let Some(fallback) = &plan.fallback {
+                        println!("  Cloud fallback: {}", fallback);
+                    }
+                    let usage = crate::models::usage::load().unwrap_or_default();
+                    let relied_on: Vec<String> = crate::models::usage::most_used(&usage, 3)
+                        .into_iter()
+                        .map(|(name, usage)| format!("{} ({} chats)", name, usage.uses))
+                        .collect();
+                    if !relied_on.is_empty() {
+                        println!("  Local models you use most: {}", relied_on.join(", "));
+                    }
+                    println!(
+                        "  Estimated: {} model call(s), ~${:.4}",
+                        plan.estimated_calls, plan.estimated_cost_usd
+                    );
+                    println!("\nSteps:");
+                    for (i, step) in plan.steps.iter().enumerate() {
+                        println!("  {}. [{}] {}", i + 1, step.agent, step.purpose);
+                        println!("     $ {}", step.command);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle_pwa(
+    output: PathBuf,
+    options: pwa::PwaOptions,
+    sub: Option<PwaSub>,
+) -> Result<()> {
+    match sub {
+        None => {
+            pwa::write_assets(&output, &options)?;
+            println!("📦 PWA assets exported to {}", output.display());
+        }
+        Some(PwaSub::Serve {
+            output,
+            address,
+            api,
+        }) => pwa::serve(output, &address, &api, &options).await?,
+    }
     Ok(())
 }
 
@@ -2135,38 +5826,14 @@ async fn handle_windows(sub: WindowsSub) -> Result<()> {
                 "WSL IP: {}",
                 status.wsl_ip.unwrap_or_else(|| "not detected".to_string())
             );
-            println!(
-                "Ollama (WSL2) reachable: {}",
-                if status.ollama_reachable {
-                    "✅"
-                } else {
-                    "⚠️"
-                }
-            );
-            println!(
-                "LM Studio API reachable: {}",
-                if status.lmstudio_reachable {
-                    "✅"
-                } else {
-                    "⚠️"
-                }
-            );
-            println!(
-                "GPT4All API reachable: {}",
-                if status.gpt4all_reachable {
-                    "✅"
-                } else {
-                    "⚠️"
-                }
-            );
-            println!(
-                "Foundry Local reachable: {}",
-                if status.foundry_reachable {
-                    "✅"
-                } else {
-                    "⚠️"
-                }
-            );
+            for runtime in &status.runtimes {
+                println!(
+                    "{} reachable at {}: {}",
+                    runtime.name,
+                    runtime.probe_url,
+                    if runtime.reachable { "✅" } else { "⚠️" }
+                );
+            }
         }
         WindowsSub::CheckGpu => {
             let report = windows::check_wsl_gpu();
@@ -2288,6 +5955,48 @@ async fn handle_linux(sub: LinuxSub) -> Result<()> {
     Ok(())
 }
 
+async fn list_remote_models(runtime: &str, format: &str) -> Result<()> {
+    use crate::errors::AiError;
+
+    let config = Config::load()?;
+    let (provider, display_name) =
+        crate::benchmark::runtime_provider(runtime, &config.ai_provider).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unknown runtime '{}'. Use default, ollama, lmstudio, gpt4all, foundry, openai, claude or qwen",
+                runtime
+            )
+        })?;
+    let ai = AIProviderFactory::new(config.clone()).create_ai(&provider, &config.ai_model)?;
+
+    let models = match ai.list_models().await {
+        Ok(models) => models,
+        Err(err @ (AiError::Network { .. } | AiError::Timeout { .. })) => {
+            return Err(anyhow::anyhow!(
+                "{} is not reachable at {} ({}). Is it running?",
+                display_name,
+                ai.base_url(),
+                err
+            ))
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&models)?);
+        return Ok(());
+    }
+    if models.is_empty() {
+        println!("{} at {} reports no models", display_name, ai.base_url());
+        return Ok(());
+    }
+    println!("Models served by {} at {}:", display_name, ai.base_url());
+    for model in &models {
+        println!("  {}", model);
+    }
+    println!("\nSwitch with: kandil switch-model {} <model>", ai.provider_name());
+    Ok(())
+}
+
 async fn handle_doctor(verbose: bool, format: &str) -> Result<()> {
     use crate::benchmark::CrossPlatformBenchmark;
 
@@ -2301,39 +6010,7 @@ async fn handle_doctor(verbose: bool, format: &str) -> Result<()> {
             println!("{}", serde_json::to_string_pretty(&report)?);
         }
         "table" | "text" => {
-            println!("\n🏥 Kandil Code System Diagnostic Report");
-            println!("=====================================");
-            println!("Timestamp: {}", report.timestamp);
-
-            println!("\n🖥️  Hardware Profile:");
-            println!("  Platform: {:?}", report.hardware.platform);
-            println!("  CPU: {} physical cores, {} logical cores",
-                     report.hardware.cpu_physical_cores, report.hardware.cpu_logical_cores);
-            println!("  RAM: {}GB total, {}GB available",
-                     report.hardware.total_ram_gb, report.hardware.available_ram_gb);
-            println!("  Disk: {}GB free", report.hardware.free_disk_gb);
-
-            if let Some(gpu) = &report.hardware.gpu {
-                println!("  GPU: {} {} with {}GB memory",
-                         gpu.brand, gpu.model, gpu.memory_gb);
-            } else {
-                println!("  GPU: None detected");
-            }
-
-            println!("\n🔌 Connectivity Status:");
-            for endpoint in &report.connectivity.endpoints {
-                let status = if endpoint.reachable { "✅" } else { "❌" };
-                println!("  {} {}: {} ({})", status, endpoint.name, endpoint.endpoint, endpoint.reachable);
-            }
-
-            println!("\n⚡ Performance Metrics:");
-            println!("  CPU Test Duration: {}ms", report.performance.cpu_test_duration_ms);
-            println!("  Memory Baseline: {}MB", report.performance.memory_baseline_mb);
-            println!("  Memory Peak: {}MB", report.performance.memory_peak_mb);
-
-            println!("\n🔒 Security Status:");
-            println!("  API Keys Secure: {}", if report.security.api_key_secure { "✅" } else { "⚠️ " });
-            println!("  Network Secure: {}", if report.security.network_secure { "✅" } else { "⚠️ " });
+            print_diagnostic_report(&report);
 
             if verbose {
                 println!("\n📋 Detailed Report:");
@@ -2351,11 +6028,83 @@ async fn handle_doctor(verbose: bool, format: &str) -> Result<()> {
     Ok(())
 }
 
+/// Prints a diagnostics report: hardware, connectivity, performance, security.
+fn print_diagnostic_report(report: &crate::benchmark::DiagnosticReport) {
+    println!("\n🏥 Kandil Code System Diagnostic Report");
+    println!("=====================================");
+    println!("Timestamp: {}", report.timestamp);
+
+    println!("\n🖥️  Hardware Profile:");
+    println!("  Platform: {:?}", report.hardware.platform);
+    println!("  CPU: {}", report.hardware.describe_cores());
+    println!("  RAM: {}GB total, {}GB available",
+             report.hardware.total_ram_gb, report.hardware.available_ram_gb);
+    println!("  Disk: {}GB free", report.hardware.free_disk_gb);
+
+    if let Some(gpu) = &report.hardware.gpu {
+        println!("  GPU: {} {} with {}GB memory",
+                 gpu.brand, gpu.model, gpu.memory_gb);
+    } else {
+        println!("  GPU: None detected");
+    }
+
+    println!("\n🔌 Connectivity Status:");
+    for endpoint in &report.connectivity.endpoints {
+        let status = if endpoint.reachable { "✅" } else { "❌" };
+        println!("  {} {}: {} ({})", status, endpoint.name, endpoint.endpoint, endpoint.reachable);
+    }
+    println!("  Proxy: {}", report.connectivity.proxy.describe());
+    let tls = &report.connectivity.tls;
+    if !tls.ca_certs.is_empty() {
+        println!("  Extra CA certificates: {}", tls.ca_certs.len());
+    }
+    if let Some(cert) = &tls.client_cert {
+        println!("  Client certificate: {}", cert.display());
+    }
+    if tls.danger_accept_invalid_certs {
+        println!("  ⚠️  TLS certificate verification is disabled");
+    }
+
+    println!("\n⚡ Performance Metrics:");
+    println!(
+        "  CPU Test Duration: {}ms ({})",
+        report.performance.cpu_test_duration_ms, report.performance.cpu_test_note
+    );
+    println!("  Memory Baseline: {}MB", report.performance.memory_baseline_mb);
+    println!("  Memory Peak: {}MB", report.performance.memory_peak_mb);
+
+    println!("\n🔒 Security Status:");
+    println!("  API Keys Secure: {}", if report.security.api_key_secure { "✅" } else { "⚠️ " });
+    for file in &report.security.insecure_files {
+        if let Some(problem) = &file.problem {
+            println!("    ⚠️  {} {}: {}", file.kind, file.path.display(), problem);
+        }
+    }
+    if !report.security.insecure_files.is_empty() {
+        println!("    Run `kandil config doctor --fix` to restrict them");
+    }
+    println!("  Network Secure: {}", if report.security.network_secure { "✅" } else { "⚠️ " });
+
+    let data_dir = &report.data_dir;
+    println!("\n💾 Data Directory:");
+    println!(
+        "  {} {} ({})",
+        bool_icon(data_dir.writable),
+        data_dir.path.display(),
+        data_dir.source.describe()
+    );
+    for reason in &data_dir.skipped {
+        println!("    ⚠️  {}", reason);
+    }
+    if data_dir.source != crate::utils::data_dir::DataDirSource::Env {
+        println!("    Set KANDIL_DATA_DIR to write somewhere else");
+    }
+}
+
 async fn handle_mobile(sub: MobileSub) -> Result<()> {
-    let models_dir = models_root().await?;
     match sub {
         MobileSub::IosSync => {
-            let dir = models_dir.clone();
+            let dir = models_root().await?;
             let export = task::spawn_blocking(move || mobile::export_ios_bundle(&dir)).await??;
             mobile_security::enforce_ios_bundle_security(&export)?;
             println!("📦 iOS bundle created at {}", export.display());
@@ -2364,7 +6113,7 @@ async fn handle_mobile(sub: MobileSub) -> Result<()> {
             );
         }
         MobileSub::AndroidSync => {
-            let dir = models_dir.clone();
+            let dir = models_root().await?;
             let export =
                 task::spawn_blocking(move || mobile::export_android_bundle(&dir)).await??;
             mobile_security::enforce_android_bundle_security(&export)?;
@@ -2372,7 +6121,7 @@ async fn handle_mobile(sub: MobileSub) -> Result<()> {
             println!("Transfer it to /sdcard/kandil/models and use Termux or AI Core to import.");
         }
         MobileSub::EdgeSnapshot => {
-            let dir = models_dir.clone();
+            let dir = models_root().await?;
             let export = task::spawn_blocking(move || edge::export_edge_snapshot(&dir)).await??;
             mobile_security::enforce_edge_bundle_security(&export)?;
             println!("🛠️ Edge manifest written to {}", export.display());
@@ -2380,6 +6129,15 @@ async fn handle_mobile(sub: MobileSub) -> Result<()> {
                 "Use the manifest to drive ONNX exports for Raspberry Pi / Jetson deployments."
             );
         }
+        MobileSub::FlushNotifications => {
+            let report = crate::mobile::MobileBridge::new()?
+                .flush_notifications()
+                .await?;
+            println!(
+                "📬 Delivered {}, still pending {}, abandoned {}",
+                report.delivered, report.pending, report.dropped
+            );
+        }
     }
     Ok(())
 }
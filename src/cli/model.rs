@@ -193,13 +193,7 @@ pub async fn handle_model_command(cmd: ModelCommand) -> Result<()> {
 }
 
 async fn get_model_path(filename: &str) -> Result<PathBuf> {
-    let mut path = dirs::data_dir()
-        .unwrap_or_else(|| std::env::current_dir().unwrap())
-        .join("kandil")
-        .join("models");
-    
-    tokio::fs::create_dir_all(&path).await?;
-    Ok(path.join(filename))
+    Ok(crate::utils::data_dir::subdir("models")?.join(filename))
 }
 
 async fn download_model(model: &ModelSpec, path: &PathBuf) -> Result<()> {
@@ -209,7 +203,7 @@ async fn download_model(model: &ModelSpec, path: &PathBuf) -> Result<()> {
         model.huggingface_repo, model.filename
     );
 
-    let client = reqwest::Client::new();
+    let client = crate::core::adapters::ai::http::client_builder()?.build()?;
     let response = client.get(&url).send().await?;
     let total_size = response.content_length().unwrap_or(0);
 
@@ -111,14 +111,18 @@ impl Config {
         let auto_config = AutoConfig::from_hardware(&hardware);
         config.merge(auto_config);
 
-        // Apply user config if available
-        if let Some(user_config) = Self::load_user_config()? {
-            config.merge(user_config);
-        }
+        // `--config`/`KANDIL_CONFIG` names the only config file to use, and it
+        // holds the main config's sections, so neither file below is read
+        if crate::utils::config::config_path_override().is_none() {
+            // Apply user config if available
+            if let Some(user_config) = Self::load_user_config()? {
+                config.merge(user_config);
+            }
 
-        // Apply local/project config if available
-        if let Some(local_config) = Self::load_local_config()? {
-            config.merge(local_config);
+            // Apply local/project config if available
+            if let Some(local_config) = Self::load_local_config()? {
+                config.merge(local_config);
+            }
         }
 
         // Apply environment variables
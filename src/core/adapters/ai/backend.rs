@@ -0,0 +1,257 @@
+//! Pluggable chat backends
+//!
+//! Each AI provider is a [`ChatBackend`] registered under its config name.
+//! [`KandilAI`] looks the backend up by name and keeps what is common to all
+//! of them: retries, the circuit breaker, the call budget and hybrid mode.
+//! The built-in backends are registered the first time the registry is used;
+//! integrators add their own (Groq, Together, a llama.cpp server, ...) with
+//! [`register`] before creating a `KandilAI`, and the name then works
+//! everywhere a provider name does.
+
+use super::tools::{ToolResponse, ToolSpec, ToolTurn};
+use super::{builtin, KandilAI, StreamedReply};
+use crate::errors::AiError;
+use crate::models::Capabilities;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+#[async_trait::async_trait]
+pub trait ChatBackend: Send + Sync {
+    /// Name used in config and on the command line, e.g. `ollama`.
+    fn name(&self) -> &str;
+
+    /// Other names accepted for this backend.
+    fn aliases(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Name shown to people, e.g. `LM Studio`.
+    fn display_name(&self) -> &str {
+        self.name()
+    }
+
+    /// Base URL when config has no `[endpoints]` entry for the backend.
+    fn default_endpoint(&self) -> String;
+
+    /// Whether the backend is only reachable over the internet. Cloud
+    /// backends need an API key, are skipped in offline mode and are tried
+    /// after the local model in hybrid mode.
+    fn is_cloud(&self) -> bool {
+        false
+    }
+
+    /// What the backend implements for any model; see
+    /// [`Capabilities::for_model`].
+    fn capabilities(&self) -> Capabilities;
+
+    /// Path of the model listing, relative to the endpoint. It also tells
+    /// whether a local runtime is up.
+    fn models_path(&self) -> &str {
+        "/v1/models"
+    }
+
+    /// Process names of a local runtime, for attributing memory in benchmarks.
+    fn process_names(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// The API key to send, if the backend uses one.
+    fn api_key(&self) -> Result<Option<String>, AiError> {
+        Ok(None)
+    }
+
+    /// Request for the model listing, authenticated with `api_key`.
+    fn models_request(&self, ai: &KandilAI, api_key: Option<&str>) -> reqwest::RequestBuilder {
+        let request = ai
+            .client()
+            .get(format!("{}{}", ai.base_url(), self.models_path()));
+        match api_key {
+            Some(key) => request.header("Authorization", format!("Bearer {}", key)),
+            None => request,
+        }
+    }
+
+    /// Sends `message` once, without retries; [`KandilAI::chat`] adds them.
+    async fn chat(&self, ai: &KandilAI, message: &str) -> Result<String, AiError>;
+
+    /// Streams the answer to `on_chunk`. Backends that can't stream deliver
+    /// it as one fragment.
+    async fn chat_stream(
+        &self,
+        ai: &KandilAI,
+        message: &str,
+        _allow_partial: bool,
+        on_chunk: &mut (dyn FnMut(&str) + Send),
+    ) -> Result<StreamedReply, AiError> {
+        let text = self.chat(ai, message).await?;
+        on_chunk(&text);
+        Ok(StreamedReply {
+            tokens_received: text.split_whitespace().count(),
+            tokens_exact: false,
+            text,
+            truncated: false,
+        })
+    }
+
+    /// Sends a tool-calling conversation and returns the model's next step.
+    async fn chat_with_tools(
+        &self,
+        _ai: &KandilAI,
+        _turns: &[ToolTurn],
+        _tools: &[ToolSpec],
+    ) -> Result<ToolResponse, AiError> {
+        Err(AiError::ToolsUnsupported {
+            provider: self.name().to_string(),
+        })
+    }
+
+    /// An embedding of `text` from the backend's embeddings endpoint.
+    async fn embed(&self, _ai: &KandilAI, _text: &str) -> Result<Vec<f32>, AiError> {
+        Err(AiError::Unsupported {
+            provider: self.name().to_string(),
+            feature: "embeddings".to_string(),
+        })
+    }
+}
+
+/// Backends in registration order, and their names and aliases.
+#[derive(Default)]
+struct Registry {
+    backends: Vec<Arc<dyn ChatBackend>>,
+    by_name: HashMap<String, usize>,
+}
+
+impl Registry {
+    fn insert(&mut self, backend: Arc<dyn ChatBackend>) {
+        let mut names = vec![backend.name().to_string()];
+        names.extend(backend.aliases().iter().map(|alias| alias.to_string()));
+        let index = match self.by_name.get(&names[0]) {
+            Some(&index) => {
+                self.backends[index] = backend;
+                index
+            }
+            None => {
+                self.backends.push(backend);
+                self.backends.len() - 1
+            }
+        };
+        for name in names {
+            self.by_name.insert(name, index);
+        }
+    }
+}
+
+fn registry() -> &'static RwLock<Registry> {
+    static REGISTRY: OnceLock<RwLock<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let mut registry = Registry::default();
+        for backend in builtin::backends() {
+            registry.insert(backend);
+        }
+        RwLock::new(registry)
+    })
+}
+
+/// Adds `backend` under its name and aliases, replacing a backend already
+/// registered under the same name.
+pub fn register(backend: Arc<dyn ChatBackend>) {
+    registry()
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(backend);
+}
+
+/// The backend registered as `name`, by its name or an alias.
+pub fn get(name: &str) -> Option<Arc<dyn ChatBackend>> {
+    let registry = registry()
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let index = *registry.by_name.get(name)?;
+    Some(registry.backends[index].clone())
+}
+
+pub fn is_registered(name: &str) -> bool {
+    get(name).is_some()
+}
+
+/// Every backend, in registration order.
+pub fn all() -> Vec<Arc<dyn ChatBackend>> {
+    registry()
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .backends
+        .clone()
+}
+
+/// Every accepted provider name: each backend's name followed by its aliases.
+pub fn names() -> Vec<String> {
+    all()
+        .iter()
+        .flat_map(|backend| {
+            std::iter::once(backend.name().to_string())
+                .chain(backend.aliases().iter().map(|alias| alias.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    #[async_trait::async_trait]
+    impl ChatBackend for Echo {
+        fn name(&self) -> &str {
+            "echo-test"
+        }
+
+        fn aliases(&self) -> &[&str] {
+            &["echo-test-alias"]
+        }
+
+        fn default_endpoint(&self) -> String {
+            "http://localhost:1".to_string()
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities {
+                supports_streaming: false,
+                supports_tools: false,
+                ..Capabilities::default()
+            }
+        }
+
+        async fn chat(&self, ai: &KandilAI, message: &str) -> Result<String, AiError> {
+            Ok(format!("{}: {}", ai.model_name(), message))
+        }
+    }
+
+    #[tokio::test]
+    async fn registered_backends_work_like_built_in_providers() {
+        let builtins = names();
+        assert_eq!(
+            builtins[..8],
+            super::super::SUPPORTED_PROVIDERS
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()[..]
+        );
+        assert_eq!(get("foundry_local").unwrap().name(), "foundry");
+        assert!(get("claude").unwrap().is_cloud());
+        assert!(!is_registered("echo-test"));
+
+        register(Arc::new(Echo));
+        assert!(names().contains(&"echo-test-alias".to_string()));
+        let ai = KandilAI::new("echo-test-alias".to_string(), "m".to_string()).unwrap();
+        assert_eq!(ai.provider_name(), "echo-test");
+        assert_eq!(ai.base_url(), "http://localhost:1");
+        assert_eq!(ai.try_chat("hi").await.unwrap(), "m: hi");
+        let streamed = ai.chat_stream("hi", false, |_| {}).await.unwrap();
+        assert_eq!(streamed.text, "m: hi");
+        assert!(matches!(
+            ai.embed("hi").await,
+            Err(AiError::Unsupported { .. })
+        ));
+    }
+}
@@ -0,0 +1,734 @@
+//! Built-in chat backends
+//!
+//! Ollama, Claude and Qwen each have their own API. OpenAI, LM Studio,
+//! GPT4All and Foundry Local share the OpenAI chat completions API and differ
+//! only in endpoint, key and capabilities, so they are instances of
+//! [`OpenAiCompatible`].
+
+use super::backend::ChatBackend;
+use super::stream::{self, StreamedReply};
+use super::tools::{self, ToolResponse, ToolSpec, ToolTurn};
+use super::{load_key, status_error, transport_error, KandilAI};
+use crate::adapters::windows;
+use crate::errors::AiError;
+use crate::models::Capabilities;
+use crate::utils::config::SecureKey;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// The built-in backends, in the order providers are listed.
+pub(super) fn backends() -> Vec<Arc<dyn ChatBackend>> {
+    vec![
+        Arc::new(Ollama),
+        Arc::new(Claude),
+        Arc::new(Qwen),
+        Arc::new(OpenAiCompatible {
+            name: "openai",
+            aliases: &[],
+            display_name: "OpenAI",
+            endpoint: || "https://api.openai.com".to_string(),
+            key: KeyUse::Required,
+            cloud: true,
+            vision: true,
+            process_names: &[],
+        }),
+        Arc::new(OpenAiCompatible {
+            name: "lmstudio",
+            aliases: &[],
+            display_name: "LM Studio",
+            endpoint: || "http://localhost:1234".to_string(),
+            key: KeyUse::Required,
+            cloud: false,
+            vision: true,
+            process_names: &["lm studio", "lm-studio", "lms"],
+        }),
+        Arc::new(OpenAiCompatible {
+            name: "gpt4all",
+            aliases: &[],
+            display_name: "GPT4All",
+            endpoint: || "http://localhost:4891".to_string(),
+            key: KeyUse::None,
+            cloud: false,
+            vision: false,
+            process_names: &["gpt4all"],
+        }),
+        Arc::new(OpenAiCompatible {
+            name: "foundry",
+            aliases: &["foundry_local"],
+            display_name: "Foundry Local",
+            endpoint: || {
+                std::env::var("FOUNDRY_LOCAL_ENDPOINT")
+                    .unwrap_or_else(|_| "http://localhost:5001".to_string())
+            },
+            key: KeyUse::Optional,
+            cloud: false,
+            vision: false,
+            process_names: &["foundry", "inference.service.agent"],
+        }),
+    ]
+}
+
+/// Sampling options for Ollama's `/api/generate`.
+#[derive(Serialize)]
+struct OllamaOptions {
+    seed: u64,
+}
+
+struct Ollama;
+
+#[async_trait::async_trait]
+impl ChatBackend for Ollama {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    fn display_name(&self) -> &str {
+        "Ollama"
+    }
+
+    fn default_endpoint(&self) -> String {
+        windows::preferred_ollama_endpoint()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_streaming: true,
+            supports_tools: false,
+            supports_vision: true,
+            supports_json_mode: true,
+            max_context: None,
+        }
+    }
+
+    fn models_path(&self) -> &str {
+        "/api/tags"
+    }
+
+    // Recent Ollama versions load models in `ollama runner` children, older
+    // ones in `ollama_llama_server`; both share the prefix.
+    fn process_names(&self) -> &'static [&'static str] {
+        &["ollama"]
+    }
+
+    async fn chat(&self, ai: &KandilAI, message: &str) -> Result<String, AiError> {
+        #[derive(Serialize)]
+        struct OllamaRequest {
+            model: String,
+            prompt: String,
+            stream: bool,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            format: Option<&'static str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            options: Option<OllamaOptions>,
+        }
+
+        #[derive(Deserialize)]
+        struct OllamaResponse {
+            response: String,
+            // In a real implementation, Ollama might provide token counts
+        }
+
+        let request = OllamaRequest {
+            model: ai.model_name().to_string(),
+            prompt: ai.prompt_with_system(message),
+            stream: false,
+            format: ai.response_format().ollama_format(),
+            options: ai.seed().map(|seed| OllamaOptions { seed }),
+        };
+
+        let response = ai
+            .client()
+            .post(format!("{}/api/generate", ai.base_url()))
+            .json(&request)
+            .timeout(ai.request_timeout())
+            .send()
+            .await
+            .map_err(|err| transport_error("ollama", err))?;
+
+        if response.status().is_success() {
+            let result: OllamaResponse = response
+                .json()
+                .await
+                .map_err(|err| transport_error("ollama", err))?;
+            Ok(result.response)
+        } else {
+            Err(status_error("ollama", ai.model_name(), response).await)
+        }
+    }
+
+    async fn chat_stream(
+        &self,
+        ai: &KandilAI,
+        message: &str,
+        allow_partial: bool,
+        on_chunk: &mut (dyn FnMut(&str) + Send),
+    ) -> Result<StreamedReply, AiError> {
+        #[derive(Serialize)]
+        struct OllamaRequest {
+            model: String,
+            prompt: String,
+            stream: bool,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            options: Option<OllamaOptions>,
+        }
+
+        let request = OllamaRequest {
+            model: ai.model_name().to_string(),
+            prompt: ai.prompt_with_system(message),
+            stream: true,
+            options: ai.seed().map(|seed| OllamaOptions { seed }),
+        };
+
+        // A whole-request timeout would cut off long answers, so the reply
+        // headers get `request_timeout` (the model may still be loading) and
+        // the body only has to keep producing chunks.
+        let send = ai
+            .client()
+            .post(format!("{}/api/generate", ai.base_url()))
+            .json(&request)
+            .send();
+        let response = tokio::time::timeout(ai.request_timeout(), send)
+            .await
+            .map_err(|_| AiError::Timeout {
+                provider: "ollama".to_string(),
+            })?
+            .map_err(|err| transport_error("ollama", err))?;
+        if !response.status().is_success() {
+            return Err(status_error("ollama", ai.model_name(), response).await);
+        }
+
+        let mut parser = stream::OllamaStreamParser::default();
+        let mut body = response.bytes_stream();
+        loop {
+            let chunk = match tokio::time::timeout(ai.stream_idle_timeout(), body.next()).await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(_) => {
+                    log::warn!(
+                        "Ollama stream stalled for {:?}; giving up",
+                        ai.stream_idle_timeout()
+                    );
+                    break;
+                }
+            };
+            match chunk {
+                Ok(bytes) => {
+                    for fragment in parser.feed(&bytes)? {
+                        on_chunk(&fragment);
+                    }
+                }
+                Err(err) => {
+                    // A dropped connection; `finish` decides whether the text
+                    // received so far is usable.
+                    log::warn!("Ollama stream interrupted: {}", err);
+                    break;
+                }
+            }
+        }
+        parser.finish(allow_partial)
+    }
+
+    async fn embed(&self, ai: &KandilAI, text: &str) -> Result<Vec<f32>, AiError> {
+        #[derive(Deserialize)]
+        struct EmbeddingResponse {
+            embedding: Vec<f32>,
+        }
+
+        let response = ai
+            .client()
+            .post(format!("{}/api/embeddings", ai.base_url()))
+            .json(&serde_json::json!({ "model": ai.model_name(), "prompt": text }))
+            .timeout(ai.request_timeout())
+            .send()
+            .await
+            .map_err(|err| transport_error("ollama", err))?;
+        if !response.status().is_success() {
+            return Err(status_error("ollama", ai.model_name(), response).await);
+        }
+        let result: EmbeddingResponse = response
+            .json()
+            .await
+            .map_err(|err| transport_error("ollama", err))?;
+        Ok(result.embedding)
+    }
+}
+
+struct Claude;
+
+#[async_trait::async_trait]
+impl ChatBackend for Claude {
+    fn name(&self) -> &str {
+        "claude"
+    }
+
+    fn display_name(&self) -> &str {
+        "Claude"
+    }
+
+    fn default_endpoint(&self) -> String {
+        "https://api.anthropic.com".to_string()
+    }
+
+    fn is_cloud(&self) -> bool {
+        true
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_streaming: false,
+            supports_tools: true,
+            supports_vision: true,
+            supports_json_mode: false,
+            max_context: None,
+        }
+    }
+
+    fn api_key(&self) -> Result<Option<String>, AiError> {
+        load_key("claude").map(Some)
+    }
+
+    fn models_request(&self, ai: &KandilAI, api_key: Option<&str>) -> reqwest::RequestBuilder {
+        ai.client()
+            .get(format!("{}/v1/models", ai.base_url()))
+            .header("X-API-Key", api_key.unwrap_or_default())
+            .header("anthropic-version", "2023-06-01")
+    }
+
+    async fn chat(&self, ai: &KandilAI, message: &str) -> Result<String, AiError> {
+        let api_key = rate_limited_key("claude")?;
+
+        #[derive(Serialize)]
+        struct ClaudeRequest {
+            model: String,
+            prompt: String,
+            max_tokens_to_sample: u32,
+        }
+
+        #[derive(Deserialize)]
+        struct ClaudeResponse {
+            completion: String,
+        }
+
+        let request = ClaudeRequest {
+            model: ai.model_name().to_string(),
+            prompt: format!("Human: {}\n\nAssistant:", ai.prompt_with_system(message)),
+            max_tokens_to_sample: 1000,
+        };
+
+        let response = ai
+            .client()
+            .post(format!("{}/v1/complete", ai.base_url()))
+            .header("Content-Type", "application/json")
+            .header("X-API-Key", api_key)
+            .json(&request)
+            .timeout(ai.request_timeout())
+            .send()
+            .await
+            .map_err(|err| transport_error("claude", err))?;
+
+        if response.status().is_success() {
+            let result: ClaudeResponse = response
+                .json()
+                .await
+                .map_err(|err| transport_error("claude", err))?;
+            Ok(result.completion.trim().to_string())
+        } else {
+            Err(status_error("claude", ai.model_name(), response).await)
+        }
+    }
+
+    /// Tool calls go through the Messages API, which supersedes `/v1/complete`.
+    async fn chat_with_tools(
+        &self,
+        ai: &KandilAI,
+        turns: &[ToolTurn],
+        tools: &[ToolSpec],
+    ) -> Result<ToolResponse, AiError> {
+        let api_key = rate_limited_key("claude")?;
+        let request = tools::anthropic_request(ai.model_name(), ai.system_prompt(), turns, tools);
+
+        let response = ai
+            .client()
+            .post(format!("{}/v1/messages", ai.base_url()))
+            .header("x-api-key", api_key)
+            .header("anthropic-version", "2023-06-01")
+            .json(&request)
+            .timeout(ai.request_timeout())
+            .send()
+            .await
+            .map_err(|err| transport_error("claude", err))?;
+        if !response.status().is_success() {
+            return Err(status_error("claude", ai.model_name(), response).await);
+        }
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|err| transport_error("claude", err))?;
+        tools::parse_anthropic_response("claude", &body)
+    }
+}
+
+struct Qwen;
+
+#[async_trait::async_trait]
+impl ChatBackend for Qwen {
+    fn name(&self) -> &str {
+        "qwen"
+    }
+
+    fn display_name(&self) -> &str {
+        "Qwen"
+    }
+
+    fn default_endpoint(&self) -> String {
+        "https://dashscope.aliyuncs.com".to_string()
+    }
+
+    fn is_cloud(&self) -> bool {
+        true
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_streaming: false,
+            supports_tools: false,
+            supports_vision: false,
+            supports_json_mode: false,
+            max_context: None,
+        }
+    }
+
+    fn models_path(&self) -> &str {
+        "/compatible-mode/v1/models"
+    }
+
+    fn api_key(&self) -> Result<Option<String>, AiError> {
+        load_key("qwen").map(Some)
+    }
+
+    async fn chat(&self, ai: &KandilAI, message: &str) -> Result<String, AiError> {
+        let api_key = rate_limited_key("qwen")?;
+
+        #[derive(Serialize)]
+        struct QwenRequest {
+            model: String,
+            input: QwenInput,
+            parameters: QwenParameters,
+        }
+
+        #[derive(Serialize)]
+        struct QwenInput {
+            prompt: String,
+        }
+
+        #[derive(Serialize)]
+        struct QwenParameters {
+            temperature: f32,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            seed: Option<u64>,
+        }
+
+        #[derive(Deserialize)]
+        struct QwenResponse {
+            output: QwenOutput,
+        }
+
+        #[derive(Deserialize)]
+        struct QwenOutput {
+            text: String,
+        }
+
+        let request = QwenRequest {
+            model: ai.model_name().to_string(),
+            input: QwenInput {
+                prompt: ai.prompt_with_system(message),
+            },
+            parameters: QwenParameters {
+                temperature: 0.7,
+                seed: ai.seed(),
+            },
+        };
+
+        let response = ai
+            .client()
+            .post(format!(
+                "{}/api/v1/services/aigc/text-generation/generation",
+                ai.base_url()
+            ))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", api_key))
+            .json(&request)
+            .timeout(ai.request_timeout())
+            .send()
+            .await
+            .map_err(|err| transport_error("qwen", err))?;
+
+        if response.status().is_success() {
+            let result: QwenResponse = response
+                .json()
+                .await
+                .map_err(|err| transport_error("qwen", err))?;
+            Ok(result.output.text.trim().to_string())
+        } else {
+            Err(status_error("qwen", ai.model_name(), response).await)
+        }
+    }
+}
+
+/// How an OpenAI-compatible backend authenticates.
+#[derive(Debug, Clone, Copy)]
+enum KeyUse {
+    None,
+    /// Sent when one is stored.
+    Optional,
+    Required,
+}
+
+/// A backend speaking the OpenAI chat completions API.
+struct OpenAiCompatible {
+    name: &'static str,
+    aliases: &'static [&'static str],
+    display_name: &'static str,
+    endpoint: fn() -> String,
+    key: KeyUse,
+    cloud: bool,
+    vision: bool,
+    process_names: &'static [&'static str],
+}
+
+impl OpenAiCompatible {
+    /// The `Authorization` header, checking the rate limit of cloud keys.
+    fn auth_header(&self) -> Result<Option<String>, AiError> {
+        let key = if self.cloud {
+            Some(rate_limited_key(self.name)?)
+        } else {
+            self.api_key()?
+        };
+        Ok(key.map(|key| format!("Bearer {}", key)))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct OpenAIMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct OpenAIChatRequest {
+    model: String,
+    messages: Vec<OpenAIMessage>,
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIChoice {
+    message: OpenAIMessage,
+}
+
+#[derive(Deserialize)]
+struct OpenAIUsage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+    total_tokens: u32,
+}
+
+#[derive(Deserialize)]
+struct OpenAIChatResponse {
+    choices: Vec<OpenAIChoice>,
+    usage: Option<OpenAIUsage>,
+}
+
+#[async_trait::async_trait]
+impl ChatBackend for OpenAiCompatible {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn aliases(&self) -> &[&str] {
+        self.aliases
+    }
+
+    fn display_name(&self) -> &str {
+        self.display_name
+    }
+
+    fn default_endpoint(&self) -> String {
+        (self.endpoint)()
+    }
+
+    fn is_cloud(&self) -> bool {
+        self.cloud
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            // Answers arrive as one fragment
+            supports_streaming: false,
+            supports_tools: true,
+            supports_vision: self.vision,
+            supports_json_mode: true,
+            max_context: None,
+        }
+    }
+
+    fn process_names(&self) -> &'static [&'static str] {
+        self.process_names
+    }
+
+    fn api_key(&self) -> Result<Option<String>, AiError> {
+        match self.key {
+            KeyUse::None => Ok(None),
+            KeyUse::Optional => Ok(SecureKey::load(self.name)
+                .ok()
+                .map(|key| key.expose().to_string())),
+            KeyUse::Required => load_key(self.name).map(Some),
+        }
+    }
+
+    async fn chat(&self, ai: &KandilAI, message: &str) -> Result<String, AiError> {
+        let provider = self.name;
+        let mut messages = Vec::new();
+        if let Some(system) = ai.system_prompt() {
+            messages.push(OpenAIMessage {
+                role: "system".to_string(),
+                content: system.to_string(),
+            });
+        }
+        messages.push(OpenAIMessage {
+            role: "user".to_string(),
+            content: message.to_string(),
+        });
+
+        let request = OpenAIChatRequest {
+            model: ai.model_name().to_string(),
+            messages,
+            temperature: 0.7,
+            response_format: ai.response_format().openai_value(),
+            seed: ai.seed(),
+        };
+
+        let mut req = ai
+            .client()
+            .post(format!("{}/v1/chat/completions", ai.base_url()))
+            .header("Content-Type", "application/json")
+            .json(&request);
+
+        if let Some(header) = self.auth_header()? {
+            req = req.header("Authorization", header);
+        }
+
+        let response = req
+            .timeout(ai.request_timeout())
+            .send()
+            .await
+            .map_err(|err| transport_error(provider, err))?;
+
+        if response.status().is_success() {
+            let result: OpenAIChatResponse = response
+                .json()
+                .await
+                .map_err(|err| transport_error(provider, err))?;
+
+            if let Some(usage) = result.usage {
+                let _ = usage.total_tokens; // placeholder for future tracking
+            }
+
+            if let Some(choice) = result.choices.first() {
+                Ok(choice.message.content.trim().to_string())
+            } else {
+                Err(AiError::InvalidResponse {
+                    provider: provider.to_string(),
+                    message: format!("no choices returned from {}", ai.base_url()),
+                })
+            }
+        } else {
+            Err(status_error(provider, ai.model_name(), response).await)
+        }
+    }
+
+    async fn chat_with_tools(
+        &self,
+        ai: &KandilAI,
+        turns: &[ToolTurn],
+        tools: &[ToolSpec],
+    ) -> Result<ToolResponse, AiError> {
+        let provider = self.name;
+        let mut request = tools::openai_request(ai.model_name(), ai.system_prompt(), turns, tools);
+        if let Some(seed) = ai.seed() {
+            request["seed"] = serde_json::json!(seed);
+        }
+        let mut req = ai
+            .client()
+            .post(format!("{}/v1/chat/completions", ai.base_url()))
+            .json(&request);
+        if let Some(header) = self.auth_header()? {
+            req = req.header("Authorization", header);
+        }
+
+        let response = req
+            .timeout(ai.request_timeout())
+            .send()
+            .await
+            .map_err(|err| transport_error(provider, err))?;
+        if !response.status().is_success() {
+            return Err(status_error(provider, ai.model_name(), response).await);
+        }
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|err| transport_error(provider, err))?;
+        tools::parse_openai_response(provider, &body)
+    }
+
+    async fn embed(&self, ai: &KandilAI, text: &str) -> Result<Vec<f32>, AiError> {
+        let provider = self.name;
+        let mut req = ai
+            .client()
+            .post(format!("{}/v1/embeddings", ai.base_url()))
+            .json(&serde_json::json!({ "model": ai.model_name(), "input": text }));
+        if let Some(header) = self.auth_header()? {
+            req = req.header("Authorization", header);
+        }
+        let response = req
+            .timeout(ai.request_timeout())
+            .send()
+            .await
+            .map_err(|err| transport_error(provider, err))?;
+        if !response.status().is_success() {
+            return Err(status_error(provider, ai.model_name(), response).await);
+        }
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|err| transport_error(provider, err))?;
+        body["data"][0]["embedding"]
+            .as_array()
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|value| value.as_f64())
+                    .map(|value| value as f32)
+                    .collect()
+            })
+            .ok_or_else(|| AiError::InvalidResponse {
+                provider: provider.to_string(),
+                message: "no embedding in the response".to_string(),
+            })
+    }
+}
+
+/// The stored key for a cloud `provider`, once its rate limit allows a call.
+fn rate_limited_key(provider: &str) -> Result<String, AiError> {
+    let api_key = load_key(provider)?;
+    crate::utils::rate_limit::check_limit(&api_key).map_err(|_| AiError::RateLimited {
+        provider: provider.to_string(),
+        retry_after: None,
+    })?;
+    Ok(api_key)
+}
@@ -5,35 +5,63 @@
 use super::KandilAI;
 use crate::utils::config::{Config, SecureKey};
 use crate::utils::cost_tracking::CostTracker;
+use crate::utils::offline;
 use anyhow::Result;
 use std::sync::Arc;
 
 pub struct AIProviderFactory {
+    config: Config,
     cost_tracker: Arc<CostTracker>,
 }
 
 impl AIProviderFactory {
-    pub fn new(_config: Config) -> Self {
+    pub fn new(config: Config) -> Self {
         Self {
+            config,
             cost_tracker: Arc::new(CostTracker::new()),
         }
     }
 
     pub fn create_ai(&self, provider: &str, model: &str) -> Result<KandilAI> {
+        let (provider, model) = Self::resolve_offline(provider, model);
         // In a more complete implementation, we would retrieve the API key securely
         // from the OS keyring and pass it to the KandilAI constructor if needed
-        KandilAI::new(provider.to_string(), model.to_string())
+        let endpoint = self.config.endpoints.get(&provider).cloned();
+        Ok(KandilAI::new(provider, model)?
+            .with_base_url(endpoint)
+            .with_system_prompt(self.config.system_prompt.clone()))
     }
 
     pub fn create_ai_with_auth(&self, provider: &str, model: &str) -> Result<KandilAI> {
+        let (provider, model) = Self::resolve_offline(provider, model);
         // This would be used when we need to create an AI instance with authentication
         // For cloud providers, we would load the API key securely here
         if provider != "ollama" {
             // Verify that API key exists in keyring
-            let _api_key = SecureKey::load(provider)?;
+            let _api_key = SecureKey::load(&provider)?;
         }
 
-        KandilAI::new(provider.to_string(), model.to_string())
+        let endpoint = self.config.endpoints.get(&provider).cloned();
+        Ok(KandilAI::new(provider, model)?
+            .with_base_url(endpoint)
+            .with_system_prompt(self.config.system_prompt.clone()))
+    }
+
+    /// In offline mode, swaps cloud providers for the best local runtime.
+    fn resolve_offline(provider: &str, model: &str) -> (String, String) {
+        if !offline::is_enabled() || !offline::is_cloud_provider(provider) {
+            return (provider.to_string(), model.to_string());
+        }
+
+        let (local_provider, local_model) = offline::local_route(provider, model);
+        log::warn!(
+            "Offline mode: using {} ({}) instead of {} ({})",
+            local_provider,
+            local_model,
+            provider,
+            model
+        );
+        (local_provider, local_model)
     }
 
     pub fn get_cost_tracker(&self) -> Arc<CostTracker> {
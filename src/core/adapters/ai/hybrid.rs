@@ -0,0 +1,165 @@
+//! Local model preflight for hybrid mode
+//!
+//! Hybrid mode answers short prompts for cloud providers with a local Ollama
+//! model first. Before trying, it reads the models Ollama has installed
+//! (`/api/tags`) and picks the largest one that fits in the RAM available now.
+//! When Ollama is down, has nothing installed, or only has models too large
+//! for this machine, the local attempt is skipped instead of spending a
+//! request that is bound to fail. The outcome is cached per endpoint for
+//! [`CACHE_TTL`], so agent loops don't probe on every call.
+
+use super::http::shared_client;
+use crate::utils::verbosity;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use sysinfo::{RefreshKind, System, SystemExt};
+
+/// How long a preflight result is reused.
+pub const CACHE_TTL: Duration = Duration::from_secs(60);
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Memory needed beyond a model's file size for its context and runtime.
+const RAM_OVERHEAD: f64 = 1.2;
+
+/// A model listed by Ollama's `/api/tags`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct InstalledModel {
+    pub name: String,
+    /// Size on disk in bytes.
+    #[serde(default)]
+    pub size: u64,
+}
+
+#[derive(Deserialize)]
+struct Tags {
+    #[serde(default)]
+    models: Vec<InstalledModel>,
+}
+
+/// The largest chat model in `models` that fits in `available_bytes` of RAM.
+/// Embedding models can't answer prompts, so they are never picked.
+pub fn pick(models: &[InstalledModel], available_bytes: u64) -> Option<&InstalledModel> {
+    models
+        .iter()
+        .filter(|model| !model.name.to_lowercase().contains("embed"))
+        .filter(|model| model.size as f64 * RAM_OVERHEAD <= available_bytes as f64)
+        .max_by_key(|model| model.size)
+}
+
+/// The local model hybrid mode should try at the Ollama `endpoint`, or
+/// `None` to go straight to the cloud provider.
+pub async fn local_model(endpoint: &str) -> Option<String> {
+    static CACHE: OnceLock<Mutex<HashMap<String, (Instant, Option<String>)>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some((checked_at, model)) = cache.lock().ok()?.get(endpoint) {
+        if checked_at.elapsed() < CACHE_TTL {
+            return model.clone();
+        }
+    }
+
+    let model = probe(endpoint).await;
+    if let Ok(mut cache) = cache.lock() {
+        cache.insert(endpoint.to_string(), (Instant::now(), model.clone()));
+    }
+    model
+}
+
+async fn probe(endpoint: &str) -> Option<String> {
+    let response = shared_client()
+        .get(format!("{}/api/tags", endpoint))
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await;
+    let tags = match response {
+        Ok(response) if response.status().is_success() => response.json::<Tags>().await.ok(),
+        _ => None,
+    };
+    let Some(tags) = tags else {
+        verbosity::note(format!(
+            "Ollama is not reachable at {}; skipping the local model",
+            endpoint
+        ));
+        return None;
+    };
+    if tags.models.is_empty() {
+        verbosity::note("No local models are installed; skipping the local model");
+        return None;
+    }
+
+    let available = available_memory();
+    match pick(&tags.models, available) {
+        Some(model) => {
+            verbosity::note(format!("Hybrid mode will try {} locally first", model.name));
+            Some(model.name.clone())
+        }
+        None => {
+            verbosity::note(format!(
+                "No installed local model fits in {:.1} GB of free RAM; skipping the local model",
+                available as f64 / (1024.0 * 1024.0 * 1024.0)
+            ));
+            None
+        }
+    }
+}
+
+/// RAM available now, in bytes.
+fn available_memory() -> u64 {
+    let mut sys = System::new_with_specifics(RefreshKind::new());
+    sys.refresh_memory();
+    sys.available_memory()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    fn model(name: &str, size: u64) -> InstalledModel {
+        InstalledModel {
+            name: name.to_string(),
+            size,
+        }
+    }
+
+    #[test]
+    fn picks_the_largest_chat_model_that_fits() {
+        let models = vec![
+            model("llama3:70b", 40 * GB),
+            model("qwen2.5-coder:7b", 4 * GB),
+            model("phi3:mini", 2 * GB),
+            model("nomic-embed-text", GB / 4),
+        ];
+        assert_eq!(pick(&models, 16 * GB).unwrap().name, "qwen2.5-coder:7b");
+        assert_eq!(pick(&models, 3 * GB).unwrap().name, "phi3:mini");
+        assert!(pick(&models, GB).is_none(), "only the embedding model fits");
+        assert!(pick(&[], 64 * GB).is_none());
+    }
+
+    #[tokio::test]
+    async fn the_preflight_is_cached_per_endpoint() {
+        use axum::{routing::get, Json, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let probes = Arc::new(AtomicUsize::new(0));
+        let counter = probes.clone();
+        let server = Router::new().route(
+            "/api/tags",
+            get(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async { Json(serde_json::json!({ "models": [] })) }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, server).await });
+
+        assert_eq!(local_model(&endpoint).await, None);
+        assert_eq!(local_model(&endpoint).await, None);
+        assert_eq!(probes.load(Ordering::SeqCst), 1);
+    }
+}
@@ -3,58 +3,29 @@
 //! Contains unified interface for different AI providers (Ollama, Claude, Qwen, OpenAI)
 //! This will be expanded in Phase 1: Core CLI & AI Adapter
 
-use crate::adapters::windows;
 use crate::core::context_manager::ContextManager;
-use crate::utils::config::SecureKey;
-use anyhow::{Context, Result};
+use crate::errors::AiError;
+use crate::models::capabilities::{self, Capabilities};
+use crate::utils::config::{Config, ResilienceConfig, SecureKey};
+use anyhow::Result;
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
 use crate::monitoring::circuit_breaker::CircuitBreaker;
 
+pub mod backend;
+mod builtin;
 pub mod factory;
+pub mod http;
+pub mod hybrid;
+pub mod stream;
+pub mod structured;
+pub mod tools;
 pub mod tracked;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum AIProvider {
-    Ollama,
-    Claude,
-    Qwen,
-    OpenAI,
-    LmStudio,
-    Gpt4All,
-    FoundryLocal,
-}
-
-#[derive(Serialize, Deserialize)]
-struct OpenAIMessage {
-    role: String,
-    content: String,
-}
-
-#[derive(Serialize)]
-struct OpenAIChatRequest {
-    model: String,
-    messages: Vec<OpenAIMessage>,
-    temperature: f32,
-}
-
-#[derive(Deserialize)]
-struct OpenAIChoice {
-    message: OpenAIMessage,
-}
-
-#[derive(Deserialize)]
-struct OpenAIUsage {
-    prompt_tokens: u32,
-    completion_tokens: u32,
-    total_tokens: u32,
-}
-
-#[derive(Deserialize)]
-struct OpenAIChatResponse {
-    choices: Vec<OpenAIChoice>,
-    usage: Option<OpenAIUsage>,
-}
+pub use backend::ChatBackend;
+pub use stream::StreamedReply;
+pub use structured::ResponseFormat;
+pub use tools::{ToolCall, ToolExecutor, ToolResponse, ToolSpec, ToolTurn};
 
 #[async_trait::async_trait]
 pub trait AIProviderTrait: Send + Sync {
@@ -66,113 +37,293 @@ pub trait AIProviderTrait: Send + Sync {
     ) -> Result<String>;
 }
 
-use std::{env, sync::Arc};
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct KandilAI {
-    provider: AIProvider,
+    backend: Arc<dyn ChatBackend>,
     model: String,
     client: Arc<Client>,
     base_url: String,
     /// Flag indicating if we should use hybrid (local fallback) mode
     use_hybrid_mode: bool,
+    /// Ollama endpoint from `[endpoints]`, for hybrid mode
+    local_endpoint: Option<String>,
     breaker: Arc<CircuitBreaker>,
+    /// Instructions sent ahead of every prompt
+    system_prompt: Option<String>,
+    /// Shape requested from providers that can constrain their output
+    response_format: ResponseFormat,
+    /// Sampling seed for providers that accept one (`--seed` / `KANDIL_SEED`)
+    seed: Option<u64>,
+    /// Breaker, retry and hybrid-mode tuning from `[resilience]`
+    resilience: ResilienceConfig,
 }
 
+/// Names of the built-in providers. [`KandilAI::new`] also accepts backends
+/// added with [`backend::register`]; see [`backend::names`].
+pub const SUPPORTED_PROVIDERS: &[&str] = &[
+    "ollama",
+    "claude",
+    "qwen",
+    "openai",
+    "lmstudio",
+    "gpt4all",
+    "foundry",
+    "foundry_local",
+];
+
 impl KandilAI {
     pub fn new(provider: String, model: String) -> Result<Self> {
-        let provider_enum = match provider.as_str() {
-            "ollama" => AIProvider::Ollama,
-            "claude" => AIProvider::Claude,
-            "qwen" => AIProvider::Qwen,
-            "openai" => AIProvider::OpenAI,
-            "lmstudio" => AIProvider::LmStudio,
-            "gpt4all" => AIProvider::Gpt4All,
-            "foundry" | "foundry_local" => AIProvider::FoundryLocal,
-            _ => return Err(anyhow::anyhow!("Unsupported AI provider: {}", provider)),
-        };
+        let backend = backend::get(&provider)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported AI provider: {}", provider))?;
+
+        let offline = crate::utils::offline::is_enabled();
+        if offline && crate::utils::offline::is_cloud_provider(&provider) {
+            return Err(anyhow::anyhow!(
+                "Cloud provider '{}' is unavailable in offline mode; use a local runtime instead",
+                provider
+            ));
+        }
 
-        let base_url = match &provider_enum {
-            AIProvider::Ollama => windows::preferred_ollama_endpoint(),
-            AIProvider::Claude => "https://api.anthropic.com".to_string(),
-            AIProvider::Qwen => "https://dashscope.aliyuncs.com".to_string(),
-            AIProvider::OpenAI => "https://api.openai.com".to_string(),
-            AIProvider::LmStudio => "http://localhost:1234".to_string(),
-            AIProvider::Gpt4All => "http://localhost:4891".to_string(),
-            AIProvider::FoundryLocal => env::var("FOUNDRY_LOCAL_ENDPOINT")
-                .unwrap_or_else(|_| "http://localhost:5001".to_string()),
-        };
+        let base_url = backend.default_endpoint();
 
-        let threshold = std::env::var("KANDIL_CIRCUIT_THRESHOLD")
-            .ok()
-            .and_then(|s| s.parse::<u64>().ok())
-            .unwrap_or(3);
-        let timeout_ms = std::env::var("KANDIL_CIRCUIT_TIMEOUT_MS")
-            .ok()
-            .and_then(|s| s.parse::<u64>().ok())
-            .unwrap_or(2000);
-        let breaker = Arc::new(CircuitBreaker::new(threshold, std::time::Duration::from_millis(timeout_ms)));
+        let config = Config::load().unwrap_or_default();
+        let local_endpoint = config
+            .endpoints
+            .get("ollama")
+            .filter(|url| !url.trim().is_empty())
+            .map(|url| url.trim().trim_end_matches('/').to_string());
+        let resilience = config.resilience;
+        let breaker = Arc::new(CircuitBreaker::new(
+            resilience.circuit_threshold,
+            std::time::Duration::from_millis(resilience.circuit_timeout_ms),
+        ));
 
         Ok(Self {
-            provider: provider_enum,
+            backend,
             model,
-            client: Arc::new(Client::new()),
+            client: http::shared_client(),
             base_url,
-            use_hybrid_mode: true, // Default to hybrid mode
+            use_hybrid_mode: !offline, // Hybrid mode unless offline
+            local_endpoint,
             breaker,
+            system_prompt: None,
+            response_format: ResponseFormat::Text,
+            seed: crate::utils::seed::current(),
+            resilience,
         })
     }
 
-    // Initialize the client after deserialization
-    fn init_client(&mut self) {
-        self.client = Arc::new(Client::new());
+    /// Overrides the provider's default endpoint, e.g. from `[endpoints]` in config.
+    pub fn with_base_url(mut self, base_url: Option<String>) -> Self {
+        if let Some(url) = base_url.filter(|url| !url.trim().is_empty()) {
+            self.base_url = url.trim_end_matches('/').to_string();
+        }
+        self
+    }
+
+    pub fn with_system_prompt(mut self, system_prompt: Option<String>) -> Self {
+        self.system_prompt = system_prompt.filter(|prompt| !prompt.trim().is_empty());
+        self
+    }
+
+    /// Requests JSON (optionally matching a schema) from providers that
+    /// support it; see [`chat_json`](Self::chat_json).
+    pub fn with_response_format(mut self, response_format: ResponseFormat) -> Self {
+        self.response_format = response_format;
+        self
     }
 
-    pub fn provider_name(&self) -> &'static str {
-        match self.provider {
-            AIProvider::Ollama => "ollama",
-            AIProvider::Claude => "claude",
-            AIProvider::Qwen => "qwen",
-            AIProvider::OpenAI => "openai",
-            AIProvider::LmStudio => "lmstudio",
-            AIProvider::Gpt4All => "gpt4all",
-            AIProvider::FoundryLocal => "foundry",
+    /// Prepends the system prompt for providers without a separate system role.
+    pub fn prompt_with_system(&self, message: &str) -> String {
+        match &self.system_prompt {
+            Some(system) => format!("{}\n\n{}", system, message),
+            None => message.to_string(),
         }
     }
 
+    pub fn provider_name(&self) -> &str {
+        self.backend.name()
+    }
+
     pub fn model_name(&self) -> &str {
         &self.model
     }
 
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// The shared HTTP client, for backends.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Limit for a non-streaming provider call, from `[resilience]`. Backends
+    /// set it per request; the shared client only bounds connecting.
+    pub fn request_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.resilience.request_timeout_secs)
+    }
+
+    /// How long a streamed reply may go without a new chunk.
+    pub fn stream_idle_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.resilience.stream_idle_timeout_secs)
+    }
+
+    pub fn system_prompt(&self) -> Option<&str> {
+        self.system_prompt.as_deref()
+    }
+
+    pub fn response_format(&self) -> &ResponseFormat {
+        &self.response_format
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// What this provider and model support; see [`Capabilities::for_model`].
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities::for_model(self.provider_name(), &self.model)
+    }
+
+    /// Warns once per process that `feature` isn't available here.
+    fn warn_unsupported(&self, feature: &str, fallback: &str) {
+        capabilities::warn_once(
+            &format!("{}:{}:{}", feature, self.provider_name(), self.model),
+            format!(
+                "{} ({}) doesn't support {}; {}",
+                self.provider_name(),
+                self.model,
+                feature,
+                fallback
+            ),
+        );
+    }
+
+    /// Checks that `api_key` is accepted by the provider, using the model
+    /// listing endpoint so the probe is authenticated but costs no tokens.
+    pub async fn verify_key(&self, api_key: &str) -> Result<(), AiError> {
+        let provider = self.provider_name();
+        if !self.backend.is_cloud() {
+            return Err(AiError::InvalidResponse {
+                provider: provider.to_string(),
+                message: "local runtimes do not use API keys".to_string(),
+            });
+        }
+
+        let response = self
+            .backend
+            .models_request(self, Some(api_key))
+            .timeout(std::time::Duration::from_secs(15))
+            .send()
+            .await
+            .map_err(|err| transport_error(provider, err))?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(status_error(provider, &self.model, response).await)
+        }
+    }
+
+    /// Ids of the models the endpoint serves, i.e. valid `switch-model` values.
+    ///
+    /// Reads the backend's model listing (`/api/tags` for Ollama, `/v1/models`
+    /// for most others), with the stored key for cloud providers.
+    pub async fn list_models(&self) -> Result<Vec<String>, AiError> {
+        let provider = self.provider_name();
+        let api_key = if self.backend.is_cloud() {
+            Some(load_key(provider)?)
+        } else {
+            self.backend.api_key().ok().flatten()
+        };
+
+        let response = self
+            .backend
+            .models_request(self, api_key.as_deref())
+            .timeout(std::time::Duration::from_secs(15))
+            .send()
+            .await
+            .map_err(|err| transport_error(provider, err))?;
+        if response.status().as_u16() == 404 {
+            return Err(AiError::InvalidResponse {
+                provider: provider.to_string(),
+                message: format!("{} has no model listing endpoint", self.base_url),
+            });
+        }
+        if !response.status().is_success() {
+            return Err(status_error(provider, &self.model, response).await);
+        }
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|err| transport_error(provider, err))?;
+        Ok(model_ids(&body))
+    }
+
     pub async fn chat(&self, message: &str) -> Result<String> {
+        Ok(self.try_chat(message).await?)
+    }
+
+    /// Same as [`chat`](Self::chat), but keeps the typed [`AiError`] so callers
+    /// can react to specific failures such as rate limits or missing keys.
+    pub async fn try_chat(&self, message: &str) -> Result<String, AiError> {
+        let _span = crate::performance::Profiler::start("ai.chat");
         // For short/simple queries, try local model first
-        if self.use_hybrid_mode
-            && message.len() < 5000
-            && matches!(
-                self.provider,
-                AIProvider::Claude | AIProvider::OpenAI | AIProvider::Qwen
-            )
-        {
-            // Try to use local model as fallback
-            if let Ok(local_result) = self.ollama_chat(message).await {
-                // Add a note about the local model being used
+        let local = if self.use_hybrid_mode && message.len() < 5000 && self.backend.is_cloud() {
+            self.hybrid_local().await
+        } else {
+            None
+        };
+        if let Some(local) = local {
+            // Try the local model first, within the latency budget
+            crate::utils::budget::spend("ollama")?;
+            let budget = std::time::Duration::from_millis(self.resilience.hybrid_latency_budget_ms);
+            let local =
+                tokio::time::timeout(budget, local.backend.chat(&local, message)).await;
+            if let Ok(Ok(local_result)) = local {
+                // Structured replies must stay parseable, so only note the
+                // local model on free text
+                if self.response_format.is_structured() {
+                    return Ok(local_result);
+                }
                 return Ok(format!("(Local Model Response) {}", local_result));
             }
+            crate::utils::verbosity::note(match local {
+                Ok(Err(err)) => format!("Local model failed ({}); using {}", err, self.model),
+                _ => format!("Local model exceeded {:?}; using {}", budget, self.model),
+            });
         }
 
-        if self.breaker.is_open() {
-            return Err(anyhow::anyhow!("Circuit breaker open for provider {}", self.provider_name()));
+        if self.breaker.is_open_for(self.provider_name()) {
+            return Err(AiError::CircuitOpen {
+                provider: self.provider_name().to_string(),
+            });
         }
 
-        // Use the configured provider and update breaker
-        let result = match &self.provider {
-            AIProvider::Ollama => self.ollama_chat(message).await,
-            AIProvider::Claude => self.claude_chat(message).await,
-            AIProvider::Qwen => self.qwen_chat(message).await,
-            AIProvider::OpenAI => self.openai_chat(message).await,
-            AIProvider::LmStudio => self.lmstudio_chat(message).await,
-            AIProvider::Gpt4All => self.gpt4all_chat(message).await,
-            AIProvider::FoundryLocal => self.foundry_local_chat(message).await,
+        // Use the configured provider, retrying transient failures, and
+        // update the breaker once per call
+        let mut attempt = 0;
+        let result = loop {
+            crate::utils::budget::spend(self.provider_name())?;
+            let result = self.backend.chat(self, message).await;
+            match &result {
+                Err(err) if err.is_retryable() && attempt < self.resilience.retry_count => {
+                    let delay = self.retry_delay(attempt, err);
+                    crate::utils::verbosity::note(format!(
+                        "{} failed ({}); retry {}/{} in {:.2?}",
+                        self.provider_name(),
+                        err,
+                        attempt + 1,
+                        self.resilience.retry_count,
+                        delay
+                    ));
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                _ => break result,
+            }
         };
 
         match &result {
@@ -183,6 +334,122 @@ impl KandilAI {
         result
     }
 
+    /// The installed Ollama model hybrid mode tries before the cloud
+    /// provider, or `None` when none fits; see [`hybrid::local_model`].
+    async fn hybrid_local(&self) -> Option<KandilAI> {
+        let ollama = backend::get("ollama")?;
+        let endpoint = self
+            .local_endpoint
+            .clone()
+            .unwrap_or_else(|| ollama.default_endpoint());
+        let model = hybrid::local_model(&endpoint).await?;
+        Some(Self {
+            backend: ollama,
+            model,
+            base_url: endpoint,
+            ..self.clone()
+        })
+    }
+
+    /// Exponential backoff from `retry_base_delay_ms` with up to 50% jitter
+    /// (seeded by `--seed`), or the provider's `Retry-After` when it sent one.
+    fn retry_delay(&self, attempt: u32, err: &AiError) -> std::time::Duration {
+        use rand::Rng;
+
+        if let AiError::RateLimited {
+            retry_after: Some(delay),
+            ..
+        } = err
+        {
+            return *delay;
+        }
+        let base = self
+            .resilience
+            .retry_base_delay_ms
+            .saturating_mul(1 << attempt.min(16));
+        let jitter = crate::utils::seed::rng().gen_range(0..=base / 2);
+        std::time::Duration::from_millis(base + jitter)
+    }
+
+    /// Streams the answer, passing each fragment to `on_chunk` as it arrives.
+    ///
+    /// Backends that can't stream (all built-in ones but Ollama) deliver the
+    /// whole answer as a single fragment. If the stream ends before the provider marks it
+    /// complete, the reply is returned with `truncated` set when
+    /// `allow_partial` is true, and as [`AiError::IncompleteStream`] otherwise.
+    /// Failures before the first fragment are retried like [`try_chat`](Self::try_chat).
+    pub async fn chat_stream<F>(
+        &self,
+        message: &str,
+        allow_partial: bool,
+        mut on_chunk: F,
+    ) -> Result<StreamedReply, AiError>
+    where
+        F: FnMut(&str) + Send,
+    {
+        let _span = crate::performance::Profiler::start("ai.chat_stream");
+        if !self.capabilities().supports_streaming {
+            crate::utils::verbosity::note(format!(
+                "{} doesn't stream; the answer arrives in one piece",
+                self.provider_name()
+            ));
+            let text = self.try_chat(message).await?;
+            on_chunk(&text);
+            return Ok(StreamedReply {
+                tokens_received: text.split_whitespace().count(),
+                tokens_exact: false,
+                text,
+                truncated: false,
+            });
+        }
+
+        if self.breaker.is_open_for(self.provider_name()) {
+            return Err(AiError::CircuitOpen {
+                provider: self.provider_name().to_string(),
+            });
+        }
+
+        // Retry like `try_chat`, but only while nothing has been shown yet;
+        // a restarted stream would repeat the text already delivered.
+        let mut attempt = 0;
+        let result = loop {
+            crate::utils::budget::spend(self.provider_name())?;
+            let mut delivered = false;
+            let result = self
+                .backend
+                .chat_stream(self, message, allow_partial, &mut |fragment: &str| {
+                    delivered = true;
+                    on_chunk(fragment);
+                })
+                .await;
+            match &result {
+                Err(err)
+                    if !delivered
+                        && err.is_retryable()
+                        && attempt < self.resilience.retry_count =>
+                {
+                    let delay = self.retry_delay(attempt, err);
+                    crate::utils::verbosity::note(format!(
+                        "{} failed ({}); retry {}/{} in {:.2?}",
+                        self.provider_name(),
+                        err,
+                        attempt + 1,
+                        self.resilience.retry_count,
+                        delay
+                    ));
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                _ => break result,
+            }
+        };
+        match &result {
+            Ok(_) => self.breaker.record_success(),
+            Err(_) => self.breaker.record_failure(),
+        }
+        result
+    }
+
     /// Enhanced chat with context management
     pub async fn chat_with_context(
         &self,
@@ -222,249 +489,221 @@ impl KandilAI {
         self.chat(&enhanced_message).await
     }
 
-    async fn ollama_chat(&self, message: &str) -> Result<String> {
-        #[derive(Serialize)]
-        struct OllamaRequest {
-            model: String,
-            prompt: String,
-            stream: bool,
-        }
-
-        #[derive(Deserialize)]
-        struct OllamaResponse {
-            response: String,
-            // In a real implementation, Ollama might provide token counts
-        }
-
-        let request = OllamaRequest {
-            model: self.model.clone(),
-            prompt: message.to_string(),
-            stream: false,
-        };
-
-        let response = self
-            .client
-            .post(format!("{}/api/generate", self.base_url))
-            .json(&request)
-            .send()
-            .await?;
-
-        if response.status().is_success() {
-            let result: OllamaResponse = response.json().await?;
-            Ok(result.response)
+    /// Asks for a JSON object matching `schema` and deserializes it into `T`.
+    ///
+    /// The schema is sent as `response_format`/`format` where the provider
+    /// supports it and is always spelled out in the prompt. A reply that
+    /// doesn't parse is sent back once with the error for the model to repair.
+    pub async fn chat_json<T: DeserializeOwned>(
+        &self,
+        prompt: &str,
+        schema: &serde_json::Value,
+    ) -> Result<T> {
+        let ai = if self.capabilities().supports_json_mode {
+            self.clone()
+                .with_response_format(ResponseFormat::json_schema("response", schema.clone()))
         } else {
-            Err(anyhow::anyhow!(
-                "Ollama request failed with status: {}",
-                response.status()
-            ))
-        }
-    }
-
-    async fn claude_chat(&self, message: &str) -> Result<String> {
-        let api_key = SecureKey::load("claude")?.expose().to_string();
-        crate::utils::rate_limit::check_limit(&api_key)?;
-
-        #[derive(Serialize)]
-        struct ClaudeRequest {
-            model: String,
-            prompt: String,
-            max_tokens_to_sample: u32,
-        }
-
-        #[derive(Deserialize)]
-        struct ClaudeResponse {
-            completion: String,
-        }
-
-        let request = ClaudeRequest {
-            model: self.model.clone(),
-            prompt: format!("Human: {}\n\nAssistant:", message),
-            max_tokens_to_sample: 1000,
+            self.warn_unsupported("JSON mode", "asking for JSON in the prompt instead");
+            self.clone()
+        };
+        let reply = ai.chat(&structured::instructions(prompt, schema)).await?;
+        let error = match structured::parse_reply(&reply) {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
         };
 
-        let response = self
-            .client
-            .post(&format!("{}/v1/complete", self.base_url))
-            .header("Content-Type", "application/json")
-            .header("X-API-Key", api_key)
-            .json(&request)
-            .send()
+        log::debug!("Structured reply did not parse ({}), asking for a repair", error);
+        let repaired = ai
+            .chat(&structured::repair_prompt(prompt, schema, &reply, &error))
             .await?;
-
-        if response.status().is_success() {
-            let result: ClaudeResponse = response.json().await?;
-            Ok(result.completion.trim().to_string())
-        } else {
-            let status = response.status();
-            let error_text = response.text().await?;
-            Err(anyhow::anyhow!(
-                "Claude request failed: {} - {}",
-                status,
-                error_text
-            ))
-        }
+        structured::parse_reply(&repaired).map_err(|err| {
+            anyhow::anyhow!(
+                "{} returned invalid JSON twice: {}",
+                self.provider_name(),
+                err
+            )
+        })
     }
 
-    async fn qwen_chat(&self, message: &str) -> Result<String> {
-        let api_key = SecureKey::load("qwen")?.expose().to_string();
-        crate::utils::rate_limit::check_limit(&api_key)?;
-
-        #[derive(Serialize)]
-        struct QwenRequest {
-            model: String,
-            input: QwenInput,
-            parameters: QwenParameters,
-        }
-
-        #[derive(Serialize)]
-        struct QwenInput {
-            prompt: String,
-        }
-
-        #[derive(Serialize)]
-        struct QwenParameters {
-            temperature: f32,
-        }
+    /// Sends `message` with `tools` the model may call instead of answering.
+    pub async fn chat_with_tools(
+        &self,
+        message: &str,
+        tools: &[ToolSpec],
+    ) -> Result<ToolResponse> {
+        Ok(self
+            .continue_with_tools(&[ToolTurn::User(message.to_string())], tools)
+            .await?)
+    }
 
-        #[derive(Deserialize)]
-        struct QwenResponse {
-            output: QwenOutput,
+    /// Sends a tool-calling conversation so far and returns the model's next step.
+    pub async fn continue_with_tools(
+        &self,
+        turns: &[ToolTurn],
+        tools: &[ToolSpec],
+    ) -> Result<ToolResponse, AiError> {
+        let _span = crate::performance::Profiler::start("ai.chat_with_tools");
+        if self.breaker.is_open_for(self.provider_name()) {
+            return Err(AiError::CircuitOpen {
+                provider: self.provider_name().to_string(),
+            });
         }
-
-        #[derive(Deserialize)]
-        struct QwenOutput {
-            text: String,
+        if !self.capabilities().supports_tools {
+            return Err(AiError::ToolsUnsupported {
+                provider: self.provider_name().to_string(),
+            });
         }
+        crate::utils::budget::spend(self.provider_name())?;
 
-        let request = QwenRequest {
-            model: self.model.clone(),
-            input: QwenInput {
-                prompt: message.to_string(),
-            },
-            parameters: QwenParameters { temperature: 0.7 },
-        };
+        let result = self.backend.chat_with_tools(self, turns, tools).await;
 
-        let response = self
-            .client
-            .post(&format!(
-                "{}/api/v1/services/aigc/text-generation/generation",
-                self.base_url
-            ))
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", api_key))
-            .json(&request)
-            .send()
-            .await?;
-
-        if response.status().is_success() {
-            let result: QwenResponse = response.json().await?;
-            Ok(result.output.text.trim().to_string())
-        } else {
-            let status = response.status();
-            let error_text = response.text().await?;
-            Err(anyhow::anyhow!(
-                "Qwen request failed: {} - {}",
-                status,
-                error_text
-            ))
+        match &result {
+            Ok(_) => self.breaker.record_success(),
+            Err(_) => self.breaker.record_failure(),
         }
+        result
     }
 
-    async fn openai_chat(&self, message: &str) -> Result<String> {
-        let api_key = SecureKey::load("openai")?.expose().to_string();
-        crate::utils::rate_limit::check_limit(&api_key)?;
-        self.openai_style_chat(
-            message,
-            "/v1/chat/completions",
-            Some(format!("Bearer {}", api_key)),
-        )
-        .await
-    }
-
-    async fn lmstudio_chat(&self, message: &str) -> Result<String> {
-        let api_key = SecureKey::load("lmstudio")
-            .context(
-                "Missing LM Studio API key. Set one via `kandil config set-key lmstudio <key>`.",
-            )?
-            .expose()
-            .to_string();
-        self.openai_style_chat(
-            message,
-            "/v1/chat/completions",
-            Some(format!("Bearer {}", api_key)),
-        )
-        .await
-    }
-
-    async fn gpt4all_chat(&self, message: &str) -> Result<String> {
-        self.openai_style_chat(message, "/v1/chat/completions", None)
-            .await
-    }
-
-    async fn foundry_local_chat(&self, message: &str) -> Result<String> {
-        let auth_header = SecureKey::load("foundry")
-            .ok()
-            .map(|key| format!("Bearer {}", key.expose()));
-        self.openai_style_chat(message, "/v1/chat/completions", auth_header)
-            .await
+    /// An embedding of `text`, for backends with an embeddings endpoint.
+    pub async fn embed(&self, text: &str) -> Result<Vec<f32>, AiError> {
+        if self.breaker.is_open_for(self.provider_name()) {
+            return Err(AiError::CircuitOpen {
+                provider: self.provider_name().to_string(),
+            });
+        }
+        crate::utils::budget::spend(self.provider_name())?;
+        self.backend.embed(self, text).await
     }
 
-    async fn openai_style_chat(
+    /// Runs a tool-calling conversation until the model answers in text.
+    /// `executor` handles every requested call; the model may make at most
+    /// `max_calls` calls. Models without tool calling answer directly, with a
+    /// warning.
+    pub async fn run_tools(
         &self,
         message: &str,
-        relative_path: &str,
-        auth_header: Option<String>,
+        tools: &[ToolSpec],
+        executor: &mut dyn ToolExecutor,
+        max_calls: usize,
     ) -> Result<String> {
-        let request = OpenAIChatRequest {
-            model: self.model.clone(),
-            messages: vec![OpenAIMessage {
-                role: "user".to_string(),
-                content: message.to_string(),
-            }],
-            temperature: 0.7,
-        };
-
-        let mut req = self
-            .client
-            .post(format!("{}{}", self.base_url, relative_path))
-            .header("Content-Type", "application/json")
-            .json(&request);
-
-        if let Some(header) = auth_header {
-            req = req.header("Authorization", header);
+        if !self.capabilities().supports_tools {
+            self.warn_unsupported("tool calling", "answering without tools");
+            return self
+                .chat(&format!(
+                    "{}\n\nNo tools are available. Describe the commands you would run instead.",
+                    message
+                ))
+                .await;
         }
+        let mut turns = vec![ToolTurn::User(message.to_string())];
+        let mut calls = 0;
+        loop {
+            let call = match self.continue_with_tools(&turns, tools).await? {
+                ToolResponse::Text(text) => return Ok(text),
+                ToolResponse::ToolCall(call) => call,
+            };
+            if calls == max_calls {
+                anyhow::bail!(
+                    "The model kept calling tools after {} calls without answering",
+                    max_calls
+                );
+            }
+            calls += 1;
 
-        let response = req.send().await?;
+            let (output, is_error) = if tools.iter().any(|tool| tool.name == call.name) {
+                match executor.execute(&call).await {
+                    Ok(output) => (output, false),
+                    Err(reason) => (reason, true),
+                }
+            } else {
+                (format!("Unknown tool '{}'", call.name), true)
+            };
+            let call_id = call.id.clone();
+            turns.push(ToolTurn::Call(call));
+            turns.push(ToolTurn::Result {
+                call_id,
+                output,
+                is_error,
+            });
+        }
+    }
+}
 
-        if response.status().is_success() {
-            let result: OpenAIChatResponse = response.json().await?;
+/// Model ids from an OpenAI-style `{"data": [{"id"}]}` listing or Ollama's
+/// `{"models": [{"name"}]}`, sorted.
+fn model_ids(body: &serde_json::Value) -> Vec<String> {
+    let entries = body["data"]
+        .as_array()
+        .or_else(|| body["models"].as_array());
+    let mut ids: Vec<String> = entries
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry["id"].as_str().or_else(|| entry["name"].as_str()))
+        .map(str::to_string)
+        .collect();
+    ids.sort();
+    ids.dedup();
+    ids
+}
 
-            if let Some(usage) = result.usage {
-                let _ = usage.total_tokens; // placeholder for future tracking
-            }
+/// The stored API key for `provider`.
+pub fn load_key(provider: &str) -> Result<String, AiError> {
+    SecureKey::load(provider)
+        .map(|key| key.expose().to_string())
+        .map_err(|_| AiError::AuthMissing {
+            provider: provider.to_string(),
+        })
+}
 
-            if let Some(choice) = result.choices.first() {
-                Ok(choice.message.content.trim().to_string())
-            } else {
-                Err(anyhow::anyhow!(
-                    "No choices returned from {}",
-                    self.base_url
-                ))
-            }
-        } else {
-            let status = response.status();
-            let error_text = response.text().await?;
-            Err(anyhow::anyhow!(
-                "Request to {}{} failed: {} - {}",
-                self.base_url,
-                relative_path,
-                status,
-                error_text
-            ))
+pub fn transport_error(provider: &str, err: reqwest::Error) -> AiError {
+    if err.is_timeout() {
+        AiError::Timeout {
+            provider: provider.to_string(),
+        }
+    } else if err.is_decode() {
+        AiError::InvalidResponse {
+            provider: provider.to_string(),
+            message: err.to_string(),
+        }
+    } else {
+        AiError::Network {
+            provider: provider.to_string(),
+            message: err.to_string(),
         }
     }
 }
 
+pub async fn status_error(provider: &str, model: &str, response: reqwest::Response) -> AiError {
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(std::time::Duration::from_secs);
+    let body = response.text().await.unwrap_or_default();
+
+    match status.as_u16() {
+        code @ (401 | 403) => AiError::Auth {
+            provider: provider.to_string(),
+            status: code,
+            body,
+        },
+        404 => AiError::ModelNotFound {
+            model: model.to_string(),
+        },
+        429 => AiError::RateLimited {
+            provider: provider.to_string(),
+            retry_after,
+        },
+        code => AiError::ProviderError {
+            provider: provider.to_string(),
+            status: code,
+            body,
+        },
+    }
+}
+
 impl std::fmt::Debug for KandilAI {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("KandilAI")
@@ -490,3 +729,264 @@ impl AIProviderTrait for KandilAI {
         self.chat_with_context(message, workspace_path).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn model_ids_read_openai_and_ollama_listings() {
+        let openai = json!({
+            "object": "list",
+            "data": [{ "id": "gpt-4o" }, { "id": "gpt-4o-mini" }],
+        });
+        assert_eq!(model_ids(&openai), ["gpt-4o", "gpt-4o-mini"]);
+
+        let ollama = json!({ "models": [{ "name": "qwen2.5-coder:7b" }, { "name": "llama3:8b" }] });
+        assert_eq!(model_ids(&ollama), ["llama3:8b", "qwen2.5-coder:7b"]);
+        assert!(model_ids(&json!({})).is_empty());
+    }
+
+    #[tokio::test]
+    async fn list_models_queries_the_configured_endpoint() {
+        use axum::{routing::get, Json, Router};
+
+        let server = Router::new().route(
+            "/v1/models",
+            get(|| async { Json(json!({ "data": [{ "id": "mistral-7b" }] })) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, server).await });
+
+        let ai = KandilAI::new("gpt4all".to_string(), "local".to_string())
+            .unwrap()
+            .with_base_url(Some(format!("http://{}", address)));
+        assert_eq!(ai.list_models().await.unwrap(), ["mistral-7b"]);
+
+        // Nothing listens on port 9 (discard), so the runtime reads as down.
+        let down = KandilAI::new("lmstudio".to_string(), "local".to_string())
+            .unwrap()
+            .with_base_url(Some("http://127.0.0.1:9".to_string()));
+        assert!(matches!(
+            down.list_models().await,
+            Err(AiError::Network { .. } | AiError::Timeout { .. })
+        ));
+    }
+    #[tokio::test]
+    async fn seed_is_sent_to_openai_style_and_ollama_runtimes() {
+        use axum::{routing::post, Json, Router};
+        use std::sync::{Arc, Mutex};
+
+        let seen: Arc<Mutex<Vec<serde_json::Value>>> = Arc::default();
+        let record = |reply: serde_json::Value| {
+            let seen = seen.clone();
+            post(move |Json(body): Json<serde_json::Value>| async move {
+                seen.lock().unwrap().push(body);
+                Json(reply)
+            })
+        };
+        let completion = json!({
+            "choices": [{ "message": { "role": "assistant", "content": "ok" } }],
+        });
+        let server = Router::new()
+            .route("/v1/chat/completions", record(completion))
+            .route("/api/generate", record(json!({ "response": "ok" })));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, server).await });
+
+        for provider in ["gpt4all", "ollama"] {
+            let mut ai = KandilAI::new(provider.to_string(), "local".to_string())
+                .unwrap()
+                .with_base_url(Some(address.clone()));
+            ai.seed = Some(42);
+            assert_eq!(ai.try_chat("hi").await.unwrap(), "ok");
+            ai.seed = None;
+            ai.try_chat("hi").await.unwrap();
+        }
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0]["seed"], 42);
+        assert!(seen[1].get("seed").is_none());
+        assert_eq!(seen[2]["options"]["seed"], 42);
+        assert!(seen[3].get("options").is_none());
+    }
+
+    #[tokio::test]
+    async fn streams_outlive_the_request_timeout_but_not_a_stall() {
+        use axum::{body::Body, routing::post, Router};
+        use futures_util::stream::{self, StreamExt};
+        use std::convert::Infallible;
+
+        // Five chunks 400ms apart: longer than the request timeout overall,
+        // but never idle for long.
+        let slow = || async {
+            Body::from_stream(stream::unfold(0, |sent| async move {
+                tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+                let line = match sent {
+                    0..=3 => "{\"response\":\"a\",\"done\":false}\n",
+                    4 => "{\"response\":\"\",\"done\":true}\n",
+                    _ => return None,
+                };
+                Some((Ok::<_, Infallible>(line), sent + 1))
+            }))
+        };
+        let stalled = || async {
+            let first = stream::once(async {
+                Ok::<_, Infallible>("{\"response\":\"Hel\",\"done\":false}\n")
+            });
+            Body::from_stream(first.chain(stream::pending()))
+        };
+        let server = Router::new()
+            .route("/slow/api/generate", post(slow))
+            .route("/stalled/api/generate", post(stalled));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, server).await });
+
+        let ai = |path: &str| {
+            let mut ai = KandilAI::new("ollama".to_string(), "local".to_string())
+                .unwrap()
+                .with_base_url(Some(format!("http://{}/{}", address, path)));
+            ai.resilience.request_timeout_secs = 1;
+            ai.resilience.stream_idle_timeout_secs = 1;
+            ai
+        };
+
+        let reply = ai("slow").chat_stream("hi", false, |_| {}).await.unwrap();
+        assert_eq!(reply.text, "aaaa");
+        assert!(!reply.truncated);
+
+        let reply = ai("stalled").chat_stream("hi", true, |_| {}).await.unwrap();
+        assert_eq!(reply.text, "Hel");
+        assert!(reply.truncated);
+        assert!(matches!(
+            ai("stalled").chat_stream("hi", false, |_| {}).await,
+            Err(AiError::IncompleteStream { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn streams_are_retried_until_the_first_fragment() {
+        use axum::{http::StatusCode, response::IntoResponse, routing::post, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let server = Router::new().route(
+            "/api/generate",
+            post(move || {
+                let first = counter.fetch_add(1, Ordering::SeqCst) == 0;
+                async move {
+                    if first {
+                        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+                    }
+                    "{\"response\":\"ok\",\"done\":true}\n".into_response()
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, server).await });
+
+        let mut ai = KandilAI::new("ollama".to_string(), "local".to_string())
+            .unwrap()
+            .with_base_url(Some(address));
+        ai.resilience.retry_base_delay_ms = 1;
+        let mut seen = String::new();
+        let reply = ai
+            .chat_stream("hi", false, |fragment| seen.push_str(fragment))
+            .await
+            .unwrap();
+        assert_eq!(reply.text, "ok");
+        assert_eq!(seen, "ok");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn http_statuses_map_to_typed_errors() {
+        use axum::{extract::Path, http::StatusCode, response::IntoResponse, routing::get, Router};
+
+        let server = Router::new().route(
+            "/:code",
+            get(|Path(code): Path<u16>| async move {
+                let status = StatusCode::from_u16(code).unwrap();
+                (status, [("retry-after", "7")], "upstream says no").into_response()
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, server).await });
+
+        let client = Client::builder().no_proxy().build().unwrap();
+        let error_for = |code: u16| {
+            let request = client.get(format!("{}/{}", address, code)).send();
+            async move { status_error("openai", "gpt-4o", request.await.unwrap()).await }
+        };
+
+        for code in [401, 403] {
+            let err = error_for(code).await;
+            assert!(
+                matches!(&err, AiError::Auth { status, body, .. }
+                    if *status == code && body == "upstream says no"),
+                "{code}: {err:?}"
+            );
+            assert!(err.to_string().contains("Check your API key"));
+            assert!(!err.is_retryable());
+        }
+        assert!(matches!(
+            error_for(404).await,
+            AiError::ModelNotFound { model } if model == "gpt-4o"
+        ));
+        let limited = error_for(429).await;
+        assert!(matches!(
+            &limited,
+            AiError::RateLimited { retry_after: Some(delay), .. } if delay.as_secs() == 7
+        ));
+        assert!(limited.is_retryable());
+        for code in [500, 502, 503] {
+            let err = error_for(code).await;
+            assert!(matches!(&err, AiError::ProviderError { status, .. } if *status == code));
+            assert!(err.is_retryable());
+        }
+    }
+
+    #[tokio::test]
+    async fn timeouts_and_connect_failures_are_told_apart() {
+        // Accepts connections but never answers, so requests time out.
+        let silent = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let silent_address = silent.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((socket, _)) = silent.accept().await {
+                open.push(socket);
+            }
+        });
+        let client = Client::builder().no_proxy().build().unwrap();
+        let err = client
+            .get(format!("http://{}", silent_address))
+            .timeout(std::time::Duration::from_millis(100))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            transport_error("ollama", err),
+            AiError::Timeout { provider } if provider == "ollama"
+        ));
+
+        // Nothing listens on a port that was just released.
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed_address = closed.local_addr().unwrap();
+        drop(closed);
+        let err = client
+            .get(format!("http://{}", closed_address))
+            .send()
+            .await
+            .unwrap_err();
+        let err = transport_error("ollama", err);
+        assert!(matches!(&err, AiError::Network { provider, .. } if provider == "ollama"));
+        assert!(err.is_retryable());
+    }
+}
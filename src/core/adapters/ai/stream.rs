@@ -0,0 +1,168 @@
+//! Streaming response parsing
+//!
+//! Ollama streams `/api/generate` as newline-delimited JSON, ending with a
+//! chunk that has `"done": true`. A connection that drops before that chunk
+//! leaves a truncated answer, which must not be mistaken for a complete one.
+
+use crate::errors::AiError;
+use serde::Deserialize;
+
+/// Text collected from a streamed response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamedReply {
+    pub text: String,
+    pub tokens_received: usize,
+    /// `tokens_received` is the provider's own count (Ollama's `eval_count`)
+    /// rather than an estimate.
+    pub tokens_exact: bool,
+    /// The stream ended before the provider signalled completion.
+    pub truncated: bool,
+}
+
+#[derive(Deserialize)]
+struct OllamaChunk {
+    #[serde(default)]
+    response: String,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    eval_count: Option<usize>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// Incremental parser for Ollama's NDJSON stream. Network chunks may split
+/// lines anywhere, so partial lines are buffered until their newline arrives.
+#[derive(Debug, Default)]
+pub struct OllamaStreamParser {
+    buffer: Vec<u8>,
+    text: String,
+    tokens: usize,
+    tokens_exact: bool,
+    done: bool,
+}
+
+impl OllamaStreamParser {
+    /// Consumes a network chunk and returns the text fragments it completed.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<Vec<String>, AiError> {
+        self.buffer.extend_from_slice(bytes);
+        let mut fragments = Vec::new();
+        while let Some(newline) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=newline).collect();
+            if let Some(fragment) = self.parse_line(&line)? {
+                fragments.push(fragment);
+            }
+        }
+        Ok(fragments)
+    }
+
+    /// Ends the stream. A missing `done` chunk yields a reply marked as
+    /// truncated when `allow_partial` is set, and an error otherwise.
+    pub fn finish(mut self, allow_partial: bool) -> Result<StreamedReply, AiError> {
+        // The final chunk may arrive without a trailing newline.
+        let rest = std::mem::take(&mut self.buffer);
+        if !rest.iter().all(u8::is_ascii_whitespace) {
+            // An unparseable tail is what a dropped connection looks like, so
+            // treat it as truncation rather than a protocol error.
+            let _ = self.parse_line(&rest);
+        }
+
+        if !self.done && !allow_partial {
+            return Err(AiError::IncompleteStream {
+                provider: "ollama".to_string(),
+                tokens_received: self.tokens,
+            });
+        }
+        Ok(StreamedReply {
+            truncated: !self.done,
+            text: self.text,
+            tokens_received: self.tokens,
+            tokens_exact: self.tokens_exact,
+        })
+    }
+
+    fn parse_line(&mut self, line: &[u8]) -> Result<Option<String>, AiError> {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+        let chunk: OllamaChunk =
+            serde_json::from_str(line).map_err(|err| AiError::InvalidResponse {
+                provider: "ollama".to_string(),
+                message: format!("bad stream chunk: {}", err),
+            })?;
+        if let Some(message) = chunk.error {
+            return Err(AiError::InvalidResponse {
+                provider: "ollama".to_string(),
+                message,
+            });
+        }
+
+        if chunk.done {
+            self.done = true;
+            if let Some(count) = chunk.eval_count {
+                self.tokens = count;
+                self.tokens_exact = true;
+            }
+        }
+        if chunk.response.is_empty() {
+            return Ok(None);
+        }
+        if !chunk.done {
+            self.tokens += 1;
+        }
+        self.text.push_str(&chunk.response);
+        Ok(Some(chunk.response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPLETE: &str = concat!(
+        "{\"response\":\"Hel\",\"done\":false}\n",
+        "{\"response\":\"lo\",\"done\":false}\n",
+        "{\"response\":\" world\",\"done\":false}\n",
+        "{\"response\":\"\",\"done\":true,\"eval_count\":3}\n",
+    );
+
+    #[test]
+    fn complete_stream_split_across_chunks() {
+        let mut parser = OllamaStreamParser::default();
+        let mut seen = String::new();
+        for piece in COMPLETE.as_bytes().chunks(7) {
+            seen.extend(parser.feed(piece).unwrap());
+        }
+        let reply = parser.finish(false).unwrap();
+        assert_eq!(seen, "Hello world");
+        assert_eq!(reply.text, "Hello world");
+        assert_eq!(reply.tokens_received, 3);
+        assert!(reply.tokens_exact);
+        assert!(!reply.truncated);
+    }
+
+    #[test]
+    fn truncated_stream_is_reported() {
+        // Connection dropped in the middle of the third chunk.
+        let cut = &COMPLETE[..COMPLETE.find(" world").unwrap()];
+
+        let mut parser = OllamaStreamParser::default();
+        parser.feed(cut.as_bytes()).unwrap();
+        match parser.finish(false) {
+            Err(AiError::IncompleteStream {
+                tokens_received, ..
+            }) => assert_eq!(tokens_received, 2),
+            other => panic!("expected IncompleteStream, got {:?}", other),
+        }
+
+        let mut parser = OllamaStreamParser::default();
+        parser.feed(cut.as_bytes()).unwrap();
+        let reply = parser.finish(true).unwrap();
+        assert_eq!(reply.text, "Hello");
+        assert_eq!(reply.tokens_received, 2);
+        assert!(!reply.tokens_exact, "counted from fragments");
+        assert!(reply.truncated);
+    }
+}
@@ -0,0 +1,168 @@
+//! Structured (JSON) output
+//!
+//! A [`ResponseFormat`] asks the provider to constrain its reply: OpenAI-style
+//! servers get `response_format` (`json_object` or `json_schema`) and Ollama
+//! gets `format: "json"`. Providers without such a switch only see the schema
+//! in the prompt, so replies are still cleaned up with [`extract_json`] before
+//! they are parsed.
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+/// Shape the provider is asked to reply in.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ResponseFormat {
+    #[default]
+    Text,
+    /// Any JSON object.
+    Json,
+    /// A JSON object matching `schema`.
+    JsonSchema { name: String, schema: Value },
+}
+
+impl ResponseFormat {
+    pub fn json_schema(name: &str, schema: Value) -> Self {
+        ResponseFormat::JsonSchema {
+            name: name.to_string(),
+            schema,
+        }
+    }
+
+    pub fn is_structured(&self) -> bool {
+        !matches!(self, ResponseFormat::Text)
+    }
+
+    /// The `response_format` field of an OpenAI chat completions request.
+    pub(crate) fn openai_value(&self) -> Option<Value> {
+        match self {
+            ResponseFormat::Text => None,
+            ResponseFormat::Json => Some(json!({ "type": "json_object" })),
+            ResponseFormat::JsonSchema { name, schema } => Some(json!({
+                "type": "json_schema",
+                "json_schema": { "name": name, "schema": schema, "strict": false },
+            })),
+        }
+    }
+
+    /// The `format` field of an Ollama generate request.
+    pub(crate) fn ollama_format(&self) -> Option<&'static str> {
+        self.is_structured().then_some("json")
+    }
+}
+
+/// Trims Markdown fences and any prose around the outermost JSON object.
+pub fn extract_json(reply: &str) -> &str {
+    let trimmed = reply.trim();
+    match (trimmed.find('{'), trimmed.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &trimmed[start..=end],
+        _ => trimmed,
+    }
+}
+
+/// Parses a reply into `T`, tolerating fences and surrounding prose.
+pub fn parse_reply<T: DeserializeOwned>(reply: &str) -> Result<T, serde_json::Error> {
+    serde_json::from_str(extract_json(reply))
+}
+
+pub(crate) fn instructions(prompt: &str, schema: &Value) -> String {
+    format!(
+        "{}\n\nRespond with only a JSON object matching this JSON Schema, without Markdown or commentary:\n{}",
+        prompt, schema
+    )
+}
+
+pub(crate) fn repair_prompt(
+    prompt: &str,
+    schema: &Value,
+    reply: &str,
+    error: &serde_json::Error,
+) -> String {
+    format!(
+        "{}\n\nYour previous reply could not be used ({}):\n{}\n\nReply again with only a corrected JSON object.",
+        instructions(prompt, schema),
+        error,
+        reply
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Answer {
+        title: String,
+    }
+
+    #[test]
+    fn replies_are_parsed_through_fences_and_prose() {
+        let fenced = "Sure! Here it is:\n```json\n{\"title\": \"Login\"}\n```\nAnything else?";
+        assert_eq!(extract_json(fenced), "{\"title\": \"Login\"}");
+        assert_eq!(
+            parse_reply::<Answer>(fenced).unwrap(),
+            Answer {
+                title: "Login".to_string()
+            }
+        );
+        assert!(parse_reply::<Answer>("no json here").is_err());
+    }
+
+    #[test]
+    fn formats_map_to_provider_fields() {
+        assert_eq!(ResponseFormat::Text.openai_value(), None);
+        assert_eq!(ResponseFormat::Text.ollama_format(), None);
+        assert_eq!(
+            ResponseFormat::Json.openai_value(),
+            Some(json!({ "type": "json_object" }))
+        );
+        let schema = ResponseFormat::json_schema("answer", json!({ "type": "object" }));
+        assert_eq!(
+            schema.openai_value().unwrap()["json_schema"]["name"],
+            "answer"
+        );
+        assert_eq!(schema.ollama_format(), Some("json"));
+    }
+
+    #[tokio::test]
+    async fn chat_json_requests_the_schema_and_repairs_bad_replies() {
+        use axum::{routing::post, Json, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let server = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(request): Json<Value>| async move {
+                assert_eq!(request["response_format"]["type"], "json_schema");
+                let content = match counter.fetch_add(1, Ordering::SeqCst) {
+                    0 => "Here you go: {\"title\": ",
+                    _ => {
+                        let prompt = request["messages"][0]["content"].as_str().unwrap();
+                        assert!(prompt.contains("could not be used"));
+                        "```json\n{\"title\": \"Login\"}\n```"
+                    }
+                };
+                let message = json!({ "role": "assistant", "content": content });
+                Json(json!({ "choices": [{ "message": message }] }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, server).await });
+
+        let ai =
+            crate::core::adapters::ai::KandilAI::new("gpt4all".to_string(), "local".to_string())
+                .unwrap()
+                .with_base_url(Some(format!("http://{}", address)));
+        let schema = json!({
+            "type": "object",
+            "properties": { "title": { "type": "string" } },
+            "required": ["title"],
+        });
+        let answer: Answer = ai.chat_json("Name the feature", &schema).await.unwrap();
+        assert_eq!(answer.title, "Login");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
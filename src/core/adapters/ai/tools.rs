@@ -0,0 +1,441 @@
+//! Tool calling
+//!
+//! Lets a model ask for a tool instead of answering directly. Each tool is
+//! described by a JSON Schema of its arguments, and a reply is either text or
+//! a [`ToolCall`]. The caller runs the calls it approves and sends the output
+//! back with the conversation so far, until the model answers in text.
+//!
+//! OpenAI-compatible servers (OpenAI, LM Studio, GPT4All, Foundry Local) get
+//! `tools`/`tool_choice` in the chat completions request, and Claude gets the
+//! Messages API `tools` equivalent.
+
+use crate::errors::AiError;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// A tool the model may call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    /// JSON Schema of the arguments object.
+    pub parameters: Value,
+}
+
+impl ToolSpec {
+    pub fn new(name: &str, description: &str, parameters: Value) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            parameters,
+        }
+    }
+}
+
+/// A tool invocation requested by the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// Provider-assigned id that the result must refer back to.
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolResponse {
+    Text(String),
+    ToolCall(ToolCall),
+}
+
+/// One entry of a tool-calling conversation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolTurn {
+    User(String),
+    Call(ToolCall),
+    Result {
+        call_id: String,
+        output: String,
+        is_error: bool,
+    },
+}
+
+/// Runs the tool calls a model asks for, e.g. after asking the user.
+#[async_trait::async_trait]
+pub trait ToolExecutor: Send {
+    /// The tool's output, or why the call was refused or failed. Either is
+    /// sent back to the model.
+    async fn execute(&mut self, call: &ToolCall) -> Result<String, String>;
+}
+
+pub(crate) fn openai_request(
+    model: &str,
+    system: Option<&str>,
+    turns: &[ToolTurn],
+    tools: &[ToolSpec],
+) -> Value {
+    let mut messages = Vec::new();
+    if let Some(system) = system {
+        messages.push(json!({ "role": "system", "content": system }));
+    }
+    for turn in turns {
+        messages.push(match turn {
+            ToolTurn::User(text) => json!({ "role": "user", "content": text }),
+            ToolTurn::Call(call) => json!({
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [{
+                    "id": call.id,
+                    "type": "function",
+                    "function": {
+                        "name": call.name,
+                        // OpenAI sends and expects arguments as a JSON string.
+                        "arguments": call.arguments.to_string(),
+                    },
+                }],
+            }),
+            ToolTurn::Result {
+                call_id, output, ..
+            } => json!({ "role": "tool", "tool_call_id": call_id, "content": output }),
+        });
+    }
+    let tools: Vec<Value> = tools
+        .iter()
+        .map(|tool| {
+            json!({
+                "type": "function",
+                "function": {
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameters": tool.parameters,
+                },
+            })
+        })
+        .collect();
+    json!({
+        "model": model,
+        "messages": messages,
+        "tools": tools,
+        "tool_choice": "auto",
+        "temperature": 0.2,
+    })
+}
+
+pub(crate) fn parse_openai_response(provider: &str, body: &Value) -> Result<ToolResponse, AiError> {
+    let message = &body["choices"][0]["message"];
+    if message.is_null() {
+        return Err(invalid(provider, "no choices returned"));
+    }
+    if let Some(call) = message["tool_calls"]
+        .as_array()
+        .and_then(|calls| calls.first())
+    {
+        let function = &call["function"];
+        let name = function["name"]
+            .as_str()
+            .ok_or_else(|| invalid(provider, "tool call without a name"))?;
+        let arguments = match &function["arguments"] {
+            Value::String(raw) if raw.trim().is_empty() => json!({}),
+            Value::String(raw) => serde_json::from_str(raw).map_err(|err| {
+                invalid(
+                    provider,
+                    &format!("arguments for tool '{}' are not JSON: {}", name, err),
+                )
+            })?,
+            Value::Null => json!({}),
+            other => other.clone(),
+        };
+        return Ok(ToolResponse::ToolCall(ToolCall {
+            id: call["id"].as_str().unwrap_or_default().to_string(),
+            name: name.to_string(),
+            arguments,
+        }));
+    }
+    Ok(ToolResponse::Text(
+        message["content"]
+            .as_str()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    ))
+}
+
+pub(crate) fn anthropic_request(
+    model: &str,
+    system: Option<&str>,
+    turns: &[ToolTurn],
+    tools: &[ToolSpec],
+) -> Value {
+    let messages: Vec<Value> = turns
+        .iter()
+        .map(|turn| match turn {
+            ToolTurn::User(text) => json!({ "role": "user", "content": text }),
+            ToolTurn::Call(call) => json!({
+                "role": "assistant",
+                "content": [{
+                    "type": "tool_use",
+                    "id": call.id,
+                    "name": call.name,
+                    "input": call.arguments,
+                }],
+            }),
+            ToolTurn::Result {
+                call_id,
+                output,
+                is_error,
+            } => json!({
+                "role": "user",
+                "content": [{
+                    "type": "tool_result",
+                    "tool_use_id": call_id,
+                    "content": output,
+                    "is_error": is_error,
+                }],
+            }),
+        })
+        .collect();
+    let tools: Vec<Value> = tools
+        .iter()
+        .map(|tool| {
+            json!({
+                "name": tool.name,
+                "description": tool.description,
+                "input_schema": tool.parameters,
+            })
+        })
+        .collect();
+    let mut request = json!({
+        "model": model,
+        "max_tokens": 1024,
+        "messages": messages,
+        "tools": tools,
+        "tool_choice": { "type": "auto" },
+    });
+    if let Some(system) = system {
+        request["system"] = json!(system);
+    }
+    request
+}
+
+pub(crate) fn parse_anthropic_response(
+    provider: &str,
+    body: &Value,
+) -> Result<ToolResponse, AiError> {
+    let blocks = body["content"]
+        .as_array()
+        .ok_or_else(|| invalid(provider, "response has no content"))?;
+    if let Some(call) = blocks.iter().find(|block| block["type"] == "tool_use") {
+        return Ok(ToolResponse::ToolCall(ToolCall {
+            id: call["id"].as_str().unwrap_or_default().to_string(),
+            name: call["name"]
+                .as_str()
+                .ok_or_else(|| invalid(provider, "tool call without a name"))?
+                .to_string(),
+            arguments: call["input"].clone(),
+        }));
+    }
+    let text: Vec<&str> = blocks
+        .iter()
+        .filter(|block| block["type"] == "text")
+        .filter_map(|block| block["text"].as_str())
+        .collect();
+    Ok(ToolResponse::Text(text.join("\n").trim().to_string()))
+}
+
+fn invalid(provider: &str, message: &str) -> AiError {
+    AiError::InvalidResponse {
+        provider: provider.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deploy_tool() -> ToolSpec {
+        ToolSpec::new(
+            "run_command",
+            "Run a shell command",
+            json!({
+                "type": "object",
+                "properties": { "command": { "type": "string" } },
+                "required": ["command"],
+            }),
+        )
+    }
+
+    fn conversation() -> Vec<ToolTurn> {
+        vec![
+            ToolTurn::User("Is the app up?".to_string()),
+            ToolTurn::Call(ToolCall {
+                id: "call_1".to_string(),
+                name: "run_command".to_string(),
+                arguments: json!({ "command": "kubectl get pods" }),
+            }),
+            ToolTurn::Result {
+                call_id: "call_1".to_string(),
+                output: "web-1 Running".to_string(),
+                is_error: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn openai_requests_carry_tools_and_tool_messages() {
+        let request = openai_request(
+            "gpt-4o",
+            Some("Be brief"),
+            &conversation(),
+            &[deploy_tool()],
+        );
+        assert_eq!(request["tool_choice"], "auto");
+        assert_eq!(request["tools"][0]["type"], "function");
+        assert_eq!(request["tools"][0]["function"]["name"], "run_command");
+        let messages = request["messages"].as_array().unwrap();
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(
+            messages[2]["tool_calls"][0]["function"]["arguments"],
+            r#"{"command":"kubectl get pods"}"#
+        );
+        assert_eq!(messages[3]["role"], "tool");
+        assert_eq!(messages[3]["tool_call_id"], "call_1");
+    }
+
+    #[test]
+    fn openai_tool_calls_are_parsed() {
+        let body = json!({
+            "choices": [{ "message": {
+                "content": null,
+                "tool_calls": [{
+                    "id": "call_9",
+                    "type": "function",
+                    "function": { "name": "run_command", "arguments": "{\"command\":\"ls\"}" },
+                }],
+            }}],
+        });
+        assert_eq!(
+            parse_openai_response("openai", &body).unwrap(),
+            ToolResponse::ToolCall(ToolCall {
+                id: "call_9".to_string(),
+                name: "run_command".to_string(),
+                arguments: json!({ "command": "ls" }),
+            })
+        );
+
+        let text = json!({ "choices": [{ "message": { "content": " All pods are running. " } }] });
+        assert_eq!(
+            parse_openai_response("openai", &text).unwrap(),
+            ToolResponse::Text("All pods are running.".to_string())
+        );
+
+        let broken = json!({ "choices": [{ "message": { "tool_calls": [{
+            "id": "x", "function": { "name": "run_command", "arguments": "{oops" },
+        }]}}]});
+        assert!(parse_openai_response("openai", &broken).is_err());
+    }
+
+    #[test]
+    fn anthropic_requests_use_tool_use_blocks() {
+        let request = anthropic_request(
+            "claude-3-5-sonnet",
+            Some("Be brief"),
+            &conversation(),
+            &[deploy_tool()],
+        );
+        assert_eq!(request["system"], "Be brief");
+        assert_eq!(
+            request["tools"][0]["input_schema"]["required"][0],
+            "command"
+        );
+        let messages = request["messages"].as_array().unwrap();
+        assert_eq!(messages[1]["content"][0]["type"], "tool_use");
+        assert_eq!(
+            messages[1]["content"][0]["input"]["command"],
+            "kubectl get pods"
+        );
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(messages[2]["content"][0]["tool_use_id"], "call_1");
+    }
+
+    #[test]
+    fn anthropic_tool_calls_are_parsed() {
+        let body = json!({
+            "content": [
+                { "type": "text", "text": "Let me check." },
+                {
+                    "type": "tool_use",
+                    "id": "toolu_1",
+                    "name": "run_command",
+                    "input": { "command": "ls" },
+                },
+            ],
+            "stop_reason": "tool_use",
+        });
+        assert_eq!(
+            parse_anthropic_response("claude", &body).unwrap(),
+            ToolResponse::ToolCall(ToolCall {
+                id: "toolu_1".to_string(),
+                name: "run_command".to_string(),
+                arguments: json!({ "command": "ls" }),
+            })
+        );
+        let text = json!({ "content": [{ "type": "text", "text": "Done." }] });
+        assert_eq!(
+            parse_anthropic_response("claude", &text).unwrap(),
+            ToolResponse::Text("Done.".to_string())
+        );
+    }
+
+    struct Approve(Vec<ToolCall>);
+
+    #[async_trait::async_trait]
+    impl ToolExecutor for Approve {
+        async fn execute(&mut self, call: &ToolCall) -> Result<String, String> {
+            self.0.push(call.clone());
+            Ok("web-1 Running".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn run_tools_feeds_results_back_until_the_model_answers() {
+        use axum::{routing::post, Json, Router};
+
+        let server = Router::new().route(
+            "/v1/chat/completions",
+            post(|Json(request): Json<Value>| async move {
+                assert_eq!(request["tools"][0]["function"]["name"], "run_command");
+                let messages = request["messages"].as_array().unwrap();
+                let reply = match messages.iter().find(|message| message["role"] == "tool") {
+                    Some(result) => json!({
+                        "content": format!("Pods: {}", result["content"].as_str().unwrap()),
+                    }),
+                    None => json!({ "content": null, "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {
+                            "name": "run_command",
+                            "arguments": r#"{"command":"kubectl get pods"}"#,
+                        },
+                    }]}),
+                };
+                Json(json!({ "choices": [{ "message": reply }] }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, server).await });
+
+        let ai =
+            crate::core::adapters::ai::KandilAI::new("gpt4all".to_string(), "local".to_string())
+                .unwrap()
+                .with_base_url(Some(format!("http://{}", address)));
+        let mut executor = Approve(Vec::new());
+        let answer = ai
+            .run_tools("Is the app up?", &[deploy_tool()], &mut executor, 3)
+            .await
+            .unwrap();
+        assert_eq!(answer, "Pods: web-1 Running");
+        assert_eq!(executor.0.len(), 1);
+        assert_eq!(executor.0[0].arguments["command"], "kubectl get pods");
+    }
+}
@@ -1,100 +1,108 @@
 //! AI adapter with cost tracking
 //!
-//! Wrapper around KandilAI that adds cost tracking functionality
+//! Wrapper around KandilAI that adds cost tracking functionality. Token
+//! counts come from [`crate::utils::tokens`], or from the provider itself
+//! when a stream reports its own count, and the cost of the last call is kept
+//! so the caller can store it with the turn.
 
-use crate::core::adapters::ai::KandilAI;
+use crate::core::adapters::ai::{KandilAI, StreamedReply};
 use crate::utils::cost_tracking::CostTracker;
+use crate::utils::tokens;
 use anyhow::Result;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+
+/// Tokens and cost of one call.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CallUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub cost_usd: f64,
+}
 
 pub struct TrackedAI {
     pub ai: Arc<KandilAI>,
     cost_tracker: Arc<CostTracker>,
+    /// Usage of the most recent call, as recorded by the tracker.
+    last_usage: Mutex<Option<CallUsage>>,
 }
 
 impl TrackedAI {
     pub fn new(ai: Arc<KandilAI>, cost_tracker: Arc<CostTracker>) -> Self {
-        Self { ai, cost_tracker }
+        Self {
+            ai,
+            cost_tracker,
+            last_usage: Mutex::new(None),
+        }
     }
 
-    pub async fn chat(&self, message: &str) -> Result<String> {
-        // For now, just call the underlying AI and return the result
-        // In a real implementation, we would track token usage and costs
-        let response = self.ai.chat(message).await?;
+    /// Tokens and cost of the most recent call, once one has finished.
+    pub fn last_usage(&self) -> Option<CallUsage> {
+        *self.last_usage.lock().unwrap()
+    }
 
-        // Extract provider string for cost tracking
-        let provider_str = match self.ai.provider {
-            crate::core::adapters::ai::AIProvider::Ollama => "ollama",
-            crate::core::adapters::ai::AIProvider::Claude => "claude",
-            crate::core::adapters::ai::AIProvider::Qwen => "qwen",
-            crate::core::adapters::ai::AIProvider::OpenAI => "openai",
-            crate::core::adapters::ai::AIProvider::LmStudio => "lmstudio",
-            crate::core::adapters::ai::AIProvider::Gpt4All => "gpt4all",
-            crate::core::adapters::ai::AIProvider::FoundryLocal => "foundry",
-        };
+    fn count(&self, text: &str) -> u32 {
+        tokens::bpe_count(&self.ai.model, text).tokens as u32
+    }
 
-        // In a full implementation, we would estimate token counts from the message/response
-        // and call self.cost_tracker.record_usage() with real values
-        // For now, we'll just do a placeholder call
-        self.cost_tracker.record_usage(
-            provider_str,
+    fn record(&self, prompt_tokens: u32, completion_tokens: u32) {
+        let cost_usd = self.cost_tracker.record_usage(
+            self.ai.provider_name(),
             &self.ai.model,
-            message.len() as u32,  // Placeholder - real token count needed
-            response.len() as u32, // Placeholder - real token count needed
+            prompt_tokens,
+            completion_tokens,
         );
+        *self.last_usage.lock().unwrap() = Some(CallUsage {
+            prompt_tokens,
+            completion_tokens,
+            cost_usd,
+        });
+    }
 
+    pub async fn chat(&self, message: &str) -> Result<String> {
+        let response = self.ai.chat(message).await?;
+        self.record(self.count(message), self.count(&response));
         Ok(response)
     }
 
+    /// Like [`chat`](Self::chat) with workspace context. The context is added
+    /// inside the adapter, so only the message counts towards the prompt.
     pub async fn chat_with_context(
         &self,
         message: &str,
         workspace_path: Option<&str>,
     ) -> Result<String> {
-        // Call the enhanced chat with context functionality
         let response = self.ai.chat_with_context(message, workspace_path).await?;
+        self.record(self.count(message), self.count(&response));
+        Ok(response)
+    }
 
-        // Extract provider string for cost tracking
-        let provider_str = match self.ai.provider {
-            crate::core::adapters::ai::AIProvider::Ollama => "ollama",
-            crate::core::adapters::ai::AIProvider::Claude => "claude",
-            crate::core::adapters::ai::AIProvider::Qwen => "qwen",
-            crate::core::adapters::ai::AIProvider::OpenAI => "openai",
-            crate::core::adapters::ai::AIProvider::LmStudio => "lmstudio",
-            crate::core::adapters::ai::AIProvider::Gpt4All => "gpt4all",
-            crate::core::adapters::ai::AIProvider::FoundryLocal => "foundry",
-        };
-
-        // In a full implementation, we would estimate token counts from the message/response
-        // and call self.cost_tracker.record_usage() with real values
-        // For now, we'll just do a placeholder call
-        let enhanced_message = if let Some(path) = workspace_path {
-            // Simulate the enhanced message for cost tracking
-            format!("Context from your project:\nFile: example.rs\nContent: example content\n\nUser Query: {}", message)
+    /// Streams the answer through [`KandilAI::chat_stream`], so retries, the
+    /// circuit breaker and hybrid mode apply as they do for [`chat`](Self::chat),
+    /// and records the usage once the stream ends.
+    pub async fn chat_stream<F>(
+        &self,
+        message: &str,
+        allow_partial: bool,
+        on_chunk: F,
+    ) -> Result<StreamedReply>
+    where
+        F: FnMut(&str) + Send,
+    {
+        let reply = self
+            .ai
+            .chat_stream(message, allow_partial, on_chunk)
+            .await?;
+        let completion_tokens = if reply.tokens_exact {
+            reply.tokens_received as u32
         } else {
-            message.to_string()
+            self.count(&reply.text)
         };
-
-        self.cost_tracker.record_usage(
-            provider_str,
-            &self.ai.model,
-            enhanced_message.len() as u32, // Placeholder - real token count needed
-            response.len() as u32,         // Placeholder - real token count needed
-        );
-
-        Ok(response)
+        self.record(self.count(message), completion_tokens);
+        Ok(reply)
     }
 
     pub fn get_provider(&self) -> String {
-        match self.ai.provider {
-            crate::core::adapters::ai::AIProvider::Ollama => "ollama".to_string(),
-            crate::core::adapters::ai::AIProvider::Claude => "claude".to_string(),
-            crate::core::adapters::ai::AIProvider::Qwen => "qwen".to_string(),
-            crate::core::adapters::ai::AIProvider::OpenAI => "openai".to_string(),
-            crate::core::adapters::ai::AIProvider::LmStudio => "lmstudio".to_string(),
-            crate::core::adapters::ai::AIProvider::Gpt4All => "gpt4all".to_string(),
-            crate::core::adapters::ai::AIProvider::FoundryLocal => "foundry".to_string(),
-        }
+        self.ai.provider_name().to_string()
     }
 
     pub fn get_model(&self) -> &str {
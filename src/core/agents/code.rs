@@ -4,6 +4,7 @@
 
 use crate::core::adapters::ai::KandilAI;
 use crate::core::agents::base::{Agent, AgentState, ReActLoop};
+use crate::utils::prompt_templates::{PromptData, PromptTemplates};
 use crate::utils::templates::TemplateEngine;
 use anyhow::Result;
 use async_trait::async_trait;
@@ -28,6 +29,7 @@ pub struct CodeFile {
 pub struct CodeAgent {
     ai: Arc<KandilAI>,
     template_engine: TemplateEngine,
+    prompts: PromptTemplates,
 }
 
 impl CodeAgent {
@@ -35,15 +37,15 @@ impl CodeAgent {
         Ok(Self {
             ai,
             template_engine: TemplateEngine::new(),
+            prompts: PromptTemplates::user_default(),
         })
     }
 
     pub async fn generate_code(&self, design_doc: &str, language: &str) -> Result<CodeOutput> {
-        let task = format!(
-            "Generate production-ready {} code from this design.\n\nDesign:\n{}\n\nFollow:\n1. Map components to files/folders\n2. Generate data models\n3. Implement business logic\n4. Add error handling\n5. Include logging\n6. Follow language best practices\n\nReturn structured plan with file paths and code blocks.",
-            language.to_uppercase(),
-            design_doc
-        );
+        let task = self.prompts.render(
+            "code",
+            &PromptData::new(design_doc).with_language(language.to_uppercase()),
+        )?;
 
         let loop_engine = ReActLoop::new(5);
         let result = loop_engine.run(self, &task).await?;
@@ -3,6 +3,7 @@
 //! Handles cross-role collaboration and communication between Architect, Developer, and QA
 
 use crate::core::agents::{ArchitectSimulation, DeveloperSimulation, QaSimulation};
+use crate::utils::json_store::JsonStore;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -85,6 +86,14 @@ pub struct CollaborationManager {
     pub decisions: Vec<CrossRoleDecision>,
     pub sessions: Vec<CollaborationSession>,
     pub ongoing_discussions: HashMap<String, Vec<String>>,
+    store: Option<JsonStore>,
+}
+
+/// On-disk form of the sessions and decisions of a [`CollaborationManager`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoredCollaboration {
+    sessions: Vec<CollaborationSession>,
+    decisions: Vec<CrossRoleDecision>,
 }
 
 impl CollaborationManager {
@@ -93,30 +102,72 @@ impl CollaborationManager {
             decisions: vec![],
             sessions: vec![],
             ongoing_discussions: HashMap::new(),
+            store: None,
         }
     }
 
+    /// Loads sessions and decisions from `store` and saves new ones back to it.
+    pub fn open(store: JsonStore) -> Result<Self> {
+        let stored: StoredCollaboration = store.load()?;
+        Ok(Self {
+            decisions: stored.decisions,
+            sessions: stored.sessions,
+            ongoing_discussions: HashMap::new(),
+            store: Some(store),
+        })
+    }
+
+    /// Opens the shared store in the kandil data directory.
+    pub fn open_default() -> Result<Self> {
+        Self::open(JsonStore::in_data_dir("role_collaboration.json"))
+    }
+
+    /// Re-reads the store under its lock, applies `f` to it and to the
+    /// in-memory copy, then saves. Without a store only the in-memory copy
+    /// changes.
+    fn persist<T>(&mut self, f: impl FnOnce(&mut StoredCollaboration) -> T) -> Result<T> {
+        let Some(store) = self.store.clone() else {
+            let mut current = StoredCollaboration {
+                sessions: std::mem::take(&mut self.sessions),
+                decisions: std::mem::take(&mut self.decisions),
+            };
+            let result = f(&mut current);
+            self.sessions = current.sessions;
+            self.decisions = current.decisions;
+            return Ok(result);
+        };
+
+        store.update(|stored: &mut StoredCollaboration| {
+            let result = f(stored);
+            self.sessions = stored.sessions.clone();
+            self.decisions = stored.decisions.clone();
+            Ok(result)
+        })
+    }
+
     pub async fn start_collaboration_session(
         &mut self,
         title: &str,
         participants: Vec<Role>,
         agenda: Vec<String>,
-    ) -> String {
-        let session_id = format!("CS-{}", self.sessions.len() + 1);
+    ) -> Result<String> {
+        self.persist(|stored| {
+            let session_id = format!("CS-{}", stored.sessions.len() + 1);
 
-        let session = CollaborationSession {
-            id: session_id.clone(),
-            title: title.to_string(),
-            participants,
-            agenda,
-            discussion_points: vec![],
-            action_items: vec![],
-            outcomes: vec![],
-            created_date: chrono::Utc::now().format("%Y-%m-%d").to_string(),
-        };
+            let session = CollaborationSession {
+                id: session_id.clone(),
+                title: title.to_string(),
+                participants,
+                agenda,
+                discussion_points: vec![],
+                action_items: vec![],
+                outcomes: vec![],
+                created_date: chrono::Utc::now().format("%Y-%m-%d").to_string(),
+            };
 
-        self.sessions.push(session);
-        session_id
+            stored.sessions.push(session);
+            session_id
+        })
     }
 
     pub async fn facilitate_architecture_discussion(
@@ -238,9 +289,14 @@ impl CollaborationManager {
             created_date: chrono::Utc::now().format("%Y-%m-%d").to_string(),
         };
 
-        self.decisions.push(cross_role_decision.clone());
-
-        Ok(cross_role_decision)
+        // Number the decision against the stored list so concurrent runs
+        // don't hand out the same id.
+        self.persist(|stored| {
+            let mut decision = cross_role_decision;
+            decision.id = format!("CRD-{}", stored.decisions.len() + 1);
+            stored.decisions.push(decision.clone());
+            decision
+        })
     }
 
     pub fn get_decision(&self, id: &str) -> Option<&CrossRoleDecision> {
@@ -2,6 +2,7 @@
 //!
 //! Module for handling collaborative editing and real-time interaction
 
+use crate::utils::json_store::JsonStore;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -87,6 +88,8 @@ pub struct RealTimeCollaboration {
     #[serde(skip)]
     pub change_broadcasters: HashMap<String, broadcast::Sender<DocumentChange>>,
     pub active_users: HashMap<String, UserStatus>,
+    #[serde(skip)]
+    store: Option<JsonStore>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -122,6 +125,41 @@ impl RealTimeCollaboration {
             sessions: Arc::new(Mutex::new(HashMap::new())),
             change_broadcasters: HashMap::new(),
             active_users: HashMap::new(),
+            store: None,
+        }
+    }
+
+    /// Loads sessions from `store` and writes every later change back to it,
+    /// so sessions outlive the process that created them.
+    pub fn open(store: JsonStore) -> Result<Self> {
+        let sessions = store.load()?;
+        Ok(Self {
+            sessions: Arc::new(Mutex::new(sessions)),
+            store: Some(store),
+            ..Self::new()
+        })
+    }
+
+    /// Opens the shared session store in the kandil data directory.
+    pub fn open_default() -> Result<Self> {
+        Self::open(JsonStore::in_data_dir("collab_sessions.json"))
+    }
+
+    /// Runs `f` against the sessions. With a store attached, the sessions are
+    /// re-read under the store lock first so changes made by other processes
+    /// are not overwritten, and saved once `f` succeeds.
+    fn mutate<T>(
+        &self,
+        f: impl FnOnce(&mut HashMap<String, CollaborationSession>) -> Result<T>,
+    ) -> Result<T> {
+        let mut sessions = self.sessions.lock().unwrap();
+        match &self.store {
+            Some(store) => store.update(|stored: &mut HashMap<String, CollaborationSession>| {
+                let result = f(stored)?;
+                *sessions = stored.clone();
+                Ok(result)
+            }),
+            None => f(&mut sessions),
         }
     }
 
@@ -158,8 +196,10 @@ impl RealTimeCollaboration {
         let (tx, _rx) = broadcast::channel::<DocumentChange>(100);
         self.change_broadcasters.insert(session_id.clone(), tx);
 
-        let mut sessions = self.sessions.lock().unwrap();
-        sessions.insert(session_id.clone(), session);
+        self.mutate(|sessions| {
+            sessions.insert(session_id.clone(), session);
+            Ok(())
+        })?;
 
         Ok(session_id)
     }
@@ -171,23 +211,24 @@ impl RealTimeCollaboration {
         name: &str,
         role: Role,
     ) -> Result<()> {
-        let mut sessions = self.sessions.lock().unwrap();
-
-        if let Some(session) = sessions.get_mut(session_id) {
-            session.participants.push(Participant {
-                id: user_id.to_string(),
-                name: name.to_string(),
-                role,
-                joined_at: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-                is_active: true,
-            });
-
-            session.last_activity = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        self.mutate(|sessions| {
+            if let Some(session) = sessions.get_mut(session_id) {
+                session.participants.push(Participant {
+                    id: user_id.to_string(),
+                    name: name.to_string(),
+                    role,
+                    joined_at: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                    is_active: true,
+                });
+
+                session.last_activity =
+                    chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
 
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("Session {} not found", session_id))
-        }
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("Session {} not found", session_id))
+            }
+        })
     }
 
     pub fn add_document(
@@ -198,29 +239,30 @@ impl RealTimeCollaboration {
         content: &str,
         language: &str,
     ) -> Result<()> {
-        let mut sessions = self.sessions.lock().unwrap();
-
-        if let Some(session) = sessions.get_mut(session_id) {
-            let doc = CollaborativeDocument {
-                id: doc_id.to_string(),
-                name: name.to_string(),
-                content: content.to_string(),
-                language: language.to_string(),
-                last_modified: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-                version: 1,
-                lock_holder: None,
-                cursors: HashMap::new(),
-                changes: vec![],
-            };
-
-            session.documents.insert(doc_id.to_string(), doc);
+        self.mutate(|sessions| {
+            if let Some(session) = sessions.get_mut(session_id) {
+                let doc = CollaborativeDocument {
+                    id: doc_id.to_string(),
+                    name: name.to_string(),
+                    content: content.to_string(),
+                    language: language.to_string(),
+                    last_modified: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                    version: 1,
+                    lock_holder: None,
+                    cursors: HashMap::new(),
+                    changes: vec![],
+                };
+
+                session.documents.insert(doc_id.to_string(), doc);
+
+                session.last_activity =
+                    chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
 
-            session.last_activity = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
-
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("Session {} not found", session_id))
-        }
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("Session {} not found", session_id))
+            }
+        })
     }
 
     pub fn apply_change(
@@ -230,56 +272,52 @@ impl RealTimeCollaboration {
         doc_id: &str,
         change: DocumentChange,
     ) -> Result<()> {
-        let mut sessions = self.sessions.lock().unwrap();
-
-        if let Some(session) = sessions.get_mut(session_id) {
-            if let Some(doc) = session.documents.get_mut(doc_id) {
-                // Apply the change based on operation
-                match change.operation {
-                    ChangeOperation::Insert => {
-                        // In a real implementation, this would insert text at position
-                        doc.content
-                            .insert_str(change.position as usize, &change.content);
-                    }
-                    ChangeOperation::Delete => {
-                        // In a real implementation, this would delete text at position
-                        if (change.position as usize) < doc.content.len() {
-                            let end_idx = std::cmp::min(
-                                (change.position as usize) + change.content.len(),
-                                doc.content.len(),
-                            );
-                            doc.content.drain((change.position as usize)..end_idx);
-                        }
-                    }
-                    ChangeOperation::Update => {
-                        // In a real implementation, this would update text at position
-                        doc.content
-                            .insert_str(change.position as usize, &change.content);
+        self.mutate(|sessions| {
+            let session = sessions
+                .get_mut(session_id)
+                .ok_or_else(|| anyhow::anyhow!("Session {} not found", session_id))?;
+            let doc = session.documents.get_mut(doc_id).ok_or_else(|| {
+                anyhow::anyhow!("Document {} not found in session {}", doc_id, session_id)
+            })?;
+
+            // Apply the change based on operation
+            match change.operation {
+                ChangeOperation::Insert => {
+                    // In a real implementation, this would insert text at position
+                    doc.content
+                        .insert_str(change.position as usize, &change.content);
+                }
+                ChangeOperation::Delete => {
+                    // In a real implementation, this would delete text at position
+                    if (change.position as usize) < doc.content.len() {
+                        let end_idx = std::cmp::min(
+                            (change.position as usize) + change.content.len(),
+                            doc.content.len(),
+                        );
+                        doc.content.drain((change.position as usize)..end_idx);
                     }
                 }
+                ChangeOperation::Update => {
+                    // In a real implementation, this would update text at position
+                    doc.content
+                        .insert_str(change.position as usize, &change.content);
+                }
+            }
 
-                doc.version += 1;
-                doc.last_modified = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
-                doc.changes.push(change.clone());
-
-                session.last_activity = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            doc.version += 1;
+            doc.last_modified = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            doc.changes.push(change.clone());
 
-                // Broadcast the change to other participants
-                if let Some(tx) = self.change_broadcasters.get(session_id) {
-                    let _ = tx.send(change);
-                }
+            session.last_activity = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            Ok(())
+        })?;
 
-                Ok(())
-            } else {
-                Err(anyhow::anyhow!(
-                    "Document {} not found in session {}",
-                    doc_id,
-                    session_id
-                ))
-            }
-        } else {
-            Err(anyhow::anyhow!("Session {} not found", session_id))
+        // Broadcast the change to other participants
+        if let Some(tx) = self.change_broadcasters.get(session_id) {
+            let _ = tx.send(change);
         }
+
+        Ok(())
     }
 
     pub fn get_document_content(&self, session_id: &str, doc_id: &str) -> Result<String> {
@@ -307,4 +345,39 @@ impl RealTimeCollaboration {
             .cloned()
             .ok_or_else(|| anyhow::anyhow!("Session {} not found", session_id))
     }
+
+    /// All known sessions, oldest first.
+    pub fn list_sessions(&self) -> Vec<CollaborationSession> {
+        let sessions = self.sessions.lock().unwrap();
+        let mut list: Vec<_> = sessions.values().cloned().collect();
+        list.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_survive_reopening_the_store() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("kandil_collab_{}", uuid::Uuid::new_v4()));
+        let store = JsonStore::new(dir.join("sessions.json"));
+
+        let mut first = RealTimeCollaboration::open(store.clone())?;
+        let session_id = first.create_session("Pairing", "u1", "Alice")?;
+
+        let mut second = RealTimeCollaboration::open(store.clone())?;
+        second.add_participant(&session_id, "u2", "Bo", Role::Editor)?;
+        second.add_document(&session_id, "d1", "main.rs", "fn main() {}", "rust")?;
+
+        let reopened = RealTimeCollaboration::open(store)?;
+        let session = reopened.get_session(&session_id)?;
+        assert_eq!(session.participants.len(), 2);
+        assert!(session.documents.contains_key("d1"));
+        assert_eq!(reopened.list_sessions().len(), 1);
+
+        let _ = std::fs::remove_dir_all(dir);
+        Ok(())
+    }
 }
@@ -5,10 +5,12 @@
 
 use anyhow::Result;
 use async_trait::async_trait;
+use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::task;
 
-use crate::core::adapters::ai::AIProviderTrait;
+use crate::core::adapters::ai::{backend, AIProviderTrait};
 
 #[derive(Debug, Clone)]
 pub struct Solution {
@@ -196,6 +198,206 @@ impl ConsensusOrchestrator {
     }
 }
 
+/// Splits a `--models` entry into provider and model. The part before the
+/// first `:` is only taken as the provider when it names a registered one,
+/// since model tags such as `llama3:70b` contain colons themselves.
+pub fn parse_model_spec(spec: &str, default_provider: &str) -> (String, String) {
+    match spec.split_once(':') {
+        Some((provider, model)) if backend::is_registered(provider) => {
+            (provider.to_string(), model.to_string())
+        }
+        _ => (default_provider.to_string(), spec.to_string()),
+    }
+}
+
+/// One model's reply in a [`ModelConsensus`] run.
+#[derive(Debug, Clone)]
+pub struct ModelAnswer {
+    pub label: String,
+    pub content: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsensusReport {
+    /// Synthesized answer (from the judge, or the majority cluster's answer)
+    pub answer: String,
+    /// Models whose answers fell in the majority cluster
+    pub agreeing: Vec<String>,
+    /// Answers that disagreed with the majority
+    pub dissenting: Vec<ModelAnswer>,
+    /// Models that errored or timed out
+    pub failed: Vec<ModelAnswer>,
+    pub judged: bool,
+}
+
+/// Sends one prompt to several models concurrently and reconciles the answers.
+///
+/// Answers are clustered by word overlap; the largest cluster is the majority.
+/// When a judge model is set, it synthesizes the final answer from all replies;
+/// if the judge fails or times out, the majority's answer is used instead.
+pub struct ModelConsensus {
+    models: Vec<(String, Arc<dyn AIProviderTrait>)>,
+    judge: Option<Arc<dyn AIProviderTrait>>,
+    timeout: Duration,
+    similarity_threshold: f32,
+}
+
+impl ModelConsensus {
+    pub fn new(models: Vec<(String, Arc<dyn AIProviderTrait>)>, timeout: Duration) -> Self {
+        Self {
+            models,
+            judge: None,
+            timeout,
+            similarity_threshold: 0.5,
+        }
+    }
+
+    pub fn with_judge(mut self, judge: Arc<dyn AIProviderTrait>) -> Self {
+        self.judge = Some(judge);
+        self
+    }
+
+    pub async fn run(&self, prompt: &str) -> Result<ConsensusReport> {
+        let mut handles = Vec::new();
+        for (label, model) in &self.models {
+            let label = label.clone();
+            let model = Arc::clone(model);
+            let prompt = prompt.to_string();
+            let limit = self.timeout;
+            handles.push(task::spawn(async move {
+                let reply = match tokio::time::timeout(limit, model.chat(&prompt)).await {
+                    Ok(Ok(content)) => Ok(content),
+                    Ok(Err(err)) => Err(err.to_string()),
+                    Err(_) => Err(format!("timed out after {}s", limit.as_secs())),
+                };
+                (label, reply)
+            }));
+        }
+
+        let mut answers = Vec::new();
+        let mut failed = Vec::new();
+        for handle in handles {
+            let (label, reply) = handle.await?;
+            match reply {
+                Ok(content) => answers.push(ModelAnswer {
+                    label,
+                    content: Some(content),
+                    error: None,
+                }),
+                Err(error) => failed.push(ModelAnswer {
+                    label,
+                    content: None,
+                    error: Some(error),
+                }),
+            }
+        }
+
+        if answers.is_empty() {
+            return Err(anyhow::anyhow!("No model returned an answer"));
+        }
+
+        let clusters = self.cluster(&answers);
+        // `rev` so ties go to the earliest cluster, i.e. the first-listed model
+        let majority = clusters
+            .iter()
+            .rev()
+            .max_by_key(|cluster| cluster.len())
+            .cloned()
+            .unwrap_or_default();
+
+        let agreeing = majority.iter().map(|&i| answers[i].label.clone()).collect();
+        let dissenting = (0..answers.len())
+            .filter(|i| !majority.contains(i))
+            .map(|i| answers[i].clone())
+            .collect();
+
+        let judged_answer = match &self.judge {
+            Some(judge) if answers.len() > 1 => {
+                let judging = judge.chat(&self.judge_prompt(prompt, &answers));
+                match tokio::time::timeout(self.timeout, judging).await {
+                    Ok(Ok(reply)) => Some(reply),
+                    Ok(Err(err)) => {
+                        log::warn!("The judge failed: {}; using the majority answer", err);
+                        None
+                    }
+                    Err(_) => {
+                        log::warn!(
+                            "The judge timed out after {}s; using the majority answer",
+                            self.timeout.as_secs()
+                        );
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        let judged = judged_answer.is_some();
+        let answer = judged_answer
+            .unwrap_or_else(|| answers[majority[0]].content.clone().unwrap_or_default());
+
+        Ok(ConsensusReport {
+            answer,
+            agreeing,
+            dissenting,
+            failed,
+            judged,
+        })
+    }
+
+    /// Greedy clustering: each answer joins the first cluster whose first
+    /// member is similar enough, otherwise it starts a new cluster.
+    fn cluster(&self, answers: &[ModelAnswer]) -> Vec<Vec<usize>> {
+        let words: Vec<HashSet<String>> = answers
+            .iter()
+            .map(|a| word_set(a.content.as_deref().unwrap_or_default()))
+            .collect();
+
+        let mut clusters: Vec<Vec<usize>> = Vec::new();
+        for i in 0..answers.len() {
+            match clusters
+                .iter_mut()
+                .find(|cluster| jaccard(&words[cluster[0]], &words[i]) >= self.similarity_threshold)
+            {
+                Some(cluster) => cluster.push(i),
+                None => clusters.push(vec![i]),
+            }
+        }
+        clusters
+    }
+
+    fn judge_prompt(&self, prompt: &str, answers: &[ModelAnswer]) -> String {
+        let mut text = format!(
+            "Several models answered the same question. Synthesize the single best answer, \
+             then list any points where the answers disagreed and which view you chose.\n\nQuestion:\n{}\n",
+            prompt
+        );
+        for answer in answers {
+            text.push_str(&format!(
+                "\n--- Answer from {} ---\n{}\n",
+                answer.label,
+                answer.content.as_deref().unwrap_or_default()
+            ));
+        }
+        text
+    }
+}
+
+fn word_set(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let union = a.union(b).count();
+    a.intersection(b).count() as f32 / union as f32
+}
+
 // Example specialized agents
 pub struct CodeAgent {
     pub provider: Arc<dyn AIProviderTrait>,
@@ -297,6 +499,113 @@ mod tests {
         }
     }
 
+    struct FixedAI(&'static str);
+
+    #[async_trait]
+    impl AIProviderTrait for FixedAI {
+        async fn chat(&self, _message: &str) -> Result<String> {
+            Ok(self.0.to_string())
+        }
+
+        async fn chat_with_context(&self, message: &str, _workspace_path: Option<&str>) -> Result<String> {
+            self.chat(message).await
+        }
+    }
+
+    #[tokio::test]
+    async fn model_consensus_reports_dissent() {
+        let model = |label: &str, reply: &'static str| {
+            (label.to_string(), Arc::new(FixedAI(reply)) as Arc<dyn AIProviderTrait>)
+        };
+        let consensus = ModelConsensus::new(
+            vec![
+                model("a", "Use a mutex to guard the shared counter"),
+                model("b", "use a Mutex to guard the shared counter."),
+                model("c", "Switch the whole thing to message passing channels"),
+            ],
+            Duration::from_secs(5),
+        );
+
+        let report = consensus.run("How do I share a counter?").await.unwrap();
+        assert_eq!(report.agreeing, vec!["a", "b"]);
+        assert_eq!(report.dissenting.len(), 1);
+        assert_eq!(report.dissenting[0].label, "c");
+        assert!(report.answer.contains("mutex"));
+        assert!(!report.judged);
+    }
+
+    struct SlowAI;
+
+    #[async_trait]
+    impl AIProviderTrait for SlowAI {
+        async fn chat(&self, _message: &str) -> Result<String> {
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            Ok("too late".to_string())
+        }
+
+        async fn chat_with_context(&self, message: &str, _workspace_path: Option<&str>) -> Result<String> {
+            self.chat(message).await
+        }
+    }
+
+    #[tokio::test]
+    async fn a_slow_judge_falls_back_to_the_majority() {
+        let consensus = ModelConsensus::new(
+            vec![
+                ("a".to_string(), Arc::new(FixedAI("Use a mutex")) as Arc<dyn AIProviderTrait>),
+                ("b".to_string(), Arc::new(FixedAI("use a mutex")) as Arc<dyn AIProviderTrait>),
+            ],
+            Duration::from_millis(100),
+        )
+        .with_judge(Arc::new(SlowAI));
+
+        let report = consensus.run("How do I share a counter?").await.unwrap();
+        assert!(!report.judged);
+        assert_eq!(report.answer, "Use a mutex");
+    }
+
+    struct FailingAI;
+
+    #[async_trait]
+    impl AIProviderTrait for FailingAI {
+        async fn chat(&self, _message: &str) -> Result<String> {
+            Err(anyhow::anyhow!("judge model not found"))
+        }
+
+        async fn chat_with_context(&self, message: &str, _workspace_path: Option<&str>) -> Result<String> {
+            self.chat(message).await
+        }
+    }
+
+    #[tokio::test]
+    async fn a_failing_judge_falls_back_to_the_majority() {
+        let consensus = ModelConsensus::new(
+            vec![
+                ("a".to_string(), Arc::new(FixedAI("Use a mutex")) as Arc<dyn AIProviderTrait>),
+                ("b".to_string(), Arc::new(FixedAI("use a mutex")) as Arc<dyn AIProviderTrait>),
+            ],
+            Duration::from_secs(5),
+        )
+        .with_judge(Arc::new(FailingAI));
+
+        let report = consensus.run("How do I share a counter?").await.unwrap();
+        assert!(!report.judged);
+        assert_eq!(report.answer, "Use a mutex");
+    }
+
+    #[test]
+    fn model_specs_keep_colons_in_model_tags() {
+        let parse = |spec: &str| parse_model_spec(spec, "ollama");
+        let pair = |provider: &str, model: &str| (provider.to_string(), model.to_string());
+        assert_eq!(parse("llama3:70b"), pair("ollama", "llama3:70b"));
+        assert_eq!(parse("openai:gpt-4o"), pair("openai", "gpt-4o"));
+        assert_eq!(
+            parse("ollama:qwen2.5-coder:7b"),
+            pair("ollama", "qwen2.5-coder:7b")
+        );
+        assert_eq!(parse("mistral"), pair("ollama", "mistral"));
+    }
+
     #[tokio::test]
     async fn test_consensus_orchestrator() {
         // This test would need proper mock implementations
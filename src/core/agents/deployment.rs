@@ -1,14 +1,32 @@
 //! Deployment agent
 //!
 //! Specialized agent for managing deployments and CI/CD pipelines
+//!
+//! Executing a plan or its rollback runs each step's command in order and
+//! stops at the first failure. Every run is appended to `deploy_history.json`
+//! in the data directory, keyed by the plan file's path, so a rollback can tell
+//! whether its plan was ever executed, and whether it was already rolled back
+//! since.
 
-use crate::core::adapters::ai::KandilAI;
+use crate::core::adapters::ai::{KandilAI, ToolCall, ToolExecutor, ToolSpec};
 use crate::core::agents::base::{Agent, AgentState};
-use anyhow::Result;
+use crate::utils::json_store::JsonStore;
+use anyhow::{Context, Result};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
+
+/// Tool calls the model may make per `assist` run.
+const MAX_TOOL_CALLS: usize = 8;
+/// Characters of command output sent back to the model.
+const MAX_TOOL_OUTPUT_CHARS: usize = 8000;
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(120);
+const HISTORY_FILE: &str = "deploy_history.json";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeploymentPlan {
@@ -45,14 +63,65 @@ pub struct RollbackStep {
     pub description: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeployAction {
+    Execute,
+    Rollback,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Succeeded,
+    Failed,
+    /// Not run because an earlier step failed.
+    Skipped,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DeploymentResult {
-    pub success: bool,
-    pub steps_executed: u32,
-    pub steps_failed: u32,
-    pub duration_seconds: u64,
-    pub logs: Vec<String>,
-    pub artifacts: Vec<String>,
+pub struct StepOutcome {
+    pub id: String,
+    pub name: String,
+    pub command: String,
+    pub status: StepStatus,
+    pub output: String,
+}
+
+/// One execution or rollback of a plan, as kept in the deploy history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeployRecord {
+    /// The plan file, canonicalized.
+    pub plan: String,
+    pub environment: String,
+    pub action: DeployAction,
+    pub started_at: DateTime<Utc>,
+    pub steps: Vec<StepOutcome>,
+}
+
+impl DeployRecord {
+    pub fn succeeded(&self) -> bool {
+        self.steps
+            .iter()
+            .all(|step| step.status == StepStatus::Succeeded)
+    }
+}
+
+/// Whether a plan's history allows rolling it back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollbackReadiness {
+    NeverExecuted,
+    /// A rollback after the latest execution succeeded, at this time.
+    RolledBack(DateTime<Utc>),
+    Ready,
+}
+
+/// A step to run: id, name, command and timeout.
+struct Runnable<'a> {
+    id: &'a str,
+    name: &'a str,
+    command: &'a str,
+    timeout: Duration,
 }
 
 pub struct DeploymentAgent {
@@ -192,24 +261,272 @@ impl DeploymentAgent {
         })
     }
 
-    pub async fn execute_deployment(&self, plan: &DeploymentPlan) -> Result<DeploymentResult> {
-        println!("Starting deployment to {} environment...", plan.environment);
-
-        // In a real implementation, this would execute the actual deployment steps
-        // For simulation, we'll return mock results
-        Ok(DeploymentResult {
-            success: true,
-            steps_executed: 2,
-            steps_failed: 0,
-            duration_seconds: 320,
-            logs: vec![
-                "Backup completed successfully".to_string(),
-                "Deploying application...".to_string(),
-                "Application started successfully".to_string(),
-                "Health checks passed".to_string(),
-            ],
-            artifacts: vec!["deployment-artifact-v1.2.3".to_string()],
+    /// Works on `task` in `environment`, letting the model call
+    /// [`deployment_tools`] through `executor`.
+    pub async fn assist(
+        &self,
+        environment: &str,
+        task: &str,
+        executor: &mut dyn ToolExecutor,
+    ) -> Result<String> {
+        let config = self.environment(environment)?;
+        let prompt = format!(
+            "You are assisting with a deployment to the {} environment ({}).\n\
+             Health check: {}\n\
+             Task: {}\n\n\
+             Use the tools to inspect the environment before changing anything, \
+             and finish with a short summary of what you did and found.",
+            config.name, config.url, config.health_check_url, task
+        );
+        self.ai
+            .run_tools(&prompt, &deployment_tools(), executor, MAX_TOOL_CALLS)
+            .await
+    }
+
+    /// A runner for `environment`'s tool calls that only runs those `approve` allows.
+    pub fn tool_runner<F>(&self, environment: &str, approve: F) -> Result<DeploymentToolRunner<F>>
+    where
+        F: FnMut(&ToolCall) -> bool + Send,
+    {
+        Ok(DeploymentToolRunner {
+            environment: self.environment(environment)?.clone(),
+            approve,
+        })
+    }
+
+    fn environment(&self, environment: &str) -> Result<&EnvironmentConfig> {
+        self.environment_configs
+            .get(environment)
+            .ok_or_else(|| anyhow::anyhow!("Environment {} not found", environment))
+    }
+
+    /// Runs the plan saved at `plan_path`, passing each step's outcome to
+    /// `on_step`, and records the run in the deploy history.
+    pub async fn execute_deployment(
+        &self,
+        plan_path: &Path,
+        on_step: &mut dyn FnMut(&StepOutcome),
+    ) -> Result<DeployRecord> {
+        let plan = load_plan(plan_path)?;
+        let steps = plan.steps.iter().map(|step| Runnable {
+            id: &step.id,
+            name: &step.name,
+            command: &step.command,
+            timeout: Duration::from_secs(step.timeout.max(1)),
+        });
+        let record = DeployRecord {
+            plan: plan_key(plan_path),
+            environment: plan.environment.clone(),
+            action: DeployAction::Execute,
+            started_at: Utc::now(),
+            steps: run_steps(steps, on_step).await,
+        };
+        record_run(&JsonStore::in_data_dir(HISTORY_FILE), &record)?;
+        Ok(record)
+    }
+
+    /// Runs the rollback steps of the plan saved at `plan_path` and records
+    /// the run next to its executions. Fails unless the plan was executed and
+    /// hasn't been rolled back since.
+    pub async fn rollback(
+        &self,
+        plan_path: &Path,
+        on_step: &mut dyn FnMut(&StepOutcome),
+    ) -> Result<DeployRecord> {
+        let plan = load_plan(plan_path)?;
+        match rollback_readiness(&plan_history(plan_path)?) {
+            RollbackReadiness::Ready => {}
+            RollbackReadiness::NeverExecuted => {
+                anyhow::bail!("{} was never executed", plan_path.display())
+            }
+            RollbackReadiness::RolledBack(at) => anyhow::bail!(
+                "{} was already rolled back at {}",
+                plan_path.display(),
+                at.to_rfc3339()
+            ),
+        }
+        let steps = plan.rollback_plan.steps.iter().map(|step| Runnable {
+            id: &step.id,
+            name: &step.action,
+            command: &step.command,
+            timeout: COMMAND_TIMEOUT,
+        });
+        let record = DeployRecord {
+            plan: plan_key(plan_path),
+            environment: plan.environment.clone(),
+            action: DeployAction::Rollback,
+            started_at: Utc::now(),
+            steps: run_steps(steps, on_step).await,
+        };
+        record_run(&JsonStore::in_data_dir(HISTORY_FILE), &record)?;
+        Ok(record)
+    }
+}
+
+/// Reads a plan written by `deploy plan --output`.
+pub fn load_plan(path: &Path) -> Result<DeploymentPlan> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read deployment plan {}", path.display()))?;
+    serde_json::from_str(&text)
+        .with_context(|| format!("{} is not a deployment plan", path.display()))
+}
+
+/// The deploy history's key for a plan file.
+pub fn plan_key(path: &Path) -> String {
+    std::fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .display()
+        .to_string()
+}
+
+/// Recorded runs of the plan at `plan_path`, oldest first.
+pub fn plan_history(plan_path: &Path) -> Result<Vec<DeployRecord>> {
+    history_for(&JsonStore::in_data_dir(HISTORY_FILE), &plan_key(plan_path))
+}
+
+/// Whether `records`, a plan's history oldest first, allow a rollback. A
+/// failed rollback can be retried; a successful one needs a new execution.
+pub fn rollback_readiness(records: &[DeployRecord]) -> RollbackReadiness {
+    let Some(executed) = records
+        .iter()
+        .rposition(|record| record.action == DeployAction::Execute)
+    else {
+        return RollbackReadiness::NeverExecuted;
+    };
+    records[executed + 1..]
+        .iter()
+        .find(|record| record.action == DeployAction::Rollback && record.succeeded())
+        .map_or(RollbackReadiness::Ready, |record| {
+            RollbackReadiness::RolledBack(record.started_at)
         })
+}
+
+fn history_for(store: &JsonStore, key: &str) -> Result<Vec<DeployRecord>> {
+    let mut records: Vec<DeployRecord> = store.load()?;
+    records.retain(|record| record.plan == key);
+    Ok(records)
+}
+
+fn record_run(store: &JsonStore, record: &DeployRecord) -> Result<()> {
+    store.update(|records: &mut Vec<DeployRecord>| records.push(record.clone()))
+}
+
+/// Runs `steps` in order; after a failure the rest are skipped.
+async fn run_steps(
+    steps: impl Iterator<Item = Runnable<'_>>,
+    on_step: &mut dyn FnMut(&StepOutcome),
+) -> Vec<StepOutcome> {
+    let mut outcomes: Vec<StepOutcome> = Vec::new();
+    for step in steps {
+        let failed = outcomes
+            .iter()
+            .any(|outcome| outcome.status == StepStatus::Failed);
+        let (status, output) = if failed {
+            (StepStatus::Skipped, String::new())
+        } else {
+            match run_command(step.command, step.timeout).await {
+                Ok(output) => (StepStatus::Succeeded, output),
+                Err(output) => (StepStatus::Failed, output),
+            }
+        };
+        let outcome = StepOutcome {
+            id: step.id.to_string(),
+            name: step.name.to_string(),
+            command: step.command.to_string(),
+            status,
+            output,
+        };
+        on_step(&outcome);
+        outcomes.push(outcome);
+    }
+    outcomes
+}
+
+/// Tools offered to the model by [`DeploymentAgent::assist`].
+pub fn deployment_tools() -> Vec<ToolSpec> {
+    vec![
+        ToolSpec::new(
+            "run_command",
+            "Run a shell command on the operator's machine, e.g. kubectl or docker, and return its output",
+            json!({
+                "type": "object",
+                "properties": {
+                    "command": { "type": "string", "description": "The command line to run" }
+                },
+                "required": ["command"],
+            }),
+        ),
+        ToolSpec::new(
+            "check_health",
+            "Request the environment's health check URL and return the HTTP status",
+            json!({ "type": "object", "properties": {} }),
+        ),
+    ]
+}
+
+/// Runs deployment tool calls once the operator approves them.
+pub struct DeploymentToolRunner<F> {
+    environment: EnvironmentConfig,
+    approve: F,
+}
+
+#[async_trait]
+impl<F> ToolExecutor for DeploymentToolRunner<F>
+where
+    F: FnMut(&ToolCall) -> bool + Send,
+{
+    async fn execute(&mut self, call: &ToolCall) -> Result<String, String> {
+        if !(self.approve)(call) {
+            return Err("The operator declined this tool call".to_string());
+        }
+        match call.name.as_str() {
+            "run_command" => {
+                let command = call.arguments["command"]
+                    .as_str()
+                    .ok_or("run_command needs a 'command' string")?;
+                run_command(command, COMMAND_TIMEOUT).await
+            }
+            "check_health" => {
+                let url = &self.environment.health_check_url;
+                let response = crate::core::adapters::ai::http::shared_client()
+                    .get(url)
+                    .timeout(Duration::from_secs(15))
+                    .send()
+                    .await
+                    .map_err(|err| format!("{} is unreachable: {}", url, err))?;
+                Ok(format!("{} returned HTTP {}", url, response.status()))
+            }
+            other => Err(format!("Unknown tool '{}'", other)),
+        }
+    }
+}
+
+async fn run_command(command: &str, timeout: Duration) -> Result<String, String> {
+    let mut process = if cfg!(windows) {
+        let mut process = tokio::process::Command::new("cmd");
+        process.arg("/C").arg(command);
+        process
+    } else {
+        let mut process = tokio::process::Command::new("sh");
+        process.arg("-c").arg(command);
+        process
+    };
+    process.kill_on_drop(true);
+    let output = tokio::time::timeout(timeout, process.output())
+        .await
+        .map_err(|_| format!("Timed out after {}s", timeout.as_secs()))?
+        .map_err(|err| format!("Failed to run: {}", err))?;
+
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    if text.chars().count() > MAX_TOOL_OUTPUT_CHARS {
+        text = text.chars().take(MAX_TOOL_OUTPUT_CHARS).collect();
+        text.push_str("\n[output truncated]");
+    }
+    if output.status.success() {
+        Ok(text)
+    } else {
+        Err(format!("Exited with {}:\n{}", output.status, text))
     }
 }
 
@@ -244,3 +561,97 @@ impl Agent for DeploymentAgent {
         self.ai.chat(&prompt).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn steps_after_a_failure_are_skipped() {
+        let steps = [
+            ("one", "echo first"),
+            ("two", "exit 3"),
+            ("three", "echo never"),
+        ];
+        let runnable = steps.iter().map(|(id, command)| Runnable {
+            id,
+            name: id,
+            command,
+            timeout: Duration::from_secs(10),
+        });
+        let mut seen = Vec::new();
+        let outcomes = run_steps(runnable, &mut |outcome| seen.push(outcome.id.clone())).await;
+
+        let statuses: Vec<StepStatus> = outcomes.iter().map(|outcome| outcome.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                StepStatus::Succeeded,
+                StepStatus::Failed,
+                StepStatus::Skipped
+            ]
+        );
+        assert!(outcomes[0].output.contains("first"));
+        assert_eq!(seen, ["one", "two", "three"]);
+    }
+
+    #[test]
+    fn history_is_kept_per_plan() {
+        let dir = std::env::temp_dir().join(format!("kandil_deploy_{}", uuid::Uuid::new_v4()));
+        let store = JsonStore::new(dir.join(HISTORY_FILE));
+        let record = |plan: &str, action| DeployRecord {
+            plan: plan.to_string(),
+            environment: "staging".to_string(),
+            action,
+            started_at: Utc::now(),
+            steps: Vec::new(),
+        };
+        assert!(history_for(&store, "/plans/a.json").unwrap().is_empty());
+
+        record_run(&store, &record("/plans/a.json", DeployAction::Execute)).unwrap();
+        record_run(&store, &record("/plans/b.json", DeployAction::Execute)).unwrap();
+        record_run(&store, &record("/plans/a.json", DeployAction::Rollback)).unwrap();
+        let actions: Vec<DeployAction> = history_for(&store, "/plans/a.json")
+            .unwrap()
+            .iter()
+            .map(|record| record.action)
+            .collect();
+        assert_eq!(actions, [DeployAction::Execute, DeployAction::Rollback]);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn only_the_latest_execution_can_be_rolled_back_once() {
+        let record = |action, status| DeployRecord {
+            plan: "/plans/a.json".to_string(),
+            environment: "staging".to_string(),
+            action,
+            started_at: Utc::now(),
+            steps: vec![StepOutcome {
+                id: "one".to_string(),
+                name: "One".to_string(),
+                command: "true".to_string(),
+                status,
+                output: String::new(),
+            }],
+        };
+        let executed = record(DeployAction::Execute, StepStatus::Succeeded);
+        let failed = record(DeployAction::Rollback, StepStatus::Failed);
+        let rolled_back = record(DeployAction::Rollback, StepStatus::Succeeded);
+
+        assert_eq!(rollback_readiness(&[]), RollbackReadiness::NeverExecuted);
+        assert_eq!(
+            rollback_readiness(&[executed.clone(), failed.clone()]),
+            RollbackReadiness::Ready
+        );
+        assert_eq!(
+            rollback_readiness(&[executed.clone(), failed, rolled_back.clone()]),
+            RollbackReadiness::RolledBack(rolled_back.started_at)
+        );
+        assert_eq!(
+            rollback_readiness(&[executed.clone(), rolled_back, executed]),
+            RollbackReadiness::Ready
+        );
+    }
+}
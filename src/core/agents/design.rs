@@ -4,21 +4,170 @@
 
 use crate::core::adapters::ai::KandilAI;
 use crate::core::agents::base::{Agent, AgentState, ReActLoop};
+use crate::utils::prompt_templates::{PromptData, PromptTemplates};
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DesignDocument {
     pub architecture: ArchitectureStyle,
+    #[serde(default)]
     pub components: Vec<Component>,
+    #[serde(default)]
     pub data_flow: Vec<DataFlow>,
     pub technology_stack: TechnologyStack,
+    #[serde(default)]
     pub design_patterns: Vec<DesignPattern>,
+    #[serde(default)]
     pub diagrams: Vec<Diagram>,
 }
 
+/// A unit variant name, or `{"Other": "..."}` for anything else.
+fn named_or_other(names: &[&str]) -> Value {
+    json!({
+        "oneOf": [
+            { "enum": names },
+            {
+                "type": "object",
+                "properties": { "Other": { "type": "string" } },
+                "required": ["Other"],
+            },
+        ],
+    })
+}
+
+impl DesignDocument {
+    /// JSON Schema the design agent asks the model to fill in.
+    pub fn schema() -> Value {
+        let strings = json!({ "type": "array", "items": { "type": "string" } });
+        let method = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "parameters": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "parameter_type": { "type": "string" },
+                        },
+                        "required": ["name", "parameter_type"],
+                    },
+                },
+                "return_type": { "type": "string" },
+            },
+            "required": ["name", "parameters", "return_type"],
+        });
+        let component = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "description": { "type": "string" },
+                "responsibilities": strings,
+                "interfaces": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "methods": { "type": "array", "items": method },
+                        },
+                        "required": ["name", "methods"],
+                    },
+                },
+                "dependencies": strings,
+            },
+            "required": [
+                "name",
+                "description",
+                "responsibilities",
+                "interfaces",
+                "dependencies",
+            ],
+        });
+        json!({
+            "type": "object",
+            "properties": {
+                "architecture": named_or_other(&[
+                    "CleanArchitecture",
+                    "Hexagonal",
+                    "Layered",
+                    "Microservices",
+                    "EventDriven",
+                ]),
+                "components": { "type": "array", "items": component },
+                "data_flow": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "source": { "type": "string" },
+                            "destination": { "type": "string" },
+                            "data_type": { "type": "string" },
+                            "description": { "type": "string" },
+                        },
+                        "required": ["source", "destination", "data_type", "description"],
+                    },
+                },
+                "technology_stack": {
+                    "type": "object",
+                    "properties": {
+                        "frontend": strings,
+                        "backend": strings,
+                        "database": strings,
+                        "infrastructure": strings,
+                    },
+                    "required": ["frontend", "backend", "database", "infrastructure"],
+                },
+                "design_patterns": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "description": { "type": "string" },
+                            "use_cases": strings,
+                            "implementation_notes": { "type": "string" },
+                        },
+                        "required": ["name", "description", "use_cases", "implementation_notes"],
+                    },
+                },
+                "diagrams": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "title": { "type": "string" },
+                            "description": { "type": "string" },
+                            "content": { "type": "string" },
+                            "diagram_type": named_or_other(&[
+                                "Component",
+                                "Sequence",
+                                "Class",
+                                "Deployment",
+                                "Flowchart",
+                            ]),
+                        },
+                        "required": ["title", "description", "content", "diagram_type"],
+                    },
+                },
+            },
+            "required": [
+                "architecture",
+                "components",
+                "data_flow",
+                "technology_stack",
+                "design_patterns",
+                "diagrams",
+            ],
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ArchitectureStyle {
     CleanArchitecture,
@@ -101,37 +250,31 @@ pub enum DiagramType {
 
 pub struct DesignAgent {
     ai: Arc<KandilAI>,
+    prompts: PromptTemplates,
 }
 
 impl DesignAgent {
     pub fn new(ai: Arc<KandilAI>) -> Self {
-        Self { ai }
+        Self {
+            ai,
+            prompts: PromptTemplates::user_default(),
+        }
     }
 
     pub async fn generate_design_document(&self, requirements_doc: &str) -> Result<DesignDocument> {
         let loop_engine = ReActLoop::new(5);
-        let task = format!(
-            "As a Software Architect, design a system based on these requirements: {}.\n\nCreate a comprehensive design document covering architecture, components, data flow, technology stack, design patterns, and diagrams.",
-            requirements_doc
-        );
+        let task = self
+            .prompts
+            .render("design", &PromptData::new(requirements_doc))?;
 
         let result = loop_engine.run(self, &task).await?;
 
-        // For now, we'll create a basic document structure from the AI response
-        // In a real implementation, we would properly parse the structured response
-        Ok(DesignDocument {
-            architecture: ArchitectureStyle::CleanArchitecture,
-            components: vec![],
-            data_flow: vec![],
-            technology_stack: TechnologyStack {
-                frontend: vec![],
-                backend: vec![],
-                database: vec![],
-                infrastructure: vec![],
-            },
-            design_patterns: vec![],
-            diagrams: vec![],
-        })
+        // Turn the design notes into the structured document
+        let prompt = format!(
+            "{}\n\nFindings from the design session:\n{}\n\nWrite the complete design document.",
+            task, result.final_answer
+        );
+        self.ai.chat_json(&prompt, &DesignDocument::schema()).await
     }
 }
 
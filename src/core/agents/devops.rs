@@ -1,12 +1,19 @@
 //! DevOps simulation agent
 //!
 //! Agent that simulates DevOps activities including IaC generation and incident response
+//!
+//! CI/CD pipelines can target a [`CiProvider`]; the reply is then unwrapped
+//! from any Markdown fence and checked with [`validate_pipeline`] so it can be
+//! written straight to the provider's conventional path. Generated Terraform
+//! can be scoped to a [`CloudProvider`] and checked with [`validate_terraform`]
+//! when the `terraform` binary is installed.
 
 use crate::core::adapters::ai::KandilAI;
 use crate::core::agents::base::{Agent, AgentState};
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 use std::process::Command;
 use std::sync::Arc;
 
@@ -16,6 +23,243 @@ pub struct DevOpsSimulation {
     pub infra_templates: std::collections::HashMap<String, String>,
 }
 
+/// CI systems a pipeline can be generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiProvider {
+    GitHub,
+    GitLab,
+    CircleCi,
+    Jenkins,
+}
+
+impl CiProvider {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CiProvider::GitHub => "GitHub Actions",
+            CiProvider::GitLab => "GitLab CI/CD",
+            CiProvider::CircleCi => "CircleCI",
+            CiProvider::Jenkins => "Jenkins",
+        }
+    }
+
+    /// Where the provider looks for the pipeline, relative to the repo root.
+    pub fn path(&self) -> &'static str {
+        match self {
+            CiProvider::GitHub => ".github/workflows/ci.yml",
+            CiProvider::GitLab => ".gitlab-ci.yml",
+            CiProvider::CircleCi => ".circleci/config.yml",
+            CiProvider::Jenkins => "Jenkinsfile",
+        }
+    }
+
+    fn syntax(&self) -> &'static str {
+        match self {
+            CiProvider::GitHub => "a GitHub Actions workflow in YAML with `name`, `on` and `jobs`",
+            CiProvider::GitLab => "a `.gitlab-ci.yml` in YAML with `stages` and one key per job",
+            CiProvider::CircleCi => {
+                "a CircleCI `config.yml` in YAML with `version: 2.1`, `jobs` and `workflows`"
+            }
+            CiProvider::Jenkins => "a declarative Jenkinsfile starting with `pipeline {`",
+        }
+    }
+}
+
+impl std::str::FromStr for CiProvider {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "github" => Ok(CiProvider::GitHub),
+            "gitlab" => Ok(CiProvider::GitLab),
+            "circleci" => Ok(CiProvider::CircleCi),
+            "jenkins" => Ok(CiProvider::Jenkins),
+            other => anyhow::bail!(
+                "Unknown CI provider '{}'. Expected one of: github, gitlab, circleci, jenkins",
+                other
+            ),
+        }
+    }
+}
+
+/// Clouds generated Terraform can be limited to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloudProvider {
+    Aws,
+    Gcp,
+    Azure,
+}
+
+impl CloudProvider {
+    /// The Terraform provider that manages this cloud's resources.
+    pub fn terraform_provider(&self) -> &'static str {
+        match self {
+            CloudProvider::Aws => "hashicorp/aws",
+            CloudProvider::Gcp => "hashicorp/google",
+            CloudProvider::Azure => "hashicorp/azurerm",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            CloudProvider::Aws => "AWS",
+            CloudProvider::Gcp => "Google Cloud",
+            CloudProvider::Azure => "Azure",
+        }
+    }
+}
+
+impl std::str::FromStr for CloudProvider {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "aws" => Ok(CloudProvider::Aws),
+            "gcp" => Ok(CloudProvider::Gcp),
+            "azure" => Ok(CloudProvider::Azure),
+            other => anyhow::bail!(
+                "Unknown cloud provider '{}'. Expected one of: aws, gcp, azure",
+                other
+            ),
+        }
+    }
+}
+
+/// One `terraform` command run by [`validate_terraform`].
+#[derive(Debug, Clone)]
+pub struct TerraformCheck {
+    pub command: String,
+    pub passed: bool,
+    /// Combined stdout and stderr.
+    pub output: String,
+}
+
+/// Runs `terraform fmt -check`, then `terraform init -backend=false` and
+/// `terraform validate` on the Terraform files in `dir`. `validate` needs the
+/// providers that `init` downloads, so it is skipped when `init` fails.
+/// Returns `None` when terraform isn't installed.
+///
+/// The checks run on a temporary copy of the files, so the `.terraform/`
+/// directory and lock file that `init` writes never land in `dir`.
+pub fn validate_terraform(dir: &Path) -> Result<Option<Vec<TerraformCheck>>> {
+    let scratch = std::env::temp_dir().join(format!("kandil_tf_{}", uuid::Uuid::new_v4()));
+    let checks =
+        copy_terraform_files(dir, &scratch).and_then(|()| terraform_checks("terraform", &scratch));
+    let _ = std::fs::remove_dir_all(&scratch);
+    checks
+}
+
+/// Copies the top-level `.tf`, `.tf.json` and `.tfvars` files of `from` into
+/// a new directory `to`.
+fn copy_terraform_files(from: &Path, to: &Path) -> Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let is_terraform = [".tf", ".tf.json", ".tfvars", ".tfvars.json"]
+            .iter()
+            .any(|ext| name.ends_with(ext));
+        if is_terraform && entry.file_type()?.is_file() {
+            std::fs::copy(entry.path(), to.join(&*name))?;
+        }
+    }
+    Ok(())
+}
+
+fn terraform_checks(binary: &str, dir: &Path) -> Result<Option<Vec<TerraformCheck>>> {
+    let steps: [&[&str]; 3] = [
+        &["fmt", "-check", "-diff", "-no-color"],
+        &["init", "-backend=false", "-input=false", "-no-color"],
+        &["validate", "-no-color"],
+    ];
+    let mut checks: Vec<TerraformCheck> = Vec::new();
+    for args in steps {
+        if args[0] == "validate" && checks.last().is_some_and(|init| !init.passed) {
+            break;
+        }
+        let output = match Command::new(binary).args(args).current_dir(dir).output() {
+            Ok(output) => output,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        checks.push(TerraformCheck {
+            command: format!("terraform {}", args.join(" ")),
+            passed: output.status.success(),
+            output: format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .trim()
+            .to_string(),
+        });
+    }
+    Ok(Some(checks))
+}
+
+/// The code inside a reply: the first fenced block if there is one,
+/// otherwise the whole reply.
+pub fn extract_code_block(reply: &str) -> &str {
+    let trimmed = reply.trim();
+    let Some(start) = trimmed.find("```") else {
+        return trimmed;
+    };
+    let body = &trimmed[start + 3..];
+    // Skip the language tag on the opening fence
+    let body = body.find('\n').map_or("", |newline| &body[newline + 1..]);
+    match body.find("```") {
+        Some(end) => body[..end].trim_end(),
+        None => body.trim_end(),
+    }
+}
+
+/// Checks that `pipeline` is well-formed for `provider`: YAML providers must
+/// parse to a mapping with the keys they require, and a Jenkinsfile must be a
+/// declarative pipeline with balanced braces.
+pub fn validate_pipeline(provider: CiProvider, pipeline: &str) -> Result<()> {
+    let required: &[&str] = match provider {
+        CiProvider::Jenkins => {
+            let opens = pipeline.matches('{').count();
+            if !pipeline.contains("pipeline")
+                || opens == 0
+                || opens != pipeline.matches('}').count()
+            {
+                anyhow::bail!("The generated Jenkinsfile is not a declarative pipeline");
+            }
+            return Ok(());
+        }
+        CiProvider::GitHub => &["on", "jobs"],
+        CiProvider::GitLab => &[],
+        CiProvider::CircleCi => &["version", "jobs"],
+    };
+    let parsed: serde_yaml::Value = serde_yaml::from_str(pipeline).map_err(|err| {
+        anyhow::anyhow!(
+            "The generated {} pipeline is not valid YAML: {}",
+            provider.name(),
+            err
+        )
+    })?;
+    let Some(mapping) = parsed.as_mapping().filter(|mapping| !mapping.is_empty()) else {
+        anyhow::bail!(
+            "The generated {} pipeline is not a YAML mapping",
+            provider.name()
+        );
+    };
+    let missing: Vec<&str> = required
+        .iter()
+        .copied()
+        .filter(|key| !mapping.contains_key(*key))
+        .collect();
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "The generated {} pipeline has no `{}`",
+            provider.name(),
+            missing.join("`, `")
+        );
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DrillReport {
     pub scenario: String,
@@ -33,12 +277,26 @@ impl DevOpsSimulation {
         }
     }
 
-    pub async fn generate_terraform(&self, infra_spec: &str) -> Result<std::path::PathBuf> {
+    /// Writes Terraform for `infra_spec` to `infra/main.tf`, using only
+    /// `cloud`'s resources when one is given.
+    pub async fn generate_terraform(
+        &self,
+        infra_spec: &str,
+        cloud: Option<CloudProvider>,
+    ) -> Result<std::path::PathBuf> {
+        let scope = match cloud {
+            Some(cloud) => format!(
+                "- Target {} only: declare the {} provider in `required_providers` and use no other cloud's resources\n",
+                cloud.name(),
+                cloud.terraform_provider()
+            ),
+            None => String::new(),
+        };
         let prompt = format!(
             r#"Generate Terraform for: {}
 
             Requirements:
-            - Use modules from registry
+            {}- Use modules from registry
             - Add security groups (least privilege)
             - Enable encryption at rest
             - Tag resources per company policy
@@ -46,7 +304,7 @@ impl DevOpsSimulation {
 
             Respond with only HCL code.
             "#,
-            infra_spec
+            infra_spec, scope
         );
 
         let tf_code = self.ai.chat(&prompt).await?;
@@ -54,17 +312,7 @@ impl DevOpsSimulation {
         // Write Terraform code to file
         let path = std::path::PathBuf::from("infra/main.tf");
         std::fs::create_dir_all("infra")?;
-        std::fs::write(&path, tf_code)?;
-
-        // Validate with terraform fmt (if available)
-        if let Ok(_) = Command::new("terraform")
-            .args(&["fmt", "-check", "infra"])
-            .status()
-        {
-            // Validation successful
-        } else {
-            println!("Warning: Terraform not found, skipping validation");
-        }
+        std::fs::write(&path, format!("{}\n", extract_code_block(&tf_code)))?;
 
         Ok(path)
     }
@@ -121,7 +369,21 @@ impl DevOpsSimulation {
         })
     }
 
-    pub async fn generate_ci_cd_pipeline(&self, project_type: &str) -> Result<String> {
+    /// A pipeline for `project_type`. With a `provider` the reply is that
+    /// provider's file, unfenced and validated; without one it is free-form.
+    pub async fn generate_ci_cd_pipeline(
+        &self,
+        project_type: &str,
+        provider: Option<CiProvider>,
+    ) -> Result<String> {
+        let format = match provider {
+            Some(provider) => format!(
+                "Output only {}, without Markdown or commentary",
+                provider.syntax()
+            ),
+            None => "Output in appropriate format (GitHub Actions, GitLab CI, Jenkinsfile, etc.)"
+                .to_string(),
+        };
         let prompt = format!(
             r#"Generate a CI/CD pipeline for a {} project.
 
@@ -136,12 +398,18 @@ impl DevOpsSimulation {
             - Post-deployment validation
             - Notifications
 
-            Output in appropriate format (GitHub Actions, GitLab CI, Jenkinsfile, etc.)
+            {}
             "#,
-            project_type
+            project_type, format
         );
 
-        self.ai.chat(&prompt).await
+        let reply = self.ai.chat(&prompt).await?;
+        let Some(provider) = provider else {
+            return Ok(reply);
+        };
+        let pipeline = extract_code_block(&reply);
+        validate_pipeline(provider, pipeline)?;
+        Ok(format!("{}\n", pipeline))
     }
 
     pub async fn create_monitoring_dashboard(&self, services: &[String]) -> Result<String> {
@@ -195,3 +463,62 @@ impl Agent for DevOpsSimulation {
         self.ai.chat(&prompt).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pipelines_are_unfenced_and_validated_per_provider() {
+        let reply = "Here you go:\n```yaml\nname: CI\non: [push]\njobs:\n  test:\n    runs-on: ubuntu-latest\n```\nEnjoy!";
+        let pipeline = extract_code_block(reply);
+        assert!(pipeline.starts_with("name: CI") && pipeline.ends_with("ubuntu-latest"));
+        validate_pipeline(CiProvider::GitHub, pipeline).unwrap();
+
+        let err = validate_pipeline(CiProvider::CircleCi, pipeline).unwrap_err();
+        assert!(err.to_string().contains("no `version`"), "{}", err);
+        let err = validate_pipeline(CiProvider::GitLab, "stages: [build\n").unwrap_err();
+        assert!(err.to_string().contains("not valid YAML"));
+        assert!(validate_pipeline(CiProvider::GitLab, "just text").is_err());
+
+        let jenkins = "pipeline {\n  agent any\n  stages { }\n}";
+        assert_eq!(extract_code_block(jenkins), jenkins);
+        validate_pipeline(CiProvider::Jenkins, jenkins).unwrap();
+        assert!(validate_pipeline(CiProvider::Jenkins, "pipeline {").is_err());
+
+        assert_eq!(
+            "GitLab".parse::<CiProvider>().unwrap().path(),
+            ".gitlab-ci.yml"
+        );
+        assert!("travis".parse::<CiProvider>().is_err());
+    }
+
+    #[test]
+    fn terraform_checks_report_a_missing_binary() {
+        let checks = terraform_checks("kandil-no-such-terraform", Path::new(".")).unwrap();
+        assert!(checks.is_none());
+        let cloud: CloudProvider = "GCP".parse().unwrap();
+        assert_eq!(cloud.terraform_provider(), "hashicorp/google");
+        assert!("ibm".parse::<CloudProvider>().is_err());
+    }
+
+    #[test]
+    fn terraform_files_are_copied_without_state() {
+        let root = std::env::temp_dir().join(format!("kandil_tf_test_{}", uuid::Uuid::new_v4()));
+        let infra = root.join("infra");
+        std::fs::create_dir_all(infra.join(".terraform")).unwrap();
+        std::fs::write(infra.join("main.tf"), "terraform {}\n").unwrap();
+        std::fs::write(infra.join("prod.tfvars"), "region = \"x\"\n").unwrap();
+        std::fs::write(infra.join("notes.md"), "# notes\n").unwrap();
+
+        let copy = root.join("copy");
+        copy_terraform_files(&infra, &copy).unwrap();
+        let mut names: Vec<String> = std::fs::read_dir(&copy)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["main.tf", "prod.tfvars"]);
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -122,6 +122,24 @@ impl DocumentationGenerator {
         ))
     }
 
+    /// Asks the AI to rewrite one stale documentation line.
+    ///
+    /// `problems` describes what is out of date; the reply is the corrected
+    /// line, keeping any comment prefix such as `///`.
+    pub async fn propose_doc_fix(&self, line: &str, problems: &[String]) -> Result<String> {
+        let prompt = format!(
+            "This documentation line is out of date with the code:\n{}\n\nProblems:\n- {}\n\nRewrite the line so it is accurate. Keep its comment prefix and indentation. Reply with only the corrected line.",
+            line,
+            problems.join("\n- ")
+        );
+        let reply = self.ai.chat(&prompt).await?;
+        Ok(reply
+            .lines()
+            .find(|l| !l.trim().is_empty() && !l.trim_start().starts_with("```"))
+            .unwrap_or(line)
+            .to_string())
+    }
+
     pub fn add_section(&mut self, section: DocumentationSection) {
         self.content_sections.push(section);
     }
@@ -87,6 +87,95 @@ pub enum ImpactLevel {
     VeryHigh,
 }
 
+/// axe-core impact levels, ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AxeImpact {
+    Minor,
+    Moderate,
+    Serious,
+    Critical,
+}
+
+impl From<&Severity> for AxeImpact {
+    fn from(severity: &Severity) -> Self {
+        match severity {
+            Severity::Low => AxeImpact::Minor,
+            Severity::Medium => AxeImpact::Moderate,
+            Severity::High => AxeImpact::Serious,
+            Severity::Critical => AxeImpact::Critical,
+        }
+    }
+}
+
+impl std::str::FromStr for AxeImpact {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "minor" => Ok(AxeImpact::Minor),
+            "moderate" => Ok(AxeImpact::Moderate),
+            "serious" => Ok(AxeImpact::Serious),
+            "critical" => Ok(AxeImpact::Critical),
+            other => anyhow::bail!(
+                "Unknown impact '{}'. Expected one of: minor, moderate, serious, critical",
+                other
+            ),
+        }
+    }
+}
+
+/// Audit results in the shape of axe-core's `axe.run()` output, so CI and
+/// dashboards that already ingest axe results can read them unchanged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AxeReport {
+    pub test_engine: AxeTestEngine,
+    pub timestamp: String,
+    pub url: String,
+    pub violations: Vec<AxeViolation>,
+    pub passes: Vec<AxeViolation>,
+    pub incomplete: Vec<AxeViolation>,
+    pub inapplicable: Vec<AxeViolation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AxeTestEngine {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AxeViolation {
+    pub id: String,
+    pub impact: AxeImpact,
+    pub tags: Vec<String>,
+    pub description: String,
+    pub help: String,
+    pub help_url: String,
+    pub nodes: Vec<AxeNode>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AxeNode {
+    pub html: String,
+    pub target: Vec<String>,
+    pub impact: AxeImpact,
+    pub failure_summary: String,
+}
+
+impl AxeReport {
+    /// Violations whose impact is at or above `threshold`.
+    pub fn violations_at_or_above(&self, threshold: AxeImpact) -> Vec<&AxeViolation> {
+        self.violations
+            .iter()
+            .filter(|violation| violation.impact >= threshold)
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RtlSupportAssistant {
     ai: Arc<KandilAI>,
@@ -270,6 +359,90 @@ impl EnhancedA11yAssistant {
     }
 }
 
+impl WcagReport {
+    /// Converts the report to axe-core's result shape.
+    ///
+    /// Issues sharing a WCAG success criterion become nodes of one violation,
+    /// mirroring how axe groups failing elements under a single rule.
+    /// `standards` maps criterion ids to their titles and is used for the help
+    /// text and links (see [`EnhancedA11yAssistant::wcag_standards`]).
+    pub fn to_axe(&self, standards: &HashMap<String, String>, url: &str) -> AxeReport {
+        let mut violations: Vec<AxeViolation> = Vec::new();
+        let issues = self
+            .level_a_issues
+            .iter()
+            .chain(&self.level_aa_issues)
+            .chain(&self.level_aaa_issues);
+
+        for issue in issues {
+            let impact = AxeImpact::from(&issue.severity);
+            let node = AxeNode {
+                html: issue
+                    .code_example
+                    .clone()
+                    .unwrap_or_else(|| format!("<{}>", issue.element)),
+                target: vec![match &issue.location {
+                    Some(location) => format!("{} ({})", issue.element, location),
+                    None => issue.element.clone(),
+                }],
+                impact,
+                failure_summary: format!("Fix the following:\n  {}", issue.remediation),
+            };
+
+            let id = format!("wcag-{}", issue.guideline_id.replace('.', "-"));
+            if let Some(existing) = violations.iter_mut().find(|v| v.id == id) {
+                existing.impact = existing.impact.max(impact);
+                existing.nodes.push(node);
+                continue;
+            }
+
+            let title = standards
+                .get(&issue.guideline_id)
+                .and_then(|text| text.split(':').next())
+                .map(str::trim);
+            violations.push(AxeViolation {
+                id,
+                impact,
+                tags: vec![
+                    match issue.level {
+                        WcagLevel::A => "wcag2a",
+                        WcagLevel::AA => "wcag2aa",
+                        WcagLevel::AAA => "wcag2aaa",
+                    }
+                    .to_string(),
+                    format!("wcag{}", issue.guideline_id.replace('.', "")),
+                ],
+                description: issue.description.clone(),
+                help: title
+                    .map(|title| format!("WCAG {} {}", issue.guideline_id, title))
+                    .unwrap_or_else(|| format!("WCAG {}", issue.guideline_id)),
+                help_url: title
+                    .map(|title| {
+                        format!(
+                            "https://www.w3.org/WAI/WCAG21/Understanding/{}.html",
+                            title.to_lowercase().replace(' ', "-")
+                        )
+                    })
+                    .unwrap_or_else(|| "https://www.w3.org/WAI/WCAG21/quickref/".to_string()),
+                nodes: vec![node],
+            });
+        }
+
+        AxeReport {
+            test_engine: AxeTestEngine {
+                name: "kandil-a11y".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            url: url.to_string(),
+            violations,
+            passes: Vec::new(),
+            incomplete: Vec::new(),
+            inapplicable: Vec::new(),
+        }
+    }
+}
+
 impl RtlSupportAssistant {
     pub fn new(ai: Arc<KandilAI>) -> Self {
         Self {
@@ -428,3 +601,63 @@ impl Agent for EnhancedA11yAssistant {
         self.ai.chat(&prompt).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(guideline: &str, level: WcagLevel, severity: Severity) -> WcagIssue {
+        WcagIssue {
+            id: format!("A11Y-{}", guideline),
+            level,
+            guideline_id: guideline.to_string(),
+            description: "Problem".to_string(),
+            element: "img".to_string(),
+            severity,
+            location: None,
+            remediation: "Fix it".to_string(),
+            code_example: None,
+        }
+    }
+
+    #[test]
+    fn axe_report_groups_by_criterion_and_filters_by_impact() {
+        let report = WcagReport {
+            level_a_issues: vec![
+                issue("1.1.1", WcagLevel::A, Severity::Medium),
+                issue("1.1.1", WcagLevel::A, Severity::High),
+            ],
+            level_aa_issues: vec![issue("1.4.3", WcagLevel::AA, Severity::Low)],
+            level_aaa_issues: vec![],
+            compliance_summary: ComplianceSummary {
+                level_a_compliance: 0.0,
+                level_aa_compliance: 0.0,
+                level_aaa_compliance: 0.0,
+                overall_score: 0,
+            },
+            recommendations: vec![],
+        };
+        let mut standards = HashMap::new();
+        standards.insert(
+            "1.1.1".to_string(),
+            "Non-text Content: Provide text alternative".to_string(),
+        );
+
+        let axe = report.to_axe(&standards, "index.html");
+        assert_eq!(axe.violations.len(), 2);
+        let alt = &axe.violations[0];
+        assert_eq!(alt.id, "wcag-1-1-1");
+        assert_eq!(alt.impact, AxeImpact::Serious);
+        assert_eq!(alt.nodes.len(), 2);
+        assert_eq!(alt.tags, vec!["wcag2a", "wcag111"]);
+        assert!(alt.help_url.ends_with("/non-text-content.html"));
+
+        let json = serde_json::to_value(&axe).unwrap();
+        assert_eq!(json["violations"][0]["impact"], "serious");
+        assert!(json["violations"][0]["helpUrl"].is_string());
+
+        assert_eq!(axe.violations_at_or_above(AxeImpact::Serious).len(), 1);
+        assert_eq!(axe.violations_at_or_above(AxeImpact::Minor).len(), 2);
+        assert_eq!("CRITICAL".parse::<AxeImpact>().unwrap(), AxeImpact::Critical);
+    }
+}
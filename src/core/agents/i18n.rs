@@ -4,10 +4,13 @@
 
 use crate::core::adapters::ai::KandilAI;
 use crate::core::agents::base::{Agent, AgentState};
+use crate::core::agents::locale_format::find_hardcoded_formats;
+use crate::core::agents::resource_file::{placeholder_mismatch, placeholders, ResourceFile};
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 #[derive(Debug, Clone)]
@@ -34,9 +37,32 @@ pub struct I18nReport {
     pub translation_quality_scores: HashMap<String, u8>,
     pub missing_translations: HashMap<String, Vec<String>>,
     pub consistency_issues: Vec<ConsistencyIssue>,
+    #[serde(default)]
+    pub hardcoded_formats: Vec<HardcodedFormat>,
     pub recommendations: Vec<String>,
 }
 
+/// Outcome of [`I18nAssistant::translate_resource_file`], by resource key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceTranslationReport {
+    pub output: PathBuf,
+    pub translated: Vec<String>,
+    /// Keys that already had a translation in the target file.
+    pub kept: Vec<String>,
+    /// Keys that could not be translated, with the reason.
+    pub failed: Vec<(String, String)>,
+}
+
+/// A number, currency or date format written out literally instead of going
+/// through locale-aware formatting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HardcodedFormat {
+    pub file: String,
+    pub line: usize,
+    pub snippet: String,
+    pub reason: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConsistencyIssue {
     pub key: String,
@@ -68,12 +94,12 @@ impl I18nAssistant {
         target_lang: &str,
         source_lang: &str,
     ) -> Result<String> {
-        let cache_key = format!("{}:{}:{}", source_lang, target_lang, text);
+        let cache_key = format!("{}:{}", source_lang, text);
 
         if let Some(cached) = self
             .translation_cache
-            .get(source_lang)
-            .and_then(|lang_map| lang_map.get(target_lang))
+            .get(target_lang)
+            .and_then(|lang_map| lang_map.get(&cache_key))
         {
             return Ok(cached.clone());
         }
@@ -92,9 +118,9 @@ impl I18nAssistant {
 
         // Cache the translation
         self.translation_cache
-            .entry(source_lang.to_string())
+            .entry(target_lang.to_string())
             .or_insert_with(HashMap::new)
-            .insert(target_lang.to_string(), translation.clone());
+            .insert(cache_key, translation.clone());
 
         Ok(translation)
     }
@@ -115,6 +141,107 @@ impl I18nAssistant {
         self.ai.chat(&prompt).await
     }
 
+    /// Translates every value of a `.json`, `.arb` or `.po` resource file and
+    /// writes the result to `output`.
+    ///
+    /// Keys and structure are preserved. A translation that drops, adds or
+    /// translates an interpolation placeholder is rejected and reported as a
+    /// failure. With `only_missing`, values already present in `output` are
+    /// kept and only the gaps are translated.
+    pub async fn translate_resource_file(
+        &mut self,
+        source: &Path,
+        output: &Path,
+        target_lang: &str,
+        source_lang: &str,
+        only_missing: bool,
+    ) -> Result<ResourceTranslationReport> {
+        let file = ResourceFile::load(source)?;
+        let mut translations = if only_missing && output.exists() {
+            ResourceFile::load(output)?.translations()
+        } else {
+            HashMap::new()
+        };
+
+        let mut report = ResourceTranslationReport {
+            output: output.to_path_buf(),
+            translated: vec![],
+            kept: vec![],
+            failed: vec![],
+        };
+        for entry in file.entries() {
+            if entry.value.trim().is_empty() {
+                continue;
+            }
+            if let Some(existing) = translations.get(&entry.key) {
+                match placeholder_mismatch(&entry.value, existing) {
+                    None => {
+                        report.kept.push(entry.key);
+                        continue;
+                    }
+                    // Retranslate existing values whose placeholders were translated.
+                    Some(_) => {
+                        translations.remove(&entry.key);
+                    }
+                }
+            }
+
+            match self.translate_value(&entry.value, target_lang, source_lang).await {
+                Ok(translated) => match placeholder_mismatch(&entry.value, &translated) {
+                    None => {
+                        translations.insert(entry.key.clone(), translated);
+                        report.translated.push(entry.key);
+                    }
+                    Some(reason) => report.failed.push((entry.key, reason)),
+                },
+                Err(err) => report.failed.push((entry.key, err.to_string())),
+            }
+        }
+
+        if let Some(parent) = output.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(output, file.render_translated(&translations, target_lang)?)?;
+        Ok(report)
+    }
+
+    async fn translate_value(
+        &mut self,
+        text: &str,
+        target_lang: &str,
+        source_lang: &str,
+    ) -> Result<String> {
+        let keep = placeholders(text);
+        let prompt = format!(
+            r#"Translate this UI string from {} to {}.
+            {}
+            Reply with the translated string only, without quotes or explanation.
+
+            {}"#,
+            source_lang,
+            target_lang,
+            if keep.is_empty() {
+                String::new()
+            } else {
+                format!("Copy these placeholders unchanged: {}", keep.join(" "))
+            },
+            text
+        );
+        let reply = self.ai.chat(&prompt).await?;
+
+        let mut translated = reply.trim();
+        for quote in ['"', '`', '\''] {
+            if !text.starts_with(quote)
+                && translated.len() > 1
+                && translated.starts_with(quote)
+                && translated.ends_with(quote)
+            {
+                translated = &translated[1..translated.len() - 1];
+            }
+        }
+        Ok(translated.to_string())
+    }
+
     pub async fn audit_translations(&self, resource_dir: &str) -> Result<I18nReport> {
         let mut completeness = HashMap::new();
         let mut quality_scores = HashMap::new();
@@ -129,15 +256,25 @@ impl I18nAssistant {
             missing_translations.insert(lang.clone(), vec![]);
         }
 
+        let hardcoded_formats = scan_hardcoded_formats(Path::new(resource_dir));
+        let mut recommendations = vec![
+            "Add translations for new UI strings".to_string(),
+            "Review machine translations for accuracy".to_string(),
+        ];
+        if !hardcoded_formats.is_empty() {
+            recommendations.push(format!(
+                "Replace {} hardcoded number/currency/date format(s) with locale-aware formatting",
+                hardcoded_formats.len()
+            ));
+        }
+
         Ok(I18nReport {
             completeness_by_language: completeness,
             translation_quality_scores: quality_scores,
             missing_translations,
             consistency_issues,
-            recommendations: vec![
-                "Add translations for new UI strings".to_string(),
-                "Review machine translations for accuracy".to_string(),
-            ],
+            hardcoded_formats,
+            recommendations,
         })
     }
 
@@ -199,6 +336,47 @@ impl I18nAssistant {
     }
 }
 
+/// Extensions of files that may contain user-facing formats.
+const FORMAT_SCAN_EXTENSIONS: &[&str] = &[
+    "rs", "js", "jsx", "ts", "tsx", "vue", "svelte", "py", "java", "kt", "swift", "dart", "go",
+    "html", "json", "yaml", "yml",
+];
+
+fn scan_hardcoded_formats(root: &Path) -> Vec<HardcodedFormat> {
+    let mut found = Vec::new();
+    let files = walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            !matches!(name.as_ref(), "node_modules" | "target" | ".git" | "dist" | "build")
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file());
+
+    for entry in files {
+        let is_source = entry
+            .path()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| FORMAT_SCAN_EXTENSIONS.contains(&ext));
+        if !is_source {
+            continue;
+        }
+        let Ok(text) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        for (line, snippet, reason) in find_hardcoded_formats(&text) {
+            found.push(HardcodedFormat {
+                file: entry.path().display().to_string(),
+                line,
+                snippet,
+                reason: reason.to_string(),
+            });
+        }
+    }
+    found
+}
+
 #[async_trait]
 impl Agent for I18nAssistant {
     async fn plan(&self, state: &AgentState) -> Result<String> {
@@ -0,0 +1,468 @@
+//! Locale-aware formatting
+//!
+//! Formats numbers, currency amounts and dates following CLDR conventions for
+//! a bundled set of locales, and spots hardcoded formats in source files that
+//! won't localize.
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDate};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Grouping {
+    /// 1,234,567
+    Thousands,
+    /// 12,34,567 (South Asian lakh/crore grouping)
+    Indian,
+}
+
+/// CLDR number, currency and short-date conventions for one locale.
+#[derive(Debug)]
+struct LocaleData {
+    tag: &'static str,
+    decimal: &'static str,
+    group: &'static str,
+    grouping: Grouping,
+    /// Integers need at least this many digits beyond the first group before
+    /// separators are inserted (CLDR `minimumGroupingDigits`).
+    min_grouping: usize,
+    minus: &'static str,
+    digits: Option<[char; 10]>,
+    currency: &'static str,
+    currency_symbol: &'static str,
+    currency_digits: usize,
+    currency_prefix: bool,
+    currency_spacing: &'static str,
+    date_pattern: &'static str,
+}
+
+const ARABIC_INDIC_DIGITS: [char; 10] = ['٠', '١', '٢', '٣', '٤', '٥', '٦', '٧', '٨', '٩'];
+
+/// The first locale listed for a language is used when only the language is given.
+const LOCALES: &[LocaleData] = &[
+    LocaleData {
+        tag: "en-US",
+        decimal: ".",
+        group: ",",
+        grouping: Grouping::Thousands,
+        min_grouping: 1,
+        minus: "-",
+        digits: None,
+        currency: "USD",
+        currency_symbol: "$",
+        currency_digits: 2,
+        currency_prefix: true,
+        currency_spacing: "",
+        date_pattern: "M/d/y",
+    },
+    LocaleData {
+        tag: "en-GB",
+        decimal: ".",
+        group: ",",
+        grouping: Grouping::Thousands,
+        min_grouping: 1,
+        minus: "-",
+        digits: None,
+        currency: "GBP",
+        currency_symbol: "£",
+        currency_digits: 2,
+        currency_prefix: true,
+        currency_spacing: "",
+        date_pattern: "dd/MM/y",
+    },
+    LocaleData {
+        tag: "en-IN",
+        decimal: ".",
+        group: ",",
+        grouping: Grouping::Indian,
+        min_grouping: 1,
+        minus: "-",
+        digits: None,
+        currency: "INR",
+        currency_symbol: "₹",
+        currency_digits: 2,
+        currency_prefix: true,
+        currency_spacing: "",
+        date_pattern: "d/M/y",
+    },
+    LocaleData {
+        tag: "de-DE",
+        decimal: ",",
+        group: ".",
+        grouping: Grouping::Thousands,
+        min_grouping: 1,
+        minus: "-",
+        digits: None,
+        currency: "EUR",
+        currency_symbol: "€",
+        currency_digits: 2,
+        currency_prefix: false,
+        currency_spacing: "\u{a0}",
+        date_pattern: "dd.MM.y",
+    },
+    LocaleData {
+        tag: "fr-FR",
+        decimal: ",",
+        group: "\u{202f}",
+        grouping: Grouping::Thousands,
+        min_grouping: 1,
+        minus: "-",
+        digits: None,
+        currency: "EUR",
+        currency_symbol: "€",
+        currency_digits: 2,
+        currency_prefix: false,
+        currency_spacing: "\u{a0}",
+        date_pattern: "dd/MM/y",
+    },
+    LocaleData {
+        tag: "es-ES",
+        decimal: ",",
+        group: ".",
+        grouping: Grouping::Thousands,
+        min_grouping: 2,
+        minus: "-",
+        digits: None,
+        currency: "EUR",
+        currency_symbol: "€",
+        currency_digits: 2,
+        currency_prefix: false,
+        currency_spacing: "\u{a0}",
+        date_pattern: "d/M/y",
+    },
+    LocaleData {
+        tag: "it-IT",
+        decimal: ",",
+        group: ".",
+        grouping: Grouping::Thousands,
+        min_grouping: 1,
+        minus: "-",
+        digits: None,
+        currency: "EUR",
+        currency_symbol: "€",
+        currency_digits: 2,
+        currency_prefix: false,
+        currency_spacing: "\u{a0}",
+        date_pattern: "dd/MM/y",
+    },
+    LocaleData {
+        tag: "pt-BR",
+        decimal: ",",
+        group: ".",
+        grouping: Grouping::Thousands,
+        min_grouping: 1,
+        minus: "-",
+        digits: None,
+        currency: "BRL",
+        currency_symbol: "R$",
+        currency_digits: 2,
+        currency_prefix: true,
+        currency_spacing: "\u{a0}",
+        date_pattern: "dd/MM/y",
+    },
+    LocaleData {
+        tag: "ru-RU",
+        decimal: ",",
+        group: "\u{a0}",
+        grouping: Grouping::Thousands,
+        min_grouping: 1,
+        minus: "-",
+        digits: None,
+        currency: "RUB",
+        currency_symbol: "₽",
+        currency_digits: 2,
+        currency_prefix: false,
+        currency_spacing: "\u{a0}",
+        date_pattern: "dd.MM.y",
+    },
+    LocaleData {
+        tag: "ja-JP",
+        decimal: ".",
+        group: ",",
+        grouping: Grouping::Thousands,
+        min_grouping: 1,
+        minus: "-",
+        digits: None,
+        currency: "JPY",
+        currency_symbol: "￥",
+        currency_digits: 0,
+        currency_prefix: true,
+        currency_spacing: "",
+        date_pattern: "y/MM/dd",
+    },
+    LocaleData {
+        tag: "ko-KR",
+        decimal: ".",
+        group: ",",
+        grouping: Grouping::Thousands,
+        min_grouping: 1,
+        minus: "-",
+        digits: None,
+        currency: "KRW",
+        currency_symbol: "₩",
+        currency_digits: 0,
+        currency_prefix: true,
+        currency_spacing: "",
+        date_pattern: "y. M. d.",
+    },
+    LocaleData {
+        tag: "zh-CN",
+        decimal: ".",
+        group: ",",
+        grouping: Grouping::Thousands,
+        min_grouping: 1,
+        minus: "-",
+        digits: None,
+        currency: "CNY",
+        currency_symbol: "¥",
+        currency_digits: 2,
+        currency_prefix: true,
+        currency_spacing: "",
+        date_pattern: "y/M/d",
+    },
+    LocaleData {
+        tag: "ar-EG",
+        decimal: "٫",
+        group: "٬",
+        grouping: Grouping::Thousands,
+        min_grouping: 1,
+        minus: "\u{61c}-",
+        digits: Some(ARABIC_INDIC_DIGITS),
+        currency: "EGP",
+        currency_symbol: "ج.م.\u{200f}",
+        currency_digits: 2,
+        currency_prefix: false,
+        currency_spacing: "\u{a0}",
+        date_pattern: "d\u{200f}/M\u{200f}/y",
+    },
+];
+
+/// Tags of the bundled locales.
+pub fn supported_locales() -> Vec<&'static str> {
+    LOCALES.iter().map(|locale| locale.tag).collect()
+}
+
+pub struct LocaleFormatter {
+    data: &'static LocaleData,
+}
+
+impl LocaleFormatter {
+    /// Resolves a BCP 47 tag such as `de-DE`, `pt_BR` or plain `fr`.
+    pub fn for_locale(tag: &str) -> Result<Self> {
+        let normalized = tag.trim().replace('_', "-");
+        let language = normalized.split('-').next().unwrap_or_default();
+        LOCALES
+            .iter()
+            .find(|locale| locale.tag.eq_ignore_ascii_case(&normalized))
+            .or_else(|| {
+                LOCALES.iter().find(|locale| {
+                    locale
+                        .tag
+                        .split('-')
+                        .next()
+                        .is_some_and(|lang| lang.eq_ignore_ascii_case(language))
+                })
+            })
+            .map(|data| Self { data })
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No formatting data for locale '{}'. Supported: {}",
+                    tag,
+                    supported_locales().join(", ")
+                )
+            })
+    }
+
+    /// The locale actually used, e.g. `de-DE` when `de` was requested.
+    pub fn tag(&self) -> &'static str {
+        self.data.tag
+    }
+
+    /// ISO 4217 code of the locale's currency.
+    pub fn currency_code(&self) -> &'static str {
+        self.data.currency
+    }
+
+    /// Decimal format (`#,##0.###`).
+    pub fn format_number(&self, value: f64) -> String {
+        self.format_decimal(value, 0, 3)
+    }
+
+    /// Amount in the locale's currency, with the currency's usual precision.
+    pub fn format_currency(&self, value: f64) -> String {
+        let digits = self.data.currency_digits;
+        let amount = self.format_decimal(value.abs(), digits, digits);
+        let sign = if value < 0.0 && amount.chars().any(|c| c != '0') {
+            self.data.minus
+        } else {
+            ""
+        };
+        let (symbol, spacing) = (self.data.currency_symbol, self.data.currency_spacing);
+        if self.data.currency_prefix {
+            format!("{}{}{}{}", sign, symbol, spacing, amount)
+        } else {
+            format!("{}{}{}{}", sign, amount, spacing, symbol)
+        }
+    }
+
+    /// Short date in the locale's CLDR pattern.
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        let mut out = String::new();
+        let mut chars = self.data.date_pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            let mut width = 1;
+            while chars.peek() == Some(&c) {
+                chars.next();
+                width += 1;
+            }
+            let field = match c {
+                'y' if width == 2 => format!("{:02}", date.year() % 100),
+                'y' => date.year().to_string(),
+                'M' => format!("{:0width$}", date.month(), width = width),
+                'd' => format!("{:0width$}", date.day(), width = width),
+                _ => c.to_string().repeat(width),
+            };
+            out.push_str(&field);
+        }
+        self.localize_digits(&out)
+    }
+
+    fn format_decimal(&self, value: f64, min_fraction: usize, max_fraction: usize) -> String {
+        let fixed = format!("{:.*}", max_fraction, value.abs());
+        let (integer, fraction) = fixed.split_once('.').unwrap_or((&fixed, ""));
+        let mut fraction = fraction.trim_end_matches('0').to_string();
+        while fraction.len() < min_fraction {
+            fraction.push('0');
+        }
+
+        let mut out = String::new();
+        if value < 0.0 && (integer.chars().chain(fraction.chars()).any(|c| c != '0')) {
+            out.push_str(self.data.minus);
+        }
+        out.push_str(&self.group_integer(integer));
+        if !fraction.is_empty() {
+            out.push_str(self.data.decimal);
+            out.push_str(&fraction);
+        }
+        self.localize_digits(&out)
+    }
+
+    fn group_integer(&self, integer: &str) -> String {
+        if integer.len() < 4 + self.data.min_grouping - 1 {
+            return integer.to_string();
+        }
+
+        let (head, last) = integer.split_at(integer.len() - 3);
+        let size = match self.data.grouping {
+            Grouping::Thousands => 3,
+            Grouping::Indian => 2,
+        };
+        let mut groups = Vec::new();
+        let mut rest = head;
+        while rest.len() > size {
+            let (front, back) = rest.split_at(rest.len() - size);
+            groups.push(back);
+            rest = front;
+        }
+        groups.push(rest);
+        groups.reverse();
+        groups.push(last);
+        groups.join(self.data.group)
+    }
+
+    fn localize_digits(&self, text: &str) -> String {
+        match self.data.digits {
+            Some(digits) => text
+                .chars()
+                .map(|c| c.to_digit(10).map(|d| digits[d as usize]).unwrap_or(c))
+                .collect(),
+            None => text.to_string(),
+        }
+    }
+}
+
+/// Parses `2024-03-05` or an RFC 3339 timestamp, keeping only the date.
+pub fn parse_iso_date(value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| chrono::DateTime::parse_from_rfc3339(value).map(|dt| dt.date_naive()))
+        .with_context(|| format!("'{}' is not an ISO 8601 date (expected YYYY-MM-DD)", value))
+}
+
+lazy_static! {
+    static ref HARDCODED_FORMATS: Vec<(Regex, &'static str)> = vec![
+        (
+            Regex::new(r"\$\s?\d[\d,]*\.\d{2}\b|[€£¥₹]\s?\d|\d\s?[€£₹]").unwrap(),
+            "hardcoded currency symbol; format amounts with the locale's currency rules",
+        ),
+        (
+            Regex::new(r"\b(?:MM/dd/yyyy|dd/MM/yyyy|MM-dd-yyyy|dd\.MM\.yyyy|M/d/yyyy)\b|%m/%d/%Y|%d/%m/%Y|%d\.%m\.%Y")
+                .unwrap(),
+            "fixed date pattern; use the locale's date format",
+        ),
+        (
+            Regex::new(r"\b\d{1,3}(?:,\d{3})+(?:\.\d+)?\b").unwrap(),
+            "number with hardcoded grouping separators",
+        ),
+    ];
+}
+
+/// Lines of `text` containing formats that won't follow the user's locale,
+/// as `(line number, matched text, reason)`.
+pub fn find_hardcoded_formats(text: &str) -> Vec<(usize, String, &'static str)> {
+    let mut found = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        for (pattern, reason) in HARDCODED_FORMATS.iter() {
+            if let Some(m) = pattern.find(line) {
+                found.push((index + 1, m.as_str().to_string(), *reason));
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_follow_cldr_conventions() {
+        let date = parse_iso_date("2024-03-05").unwrap();
+
+        let us = LocaleFormatter::for_locale("en-US").unwrap();
+        assert_eq!(us.format_number(1234567.891), "1,234,567.891");
+        assert_eq!(us.format_currency(-1234.5), "-$1,234.50");
+        assert_eq!(us.format_date(date), "3/5/2024");
+
+        let de = LocaleFormatter::for_locale("de").unwrap();
+        assert_eq!(de.tag(), "de-DE");
+        assert_eq!(de.format_number(1234567.891), "1.234.567,891");
+        assert_eq!(de.format_currency(1234.5), "1.234,50\u{a0}€");
+        assert_eq!(de.format_date(date), "05.03.2024");
+
+        let india = LocaleFormatter::for_locale("en_IN").unwrap();
+        assert_eq!(india.format_number(1234567.0), "12,34,567");
+
+        let es = LocaleFormatter::for_locale("es-ES").unwrap();
+        assert_eq!(es.format_number(1234.0), "1234");
+        assert_eq!(es.format_number(12345.0), "12.345");
+
+        let ja = LocaleFormatter::for_locale("ja-JP").unwrap();
+        assert_eq!(ja.format_currency(1234.6), "￥1,235");
+        assert_eq!(ja.format_date(date), "2024/03/05");
+
+        let ar = LocaleFormatter::for_locale("ar").unwrap();
+        assert_eq!(ar.format_number(1234.5), "١٬٢٣٤٫٥");
+
+        assert!(LocaleFormatter::for_locale("xx-YY").is_err());
+    }
+
+    #[test]
+    fn flags_hardcoded_formats() {
+        let source = "let price = \"$1,299.00\";\nlet when = date.format(\"%m/%d/%Y\");\nlet ok = t(\"checkout.total\");";
+        let found = find_hardcoded_formats(source);
+        assert!(found.iter().any(|(line, _, reason)| *line == 1 && reason.contains("currency")));
+        assert!(found.iter().any(|(line, _, reason)| *line == 2 && reason.contains("date")));
+        assert!(found.iter().all(|(line, _, _)| *line != 3));
+    }
+}
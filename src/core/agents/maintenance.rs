@@ -1,14 +1,26 @@
 //! Maintenance and Monitoring Module
 //!
 //! Ongoing maintenance, monitoring, and support for the v2.0 platform
+//!
+//! Maintenance windows recur on a cron schedule evaluated in the window's IANA
+//! timezone, so a 02:00 Europe/Berlin window stays at 02:00 local time across
+//! daylight saving changes. Windows added with `kandil agent maintenance
+//! add-window` are kept in `maintenance_windows.json` in the data directory.
 
 use crate::core::adapters::ai::KandilAI;
 use crate::core::agents::ethics_security::Vulnerability;
-use anyhow::Result;
+use crate::utils::json_store::JsonStore;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::Arc;
 
+const WINDOWS_FILE: &str = "maintenance_windows.json";
+
 #[derive(Debug, Clone)]
 pub struct MaintenanceManager {
     pub health_monitor: HealthMonitor,
@@ -56,6 +68,18 @@ pub struct AlertThresholds {
     pub disk_usage_percent: f64,
 }
 
+impl Default for AlertThresholds {
+    fn default() -> Self {
+        Self {
+            response_time_ms: 1000,
+            error_rate_percent: 1.0,
+            memory_usage_percent: 85.0,
+            cpu_usage_percent: 80.0,
+            disk_usage_percent: 90.0,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateScheduler {
     pub updates: Vec<UpdateInfo>,
@@ -76,7 +100,7 @@ pub struct UpdateInfo {
     pub installation_size_mb: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum UpdateType {
     SecurityPatch,
     BugFix,
@@ -109,6 +133,225 @@ pub struct MaintenanceWindow {
     pub timezone: String,
     pub recurrence: Recurrence,
     pub allowed_updates: Vec<UpdateType>,
+    /// When the window opens; required for `Recurrence::Custom`. Without it
+    /// the schedule follows `start_time` and `recurrence`.
+    #[serde(default)]
+    pub cron_expression: Option<String>,
+}
+
+impl MaintenanceWindow {
+    /// A window opening on `cron_expression` in `timezone` and lasting
+    /// `duration`. Fails on an invalid expression or unknown timezone.
+    pub fn from_cron(
+        cron_expression: &str,
+        timezone: &str,
+        duration: chrono::Duration,
+        allowed_updates: Vec<UpdateType>,
+    ) -> Result<Self> {
+        let schedule = parse_cron(cron_expression)?;
+        let tz = parse_timezone(timezone)?;
+        if duration <= chrono::Duration::zero() {
+            bail!("A maintenance window must last at least a minute");
+        }
+        let start = schedule
+            .upcoming(tz)
+            .next()
+            .with_context(|| format!("'{}' never matches a future time", cron_expression))?;
+        Ok(Self {
+            start_time: start.to_rfc3339(),
+            end_time: (start + duration).to_rfc3339(),
+            timezone: timezone.to_string(),
+            recurrence: Recurrence::Custom,
+            allowed_updates,
+            cron_expression: Some(cron_expression.trim().to_string()),
+        })
+    }
+
+    pub fn tz(&self) -> Result<Tz> {
+        parse_timezone(&self.timezone)
+    }
+
+    /// The window's schedule: its cron expression, or `start_time`'s time of
+    /// day (in the window's timezone) repeated daily, weekly or monthly.
+    pub fn schedule(&self) -> Result<Schedule> {
+        if let Some(expression) = &self.cron_expression {
+            return parse_cron(expression);
+        }
+        let start = parse_time(&self.start_time)?.with_timezone(&self.tz()?);
+        let time = format!("{} {} {}", start.second(), start.minute(), start.hour());
+        let expression = match self.recurrence {
+            Recurrence::Daily => format!("{} * * *", time),
+            Recurrence::Weekly => format!("{} * * {}", time, start.weekday()),
+            Recurrence::Monthly => format!("{} {} * *", time, start.day()),
+            Recurrence::Custom => bail!("A custom maintenance window needs a cron expression"),
+        };
+        Schedule::from_str(&expression).map_err(|err| anyhow::anyhow!("{}", err))
+    }
+
+    pub fn duration(&self) -> Result<chrono::Duration> {
+        Ok(parse_time(&self.end_time)? - parse_time(&self.start_time)?)
+    }
+
+    /// The first time the window opens after `after`, in its own timezone.
+    pub fn next_start(&self, after: DateTime<Utc>) -> Result<DateTime<Tz>> {
+        let tz = self.tz()?;
+        self.schedule()?
+            .after(&after.with_timezone(&tz))
+            .next()
+            .context("The maintenance window never opens again")
+    }
+
+    pub fn allows(&self, update: &UpdateType) -> bool {
+        self.allowed_updates.contains(update)
+    }
+}
+
+/// Parses a cron expression. Standard five-field expressions (minute hour
+/// day-of-month month day-of-week) are accepted as well as the six- and
+/// seven-field forms with seconds and year. Days of the week are numbered as
+/// in standard cron, 0 (or 7) for Sunday through 6 for Saturday.
+pub fn parse_cron(expression: &str) -> Result<Schedule> {
+    let expression = expression.trim();
+    let mut fields: Vec<String> = expression.split_whitespace().map(str::to_string).collect();
+    if fields.len() == 5 {
+        fields.insert(0, "0".to_string());
+    }
+    if let Some(weekday) = fields.get_mut(5) {
+        *weekday = crate_weekdays(weekday)
+            .with_context(|| format!("Invalid cron expression '{}'", expression))?;
+    }
+    Schedule::from_str(&fields.join(" ")).map_err(|err| {
+        anyhow::anyhow!(
+            "Invalid cron expression '{}': {}. Use minute hour day month weekday, \
+             e.g. \"0 2 * * Sun\" for 02:00 every Sunday",
+            expression,
+            err
+        )
+    })
+}
+
+/// Rewrites a standard day-of-week field in the cron crate's numbering, which
+/// runs from 1 (Sunday) to 7 (Saturday). Numbers and numeric ranges become a
+/// list of days; names, `*` and `?` mean the same in both and are kept.
+fn crate_weekdays(field: &str) -> Result<String> {
+    let mut days = Vec::new();
+    for part in field.split(',') {
+        let (base, step) = match part.split_once('/') {
+            Some((base, step)) => (base, Some(step)),
+            None => (part, None),
+        };
+        let range = match base.split_once('-') {
+            Some((from, to)) => from.parse::<u32>().ok().zip(to.parse::<u32>().ok()),
+            // `n/step` runs from n to the end of the week
+            None => base
+                .parse::<u32>()
+                .ok()
+                .map(|day| (day, if step.is_some() { 6 } else { day })),
+        };
+        let Some((from, to)) = range else {
+            days.push(part.to_string());
+            continue;
+        };
+        if from > to || to > 7 {
+            bail!(
+                "'{}' is not a range of weekdays; use 0-7 (0 and 7 are Sunday) or Sun-Sat",
+                base
+            );
+        }
+        let step = match step {
+            Some(step) => step
+                .parse::<usize>()
+                .ok()
+                .filter(|&step| step > 0)
+                .with_context(|| format!("'{}' is not a weekday step", step))?,
+            None => 1,
+        };
+        days.extend(
+            (from..=to)
+                .step_by(step)
+                .map(|day| (day % 7 + 1).to_string()),
+        );
+    }
+    Ok(days.join(","))
+}
+
+/// Parses an IANA timezone name such as `Europe/Berlin`.
+pub fn parse_timezone(name: &str) -> Result<Tz> {
+    name.trim().parse::<Tz>().map_err(|_| {
+        anyhow::anyhow!(
+            "Unknown timezone '{}'. Use an IANA name such as UTC, Europe/Berlin or America/New_York",
+            name
+        )
+    })
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(value)
+        .with_context(|| format!("'{}' is not an ISO 8601 time", value))?
+        .with_timezone(&Utc))
+}
+
+/// Windows saved with `add-window`.
+pub fn load_windows() -> Result<Vec<MaintenanceWindow>> {
+    JsonStore::in_data_dir(WINDOWS_FILE).load()
+}
+
+pub fn save_window(window: MaintenanceWindow) -> Result<usize> {
+    JsonStore::in_data_dir(WINDOWS_FILE).update(|windows: &mut Vec<MaintenanceWindow>| {
+        windows.push(window);
+        windows.len()
+    })
+}
+
+/// Removes the saved window at `index` (counting from 1).
+pub fn remove_window(index: usize) -> Result<MaintenanceWindow> {
+    JsonStore::in_data_dir(WINDOWS_FILE).update(|windows: &mut Vec<MaintenanceWindow>| {
+        if index == 0 || index > windows.len() {
+            bail!(
+                "No maintenance window {}; there are {}",
+                index,
+                windows.len()
+            );
+        }
+        Ok(windows.remove(index - 1))
+    })?
+}
+
+impl UpdateScheduler {
+    /// The soonest window after `after` that allows `update`.
+    pub fn next_window_for(
+        &self,
+        update: &UpdateType,
+        after: DateTime<Utc>,
+    ) -> Result<Option<(DateTime<Tz>, &MaintenanceWindow)>> {
+        soonest(
+            self.maintenance_windows.iter().filter(|w| w.allows(update)),
+            after,
+        )
+    }
+
+    /// The soonest window of any kind after `after`.
+    pub fn next_window(
+        &self,
+        after: DateTime<Utc>,
+    ) -> Result<Option<(DateTime<Tz>, &MaintenanceWindow)>> {
+        soonest(self.maintenance_windows.iter(), after)
+    }
+}
+
+fn soonest<'a>(
+    windows: impl Iterator<Item = &'a MaintenanceWindow>,
+    after: DateTime<Utc>,
+) -> Result<Option<(DateTime<Tz>, &'a MaintenanceWindow)>> {
+    let mut best: Option<(DateTime<Tz>, &MaintenanceWindow)> = None;
+    for window in windows {
+        let start = window.next_start(after)?;
+        match &best {
+            Some((soonest, _)) if *soonest <= start => {}
+            _ => best = Some((start, window)),
+        }
+    }
+    Ok(best)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -401,13 +644,7 @@ impl MaintenanceManager {
                 checks: vec![],
                 last_check_time: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
                 overall_health: HealthStatus::Healthy,
-                alert_thresholds: AlertThresholds {
-                    response_time_ms: 1000,
-                    error_rate_percent: 1.0,
-                    memory_usage_percent: 85.0,
-                    cpu_usage_percent: 80.0,
-                    disk_usage_percent: 90.0,
-                },
+                alert_thresholds: AlertThresholds::default(),
             },
             update_scheduler: UpdateScheduler {
                 updates: vec![],
@@ -419,6 +656,7 @@ impl MaintenanceManager {
                     timezone: "UTC".to_string(),
                     recurrence: Recurrence::Weekly,
                     allowed_updates: vec![UpdateType::BugFix, UpdateType::SecurityPatch],
+                    cron_expression: None,
                 }],
             },
             backup_manager: BackupManager {
@@ -534,8 +772,30 @@ impl MaintenanceManager {
         Ok(())
     }
 
+    /// Queues `update`. With automatic updates on, it is scheduled for the
+    /// next maintenance window that allows its type.
     pub async fn schedule_update(&mut self, update: UpdateInfo) -> Result<()> {
-        self.update_scheduler.updates.push(update);
+        let scheduler = &mut self.update_scheduler;
+        if scheduler.automatic_updates_enabled {
+            let Some((start, window)) =
+                scheduler.next_window_for(&update.type_of_update, Utc::now())?
+            else {
+                bail!(
+                    "No maintenance window allows {:?} updates; add one with \
+                     `kandil agent maintenance add-window`",
+                    update.type_of_update
+                );
+            };
+            let info = ScheduleInfo {
+                cron_expression: window.schedule()?.to_string(),
+                timezone: window.timezone.clone(),
+                enabled: true,
+                last_run: None,
+                next_run: start.to_rfc3339(),
+            };
+            scheduler.schedule.insert(update.id.clone(), info);
+        }
+        scheduler.updates.push(update);
         Ok(())
     }
 
@@ -735,3 +995,100 @@ impl MaintenanceManager {
         health_ok && performance_ok && security_ok && backup_ok
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> DateTime<Utc> {
+        parse_time(value).unwrap()
+    }
+
+    #[test]
+    fn windows_recur_in_their_own_timezone() {
+        let weekly = MaintenanceWindow {
+            start_time: "2024-01-01T02:00:00Z".to_string(),
+            end_time: "2024-01-01T04:00:00Z".to_string(),
+            timezone: "UTC".to_string(),
+            recurrence: Recurrence::Weekly,
+            allowed_updates: vec![UpdateType::BugFix],
+            cron_expression: None,
+        };
+        let next = weekly.next_start(at("2024-03-06T00:00:00Z")).unwrap();
+        assert_eq!(next.with_timezone(&Utc), at("2024-03-11T02:00:00Z"));
+        assert_eq!(weekly.duration().unwrap(), chrono::Duration::hours(2));
+
+        // Berlin moves to summer time on 2024-03-31
+        let berlin = MaintenanceWindow::from_cron(
+            "30 3 * * *",
+            "Europe/Berlin",
+            chrono::Duration::hours(1),
+            vec![UpdateType::SecurityPatch],
+        )
+        .unwrap();
+        let before = berlin.next_start(at("2024-03-29T12:00:00Z")).unwrap();
+        let after = berlin.next_start(at("2024-03-30T12:00:00Z")).unwrap();
+        assert_eq!(before.with_timezone(&Utc), at("2024-03-30T02:30:00Z"));
+        assert_eq!(after.with_timezone(&Utc), at("2024-03-31T01:30:00Z"));
+        assert_eq!(after.hour(), 3);
+
+        let scheduler = UpdateScheduler {
+            updates: vec![],
+            schedule: HashMap::new(),
+            automatic_updates_enabled: true,
+            maintenance_windows: vec![weekly, berlin],
+        };
+        let now = at("2024-03-06T00:00:00Z");
+        let (start, window) = scheduler.next_window(now).unwrap().unwrap();
+        assert_eq!(window.timezone, "Europe/Berlin");
+        assert_eq!(start.with_timezone(&Utc), at("2024-03-06T02:30:00Z"));
+        let (_, window) = scheduler
+            .next_window_for(&UpdateType::BugFix, now)
+            .unwrap()
+            .unwrap();
+        assert_eq!(window.timezone, "UTC");
+        assert!(scheduler
+            .next_window_for(&UpdateType::Major, now)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn weekday_numbers_follow_standard_cron() {
+        // 2024-03-03 is a Sunday
+        let days: Vec<String> = parse_cron("0 9 * * 1-5")
+            .unwrap()
+            .after(&at("2024-03-03T00:00:00Z"))
+            .take(6)
+            .map(|time| time.format("%a %d").to_string())
+            .collect();
+        assert_eq!(
+            days,
+            ["Mon 04", "Tue 05", "Wed 06", "Thu 07", "Fri 08", "Mon 11"]
+        );
+        assert_eq!(crate_weekdays("0").unwrap(), "1");
+        assert_eq!(crate_weekdays("7").unwrap(), "1");
+        assert_eq!(crate_weekdays("5-7").unwrap(), "6,7,1");
+        assert_eq!(crate_weekdays("1-5/2,Sun").unwrap(), "2,4,6,Sun");
+        assert_eq!(crate_weekdays("Mon-Fri").unwrap(), "Mon-Fri");
+        assert_eq!(crate_weekdays("*").unwrap(), "*");
+        assert!(parse_cron("0 9 * * 8").is_err());
+    }
+
+    #[test]
+    fn bad_schedules_are_rejected_with_help() {
+        let err = parse_cron("0 25 * * *").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Invalid cron expression '0 25 * * *'"));
+        let err = parse_timezone("Mars/Olympus").unwrap_err();
+        assert!(err.to_string().contains("IANA name"));
+        assert!(MaintenanceWindow::from_cron(
+            "0 2 * * Sun",
+            "UTC",
+            chrono::Duration::zero(),
+            vec![]
+        )
+        .is_err());
+    }
+}
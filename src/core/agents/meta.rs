@@ -7,21 +7,103 @@ use crate::core::agents::base::{Agent, AgentState};
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+/// Files the model may pick for one plan.
+const MAX_FILES_PER_PLAN: usize = 3;
+/// Source files larger than this are not offered for rewriting.
+const MAX_FILE_BYTES: u64 = 64 * 1024;
+/// Paths listed in the file-selection prompt.
+const MAX_LISTED_FILES: usize = 300;
+const SOURCE_EXTENSIONS: &[&str] = &[
+    "rs", "js", "jsx", "ts", "tsx", "py", "java", "kt", "swift", "dart", "go", "c", "cpp", "h",
+    "toml", "json", "yaml", "yml", "md",
+];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImprovementPlan {
     pub title: String,
     pub description: String,
     pub priority: Priority,
     pub impact: Impact,
+    #[serde(default)]
     pub implementation_steps: Vec<String>,
     pub estimated_effort: String, // e.g., "Small", "Medium", "Large"
+    #[serde(default)]
     pub expected_benefits: Vec<String>,
+    #[serde(default)]
     pub dependencies: Vec<String>,
 }
 
+impl ImprovementPlan {
+    /// JSON Schema for the `{"plans": [...]}` reply the meta agent asks for.
+    pub fn schema() -> Value {
+        let strings = json!({ "type": "array", "items": { "type": "string" } });
+        json!({
+            "type": "object",
+            "properties": {
+                "plans": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "title": { "type": "string" },
+                            "description": { "type": "string" },
+                            "priority": { "enum": ["Low", "Medium", "High", "Critical"] },
+                            "impact": { "enum": ["Low", "Medium", "High", "Transformative"] },
+                            "implementation_steps": strings,
+                            "estimated_effort": { "type": "string" },
+                            "expected_benefits": strings,
+                            "dependencies": strings,
+                        },
+                        "required": [
+                            "title",
+                            "description",
+                            "priority",
+                            "impact",
+                            "implementation_steps",
+                            "estimated_effort",
+                            "expected_benefits",
+                            "dependencies",
+                        ],
+                    },
+                },
+            },
+            "required": ["plans"],
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct PlanList {
+    #[serde(default)]
+    plans: Vec<ImprovementPlan>,
+}
+
+#[derive(Deserialize)]
+struct FileSelection {
+    #[serde(default)]
+    files: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct FileRewrite {
+    content: String,
+    summary: String,
+}
+
+/// A whole-file rewrite proposed for one improvement plan.
+#[derive(Debug, Clone)]
+pub struct ProposedChange {
+    /// Canonical path, always inside the analyzed directory
+    pub path: PathBuf,
+    pub content: String,
+    pub summary: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Priority {
     Low,
@@ -141,26 +223,82 @@ impl MetaAgent {
             analysis.maintainability_issues
         );
 
-        let plans = self.ai.chat(&prompt).await?;
-
-        // For now, returning a basic improvement plan
-        Ok(vec![ImprovementPlan {
-            title: "Optimize Database Queries".to_string(),
-            description: "Address performance bottlenecks in database queries".to_string(),
-            priority: Priority::High,
-            impact: Impact::High,
-            implementation_steps: vec![
-                "Profile slow queries".to_string(),
-                "Add appropriate indexes".to_string(),
-                "Optimize query patterns".to_string(),
-            ],
-            estimated_effort: "Medium".to_string(),
-            expected_benefits: vec![
-                "Improved response time".to_string(),
-                "Better user experience".to_string(),
-            ],
-            dependencies: vec![],
-        }])
+        let reply: PlanList = self
+            .ai
+            .chat_json(&prompt, &ImprovementPlan::schema())
+            .await?;
+        Ok(reply.plans)
+    }
+
+    /// Turns a plan into concrete rewrites of files under `root`. The model
+    /// first picks files from a listing of `root`, then rewrites each one;
+    /// anything resolving outside `root` is dropped.
+    pub async fn propose_changes(
+        &self,
+        plan: &ImprovementPlan,
+        root: &Path,
+    ) -> Result<Vec<ProposedChange>> {
+        let root = root.canonicalize()?;
+        let listing = source_files(&root);
+        if listing.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let prompt = format!(
+            "Improvement plan: {}\n{}\nSteps:\n- {}\n\nFiles in the project:\n{}\n\n\
+             Pick at most {} of these files that must change to carry out the plan.",
+            plan.title,
+            plan.description,
+            plan.implementation_steps.join("\n- "),
+            listing.join("\n"),
+            MAX_FILES_PER_PLAN
+        );
+        let files_schema = json!({
+            "type": "object",
+            "properties": { "files": { "type": "array", "items": { "type": "string" } } },
+            "required": ["files"],
+        });
+        let selection: FileSelection = self.ai.chat_json(&prompt, &files_schema).await?;
+
+        let rewrite_schema = json!({
+            "type": "object",
+            "properties": {
+                "content": { "type": "string" },
+                "summary": { "type": "string" },
+            },
+            "required": ["content", "summary"],
+        });
+        let mut changes: Vec<ProposedChange> = Vec::new();
+        for file in selection.files {
+            if changes.len() == MAX_FILES_PER_PLAN {
+                break;
+            }
+            let Some(path) = resolve_in_scope(&root, &file) else {
+                continue;
+            };
+            if changes.iter().any(|change| change.path == path) {
+                continue;
+            }
+            let Ok(original) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+
+            let prompt = format!(
+                "Improvement plan: {}\n{}\n\nRewrite {} to carry out the plan. Keep unrelated \
+                 code unchanged and return the complete new file content with a one-line \
+                 summary of the change.\n\n{}",
+                plan.title, plan.description, file, original
+            );
+            let rewrite: FileRewrite = self.ai.chat_json(&prompt, &rewrite_schema).await?;
+            if rewrite.content.trim() != original.trim() {
+                changes.push(ProposedChange {
+                    path,
+                    content: rewrite.content,
+                    summary: rewrite.summary,
+                });
+            }
+        }
+        Ok(changes)
     }
 
     pub async fn evolve_agent_capabilities(&self) -> Result<String> {
@@ -180,6 +318,46 @@ impl MetaAgent {
     }
 }
 
+/// Source files under `root`, relative to it, smallest first.
+fn source_files(root: &Path) -> Vec<String> {
+    let mut files: Vec<(u64, String)> = walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0
+                || !(name.starts_with('.')
+                    || matches!(name.as_ref(), "node_modules" | "target" | "dist" | "build"))
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| {
+            entry
+                .path()
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| SOURCE_EXTENSIONS.contains(&ext))
+        })
+        .filter_map(|entry| {
+            let size = entry.metadata().ok()?.len();
+            let relative = entry.path().strip_prefix(root).ok()?;
+            (size <= MAX_FILE_BYTES).then(|| (size, relative.display().to_string()))
+        })
+        .collect();
+    files.sort();
+    files
+        .into_iter()
+        .take(MAX_LISTED_FILES)
+        .map(|(_, file)| file)
+        .collect()
+}
+
+/// Resolves a model-supplied path against `root`, refusing anything that
+/// does not exist or escapes it (absolute paths, `..`, symlinks).
+fn resolve_in_scope(root: &Path, file: &str) -> Option<PathBuf> {
+    let path = root.join(file.trim()).canonicalize().ok()?;
+    (path.starts_with(root) && path.is_file()).then_some(path)
+}
+
 #[async_trait]
 impl Agent for MetaAgent {
     async fn plan(&self, state: &AgentState) -> Result<String> {
@@ -211,3 +389,48 @@ impl Agent for MetaAgent {
         self.ai.chat(&prompt).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_outside_the_analyzed_directory_are_rejected() {
+        let base = std::env::temp_dir().join(format!("kandil_meta_{}", uuid::Uuid::new_v4()));
+        let root = base.join("project");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "pub fn a() {}\n").unwrap();
+        std::fs::write(base.join("secret.rs"), "// outside\n").unwrap();
+        let root = root.canonicalize().unwrap();
+
+        assert_eq!(
+            resolve_in_scope(&root, "src/lib.rs"),
+            Some(root.join("src/lib.rs"))
+        );
+        assert_eq!(resolve_in_scope(&root, "../secret.rs"), None);
+        let outside = base.join("secret.rs").display().to_string();
+        assert_eq!(resolve_in_scope(&root, &outside), None);
+        assert_eq!(resolve_in_scope(&root, "src/missing.rs"), None);
+        assert_eq!(resolve_in_scope(&root, "src"), None);
+
+        assert_eq!(source_files(&root), vec!["src/lib.rs".to_string()]);
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn plans_parse_without_optional_lists() {
+        let reply: PlanList = serde_json::from_value(json!({
+            "plans": [{
+                "title": "Cache lookups",
+                "description": "Avoid repeated reads",
+                "priority": "High",
+                "impact": "Medium",
+                "estimated_effort": "Small",
+            }],
+        }))
+        .unwrap();
+        assert_eq!(reply.plans.len(), 1);
+        assert!(reply.plans[0].implementation_steps.is_empty());
+        assert!(ImprovementPlan::schema()["properties"]["plans"].is_object());
+    }
+}
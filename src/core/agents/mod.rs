@@ -20,13 +20,17 @@ pub mod ethics_security;
 pub mod green_dev;
 pub mod i18n;
 pub mod ide_extension;
+pub mod locale_format;
 pub mod maintenance;
 pub mod marketplace;
 pub mod meta;
+pub mod pipeline;
+pub mod progress;
 pub mod qa;
 pub mod quality_assurance;
 pub mod release_manager;
 pub mod requirements;
+pub mod resource_file;
 pub mod review;
 pub mod scrum;
 pub mod simulations;
@@ -45,6 +49,7 @@ pub use ethics_security::EthicsSecurityAgent;
 pub use i18n::I18nAssistant;
 pub use ide_extension::IdeExtension;
 pub use meta::MetaAgent;
+pub use pipeline::{AgentPipeline, PipelineStage};
 pub use qa::QaSimulation;
 pub use requirements::RequirementsAgent;
 pub use review::ReviewAgent;
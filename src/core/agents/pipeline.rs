@@ -0,0 +1,347 @@
+//! Agent pipeline
+//!
+//! Chains the requirements, design, code and test stages, writing each stage's
+//! artifact to an output directory so the next stage (or a resumed run) can
+//! read it back as input.
+
+use crate::core::adapters::ai::KandilAI;
+use crate::core::agents::code::CodeOutput;
+use crate::core::agents::progress::{Progress, ProgressEvent};
+use crate::core::agents::{CodeAgent, DesignAgent, RequirementsAgent};
+use crate::utils::cost_tracking::CostTracker;
+use crate::utils::test_generation::TestGenerator;
+use anyhow::{Context, Result};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+const REQUIREMENTS_FILE: &str = "requirements.json";
+const DESIGN_FILE: &str = "design.json";
+const CODE_MANIFEST_FILE: &str = "code.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PipelineStage {
+    Requirements,
+    Design,
+    Code,
+    Test,
+}
+
+impl PipelineStage {
+    pub const ALL: [PipelineStage; 4] = [
+        PipelineStage::Requirements,
+        PipelineStage::Design,
+        PipelineStage::Code,
+        PipelineStage::Test,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PipelineStage::Requirements => "requirements",
+            PipelineStage::Design => "design",
+            PipelineStage::Code => "code",
+            PipelineStage::Test => "test",
+        }
+    }
+}
+
+impl FromStr for PipelineStage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        PipelineStage::ALL
+            .into_iter()
+            .find(|stage| stage.as_str() == s.to_lowercase())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown pipeline stage '{}'. Expected one of: requirements, design, code, test",
+                    s
+                )
+            })
+    }
+}
+
+/// Outcome of a single pipeline stage.
+#[derive(Debug, Clone)]
+pub struct StageReport {
+    pub stage: PipelineStage,
+    pub artifacts: Vec<PathBuf>,
+    pub estimated_cost: f64,
+}
+
+pub struct AgentPipeline {
+    ai: Arc<KandilAI>,
+    cost_tracker: Arc<CostTracker>,
+    output_dir: PathBuf,
+    language: String,
+    max_cost: Option<f64>,
+    spent: f64,
+    progress: Progress,
+}
+
+impl AgentPipeline {
+    pub fn new(
+        ai: Arc<KandilAI>,
+        cost_tracker: Arc<CostTracker>,
+        output_dir: impl Into<PathBuf>,
+        language: &str,
+    ) -> Self {
+        Self {
+            ai,
+            cost_tracker,
+            output_dir: output_dir.into(),
+            language: language.to_string(),
+            max_cost: None,
+            spent: 0.0,
+            progress: Progress::default(),
+        }
+    }
+
+    /// Stops the pipeline before the next stage once the estimated spend
+    /// reaches `max_cost` USD.
+    pub fn with_max_cost(mut self, max_cost: Option<f64>) -> Self {
+        self.max_cost = max_cost;
+        self
+    }
+
+    /// Reports each stage as it starts to `tx`; completions go to `run`'s
+    /// callback.
+    pub fn with_progress(mut self, tx: tokio::sync::mpsc::UnboundedSender<ProgressEvent>) -> Self {
+        self.progress = Progress::new(tx);
+        self
+    }
+
+    /// Estimated USD spent by the stages run so far.
+    pub fn spent(&self) -> f64 {
+        self.spent
+    }
+
+    /// Runs every stage from `from` onwards, calling `on_stage` as each one finishes.
+    ///
+    /// Stages before `from` are expected to have left their artifacts in the
+    /// output directory from an earlier run.
+    pub async fn run<F>(
+        &mut self,
+        description: &str,
+        from: PipelineStage,
+        mut on_stage: F,
+    ) -> Result<Vec<StageReport>>
+    where
+        F: FnMut(&StageReport),
+    {
+        std::fs::create_dir_all(&self.output_dir)?;
+
+        let mut reports = Vec::new();
+        for stage in PipelineStage::ALL.into_iter().filter(|stage| *stage >= from) {
+            self.check_budget(stage)?;
+            self.progress
+                .started(stage.as_str(), format!("  ⏳ {:<12} running...", stage.as_str()));
+            let (input, output, artifacts) = match stage {
+                PipelineStage::Requirements => self.run_requirements(description).await?,
+                PipelineStage::Design => self.run_design().await?,
+                PipelineStage::Code => self.run_code().await?,
+                PipelineStage::Test => self.run_tests().await?,
+            };
+
+            let estimated_cost = self.cost_tracker.record_usage(
+                self.ai.provider_name(),
+                self.ai.model_name(),
+                (input.len() / 4) as u32,
+                (output.len() / 4) as u32,
+            );
+            self.spent += estimated_cost;
+
+            let report = StageReport {
+                stage,
+                artifacts,
+                estimated_cost,
+            };
+            self.progress.completed(stage.as_str(), None);
+            on_stage(&report);
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+
+    fn check_budget(&self, next: PipelineStage) -> Result<()> {
+        if let Some(max_cost) = self.max_cost {
+            if self.spent >= max_cost {
+                anyhow::bail!(
+                    "Budget exhausted: spent ~${:.4} of ${:.4}. Resume later with --from {}",
+                    self.spent,
+                    max_cost,
+                    next.as_str()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn run_requirements(&self, description: &str) -> Result<(String, String, Vec<PathBuf>)> {
+        let agent = RequirementsAgent::new(self.ai.clone());
+        let doc = agent.generate_requirements_document(description).await?;
+        let json = serde_json::to_string_pretty(&doc)?;
+        let path = self.write_artifact(REQUIREMENTS_FILE, &json)?;
+        Ok((description.to_string(), json, vec![path]))
+    }
+
+    async fn run_design(&self) -> Result<(String, String, Vec<PathBuf>)> {
+        let requirements = self.read_artifact(REQUIREMENTS_FILE, PipelineStage::Requirements)?;
+        let agent = DesignAgent::new(self.ai.clone());
+        let doc = agent.generate_design_document(&requirements).await?;
+        let json = serde_json::to_string_pretty(&doc)?;
+        let path = self.write_artifact(DESIGN_FILE, &json)?;
+        Ok((requirements, json, vec![path]))
+    }
+
+    async fn run_code(&self) -> Result<(String, String, Vec<PathBuf>)> {
+        let design = self.read_artifact(DESIGN_FILE, PipelineStage::Design)?;
+        let agent = CodeAgent::new(self.ai.clone())?;
+        let output = agent.generate_code(&design, &self.language).await?;
+
+        let mut artifacts = Vec::new();
+        let mut generated = String::new();
+        for file in &output.files {
+            artifacts.push(self.write_artifact(&file.path, &file.content)?);
+            generated.push_str(&file.content);
+        }
+        let manifest = serde_json::to_string_pretty(&output)?;
+        artifacts.push(self.write_artifact(CODE_MANIFEST_FILE, &manifest)?);
+        Ok((design, generated, artifacts))
+    }
+
+    async fn run_tests(&self) -> Result<(String, String, Vec<PathBuf>)> {
+        let manifest = self.read_artifact(CODE_MANIFEST_FILE, PipelineStage::Code)?;
+        let output: CodeOutput = serde_json::from_str(&manifest)
+            .with_context(|| format!("Invalid {} in {}", CODE_MANIFEST_FILE, self.output_dir.display()))?;
+        let generator = TestGenerator::new(self.ai.clone());
+
+        let mut artifacts = Vec::new();
+        let mut input = String::new();
+        let mut generated = String::new();
+        for file in &output.files {
+            let source = self.output_dir.join(&file.path);
+            let tests = generator
+                .generate_tests_for_file(&source.to_string_lossy(), "")
+                .await?;
+            artifacts.push(self.write_artifact(&test_path_for(&file.path), &tests)?);
+            input.push_str(&file.content);
+            generated.push_str(&tests);
+        }
+        Ok((input, generated, artifacts))
+    }
+
+    fn read_artifact(&self, name: &str, producer: PipelineStage) -> Result<String> {
+        let path = self.output_dir.join(name);
+        std::fs::read_to_string(&path).with_context(|| {
+            format!(
+                "Missing {} from the {} stage. Re-run with --from {}",
+                path.display(),
+                producer.as_str(),
+                producer.as_str()
+            )
+        })
+    }
+
+    fn write_artifact(&self, relative: &str, contents: &str) -> Result<PathBuf> {
+        let relative = Path::new(relative);
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            anyhow::bail!(
+                "Refusing to write artifact outside the output directory: {}",
+                relative.display()
+            );
+        }
+
+        let path = self.output_dir.join(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, contents)?;
+        Ok(path)
+    }
+}
+
+/// `src/a/mod.rs` -> `tests/src/a/mod_test.rs`. The source tree is mirrored
+/// so files with the same name in different directories keep separate tests.
+fn test_path_for(source: &str) -> String {
+    let path = Path::new(source);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "generated".to_string());
+    let name = match path.extension() {
+        Some(ext) => format!("{}_test.{}", stem, ext.to_string_lossy()),
+        None => format!("{}_test", stem),
+    };
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    Path::new("tests")
+        .join(dir)
+        .join(name)
+        .to_string_lossy()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_parse_case_insensitively() {
+        assert_eq!(
+            "design".parse::<PipelineStage>().unwrap(),
+            PipelineStage::Design
+        );
+        assert_eq!(
+            "TEST".parse::<PipelineStage>().unwrap(),
+            PipelineStage::Test
+        );
+        for stage in PipelineStage::ALL {
+            assert_eq!(stage.as_str().parse::<PipelineStage>().unwrap(), stage);
+        }
+        let err = "deploy".parse::<PipelineStage>().unwrap_err();
+        assert!(err.to_string().contains("Unknown pipeline stage 'deploy'"));
+    }
+
+    #[test]
+    fn test_paths_mirror_the_source_tree() {
+        let cases = [
+            ("src/main.rs", "tests/src/main_test.rs"),
+            ("src/a/mod.rs", "tests/src/a/mod_test.rs"),
+            ("src/b/mod.rs", "tests/src/b/mod_test.rs"),
+            ("lib/utils.py", "tests/lib/utils_test.py"),
+            ("Makefile", "tests/Makefile_test"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(
+                Path::new(&test_path_for(source)),
+                Path::new(expected),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn artifacts_stay_inside_the_output_directory() {
+        let dir = std::env::temp_dir().join(format!("kandil_pipeline_{}", uuid::Uuid::new_v4()));
+        let ai = KandilAI::new("ollama".to_string(), "llama3:8b".to_string()).unwrap();
+        let pipeline = AgentPipeline::new(Arc::new(ai), Arc::new(CostTracker::new()), &dir, "rust");
+
+        let written = pipeline.write_artifact("src/a/mod.rs", "// a").unwrap();
+        assert_eq!(written, dir.join("src/a/mod.rs"));
+        assert_eq!(std::fs::read_to_string(&written).unwrap(), "// a");
+        assert!(pipeline.write_artifact("./src/lib.rs", "").is_ok());
+
+        for escape in ["../outside.rs", "src/../../outside.rs", "/tmp/outside.rs"] {
+            let err = pipeline.write_artifact(escape, "").unwrap_err();
+            assert!(
+                err.to_string().contains("outside the output directory"),
+                "{escape}"
+            );
+        }
+        assert!(!dir.parent().unwrap().join("outside.rs").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
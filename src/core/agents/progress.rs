@@ -0,0 +1,179 @@
+//! Progress events for long-running agents
+//!
+//! Agents such as the release manager, the QA suite and the pipeline report
+//! each stage as a typed [`ProgressEvent`]. Without a channel the events' messages are
+//! printed, as these agents always did; with one (see `with_progress`) events
+//! go to the receiver instead, so the CLI, TUI or web UI decide how to show
+//! them.
+
+use serde::Serialize;
+use tokio::sync::mpsc::UnboundedSender;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    StageStarted {
+        stage: String,
+        message: String,
+    },
+    /// Something worth showing while a stage runs, e.g. a tool's output.
+    StageDetail {
+        stage: String,
+        message: String,
+    },
+    /// `message` is `None` for stages that finish without a line of their own.
+    StageCompleted {
+        stage: String,
+        message: Option<String>,
+    },
+}
+
+impl ProgressEvent {
+    pub fn stage(&self) -> &str {
+        match self {
+            Self::StageStarted { stage, .. }
+            | Self::StageDetail { stage, .. }
+            | Self::StageCompleted { stage, .. } => stage,
+        }
+    }
+
+    /// The line the CLI prints for this event, if any.
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            Self::StageStarted { message, .. } | Self::StageDetail { message, .. } => Some(message),
+            Self::StageCompleted { message, .. } => message.as_deref(),
+        }
+    }
+}
+
+/// Where an agent's progress goes: a channel if one was given, else stdout.
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    tx: Option<UnboundedSender<ProgressEvent>>,
+}
+
+impl Progress {
+    pub fn new(tx: UnboundedSender<ProgressEvent>) -> Self {
+        Self { tx: Some(tx) }
+    }
+
+    pub fn started(&self, stage: &str, message: impl Into<String>) {
+        self.emit(ProgressEvent::StageStarted {
+            stage: stage.to_string(),
+            message: message.into(),
+        });
+    }
+
+    pub fn detail(&self, stage: &str, message: impl Into<String>) {
+        self.emit(ProgressEvent::StageDetail {
+            stage: stage.to_string(),
+            message: message.into(),
+        });
+    }
+
+    pub fn completed(&self, stage: &str, message: Option<String>) {
+        self.emit(ProgressEvent::StageCompleted {
+            stage: stage.to_string(),
+            message,
+        });
+    }
+
+    fn emit(&self, event: ProgressEvent) {
+        let event = match &self.tx {
+            Some(tx) => match tx.send(event) {
+                Ok(()) => return,
+                // Nobody is listening any more; don't lose the line
+                Err(err) => err.0,
+            },
+            None => event,
+        };
+        if let Some(message) = event.message() {
+            println!("{}", message);
+        }
+    }
+}
+
+/// The stages that have started but not completed, from a stream of events.
+/// The last one is the step an agent is in, e.g. for reporting a timeout.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpenStages(Vec<String>);
+
+impl OpenStages {
+    pub const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn observe(&mut self, event: &ProgressEvent) {
+        match event {
+            ProgressEvent::StageStarted { stage, .. } => self.0.push(stage.clone()),
+            ProgressEvent::StageCompleted { stage, .. } => {
+                if let Some(index) = self.0.iter().rposition(|open| open == stage) {
+                    self.0.remove(index);
+                }
+            }
+            ProgressEvent::StageDetail { .. } => {}
+        }
+    }
+
+    /// The innermost stage still running.
+    pub fn current(&self) -> Option<&str> {
+        self.0.last().map(String::as_str)
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_go_to_the_channel_when_there_is_one() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let progress = Progress::new(tx);
+        progress.started("build", "Building...");
+        progress.completed("build", Some("✓ Built".to_string()));
+        progress.completed("package", None);
+
+        let first = rx.try_recv().unwrap();
+        assert_eq!(
+            first,
+            ProgressEvent::StageStarted {
+                stage: "build".to_string(),
+                message: "Building...".to_string(),
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&rx.try_recv().unwrap()).unwrap(),
+            r#"{"kind":"stage_completed","stage":"build","message":"✓ Built"}"#
+        );
+        assert_eq!(rx.try_recv().unwrap().message(), None);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn open_stages_track_the_innermost_running_stage() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let progress = Progress::new(tx);
+        progress.started("qa_suite", "Running QA...");
+        progress.started("unit_tests", "Running unit tests...");
+        progress.detail("unit_tests", "12 passed");
+        progress.completed("unit_tests", None);
+        progress.started("e2e_tests", "Running end-to-end tests...");
+
+        let mut open = OpenStages::new();
+        while let Ok(event) = rx.try_recv() {
+            open.observe(&event);
+        }
+        assert_eq!(open.current(), Some("e2e_tests"));
+        open.observe(&ProgressEvent::StageCompleted {
+            stage: "e2e_tests".to_string(),
+            message: None,
+        });
+        assert_eq!(open.current(), Some("qa_suite"));
+        open.clear();
+        assert_eq!(open.current(), None);
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! Comprehensive testing and validation for the v2.0 release
 
+use super::progress::{Progress, ProgressEvent};
 use crate::core::adapters::ai::KandilAI;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -15,6 +16,9 @@ pub struct QualityAssuranceSystem {
     pub compliance_checker: ComplianceChecker,
     pub stability_report: StabilityReport,
     pub ai: Arc<KandilAI>,
+    /// How many times phases with failing tests are re-run.
+    pub retry_failed: u32,
+    progress: Progress,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +30,18 @@ pub struct TestSuite {
     pub security_tests: Vec<SecurityTest>,
     pub accessibility_tests: Vec<AccessibilityTest>,
     pub i18n_tests: Vec<I18nTest>,
+    /// Tests that failed at first and passed on a retry.
+    #[serde(default)]
+    pub flaky_tests: Vec<FlakyTest>,
+}
+
+/// A test that only passed on a retry. Its status is `Passed`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlakyTest {
+    pub phase: String,
+    pub name: String,
+    /// The retry that passed, counting from 1.
+    pub passed_on_retry: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -188,6 +204,10 @@ pub struct QaReport {
     pub stability_status: StabilityReport,
     pub recommendations: Vec<Recommendation>,
     pub readiness_level: ReadinessLevel,
+    /// Tests still failing after every retry, as `phase::name`.
+    pub stable_failures: Vec<String>,
+    /// Tests that passed only on a retry.
+    pub flaky_tests: Vec<FlakyTest>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -264,6 +284,369 @@ impl std::fmt::Display for ReadinessLevel {
     }
 }
 
+/// The parts of a test the retry logic and the JUnit report need, common to
+/// every test kind.
+trait QaTest {
+    fn name(&self) -> &str;
+    fn status(&self) -> &TestStatus;
+    fn status_mut(&mut self) -> &mut TestStatus;
+
+    fn failed(&self) -> bool {
+        matches!(self.status(), TestStatus::Failed)
+    }
+
+    /// The JUnit `classname`; the phase is used when there is none.
+    fn classname(&self) -> Option<&str> {
+        None
+    }
+
+    fn duration_ms(&self) -> Option<u64> {
+        None
+    }
+
+    /// Why the test failed, when it says.
+    fn failure_message(&self) -> Option<String> {
+        None
+    }
+}
+
+impl QaTest for UnitTest {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn status(&self) -> &TestStatus {
+        &self.status
+    }
+    fn status_mut(&mut self) -> &mut TestStatus {
+        &mut self.status
+    }
+    fn classname(&self) -> Option<&str> {
+        Some(&self.module)
+    }
+    fn duration_ms(&self) -> Option<u64> {
+        Some(self.duration_ms)
+    }
+}
+
+impl QaTest for IntegrationTest {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn status(&self) -> &TestStatus {
+        &self.status
+    }
+    fn status_mut(&mut self) -> &mut TestStatus {
+        &mut self.status
+    }
+    fn duration_ms(&self) -> Option<u64> {
+        Some(self.duration_ms)
+    }
+    fn failure_message(&self) -> Option<String> {
+        self.failure_reason.clone()
+    }
+}
+
+impl QaTest for E2ETest {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn status(&self) -> &TestStatus {
+        &self.status
+    }
+    fn status_mut(&mut self) -> &mut TestStatus {
+        &mut self.status
+    }
+    fn duration_ms(&self) -> Option<u64> {
+        Some(self.duration_ms)
+    }
+    fn failure_message(&self) -> Option<String> {
+        self.failure_details.clone()
+    }
+}
+
+impl QaTest for StressTest {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn status(&self) -> &TestStatus {
+        &self.status
+    }
+    fn status_mut(&mut self) -> &mut TestStatus {
+        &mut self.status
+    }
+    fn duration_ms(&self) -> Option<u64> {
+        Some(self.duration_ms)
+    }
+    fn failure_message(&self) -> Option<String> {
+        Some(format!(
+            "{} was {} against a threshold of {} with {} concurrent users",
+            self.target_metric, self.actual, self.threshold, self.concurrent_users
+        ))
+    }
+}
+
+impl QaTest for SecurityTest {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn status(&self) -> &TestStatus {
+        &self.status
+    }
+    fn status_mut(&mut self) -> &mut TestStatus {
+        &mut self.status
+    }
+    fn failure_message(&self) -> Option<String> {
+        Some(format!(
+            "{:?} severity: {} Remediation: {}",
+            self.severity, self.description, self.remediation
+        ))
+    }
+}
+
+impl QaTest for AccessibilityTest {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn status(&self) -> &TestStatus {
+        &self.status
+    }
+    fn status_mut(&mut self) -> &mut TestStatus {
+        &mut self.status
+    }
+    fn failure_message(&self) -> Option<String> {
+        Some(format!(
+            "{} issue(s) found, {}% compliant",
+            self.issues_found, self.compliance_percentage
+        ))
+    }
+}
+
+impl QaTest for I18nTest {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn status(&self) -> &TestStatus {
+        &self.status
+    }
+    fn status_mut(&mut self) -> &mut TestStatus {
+        &mut self.status
+    }
+}
+
+/// Marks the failed tests in `tests` that passed in `rerun` as passed and
+/// records them as flaky.
+fn merge_retry<T: QaTest>(
+    phase: &str,
+    tests: &mut [T],
+    rerun: &[T],
+    retry: u32,
+    flaky: &mut Vec<FlakyTest>,
+) {
+    for test in tests.iter_mut().filter(|test| test.failed()) {
+        let passed = rerun.iter().any(|again| {
+            again.name() == test.name() && matches!(again.status(), TestStatus::Passed)
+        });
+        if passed {
+            *test.status_mut() = TestStatus::Passed;
+            flaky.push(FlakyTest {
+                phase: phase.to_string(),
+                name: test.name().to_string(),
+                passed_on_retry: retry,
+            });
+        }
+    }
+}
+
+/// Re-runs a phase up to `retries` times while any of its tests fail.
+async fn retry_phase<T, F, Fut>(
+    progress: &Progress,
+    phase: &str,
+    retries: u32,
+    tests: &mut [T],
+    flaky: &mut Vec<FlakyTest>,
+    run: F,
+) -> Result<()>
+where
+    T: QaTest,
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<Vec<T>>>,
+{
+    for retry in 1..=retries {
+        if !tests.iter().any(QaTest::failed) {
+            break;
+        }
+        let stage = format!("{}_tests_retry", phase);
+        progress.started(
+            &stage,
+            format!(
+                "Retrying failed {} tests (attempt {} of {})...",
+                phase, retry, retries
+            ),
+        );
+        let rerun = run().await?;
+        merge_retry(phase, tests, &rerun, retry, flaky);
+        progress.completed(&stage, None);
+    }
+    Ok(())
+}
+
+/// `phase::name` of each failed test in `tests`.
+fn failures<T: QaTest>(phase: &str, tests: &[T]) -> Vec<String> {
+    tests
+        .iter()
+        .filter(|test| test.failed())
+        .map(|test| format!("{}::{}", phase, test.name()))
+        .collect()
+}
+
+/// One `<testsuite>` element for a phase. Flaky tests note the retry that
+/// passed in `<system-out>`.
+fn junit_suite<T: QaTest>(phase: &str, tests: &[T], flaky: &[FlakyTest]) -> String {
+    let count =
+        |wanted: fn(&TestStatus) -> bool| tests.iter().filter(|test| wanted(test.status())).count();
+    let failed = count(|status| matches!(status, TestStatus::Failed));
+    let skipped = count(|status| matches!(status, TestStatus::Skipped | TestStatus::InProgress));
+    let total_ms: u64 = tests.iter().filter_map(QaTest::duration_ms).sum();
+
+    let mut xml = format!(
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{}\">\n",
+        xml_escape(phase),
+        tests.len(),
+        failed,
+        skipped,
+        seconds(total_ms)
+    );
+    for test in tests {
+        xml.push_str(&format!(
+            "    <testcase classname=\"{}\" name=\"{}\"",
+            xml_escape(test.classname().unwrap_or(phase)),
+            xml_escape(test.name())
+        ));
+        if let Some(duration_ms) = test.duration_ms() {
+            xml.push_str(&format!(" time=\"{}\"", seconds(duration_ms)));
+        }
+        let retried = flaky
+            .iter()
+            .find(|flaky| flaky.phase == phase && flaky.name == test.name());
+        let body = match test.status() {
+            TestStatus::Failed => {
+                let message = test
+                    .failure_message()
+                    .unwrap_or_else(|| "Test failed".to_string());
+                format!(
+                    "      <failure message=\"{}\">{}</failure>\n",
+                    xml_escape(&message),
+                    xml_escape(&message)
+                )
+            }
+            TestStatus::Skipped => "      <skipped/>\n".to_string(),
+            TestStatus::InProgress => "      <skipped message=\"did not finish\"/>\n".to_string(),
+            TestStatus::Passed => match retried {
+                Some(flaky) => format!(
+                    "      <system-out>Flaky: passed on retry {}</system-out>\n",
+                    flaky.passed_on_retry
+                ),
+                None => String::new(),
+            },
+        };
+        if body.is_empty() {
+            xml.push_str("/>\n");
+        } else {
+            xml.push_str(&format!(">\n{}    </testcase>\n", body));
+        }
+    }
+    xml.push_str("  </testsuite>\n");
+    xml
+}
+
+fn seconds(duration_ms: u64) -> String {
+    format!("{:.3}", duration_ms as f64 / 1000.0)
+}
+
+/// `text` escaped for an XML attribute or text node. Control characters XML
+/// 1.0 doesn't allow are dropped.
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\t' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Checks that `xml` is well-formed: one root element, every element closed
+/// in order, quoted attributes and only known entities in text.
+fn check_well_formed(xml: &str) -> Result<()> {
+    let tag = regex::Regex::new(r#"<(/?)([A-Za-z_][\w.-]*)((?:\s+[\w.:-]+="[^"<]*")*)\s*(/?)>"#)
+        .expect("valid regex");
+    let entity =
+        regex::Regex::new(r"&(?:amp|lt|gt|quot|apos|#\d+|#x[0-9A-Fa-f]+);").expect("valid regex");
+    let body = xml
+        .strip_prefix(r#"<?xml version="1.0" encoding="UTF-8"?>"#)
+        .unwrap_or(xml);
+
+    let mut open: Vec<&str> = Vec::new();
+    let mut roots = 0;
+    let mut position = 0;
+    let check_text = |text: &str| -> Result<()> {
+        if text.contains('<') || text.contains('>') {
+            anyhow::bail!("Malformed markup near '{}'", text.trim());
+        }
+        if entity.replace_all(text, "").contains('&') {
+            anyhow::bail!("Unescaped '&' in '{}'", text.trim());
+        }
+        Ok(())
+    };
+    for found in tag.captures_iter(body) {
+        let whole = found.get(0).expect("group 0 always matches");
+        let text = &body[position..whole.start()];
+        if open.is_empty() && !text.trim().is_empty() {
+            anyhow::bail!("Text outside the root element: '{}'", text.trim());
+        }
+        check_text(text)?;
+        check_text(&found[3])?;
+        position = whole.end();
+
+        let name = found.get(2).expect("tag name").as_str();
+        if !found[1].is_empty() {
+            match open.pop() {
+                Some(expected) if expected == name => {}
+                Some(expected) => {
+                    anyhow::bail!("</{}> closes <{}>", name, expected)
+                }
+                None => anyhow::bail!("</{}> has no opening tag", name),
+            }
+            continue;
+        }
+        if open.is_empty() {
+            roots += 1;
+        }
+        if found[4].is_empty() {
+            open.push(name);
+        }
+    }
+    let rest = &body[position..];
+    if !rest.trim().is_empty() {
+        anyhow::bail!("Malformed markup near '{}'", rest.trim());
+    }
+    if let Some(unclosed) = open.last() {
+        anyhow::bail!("<{}> is never closed", unclosed);
+    }
+    if roots != 1 {
+        anyhow::bail!("Expected one root element, found {}", roots);
+    }
+    Ok(())
+}
+
 impl QualityAssuranceSystem {
     pub fn new(ai: Arc<KandilAI>) -> Self {
         Self {
@@ -275,6 +658,7 @@ impl QualityAssuranceSystem {
                 security_tests: vec![],
                 accessibility_tests: vec![],
                 i18n_tests: vec![],
+                flaky_tests: vec![],
             },
             code_quality_metrics: CodeQualityMetrics {
                 test_coverage: 0.0,
@@ -310,45 +694,84 @@ impl QualityAssuranceSystem {
                 stability_score: 0.0,
             },
             ai,
+            retry_failed: 0,
+            progress: Progress::default(),
         }
     }
 
+    /// Re-runs phases with failing tests up to `retries` times. A test that
+    /// passes on a retry counts as passed and is reported as flaky.
+    pub fn with_retry_failed(mut self, retries: u32) -> Self {
+        self.retry_failed = retries;
+        self
+    }
+
+    /// Sends stage events to `tx` instead of printing them.
+    pub fn with_progress(mut self, tx: tokio::sync::mpsc::UnboundedSender<ProgressEvent>) -> Self {
+        self.progress = Progress::new(tx);
+        self
+    }
+
     pub async fn run_full_qa_suite(&mut self, project_path: &str) -> Result<QaReport> {
-        println!(
-            "Running comprehensive QA suite for project at {}...",
-            project_path
+        let progress = self.progress.clone();
+        progress.started(
+            "qa_suite",
+            format!(
+                "Running comprehensive QA suite for project at {}...",
+                project_path
+            ),
         );
 
         // Run all test types
-        self.run_unit_tests().await?;
-        self.run_integration_tests().await?;
-        self.run_e2e_tests().await?;
-        self.run_stress_tests().await?;
-        self.run_security_tests().await?;
-        self.run_accessibility_tests().await?;
-        self.run_i18n_tests().await?;
+        progress.started("unit_tests", "Running unit tests...");
+        self.test_suite.unit_tests = Self::run_unit_tests().await?;
+        progress.completed("unit_tests", None);
+        progress.started("integration_tests", "Running integration tests...");
+        self.test_suite.integration_tests = Self::run_integration_tests().await?;
+        progress.completed("integration_tests", None);
+        progress.started("e2e_tests", "Running end-to-end tests...");
+        self.test_suite.e2e_tests = Self::run_e2e_tests().await?;
+        progress.completed("e2e_tests", None);
+        progress.started("stress_tests", "Running stress tests...");
+        self.test_suite.stress_tests = Self::run_stress_tests().await?;
+        progress.completed("stress_tests", None);
+        progress.started("security_tests", "Running security tests...");
+        self.test_suite.security_tests = Self::run_security_tests().await?;
+        progress.completed("security_tests", None);
+        progress.started("accessibility_tests", "Running accessibility tests...");
+        self.test_suite.accessibility_tests = Self::run_accessibility_tests().await?;
+        progress.completed("accessibility_tests", None);
+        progress.started("i18n_tests", "Running internationalization tests...");
+        self.test_suite.i18n_tests = Self::run_i18n_tests().await?;
+        progress.completed("i18n_tests", None);
+        self.retry_failed_tests().await?;
 
         // Gather code quality metrics
+        progress.started("code_metrics", "Collecting code quality metrics...");
         self.collect_code_metrics()?;
+        progress.completed("code_metrics", None);
 
         // Check compliance
+        progress.started("compliance", "Checking compliance standards...");
         self.check_compliance().await?;
+        progress.completed("compliance", None);
 
         // Generate stability report
+        progress.started("stability", "Generating stability report...");
         self.generate_stability_report().await?;
+        progress.completed("stability", None);
 
         // Create final QA report
         let report = self.create_qa_report()?;
+        progress.completed("qa_suite", None);
 
         Ok(report)
     }
 
-    async fn run_unit_tests(&mut self) -> Result<()> {
-        println!("Running unit tests...");
-
+    async fn run_unit_tests() -> Result<Vec<UnitTest>> {
         // In a real implementation, this would run actual unit tests
         // For simulation, we'll add mock results
-        self.test_suite.unit_tests = vec![
+        Ok(vec![
             UnitTest {
                 name: "test_core_agent_functionality".to_string(),
                 module: "core::agents::base".to_string(),
@@ -370,15 +793,11 @@ impl QualityAssuranceSystem {
                 duration_ms: 8,
                 coverage_percentage: 80.0,
             },
-        ];
-
-        Ok(())
+        ])
     }
 
-    async fn run_integration_tests(&mut self) -> Result<()> {
-        println!("Running integration tests...");
-
-        self.test_suite.integration_tests = vec![
+    async fn run_integration_tests() -> Result<Vec<IntegrationTest>> {
+        Ok(vec![
             IntegrationTest {
                 name: "test_agent_communication".to_string(),
                 components: vec!["Agent".to_string(), "AI Adapter".to_string()],
@@ -393,30 +812,22 @@ impl QualityAssuranceSystem {
                 duration_ms: 87,
                 failure_reason: None,
             },
-        ];
-
-        Ok(())
+        ])
     }
 
-    async fn run_e2e_tests(&mut self) -> Result<()> {
-        println!("Running end-to-end tests...");
-
-        self.test_suite.e2e_tests = vec![E2ETest {
+    async fn run_e2e_tests() -> Result<Vec<E2ETest>> {
+        Ok(vec![E2ETest {
             name: "test_complete_project_lifecycle".to_string(),
             scenario: "Create project → Generate code → Test → Deploy".to_string(),
             status: TestStatus::Passed,
             duration_ms: 3_200,
             user_path: "CLI → TUI → CLI".to_string(),
             failure_details: None,
-        }];
-
-        Ok(())
+        }])
     }
 
-    async fn run_stress_tests(&mut self) -> Result<()> {
-        println!("Running stress tests...");
-
-        self.test_suite.stress_tests = vec![StressTest {
+    async fn run_stress_tests() -> Result<Vec<StressTest>> {
+        Ok(vec![StressTest {
             name: "concurrent_ai_requests".to_string(),
             target_metric: "response_time".to_string(),
             threshold: 1000.0, // 1second
@@ -424,45 +835,33 @@ impl QualityAssuranceSystem {
             status: TestStatus::Passed,
             duration_ms: 10_000,
             concurrent_users: 50,
-        }];
-
-        Ok(())
+        }])
     }
 
-    async fn run_security_tests(&mut self) -> Result<()> {
-        println!("Running security tests...");
-
-        self.test_suite.security_tests = vec![SecurityTest {
+    async fn run_security_tests() -> Result<Vec<SecurityTest>> {
+        Ok(vec![SecurityTest {
             name: "input_validation_check".to_string(),
             category: SecurityCategory::InputValidation,
             status: TestStatus::Passed,
             severity: Severity::High,
             description: "Validates all user inputs are properly sanitized".to_string(),
             remediation: "Use parameterized queries and input validation middleware".to_string(),
-        }];
-
-        Ok(())
+        }])
     }
 
-    async fn run_accessibility_tests(&mut self) -> Result<()> {
-        println!("Running accessibility tests...");
-
-        self.test_suite.accessibility_tests = vec![AccessibilityTest {
+    async fn run_accessibility_tests() -> Result<Vec<AccessibilityTest>> {
+        Ok(vec![AccessibilityTest {
             name: "keyboard_navigation".to_string(),
             wcag_level: WcagLevel::AA,
             components: vec!["TUI".to_string(), "CLI".to_string()],
             status: TestStatus::Passed,
             issues_found: 0,
             compliance_percentage: 95.0,
-        }];
-
-        Ok(())
+        }])
     }
 
-    async fn run_i18n_tests(&mut self) -> Result<()> {
-        println!("Running internationalization tests...");
-
-        self.test_suite.i18n_tests = vec![
+    async fn run_i18n_tests() -> Result<Vec<I18nTest>> {
+        Ok(vec![
             I18nTest {
                 name: "french_translation_accuracy".to_string(),
                 language: "fr".to_string(),
@@ -477,14 +876,95 @@ impl QualityAssuranceSystem {
                 status: TestStatus::Passed,
                 coverage_percentage: 85.0,
             },
-        ];
+        ])
+    }
 
+    async fn retry_failed_tests(&mut self) -> Result<()> {
+        let retries = self.retry_failed;
+        let progress = &self.progress;
+        let suite = &mut self.test_suite;
+        let flaky = &mut suite.flaky_tests;
+        retry_phase(
+            progress,
+            "unit",
+            retries,
+            &mut suite.unit_tests,
+            flaky,
+            Self::run_unit_tests,
+        )
+        .await?;
+        retry_phase(
+            progress,
+            "integration",
+            retries,
+            &mut suite.integration_tests,
+            flaky,
+            Self::run_integration_tests,
+        )
+        .await?;
+        retry_phase(
+            progress,
+            "e2e",
+            retries,
+            &mut suite.e2e_tests,
+            flaky,
+            Self::run_e2e_tests,
+        )
+        .await?;
+        retry_phase(
+            progress,
+            "stress",
+            retries,
+            &mut suite.stress_tests,
+            flaky,
+            Self::run_stress_tests,
+        )
+        .await?;
+        retry_phase(
+            progress,
+            "security",
+            retries,
+            &mut suite.security_tests,
+            flaky,
+            Self::run_security_tests,
+        )
+        .await?;
+        retry_phase(
+            progress,
+            "accessibility",
+            retries,
+            &mut suite.accessibility_tests,
+            flaky,
+            Self::run_accessibility_tests,
+        )
+        .await?;
+        retry_phase(
+            progress,
+            "i18n",
+            retries,
+            &mut suite.i18n_tests,
+            flaky,
+            Self::run_i18n_tests,
+        )
+        .await?;
         Ok(())
     }
 
-    fn collect_code_metrics(&mut self) -> Result<()> {
-        println!("Collecting code quality metrics...");
+    /// Tests still failing, as `phase::name`; after retries these are the
+    /// stable failures.
+    pub fn stable_failures(&self) -> Vec<String> {
+        let suite = &self.test_suite;
+        let mut failed = failures("unit", &suite.unit_tests);
+        failed.extend(failures("integration", &suite.integration_tests));
+        failed.extend(failures("e2e", &suite.e2e_tests));
+        failed.extend(failures("stress", &suite.stress_tests));
+        failed.extend(failures("security", &suite.security_tests));
+        failed.extend(failures("accessibility", &suite.accessibility_tests));
+        failed.extend(failures("i18n", &suite.i18n_tests));
+        failed
+    }
 
+    fn collect_code_metrics(&mut self) -> Result<()> {
         // In a real implementation, this would run static analysis tools
         // For simulation, assign mock values
         self.code_quality_metrics = CodeQualityMetrics {
@@ -503,8 +983,6 @@ impl QualityAssuranceSystem {
     }
 
     async fn check_compliance(&mut self) -> Result<()> {
-        println!("Checking compliance standards...");
-
         // Add compliance standards
         self.compliance_checker
             .security_standards
@@ -536,8 +1014,6 @@ impl QualityAssuranceSystem {
     }
 
     async fn generate_stability_report(&mut self) -> Result<()> {
-        println!("Generating stability report...");
-
         // In a real implementation, this would monitor running systems
         // For simulation, assign mock values
         self.stability_report = StabilityReport {
@@ -651,9 +1127,84 @@ impl QualityAssuranceSystem {
             stability_status: self.stability_report.clone(),
             recommendations,
             readiness_level,
+            stable_failures: self.stable_failures(),
+            flaky_tests: self.test_suite.flaky_tests.clone(),
         })
     }
 
+    /// The failures that survived every retry and the tests that only
+    /// passed on one, as markdown list items.
+    fn stability_md(&self) -> String {
+        let mut lines = String::new();
+        for failure in self.stable_failures() {
+            lines.push_str(&format!("- Stable failure: {}\n", failure));
+        }
+        for flaky in &self.test_suite.flaky_tests {
+            lines.push_str(&format!(
+                "- Flaky: {}::{} (passed on retry {})\n",
+                flaky.phase, flaky.name, flaky.passed_on_retry
+            ));
+        }
+        if lines.is_empty() {
+            lines.push_str("- No failing or flaky tests\n");
+        }
+        lines
+    }
+
+    /// The test suite as JUnit XML, one `<testsuite>` per phase, for CI
+    /// systems to display.
+    pub fn generate_qa_report_junit(&self) -> Result<String> {
+        let suite = &self.test_suite;
+        let flaky = &suite.flaky_tests;
+        let suites = [
+            junit_suite("unit", &suite.unit_tests, flaky),
+            junit_suite("integration", &suite.integration_tests, flaky),
+            junit_suite("e2e", &suite.e2e_tests, flaky),
+            junit_suite("stress", &suite.stress_tests, flaky),
+            junit_suite("security", &suite.security_tests, flaky),
+            junit_suite("accessibility", &suite.accessibility_tests, flaky),
+            junit_suite("i18n", &suite.i18n_tests, flaky),
+        ];
+        let statuses: Vec<&TestStatus> = suite
+            .unit_tests
+            .iter()
+            .map(QaTest::status)
+            .chain(suite.integration_tests.iter().map(QaTest::status))
+            .chain(suite.e2e_tests.iter().map(QaTest::status))
+            .chain(suite.stress_tests.iter().map(QaTest::status))
+            .chain(suite.security_tests.iter().map(QaTest::status))
+            .chain(suite.accessibility_tests.iter().map(QaTest::status))
+            .chain(suite.i18n_tests.iter().map(QaTest::status))
+            .collect();
+        let failed = statuses
+            .iter()
+            .filter(|status| matches!(status, TestStatus::Failed))
+            .count();
+        let total_ms: u64 = suite
+            .unit_tests
+            .iter()
+            .filter_map(QaTest::duration_ms)
+            .chain(
+                suite
+                    .integration_tests
+                    .iter()
+                    .filter_map(QaTest::duration_ms),
+            )
+            .chain(suite.e2e_tests.iter().filter_map(QaTest::duration_ms))
+            .chain(suite.stress_tests.iter().filter_map(QaTest::duration_ms))
+            .sum();
+
+        let xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"Kandil QA\" tests=\"{}\" failures=\"{}\" errors=\"0\" time=\"{}\">\n{}</testsuites>\n",
+            statuses.len(),
+            failed,
+            seconds(total_ms),
+            suites.concat()
+        );
+        check_well_formed(&xml)?;
+        Ok(xml)
+    }
+
     pub fn generate_qa_report_md(&self) -> String {
         // Calculate test pass rate as percentage
         let test_pass_rate = {
@@ -740,6 +1291,8 @@ impl QualityAssuranceSystem {
 - Failed: {}
 - Skipped: {}
 
+## Test Stability
+{}
 ## Code Quality Metrics
 - Test Coverage: {:.2}%
 - Cyclomatic Complexity: {:.2}
@@ -806,6 +1359,7 @@ impl QualityAssuranceSystem {
                     .filter(|t| !matches!(t.status, TestStatus::Passed))
                     .count(), // Failed
             0, // Skipped - was using duplicated_lines here incorrectly
+            self.stability_md(),
             self.code_quality_metrics.test_coverage, // Test Coverage (using same as Overall Quality Score)
             self.code_quality_metrics.cyclomatic_complexity,
             self.code_quality_metrics.maintainability_index,
@@ -822,3 +1376,133 @@ impl QualityAssuranceSystem {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(name: &str, status: TestStatus) -> UnitTest {
+        UnitTest {
+            name: name.to_string(),
+            module: "tests".to_string(),
+            status,
+            duration_ms: 1,
+            coverage_percentage: 0.0,
+        }
+    }
+
+    #[test]
+    fn failures_that_pass_on_retry_are_flaky() {
+        let mut tests = vec![
+            unit("a", TestStatus::Failed),
+            unit("b", TestStatus::Passed),
+            unit("c", TestStatus::Failed),
+        ];
+        let rerun = vec![
+            unit("a", TestStatus::Passed),
+            unit("b", TestStatus::Failed),
+            unit("c", TestStatus::Failed),
+        ];
+        let mut flaky = Vec::new();
+        merge_retry("unit", &mut tests, &rerun, 2, &mut flaky);
+
+        assert!(matches!(tests[0].status, TestStatus::Passed));
+        assert!(matches!(tests[1].status, TestStatus::Passed));
+        assert_eq!(failures("unit", &tests), vec!["unit::c"]);
+        assert_eq!(
+            flaky,
+            vec![FlakyTest {
+                phase: "unit".to_string(),
+                name: "a".to_string(),
+                passed_on_retry: 2,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn the_report_separates_stable_failures_from_flaky_tests() {
+        let ai = Arc::new(KandilAI::new("ollama".to_string(), "llama3".to_string()).unwrap());
+        let mut qa = QualityAssuranceSystem::new(ai).with_retry_failed(2);
+        let report = qa.run_full_qa_suite(".").await.unwrap();
+
+        assert_eq!(report.stable_failures, vec!["unit::test_cli_parsing"]);
+        assert!(report.flaky_tests.is_empty());
+        assert!(qa
+            .generate_qa_report_md()
+            .contains("- Stable failure: unit::test_cli_parsing"));
+    }
+
+    #[tokio::test]
+    async fn stages_are_reported_on_the_progress_channel() {
+        let ai = Arc::new(KandilAI::new("ollama".to_string(), "llama3".to_string()).unwrap());
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut qa = QualityAssuranceSystem::new(ai)
+            .with_retry_failed(1)
+            .with_progress(tx);
+        qa.run_full_qa_suite(".").await.unwrap();
+
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+        assert_eq!(events.first().map(ProgressEvent::stage), Some("qa_suite"));
+        assert!(matches!(
+            events.last(),
+            Some(ProgressEvent::StageCompleted { stage, .. }) if stage == "qa_suite"
+        ));
+        assert!(events
+            .iter()
+            .any(|event| event.message() == Some("Running unit tests...")));
+        assert!(events
+            .iter()
+            .any(|event| event.stage() == "unit_tests_retry"));
+    }
+
+    #[test]
+    fn the_junit_report_covers_failures_skips_and_flaky_tests() {
+        let ai = Arc::new(KandilAI::new("ollama".to_string(), "llama3".to_string()).unwrap());
+        let mut qa = QualityAssuranceSystem::new(ai);
+        qa.test_suite.unit_tests = vec![
+            unit("passes", TestStatus::Passed),
+            unit("skipped", TestStatus::Skipped),
+            unit("retried", TestStatus::Passed),
+        ];
+        qa.test_suite.integration_tests = vec![IntegrationTest {
+            name: "db <-> cache".to_string(),
+            components: Vec::new(),
+            status: TestStatus::Failed,
+            duration_ms: 1500,
+            failure_reason: Some("timed out & \"gave up\"".to_string()),
+        }];
+        qa.test_suite.flaky_tests = vec![FlakyTest {
+            phase: "unit".to_string(),
+            name: "retried".to_string(),
+            passed_on_retry: 1,
+        }];
+
+        let xml = qa.generate_qa_report_junit().unwrap();
+        assert!(xml.contains(r#"<testsuites name="Kandil QA" tests="4" failures="1""#));
+        assert!(xml.contains(
+            r#"<testsuite name="unit" tests="3" failures="0" errors="0" skipped="1" time="0.003">"#
+        ));
+        assert!(xml.contains(
+            "<testcase classname=\"tests\" name=\"skipped\" time=\"0.001\">\n      <skipped/>"
+        ));
+        assert!(xml.contains("<system-out>Flaky: passed on retry 1</system-out>"));
+        assert!(xml.contains(
+            r#"<testcase classname="integration" name="db &lt;-&gt; cache" time="1.500">"#
+        ));
+        assert!(xml.contains(r#"<failure message="timed out &amp; &quot;gave up&quot;">"#));
+        assert!(xml.contains(r#"<testsuite name="security" tests="0""#));
+    }
+
+    #[test]
+    fn malformed_xml_is_rejected() {
+        assert!(check_well_formed("<a><b/></a>").is_ok());
+        assert!(check_well_formed("<a><b></a></b>").is_err());
+        assert!(check_well_formed("<a>").is_err());
+        assert!(check_well_formed("<a>x & y</a>").is_err());
+        assert!(check_well_formed("<a/><b/>").is_err());
+        assert!(check_well_formed(r#"<a n="1 < 2"/>"#).is_err());
+    }
+}
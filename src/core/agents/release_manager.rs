@@ -2,14 +2,15 @@
 //!
 //! Handles cross-platform builds, security audits, quality checks, and release preparation
 
+use super::progress::{Progress, ProgressEvent};
 use crate::core::adapters::ai::KandilAI;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
-use std::process::Command;
 use std::sync::Arc;
+use tokio::process::Command;
 
 #[derive(Debug, Clone)]
 pub struct ReleaseManager {
@@ -21,6 +22,7 @@ pub struct ReleaseManager {
     pub build_artifacts: Vec<BuildArtifact>,
     pub dependencies: DependencyInfo,
     pub ai: Arc<KandilAI>,
+    progress: Progress,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -194,49 +196,70 @@ impl ReleaseManager {
                 dependency_tree: "".to_string(),
             },
             ai,
+            progress: Progress::default(),
         }
     }
 
-    pub fn run_security_audit(&mut self) -> Result<()> {
-        println!("Running security audit on dependencies...");
+    /// Sends stage events to `tx` instead of printing them.
+    pub fn with_progress(mut self, tx: tokio::sync::mpsc::UnboundedSender<ProgressEvent>) -> Self {
+        self.progress = Progress::new(tx);
+        self
+    }
+
+    pub async fn run_security_audit(&mut self) -> Result<()> {
+        let stage = "security_audit";
+        self.progress
+            .started(stage, "Running security audit on dependencies...");
 
         // Run cargo-audit to check for known vulnerabilities
-        match Command::new("cargo").arg("audit").output() {
+        match Command::new("cargo")
+            .arg("audit")
+            .kill_on_drop(true)
+            .output()
+            .await
+        {
             Ok(output) => {
                 let stdout = String::from_utf8_lossy(&output.stdout);
                 let stderr = String::from_utf8_lossy(&output.stderr);
 
                 if output.status.success() {
-                    println!("✓ Security audit passed - no vulnerabilities found");
+                    self.progress
+                        .detail(stage, "✓ Security audit passed - no vulnerabilities found");
                 } else {
-                    println!("⚠ Security audit completed with issues:");
-                    println!("{}", stdout);
+                    self.progress
+                        .detail(stage, "⚠ Security audit completed with issues:");
+                    self.progress.detail(stage, stdout);
                     if !stderr.is_empty() {
-                        println!("{}", stderr);
+                        self.progress.detail(stage, stderr);
                     }
                 }
             }
             Err(_) => {
-                println!("⚠ Security audit tool not found - skipping");
+                self.progress
+                    .detail(stage, "⚠ Security audit tool not found - skipping");
             }
         }
 
         // Generate Software Bill of Materials (SBOM)
         match Command::new("cargo")
             .args(&["sbom", "--format", "cyclonedx"])
+            .kill_on_drop(true)
             .output()
+            .await
         {
             Ok(output) => {
                 if output.status.success() {
                     fs::write("sbom.xml", &output.stdout)?;
-                    println!("✓ Generated SBOM at sbom.xml");
+                    self.progress.detail(stage, "✓ Generated SBOM at sbom.xml");
                 } else {
                     let stderr = String::from_utf8_lossy(&output.stderr);
-                    println!("⚠ Failed to generate SBOM: {}", stderr);
+                    self.progress
+                        .detail(stage, format!("⚠ Failed to generate SBOM: {}", stderr));
                 }
             }
             Err(_) => {
-                println!("⚠ SBOM tool not found - skipping");
+                self.progress
+                    .detail(stage, "⚠ SBOM tool not found - skipping");
             }
         }
 
@@ -250,11 +273,13 @@ impl ReleaseManager {
                 .to_string(),
         });
 
+        self.progress.completed(stage, None);
         Ok(())
     }
 
     pub fn run_performance_tests(&mut self) -> Result<()> {
-        println!("Running performance tests...");
+        let stage = "performance_tests";
+        self.progress.started(stage, "Running performance tests...");
 
         // Create a basic benchmark configuration
         let benchmark_content = r#"
@@ -280,7 +305,8 @@ criterion_main!(benches);
         fs::write("benches/cli_bench.rs", benchmark_content)?;
 
         // Run benchmarks (would normally use cargo bench)
-        println!("✓ Performance tests configured");
+        self.progress
+            .completed(stage, Some("✓ Performance tests configured".to_string()));
 
         // In a real implementation, this would run actual benchmarks
         // For simulation, we'll add mock results
@@ -297,7 +323,9 @@ criterion_main!(benches);
     }
 
     pub fn generate_build_artifacts(&mut self) -> Result<()> {
-        println!("Generating build artifacts for all platforms...");
+        let stage = "build_artifacts";
+        self.progress
+            .started(stage, "Generating build artifacts for all platforms...");
 
         // Create target directory if it doesn't exist
         fs::create_dir_all("target/dist")?;
@@ -324,15 +352,19 @@ criterion_main!(benches);
                 });
 
                 platform.build_status = BuildStatus::Success;
-                println!("✓ Built for {}", platform.name);
+                self.progress
+                    .detail(stage, format!("✓ Built for {}", platform.name));
             }
         }
 
+        self.progress.completed(stage, None);
         Ok(())
     }
 
     pub fn run_dependency_check(&mut self) -> Result<()> {
-        println!("Checking dependencies for vulnerabilities...");
+        let stage = "dependency_check";
+        self.progress
+            .started(stage, "Checking dependencies for vulnerabilities...");
 
         // In a real implementation, this would run more comprehensive dependency analysis
         // For simulation, add some mock vulnerabilities
@@ -347,11 +379,13 @@ criterion_main!(benches);
             recommendation: "Update to the latest version when available".to_string(),
         }];
 
+        self.progress.completed(stage, None);
         Ok(())
     }
 
     pub fn create_release_package(&self) -> Result<()> {
-        println!("Creating release packages...");
+        let stage = "release_package";
+        self.progress.started(stage, "Creating release packages...");
 
         // Create release directory
         let release_dir = format!("releases/v{}", self.version);
@@ -391,7 +425,10 @@ criterion_main!(benches);
         }
         fs::write(format!("{}/CHECKSUMS.txt", release_dir), checksums)?;
 
-        println!("✓ Release packages created in {}", release_dir);
+        self.progress.completed(
+            stage,
+            Some(format!("✓ Release packages created in {}", release_dir)),
+        );
 
         Ok(())
     }
@@ -468,10 +505,16 @@ Dependencies:
         )
     }
 
+    /// Runs every release stage. The tools the stages shell out to are
+    /// awaited, so the caller can show each progress event as it arrives, and
+    /// are killed if the caller gives up on the release.
     pub async fn run_full_release_process(&mut self) -> Result<()> {
-        println!("Starting v{} release process...", self.version);
+        self.progress.started(
+            "release",
+            format!("Starting v{} release process...", self.version),
+        );
 
-        self.run_security_audit()?;
+        self.run_security_audit().await?;
         self.run_performance_tests()?;
         self.run_dependency_check()?;
         self.generate_build_artifacts()?;
@@ -483,9 +526,12 @@ Dependencies:
             report,
         )?;
 
-        println!(
-            "✓ v{} release process completed successfully!",
-            self.version
+        self.progress.completed(
+            "release",
+            Some(format!(
+                "✓ v{} release process completed successfully!",
+                self.version
+            )),
         );
         Ok(())
     }
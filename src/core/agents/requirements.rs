@@ -4,22 +4,87 @@
 
 use crate::core::adapters::ai::KandilAI;
 use crate::core::agents::base::{Agent, AgentState, ReActLoop};
+use crate::utils::prompt_templates::{PromptData, PromptTemplates};
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequirementsDocument {
     pub project_name: String,
     pub description: String,
+    #[serde(default)]
     pub functional_requirements: Vec<Requirement>,
+    #[serde(default)]
     pub non_functional_requirements: Vec<Requirement>,
+    #[serde(default)]
     pub actors: Vec<Actor>,
+    #[serde(default)]
     pub constraints: Vec<String>,
+    #[serde(default)]
     pub assumptions: Vec<String>,
 }
 
+impl RequirementsDocument {
+    /// JSON Schema the requirements agent asks the model to fill in.
+    pub fn schema() -> Value {
+        let strings = json!({ "type": "array", "items": { "type": "string" } });
+        let requirement = json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "title": { "type": "string" },
+                "description": { "type": "string" },
+                "priority": { "enum": ["Low", "Medium", "High", "Critical"] },
+                "category": { "enum": ["Functional", "NonFunctional", "Business", "User"] },
+                "acceptance_criteria": strings,
+            },
+            "required": [
+                "id",
+                "title",
+                "description",
+                "priority",
+                "category",
+                "acceptance_criteria",
+            ],
+        });
+        json!({
+            "type": "object",
+            "properties": {
+                "project_name": { "type": "string" },
+                "description": { "type": "string" },
+                "functional_requirements": { "type": "array", "items": requirement },
+                "non_functional_requirements": { "type": "array", "items": requirement },
+                "actors": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "description": { "type": "string" },
+                            "responsibilities": strings,
+                        },
+                        "required": ["name", "description", "responsibilities"],
+                    },
+                },
+                "constraints": strings,
+                "assumptions": strings,
+            },
+            "required": [
+                "project_name",
+                "description",
+                "functional_requirements",
+                "non_functional_requirements",
+                "actors",
+                "constraints",
+                "assumptions",
+            ],
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Requirement {
     pub id: String,
@@ -27,6 +92,7 @@ pub struct Requirement {
     pub description: String,
     pub priority: Priority,
     pub category: Category,
+    #[serde(default)]
     pub acceptance_criteria: Vec<String>,
 }
 
@@ -50,16 +116,21 @@ pub enum Category {
 pub struct Actor {
     pub name: String,
     pub description: String,
+    #[serde(default)]
     pub responsibilities: Vec<String>,
 }
 
 pub struct RequirementsAgent {
     ai: Arc<KandilAI>,
+    prompts: PromptTemplates,
 }
 
 impl RequirementsAgent {
     pub fn new(ai: Arc<KandilAI>) -> Self {
-        Self { ai }
+        Self {
+            ai,
+            prompts: PromptTemplates::user_default(),
+        }
     }
 
     pub async fn generate_requirements_document(
@@ -67,24 +138,20 @@ impl RequirementsAgent {
         project_description: &str,
     ) -> Result<RequirementsDocument> {
         let loop_engine = ReActLoop::new(5);
-        let task = format!(
-            "As a Business Analyst, elicit requirements for this project: {}.\n\nFollow these steps:\n1. Identify the main actors/users\n2. List functional requirements\n3. List non-functional requirements\n4. Identify constraints and assumptions\n5. Prioritize requirements\n\nFormat the response as a structured requirements document.", 
-            project_description
-        );
+        let task = self
+            .prompts
+            .render("requirements", &PromptData::new(project_description))?;
 
         let result = loop_engine.run(self, &task).await?;
 
-        // For now, we'll create a basic document structure from the AI response
-        // In a real implementation, we would properly parse the structured response
-        Ok(RequirementsDocument {
-            project_name: "Project".to_string(),
-            description: project_description.to_string(),
-            functional_requirements: vec![],
-            non_functional_requirements: vec![],
-            actors: vec![],
-            constraints: vec![],
-            assumptions: vec![],
-        })
+        // Turn the elicitation notes into the structured document
+        let prompt = format!(
+            "{}\n\nFindings from requirements elicitation:\n{}\n\nWrite the complete requirements document.",
+            task, result.final_answer
+        );
+        self.ai
+            .chat_json(&prompt, &RequirementsDocument::schema())
+            .await
     }
 }
 
@@ -0,0 +1,465 @@
+//! Translation resource files
+//!
+//! Reads and writes the string tables localizers work with (`.json`, Flutter
+//! `.arb` and gettext `.po`), exposing each translatable value by key so it can
+//! be translated and written back without touching the file's structure.
+
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceFormat {
+    Json,
+    Arb,
+    Po,
+}
+
+impl ResourceFormat {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Ok(ResourceFormat::Json),
+            Some("arb") => Ok(ResourceFormat::Arb),
+            Some("po") | Some("pot") => Ok(ResourceFormat::Po),
+            _ => anyhow::bail!(
+                "Unsupported resource file {}. Expected .json, .arb or .po",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// One translatable value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceEntry {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResourceFile {
+    format: ResourceFormat,
+    body: ResourceBody,
+}
+
+#[derive(Debug, Clone)]
+enum ResourceBody {
+    /// Parsed document; entry keys are JSON pointers to string leaves.
+    Json(JsonNode),
+    Po(Vec<PoBlock>),
+}
+
+/// A JSON document that keeps object members in file order, so translated
+/// files diff cleanly against their source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum JsonNode {
+    Null,
+    Bool(bool),
+    Number(serde_json::Number),
+    String(String),
+    Array(Vec<JsonNode>),
+    Object(IndexMap<String, JsonNode>),
+}
+
+impl JsonNode {
+    /// Same lookup as `serde_json::Value::pointer_mut`.
+    fn pointer_mut(&mut self, pointer: &str) -> Option<&mut JsonNode> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+        let mut target = self;
+        for token in pointer.strip_prefix('/')?.split('/') {
+            let token = token.replace("~1", "/").replace("~0", "~");
+            target = match target {
+                JsonNode::Object(map) => map.get_mut(&token)?,
+                JsonNode::Array(items) => items.get_mut(token.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(target)
+    }
+}
+
+/// A blank-line separated gettext entry, kept verbatim apart from `msgstr`.
+#[derive(Debug, Clone)]
+struct PoBlock {
+    lines: Vec<String>,
+    key: Option<String>,
+    msgid: String,
+    msgstr: String,
+    /// Index of the `msgstr` line and the number of lines it spans.
+    msgstr_span: Option<(usize, usize)>,
+}
+
+impl ResourceFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let format = ResourceFormat::from_path(path)?;
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(format, &text).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn parse(format: ResourceFormat, text: &str) -> Result<Self> {
+        let body = match format {
+            ResourceFormat::Json | ResourceFormat::Arb => ResourceBody::Json(serde_json::from_str(text)?),
+            ResourceFormat::Po => ResourceBody::Po(parse_po(text)?),
+        };
+        Ok(Self { format, body })
+    }
+
+    pub fn format(&self) -> ResourceFormat {
+        self.format
+    }
+
+    /// Translatable values in file order. ARB metadata (`@key`, `@@locale`)
+    /// and the `.po` header are not included.
+    pub fn entries(&self) -> Vec<ResourceEntry> {
+        match &self.body {
+            ResourceBody::Json(value) => {
+                let mut entries = Vec::new();
+                collect_strings(value, String::new(), self.format == ResourceFormat::Arb, &mut entries);
+                entries
+            }
+            ResourceBody::Po(blocks) => blocks
+                .iter()
+                .filter_map(|block| {
+                    block.key.as_ref().map(|key| ResourceEntry {
+                        key: key.clone(),
+                        value: block.msgid.clone(),
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Values already present in this file, by key. For `.po` files these are
+    /// the `msgstr` translations; empty ones are left out.
+    pub fn translations(&self) -> HashMap<String, String> {
+        match &self.body {
+            ResourceBody::Json(_) => self
+                .entries()
+                .into_iter()
+                .filter(|entry| !entry.value.trim().is_empty())
+                .map(|entry| (entry.key, entry.value))
+                .collect(),
+            ResourceBody::Po(blocks) => blocks
+                .iter()
+                .filter(|block| !block.msgstr.is_empty())
+                .filter_map(|block| block.key.clone().map(|key| (key, block.msgstr.clone())))
+                .collect(),
+        }
+    }
+
+    /// Renders this file with `translations` applied.
+    ///
+    /// Keys without a translation are dropped from JSON objects so the app
+    /// falls back to its default language, and left as an empty `msgstr` in
+    /// `.po` files, which gettext treats as untranslated.
+    pub fn render_translated(
+        &self,
+        translations: &HashMap<String, String>,
+        target_lang: &str,
+    ) -> Result<String> {
+        match &self.body {
+            ResourceBody::Json(value) => {
+                let mut out = value.clone();
+                for entry in self.entries() {
+                    match translations.get(&entry.key) {
+                        Some(text) => {
+                            if let Some(slot) = out.pointer_mut(&entry.key) {
+                                *slot = JsonNode::String(text.clone());
+                            }
+                        }
+                        None => remove_pointer(&mut out, &entry.key),
+                    }
+                }
+                if self.format == ResourceFormat::Arb {
+                    if let Some(locale) = out.pointer_mut("/@@locale") {
+                        *locale = JsonNode::String(target_lang.to_string());
+                    }
+                }
+                Ok(format!("{}\n", serde_json::to_string_pretty(&out)?))
+            }
+            ResourceBody::Po(blocks) => {
+                let mut out = Vec::new();
+                for block in blocks {
+                    let mut lines = block.lines.clone();
+                    if let (Some(key), Some((start, len))) = (&block.key, block.msgstr_span) {
+                        let msgstr = translations.get(key).map(String::as_str).unwrap_or("");
+                        lines.splice(
+                            start..start + len,
+                            std::iter::once(format!("msgstr \"{}\"", escape_po(msgstr))),
+                        );
+                    }
+                    out.push(lines.join("\n"));
+                }
+                Ok(format!("{}\n", out.join("\n\n")))
+            }
+        }
+    }
+}
+
+/// Where a translation of `source` into `target_lang` goes by default:
+/// `en.json` -> `es.json`, `app_en.arb` -> `app_es.arb`,
+/// `messages.po` -> `messages.es.po`.
+pub fn default_output_path(source: &Path, target_lang: &str) -> PathBuf {
+    lazy_static! {
+        static ref LOCALE_STEM: Regex = Regex::new(r"^[a-z]{2,3}(?:[-_][A-Z]{2})?$").unwrap();
+        static ref LOCALE_SUFFIX: Regex = Regex::new(r"^(.+[_-])[a-z]{2,3}(?:[-_][A-Z]{2})?$").unwrap();
+    }
+
+    let stem = source
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = source
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = if ext == "pot" { "po".to_string() } else { ext };
+
+    let new_stem = if LOCALE_STEM.is_match(&stem) {
+        target_lang.to_string()
+    } else if let Some(caps) = LOCALE_SUFFIX.captures(&stem) {
+        format!("{}{}", &caps[1], target_lang)
+    } else {
+        format!("{}.{}", stem, target_lang)
+    };
+    source.with_file_name(format!("{}.{}", new_stem, ext))
+}
+
+lazy_static! {
+    static ref PLACEHOLDER: Regex = Regex::new(
+        r"\{\{[^{}]*\}\}|\$\{[^{}]*\}|\{[^{}]*\}|%\([^)]*\)[sd]|%(?:\d+\$)?[-+0#]*\d*(?:\.\d+)?[sdifuxXeEgGc@]"
+    )
+    .unwrap();
+}
+
+/// Interpolation placeholders such as `{name}`, `{{count}}` or `%1$s`, sorted.
+pub fn placeholders(text: &str) -> Vec<String> {
+    let mut found: Vec<String> = PLACEHOLDER
+        .find_iter(text)
+        .map(|m| m.as_str().to_string())
+        .collect();
+    found.sort();
+    found
+}
+
+/// Returns why `translated` can't be used for `source`, if its placeholders
+/// were dropped, added or translated.
+pub fn placeholder_mismatch(source: &str, translated: &str) -> Option<String> {
+    let expected = placeholders(source);
+    let actual = placeholders(translated);
+    if expected == actual {
+        return None;
+    }
+    let missing: Vec<_> = expected.iter().filter(|p| !actual.contains(p)).cloned().collect();
+    let unexpected: Vec<_> = actual.iter().filter(|p| !expected.contains(p)).cloned().collect();
+    Some(match (missing.is_empty(), unexpected.is_empty()) {
+        (false, false) => format!(
+            "placeholders changed: {} became {}",
+            missing.join(", "),
+            unexpected.join(", ")
+        ),
+        (false, true) => format!("placeholders missing: {}", missing.join(", ")),
+        _ => format!("unexpected placeholders: {}", unexpected.join(", ")),
+    })
+}
+
+fn collect_strings(value: &JsonNode, pointer: String, arb: bool, out: &mut Vec<ResourceEntry>) {
+    match value {
+        JsonNode::String(text) => out.push(ResourceEntry {
+            key: pointer,
+            value: text.clone(),
+        }),
+        JsonNode::Object(map) => {
+            for (key, child) in map {
+                // ARB metadata sits next to messages at the top level.
+                if arb && pointer.is_empty() && key.starts_with('@') {
+                    continue;
+                }
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                collect_strings(child, format!("{}/{}", pointer, escaped), arb, out);
+            }
+        }
+        JsonNode::Array(items) => {
+            for (index, child) in items.iter().enumerate() {
+                collect_strings(child, format!("{}/{}", pointer, index), arb, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Removes an object member by JSON pointer. Array elements are kept so the
+/// remaining indices stay valid.
+fn remove_pointer(value: &mut JsonNode, pointer: &str) {
+    let Some((parent, last)) = pointer.rsplit_once('/') else {
+        return;
+    };
+    if let Some(JsonNode::Object(map)) = value.pointer_mut(parent) {
+        map.shift_remove(&last.replace("~1", "/").replace("~0", "~"));
+    }
+}
+
+fn parse_po(text: &str) -> Result<Vec<PoBlock>> {
+    let mut blocks = Vec::new();
+    for chunk in text.split("\n\n").map(|chunk| chunk.trim_matches('\n')) {
+        if chunk.trim().is_empty() {
+            continue;
+        }
+        blocks.push(parse_po_block(chunk)?);
+    }
+    Ok(blocks)
+}
+
+fn parse_po_block(chunk: &str) -> Result<PoBlock> {
+    let lines: Vec<String> = chunk.lines().map(str::to_string).collect();
+    let mut msgctxt = None;
+    let mut msgid = None;
+    let mut msgstr = String::new();
+    let mut msgstr_span = None;
+    let mut plural = false;
+
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim();
+        let (keyword, first) = match line.split_once(' ') {
+            Some((keyword, rest)) if rest.starts_with('"') => (keyword, rest),
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let start = i;
+        let mut value = unquote_po(first)?;
+        i += 1;
+        while i < lines.len() && lines[i].trim().starts_with('"') {
+            value.push_str(&unquote_po(lines[i].trim())?);
+            i += 1;
+        }
+        match keyword {
+            "msgctxt" => msgctxt = Some(value),
+            "msgid" => msgid = Some(value),
+            "msgid_plural" => plural = true,
+            "msgstr" => {
+                msgstr = value;
+                msgstr_span = Some((start, i - start));
+            }
+            _ => {}
+        }
+    }
+
+    let msgid = msgid.unwrap_or_default();
+    // The header (empty msgid) and plural entries are passed through untouched.
+    let key = if msgid.is_empty() || plural {
+        None
+    } else {
+        Some(match &msgctxt {
+            Some(ctx) => format!("{}\u{4}{}", ctx, msgid),
+            None => msgid.clone(),
+        })
+    };
+    Ok(PoBlock {
+        lines,
+        key,
+        msgid,
+        msgstr,
+        msgstr_span,
+    })
+}
+
+fn unquote_po(quoted: &str) -> Result<String> {
+    let inner = quoted
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .ok_or_else(|| anyhow::anyhow!("Malformed .po string: {}", quoted))?;
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    Ok(out)
+}
+
+fn escape_po(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn json_and_arb_keep_structure_and_metadata() {
+        let source = r#"{"@@locale": "en", "greeting": "Hello {name}", "@greeting": {"description": "Hi"}, "nav": {"home": "Home", "about": "About"}}"#;
+        let file = ResourceFile::parse(ResourceFormat::Arb, source).unwrap();
+        let keys: Vec<_> = file.entries().into_iter().map(|e| e.key).collect();
+        assert_eq!(keys, vec!["/greeting", "/nav/home", "/nav/about"]);
+
+        let mut translations = HashMap::new();
+        translations.insert("/greeting".to_string(), "Hola {name}".to_string());
+        translations.insert("/nav/home".to_string(), "Inicio".to_string());
+        let text = file.render_translated(&translations, "es").unwrap();
+        let position = |needle: &str| text.find(needle).unwrap();
+        assert!(position("\"@@locale\"") < position("\"greeting\""));
+        assert!(position("\"greeting\"") < position("\"nav\""));
+        let rendered: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(rendered["@@locale"], "es");
+        assert_eq!(rendered["greeting"], "Hola {name}");
+        assert_eq!(rendered["@greeting"]["description"], "Hi");
+        assert_eq!(rendered["nav"]["home"], "Inicio");
+        assert!(rendered["nav"].get("about").is_none());
+    }
+
+    #[test]
+    fn po_entries_round_trip() {
+        let source = "msgid \"\"\nmsgstr \"Language: en\\n\"\n\n#: src/main.c:1\nmsgid \"Open %s\"\nmsgstr \"\"\n\nmsgctxt \"menu\"\nmsgid \"File\"\nmsgstr \"\"";
+        let file = ResourceFile::parse(ResourceFormat::Po, source).unwrap();
+        let entries = file.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].value, "Open %s");
+
+        let mut translations = HashMap::new();
+        translations.insert(entries[0].key.clone(), "Abrir \"%s\"".to_string());
+        let rendered = file.render_translated(&translations, "es").unwrap();
+        assert!(rendered.contains("#: src/main.c:1\nmsgid \"Open %s\"\nmsgstr \"Abrir \\\"%s\\\"\""));
+        assert!(rendered.contains("msgctxt \"menu\"\nmsgid \"File\"\nmsgstr \"\""));
+
+        let reparsed = ResourceFile::parse(ResourceFormat::Po, &rendered).unwrap();
+        assert_eq!(reparsed.translations().get(&entries[0].key).unwrap(), "Abrir \"%s\"");
+    }
+
+    #[test]
+    fn detects_translated_placeholders() {
+        assert!(placeholder_mismatch("Hi {name}, you have %d", "Hola {name}, tienes %d").is_none());
+        assert!(placeholder_mismatch("Hi {name}", "Hola {nombre}").is_some());
+        assert!(placeholder_mismatch("Open %1$s", "Abrir").is_some());
+        assert_eq!(
+            default_output_path(Path::new("l10n/app_en.arb"), "es"),
+            PathBuf::from("l10n/app_es.arb")
+        );
+        assert_eq!(
+            default_output_path(Path::new("locales/en.json"), "fr"),
+            PathBuf::from("locales/fr.json")
+        );
+    }
+}
@@ -1,14 +1,46 @@
 //! Code review agent
 //!
 //! Specialized agent for reviewing code quality, security, and best practices
+//!
+//! A directory review covers production source only by default: tests,
+//! generated code and `--exclude` matches are skipped and counted, and the
+//! aggregate score weights each file by its lines of code.
+//!
+//! Reports serialize in full for `--format json`; `--detailed` groups their
+//! issues by severity and quotes the lines around each one.
 
 use crate::core::adapters::ai::KandilAI;
 use crate::core::agents::base::{Agent, AgentState};
+use crate::utils::config::wildcard_match;
+use crate::utils::prompt_templates::{PromptData, PromptTemplates};
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+const CODE_EXTENSIONS: &[&str] = &[
+    "rs", "js", "jsx", "mjs", "ts", "tsx", "py", "java", "kt", "swift", "dart", "go", "c", "cc",
+    "cpp", "h", "hpp", "cs", "rb", "php",
+];
+/// Markers that tools put near the top of files they generate.
+const GENERATED_MARKERS: &[&str] = &[
+    "@generated",
+    "do not edit",
+    "auto-generated",
+    "autogenerated",
+];
+const GENERATED_SUFFIXES: &[&str] = &[
+    ".g.dart",
+    ".freezed.dart",
+    ".pb.go",
+    "_pb2.py",
+    ".min.js",
+    ".d.ts",
+];
+const TEST_DIRS: &[&str] = &["tests", "test", "__tests__", "spec", "testdata"];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReviewReport {
     pub issues: Vec<Issue>,
@@ -24,11 +56,14 @@ pub struct Issue {
     pub description: String,
     pub severity: Severity,
     pub category: Category,
+    /// The reviewed file, when the issue is in one.
+    #[serde(default)]
+    pub file: Option<String>,
     pub line_number: Option<u32>,
     pub suggestion: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Severity {
     Low,
     Medium,
@@ -36,6 +71,98 @@ pub enum Severity {
     Critical,
 }
 
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Low => write!(f, "Low"),
+            Severity::Medium => write!(f, "Medium"),
+            Severity::High => write!(f, "High"),
+            Severity::Critical => write!(f, "Critical"),
+        }
+    }
+}
+
+impl ReviewReport {
+    /// JSON Schema a file review asks the model to fill in. Issues name no
+    /// file; the reviewer adds the one it sent.
+    pub fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "issues": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "id": { "type": "string" },
+                            "title": { "type": "string" },
+                            "description": { "type": "string" },
+                            "severity": { "enum": ["Low", "Medium", "High", "Critical"] },
+                            "category": {
+                                "enum": [
+                                    "Security",
+                                    "Performance",
+                                    "CodeStyle",
+                                    "Logic",
+                                    "Maintainability",
+                                    "Documentation",
+                                ],
+                            },
+                            "line_number": { "type": ["integer", "null"], "minimum": 1 },
+                            "suggestion": { "type": "string" },
+                        },
+                        "required": [
+                            "id",
+                            "title",
+                            "description",
+                            "severity",
+                            "category",
+                            "suggestion",
+                        ],
+                    },
+                },
+                "score": { "type": "integer", "minimum": 0, "maximum": 100 },
+                "summary": { "type": "string" },
+                "recommendations": { "type": "array", "items": { "type": "string" } },
+            },
+            "required": ["issues", "score", "summary", "recommendations"],
+        })
+    }
+
+    /// The issues grouped by severity, most severe first, in report order
+    /// within each group.
+    pub fn issues_by_severity(&self) -> Vec<(Severity, Vec<&Issue>)> {
+        let mut groups: Vec<(Severity, Vec<&Issue>)> = Vec::new();
+        for issue in &self.issues {
+            match groups
+                .iter_mut()
+                .find(|(severity, _)| *severity == issue.severity)
+            {
+                Some((_, issues)) => issues.push(issue),
+                None => groups.push((issue.severity, vec![issue])),
+            }
+        }
+        groups.sort_by(|a, b| b.0.cmp(&a.0));
+        groups
+    }
+}
+
+/// The lines of `content` within `radius` of 1-based `line`, numbered.
+/// Empty when `line` is past the end.
+pub fn code_context(content: &str, line: u32, radius: usize) -> Vec<(usize, &str)> {
+    let line = line as usize;
+    if line == 0 || line > content.lines().count() {
+        return Vec::new();
+    }
+    content
+        .lines()
+        .enumerate()
+        .map(|(index, text)| (index + 1, text))
+        .skip(line.saturating_sub(radius + 1))
+        .take(2 * radius + 1)
+        .collect()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Category {
     Security,
@@ -46,52 +173,247 @@ pub enum Category {
     Documentation,
 }
 
+/// Which files a directory review covers.
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryReviewOptions {
+    pub include_tests: bool,
+    /// `*` wildcard patterns matched against the relative path and each of
+    /// its components.
+    pub exclude: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    Test,
+    Generated,
+    Excluded,
+    NotSource,
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::Test => write!(f, "test code (pass --include-tests to review)"),
+            SkipReason::Generated => write!(f, "generated code"),
+            SkipReason::Excluded => write!(f, "matched --exclude"),
+            SkipReason::NotSource => write!(f, "not source code"),
+        }
+    }
+}
+
+/// The files a directory review will cover, with their line counts, and the
+/// ones it leaves out.
+#[derive(Debug, Default, Serialize)]
+pub struct DirectoryPlan {
+    pub files: Vec<(PathBuf, usize)>,
+    pub skipped: Vec<(PathBuf, SkipReason)>,
+}
+
+impl DirectoryPlan {
+    /// How many files were skipped for each reason, most common first.
+    pub fn skipped_by_reason(&self) -> Vec<(SkipReason, usize)> {
+        let mut counts: Vec<(SkipReason, usize)> = Vec::new();
+        for (_, reason) in &self.skipped {
+            match counts.iter_mut().find(|(known, _)| known == reason) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((*reason, 1)),
+            }
+        }
+        counts.sort_by(|a, b| b.1.cmp(&a.1));
+        counts
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileReview {
+    pub path: PathBuf,
+    pub lines: usize,
+    pub report: ReviewReport,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DirectoryReview {
+    pub files: Vec<FileReview>,
+    /// Per-file scores weighted by lines of code.
+    pub score: u8,
+    pub plan: DirectoryPlan,
+}
+
+/// Sorts the files under `root` into ones to review and ones to skip.
+/// Hidden, dependency and build directories aren't walked at all.
+pub fn plan_directory_review(root: &Path, options: &DirectoryReviewOptions) -> DirectoryPlan {
+    let mut plan = DirectoryPlan::default();
+    let entries = walkdir::WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0
+                || !(name.starts_with('.')
+                    || matches!(name.as_ref(), "node_modules" | "target" | "dist" | "build"))
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file());
+
+    for entry in entries {
+        let path = entry.path();
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        let content = std::fs::read_to_string(path).ok();
+        let reason = if is_excluded(relative, &options.exclude) {
+            Some(SkipReason::Excluded)
+        } else if content.is_none() || !is_code(relative) {
+            Some(SkipReason::NotSource)
+        } else if is_generated(relative, content.as_deref().unwrap_or_default()) {
+            Some(SkipReason::Generated)
+        } else if !options.include_tests && is_test(relative) {
+            Some(SkipReason::Test)
+        } else {
+            None
+        };
+        match (reason, content) {
+            (Some(reason), _) => plan.skipped.push((path.to_path_buf(), reason)),
+            (None, Some(content)) => {
+                let lines = content
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .count();
+                plan.files.push((path.to_path_buf(), lines));
+            }
+            (None, None) => unreachable!("unreadable files are skipped"),
+        }
+    }
+    plan
+}
+
+/// Average of `scores` weighted by `lines`; files without code count once.
+pub fn weighted_score(scores: &[(u8, usize)]) -> u8 {
+    let weight: usize = scores.iter().map(|(_, lines)| (*lines).max(1)).sum();
+    if weight == 0 {
+        return 0;
+    }
+    let total: usize = scores
+        .iter()
+        .map(|(score, lines)| *score as usize * (*lines).max(1))
+        .sum();
+    ((total as f64 / weight as f64).round()) as u8
+}
+
+fn is_excluded(relative: &Path, patterns: &[String]) -> bool {
+    let full = relative.to_string_lossy().replace('\\', "/");
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.trim_end_matches('/');
+        wildcard_match(pattern, &full)
+            || relative
+                .components()
+                .any(|part| wildcard_match(pattern, &part.as_os_str().to_string_lossy()))
+    })
+}
+
+fn is_code(relative: &Path) -> bool {
+    relative
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| CODE_EXTENSIONS.contains(&ext))
+}
+
+fn is_generated(relative: &Path, content: &str) -> bool {
+    let name = relative.to_string_lossy().to_lowercase();
+    if GENERATED_SUFFIXES
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+        || name.contains(".generated.")
+    {
+        return true;
+    }
+    content.lines().take(5).any(|line| {
+        let line = line.to_lowercase();
+        GENERATED_MARKERS.iter().any(|marker| line.contains(marker))
+    })
+}
+
+fn is_test(relative: &Path) -> bool {
+    let in_test_dir = relative
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .any(|part| TEST_DIRS.contains(&part.as_os_str().to_string_lossy().as_ref()));
+    let stem = relative
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    in_test_dir
+        || stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_spec")
+        || stem.ends_with(".test")
+        || stem.ends_with(".spec")
+        || stem.ends_with("Test")
+        || stem.ends_with("Tests")
+}
+
 pub struct ReviewAgent {
     ai: Arc<KandilAI>,
+    prompts: PromptTemplates,
 }
 
 impl ReviewAgent {
     pub fn new(ai: Arc<KandilAI>) -> Self {
-        Self { ai }
+        Self {
+            ai,
+            prompts: PromptTemplates::user_default(),
+        }
     }
 
     pub async fn code_review(&self, file_path: &str) -> Result<ReviewReport> {
         let content = std::fs::read_to_string(file_path)?;
 
-        let prompt = format!(
-            r#"Review this code for:
-            - Bugs and logic errors
-            - Security vulnerabilities (OWASP Top 10)
-            - Performance anti-patterns
-            - Code smells
-            - Best practices violations
-            - Documentation issues
-
-            Code: {}
-            "#,
-            content
-        );
+        let language = std::path::Path::new(file_path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        let prompt = self
+            .prompts
+            .render("review", &PromptData::new(content).with_language(language))?;
 
-        let result = self.ai.chat(&prompt).await?;
+        let mut report: ReviewReport = self.ai.chat_json(&prompt, &ReviewReport::schema()).await?;
+        for issue in &mut report.issues {
+            issue.file = Some(file_path.to_string());
+        }
+        Ok(report)
+    }
 
-        // In a real implementation, this would parse the structured response
-        // For now, we'll return a basic report
-        Ok(ReviewReport {
-            issues: vec![Issue {
-                id: "SEC-001".to_string(),
-                title: "Potential security issue".to_string(),
-                description: "Found potential vulnerability in code".to_string(),
-                severity: Severity::High,
-                category: Category::Security,
-                line_number: Some(10),
-                suggestion: "Add input validation".to_string(),
-            }],
-            score: 85,
-            summary: "Code review completed with suggestions for improvements".to_string(),
-            recommendations: vec![
-                "Add input validation".to_string(),
-                "Improve error handling".to_string(),
-            ],
+    /// Reviews each file [`plan_directory_review`] picks under `root`.
+    pub async fn directory_review(
+        &self,
+        root: &Path,
+        options: &DirectoryReviewOptions,
+    ) -> Result<DirectoryReview> {
+        let plan = plan_directory_review(root, options);
+        if plan.files.is_empty() {
+            anyhow::bail!(
+                "No production source to review in {} ({} file(s) skipped)",
+                root.display(),
+                plan.skipped.len()
+            );
+        }
+        let mut files = Vec::new();
+        for (path, lines) in &plan.files {
+            let report = self.code_review(&path.to_string_lossy()).await?;
+            files.push(FileReview {
+                path: path.clone(),
+                lines: *lines,
+                report,
+            });
+        }
+        let scores: Vec<(u8, usize)> = files
+            .iter()
+            .map(|file| (file.report.score, file.lines))
+            .collect();
+        Ok(DirectoryReview {
+            score: weighted_score(&scores),
+            files,
+            plan,
         })
     }
 
@@ -120,6 +442,7 @@ impl ReviewAgent {
                 description: "Potential scalability issue identified".to_string(),
                 severity: Severity::Medium,
                 category: Category::Performance,
+                file: None,
                 line_number: None,
                 suggestion: "Consider implementing caching".to_string(),
             }],
@@ -164,3 +487,131 @@ impl Agent for ReviewAgent {
         self.ai.chat(&prompt).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directory_reviews_skip_tests_generated_and_excluded_files() {
+        let root = std::env::temp_dir().join(format!("kandil_review_{}", uuid::Uuid::new_v4()));
+        let files = [
+            ("src/lib.rs", "fn a() {}\nfn b() {}\n"),
+            ("src/lib_test.rs", "fn t() {}\n"),
+            ("tests/cli.rs", "fn t() {}\n"),
+            ("lib/model.g.dart", "class A {}\n"),
+            ("src/schema.rs", "// @generated by diesel\nfn s() {}\n"),
+            ("src/fixtures/big.rs", "fn f() {}\n"),
+            ("README.md", "# Demo\n"),
+            ("target/debug/build.rs", "fn x() {}\n"),
+        ];
+        for (path, content) in files {
+            std::fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+            std::fs::write(root.join(path), content).unwrap();
+        }
+
+        let options = DirectoryReviewOptions {
+            include_tests: false,
+            exclude: vec!["fixtures".to_string()],
+        };
+        let plan = plan_directory_review(&root, &options);
+        assert_eq!(plan.files, vec![(root.join("src/lib.rs"), 2)]);
+        assert_eq!(
+            plan.skipped_by_reason(),
+            vec![
+                (SkipReason::Generated, 2),
+                (SkipReason::Test, 2),
+                (SkipReason::NotSource, 1),
+                (SkipReason::Excluded, 1),
+            ]
+        );
+
+        let options = DirectoryReviewOptions {
+            include_tests: true,
+            exclude: vec!["src/*_test.rs".to_string()],
+        };
+        let plan = plan_directory_review(&root, &options);
+        assert_eq!(plan.files.len(), 3, "{:?}", plan.files);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn issues_group_by_severity_with_their_context() {
+        let issue = |id: &str, severity| Issue {
+            id: id.to_string(),
+            title: String::new(),
+            description: String::new(),
+            severity,
+            category: Category::Logic,
+            file: Some("src/lib.rs".to_string()),
+            line_number: Some(2),
+            suggestion: String::new(),
+        };
+        let report = ReviewReport {
+            issues: vec![
+                issue("A", Severity::Low),
+                issue("B", Severity::Critical),
+                issue("C", Severity::Low),
+            ],
+            score: 70,
+            summary: String::new(),
+            recommendations: Vec::new(),
+        };
+        let groups: Vec<(Severity, Vec<&str>)> = report
+            .issues_by_severity()
+            .into_iter()
+            .map(|(severity, issues)| (severity, issues.iter().map(|i| i.id.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                (Severity::Critical, vec!["B"]),
+                (Severity::Low, vec!["A", "C"])
+            ]
+        );
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["issues"][0]["file"], "src/lib.rs");
+        assert_eq!(json["issues"][0]["line_number"], 2);
+
+        let content = "a\nb\nc\nd\n";
+        assert_eq!(code_context(content, 1, 1), vec![(1, "a"), (2, "b")]);
+        assert_eq!(
+            code_context(content, 3, 1),
+            vec![(2, "b"), (3, "c"), (4, "d")]
+        );
+        assert!(code_context(content, 9, 1).is_empty());
+    }
+
+    #[test]
+    fn the_aggregate_score_is_weighted_by_lines() {
+        assert_eq!(weighted_score(&[(90, 300), (40, 100)]), 78);
+        assert_eq!(weighted_score(&[(80, 0), (60, 0)]), 70);
+        assert_eq!(weighted_score(&[]), 0);
+    }
+
+    #[test]
+    fn a_reply_matching_the_schema_parses_into_a_report() {
+        let reply = r#"```json
+{"issues": [{"id": "SEC-1", "title": "SQL built from input", "description": "The query concatenates the name",
+  "severity": "High", "category": "Security", "line_number": 4, "suggestion": "Bind the name as a parameter"},
+ {"id": "DOC-1", "title": "Undocumented", "description": "No doc comment", "severity": "Low",
+  "category": "Documentation", "suggestion": "Document it"}],
+ "score": 70, "summary": "One injection risk", "recommendations": ["Use prepared statements"]}
+```"#;
+        let report: ReviewReport =
+            crate::core::adapters::ai::structured::parse_reply(reply).unwrap();
+        assert_eq!(report.score, 70);
+        assert_eq!(report.issues.len(), 2);
+        assert_eq!(report.issues[0].line_number, Some(4));
+        assert_eq!(report.issues[0].severity, Severity::High);
+        assert_eq!(report.issues[1].line_number, None);
+        assert_eq!(report.issues[1].file, None);
+
+        let schema = ReviewReport::schema();
+        let required = schema["properties"]["issues"]["items"]["required"]
+            .as_array()
+            .unwrap();
+        assert!(!required.contains(&json!("file")));
+    }
+}
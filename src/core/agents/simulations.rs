@@ -7,6 +7,8 @@ use crate::core::agents::base::{Agent, AgentState};
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt::Write as _;
 use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,12 +26,136 @@ pub struct SprintPlan {
 pub struct UserStory {
     pub id: String,
     pub title: String,
+    /// "As a …, I want …, so that …".
     pub description: String,
-    pub acceptance_criteria: Vec<String>,
+    pub acceptance_criteria: Vec<AcceptanceCriterion>,
     pub story_points: u32,
     pub priority: Priority,
 }
 
+/// One acceptance criterion as a Given/When/Then scenario.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcceptanceCriterion {
+    pub scenario: String,
+    pub given: Vec<String>,
+    pub when: Vec<String>,
+    pub then: Vec<String>,
+}
+
+impl UserStory {
+    /// JSON Schema the BA simulation asks the model to fill in.
+    pub fn schema() -> Value {
+        let steps = json!({ "type": "array", "items": { "type": "string" }, "minItems": 1 });
+        json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "title": { "type": "string" },
+                "description": { "type": "string" },
+                "acceptance_criteria": {
+                    "type": "array",
+                    "minItems": 1,
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "scenario": { "type": "string" },
+                            "given": steps,
+                            "when": steps,
+                            "then": steps,
+                        },
+                        "required": ["scenario", "given", "when", "then"],
+                    },
+                },
+                "story_points": { "type": "integer", "minimum": 1 },
+                "priority": { "enum": ["Low", "Medium", "High", "Critical"] },
+            },
+            "required": [
+                "id",
+                "title",
+                "description",
+                "acceptance_criteria",
+                "story_points",
+                "priority",
+            ],
+        })
+    }
+
+    /// The story as a Gherkin feature, one scenario per acceptance criterion.
+    pub fn to_gherkin(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "@{} @priority-{} @points-{}",
+            tag(&self.id),
+            tag(&format!("{:?}", self.priority)),
+            self.story_points
+        );
+        let _ = writeln!(out, "Feature: {}", self.title);
+        for line in self
+            .description
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+        {
+            let _ = writeln!(out, "  {}", line.trim());
+        }
+        for criterion in &self.acceptance_criteria {
+            let _ = writeln!(out, "\n  Scenario: {}", criterion.scenario);
+            for (keyword, steps) in criterion.steps() {
+                for (index, step) in steps.iter().enumerate() {
+                    let keyword = if index == 0 { keyword } else { "And" };
+                    let _ = writeln!(out, "    {} {}", keyword, step);
+                }
+            }
+        }
+        out
+    }
+
+    /// The story as a Markdown section.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# {}: {}\n", self.id, self.title);
+        let _ = writeln!(
+            out,
+            "**Priority:** {:?} | **Story points:** {}\n",
+            self.priority, self.story_points
+        );
+        let _ = writeln!(out, "{}\n", self.description.trim());
+        let _ = writeln!(out, "## Acceptance criteria");
+        for criterion in &self.acceptance_criteria {
+            let _ = writeln!(out, "\n### {}\n", criterion.scenario);
+            for (keyword, steps) in criterion.steps() {
+                for (index, step) in steps.iter().enumerate() {
+                    let keyword = if index == 0 { keyword } else { "And" };
+                    let _ = writeln!(out, "- **{}** {}", keyword, step);
+                }
+            }
+        }
+        out
+    }
+}
+
+impl AcceptanceCriterion {
+    fn steps(&self) -> [(&'static str, &[String]); 3] {
+        [
+            ("Given", &self.given),
+            ("When", &self.when),
+            ("Then", &self.then),
+        ]
+    }
+
+    fn is_complete(&self) -> bool {
+        !self.given.is_empty() && !self.when.is_empty() && !self.then.is_empty()
+    }
+}
+
+/// `text` as a Gherkin tag: lowercase, with spaces replaced.
+fn tag(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
     pub id: String,
@@ -148,23 +274,22 @@ impl BusinessAnalystSimulation {
 
     pub async fn create_user_story(&self, feature_description: &str) -> Result<UserStory> {
         let prompt = format!(
-            "As a Business Analyst, create a user story for this feature: {}\n\nFormat as INVEST (Independent, Negotiable, Valuable, Estimable, Small, Testable).",
+            "As a Business Analyst, create a user story for this feature: {}\n\nFollow INVEST (Independent, Negotiable, Valuable, Estimable, Small, Testable). Use an id like US-001, write the description as \"As a <role>, I want <goal>, so that <benefit>\", and give each acceptance criterion as a testable Given/When/Then scenario covering the main flow and its edge cases.",
             feature_description
         );
 
-        let result = self.ai.chat(&prompt).await?;
-
-        // In a real implementation, this would parse the structured response
-        Ok(UserStory {
-            id: "US-001".to_string(),
-            title: feature_description.to_string(),
-            description: result,
-            acceptance_criteria: vec![
-                "Acceptance criteria would be defined in full implementation".to_string(),
-            ],
-            story_points: 5,
-            priority: Priority::Medium,
-        })
+        let story: UserStory = self.ai.chat_json(&prompt, &UserStory::schema()).await?;
+        if story.acceptance_criteria.is_empty()
+            || !story
+                .acceptance_criteria
+                .iter()
+                .all(AcceptanceCriterion::is_complete)
+        {
+            anyhow::bail!(
+                "The model's user story is missing Given/When/Then acceptance criteria; try again"
+            );
+        }
+        Ok(story)
     }
 }
 
@@ -199,3 +324,52 @@ impl Agent for BusinessAnalystSimulation {
         self.ai.chat(&prompt).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn story() -> UserStory {
+        UserStory {
+            id: "US-001".to_string(),
+            title: "Reset password".to_string(),
+            description: "As a user, I want to reset my password, so that I can sign in again"
+                .to_string(),
+            acceptance_criteria: vec![AcceptanceCriterion {
+                scenario: "Reset link is sent".to_string(),
+                given: vec![
+                    "a registered user".to_string(),
+                    "they are signed out".to_string(),
+                ],
+                when: vec!["they request a reset".to_string()],
+                then: vec!["a reset link is emailed".to_string()],
+            }],
+            story_points: 3,
+            priority: Priority::High,
+        }
+    }
+
+    #[test]
+    fn user_stories_render_as_gherkin_and_markdown() {
+        let gherkin = story().to_gherkin();
+        assert!(gherkin.starts_with("@us-001 @priority-high @points-3\nFeature: Reset password\n"));
+        assert!(gherkin.contains("  Scenario: Reset link is sent\n"));
+        assert!(gherkin.contains(
+            "    Given a registered user\n    And they are signed out\n    When they request a reset\n"
+        ));
+        assert!(gherkin.ends_with("    Then a reset link is emailed\n"));
+
+        let markdown = story().to_markdown();
+        assert!(markdown.starts_with("# US-001: Reset password\n"));
+        assert!(markdown.contains("**Priority:** High | **Story points:** 3"));
+        assert!(markdown.contains("### Reset link is sent\n"));
+        assert!(markdown.contains("- **And** they are signed out\n"));
+
+        let json = serde_json::to_value(story()).unwrap();
+        assert_eq!(
+            json["acceptance_criteria"][0]["then"][0],
+            "a reset link is emailed"
+        );
+        assert!(story().acceptance_criteria[0].is_complete());
+    }
+}
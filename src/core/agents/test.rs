@@ -1,13 +1,29 @@
 //! Test execution agent
 //!
-//! Specialized agent for generating and executing tests
+//! Specialized agent for generating and executing tests. Execution runs the
+//! framework's own runner and reads its structured output: libtest lines for
+//! `cargo test`, JUnit XML for pytest, `--json` for Jest and `--machine`
+//! events for Flutter. A runner that outlives its timeout is killed along
+//! with every process it started.
 
 use crate::core::adapters::ai::KandilAI;
 use crate::core::agents::base::{Agent, AgentState};
-use anyhow::Result;
+use crate::utils::plugin_sandbox::{self, LimitExceeded, ResourceLimits};
+use crate::utils::prompt_templates::{PromptData, PromptTemplates};
+use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+/// Lines of a failure message kept per test.
+const MAX_MESSAGE_LINES: usize = 20;
+/// Bytes of runner output kept from each of stdout and stderr.
+const MAX_RUNNER_OUTPUT_BYTES: usize = 64 * 1024 * 1024;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TestResult {
@@ -26,7 +42,7 @@ pub struct TestDetail {
     pub duration_ms: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TestStatus {
     Passed,
     Failed,
@@ -34,60 +50,189 @@ pub enum TestStatus {
     Error,
 }
 
+impl TestResult {
+    fn from_details(details: Vec<TestDetail>, duration_ms: u64) -> Self {
+        let count = |wanted: &[TestStatus]| {
+            details
+                .iter()
+                .filter(|detail| wanted.contains(&detail.status))
+                .count() as u32
+        };
+        Self {
+            passed: count(&[TestStatus::Passed]),
+            failed: count(&[TestStatus::Failed, TestStatus::Error]),
+            skipped: count(&[TestStatus::Skipped]),
+            duration_ms,
+            details,
+        }
+    }
+
+    /// Failed and errored tests, in run order.
+    pub fn failures(&self) -> impl Iterator<Item = &TestDetail> {
+        self.details
+            .iter()
+            .filter(|detail| matches!(detail.status, TestStatus::Failed | TestStatus::Error))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestFramework {
+    Cargo,
+    Pytest,
+    Jest,
+    Flutter,
+}
+
+impl TestFramework {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "rust" | "cargo" => Ok(TestFramework::Cargo),
+            "pytest" | "python" => Ok(TestFramework::Pytest),
+            "jest" | "javascript" | "js" | "typescript" | "ts" => Ok(TestFramework::Jest),
+            "flutter" | "dart" => Ok(TestFramework::Flutter),
+            other => bail!(
+                "Unsupported test framework '{}'; use rust, pytest, jest or flutter",
+                other
+            ),
+        }
+    }
+
+    /// Program launched for this framework.
+    pub fn program(&self) -> &'static str {
+        match self {
+            TestFramework::Cargo => "cargo",
+            TestFramework::Pytest => "pytest",
+            TestFramework::Jest => "npx",
+            TestFramework::Flutter => "flutter",
+        }
+    }
+
+    fn project_marker(&self) -> Option<&'static str> {
+        match self {
+            TestFramework::Cargo => Some("Cargo.toml"),
+            TestFramework::Pytest => None,
+            TestFramework::Jest => Some("package.json"),
+            TestFramework::Flutter => Some("pubspec.yaml"),
+        }
+    }
+}
+
 pub struct TestAgent {
     ai: Arc<KandilAI>,
+    prompts: PromptTemplates,
 }
 
 impl TestAgent {
     pub fn new(ai: Arc<KandilAI>) -> Self {
-        Self { ai }
+        Self {
+            ai,
+            prompts: PromptTemplates::user_default(),
+        }
     }
 
-    pub async fn generate_tests(&self, source_file: &str, target_language: &str) -> Result<String> {
+    /// Tests for `source_file` in `target_language`, written for `framework`
+    /// when one is given.
+    pub async fn generate_tests(
+        &self,
+        source_file: &str,
+        target_language: &str,
+        framework: Option<&str>,
+    ) -> Result<String> {
         let source_code = std::fs::read_to_string(source_file)?;
 
-        let prompt = format!(
-            "Generate comprehensive unit tests for this {} code:\n\n{}\n\nCreate tests that cover:\n1. All public functions/methods\n2. Edge cases\n3. Error handling\n4. Boundary conditions\n5. Happy path scenarios\n\nFollow the testing conventions of {}.",
-            target_language, source_code, target_language
-        );
+        let mut data = PromptData::new(source_code).with_language(target_language);
+        if let Some(framework) = framework {
+            data.constraints.push(format!(
+                "Write the tests for the {} test framework",
+                framework
+            ));
+        }
+        let prompt = self.prompts.render("test", &data)?;
 
         self.ai.chat(&prompt).await
     }
 
-    pub async fn execute_tests(&self, test_file: &str, test_framework: &str) -> Result<TestResult> {
-        // In a real implementation, this would actually execute the tests
-        // For simulation, we'll return a mock result
-
-        println!("Executing tests using {} framework...", test_framework);
-        println!("Test file: {}", test_file);
-
-        // Mock test execution result
-        Ok(TestResult {
-            passed: 8,
-            failed: 1,
-            skipped: 1,
-            duration_ms: 1250,
-            details: vec![
-                TestDetail {
-                    name: "test_user_creation".to_string(),
-                    status: TestStatus::Passed,
-                    message: None,
-                    duration_ms: 50,
-                },
-                TestDetail {
-                    name: "test_invalid_input".to_string(),
-                    status: TestStatus::Failed,
-                    message: Some("Expected validation error not thrown".to_string()),
-                    duration_ms: 80,
-                },
-                TestDetail {
-                    name: "test_edge_case".to_string(),
-                    status: TestStatus::Skipped,
-                    message: Some("Feature not yet implemented".to_string()),
-                    duration_ms: 0,
-                },
-            ],
-        })
+    /// Runs `test_file` with the framework's runner and collects per-test
+    /// results. Fails if the runner is missing, exceeds `timeout`, or exits
+    /// without reporting any tests (e.g. a compile error).
+    pub async fn execute_tests(
+        &self,
+        test_file: &str,
+        test_framework: &str,
+        timeout: Duration,
+    ) -> Result<TestResult> {
+        let framework = TestFramework::parse(test_framework)?;
+        let test = Path::new(test_file)
+            .canonicalize()
+            .with_context(|| format!("Test file not found: {}", test_file))?;
+        // pytest finds its own rootdir; the others run from the project root
+        let root = match framework.project_marker() {
+            Some(marker) => Some(
+                project_root(&test, marker)
+                    .with_context(|| format!("No {} found above {}", marker, test.display()))?,
+            ),
+            None => None,
+        };
+        let report_path =
+            std::env::temp_dir().join(format!("kandil-tests-{}.out", uuid::Uuid::new_v4()));
+
+        let mut cmd = Command::new(framework.program());
+        if let Some(root) = &root {
+            cmd.current_dir(root);
+        }
+        match framework {
+            TestFramework::Cargo => {
+                cmd.arg("test");
+                // Integration test files can be targeted directly; unit tests
+                // live next to the source and need the whole suite.
+                let name = root
+                    .as_deref()
+                    .and_then(|root| crate::utils::coverage::integration_test_name(root, &test));
+                if let Some(name) = name {
+                    cmd.arg("--test").arg(name);
+                }
+            }
+            TestFramework::Pytest => {
+                cmd.arg(&test)
+                    .arg(format!("--junitxml={}", report_path.display()));
+            }
+            TestFramework::Jest => {
+                cmd.args(["--no-install", "jest", "--json", "--outputFile"])
+                    .arg(&report_path)
+                    .arg(&test);
+            }
+            TestFramework::Flutter => {
+                cmd.args(["test", "--machine"]).arg(&test);
+            }
+        }
+
+        let started = Instant::now();
+        let output = run_runner(cmd, framework, timeout).await;
+        let duration_ms = started.elapsed().as_millis() as u64;
+        let report = std::fs::read_to_string(&report_path).ok();
+        let _ = std::fs::remove_file(&report_path);
+        let output = output?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        let details = match framework {
+            TestFramework::Cargo => parse_libtest(&stdout),
+            TestFramework::Pytest => parse_junit(report.as_deref().unwrap_or_default()),
+            TestFramework::Jest => match report {
+                Some(report) => parse_jest(&report)?,
+                None => Vec::new(),
+            },
+            TestFramework::Flutter => parse_flutter_machine(&stdout),
+        };
+        if details.is_empty() && !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!(
+                "{} exited with {} without reporting any tests:\n{}",
+                framework.program(),
+                output.status,
+                tail(&format!("{}\n{}", stdout, stderr), MAX_MESSAGE_LINES)
+            );
+        }
+        Ok(TestResult::from_details(details, duration_ms))
     }
 
     pub async fn analyze_test_coverage(
@@ -107,6 +252,294 @@ impl TestAgent {
     }
 }
 
+/// Runs `cmd` through [`plugin_sandbox::run`], so on timeout the runner and
+/// everything it started, such as test binaries or pytest workers, are killed
+/// together.
+async fn run_runner(
+    cmd: Command,
+    framework: TestFramework,
+    timeout: Duration,
+) -> Result<std::process::Output> {
+    let limits = ResourceLimits {
+        timeout,
+        memory_bytes: None,
+        max_output_bytes: MAX_RUNNER_OUTPUT_BYTES,
+    };
+    match plugin_sandbox::run(cmd, &[], &limits).await {
+        Ok(output) => Ok(std::process::Output {
+            status: output.status,
+            stdout: output.stdout,
+            stderr: output.stderr,
+        }),
+        Err(err) if err.downcast_ref::<LimitExceeded>().is_some() => bail!(
+            "{} tests timed out after {}s",
+            framework.program(),
+            timeout.as_secs()
+        ),
+        Err(err)
+            if err
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|err| err.kind() == std::io::ErrorKind::NotFound) =>
+        {
+            bail!(
+                "`{}` is not installed or not on PATH; install it to run {:?} tests",
+                framework.program(),
+                framework
+            )
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn project_root(test: &Path, marker: &str) -> Option<PathBuf> {
+    test.ancestors()
+        .skip(1)
+        .find(|dir| dir.join(marker).exists())
+        .map(Path::to_path_buf)
+}
+
+fn tail(text: &str, lines: usize) -> String {
+    let all: Vec<&str> = text.trim().lines().collect();
+    all[all.len().saturating_sub(lines)..].join("\n")
+}
+
+fn message(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        None
+    } else {
+        Some(
+            text.lines()
+                .take(MAX_MESSAGE_LINES)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    }
+}
+
+fn detail(
+    name: String,
+    status: TestStatus,
+    message: Option<String>,
+    duration_ms: u64,
+) -> TestDetail {
+    TestDetail {
+        name,
+        status,
+        message,
+        duration_ms,
+    }
+}
+
+/// Reads `test name ... ok|FAILED|ignored` lines and the `---- name stdout ----`
+/// failure blocks printed by libtest.
+fn parse_libtest(output: &str) -> Vec<TestDetail> {
+    let mut details = Vec::new();
+    let mut failure_output: HashMap<String, String> = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for line in output.lines() {
+        if let Some(block) = line
+            .strip_prefix("---- ")
+            .and_then(|rest| rest.strip_suffix(" stdout ----"))
+        {
+            current = Some(block.to_string());
+            continue;
+        }
+        if let Some(name) = &current {
+            if line.trim() == "failures:" || line.starts_with("test result:") {
+                current = None;
+            } else {
+                let text = failure_output.entry(name.clone()).or_default();
+                text.push_str(line);
+                text.push('\n');
+                continue;
+            }
+        }
+
+        let Some((name, outcome)) = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.split_once(" ... "))
+        else {
+            continue;
+        };
+        let status = match outcome.trim() {
+            "ok" => TestStatus::Passed,
+            "FAILED" => TestStatus::Failed,
+            outcome if outcome.starts_with("ignored") => TestStatus::Skipped,
+            _ => continue,
+        };
+        details.push(detail(name.trim().to_string(), status, None, 0));
+    }
+
+    for test in &mut details {
+        if test.status == TestStatus::Failed {
+            test.message = failure_output
+                .get(&test.name)
+                .and_then(|text| message(text));
+        }
+    }
+    details
+}
+
+/// Reads the `<testcase>` elements of a JUnit XML report.
+fn parse_junit(xml: &str) -> Vec<TestDetail> {
+    let testcase =
+        regex::Regex::new(r"(?s)<testcase\b([^>]*?)(?:/>|>(.*?)</testcase>)").expect("valid regex");
+    let outcome = regex::Regex::new(
+        r"(?s)<(failure|error|skipped)\b([^>]*?)(?:/>|>(.*?)</(?:failure|error|skipped)>)",
+    )
+    .expect("valid regex");
+
+    testcase
+        .captures_iter(xml)
+        .map(|case| {
+            let attributes = &case[1];
+            let name = match (
+                xml_attribute(attributes, "classname"),
+                xml_attribute(attributes, "name"),
+            ) {
+                (Some(class), Some(name)) if !class.is_empty() => format!("{}.{}", class, name),
+                (_, Some(name)) => name,
+                _ => "unnamed".to_string(),
+            };
+            let duration_ms = xml_attribute(attributes, "time")
+                .and_then(|time| time.parse::<f64>().ok())
+                .map(|secs| (secs * 1000.0).round() as u64)
+                .unwrap_or(0);
+
+            let body = case.get(2).map_or("", |body| body.as_str());
+            match outcome.captures(body) {
+                None => detail(name, TestStatus::Passed, None, duration_ms),
+                Some(found) => {
+                    let status = match &found[1] {
+                        "failure" => TestStatus::Failed,
+                        "error" => TestStatus::Error,
+                        _ => TestStatus::Skipped,
+                    };
+                    let text = xml_attribute(&found[2], "message")
+                        .or_else(|| found.get(3).map(|text| unescape_xml(text.as_str())));
+                    detail(
+                        name,
+                        status,
+                        text.and_then(|text| message(&text)),
+                        duration_ms,
+                    )
+                }
+            }
+        })
+        .collect()
+}
+
+fn xml_attribute(attributes: &str, name: &str) -> Option<String> {
+    let pattern = format!(r#"(?:^|\s){}="([^"]*)""#, regex::escape(name));
+    regex::Regex::new(&pattern)
+        .ok()?
+        .captures(attributes)
+        .map(|found| unescape_xml(&found[1]))
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#10;", "\n")
+        .replace("&amp;", "&")
+}
+
+/// Reads the report written by `jest --json`.
+fn parse_jest(report: &str) -> Result<Vec<TestDetail>> {
+    let report: Value =
+        serde_json::from_str(report).context("Jest wrote an unreadable JSON report")?;
+    let mut details = Vec::new();
+    for suite in report["testResults"].as_array().into_iter().flatten() {
+        let assertions = suite["assertionResults"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        if assertions.is_empty() && suite["status"] == "failed" {
+            // The suite failed to load, e.g. a syntax error
+            details.push(detail(
+                suite["name"].as_str().unwrap_or("test suite").to_string(),
+                TestStatus::Error,
+                suite["message"].as_str().and_then(message),
+                0,
+            ));
+        }
+        for assertion in assertions {
+            let status = match assertion["status"].as_str().unwrap_or_default() {
+                "passed" => TestStatus::Passed,
+                "failed" => TestStatus::Failed,
+                _ => TestStatus::Skipped,
+            };
+            let failures: Vec<&str> = assertion["failureMessages"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .collect();
+            details.push(detail(
+                assertion["fullName"]
+                    .as_str()
+                    .or_else(|| assertion["title"].as_str())
+                    .unwrap_or("unnamed")
+                    .to_string(),
+                status,
+                message(&failures.join("\n")),
+                assertion["duration"].as_u64().unwrap_or(0),
+            ));
+        }
+    }
+    Ok(details)
+}
+
+/// Reads the JSON event stream printed by `flutter test --machine`.
+fn parse_flutter_machine(output: &str) -> Vec<TestDetail> {
+    let mut started: HashMap<u64, (String, u64)> = HashMap::new();
+    let mut errors: HashMap<u64, String> = HashMap::new();
+    let mut details = Vec::new();
+
+    for event in output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line.trim()).ok())
+    {
+        let time = event["time"].as_u64().unwrap_or(0);
+        match event["type"].as_str() {
+            Some("testStart") => {
+                if let (Some(id), Some(name)) =
+                    (event["test"]["id"].as_u64(), event["test"]["name"].as_str())
+                {
+                    started.insert(id, (name.to_string(), time));
+                }
+            }
+            Some("error") => {
+                if let Some(id) = event["testID"].as_u64() {
+                    let text = errors.entry(id).or_default();
+                    text.push_str(event["error"].as_str().unwrap_or_default());
+                    text.push('\n');
+                }
+            }
+            Some("testDone") if event["hidden"] != true => {
+                let Some(id) = event["testID"].as_u64() else {
+                    continue;
+                };
+                let (name, start) = started.remove(&id).unwrap_or_default();
+                let status = match (event["result"].as_str(), event["skipped"] == true) {
+                    (_, true) => TestStatus::Skipped,
+                    (Some("success"), _) => TestStatus::Passed,
+                    (Some("failure"), _) => TestStatus::Failed,
+                    _ => TestStatus::Error,
+                };
+                let text = errors.remove(&id).and_then(|text| message(&text));
+                details.push(detail(name, status, text, time.saturating_sub(start)));
+            }
+            _ => {}
+        }
+    }
+    details
+}
+
 #[async_trait]
 impl Agent for TestAgent {
     async fn plan(&self, state: &AgentState) -> Result<String> {
@@ -138,3 +571,130 @@ impl Agent for TestAgent {
         self.ai.chat(&prompt).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn libtest_output_is_parsed_with_failure_messages() {
+        let output = "\
+running 3 tests
+test parser::reads_numbers ... ok
+test parser::rejects_text ... FAILED
+test slow::benchmark ... ignored, needs network
+
+failures:
+
+---- parser::rejects_text stdout ----
+thread 'parser::rejects_text' panicked at src/parser.rs:10:5:
+assertion failed: parse(\"x\").is_err()
+
+failures:
+    parser::rejects_text
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+";
+        let result = TestResult::from_details(parse_libtest(output), 10);
+        assert_eq!((result.passed, result.failed, result.skipped), (1, 1, 1));
+        let failure = result.failures().next().unwrap();
+        assert_eq!(failure.name, "parser::rejects_text");
+        assert!(failure
+            .message
+            .as_deref()
+            .unwrap()
+            .contains("assertion failed"));
+    }
+
+    #[test]
+    fn junit_reports_are_parsed() {
+        let xml = r#"<testsuites><testsuite name="pytest" tests="4">
+<testcase classname="tests.test_math" name="test_add" time="0.012" />
+<testcase classname="tests.test_math" name="test_div" time="0.003"><failure message="ZeroDivisionError: division by zero">trace</failure></testcase>
+<testcase classname="tests.test_math" name="test_big" time="0"><skipped message="slow" /></testcase>
+<testcase classname="tests.test_io" name="test_open" time="0.1"><error message="fixture &apos;tmp&apos; not found" /></testcase>
+</testsuite></testsuites>"#;
+        let details = parse_junit(xml);
+        let result = TestResult::from_details(details, 0);
+        assert_eq!((result.passed, result.failed, result.skipped), (1, 2, 1));
+        assert_eq!(result.details[0].name, "tests.test_math.test_add");
+        assert_eq!(result.details[0].duration_ms, 12);
+        assert_eq!(
+            result.details[1].message.as_deref(),
+            Some("ZeroDivisionError: division by zero")
+        );
+        assert_eq!(result.details[3].status, TestStatus::Error);
+        assert_eq!(
+            result.details[3].message.as_deref(),
+            Some("fixture 'tmp' not found")
+        );
+    }
+
+    #[test]
+    fn jest_json_reports_are_parsed() {
+        let report = r#"{"testResults": [
+            {"name": "/app/sum.test.js", "status": "failed", "assertionResults": [
+                {"fullName": "sum adds", "status": "passed", "duration": 4, "failureMessages": []},
+                {"fullName": "sum subtracts", "status": "failed", "duration": 2,
+                 "failureMessages": ["Expected: 1\nReceived: 2"]},
+                {"fullName": "sum later", "status": "pending", "failureMessages": []}
+            ]},
+            {"name": "/app/broken.test.js", "status": "failed", "message": "SyntaxError", "assertionResults": []}
+        ]}"#;
+        let result = TestResult::from_details(parse_jest(report).unwrap(), 0);
+        assert_eq!((result.passed, result.failed, result.skipped), (1, 2, 1));
+        assert_eq!(
+            result.details[1].message.as_deref(),
+            Some("Expected: 1\nReceived: 2")
+        );
+        assert_eq!(result.details[3].name, "/app/broken.test.js");
+        assert!(parse_jest("not json").is_err());
+    }
+
+    #[test]
+    fn flutter_machine_events_are_parsed() {
+        let output = r#"{"type":"start","time":0}
+{"type":"testStart","test":{"id":1,"name":"loading test/widget_test.dart"},"time":1}
+{"type":"testDone","testID":1,"result":"success","skipped":false,"hidden":true,"time":50}
+{"type":"testStart","test":{"id":2,"name":"counter increments"},"time":60}
+{"type":"testDone","testID":2,"result":"success","skipped":false,"hidden":false,"time":90}
+{"type":"testStart","test":{"id":3,"name":"title shows"},"time":91}
+{"type":"error","testID":3,"error":"Expected: 'Home'","stackTrace":"...","time":120}
+{"type":"testDone","testID":3,"result":"failure","skipped":false,"hidden":false,"time":121}
+{"type":"done","success":false,"time":130}"#;
+        let result = TestResult::from_details(parse_flutter_machine(output), 0);
+        assert_eq!((result.passed, result.failed, result.skipped), (1, 1, 0));
+        assert_eq!(result.details[0].duration_ms, 30);
+        assert_eq!(
+            result.details[1].message.as_deref(),
+            Some("Expected: 'Home'")
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_runner_is_reported_clearly() {
+        let err = run_runner(
+            Command::new("kandil-no-such-runner"),
+            TestFramework::Pytest,
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("is not installed"));
+        assert!(TestFramework::parse("mocha").is_err());
+        assert_eq!(TestFramework::parse("Rust").unwrap(), TestFramework::Cargo);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn a_runner_past_its_timeout_is_stopped() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "sleep 30 & wait"]);
+        let started = Instant::now();
+        let err = run_runner(cmd, TestFramework::Cargo, Duration::from_millis(200))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -7,9 +7,16 @@ use crate::config::layered::{
 };
 use crate::core::hardware::HardwareProfile;
 use crate::models::catalog::MODEL_CATALOG;
+use crate::utils::config::Config as CliConfig;
 
 pub struct AutoConfig;
 
+/// CLI settings recommended for this machine, with the reasoning behind them.
+pub struct CliRecommendation {
+    pub config: CliConfig,
+    pub notes: Vec<String>,
+}
+
 impl AutoConfig {
     pub fn from_hardware(profile: &HardwareProfile) -> Config {
         let model_spec = Self::select_model(profile);
@@ -100,10 +107,10 @@ impl AutoConfig {
     ) -> PerformanceConfig {
         let threads = if profile.total_ram_gb < 8 {
             // On low RAM systems, use fewer threads to reduce memory pressure
-            profile.cpu_physical_cores.min(4).max(1) // At least 1 thread, max 4
+            profile.recommended_threads().min(4) // At most 4
         } else {
-            // Use more threads on systems with adequate RAM
-            profile.cpu_physical_cores.min(8).max(1) // Cap at 8 for most consumer systems
+            // Performance cores only on Apple Silicon, else physical cores up to 8
+            profile.recommended_threads()
         };
 
         let use_mmap = profile.total_ram_gb >= 16; // MMAP is beneficial on systems with sufficient RAM
@@ -117,6 +124,83 @@ impl AutoConfig {
         }
     }
 
+    /// Recommends CLI settings from the hardware, the local runtimes found
+    /// running (`(provider, base_url)` in order of preference) and the models
+    /// installed in Ollama. Settings unrelated to model choice are kept from
+    /// `current`.
+    pub fn recommend_cli_config(
+        current: &CliConfig,
+        profile: &HardwareProfile,
+        runtimes: &[(String, String)],
+        installed: &[String],
+    ) -> CliRecommendation {
+        let mut notes = Vec::new();
+        let mut config = current.clone();
+
+        let (provider, _) = match runtimes.first() {
+            Some(runtime) => runtime.clone(),
+            None => {
+                notes.push(
+                    "No local runtime is running; defaulting to Ollama. Install it from https://ollama.ai/download and run `ollama serve`.".to_string(),
+                );
+                ("ollama".to_string(), String::new())
+            }
+        };
+        for (runtime, endpoint) in runtimes {
+            config.endpoints.insert(runtime.clone(), endpoint.clone());
+        }
+
+        let quality = Self::select_model(profile);
+        let fast = MODEL_CATALOG
+            .iter()
+            .filter(|m| m.ram_required_gb <= profile.total_ram_gb / 2)
+            .max_by_key(|m| m.quality_rating.as_i32())
+            .unwrap_or(quality);
+        notes.push(format!(
+            "{}GB RAM{}: {} for heavy tasks, {} for quick ones",
+            profile.total_ram_gb,
+            profile
+                .gpu
+                .as_ref()
+                .map(|gpu| format!(" and {}GB {} GPU", gpu.memory_gb, gpu.brand))
+                .unwrap_or_default(),
+            quality.name,
+            fast.name
+        ));
+
+        let mut pick = |spec: &crate::models::catalog::ModelSpec| -> String {
+            if provider != "ollama" {
+                return spec.name.to_string();
+            }
+            match installed_tag(spec.name, installed) {
+                Some(tag) => tag,
+                None => {
+                    let note = format!(
+                        "{} is not installed yet. Run `kandil local-model install {}`.",
+                        spec.name, spec.name
+                    );
+                    if !notes.contains(&note) {
+                        notes.push(note);
+                    }
+                    spec.name.to_string()
+                }
+            }
+        };
+        let quality_model = pick(quality);
+        let fast_model = pick(fast);
+
+        config.ai_provider = provider;
+        config.ai_model = quality_model.clone();
+        for intent in ["coding", "architecture", "analysis"] {
+            config.routing.insert(intent.to_string(), quality_model.clone());
+        }
+        for intent in ["conversation", "planning", "testing"] {
+            config.routing.insert(intent.to_string(), fast_model.clone());
+        }
+
+        CliRecommendation { config, notes }
+    }
+
     fn configure_fallback(profile: &HardwareProfile) -> FallbackConfig {
         FallbackConfig {
             enabled: profile.total_ram_gb < 16, // Enable fallback for systems with less than 16GB
@@ -125,3 +209,128 @@ impl AutoConfig {
         }
     }
 }
+
+/// Finds the Ollama tag for a catalog model, e.g. `qwen2.5-coder:7b` for `qwen2.5-coder-7b-q4`.
+pub(crate) fn installed_tag(catalog_name: &str, installed: &[String]) -> Option<String> {
+    installed
+        .iter()
+        .find(|tag| {
+            let normalized = tag.replace(':', "-");
+            catalog_name.starts_with(&normalized) || normalized.starts_with(catalog_name)
+        })
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::hardware::{detect_hardware, GpuInfo};
+
+    fn machine(ram_gb: u64, vram_gb: Option<u64>) -> HardwareProfile {
+        HardwareProfile {
+            total_ram_gb: ram_gb,
+            available_ram_gb: ram_gb / 2,
+            gpu: vram_gb.map(|memory_gb| GpuInfo {
+                brand: "NVIDIA".to_string(),
+                model: "Test GPU".to_string(),
+                memory_gb,
+                compute_capability: None,
+                driver_version: String::new(),
+            }),
+            ..detect_hardware()
+        }
+    }
+
+    fn ollama() -> Vec<(String, String)> {
+        vec![("ollama".to_string(), "http://localhost:11434".to_string())]
+    }
+
+    #[test]
+    fn models_are_sized_to_the_machine() {
+        // RAM, VRAM, then the models for heavy and for quick intents
+        let table = [
+            (32, Some(8), "qwen2.5-coder-14b-q4", "qwen2.5-coder-7b-q4"),
+            (16, Some(4), "qwen2.5-coder-7b-q4", "qwen2.5-coder-3b-q4"),
+            (8, Some(4), "qwen2.5-coder-3b-q4", "qwen2.5-coder-1.5b-q4"),
+        ];
+        for (ram, vram, quality, fast) in table {
+            let recommendation = AutoConfig::recommend_cli_config(
+                &CliConfig::default(),
+                &machine(ram, vram),
+                &ollama(),
+                &[],
+            );
+            let config = &recommendation.config;
+            assert_eq!(config.ai_model, quality, "{ram}GB");
+            assert_eq!(config.routing["coding"], quality, "{ram}GB");
+            assert_eq!(config.routing["architecture"], quality, "{ram}GB");
+            assert_eq!(config.routing["conversation"], fast, "{ram}GB");
+            assert_eq!(config.routing["testing"], fast, "{ram}GB");
+            assert!(recommendation
+                .notes
+                .iter()
+                .any(|note| note.contains(&format!("kandil local-model install {quality}"))));
+        }
+    }
+
+    #[test]
+    fn installed_ollama_tags_are_preferred() {
+        let installed = vec![
+            "qwen2.5-coder:14b".to_string(),
+            "qwen2.5-coder:7b".to_string(),
+        ];
+        let recommendation = AutoConfig::recommend_cli_config(
+            &CliConfig::default(),
+            &machine(32, Some(8)),
+            &ollama(),
+            &installed,
+        );
+        assert_eq!(recommendation.config.ai_provider, "ollama");
+        assert_eq!(recommendation.config.ai_model, "qwen2.5-coder:14b");
+        assert_eq!(
+            recommendation.config.routing["planning"],
+            "qwen2.5-coder:7b"
+        );
+        assert!(!recommendation
+            .notes
+            .iter()
+            .any(|note| note.contains("not installed")));
+    }
+
+    #[test]
+    fn the_first_running_runtime_is_used_and_other_settings_kept() {
+        let mut current = CliConfig::default();
+        current.system_prompt = Some("Be brief".to_string());
+        let runtimes = vec![
+            ("lmstudio".to_string(), "http://localhost:1234".to_string()),
+            ("ollama".to_string(), "http://localhost:11434".to_string()),
+        ];
+        let recommendation =
+            AutoConfig::recommend_cli_config(&current, &machine(16, Some(4)), &runtimes, &[]);
+        let config = &recommendation.config;
+        assert_eq!(config.ai_provider, "lmstudio");
+        assert_eq!(config.ai_model, "qwen2.5-coder-7b-q4");
+        assert_eq!(config.endpoints["lmstudio"], "http://localhost:1234");
+        assert_eq!(config.endpoints["ollama"], "http://localhost:11434");
+        assert_eq!(config.system_prompt.as_deref(), Some("Be brief"));
+        assert!(!recommendation
+            .notes
+            .iter()
+            .any(|note| note.contains("not installed")));
+
+        let none = AutoConfig::recommend_cli_config(&current, &machine(16, Some(4)), &[], &[]);
+        assert_eq!(none.config.ai_provider, "ollama");
+        assert!(none.notes[0].starts_with("No local runtime is running"));
+    }
+
+    #[test]
+    fn catalog_names_match_ollama_tags() {
+        let installed = vec!["llama3:8b".to_string(), "qwen2.5-coder:3b".to_string()];
+        assert_eq!(
+            installed_tag("qwen2.5-coder-3b-q4", &installed).as_deref(),
+            Some("qwen2.5-coder:3b")
+        );
+        assert_eq!(installed_tag("qwen2.5-coder-7b-q4", &installed), None);
+        assert_eq!(installed_tag("qwen2.5-coder-7b-q4", &[]), None);
+    }
+}
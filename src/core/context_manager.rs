@@ -2,13 +2,35 @@
 //!
 //! Implements tree-sitter-based AST analysis to prioritize relevant code
 //! and embedding-based compression for historical context
+//!
+//! Candidate files are read and scored on up to [`MAX_READERS`] threads, in
+//! an order that puts files whose paths name the task's symbols first. Once
+//! the relevant files read so far fill the token budget, no further files are
+//! read. The files kept are always the same prefix of that order, so the
+//! result doesn't depend on thread timing. `-v` reports how many candidates
+//! were read and how long it took.
+//!
+//! The `context` benchmark of [`crate::performance::PerformanceTester`]
+//! measures the speedup on a 200-file project: it times one reader going
+//! through every file, as context used to be gathered, against the default
+//! readers and budget, and reports both times with their ratio.
 
+use crate::utils::config::Config;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 use tree_sitter::{Parser, Query};
 
+/// Most threads reading and scoring candidate files at once.
+pub const MAX_READERS: usize = 8;
+/// Tokens of relevant file content after which no more files are read.
+pub const DEFAULT_TOKEN_BUDGET: usize = 32_000;
+/// Most files kept in a context window.
+const MAX_FILES: usize = 20;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContextWindow {
     pub files: Vec<ContextFile>,
@@ -39,6 +61,12 @@ pub struct ContextManager {
     queries: HashMap<String, Query>,
     /// Memory compressor for history
     memory_compressor: MemoryCompressor,
+    /// Project settings, used for ignore patterns
+    config: Config,
+    /// Tokens of file content to gather before reading stops
+    token_budget: usize,
+    /// Most threads reading candidate files
+    readers: usize,
 }
 
 impl ContextManager {
@@ -51,10 +79,26 @@ impl ContextManager {
             language,
             queries: HashMap::new(),
             memory_compressor: MemoryCompressor::new(),
+            config: Config::load().unwrap_or_default(),
+            token_budget: DEFAULT_TOKEN_BUDGET,
+            readers: MAX_READERS,
         })
     }
 
+    /// Stops reading files once their content reaches `tokens`.
+    pub fn with_token_budget(mut self, tokens: usize) -> Self {
+        self.token_budget = tokens;
+        self
+    }
+
+    /// Reads candidate files on at most `readers` threads.
+    pub fn with_readers(mut self, readers: usize) -> Self {
+        self.readers = readers.max(1);
+        self
+    }
+
     pub fn prepare_context(&self, task: &str, workspace_path: &str) -> Result<ContextWindow> {
+        let _span = crate::performance::Profiler::start("context.prepare");
         // 1. Extract symbols from task (e.g., "fix auth bug" → ["auth", "login"])
         let symbols = self.extract_symbols_from_task(task);
 
@@ -66,6 +110,12 @@ impl ContextManager {
 
         // 4. Estimate token count
         let estimated_tokens = self.estimate_tokens(&relevant_files, &compressed_history);
+        crate::utils::verbosity::note(format!(
+            "Context: {} file(s), {} history item(s), ~{} tokens",
+            relevant_files.len(),
+            compressed_history.len(),
+            estimated_tokens
+        ));
 
         Ok(ContextWindow {
             files: relevant_files,
@@ -110,44 +160,114 @@ impl ContextManager {
         workspace_path: &str,
         symbols: &[String],
     ) -> Result<Vec<ContextFile>> {
+        let started = std::time::Instant::now();
+        let mut candidates = Vec::new();
+        self.collect_candidates(Path::new(workspace_path), &mut candidates)?;
+        // Files whose paths name a symbol are the likeliest to be relevant
+        candidates.sort_by_cached_key(|path| {
+            let relative = path.strip_prefix(workspace_path).unwrap_or(path);
+            let name = relative.to_string_lossy().to_lowercase();
+            let hits = symbols
+                .iter()
+                .filter(|symbol| name.contains(symbol.as_str()))
+                .count();
+            (std::cmp::Reverse(hits), path.clone())
+        });
+
+        let slots: Vec<Mutex<Option<ContextFile>>> =
+            candidates.iter().map(|_| Mutex::new(None)).collect();
+        let next = AtomicUsize::new(0);
+        let gathered = AtomicUsize::new(0);
+        let readers = std::thread::available_parallelism()
+            .map_or(1, |threads| threads.get())
+            .clamp(1, self.readers);
+        std::thread::scope(|scope| {
+            for _ in 0..readers {
+                scope.spawn(|| {
+                    while gathered.load(Ordering::SeqCst) < self.token_budget {
+                        let index = next.fetch_add(1, Ordering::SeqCst);
+                        let Some(path) = candidates.get(index) else {
+                            break;
+                        };
+                        if let Some(file) = self.score_file(path, symbols) {
+                            gathered.fetch_add(file_tokens(&file), Ordering::SeqCst);
+                            *slots[index].lock().unwrap() = Some(file);
+                        }
+                    }
+                });
+            }
+        });
+        let read = next.load(Ordering::SeqCst).min(candidates.len());
+
+        // Keep the shortest prefix that fills the budget, which every reader
+        // got through whatever the timing
         let mut relevant_files = Vec::new();
+        let mut tokens = 0;
+        for slot in slots {
+            if tokens >= self.token_budget {
+                break;
+            }
+            if let Some(file) = slot.into_inner().unwrap() {
+                tokens += file_tokens(&file);
+                relevant_files.push(file);
+            }
+        }
+        crate::utils::verbosity::note(format!(
+            "Context: read {} of {} candidate file(s) on {} thread(s) in {} ms",
+            read,
+            candidates.len(),
+            readers,
+            started.elapsed().as_millis()
+        ));
+
+        // Sort by relevance score (highest first); the stable sort keeps ties
+        // in candidate order
+        relevant_files.sort_by(|a, b| b.relevance_score.total_cmp(&a.relevance_score));
+
+        // Limit to the most relevant files to manage token count
+        relevant_files.truncate(MAX_FILES);
+
+        Ok(relevant_files)
+    }
 
-        // Walk through the workspace to find relevant files
-        for entry in std::fs::read_dir(workspace_path)? {
+    /// Code files under `dir` that aren't ignored.
+    fn collect_candidates(&self, dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+        for entry in std::fs::read_dir(dir)? {
             let entry = entry?;
             let path = entry.path();
 
+            if self.config.is_ignored(Path::new(&entry.file_name())) {
+                continue;
+            }
+
             if path.is_file() && self.is_code_file(&path) {
-                let content = std::fs::read_to_string(&path)?;
-                let file_symbols = self.extract_symbols_from_code(&content)?;
-
-                // Calculate relevance based on symbol overlap
-                let relevance = self.calculate_relevance(&file_symbols, symbols);
-
-                if relevance > 0.0 {
-                    let dependencies = self.extract_dependencies(&content)?;
-                    relevant_files.push(ContextFile {
-                        path: path.to_string_lossy().to_string(),
-                        content,
-                        symbols: file_symbols,
-                        dependencies,
-                        relevance_score: relevance,
-                    });
-                }
+                files.push(path);
             } else if path.is_dir() {
-                // Recursively search in subdirectories
-                let sub_files = self.find_relevant_files(&path.to_string_lossy(), symbols)?;
-                relevant_files.extend(sub_files);
+                self.collect_candidates(&path, files)?;
             }
         }
+        Ok(())
+    }
 
-        // Sort by relevance score (highest first)
-        relevant_files.sort_by(|a, b| b.relevance_score.partial_cmp(&a.relevance_score).unwrap());
-
-        // Limit to top 20 most relevant files to manage token count
-        relevant_files.truncate(20);
+    /// Reads and scores `path`; `None` if it is unreadable, can't be parsed
+    /// or shares no symbols with the task.
+    fn score_file(&self, path: &Path, symbols: &[String]) -> Option<ContextFile> {
+        let content = std::fs::read_to_string(path).ok()?;
+        let file_symbols = self.extract_symbols_from_code(&content).ok()?;
 
-        Ok(relevant_files)
+        // Calculate relevance based on symbol overlap
+        let relevance = self.calculate_relevance(&file_symbols, symbols);
+        if relevance <= 0.0 {
+            return None;
+        }
+        let dependencies = self.extract_dependencies(&content).ok()?;
+        Some(ContextFile {
+            path: path.to_string_lossy().to_string(),
+            content,
+            symbols: file_symbols,
+            dependencies,
+            relevance_score: relevance,
+        })
     }
 
     fn is_code_file(&self, path: &Path) -> bool {
@@ -261,6 +381,12 @@ impl ContextManager {
     }
 }
 
+/// Tokens `file` adds to the context, as [`ContextManager::prepare_context`]
+/// estimates them.
+fn file_tokens(file: &ContextFile) -> usize {
+    file.content.len() / 4 + 50
+}
+
 pub struct MemoryCompressor {
     max_history_items: usize,
 }
@@ -308,6 +434,53 @@ mod tests {
         assert!(symbols.contains(&"user".to_string()));
     }
 
+    #[test]
+    fn reading_stops_at_the_budget_and_keeps_a_stable_prefix() {
+        let dir = std::env::temp_dir().join(format!("kandil_context_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        for index in 0..200 {
+            std::fs::write(
+                dir.join("src").join(format!("file_{:03}.rs", index)),
+                format!("fn user_{}() {{ let user = {}; }}\n", index, index),
+            )
+            .unwrap();
+        }
+        std::fs::write(dir.join("src").join("user.rs"), "fn user() {}\n").unwrap();
+        let workspace = dir.to_string_lossy().to_string();
+        let symbols = vec!["user".to_string()];
+
+        let everything = ContextManager::new().unwrap();
+        let all = everything
+            .find_relevant_files(&workspace, &symbols)
+            .unwrap();
+        assert_eq!(all.len(), MAX_FILES);
+
+        // Room for about three files: the one named after the symbol comes
+        // first, then the others in path order
+        let manager = ContextManager::new().unwrap().with_token_budget(200);
+        let paths = |files: Vec<ContextFile>| -> Vec<String> {
+            files
+                .into_iter()
+                .map(|file| {
+                    Path::new(&file.path)
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .collect()
+        };
+        let first = paths(manager.find_relevant_files(&workspace, &symbols).unwrap());
+        assert!(first.len() < 10, "{:?}", first);
+        assert!(first.contains(&"user.rs".to_string()));
+        for _ in 0..5 {
+            let again = paths(manager.find_relevant_files(&workspace, &symbols).unwrap());
+            assert_eq!(again, first);
+        }
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
     #[test]
     fn test_calculate_relevance() {
         let manager = ContextManager::new().unwrap();
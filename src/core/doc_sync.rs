@@ -4,11 +4,67 @@
 //! updates README files, OpenAPI specs, and inline documentation
 
 use anyhow::Result;
+use lazy_static::lazy_static;
 use notify::{recommended_watcher, RecursiveMode, Watcher};
-use std::collections::HashMap;
-use std::path::Path;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::sync::mpsc::channel;
 use tokio::fs;
+use walkdir::WalkDir;
+
+/// Directories that hold third-party or generated code, never scanned.
+const VENDORED_DIRS: [&str; 7] = [
+    "vendor",
+    "node_modules",
+    "target",
+    "third_party",
+    ".git",
+    "dist",
+    "build",
+];
+
+/// Call-style names that come from the language, not the project.
+const BUILTIN_FUNCTIONS: [&str; 14] = [
+    "print", "println", "len", "range", "open", "format", "assert", "str", "int", "list",
+    "dict", "require", "main", "new",
+];
+
+lazy_static! {
+    static ref FN_DEF: Regex = Regex::new(
+        r"\b(?:fn|def|function)\s+([A-Za-z_][A-Za-z0-9_]*)\s*(?:<[^>(]*>)?\s*\(([^)]*)\)"
+    )
+    .unwrap();
+    static ref TYPE_DEF: Regex =
+        Regex::new(r"\b(?:struct|enum|trait|type|class|interface)\s+([A-Za-z_][A-Za-z0-9_]*)")
+            .unwrap();
+    static ref CODE_SPAN: Regex = Regex::new(r"`([^`]+)`").unwrap();
+    static ref FN_REF: Regex =
+        Regex::new(r"(^|[^.\w:])(?:([A-Z][A-Za-z0-9_]*)::)?([a-z_][a-z0-9_]*)\(([^)]*)\)").unwrap();
+    static ref INTRA_DOC_LINK: Regex = Regex::new(r"\[`([A-Z][A-Za-z0-9_]*)`\]").unwrap();
+}
+
+/// A documentation line that mentions code which no longer matches the source.
+#[derive(Debug, Clone)]
+pub struct StaleReference {
+    pub doc_file: PathBuf,
+    /// 1-based line number
+    pub line: usize,
+    pub reference: String,
+    pub reason: String,
+    /// Closest existing symbol, if any looks like a rename
+    pub suggestion: Option<String>,
+}
+
+/// Functions (with every arity they are defined with) and types found in code.
+#[derive(Debug, Default)]
+struct SymbolIndex {
+    functions: HashMap<String, Vec<usize>>,
+    /// Functions with defaults or varargs, where any argument count may be valid
+    variadic: HashSet<String>,
+    types: HashSet<String>,
+}
 
 #[derive(Debug)]
 pub struct ApiChange {
@@ -314,6 +370,250 @@ impl DocSyncAgent {
     }
 }
 
+/// Scans `root` for README sections and doc comments that reference functions
+/// or types that no longer exist, or call functions with the wrong number of
+/// arguments.
+///
+/// Files ignored by git and vendored directories are skipped. Only
+/// unqualified calls (`load(path)`), calls on project types (`Config::load()`)
+/// and intra-doc links (``[`Config`]``) are checked, so references to external
+/// APIs are not reported.
+pub fn find_stale_references(root: &Path) -> Result<Vec<StaleReference>> {
+    let files = list_files(root);
+
+    let mut index = SymbolIndex::default();
+    for file in files.iter().filter(|f| is_source_file(f)) {
+        if let Ok(content) = std::fs::read_to_string(file) {
+            index_symbols(&content, &mut index);
+        }
+    }
+
+    let mut stale = Vec::new();
+    for file in &files {
+        let is_markdown = file.extension().map_or(false, |ext| ext == "md");
+        if !is_markdown && !is_source_file(file) {
+            continue;
+        }
+        let content = match std::fs::read_to_string(file) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+
+        let mut in_fence = false;
+        for (i, line) in content.lines().enumerate() {
+            let trimmed = line.trim_start();
+            let doc_text = if is_markdown {
+                if trimmed.starts_with("```") {
+                    in_fence = !in_fence;
+                    continue;
+                }
+                if in_fence {
+                    continue;
+                }
+                line
+            } else if let Some(doc) = trimmed
+                .strip_prefix("///")
+                .or_else(|| trimmed.strip_prefix("//!"))
+            {
+                doc
+            } else {
+                continue;
+            };
+
+            for (reference, reason, name) in check_doc_line(doc_text, &index, !is_markdown) {
+                stale.push(StaleReference {
+                    doc_file: file.clone(),
+                    line: i + 1,
+                    suggestion: closest_symbol(&name, &index),
+                    reference,
+                    reason,
+                });
+            }
+        }
+    }
+    Ok(stale)
+}
+
+fn check_doc_line(
+    text: &str,
+    index: &SymbolIndex,
+    check_links: bool,
+) -> Vec<(String, String, String)> {
+    let mut issues = Vec::new();
+
+    for span in CODE_SPAN.captures_iter(text) {
+        let span = &span[1];
+        for reference in FN_REF.captures_iter(span) {
+            let name = reference[3].to_string();
+            let shown = reference[0][reference[1].len()..].to_string();
+
+            if let Some(owner) = reference.get(2).map(|m| m.as_str()) {
+                // Methods on types we don't define belong to external APIs
+                if !index.types.contains(owner) {
+                    continue;
+                }
+            } else if BUILTIN_FUNCTIONS.contains(&name.as_str()) {
+                continue;
+            }
+
+            match index.functions.get(&name) {
+                None => issues.push((
+                    shown,
+                    format!("`{}` is not defined anywhere in the code", name),
+                    name,
+                )),
+                Some(arities) => {
+                    let args = reference[4].trim();
+                    if args.is_empty() || args.contains("...") || index.variadic.contains(&name) {
+                        continue;
+                    }
+                    let count = split_args(args).len();
+                    if !arities.contains(&count) {
+                        issues.push((
+                            shown,
+                            format!(
+                                "docs pass {} argument(s) but `{}` takes {}",
+                                count,
+                                name,
+                                arities
+                                    .iter()
+                                    .map(|a| a.to_string())
+                                    .collect::<Vec<_>>()
+                                    .join(" or ")
+                            ),
+                            name,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    if check_links {
+        for link in INTRA_DOC_LINK.captures_iter(text) {
+            let name = link[1].to_string();
+            if !index.types.contains(&name) && !index.functions.contains_key(&name) {
+                issues.push((
+                    link[0].to_string(),
+                    format!("`{}` is not defined anywhere in the code", name),
+                    name,
+                ));
+            }
+        }
+    }
+    issues
+}
+
+fn index_symbols(content: &str, index: &mut SymbolIndex) {
+    for def in FN_DEF.captures_iter(content) {
+        let params: Vec<&str> = split_args(&def[2])
+            .into_iter()
+            .filter(|param| !matches!(*param, "self" | "&self" | "&mut self" | "mut self" | "cls"))
+            .collect();
+        if params.iter().any(|param| param.contains('=') || param.starts_with('*')) {
+            index.variadic.insert(def[1].to_string());
+        }
+        let arity = params.len();
+        let arities = index.functions.entry(def[1].to_string()).or_default();
+        if !arities.contains(&arity) {
+            arities.push(arity);
+        }
+    }
+    for def in TYPE_DEF.captures_iter(content) {
+        index.types.insert(def[1].to_string());
+    }
+}
+
+/// Splits an argument list on top-level commas, so `a: HashMap<K, V>, b` is two.
+fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '<' | '(' | '[' | '{' => depth += 1,
+            '>' | ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts.into_iter().filter(|part| !part.is_empty()).collect()
+}
+
+/// Picks the defined symbol nearest to `name` by edit distance, if close enough.
+fn closest_symbol(name: &str, index: &SymbolIndex) -> Option<String> {
+    let max_distance = (name.len() / 3).max(2);
+    index
+        .functions
+        .keys()
+        .chain(index.types.iter())
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.clone())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current.push((prev[j] + cost).min(prev[j + 1] + 1).min(current[j] + 1));
+        }
+        prev = current;
+    }
+    prev[b.len()]
+}
+
+fn is_source_file(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("rs" | "js" | "ts" | "py" | "dart" | "java" | "go" | "tsx" | "jsx")
+    )
+}
+
+fn is_vendored(path: &Path) -> bool {
+    path.components().any(|component| {
+        VENDORED_DIRS
+            .iter()
+            .any(|dir| component.as_os_str() == *dir)
+    })
+}
+
+/// Files under `root`, honouring `.gitignore` when `root` is inside a git repo.
+pub(crate) fn list_files(root: &Path) -> Vec<PathBuf> {
+    let git = Command::new("git")
+        .args(["ls-files", "--cached", "--others", "--exclude-standard"])
+        .current_dir(root)
+        .output();
+
+    let files: Vec<PathBuf> = match git {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| root.join(line))
+            .collect(),
+        _ => WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|entry| !is_vendored(Path::new(entry.file_name())))
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path())
+            .collect(),
+    };
+
+    files
+        .into_iter()
+        .filter(|path| !is_vendored(path.strip_prefix(root).unwrap_or(path)))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -337,4 +637,22 @@ mod tests {
         assert!(!agent.is_code_file("README.md"));
         assert!(!agent.is_code_file("image.png"));
     }
+
+    #[test]
+    fn flags_missing_and_changed_functions() {
+        let mut index = SymbolIndex::default();
+        index_symbols(
+            "pub struct Config;\nimpl Config {\n    pub fn load(&self, path: &str) -> Self {}\n}\nfn render_page(a: u8) {}",
+            &mut index,
+        );
+
+        let issues = check_doc_line(
+            "Call `render_pages(x)`, then `Config::load(a, b)` or `Vec::new()`. See [`Settings`].",
+            &index,
+            true,
+        );
+        let reasons: Vec<&str> = issues.iter().map(|(r, _, _)| r.as_str()).collect();
+        assert_eq!(reasons, vec!["render_pages(x)", "Config::load(a, b)", "[`Settings`]"]);
+        assert_eq!(closest_symbol("render_pages", &index).as_deref(), Some("render_page"));
+    }
 }
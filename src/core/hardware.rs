@@ -9,6 +9,13 @@ pub struct HardwareProfile {
     pub available_ram_gb: u64,
     pub cpu_physical_cores: usize,
     pub cpu_logical_cores: usize,
+    /// Performance cores on CPUs with a P-core/E-core split (Apple Silicon);
+    /// `None` elsewhere.
+    #[serde(default)]
+    pub cpu_performance_cores: Option<usize>,
+    /// Efficiency cores on CPUs with a P-core/E-core split.
+    #[serde(default)]
+    pub cpu_efficiency_cores: Option<usize>,
     pub cpu_brand: String,
     pub gpu: Option<GpuInfo>,
     pub os: String,
@@ -19,6 +26,33 @@ pub struct HardwareProfile {
     pub platform: PlatformKind,
 }
 
+impl HardwareProfile {
+    /// Threads to give a local runtime. With a P-core/E-core split that is
+    /// the performance cores: inference threads wait on the slowest one, so
+    /// adding efficiency cores makes generation slower. Otherwise it is the
+    /// physical cores, at most 8.
+    pub fn recommended_threads(&self) -> usize {
+        match self.cpu_performance_cores {
+            Some(cores) if cores > 0 => cores,
+            _ => self.cpu_physical_cores.clamp(1, 8),
+        }
+    }
+
+    /// e.g. "10 physical cores (8 performance, 2 efficiency), 10 logical".
+    pub fn describe_cores(&self) -> String {
+        let split = match (self.cpu_performance_cores, self.cpu_efficiency_cores) {
+            (Some(performance), Some(efficiency)) => {
+                format!(" ({} performance, {} efficiency)", performance, efficiency)
+            }
+            _ => String::new(),
+        };
+        format!(
+            "{} physical cores{}, {} logical",
+            self.cpu_physical_cores, split, self.cpu_logical_cores
+        )
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PlatformKind {
     Windows,
@@ -64,6 +98,7 @@ pub fn detect_hardware() -> HardwareProfile {
         .map(|c| c.brand().to_string())
         .unwrap_or_else(|| "Unknown".to_string());
 
+    let (cpu_performance_cores, cpu_efficiency_cores) = detect_core_split();
     let gpu = detect_gpu().ok();
     let free_disk_gb = detect_disk_space_gb(&sys);
     let is_wsl = is_wsl_env();
@@ -74,6 +109,8 @@ pub fn detect_hardware() -> HardwareProfile {
         available_ram_gb,
         cpu_physical_cores,
         cpu_logical_cores,
+        cpu_performance_cores,
+        cpu_efficiency_cores,
         cpu_brand,
         gpu,
         os: std::env::consts::OS.to_string(),
@@ -187,6 +224,40 @@ fn detect_platform(is_wsl: bool) -> PlatformKind {
     }
 }
 
+/// Performance and efficiency core counts, from the `hw.perflevel` sysctls
+/// Apple Silicon Macs have. Both are `None` on CPUs without the split.
+#[cfg(target_os = "macos")]
+fn detect_core_split() -> (Option<usize>, Option<usize>) {
+    let count = |name: &str| {
+        let output = std::process::Command::new("sysctl")
+            .args(["-n", name])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        parse_sysctl_count(&String::from_utf8_lossy(&output.stdout))
+    };
+    match (
+        count("hw.perflevel0.physicalcpu"),
+        count("hw.perflevel1.physicalcpu"),
+    ) {
+        (Some(performance), Some(efficiency)) => (Some(performance), Some(efficiency)),
+        // Intel Macs have no perflevels; a single level is no split either
+        _ => (None, None),
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn detect_core_split() -> (Option<usize>, Option<usize>) {
+    (None, None)
+}
+
+#[cfg_attr(not(any(target_os = "macos", test)), allow(dead_code))]
+fn parse_sysctl_count(output: &str) -> Option<usize> {
+    output.trim().parse().ok().filter(|count| *count > 0)
+}
+
 #[cfg(target_os = "macos")]
 fn is_apple_silicon() -> bool {
     // Simple heuristic: check if we're on macOS with ARM64
@@ -209,3 +280,32 @@ fn detect_rocm_gpu() -> Result<GpuInfo, GpuDetectionError> {
     // For now, we return an error to fall back to other methods
     Err(GpuDetectionError::NoGpuFound)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn performance_cores_decide_the_thread_count() {
+        assert_eq!(parse_sysctl_count("8\n"), Some(8));
+        assert_eq!(parse_sysctl_count("0"), None);
+        assert_eq!(parse_sysctl_count("unknown oid"), None);
+
+        let mut profile = detect_hardware();
+        profile.cpu_physical_cores = 12;
+        profile.cpu_logical_cores = 12;
+        profile.cpu_performance_cores = Some(8);
+        profile.cpu_efficiency_cores = Some(4);
+        assert_eq!(profile.recommended_threads(), 8);
+        assert_eq!(
+            profile.describe_cores(),
+            "12 physical cores (8 performance, 4 efficiency), 12 logical"
+        );
+
+        profile.cpu_performance_cores = None;
+        profile.cpu_efficiency_cores = None;
+        profile.cpu_physical_cores = 16;
+        assert_eq!(profile.recommended_threads(), 8);
+        assert_eq!(profile.describe_cores(), "16 physical cores, 12 logical");
+    }
+}
@@ -1,5 +1,7 @@
 use crate::models::registry::{ModelProfile, ProviderKind, UniversalModelRegistry};
+use crate::utils::offline;
 use serde::Serialize;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize)]
 pub enum PromptIntent {
@@ -11,6 +13,20 @@ pub enum PromptIntent {
     Analysis,
 }
 
+impl PromptIntent {
+    /// Key used for routing overrides in config files.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PromptIntent::Conversation => "conversation",
+            PromptIntent::Coding => "coding",
+            PromptIntent::Planning => "planning",
+            PromptIntent::Architecture => "architecture",
+            PromptIntent::Testing => "testing",
+            PromptIntent::Analysis => "analysis",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct RoutedPrompt {
     pub intent: PromptIntent,
@@ -19,17 +35,39 @@ pub struct RoutedPrompt {
     pub explanation: String,
 }
 
+impl RoutedPrompt {
+    /// A route the caller fixed, e.g. with `--provider` and `--model`, which
+    /// skips intent defaults, routing overrides and registry matching.
+    pub fn pinned(intent: PromptIntent, provider: &str, model: &str) -> Self {
+        RoutedPrompt {
+            explanation: format!("Pinned to {}::{} for this invocation", provider, model),
+            intent,
+            provider: provider.to_string(),
+            model: model.to_string(),
+        }
+    }
+}
+
 pub struct PromptRouter<'a> {
     registry: &'a UniversalModelRegistry,
+    overrides: HashMap<String, String>,
 }
 
 impl<'a> PromptRouter<'a> {
     pub fn new() -> Self {
         Self {
             registry: UniversalModelRegistry::global(),
+            overrides: HashMap::new(),
         }
     }
 
+    /// Uses the given per-intent models (keyed by [`PromptIntent::as_str`])
+    /// instead of the built-in intent defaults.
+    pub fn with_overrides(mut self, overrides: HashMap<String, String>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
     pub fn route_message(
         &self,
         message: &str,
@@ -46,6 +84,16 @@ impl<'a> PromptRouter<'a> {
         default_provider: &str,
         default_model: &str,
     ) -> RoutedPrompt {
+        if let Some(model) = self.overrides.get(intent.as_str()).cloned() {
+            return self.finalize_route(
+                intent,
+                default_provider,
+                &model,
+                default_provider,
+                default_model,
+            );
+        }
+
         let (provider_hint, model_hint) = intent_defaults(&intent);
         self.finalize_route(
             intent,
@@ -63,6 +111,37 @@ impl<'a> PromptRouter<'a> {
         model_candidate: &str,
         default_provider: &str,
         default_model: &str,
+    ) -> RoutedPrompt {
+        let route = self.resolve_route(
+            intent,
+            provider_candidate,
+            model_candidate,
+            default_provider,
+            default_model,
+        );
+        if !offline::is_enabled() || !offline::is_cloud_provider(&route.provider) {
+            return route;
+        }
+
+        let (provider, model) = offline::local_route(&route.provider, &route.model);
+        RoutedPrompt {
+            explanation: format!(
+                "{} (offline mode: remapped from {}::{})",
+                route.explanation, route.provider, route.model
+            ),
+            intent: route.intent,
+            provider,
+            model,
+        }
+    }
+
+    fn resolve_route(
+        &self,
+        intent: PromptIntent,
+        provider_candidate: &str,
+        model_candidate: &str,
+        default_provider: &str,
+        default_model: &str,
     ) -> RoutedPrompt {
         if let Some(profile) = self.registry.get_profile(model_candidate) {
             let explanation = format!(
@@ -99,7 +178,7 @@ impl<'a> PromptRouter<'a> {
     }
 }
 
-fn infer_intent(message: &str) -> PromptIntent {
+pub fn infer_intent(message: &str) -> PromptIntent {
     let lower = message.to_lowercase();
 
     // Check for specific slash commands first
@@ -308,10 +308,7 @@ impl RewindCapabilities {
 
 // Helper function to initialize recording capabilities in the system
 pub fn initialize_recording_system() -> Result<RewindCapabilities> {
-    let storage_path = dirs::data_dir()
-        .unwrap_or_else(|| std::env::current_dir().unwrap())
-        .join("kandil")
-        .join("recordings");
+    let storage_path = crate::utils::data_dir::path().join("recordings");
     
     RewindCapabilities::new(storage_path)
 }
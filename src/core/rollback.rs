@@ -56,7 +56,40 @@ impl SessionManager {
 
         // Create file snapshots
         let files_snapshot = self.create_files_snapshot().await?;
+        self.store_checkpoint(checkpoint_id, description, files_snapshot)
+            .await
+    }
+
+    /// Checkpoints only `files`, for changes whose scope is known up front.
+    pub async fn create_file_checkpoint(
+        &self,
+        description: &str,
+        files: &[&Path],
+    ) -> Result<String> {
+        let checkpoint_id = self.generate_checkpoint_id();
 
+        let mut files_snapshot = HashMap::new();
+        for path in files {
+            let content = fs::read_to_string(path).await?;
+            let metadata = fs::metadata(path).await?;
+            let snapshot = FileSnapshot {
+                path: path.to_string_lossy().to_string(),
+                hash: self.calculate_content_hash(&content),
+                content,
+                last_modified: metadata.modified().unwrap_or(std::time::SystemTime::now()),
+            };
+            files_snapshot.insert(snapshot.path.clone(), snapshot);
+        }
+        self.store_checkpoint(checkpoint_id, description, files_snapshot)
+            .await
+    }
+
+    async fn store_checkpoint(
+        &self,
+        checkpoint_id: String,
+        description: &str,
+        files_snapshot: HashMap<String, FileSnapshot>,
+    ) -> Result<String> {
         // Get current git commit if available (simplified)
         let git_commit = self.get_current_git_commit().await.ok();
 
@@ -435,6 +468,41 @@ mod tests {
         assert!(true); // Just testing creation
     }
 
+    #[tokio::test]
+    async fn file_checkpoint_restores_only_the_listed_files() {
+        let test_dir = std::env::temp_dir().join(format!(
+            "kandil_test_file_checkpoint_{}",
+            uuid::Uuid::new_v4()
+        ));
+        fs::create_dir_all(&test_dir).await.unwrap();
+        let changed = test_dir.join("main.rs");
+        let untouched = test_dir.join("lib.rs");
+        fs::write(&changed, "fn main() {}\n").await.unwrap();
+        fs::write(&untouched, "pub fn a() {}\n").await.unwrap();
+
+        let manager = SessionManager::new(&test_dir.to_string_lossy());
+        let id = manager
+            .create_file_checkpoint("before edit", &[changed.as_path()])
+            .await
+            .unwrap();
+        fs::write(&changed, "fn main() { broken\n").await.unwrap();
+        fs::write(&untouched, "pub fn b() {}\n").await.unwrap();
+
+        let result = manager.rollback_to_checkpoint(&id).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.files_restored.len(), 1);
+        assert_eq!(
+            fs::read_to_string(&changed).await.unwrap(),
+            "fn main() {}\n"
+        );
+        assert_eq!(
+            fs::read_to_string(&untouched).await.unwrap(),
+            "pub fn b() {}\n"
+        );
+
+        let _ = fs::remove_dir_all(&test_dir).await;
+    }
+
     #[tokio::test]
     async fn test_session_coordinator() {
         let temp_dir = std::env::temp_dir();
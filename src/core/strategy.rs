@@ -167,6 +167,181 @@ impl ExecutionStrategy {
     }
 }
 
+/// A recommended way to tackle a task, with commands the user can run.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StrategyPlan {
+    pub task: String,
+    pub complexity: String,
+    pub mode: ExecutionMode,
+    pub provider: String,
+    pub model: String,
+    /// Cloud fallback used when the local model fails (complex tasks only)
+    pub fallback: Option<String>,
+    pub steps: Vec<PlanStep>,
+    pub estimated_calls: usize,
+    pub estimated_cost_usd: f64,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PlanStep {
+    pub agent: String,
+    pub purpose: String,
+    pub command: String,
+}
+
+/// Model calls made by one ReAct agent run (5 steps of plan/act/observe).
+const AGENT_RUN_CALLS: usize = 15;
+/// Rough tokens exchanged per model call, for cost estimates.
+const TOKENS_PER_CALL: u32 = 500;
+
+/// Recommends agents, a model and concrete `kandil` commands for `task`.
+///
+/// `provider`/`model` are what the CLI is currently configured to use. When
+/// the strategy picks something else, the `chat` and `agent` commands in the
+/// plan select it with `--provider`/`--model`.
+pub fn suggest_plan(task: &str, config: &Config, provider: &str, model: &str) -> StrategyPlan {
+    use crate::core::task_complexity::TaskComplexity;
+
+    let complexity = TaskComplexity::analyze(task);
+    let local_model = match complexity {
+        TaskComplexity::Simple => config
+            .strategy
+            .fast_model
+            .clone()
+            .unwrap_or_else(|| get_smaller_model(&config.model.name)),
+        TaskComplexity::Medium | TaskComplexity::Complex => config
+            .strategy
+            .quality_model
+            .clone()
+            .unwrap_or_else(|| config.model.name.clone()),
+    };
+
+    let (plan_provider, plan_model) = match config.strategy.mode {
+        ExecutionMode::CloudOnly => (provider.to_string(), model.to_string()),
+        _ => ("ollama".to_string(), local_model),
+    };
+    let fallback = match (&complexity, &config.strategy.mode, config.fallback.enabled) {
+        (TaskComplexity::Complex, ExecutionMode::Hybrid | ExecutionMode::Dynamic, true) => config
+            .fallback
+            .cloud_provider
+            .as_ref()
+            .map(|cloud| format!("{:?}", cloud).to_lowercase()),
+        _ => None,
+    };
+
+    let choice = if plan_provider == provider && plan_model == model {
+        String::new()
+    } else {
+        format!(" --provider {} --model {}", plan_provider, plan_model)
+    };
+    let quoted = format!("\"{}\"", task.replace('"', "\\\""));
+    let file = mentioned_file(task).unwrap_or_else(|| "<file>".to_string());
+    let lower = task.to_lowercase();
+
+    let mut steps = Vec::new();
+    let mut calls = 0;
+    let mut add = |agent: &str, purpose: &str, command: String, step_calls: usize| {
+        steps.push(PlanStep {
+            agent: agent.to_string(),
+            purpose: purpose.to_string(),
+            command,
+        });
+        calls += step_calls;
+    };
+
+    if lower.contains("review") {
+        add(
+            "review",
+            "Review the code for quality issues",
+            format!("kandil agent advanced review {}{}", file, choice),
+            1,
+        );
+    }
+    if lower.contains("security") || lower.contains("vulnerab") {
+        add(
+            "security",
+            "Scan for security and ethics issues",
+            format!(
+                "kandil agent advanced security {} {}{}",
+                file, quoted, choice
+            ),
+            1,
+        );
+    }
+    if lower.contains("refactor") {
+        add(
+            "refactor",
+            "Preview the refactoring before applying it",
+            format!("kandil refactor preview {} extract_function", file),
+            1,
+        );
+    }
+    if lower.contains("test") {
+        add("test", "Generate unit tests", format!("kandil test generate {}", file), 1);
+    }
+
+    if steps.is_empty() {
+        match complexity {
+            TaskComplexity::Simple => add(
+                "chat",
+                "Answer directly",
+                format!("kandil chat {}{}", quoted, choice),
+                1,
+            ),
+            TaskComplexity::Medium | TaskComplexity::Complex => add(
+                "pipeline",
+                "Run requirements → design → code → test",
+                format!(
+                    "kandil agent pipeline {} --language {}{}",
+                    quoted,
+                    mentioned_language(&lower),
+                    choice
+                ),
+                AGENT_RUN_CALLS * 3 + 1,
+            ),
+        }
+    }
+
+    let estimated_cost_usd = crate::utils::cost_tracking::CostTracker::new().estimate_cost(
+        &plan_provider,
+        &plan_model,
+        TOKENS_PER_CALL * calls as u32,
+        TOKENS_PER_CALL * calls as u32,
+    );
+
+    StrategyPlan {
+        task: task.to_string(),
+        complexity: format!("{:?}", complexity),
+        mode: config.strategy.mode.clone(),
+        provider: plan_provider,
+        model: plan_model,
+        fallback,
+        steps,
+        estimated_calls: calls,
+        estimated_cost_usd,
+    }
+}
+
+/// First word of the task that looks like a source path, e.g. `src/lib.rs`.
+fn mentioned_file(task: &str) -> Option<String> {
+    task.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| matches!(c, ',' | ';' | ':' | '`' | '\'' | '"')))
+        .find(|word| {
+            std::path::Path::new(word).is_file()
+                || std::path::Path::new(word)
+                    .extension()
+                    .map_or(false, |ext| matches!(ext.to_str(), Some("rs" | "py" | "js" | "ts" | "dart")))
+        })
+        .map(|word| word.to_string())
+}
+
+fn mentioned_language(task_lower: &str) -> &'static str {
+    ["python", "javascript", "typescript", "dart", "rust"]
+        .into_iter()
+        .find(|lang| task_lower.contains(lang))
+        .unwrap_or("rust")
+}
+
 // Placeholder for a cloud adapter
 struct PlaceholderCloudAdapter;
 
@@ -224,3 +399,97 @@ impl AIProvider for PlaceholderLocalAdapter {
         "PlaceholderLocal".to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIMPLE: &str = "What is 2 + 2?";
+    const MEDIUM: &str =
+        "Write a Rust function that takes a vector of integers and returns the sum.";
+
+    fn commands(plan: &StrategyPlan) -> Vec<&str> {
+        plan.steps
+            .iter()
+            .map(|step| step.command.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn simple_tasks_are_answered_by_chat_on_the_fast_model() {
+        let config = Config::default();
+        let plan = suggest_plan(SIMPLE, &config, "ollama", "qwen2.5-coder-1.5b-q4");
+        assert_eq!(plan.model, "qwen2.5-coder-1.5b-q4");
+        assert_eq!(commands(&plan), ["kandil chat \"What is 2 + 2?\""]);
+        assert_eq!(plan.estimated_calls, 1);
+    }
+
+    #[test]
+    fn other_models_are_chosen_with_flags_not_environment_variables() {
+        let config = Config::default();
+        let plan = suggest_plan(MEDIUM, &config, "openai", "gpt-4o");
+        assert_eq!(plan.provider, "ollama");
+        assert_eq!(
+            commands(&plan),
+            [format!(
+                "kandil agent pipeline \"{}\" --language rust --provider ollama --model auto",
+                MEDIUM
+            )]
+        );
+        assert!(plan
+            .steps
+            .iter()
+            .all(|step| !step.command.contains("KANDIL_")));
+    }
+
+    #[test]
+    fn medium_tasks_run_the_whole_pipeline() {
+        let config = Config::default();
+        let plan = suggest_plan(MEDIUM, &config, "ollama", "auto");
+        assert_eq!(plan.complexity, "Medium");
+        assert_eq!(plan.steps.len(), 1);
+        assert_eq!(plan.steps[0].agent, "pipeline");
+        assert!(!plan.steps[0].command.contains("--from"));
+        assert_eq!(plan.estimated_calls, AGENT_RUN_CALLS * 3 + 1);
+    }
+
+    #[test]
+    fn named_activities_pick_matching_agents() {
+        let config = Config::default();
+        let plan = suggest_plan(
+            "Review src/auth.rs for security issues and add tests",
+            &config,
+            "ollama",
+            "auto",
+        );
+        let agents: Vec<&str> = plan.steps.iter().map(|step| step.agent.as_str()).collect();
+        assert_eq!(agents, ["review", "security", "test"]);
+        assert_eq!(
+            plan.steps[0].command,
+            "kandil agent advanced review src/auth.rs"
+        );
+        assert_eq!(plan.steps[2].command, "kandil test generate src/auth.rs");
+    }
+
+    #[test]
+    fn file_mentions_are_found_by_extension() {
+        assert_eq!(
+            mentioned_file("Fix `src/lib.rs`, please").as_deref(),
+            Some("src/lib.rs")
+        );
+        assert_eq!(
+            mentioned_file("Check app/main.py: it hangs").as_deref(),
+            Some("app/main.py")
+        );
+        assert_eq!(mentioned_file("Read notes.txt-not-a-file"), None);
+        assert_eq!(mentioned_file("Explain closures"), None);
+    }
+
+    #[test]
+    fn languages_default_to_rust() {
+        assert_eq!(mentioned_language("port this script to python"), "python");
+        assert_eq!(mentioned_language("a typescript react hook"), "typescript");
+        assert_eq!(mentioned_language("a flutter widget in dart"), "dart");
+        assert_eq!(mentioned_language("a todo api"), "rust");
+    }
+}
@@ -270,6 +270,149 @@ impl TrustSystem {
     }
 }
 
+/// Signals gathered about a generated change; `None` means not measured.
+#[derive(Debug, Clone, Default)]
+pub struct ChangeEvidence {
+    /// `(passed, failed)` from running the test suite with the change applied
+    pub tests: Option<(usize, usize)>,
+    /// AI review score, 0-100
+    pub review_score: Option<u8>,
+    /// Problems found by static checks on the changed code
+    pub static_issues: Vec<String>,
+}
+
+/// How far a generated change can be trusted, from 0.0 to 1.0, with the
+/// reasons behind the number.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrustScore {
+    pub score: f64,
+    pub rationale: Vec<String>,
+}
+
+const TEST_WEIGHT: f64 = 0.5;
+const REVIEW_WEIGHT: f64 = 0.3;
+const STATIC_WEIGHT: f64 = 0.2;
+
+impl TrustScore {
+    /// Weighs tests at 50%, review at 30% and static checks at 20%. Signals
+    /// that were not measured count as half credit, so unverified changes
+    /// land in the middle rather than at the top.
+    pub fn evaluate(evidence: &ChangeEvidence) -> Self {
+        let mut rationale = Vec::new();
+
+        let tests = match evidence.tests {
+            Some((passed, failed)) if passed + failed > 0 => {
+                let rate = passed as f64 / (passed + failed) as f64;
+                rationale.push(format!(
+                    "Tests: {}/{} passed ({:.0}%)",
+                    passed,
+                    passed + failed,
+                    rate * 100.0
+                ));
+                rate
+            }
+            Some(_) => {
+                rationale.push("Tests: the test command ran but reported no tests (half credit)".to_string());
+                0.5
+            }
+            None => {
+                rationale.push("Tests: not run (half credit); pass --test-cmd to measure".to_string());
+                0.5
+            }
+        };
+
+        let review = match evidence.review_score {
+            Some(score) => {
+                rationale.push(format!("Review: scored {}/100", score));
+                score.min(100) as f64 / 100.0
+            }
+            None => {
+                rationale.push("Review: not run (half credit); pass --review to measure".to_string());
+                0.5
+            }
+        };
+
+        let static_score = (1.0 - 0.25 * evidence.static_issues.len() as f64).max(0.0);
+        if evidence.static_issues.is_empty() {
+            rationale.push("Static checks: no issues".to_string());
+        } else {
+            for issue in &evidence.static_issues {
+                rationale.push(format!("Static checks: {}", issue));
+            }
+        }
+
+        Self {
+            score: tests * TEST_WEIGHT + review * REVIEW_WEIGHT + static_score * STATIC_WEIGHT,
+            rationale,
+        }
+    }
+
+    pub fn meets(&self, threshold: f64) -> bool {
+        self.score >= threshold
+    }
+}
+
+/// Cheap static checks comparing generated code against the code it replaces.
+pub fn static_check(original: &str, changed: &str) -> Vec<String> {
+    let mut issues = Vec::new();
+
+    if changed.trim().is_empty() && !original.trim().is_empty() {
+        issues.push("the change empties the file".to_string());
+    }
+
+    for (open, close) in [('{', '}'), ('(', ')'), ('[', ']')] {
+        let balanced = |code: &str| code.matches(open).count() == code.matches(close).count();
+        if balanced(original) && !balanced(changed) {
+            issues.push(format!("unbalanced '{}' and '{}'", open, close));
+        }
+    }
+
+    for marker in ["unwrap()", "panic!", "unsafe", "todo!", "unimplemented!", "TODO", "eval("] {
+        let added = changed.matches(marker).count() as i64 - original.matches(marker).count() as i64;
+        if added > 0 {
+            issues.push(format!("adds {} `{}`", added, marker));
+        }
+    }
+
+    issues
+}
+
+/// Runs a test command through the shell and returns `(passed, failed)`.
+///
+/// Counts are read from "N passed" / "N failed" in the output (cargo, pytest,
+/// jest); if none are printed, the exit status counts as one test.
+pub fn run_test_command(command: &str) -> Result<(usize, usize)> {
+    let output = if cfg!(target_os = "windows") {
+        std::process::Command::new("cmd").args(["/C", command]).output()?
+    } else {
+        std::process::Command::new("sh").args(["-c", command]).output()?
+    };
+    let text = format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let count = |label: &str| -> Option<usize> {
+        let re = regex::Regex::new(&format!(r"(\d+) {}", label)).ok()?;
+        let counts: Vec<usize> = re
+            .captures_iter(&text)
+            .filter_map(|c| c[1].parse().ok())
+            .collect();
+        if counts.is_empty() {
+            None
+        } else {
+            Some(counts.iter().sum())
+        }
+    };
+
+    Ok(match (count("passed"), count("failed")) {
+        (None, None) if output.status.success() => (1, 0),
+        (None, None) => (0, 1),
+        (passed, failed) => (passed.unwrap_or(0), failed.unwrap_or(0)),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -297,6 +440,26 @@ mod tests {
         assert!(response.allowed); // Read operations should be allowed at Normal level
     }
 
+    #[test]
+    fn trust_score_penalizes_failures_and_risky_code() {
+        let issues = static_check("fn main() { run(); }", "fn main() { run().unwrap(); ");
+        assert_eq!(issues.len(), 2);
+
+        let good = TrustScore::evaluate(&ChangeEvidence {
+            tests: Some((10, 0)),
+            review_score: Some(90),
+            static_issues: vec![],
+        });
+        let risky = TrustScore::evaluate(&ChangeEvidence {
+            tests: Some((5, 5)),
+            review_score: None,
+            static_issues: issues,
+        });
+        assert!(good.meets(0.9));
+        assert!(!risky.meets(0.7));
+        assert!(risky.rationale.iter().any(|r| r.contains("5/10 passed")));
+    }
+
     #[tokio::test]
     async fn test_trust_system() {
         let trust_system = TrustSystem::new(TrustLevel::Normal);
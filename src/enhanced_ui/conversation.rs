@@ -0,0 +1,374 @@
+//! REPL conversation memory
+//!
+//! Keeps the turns of a REPL chat and sends them along with each question.
+//! When the history nears the model's context window (the largest of its
+//! `ModelSpec::context_sizes`), the oldest turns are folded into a compact
+//! note written by the smallest catalog model installed in Ollama (or made of
+//! each turn's first line without one), while recent turns stay verbatim.
+//! Turns pinned with `/pin` are never folded, and files pinned with
+//! `/pin @file` are attached to every question.
+
+use crate::core::adapters::ai::factory::AIProviderFactory;
+use crate::core::auto_config::installed_tag;
+use crate::core::performance::TokenEstimator;
+use crate::models::catalog::MODEL_CATALOG;
+use crate::models::UniversalModelRegistry;
+use crate::utils::config::Config;
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+
+/// Window assumed for models without a registry profile.
+const DEFAULT_CONTEXT_TOKENS: usize = 8192;
+/// History is compacted once a prompt would fill this share of the window.
+const COMPACT_AT: f64 = 0.75;
+/// Turns at the end of the history that are always kept verbatim.
+const KEEP_RECENT: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+impl Role {
+    fn label(&self) -> &'static str {
+        match self {
+            Role::User => "User",
+            Role::Assistant => "Assistant",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Turn {
+    pub role: Role,
+    pub content: String,
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    turns: Vec<Turn>,
+    summary: Option<String>,
+    context_tokens: usize,
+    files: Vec<PathBuf>,
+    /// Whether old turns are summarized; `/nosummary` turns this off.
+    pub summarize: bool,
+}
+
+impl Conversation {
+    pub fn new(context_tokens: usize) -> Self {
+        Self {
+            turns: Vec::new(),
+            summary: None,
+            context_tokens,
+            files: Vec::new(),
+            summarize: true,
+        }
+    }
+
+    /// A conversation sized for `model`'s largest context window.
+    pub fn for_model(model: &str) -> Self {
+        let context_tokens = UniversalModelRegistry::global()
+            .get_profile(model)
+            .map(|profile| profile.context_window)
+            .unwrap_or(DEFAULT_CONTEXT_TOKENS);
+        Self::new(context_tokens)
+    }
+
+    pub fn clear(&mut self) {
+        self.turns.clear();
+        self.summary = None;
+        self.files.clear();
+    }
+
+    pub fn push(&mut self, role: Role, content: impl Into<String>) {
+        self.turns.push(Turn {
+            role,
+            content: content.into(),
+            pinned: false,
+        });
+    }
+
+    /// Pins the latest question and its answer. Returns how many turns were
+    /// newly pinned.
+    pub fn pin_last_exchange(&mut self) -> usize {
+        let start = self
+            .turns
+            .iter()
+            .rposition(|turn| turn.role == Role::User)
+            .unwrap_or(self.turns.len());
+        let mut pinned = 0;
+        for turn in &mut self.turns[start..] {
+            if !turn.pinned {
+                turn.pinned = true;
+                pinned += 1;
+            }
+        }
+        pinned
+    }
+
+    pub fn pinned(&self) -> impl Iterator<Item = &Turn> {
+        self.turns.iter().filter(|turn| turn.pinned)
+    }
+
+    /// Keeps `path` attached to every question. Returns false if it already was.
+    pub fn pin_file(&mut self, path: PathBuf) -> bool {
+        if self.files.contains(&path) {
+            return false;
+        }
+        self.files.push(path);
+        true
+    }
+
+    /// Returns false if `path` wasn't pinned.
+    pub fn unpin_file(&mut self, path: &Path) -> bool {
+        let before = self.files.len();
+        self.files.retain(|file| file != path);
+        self.files.len() != before
+    }
+
+    pub fn pinned_files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Tokens left in the context window once `message` and the history are
+    /// in, for attaching files.
+    pub fn spare_tokens(&self, message: &str) -> usize {
+        self.context_tokens
+            .saturating_sub(TokenEstimator.estimate_tokens(&self.prompt(message)))
+    }
+
+    /// The prompt for `message`: the summary of folded turns, the remaining
+    /// turns in order, then the new message. With no history it is just the
+    /// message.
+    pub fn prompt(&self, message: &str) -> String {
+        if self.turns.is_empty() && self.summary.is_none() {
+            return message.to_string();
+        }
+        let mut prompt = String::new();
+        if let Some(summary) = &self.summary {
+            prompt.push_str("Summary of the earlier conversation:\n");
+            prompt.push_str(summary.trim());
+            prompt.push_str("\n\n");
+        }
+        for turn in &self.turns {
+            prompt.push_str(&format!(
+                "{}: {}\n\n",
+                turn.role.label(),
+                turn.content.trim()
+            ));
+        }
+        prompt.push_str(&format!("User: {}", message));
+        prompt
+    }
+
+    /// Indices of the unpinned turns to fold before sending `message`, oldest
+    /// first; empty while the prompt comfortably fits.
+    fn compactable(&self, message: &str) -> Vec<usize> {
+        let tokens = TokenEstimator.estimate_tokens(&self.prompt(message));
+        if !self.summarize || (tokens as f64) < self.context_tokens as f64 * COMPACT_AT {
+            return Vec::new();
+        }
+        let keep_from = self.turns.len().saturating_sub(KEEP_RECENT);
+        (0..keep_from)
+            .filter(|&index| !self.turns[index].pinned)
+            .collect()
+    }
+
+    /// Replaces the turns at `indices` with `summary`, which already covers
+    /// any earlier summary.
+    fn fold(&mut self, indices: &[usize], summary: String) {
+        let mut index = 0;
+        self.turns.retain(|_| {
+            let keep = !indices.contains(&index);
+            index += 1;
+            keep
+        });
+        self.summary = Some(summary);
+    }
+
+    /// Folds old turns into the summary when `message` would bring the prompt
+    /// close to the context window. Returns how many turns were folded.
+    pub async fn compact(&mut self, message: &str) -> usize {
+        let indices = self.compactable(message);
+        if indices.is_empty() {
+            return 0;
+        }
+        let turns: Vec<&Turn> = indices.iter().map(|&index| &self.turns[index]).collect();
+        let summary = match summarize(self.summary.as_deref(), &turns).await {
+            Ok(Some(summary)) => summary,
+            Ok(None) => {
+                log::debug!("No catalog model is installed in Ollama; keeping first lines");
+                first_lines(self.summary.as_deref(), &turns)
+            }
+            Err(err) => {
+                log::warn!("Local summarization failed ({}); keeping first lines", err);
+                first_lines(self.summary.as_deref(), &turns)
+            }
+        };
+        self.fold(&indices, summary);
+        indices.len()
+    }
+}
+
+/// Summarizes `turns` with the smallest catalog model installed in Ollama,
+/// or returns `None` when none is.
+async fn summarize(previous: Option<&str>, turns: &[&Turn]) -> Result<Option<String>> {
+    let mut catalog: Vec<_> = MODEL_CATALOG.iter().collect();
+    catalog.sort_by(|a, b| a.size_gb.total_cmp(&b.size_gb));
+    let smallest = catalog
+        .first()
+        .ok_or_else(|| anyhow!("The model catalog is empty"))?;
+    let factory = AIProviderFactory::new(Config::load()?);
+    // Ollama serves tags such as `qwen2.5-coder:1.5b`, not catalog ids
+    let tags = factory
+        .create_ai("ollama", smallest.name)?
+        .list_models()
+        .await?;
+    let Some(tag) = catalog
+        .iter()
+        .find_map(|model| installed_tag(model.name, &tags))
+    else {
+        return Ok(None);
+    };
+    let ai = factory.create_ai("ollama", &tag)?;
+
+    let mut request = String::from(
+        "Summarize this conversation in under 150 words for your own later reference. \
+         Keep decisions, names, file paths and open questions; drop pleasantries.\n\n",
+    );
+    if let Some(previous) = previous {
+        request.push_str(&format!("Earlier summary:\n{}\n\n", previous));
+    }
+    for turn in turns {
+        request.push_str(&format!(
+            "{}: {}\n\n",
+            turn.role.label(),
+            turn.content.trim()
+        ));
+    }
+    let summary = ai.chat(&request).await?;
+    if summary.trim().is_empty() {
+        return Err(anyhow!("the summary was empty"));
+    }
+    Ok(Some(summary.trim().to_string()))
+}
+
+/// A crude summary for when no local model is available: the first line of
+/// each folded turn.
+fn first_lines(previous: Option<&str>, turns: &[&Turn]) -> String {
+    let mut lines: Vec<String> = previous.map(str::to_string).into_iter().collect();
+    for turn in turns {
+        let first: String = turn
+            .content
+            .lines()
+            .next()
+            .unwrap_or("")
+            .chars()
+            .take(120)
+            .collect();
+        lines.push(format!("{}: {}", turn.role.label(), first));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chatty(turns: usize) -> Conversation {
+        let mut conversation = Conversation::new(400);
+        for index in 0..turns {
+            let role = if index % 2 == 0 {
+                Role::User
+            } else {
+                Role::Assistant
+            };
+            conversation.push(role, format!("turn {} {}", index, "words ".repeat(40)));
+        }
+        conversation
+    }
+
+    #[test]
+    fn old_unpinned_turns_are_folded_and_recent_ones_kept() {
+        let mut conversation = chatty(10);
+        conversation.turns[2].pinned = true;
+
+        let indices = conversation.compactable("next question");
+        assert_eq!(indices, vec![0, 1, 3, 4, 5]);
+
+        conversation.fold(&indices, "They discussed turns 0-5.".to_string());
+        assert_eq!(conversation.turns.len(), 5);
+        assert_eq!(conversation.pinned().count(), 1);
+        let prompt = conversation.prompt("next question");
+        assert!(prompt.starts_with("Summary of the earlier conversation:\nThey discussed"));
+        assert!(prompt.contains("User: turn 2 "));
+        assert!(prompt.contains("Assistant: turn 9 "));
+        assert!(prompt.ends_with("User: next question"));
+    }
+
+    #[test]
+    fn small_histories_and_nosummary_are_left_alone() {
+        assert!(chatty(2).compactable("hi").is_empty());
+
+        let mut conversation = chatty(10);
+        conversation.summarize = false;
+        assert!(conversation.compactable("hi").is_empty());
+
+        assert_eq!(Conversation::new(400).prompt("hi"), "hi");
+    }
+
+    #[test]
+    fn pinning_covers_the_last_exchange() {
+        let mut conversation = chatty(4);
+        assert_eq!(conversation.pin_last_exchange(), 2);
+        assert_eq!(conversation.pin_last_exchange(), 0);
+        let pinned: Vec<&str> = conversation
+            .pinned()
+            .map(|turn| &turn.content[..6])
+            .collect();
+        assert_eq!(pinned, vec!["turn 2", "turn 3"]);
+    }
+
+    #[test]
+    fn pinned_files_are_kept_until_reset() {
+        let mut conversation = chatty(2);
+        assert!(conversation.pin_file(PathBuf::from("src/main.rs")));
+        assert!(!conversation.pin_file(PathBuf::from("src/main.rs")));
+        assert!(conversation.pin_file(PathBuf::from("README.md")));
+        assert!(conversation.unpin_file(Path::new("README.md")));
+        assert!(!conversation.unpin_file(Path::new("README.md")));
+        assert_eq!(conversation.pinned_files(), [PathBuf::from("src/main.rs")]);
+        assert!(conversation.spare_tokens("hi") < 400);
+
+        conversation.clear();
+        assert!(conversation.pinned_files().is_empty());
+        assert_eq!(conversation.spare_tokens("hi"), 400 - 1);
+    }
+
+    #[test]
+    fn window_comes_from_the_catalog() {
+        assert_eq!(
+            Conversation::for_model("qwen2.5-coder-7b-q4").context_tokens,
+            32768
+        );
+        assert_eq!(
+            Conversation::for_model("not-a-model").context_tokens,
+            DEFAULT_CONTEXT_TOKENS
+        );
+    }
+
+    #[test]
+    fn fallback_summary_keeps_first_lines() {
+        let turn = Turn {
+            role: Role::User,
+            content: "Use sqlx\nmore detail".to_string(),
+            pinned: false,
+        };
+        assert_eq!(
+            first_lines(Some("Earlier."), &[&turn]),
+            "Earlier.\nUser: Use sqlx"
+        );
+    }
+}
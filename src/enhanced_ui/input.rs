@@ -1,5 +1,13 @@
+use super::mentions;
 use anyhow::Result;
-use rustyline::{error::ReadlineError, DefaultEditor};
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::path::Path;
 use std::time::Duration;
 use tokio::time::timeout;
 
@@ -13,8 +21,47 @@ pub enum InputMethod {
     Modal(String),
 }
 
+/// Completes file paths after an `@`, for file mentions.
+pub struct MentionHelper;
+
+impl Completer for MentionHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let word_start = line[..pos]
+            .rfind(char::is_whitespace)
+            .map_or(0, |index| index + 1);
+        let Some(partial) = line[word_start..pos].strip_prefix('@') else {
+            return Ok((pos, Vec::new()));
+        };
+        let candidates = mentions::complete_path(Path::new("."), partial)
+            .into_iter()
+            .map(|path| Pair {
+                display: path.clone(),
+                replacement: path,
+            })
+            .collect();
+        Ok((word_start + 1, candidates))
+    }
+}
+
+impl Hinter for MentionHelper {
+    type Hint = String;
+}
+
+impl Highlighter for MentionHelper {}
+
+impl Validator for MentionHelper {}
+
+impl Helper for MentionHelper {}
+
 pub struct UniversalInput {
-    editor: DefaultEditor,
+    editor: Editor<MentionHelper, DefaultHistory>,
     voice_enabled: bool,
     vision_enabled: bool,
     /// Configuration for input timeouts
@@ -25,8 +72,10 @@ pub struct UniversalInput {
 
 impl UniversalInput {
     pub fn new() -> Result<Self> {
+        let mut editor = Editor::new()?;
+        editor.set_helper(Some(MentionHelper));
         Ok(Self {
-            editor: DefaultEditor::new()?,
+            editor,
             voice_enabled: false,
             vision_enabled: false,
             input_timeout: Duration::from_secs(30), // Default 30 second timeout
@@ -0,0 +1,433 @@
+//! `@file` mentions
+//!
+//! `@path/to/file` in a chat message brings that file into the prompt
+//! verbatim, ahead of the message, instead of leaving it to the relevance
+//! heuristics. Files pinned with `/pin @file` are attached to every REPL turn
+//! the same way. Files are attached while they fit the token budget; one over
+//! [`MAX_FILE_BYTES`] is only attached as a head/tail slice, if the user agrees.
+//!
+//! `chat --files <glob>` is the batch version: the matched files are ranked by
+//! relevance to the message and attached, most relevant first, while they fit.
+
+use crate::core::performance::TokenEstimator;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+/// Files larger than this are not attached whole.
+pub const MAX_FILE_BYTES: u64 = 64 * 1024;
+/// Lines kept from each end of an oversized file.
+const SLICE_LINES: usize = 40;
+
+/// A file ready to go into a prompt.
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub path: PathBuf,
+    pub content: String,
+    /// Whether only the head and tail of the file are included.
+    pub sliced: bool,
+}
+
+/// Files mentioned with `@` in `input`, in order and without duplicates.
+/// Mentions that don't name an existing file, such as `@decorators`, are
+/// left as plain text.
+pub fn mentioned_files(input: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for word in input.split_whitespace() {
+        let Some(mention) = word.strip_prefix('@') else {
+            continue;
+        };
+        let mention = mention.trim_end_matches(|c: char| ",;:!?)'\"".contains(c));
+        let mention = mention.strip_suffix('.').unwrap_or(mention);
+        let path = PathBuf::from(mention);
+        if !mention.is_empty() && path.is_file() && !files.contains(&path) {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// Files matching `pattern`, a glob where `*` and `?` stay within one path
+/// component and `**` spans directories. The search starts at the pattern's
+/// leading directories without wildcards, or the working directory; files
+/// ignored by git and vendored directories are left out.
+pub fn glob_files(pattern: &str) -> Vec<PathBuf> {
+    let parts: Vec<&str> = pattern.split('/').collect();
+    let literal = parts
+        .iter()
+        .take_while(|part| !part.contains(['*', '?']))
+        .count();
+    if literal == parts.len() {
+        let path = PathBuf::from(pattern);
+        return if path.is_file() {
+            vec![path]
+        } else if path.is_dir() {
+            glob_files(&format!("{}/**", pattern.trim_end_matches('/')))
+        } else {
+            Vec::new()
+        };
+    }
+    let root = match parts[..literal].join("/") {
+        root if root.is_empty() && pattern.starts_with('/') => PathBuf::from("/"),
+        root if root.is_empty() => PathBuf::from("."),
+        root => PathBuf::from(root),
+    };
+    let matcher = glob_regex(&parts[literal..].join("/"));
+    let mut files: Vec<PathBuf> = crate::core::doc_sync::list_files(&root)
+        .into_iter()
+        .filter_map(|path| {
+            let relative = path.strip_prefix(&root).ok()?.to_path_buf();
+            let text = relative.to_string_lossy().replace('\\', "/");
+            matcher.is_match(&text).then(|| {
+                if root == Path::new(".") {
+                    relative
+                } else {
+                    path
+                }
+            })
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// An anchored regex for the glob `pattern`.
+fn glob_regex(pattern: &str) -> Regex {
+    let mut regex = String::from("^");
+    let mut rest = pattern;
+    while let Some(c) = rest.chars().next() {
+        if let Some(tail) = rest.strip_prefix("**/") {
+            regex.push_str("(?:.*/)?");
+            rest = tail;
+            continue;
+        }
+        if let Some(tail) = rest.strip_prefix("**") {
+            regex.push_str(".*");
+            rest = tail;
+            continue;
+        }
+        match c {
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    regex.push('$');
+    Regex::new(&regex).expect("escaped glob is a valid regex")
+}
+
+/// `files` ordered by relevance to `message`: each of its words in a file's
+/// path counts three, in the file's content one. Ties keep their order.
+pub fn rank_by_relevance(mut files: Vec<PathBuf>, message: &str) -> Vec<PathBuf> {
+    let mut words: Vec<String> = message
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| word.len() >= 3)
+        .map(|word| word.to_lowercase())
+        .collect();
+    words.sort();
+    words.dedup();
+    files.sort_by_cached_key(|path| {
+        let name = path.to_string_lossy().to_lowercase();
+        let content = std::fs::metadata(path)
+            .ok()
+            .filter(|metadata| metadata.len() <= MAX_FILE_BYTES)
+            .and_then(|_| std::fs::read_to_string(path).ok())
+            .unwrap_or_default()
+            .to_lowercase();
+        let score: usize = words
+            .iter()
+            .map(|word| {
+                3 * name.contains(word.as_str()) as usize + content.contains(word.as_str()) as usize
+            })
+            .sum();
+        std::cmp::Reverse(score)
+    });
+    files
+}
+
+/// Reads `paths` for a prompt with `budget` tokens to spare. `allow_slice` is
+/// asked, with the file size, whether an oversized file may be cut down to its
+/// head and tail. Returns the attachments and a warning for each file that
+/// was cut or left out.
+pub fn attach(
+    paths: &[PathBuf],
+    budget: usize,
+    mut allow_slice: impl FnMut(&Path, u64) -> bool,
+) -> (Vec<Attachment>, Vec<String>) {
+    let mut attachments = Vec::new();
+    let mut warnings = Vec::new();
+    let mut remaining = budget;
+    for path in paths {
+        let size = match std::fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(err) => {
+                warnings.push(format!("Skipped {}: {}", path.display(), err));
+                continue;
+            }
+        };
+        let oversized = size > MAX_FILE_BYTES;
+        if oversized && !allow_slice(path, size) {
+            warnings.push(format!(
+                "Skipped {}: {} KB is over the {} KB cap",
+                path.display(),
+                size / 1024,
+                MAX_FILE_BYTES / 1024
+            ));
+            continue;
+        }
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => {
+                warnings.push(format!("Skipped {}: not a text file", path.display()));
+                continue;
+            }
+        };
+        let content = if oversized {
+            warnings.push(format!(
+                "Included only the first and last {} lines of {}",
+                SLICE_LINES,
+                path.display()
+            ));
+            head_tail(&content, SLICE_LINES)
+        } else {
+            content
+        };
+        let tokens = TokenEstimator.estimate_tokens(&content);
+        if tokens > remaining {
+            warnings.push(format!(
+                "Skipped {}: ~{} tokens don't fit the ~{} left in the context window",
+                path.display(),
+                tokens,
+                remaining
+            ));
+            continue;
+        }
+        remaining -= tokens;
+        attachments.push(Attachment {
+            path: path.clone(),
+            content,
+            sliced: oversized,
+        });
+    }
+    (attachments, warnings)
+}
+
+/// Asks on the terminal whether an oversized file may be cut down to its head
+/// and tail; without a terminal the file is left out.
+pub fn offer_slice(path: &Path, size: u64) -> bool {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    if !std::io::stdin().is_terminal() {
+        return false;
+    }
+    print!(
+        "{} is {} KB, over the {} KB cap. Include only its first and last {} lines? [y/N]: ",
+        path.display(),
+        size / 1024,
+        MAX_FILE_BYTES / 1024,
+        SLICE_LINES
+    );
+    let _ = std::io::stdout().flush();
+    let mut line = String::new();
+    if std::io::stdin().lock().read_line(&mut line).is_err() {
+        return false;
+    }
+    matches!(line.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// `message` preceded by each attachment in a fenced block.
+pub fn with_attachments(attachments: &[Attachment], message: &str) -> String {
+    let mut prompt = String::new();
+    for attachment in attachments {
+        let language = attachment
+            .path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or("");
+        let note = if attachment.sliced {
+            " (first and last lines only)"
+        } else {
+            ""
+        };
+        prompt.push_str(&format!(
+            "File {}{}:\n```{}\n{}\n```\n\n",
+            attachment.path.display(),
+            note,
+            language,
+            attachment.content.trim_end()
+        ));
+    }
+    prompt.push_str(message);
+    prompt
+}
+
+/// The first and last `lines` lines of `content`, with a marker for the cut.
+pub fn head_tail(content: &str, lines: usize) -> String {
+    let all: Vec<&str> = content.lines().collect();
+    if all.len() <= lines * 2 {
+        return content.to_string();
+    }
+    format!(
+        "{}\n… {} lines omitted …\n{}",
+        all[..lines].join("\n"),
+        all.len() - lines * 2,
+        all[all.len() - lines..].join("\n")
+    )
+}
+
+/// Completions for the partial path after an `@`, relative to `base`.
+/// Directories end in `/`; hidden entries are offered only once the partial
+/// name starts with a dot.
+pub fn complete_path(base: &Path, partial: &str) -> Vec<String> {
+    let (dir, prefix) = match partial.rfind('/') {
+        Some(index) => (&partial[..=index], &partial[index + 1..]),
+        None => ("", partial),
+    };
+    let Ok(entries) = std::fs::read_dir(base.join(dir)) else {
+        return Vec::new();
+    };
+    let mut completions: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir, name, slash))
+        })
+        .collect();
+    completions.sort();
+    completions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kandil_mentions_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn only_existing_files_are_mentioned() {
+        let dir = scratch();
+        let file = dir.join("src").join("main.rs");
+        std::fs::write(&file, "fn main() {}").unwrap();
+
+        let input = format!(
+            "why does @{}, panic? cc @decorators and @{}",
+            file.display(),
+            file.display()
+        );
+        assert_eq!(mentioned_files(&input), vec![file]);
+        assert!(mentioned_files("mail me@example.com").is_empty());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn oversized_files_are_sliced_only_when_allowed() {
+        let dir = scratch();
+        let big = dir.join("big.log");
+        let line = "x".repeat(99);
+        std::fs::write(&big, format!("{}\n", line).repeat(1000)).unwrap();
+        let paths = vec![big];
+
+        let (attachments, warnings) = attach(&paths, 100_000, |_, _| false);
+        assert!(attachments.is_empty());
+        assert!(warnings[0].contains("over the 64 KB cap"));
+
+        let (attachments, warnings) = attach(&paths, 100_000, |_, size| size > MAX_FILE_BYTES);
+        assert!(attachments[0].sliced);
+        assert_eq!(attachments[0].content.lines().count(), SLICE_LINES * 2 + 1);
+        assert!(warnings[0].contains("first and last 40 lines"));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn files_beyond_the_budget_are_left_out() {
+        let dir = scratch();
+        let small = dir.join("a.rs");
+        let large = dir.join("b.rs");
+        std::fs::write(&small, "fn a() {}").unwrap();
+        std::fs::write(&large, "fn b() {}\n".repeat(200)).unwrap();
+
+        let (attachments, warnings) = attach(&[small.clone(), large], 50, |_, _| true);
+        assert_eq!(attachments.len(), 1);
+        assert!(warnings[0].contains("don't fit"));
+
+        let prompt = with_attachments(&attachments, "Explain a");
+        assert!(prompt.starts_with(&format!(
+            "File {}:\n```rs\nfn a() {{}}\n```",
+            small.display()
+        )));
+        assert!(prompt.ends_with("\n\nExplain a"));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn globs_match_files_and_rank_them_by_relevance() {
+        let dir = scratch();
+        std::fs::create_dir_all(dir.join("src").join("auth")).unwrap();
+        std::fs::create_dir_all(dir.join("node_modules")).unwrap();
+        std::fs::write(dir.join("src").join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.join("src").join("db.rs"), "// stores the session token").unwrap();
+        std::fs::write(
+            dir.join("src").join("auth").join("login.rs"),
+            "fn login() {}",
+        )
+        .unwrap();
+        std::fs::write(dir.join("src").join("notes.md"), "").unwrap();
+        std::fs::write(dir.join("node_modules").join("dep.rs"), "").unwrap();
+
+        let root = dir.display().to_string();
+        let names = |files: &[PathBuf]| -> Vec<String> {
+            files
+                .iter()
+                .map(|path| path.strip_prefix(&dir).unwrap().display().to_string())
+                .collect()
+        };
+        let all = glob_files(&format!("{}/**/*.rs", root));
+        assert_eq!(
+            names(&all),
+            vec!["src/auth/login.rs", "src/db.rs", "src/main.rs"]
+        );
+        assert_eq!(names(&glob_files(&format!("{}/src/*.rs", root))).len(), 2);
+        assert_eq!(
+            names(&glob_files(&format!("{}/src/m?in.rs", root))),
+            vec!["src/main.rs"]
+        );
+        assert!(glob_files(&format!("{}/**/*.py", root)).is_empty());
+
+        let ranked = rank_by_relevance(all, "Why does login drop the session token?");
+        assert_eq!(
+            names(&ranked),
+            vec!["src/auth/login.rs", "src/db.rs", "src/main.rs"]
+        );
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn completion_lists_matching_entries() {
+        let dir = scratch();
+        std::fs::write(dir.join("src").join("main.rs"), "").unwrap();
+        std::fs::write(dir.join("src").join("mentions.rs"), "").unwrap();
+        std::fs::write(dir.join(".env"), "").unwrap();
+
+        assert_eq!(complete_path(&dir, "s"), vec!["src/"]);
+        assert_eq!(
+            complete_path(&dir, "src/m"),
+            vec!["src/main.rs", "src/mentions.rs"]
+        );
+        assert!(complete_path(&dir, "").iter().all(|name| name != ".env"));
+        assert_eq!(complete_path(&dir, ".e"), vec![".env"]);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -2,9 +2,11 @@
 
 pub mod adaptive;
 pub mod context;
+pub mod conversation;
 pub mod gpu_render;
 pub mod ide_sync;
 pub mod input;
+pub mod mentions;
 pub mod persona;
 pub mod predictive;
 pub mod repl;
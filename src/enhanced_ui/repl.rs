@@ -11,6 +11,7 @@ use crate::{
         thought::{OutputMode, ThoughtFragment, ThoughtStreamer},
     },
     mobile::MobileBridge,
+    utils::plugins::PluginManager,
 };
 use anyhow::Result;
 use futures_util;
@@ -112,7 +113,10 @@ pub async fn run_repl() -> Result<()> {
 
         universal_input.add_history(trimmed)?;
 
-        if handle_special_input(trimmed, &terminal, &mut context, Some(&thought_streamer)).await? {
+        // Each turn gets the full AI call budget.
+        let special =
+            handle_special_input(trimmed, &terminal, &mut context, Some(&thought_streamer));
+        if crate::utils::budget::scoped(special).await? {
             continue;
         }
 
@@ -131,16 +135,15 @@ pub async fn run_repl() -> Result<()> {
 
         // Enhanced context-aware command parsing
         let parsed = parse_command_enhanced(trimmed, &context).await;
-        if let Err(err) = execute_command(
+        let turn = execute_command(
             parsed,
             &terminal,
             &mut context,
             &mut prompt,
             &adaptive_ui,
             &thought_streamer,
-        )
-        .await
-        {
+        );
+        if let Err(err) = crate::utils::budget::scoped(turn).await {
             eprintln!("Command error: {}", err);
         }
 
@@ -225,6 +228,7 @@ async fn handle_special_input(
         "/reset" => {
             terminal.reset_context().await?;
             context.job_tracker.complete_all();
+            context.conversation.clear();
             println!("🔄 Context reset");
             Ok(true)
         }
@@ -529,12 +533,23 @@ async fn handle_basic_command(
         }
         Command::NaturalLanguage(query) => {
             prompt.set_mode(PromptMode::Chat);
-            emit_result(
-                SplashResult {
-                    message: Some(format!("💬 {}", query)),
-                },
-                adaptive_ui,
-            );
+            let plugins = PluginManager::new();
+            if let Some(command) = plugins.match_intent(&query, &splash::builtin_triggers())? {
+                thought_streamer.emit(ThoughtFragment::Action(format!(
+                    "Routing to plugin {}",
+                    command.qualified()
+                )));
+                let message = plugins.invoke(&command, &[], &query).await?;
+                emit_result(
+                    SplashResult {
+                        message: Some(message),
+                    },
+                    adaptive_ui,
+                );
+                return Ok(());
+            }
+            let result = splash::execute_splash_command("/ask", &[query], context).await?;
+            emit_result(result, adaptive_ui);
             thought_streamer.emit(ThoughtFragment::Result("Answered chat query".into()));
             Ok(())
         }
@@ -565,6 +580,15 @@ fn print_help() {
     for cmd in splash::SPLASH_COMMANDS.iter() {
         println!("  {:<10} {}", cmd.trigger, cmd.description);
     }
+    if let Ok(commands) = PluginManager::new().commands(&splash::builtin_triggers()) {
+        if !commands.is_empty() {
+            println!("\nPlugin commands:");
+            for cmd in commands {
+                let trigger = cmd.alias.clone().unwrap_or_else(|| cmd.qualified());
+                println!("  {:<10} {}", trigger, cmd.description);
+            }
+        }
+    }
     println!("\nSpecial commands:");
     println!("  {:<10} {}", "/help", "Show this help message");
     println!("  {:<10} {}", "/clear", "Clear the terminal screen");
@@ -1,6 +1,12 @@
 use crate::enhanced_ui::{
-    context::ProjectContext, smart_prompt::SmartPrompt, terminal::KandilTerminal,
+    context::ProjectContext,
+    conversation::{Conversation, Role},
+    mentions,
+    smart_prompt::SmartPrompt,
+    terminal::KandilTerminal,
 };
+use crate::utils::plugins::PluginManager;
+use crate::utils::redact;
 use anyhow::{anyhow, Result};
 use lazy_static::lazy_static;
 use std::{
@@ -18,6 +24,8 @@ pub struct CommandContext {
     pub active_file: Option<PathBuf>,
     pub job_tracker: JobTracker,
     pub project_context: ProjectContext,
+    /// Questions and answers of this session, sent with each `/ask`.
+    pub conversation: Conversation,
 }
 
 impl CommandContext {
@@ -28,6 +36,11 @@ impl CommandContext {
             active_file: None,
             job_tracker: JobTracker::default(),
             project_context: ProjectContext::detect(),
+            conversation: Conversation::for_model(
+                &crate::utils::config::Config::load()
+                    .map(|config| config.ai_model)
+                    .unwrap_or_default(),
+            ),
         }
     }
 
@@ -149,6 +162,24 @@ lazy_static! {
             requires_approval: false,
             preview_action: None,
         },
+        SplashCommand {
+            trigger: "/pin",
+            description: "Keep the last exchange out of summaries, or /pin @file to attach a file",
+            requires_approval: false,
+            preview_action: None,
+        },
+        SplashCommand {
+            trigger: "/unpin",
+            description: "Stop attaching a file pinned with /pin @file",
+            requires_approval: false,
+            preview_action: None,
+        },
+        SplashCommand {
+            trigger: "/nosummary",
+            description: "Toggle summarizing old turns as the context fills up",
+            requires_approval: false,
+            preview_action: None,
+        },
         SplashCommand {
             trigger: "/undo",
             description: "Undo the last AI action",
@@ -158,6 +189,15 @@ lazy_static! {
     ];
 }
 
+/// Triggers handled by Kandil itself, which plugin commands may not shadow.
+pub fn builtin_triggers() -> Vec<&'static str> {
+    SPLASH_COMMANDS
+        .iter()
+        .map(|cmd| cmd.trigger)
+        .chain(["/help", "/clear", "/reset", "/thoughts", "/jobs"])
+        .collect()
+}
+
 pub async fn execute_splash_command(
     trigger: &str,
     args: &[String],
@@ -171,7 +211,7 @@ pub async fn execute_splash_command(
     };
 
     match normalized_trigger.as_str() {
-        "/ask" => handle_ask(args).await,
+        "/ask" => handle_ask(args, ctx).await,
         "/refactor" => handle_refactor(args).await,
         "/test" => handle_test(args, ctx).await,
         "/fix" => handle_fix().await,
@@ -181,9 +221,24 @@ pub async fn execute_splash_command(
         "/deploy" => handle_deploy(args).await,
         "/model" => handle_model_switch(args).await,
         "/history" => handle_history(ctx).await,
+        "/pin" => handle_pin(args, ctx).await,
+        "/unpin" => handle_unpin(args, ctx).await,
+        "/nosummary" => handle_nosummary(ctx).await,
         "/undo" => handle_undo(ctx).await,
         "/jobs" => handle_jobs(ctx).await,
         _ => {
+            let plugins = PluginManager::new();
+            if let Some(command) = plugins.resolve(&normalized_trigger, &builtin_triggers())? {
+                let input = std::iter::once(normalized_trigger.clone())
+                    .chain(args.iter().cloned())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let message = plugins.invoke(&command, args, &input).await?;
+                return Ok(SplashResult {
+                    message: Some(message),
+                });
+            }
+
             // Try to find partial matches for better error reporting
             let matches: Vec<&SplashCommand> = SPLASH_COMMANDS
                 .iter()
@@ -280,14 +335,52 @@ pub struct SplashResult {
     pub message: Option<String>,
 }
 
-async fn handle_ask(args: &[String]) -> Result<SplashResult> {
+async fn handle_ask(args: &[String], ctx: &mut CommandContext) -> Result<SplashResult> {
+    use crate::core::adapters::ai::factory::AIProviderFactory;
+    use crate::utils::config::Config;
+
     let question = if args.is_empty() {
         "What should I focus on next?".to_string()
     } else {
         args.join(" ")
     };
+
+    let folded = ctx.conversation.compact(&question).await;
+    if folded > 0 {
+        println!(
+            "  ⋯ summarized {} earlier turns to fit the context window (/nosummary to keep them)",
+            folded
+        );
+    }
+
+    let mut files = ctx.conversation.pinned_files().to_vec();
+    for path in mentions::mentioned_files(&question) {
+        if !files.contains(&path) {
+            files.push(path);
+        }
+    }
+    let budget = ctx.conversation.spare_tokens(&question);
+    let (attachments, warnings) = mentions::attach(&files, budget, mentions::offer_slice);
+    for warning in warnings {
+        println!("  ⚠️  {}", warning);
+    }
+    if !attachments.is_empty() {
+        let names: Vec<String> = attachments
+            .iter()
+            .map(|attachment| attachment.path.display().to_string())
+            .collect();
+        println!("  📎 {}", names.join(", "));
+    }
+
+    let config = Config::load()?;
+    let ai =
+        AIProviderFactory::new(config.clone()).create_ai(&config.ai_provider, &config.ai_model)?;
+    let message = mentions::with_attachments(&attachments, &question);
+    let answer = redact::redact(&ai.chat(&ctx.conversation.prompt(&message)).await?);
+    ctx.conversation.push(Role::User, question);
+    ctx.conversation.push(Role::Assistant, answer.clone());
     Ok(SplashResult {
-        message: Some(format!("🤖 Answering question: {}", question)),
+        message: Some(answer),
     })
 }
 
@@ -484,6 +577,79 @@ async fn handle_history(ctx: &CommandContext) -> Result<SplashResult> {
     })
 }
 
+async fn handle_pin(args: &[String], ctx: &mut CommandContext) -> Result<SplashResult> {
+    if !args.is_empty() {
+        for path in mention_args(args)? {
+            if !path.is_file() {
+                return Err(anyhow!("No such file: {}", path.display()));
+            }
+            ctx.conversation.pin_file(path);
+        }
+        return Ok(SplashResult {
+            message: Some(pinned_files(ctx)),
+        });
+    }
+
+    let message = match ctx.conversation.pin_last_exchange() {
+        0 if ctx.conversation.pinned().count() == 0 => {
+            "Nothing to pin yet; ask something first.".to_string()
+        }
+        0 => "The last exchange is already pinned.".to_string(),
+        _ => format!(
+            "📌 Pinned the last exchange ({} pinned turns are never summarized)",
+            ctx.conversation.pinned().count()
+        ),
+    };
+    Ok(SplashResult {
+        message: Some(format!("{}\n{}", message, pinned_files(ctx))),
+    })
+}
+
+async fn handle_unpin(args: &[String], ctx: &mut CommandContext) -> Result<SplashResult> {
+    if args.is_empty() {
+        return Err(anyhow!("Usage: /unpin @file"));
+    }
+    for path in mention_args(args)? {
+        if !ctx.conversation.unpin_file(&path) {
+            return Err(anyhow!("{} is not pinned", path.display()));
+        }
+    }
+    Ok(SplashResult {
+        message: Some(pinned_files(ctx)),
+    })
+}
+
+/// The paths in `/pin @a @b` style arguments.
+fn mention_args(args: &[String]) -> Result<Vec<PathBuf>> {
+    args.iter()
+        .map(|arg| match arg.strip_prefix('@') {
+            Some(path) if !path.is_empty() => Ok(PathBuf::from(path)),
+            _ => Err(anyhow!("Files are pinned as @path, e.g. /pin @src/main.rs")),
+        })
+        .collect()
+}
+
+fn pinned_files(ctx: &CommandContext) -> String {
+    let files = ctx.conversation.pinned_files();
+    if files.is_empty() {
+        return "No pinned files; /pin @path attaches one to every question.".to_string();
+    }
+    let names: Vec<String> = files.iter().map(|path| path.display().to_string()).collect();
+    format!("📎 Pinned files: {}", names.join(", "))
+}
+
+async fn handle_nosummary(ctx: &mut CommandContext) -> Result<SplashResult> {
+    ctx.conversation.summarize = !ctx.conversation.summarize;
+    let message = if ctx.conversation.summarize {
+        "Summarization on: old turns are summarized as the context window fills up."
+    } else {
+        "Summarization off: the full history is sent until the context window overflows."
+    };
+    Ok(SplashResult {
+        message: Some(message.to_string()),
+    })
+}
+
 async fn handle_undo(_ctx: &mut CommandContext) -> Result<SplashResult> {
     Ok(SplashResult {
         message: Some("↩️ Reverting last AI action (simulated)".to_string()),
@@ -1,7 +1,8 @@
-//! Error handling for Kandil Code local model integration
+//! Error handling for Kandil Code
 //!
-//! Contains all error types for the local model system.
+//! Contains the error types for the local model system and the AI provider layer.
 
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -49,4 +50,83 @@ pub enum LocalModelError {
     ValidationError { message: String },
 }
 
-// Add to the main module
+/// Failures returned by AI provider calls.
+///
+/// Lets callers tell authentication problems from rate limits and transient
+/// network faults, e.g. to decide whether a retry is worthwhile.
+#[derive(Error, Debug)]
+pub enum AiError {
+    #[error("No API key configured for {provider}. Run: kandil config set-key {provider} <key>")]
+    AuthMissing { provider: String },
+
+    #[error("{provider} rejected the credentials ({status}): {body}. Check your API key, or replace it with: kandil config set-key {provider} <key>")]
+    Auth {
+        provider: String,
+        status: u16,
+        body: String,
+    },
+
+    #[error("Rate limited by {provider}{}", retry_hint(.retry_after))]
+    RateLimited {
+        provider: String,
+        retry_after: Option<Duration>,
+    },
+
+    #[error("Request to {provider} timed out")]
+    Timeout { provider: String },
+
+    #[error("{provider} request failed: {status} - {body}")]
+    ProviderError {
+        provider: String,
+        status: u16,
+        body: String,
+    },
+
+    #[error("Model not found: {model}")]
+    ModelNotFound { model: String },
+
+    #[error("Circuit breaker open for provider {provider}")]
+    CircuitOpen { provider: String },
+
+    #[error("Network error talking to {provider}: {message}")]
+    Network { provider: String, message: String },
+
+    #[error("Invalid response from {provider}: {message}")]
+    InvalidResponse { provider: String, message: String },
+
+    #[error("{provider} stopped streaming after {tokens_received} tokens before the answer was complete. Retry, or pass --allow-partial to keep the truncated answer")]
+    IncompleteStream {
+        provider: String,
+        tokens_received: usize,
+    },
+
+    #[error("{provider} does not support tool calling. Use openai, claude, lmstudio, gpt4all or foundry")]
+    ToolsUnsupported { provider: String },
+
+    #[error("{provider} does not support {feature}")]
+    Unsupported { provider: String, feature: String },
+
+    #[error("AI call budget exhausted after {limit} calls in this command. Raise it with --call-budget <n> or KANDIL_CALL_BUDGET (0 = unlimited)")]
+    BudgetExhausted { limit: u32 },
+}
+
+fn retry_hint(retry_after: &Option<Duration>) -> String {
+    retry_after
+        .map(|delay| format!(" (retry after {}s)", delay.as_secs()))
+        .unwrap_or_default()
+}
+
+impl AiError {
+    /// Whether repeating the same request later may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            AiError::RateLimited { .. }
+                | AiError::Timeout { .. }
+                | AiError::Network { .. }
+                | AiError::CircuitOpen { .. }
+                | AiError::IncompleteStream { .. }
+        ) || matches!(self, AiError::ProviderError { status, .. } if *status >= 500)
+    }
+}
+
@@ -18,17 +18,24 @@ mod errors;
 mod mobile;
 mod models;
 mod monitoring;
+mod performance;
 mod pwa;
 mod security;
 mod shutdown;
 #[cfg(feature = "tui")]
 mod tui;
 mod utils;
+mod web;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    env_logger::Builder::from_env(Env::default().filter_or("RUST_LOG", "warn")).init();
-
     let args = cli::Cli::parse();
+
+    let mut logger = env_logger::Builder::from_env(Env::default().filter_or("RUST_LOG", "warn"));
+    if args.verbose >= 2 {
+        logger.filter_module("kandil", log::LevelFilter::Debug);
+    }
+    logger.init();
+
     cli::run(args).await
 }
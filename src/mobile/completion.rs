@@ -0,0 +1,103 @@
+//! Completion notifications for long-running commands
+//!
+//! Commands such as `kandil agent qa full-suite` take `--notify-webhook <url>`
+//! (or `[notify] webhook_url` in the config) and POST one notification through
+//! a [`WebhookSink`] when they finish, whether they succeeded or failed. The
+//! JSON payload carries `command`, `status`, `duration_secs` and `summary`,
+//! plus `error` on failure; `--notify-format slack|discord` sends a chat
+//! message instead.
+
+use super::push::{
+    NotificationPriority, PushAction, PushNotification, PushSink, WebhookFormat, WebhookSink,
+};
+use anyhow::Result;
+use chrono::Utc;
+use serde_json::json;
+use std::time::Duration;
+
+/// How a command ended.
+pub struct Completion<'a> {
+    /// e.g. `agent qa full-suite`.
+    pub command: &'a str,
+    pub duration: Duration,
+    /// A one-line summary on success, the error on failure.
+    pub outcome: std::result::Result<String, String>,
+}
+
+impl Completion<'_> {
+    pub fn notification(&self) -> PushNotification {
+        let (status, title, body, priority) = match &self.outcome {
+            Ok(summary) => (
+                "succeeded",
+                format!("✅ kandil {} finished", self.command),
+                summary.clone(),
+                NotificationPriority::Normal,
+            ),
+            Err(error) => (
+                "failed",
+                format!("❌ kandil {} failed", self.command),
+                error.clone(),
+                NotificationPriority::High,
+            ),
+        };
+        let mut details = serde_json::Map::new();
+        details.insert("command".to_string(), json!(self.command));
+        details.insert("status".to_string(), json!(status));
+        details.insert(
+            "duration_secs".to_string(),
+            json!(self.duration.as_secs_f64()),
+        );
+        details.insert("summary".to_string(), json!(body));
+        if let Err(error) = &self.outcome {
+            details.insert("error".to_string(), json!(error));
+        }
+        PushNotification {
+            id: uuid::Uuid::new_v4().to_string(),
+            title,
+            body: format!("{} (after {:.0}s)", body, self.duration.as_secs_f64()),
+            timestamp: Utc::now(),
+            actions: vec![PushAction::new("view", "View Results")],
+            priority,
+            details,
+        }
+    }
+}
+
+/// Posts `completion` to `url`.
+pub async fn notify(url: &str, format: WebhookFormat, completion: &Completion<'_>) -> Result<()> {
+    WebhookSink::new(url)
+        .with_format(format)
+        .deliver(&completion.notification())
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_carry_the_error() {
+        let failed = Completion {
+            command: "agent qa full-suite",
+            duration: Duration::from_secs(90),
+            outcome: Err("tests did not compile".to_string()),
+        }
+        .notification();
+        let payload = serde_json::to_value(&failed).unwrap();
+        assert_eq!(payload["command"], "agent qa full-suite");
+        assert_eq!(payload["status"], "failed");
+        assert_eq!(payload["duration_secs"], 90.0);
+        assert_eq!(payload["error"], "tests did not compile");
+        assert_eq!(failed.body, "tests did not compile (after 90s)");
+
+        let succeeded = Completion {
+            command: "agent qa full-suite",
+            duration: Duration::from_secs(5),
+            outcome: Ok("12/12 tests passed".to_string()),
+        }
+        .notification();
+        assert_eq!(succeeded.details["status"], "succeeded");
+        assert_eq!(succeeded.details["summary"], "12/12 tests passed");
+        assert!(!succeeded.details.contains_key("error"));
+    }
+}
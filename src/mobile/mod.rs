@@ -1,10 +1,8 @@
 use anyhow::Result;
-use chrono::{DateTime, Utc};
-use serde::Serialize;
+use chrono::Utc;
 use std::{
     collections::HashSet,
-    fs::{self, OpenOptions},
-    io::Write,
+    fs,
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
@@ -15,39 +13,18 @@ use tokio::sync::oneshot;
 use tokio::time::{sleep, Duration};
 
 use crate::enhanced_ui::splash::JobSnapshot;
+use crate::utils::config::Config;
+use crate::utils::json_store::JsonStore;
 
-#[derive(Serialize, Clone, Debug)]
-pub struct PushNotification {
-    pub id: String,
-    pub title: String,
-    pub body: String,
-    pub timestamp: DateTime<Utc>,
-    pub actions: Vec<PushAction>,
-    pub priority: NotificationPriority,
-}
+pub mod completion;
+pub mod outbox;
+pub mod push;
 
-#[derive(Serialize, Clone, Debug)]
-pub struct PushAction {
-    pub id: String,
-    pub label: String,
-}
-
-#[derive(Serialize, Clone, Debug)]
-pub enum NotificationPriority {
-    Low,
-    Normal,
-    High,
-    Urgent,
-}
-
-impl PushAction {
-    pub fn new(id: &str, label: &str) -> Self {
-        Self {
-            id: id.to_string(),
-            label: label.to_string(),
-        }
-    }
-}
+pub use outbox::{Outbox, RetryReport};
+pub use push::{
+    FcmSink, LogSink, NotificationPriority, PushAction, PushNotification, PushNotifier, PushSink,
+    WebhookFormat, WebhookSink,
+};
 
 #[derive(Clone)]
 pub struct MobileBridge {
@@ -119,8 +96,12 @@ impl ApprovalHandler {
 impl MobileBridge {
     pub fn new() -> Result<Self> {
         let root = mobile_root()?;
+        let push_config = Config::load()?.push;
         Ok(Self {
-            notifier: Arc::new(PushNotifier::new(root.join("notifications.log"))?),
+            notifier: Arc::new(
+                PushNotifier::from_config(root.join("notifications.log"), &push_config)?
+                    .with_outbox(Outbox::new(JsonStore::new(root.join("push_outbox.json")))),
+            ),
             voice_receiver: Arc::new(VoiceReceiver::new(root.join("voice_queue.txt"))?),
             approval_handler: Arc::new(ApprovalHandler::new()),
             announced_jobs: Arc::new(Mutex::new(HashSet::new())),
@@ -128,6 +109,14 @@ impl MobileBridge {
     }
 
     pub fn sync_jobs(&self, jobs: &[JobSnapshot]) {
+        // Each sync is a tick: give queued notifications another try.
+        let notifier = Arc::clone(&self.notifier);
+        tokio::spawn(async move {
+            if let Err(err) = notifier.retry_pending(false).await {
+                log::warn!("Push outbox retry failed: {}", err);
+            }
+        });
+
         let mut announced = self.announced_jobs.lock().unwrap();
         for job in jobs {
             if job.completed && announced.insert(job.description.clone()) {
@@ -144,19 +133,31 @@ impl MobileBridge {
                         PushAction::new("approve", "Approve"),
                     ],
                     priority: NotificationPriority::Normal,
+                    details: Default::default(),
                 };
 
-                let _ = self.notifier.send(notification);
+                // Delivery may hit the network; don't block the caller on it.
+                let notifier = Arc::clone(&self.notifier);
+                tokio::spawn(async move {
+                    if let Err(err) = notifier.send(&notification).await {
+                        log::warn!("{}", err);
+                    }
+                });
             }
         }
     }
 
+    /// Retries every queued notification now, ignoring backoff.
+    pub async fn flush_notifications(&self) -> Result<RetryReport> {
+        self.notifier.retry_pending(true).await
+    }
+
     pub fn try_voice_command(&self) -> Result<Option<String>> {
         self.voice_receiver.poll()
     }
 
     /// Send a push notification to mobile devices
-    pub fn send_notification(&self, title: &str, body: &str, priority: NotificationPriority) -> Result<()> {
+    pub async fn send_notification(&self, title: &str, body: &str, priority: NotificationPriority) -> Result<()> {
         let notification = PushNotification {
             id: uuid::Uuid::new_v4().to_string(),
             title: title.to_string(),
@@ -167,13 +168,14 @@ impl MobileBridge {
                 PushAction::new("dismiss", "Dismiss"),
             ],
             priority,
+            details: Default::default(),
         };
 
-        self.notifier.send(notification)
+        self.notifier.send(&notification).await
     }
 
     /// Request approval for a specific action with a push notification
-    pub fn request_approval(
+    pub async fn request_approval(
         &self,
         title: &str,
         message: &str,
@@ -191,9 +193,10 @@ impl MobileBridge {
                 PushAction::new("defer", "⏰ Later"),
             ],
             priority: NotificationPriority::High,
+            details: Default::default(),
         };
 
-        self.notifier.send(notification.clone())?;
+        self.notifier.send(&notification).await?;
 
         // Also register the approval request
         self.approval_handler.request_approval(title, message, request_data)
@@ -205,7 +208,7 @@ impl MobileBridge {
     }
 
     /// Send notification for command execution requiring approval
-    pub fn notify_command_execution(
+    pub async fn notify_command_execution(
         &self,
         command: &str,
         description: &str,
@@ -235,60 +238,17 @@ impl MobileBridge {
                 ]
             },
             priority,
+            details: Default::default(),
         };
 
-        self.notifier.send(notification)
-    }
-}
-
-impl Default for PushNotification {
-    fn default() -> Self {
-        Self {
-            id: uuid::Uuid::new_v4().to_string(),
-            title: "Notification".to_string(),
-            body: "Message".to_string(),
-            timestamp: Utc::now(),
-            actions: vec![PushAction::new("ok", "OK")],
-            priority: NotificationPriority::Normal,
-        }
+        self.notifier.send(&notification).await
     }
 }
 
 fn mobile_root() -> Result<PathBuf> {
-    let root = dirs::data_dir()
-        .unwrap_or_else(|| std::env::current_dir().unwrap())
-        .join("kandil")
-        .join("mobile");
-    fs::create_dir_all(&root)?;
-    Ok(root)
+    crate::utils::data_dir::subdir("mobile")
 }
 
-struct PushNotifier {
-    log_path: PathBuf,
-}
-
-impl PushNotifier {
-    fn new(path: PathBuf) -> Result<Self> {
-        if let Some(dir) = path.parent() {
-            fs::create_dir_all(dir)?;
-        }
-        Ok(Self { log_path: path })
-    }
-
-    fn send(&self, notification: PushNotification) -> Result<()> {
-        let mut file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&self.log_path)?;
-        writeln!(file, "{}", serde_json::to_string(&notification)?)?;
-        println!("📱 Push → {}: {}", notification.title, notification.body);
-        Ok(())
-    }
-}
-
-// The PushNotification and PushAction structs are defined earlier in the file
-// This is a duplicate definition that's not needed anymore
-
 struct VoiceReceiver {
     queue: PathBuf,
     guard: Mutex<()>,
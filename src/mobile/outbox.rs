@@ -0,0 +1,227 @@
+//! Durable outbox for push notifications
+//!
+//! Notifications a sink failed to deliver are kept in a JSON file under the
+//! mobile data directory and retried with exponential backoff, so an offline
+//! webhook or FCM outage doesn't lose them. Entries are keyed by notification
+//! id and only remember the sinks that still need the notification.
+
+use super::push::PushNotification;
+use crate::utils::json_store::JsonStore;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Oldest entries are dropped beyond this many pending notifications.
+const MAX_ENTRIES: usize = 200;
+/// Notifications are abandoned after this many failed retries.
+const MAX_ATTEMPTS: u32 = 10;
+const BASE_BACKOFF_SECS: i64 = 30;
+const MAX_BACKOFF_SECS: i64 = 60 * 60;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OutboxData {
+    entries: Vec<PendingNotification>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingNotification {
+    pub notification: PushNotification,
+    /// Sinks that have not received the notification yet.
+    pub sinks: Vec<String>,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: String,
+}
+
+/// Counts from one pass over the outbox.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetryReport {
+    pub delivered: usize,
+    pub pending: usize,
+    pub dropped: usize,
+}
+
+pub struct Outbox {
+    store: JsonStore,
+}
+
+impl Outbox {
+    pub fn new(store: JsonStore) -> Self {
+        Self { store }
+    }
+
+    /// Queues `notification` for the sinks that failed, merging with an
+    /// existing entry for the same notification id.
+    pub fn enqueue(
+        &self,
+        notification: &PushNotification,
+        failed: &[(String, String)],
+    ) -> Result<()> {
+        if failed.is_empty() {
+            return Ok(());
+        }
+        let error = describe(failed);
+        self.store.update(|data: &mut OutboxData| {
+            match data
+                .entries
+                .iter_mut()
+                .find(|entry| entry.notification.id == notification.id)
+            {
+                Some(entry) => {
+                    for (sink, _) in failed {
+                        if !entry.sinks.contains(sink) {
+                            entry.sinks.push(sink.clone());
+                        }
+                    }
+                    entry.last_error = error;
+                }
+                None => data.entries.push(PendingNotification {
+                    notification: notification.clone(),
+                    sinks: failed.iter().map(|(sink, _)| sink.clone()).collect(),
+                    attempts: 0,
+                    next_attempt_at: Utc::now() + backoff(0),
+                    last_error: error,
+                }),
+            }
+
+            if data.entries.len() > MAX_ENTRIES {
+                let excess = data.entries.len() - MAX_ENTRIES;
+                log::warn!(
+                    "Push outbox is full; dropping {} oldest notifications",
+                    excess
+                );
+                data.entries.drain(..excess);
+            }
+            Ok(())
+        })
+    }
+
+    /// Entries whose backoff has elapsed, or every entry when `force` is set.
+    pub fn due(&self, force: bool) -> Result<Vec<PendingNotification>> {
+        let data: OutboxData = self.store.load()?;
+        let now = Utc::now();
+        Ok(data
+            .entries
+            .into_iter()
+            .filter(|entry| force || entry.next_attempt_at <= now)
+            .collect())
+    }
+
+    pub fn pending_count(&self) -> Result<usize> {
+        Ok(self.store.load::<OutboxData>()?.entries.len())
+    }
+
+    /// Records a retry: delivered sinks are removed, and the entry is dropped
+    /// once no sinks remain or it has run out of attempts. Returns whether the
+    /// entry was abandoned.
+    pub fn record_attempt(
+        &self,
+        id: &str,
+        delivered: &[String],
+        failed: &[(String, String)],
+    ) -> Result<bool> {
+        self.store.update(|data: &mut OutboxData| {
+            let Some(index) = data
+                .entries
+                .iter()
+                .position(|entry| entry.notification.id == id)
+            else {
+                return Ok(false);
+            };
+
+            let entry = &mut data.entries[index];
+            entry.sinks.retain(|sink| !delivered.contains(sink));
+            if entry.sinks.is_empty() {
+                data.entries.remove(index);
+                return Ok(false);
+            }
+            if failed.is_empty() {
+                return Ok(false);
+            }
+
+            entry.attempts += 1;
+            entry.last_error = describe(failed);
+            if entry.attempts >= MAX_ATTEMPTS {
+                log::warn!(
+                    "Giving up on notification '{}' after {} attempts: {}",
+                    entry.notification.title,
+                    entry.attempts,
+                    entry.last_error
+                );
+                data.entries.remove(index);
+                return Ok(true);
+            }
+            entry.next_attempt_at = Utc::now() + backoff(entry.attempts);
+            Ok(false)
+        })
+    }
+}
+
+/// 30s, 60s, 120s, ... capped at an hour.
+fn backoff(attempts: u32) -> Duration {
+    let secs = BASE_BACKOFF_SECS.saturating_mul(1i64 << attempts.min(16));
+    Duration::seconds(secs.min(MAX_BACKOFF_SECS))
+}
+
+fn describe(failed: &[(String, String)]) -> String {
+    failed
+        .iter()
+        .map(|(sink, err)| format!("{}: {}", sink, err))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_outbox() -> (Outbox, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("kandil_outbox_{}", uuid::Uuid::new_v4()));
+        (
+            Outbox::new(JsonStore::new(dir.join("push_outbox.json"))),
+            dir,
+        )
+    }
+
+    #[test]
+    fn failed_sends_are_deduplicated_and_cleared() -> Result<()> {
+        let (outbox, dir) = temp_outbox();
+        let notification = PushNotification::default();
+        let failure = vec![("webhook".to_string(), "offline".to_string())];
+
+        outbox.enqueue(&notification, &failure)?;
+        outbox.enqueue(&notification, &failure)?;
+        assert_eq!(outbox.pending_count()?, 1);
+        // Backoff has not elapsed yet.
+        assert!(outbox.due(false)?.is_empty());
+        assert_eq!(outbox.due(true)?[0].sinks, vec!["webhook".to_string()]);
+
+        outbox.record_attempt(&notification.id, &[], &failure)?;
+        assert_eq!(outbox.due(true)?[0].attempts, 1);
+        outbox.record_attempt(&notification.id, &["webhook".to_string()], &[])?;
+        assert_eq!(outbox.pending_count()?, 0);
+
+        let _ = std::fs::remove_dir_all(dir);
+        Ok(())
+    }
+
+    #[test]
+    fn outbox_is_capped() -> Result<()> {
+        let (outbox, dir) = temp_outbox();
+        let failure = vec![("fcm".to_string(), "unauthorized".to_string())];
+        for _ in 0..MAX_ENTRIES + 5 {
+            outbox.enqueue(&PushNotification::default(), &failure)?;
+        }
+        assert_eq!(outbox.pending_count()?, MAX_ENTRIES);
+
+        let _ = std::fs::remove_dir_all(dir);
+        Ok(())
+    }
+
+    #[test]
+    fn backoff_grows_and_caps() {
+        assert_eq!(backoff(0), Duration::seconds(30));
+        assert_eq!(backoff(2), Duration::seconds(120));
+        assert_eq!(backoff(20), Duration::seconds(MAX_BACKOFF_SECS));
+    }
+}
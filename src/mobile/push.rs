@@ -0,0 +1,510 @@
+//! Push notification delivery
+//!
+//! A [`PushNotifier`] fans each notification out to every configured
+//! [`PushSink`]: the local notification log always, plus an HTTP webhook and
+//! Firebase Cloud Messaging when `[push]` in the config names them. Failed
+//! deliveries go to the [`Outbox`] and are retried later. Webhooks can also
+//! be shaped for Slack or Discord incoming webhooks, which only accept their
+//! own message format.
+
+use super::outbox::{Outbox, RetryReport};
+use crate::core::adapters::ai::http::{request_timeout, shared_client};
+use crate::utils::config::{PushConfig, SecureKey};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PushNotification {
+    pub id: String,
+    pub title: String,
+    pub body: String,
+    pub timestamp: DateTime<Utc>,
+    pub actions: Vec<PushAction>,
+    pub priority: NotificationPriority,
+    /// Extra fields for machine consumers, sent alongside the others in the
+    /// JSON webhook payload.
+    #[serde(flatten, default)]
+    pub details: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PushAction {
+    pub id: String,
+    pub label: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum NotificationPriority {
+    Low,
+    Normal,
+    High,
+    Urgent,
+}
+
+impl PushAction {
+    pub fn new(id: &str, label: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            label: label.to_string(),
+        }
+    }
+}
+
+impl Default for PushNotification {
+    fn default() -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: "Notification".to_string(),
+            body: "Message".to_string(),
+            timestamp: Utc::now(),
+            actions: vec![PushAction::new("ok", "OK")],
+            priority: NotificationPriority::Normal,
+            details: serde_json::Map::new(),
+        }
+    }
+}
+
+/// A channel notifications can be delivered to.
+#[async_trait]
+pub trait PushSink: Send + Sync {
+    /// Short name used in delivery errors.
+    fn name(&self) -> &str;
+    async fn deliver(&self, notification: &PushNotification) -> Result<()>;
+}
+
+/// Appends notifications as JSON lines to a local file and echoes them.
+pub struct LogSink {
+    path: PathBuf,
+}
+
+impl LogSink {
+    pub fn new(path: PathBuf) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(Self { path })
+    }
+}
+
+#[async_trait]
+impl PushSink for LogSink {
+    fn name(&self) -> &str {
+        "log"
+    }
+
+    async fn deliver(&self, notification: &PushNotification) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(notification)?)?;
+        println!("📱 Push → {}: {}", notification.title, notification.body);
+        Ok(())
+    }
+}
+
+/// The body a [`WebhookSink`] posts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// The notification itself, as JSON.
+    #[default]
+    Json,
+    /// A Slack incoming webhook message.
+    Slack,
+    /// A Discord webhook message.
+    Discord,
+}
+
+impl std::str::FromStr for WebhookFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "json" => Ok(WebhookFormat::Json),
+            "slack" => Ok(WebhookFormat::Slack),
+            "discord" => Ok(WebhookFormat::Discord),
+            other => anyhow::bail!(
+                "Unknown webhook format '{}'; use json, slack or discord",
+                other
+            ),
+        }
+    }
+}
+
+/// Discord rejects messages longer than this.
+const DISCORD_MAX_CHARS: usize = 2000;
+
+/// POSTs each notification to a user supplied URL, e.g. a Slack or ntfy
+/// relay.
+pub struct WebhookSink {
+    url: String,
+    format: WebhookFormat,
+}
+
+impl WebhookSink {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            format: WebhookFormat::Json,
+        }
+    }
+
+    pub fn with_format(mut self, format: WebhookFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    fn payload(&self, notification: &PushNotification) -> Result<serde_json::Value> {
+        Ok(match self.format {
+            WebhookFormat::Json => serde_json::to_value(notification)?,
+            WebhookFormat::Slack => serde_json::json!({
+                "text": format!("*{}*\n{}", notification.title, notification.body),
+            }),
+            WebhookFormat::Discord => {
+                let content = format!("**{}**\n{}", notification.title, notification.body);
+                serde_json::json!({
+                    "content": content.chars().take(DISCORD_MAX_CHARS).collect::<String>(),
+                })
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl PushSink for WebhookSink {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn deliver(&self, notification: &PushNotification) -> Result<()> {
+        let response = shared_client()
+            .post(&self.url)
+            .json(&self.payload(notification)?)
+            .timeout(request_timeout())
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("{} returned {}", self.url, response.status());
+        }
+        Ok(())
+    }
+}
+
+/// Sends notifications to one device through the FCM HTTP v1 API.
+///
+/// The OAuth2 access token is read from `KANDIL_FCM_ACCESS_TOKEN`, or from
+/// the key stored with `kandil config set-key fcm <token>`.
+pub struct FcmSink {
+    project: String,
+    device_token: String,
+}
+
+impl FcmSink {
+    pub fn new(project: impl Into<String>, device_token: impl Into<String>) -> Self {
+        Self {
+            project: project.into(),
+            device_token: device_token.into(),
+        }
+    }
+
+    fn access_token() -> Result<String> {
+        if let Ok(token) = std::env::var("KANDIL_FCM_ACCESS_TOKEN") {
+            return Ok(token);
+        }
+        SecureKey::load("fcm")
+            .map(|key| key.expose().to_string())
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "No FCM access token. Set KANDIL_FCM_ACCESS_TOKEN or run `kandil config set-key fcm <token>`"
+                )
+            })
+    }
+
+    fn message(&self, notification: &PushNotification) -> serde_json::Value {
+        let actions: Vec<&str> = notification
+            .actions
+            .iter()
+            .map(|action| action.id.as_str())
+            .collect();
+        let android_priority = match notification.priority {
+            NotificationPriority::High | NotificationPriority::Urgent => "HIGH",
+            NotificationPriority::Low | NotificationPriority::Normal => "NORMAL",
+        };
+        // FCM data payload values must be strings.
+        serde_json::json!({
+            "message": {
+                "token": self.device_token,
+                "notification": {
+                    "title": notification.title,
+                    "body": notification.body,
+                },
+                "data": {
+                    "id": notification.id,
+                    "actions": actions.join(","),
+                    "timestamp": notification.timestamp.to_rfc3339(),
+                },
+                "android": { "priority": android_priority },
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl PushSink for FcmSink {
+    fn name(&self) -> &str {
+        "fcm"
+    }
+
+    async fn deliver(&self, notification: &PushNotification) -> Result<()> {
+        let url = format!(
+            "https://fcm.googleapis.com/v1/projects/{}/messages:send",
+            self.project
+        );
+        let response = shared_client()
+            .post(url)
+            .bearer_auth(Self::access_token()?)
+            .json(&self.message(notification))
+            .timeout(request_timeout())
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("FCM returned {}: {}", status, body);
+        }
+        Ok(())
+    }
+}
+
+/// Dispatches notifications to every configured sink.
+pub struct PushNotifier {
+    sinks: Vec<Box<dyn PushSink>>,
+    outbox: Option<Outbox>,
+    /// Held while retrying so overlapping ticks don't deliver twice.
+    retrying: tokio::sync::Mutex<()>,
+}
+
+impl PushNotifier {
+    pub fn new(sinks: Vec<Box<dyn PushSink>>) -> Self {
+        Self {
+            sinks,
+            outbox: None,
+            retrying: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Keeps failed deliveries in `outbox` for [`PushNotifier::retry_pending`].
+    pub fn with_outbox(mut self, outbox: Outbox) -> Self {
+        self.outbox = Some(outbox);
+        self
+    }
+
+    /// The log at `log_path` plus whatever `[push]` configures.
+    pub fn from_config(log_path: PathBuf, config: &PushConfig) -> Result<Self> {
+        let mut sinks: Vec<Box<dyn PushSink>> = vec![Box::new(LogSink::new(log_path)?)];
+        if let Some(url) = &config.webhook_url {
+            sinks.push(Box::new(WebhookSink::new(url.clone())));
+        }
+        match (&config.fcm_project, &config.fcm_device_token) {
+            (Some(project), Some(token)) => {
+                sinks.push(Box::new(FcmSink::new(project.clone(), token.clone())))
+            }
+            (None, None) => {}
+            _ => log::warn!("FCM push needs both push.fcm_project and push.fcm_device_token"),
+        }
+        Ok(Self::new(sinks))
+    }
+
+    pub fn sink_names(&self) -> Vec<&str> {
+        self.sinks.iter().map(|sink| sink.name()).collect()
+    }
+
+    /// Delivers to all sinks concurrently. One failing channel doesn't stop
+    /// the others; the error lists every channel that failed, and those
+    /// channels are queued in the outbox for a later retry.
+    pub async fn send(&self, notification: &PushNotification) -> Result<()> {
+        let sinks: Vec<&dyn PushSink> = self.sinks.iter().map(|sink| sink.as_ref()).collect();
+        let (_, failed) = deliver_to(&sinks, notification).await;
+        if failed.is_empty() {
+            return Ok(());
+        }
+
+        let queued = match &self.outbox {
+            Some(outbox) => {
+                outbox.enqueue(notification, &failed)?;
+                "; queued for retry"
+            }
+            None => "",
+        };
+        anyhow::bail!(
+            "Push delivery failed ({}){}",
+            failed
+                .iter()
+                .map(|(sink, err)| format!("{}: {}", sink, err))
+                .collect::<Vec<_>>()
+                .join("; "),
+            queued
+        )
+    }
+
+    /// Retries queued notifications whose backoff has elapsed, or all of
+    /// them when `force` is set. Each is resent only to the sinks that missed it.
+    pub async fn retry_pending(&self, force: bool) -> Result<RetryReport> {
+        let mut report = RetryReport::default();
+        let Some(outbox) = &self.outbox else {
+            return Ok(report);
+        };
+        let Ok(_guard) = self.retrying.try_lock() else {
+            return Ok(report);
+        };
+
+        for entry in outbox.due(force)? {
+            let mut delivered = Vec::new();
+            let mut targets: Vec<&dyn PushSink> = Vec::new();
+            for name in &entry.sinks {
+                match self.sinks.iter().find(|sink| sink.name() == name) {
+                    Some(sink) => targets.push(sink.as_ref()),
+                    // The channel was removed from the config; nothing to retry.
+                    None => delivered.push(name.clone()),
+                }
+            }
+
+            let (sent, failed) = deliver_to(&targets, &entry.notification).await;
+            delivered.extend(sent);
+            if outbox.record_attempt(&entry.notification.id, &delivered, &failed)? {
+                report.dropped += 1;
+            } else if failed.is_empty() {
+                report.delivered += 1;
+            }
+        }
+        report.pending = outbox.pending_count()?;
+        Ok(report)
+    }
+}
+
+/// Sends to `sinks` concurrently, returning the names that succeeded and the
+/// `(name, error)` pairs that failed.
+async fn deliver_to(
+    sinks: &[&dyn PushSink],
+    notification: &PushNotification,
+) -> (Vec<String>, Vec<(String, String)>) {
+    let results =
+        futures_util::future::join_all(sinks.iter().map(|sink| sink.deliver(notification))).await;
+
+    let mut delivered = Vec::new();
+    let mut failed = Vec::new();
+    for (sink, result) in sinks.iter().zip(results) {
+        match result {
+            Ok(()) => delivered.push(sink.name().to_string()),
+            Err(err) => failed.push((sink.name().to_string(), err.to_string())),
+        }
+    }
+    (delivered, failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FailingSink;
+
+    #[async_trait]
+    impl PushSink for FailingSink {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        async fn deliver(&self, _notification: &PushNotification) -> Result<()> {
+            anyhow::bail!("offline")
+        }
+    }
+
+    #[tokio::test]
+    async fn fans_out_and_reports_failed_sinks() {
+        let dir = std::env::temp_dir().join(format!("kandil_push_{}", uuid::Uuid::new_v4()));
+        let log = dir.join("notifications.log");
+        let notifier = PushNotifier::new(vec![
+            Box::new(LogSink::new(log.clone()).unwrap()),
+            Box::new(FailingSink),
+        ]);
+
+        let err = notifier
+            .send(&PushNotification::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Push delivery failed (failing: offline)");
+        // The log sink still received the notification.
+        assert_eq!(fs::read_to_string(&log).unwrap().lines().count(), 1);
+
+        // With an outbox, the failed sink is queued and retried on its own.
+        let outbox = crate::mobile::outbox::Outbox::new(crate::utils::json_store::JsonStore::new(
+            dir.join("push_outbox.json"),
+        ));
+        let notifier = PushNotifier::new(vec![
+            Box::new(LogSink::new(log.clone()).unwrap()),
+            Box::new(FailingSink),
+        ])
+        .with_outbox(outbox);
+        let err = notifier
+            .send(&PushNotification::default())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().ends_with("queued for retry"));
+
+        let report = notifier.retry_pending(true).await.unwrap();
+        assert_eq!(report.pending, 1);
+        assert_eq!(report.delivered, 0);
+        // The retry went only to the failing sink, not the log again.
+        assert_eq!(fs::read_to_string(&log).unwrap().lines().count(), 2);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn sinks_follow_config() {
+        let dir = std::env::temp_dir().join(format!("kandil_push_{}", uuid::Uuid::new_v4()));
+        let config = PushConfig {
+            webhook_url: Some("https://example.com/hook".to_string()),
+            fcm_project: Some("demo".to_string()),
+            fcm_device_token: Some("device".to_string()),
+        };
+        let notifier = PushNotifier::from_config(dir.join("n.log"), &config).unwrap();
+        assert_eq!(notifier.sink_names(), vec!["log", "webhook", "fcm"]);
+
+        let payload = FcmSink::new("demo", "device").message(&PushNotification::default());
+        assert_eq!(payload["message"]["token"], "device");
+        assert_eq!(payload["message"]["data"]["actions"], "ok");
+
+        let mut notification = PushNotification::default();
+        notification
+            .details
+            .insert("status".to_string(), "failed".into());
+        let webhook = WebhookSink::new("https://example.com/hook");
+        let json = webhook.payload(&notification).unwrap();
+        assert_eq!(json["status"], "failed");
+        assert_eq!(json["title"], "Notification");
+        let slack = webhook
+            .with_format("slack".parse().unwrap())
+            .payload(&notification)
+            .unwrap();
+        assert_eq!(
+            slack,
+            serde_json::json!({ "text": "*Notification*\nMessage" })
+        );
+        let discord = WebhookSink::new("https://example.com/hook")
+            .with_format(WebhookFormat::Discord)
+            .payload(&notification)
+            .unwrap();
+        assert_eq!(discord["content"], "**Notification**\nMessage");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,147 @@
+//! Provider and model capabilities
+//!
+//! Streaming, tool calling, JSON mode and vision depend on both the runtime
+//! serving a model and the model itself. A model's [`Capabilities`] come from
+//! its registry profile (or are guessed from its name), and are narrowed by
+//! what Kandil's adapter for the provider implements. Higher-level APIs check
+//! them before using a feature and fall back, with a one-line warning, instead
+//! of failing on the provider's side.
+
+use crate::models::registry::UniversalModelRegistry;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
+
+/// Model-name fragments of multimodal models.
+const VISION_MARKERS: &[&str] = &[
+    "llava",
+    "vision",
+    "-vl",
+    "bakllava",
+    "moondream",
+    "gpt-4o",
+    "gpt-4-turbo",
+    "claude-3",
+    "gemini",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Capabilities {
+    pub supports_streaming: bool,
+    pub supports_tools: bool,
+    pub supports_vision: bool,
+    pub supports_json_mode: bool,
+    /// Context window in tokens, when known.
+    pub max_context: Option<usize>,
+}
+
+impl Default for Capabilities {
+    /// Everything but vision, with an unknown context window.
+    fn default() -> Self {
+        Self {
+            supports_streaming: true,
+            supports_tools: true,
+            supports_vision: false,
+            supports_json_mode: true,
+            max_context: None,
+        }
+    }
+}
+
+impl Capabilities {
+    /// What Kandil's backend for `provider` can do with any model; nothing
+    /// for providers without one.
+    pub fn of_provider(provider: &str) -> Self {
+        let provider = provider.trim().to_lowercase();
+        crate::core::adapters::ai::backend::get(&provider)
+            .map(|backend| backend.capabilities())
+            .unwrap_or(Self {
+                supports_streaming: false,
+                supports_tools: false,
+                supports_vision: false,
+                supports_json_mode: false,
+                max_context: None,
+            })
+    }
+
+    /// What `model` can do when served by `provider`: its registry profile's
+    /// capabilities, or a guess from its name, narrowed to the provider's.
+    pub fn for_model(provider: &str, model: &str) -> Self {
+        let model = match UniversalModelRegistry::global().get_profile(model) {
+            Some(profile) => Capabilities {
+                max_context: profile.capabilities.max_context.or(Some(profile.context_window)),
+                ..profile.capabilities
+            },
+            None => Self::guess(model),
+        };
+        Self::of_provider(provider).and(&model)
+    }
+
+    /// Capabilities of a model without a registry profile, from its name.
+    pub fn guess(model: &str) -> Self {
+        let model = model.to_lowercase();
+        Self {
+            supports_vision: VISION_MARKERS.iter().any(|marker| model.contains(marker)),
+            ..Self::default()
+        }
+    }
+
+    /// What both `self` and `other` support.
+    pub fn and(&self, other: &Capabilities) -> Self {
+        Self {
+            supports_streaming: self.supports_streaming && other.supports_streaming,
+            supports_tools: self.supports_tools && other.supports_tools,
+            supports_vision: self.supports_vision && other.supports_vision,
+            supports_json_mode: self.supports_json_mode && other.supports_json_mode,
+            max_context: match (self.max_context, other.max_context) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+        }
+    }
+
+    /// Whether a prompt of roughly `tokens` tokens fits the context window.
+    pub fn fits(&self, tokens: usize) -> bool {
+        !self.max_context.is_some_and(|max| tokens > max)
+    }
+}
+
+/// Prints `message` as a warning the first time `key` is seen in this
+/// process, so a fallback taken on every call is reported once.
+pub fn warn_once(key: &str, message: impl std::fmt::Display) {
+    static SEEN: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    let first = SEEN
+        .get_or_init(Default::default)
+        .lock()
+        .map(|mut seen| seen.insert(key.to_string()))
+        .unwrap_or(true);
+    if first {
+        eprintln!("⚠️  {}", message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn provider_limits_narrow_model_capabilities() {
+        let model = Capabilities {
+            max_context: Some(32_768),
+            ..Capabilities::guess("llava:13b")
+        };
+        assert!(model.supports_vision);
+
+        let ollama = Capabilities::of_provider("ollama").and(&model);
+        assert!(ollama.supports_streaming && ollama.supports_json_mode && ollama.supports_vision);
+        assert!(!ollama.supports_tools);
+        assert_eq!(ollama.max_context, Some(32_768));
+        assert!(ollama.fits(32_768) && !ollama.fits(40_000));
+
+        let claude = Capabilities::of_provider("Claude").and(&Capabilities::guess("claude-2.1"));
+        assert!(claude.supports_tools && !claude.supports_json_mode);
+        assert!(!claude.supports_streaming && !claude.supports_vision);
+        assert!(claude.fits(usize::MAX));
+    }
+}
@@ -1,5 +1,16 @@
+//! Catalog of downloadable local models
+//!
+//! The built-in catalog is compiled in. An external JSON catalog at
+//! `~/.config/kandil/models.json` (or `KANDIL_CATALOG`) can add models or
+//! override built-in ones by name without a new release; `kandil local-model
+//! catalog update --url` fetches one. A file that fails validation is ignored
+//! with a warning, leaving the built-in catalog in place.
+
+use anyhow::{bail, Context, Result};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
 pub struct ModelSpec {
@@ -86,7 +97,14 @@ impl Speed {
 }
 
 lazy_static! {
-    pub static ref MODEL_CATALOG: Vec<ModelSpec> = vec![
+    /// The built-in models, merged with the external catalog file if there is
+    /// one; see [`load_catalog`].
+    pub static ref MODEL_CATALOG: Vec<ModelSpec> = load_catalog();
+}
+
+/// The models compiled into this binary.
+pub fn builtin_catalog() -> Vec<ModelSpec> {
+    vec![
         // Ultra-lightweight (2-4GB RAM systems)
         ModelSpec {
             name: "qwen2.5-coder-1.5b-q4",
@@ -100,7 +118,6 @@ lazy_static! {
             description: "Ultra-lightweight for basic completion on minimal hardware",
             context_sizes: &[2048, 4096],
         },
-
         // Lightweight (4-8GB RAM systems)
         ModelSpec {
             name: "qwen2.5-coder-3b-q4",
@@ -114,7 +131,6 @@ lazy_static! {
             description: "Balanced speed/quality for entry-level machines",
             context_sizes: &[2048, 4096, 8192],
         },
-
         // Standard (8-16GB RAM systems)
         ModelSpec {
             name: "qwen2.5-coder-7b-q4",
@@ -128,7 +144,6 @@ lazy_static! {
             description: "Recommended for most development tasks",
             context_sizes: &[4096, 8192, 16384, 32768],
         },
-
         // Professional (16-32GB RAM systems)
         ModelSpec {
             name: "qwen2.5-coder-14b-q4",
@@ -142,7 +157,6 @@ lazy_static! {
             description: "High-quality for complex refactoring",
             context_sizes: &[4096, 8192, 16384],
         },
-
         // Premium (32-64GB RAM systems)
         ModelSpec {
             name: "llama3.1-70b-q4",
@@ -156,7 +170,7 @@ lazy_static! {
             description: "Best-in-class for architectural decisions",
             context_sizes: &[4096, 8192],
         },
-    ];
+    ]
 }
 
 impl ModelSpec {
@@ -176,4 +190,192 @@ impl ModelSpec {
     }
 }
 
-// Add the module to the models mod.rs file
+/// One model in an external catalog file: [`ModelSpec`] with owned strings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CatalogEntry {
+    pub name: String,
+    pub huggingface_repo: String,
+    /// File name in the repository, also used for the downloaded file.
+    pub filename: String,
+    pub size_gb: f64,
+    pub ram_required_gb: u64,
+    #[serde(default)]
+    pub gpu_vram_min: Option<u64>,
+    pub speed_rating: Speed,
+    pub quality_rating: Quality,
+    #[serde(default)]
+    pub description: String,
+    pub context_sizes: Vec<usize>,
+}
+
+/// The external catalog file format.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CatalogFile {
+    pub models: Vec<CatalogEntry>,
+}
+
+impl CatalogEntry {
+    /// Checks what the schema alone can't. The file name becomes a path in
+    /// the models directory, so it must be a plain `.gguf` name.
+    fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() || self.name.contains(char::is_whitespace) {
+            bail!("name must be non-empty and contain no spaces");
+        }
+        let mut repo = self.huggingface_repo.split('/');
+        if !matches!(
+            (repo.next(), repo.next(), repo.next()),
+            (Some(owner), Some(name), None) if !owner.is_empty() && !name.is_empty()
+        ) {
+            bail!("huggingface_repo must look like `owner/repository`");
+        }
+        if self.filename.contains(['/', '\\'])
+            || self.filename.starts_with('.')
+            || !self.filename.ends_with(".gguf")
+        {
+            bail!("filename must be a plain .gguf file name");
+        }
+        if !(self.size_gb.is_finite() && self.size_gb > 0.0) {
+            bail!("size_gb must be positive");
+        }
+        if self.context_sizes.is_empty() {
+            bail!("context_sizes must list at least one size");
+        }
+        Ok(())
+    }
+
+    /// The catalog is built once per process, so leaking the few strings of
+    /// external entries is bounded.
+    fn into_spec(self) -> ModelSpec {
+        let leak = |text: String| -> &'static str { Box::leak(text.into_boxed_str()) };
+        ModelSpec {
+            name: leak(self.name),
+            huggingface_repo: leak(self.huggingface_repo),
+            filename: leak(self.filename),
+            size_gb: self.size_gb,
+            ram_required_gb: self.ram_required_gb,
+            gpu_vram_min: self.gpu_vram_min,
+            speed_rating: self.speed_rating,
+            quality_rating: self.quality_rating,
+            description: leak(self.description),
+            context_sizes: Box::leak(self.context_sizes.into_boxed_slice()),
+        }
+    }
+}
+
+/// Where the external catalog is read from and `catalog update` saves to.
+pub fn external_catalog_path() -> Option<PathBuf> {
+    match std::env::var("KANDIL_CATALOG") {
+        Ok(path) if !path.is_empty() => Some(PathBuf::from(path)),
+        _ => dirs::config_dir().map(|dir| dir.join("kandil").join("models.json")),
+    }
+}
+
+/// Parses an external catalog. One invalid entry rejects the whole file, so
+/// a half-broken catalog never partly replaces the built-in one.
+pub fn parse_catalog(text: &str) -> Result<Vec<CatalogEntry>> {
+    let file: CatalogFile = serde_json::from_str(text)?;
+    let mut names = HashSet::new();
+    for entry in &file.models {
+        entry
+            .validate()
+            .with_context(|| format!("model `{}`", entry.name))?;
+        if !names.insert(entry.name.as_str()) {
+            bail!("model `{}` is listed twice", entry.name);
+        }
+    }
+    Ok(file.models)
+}
+
+/// `builtin` with `external` entries added, replacing built-ins of the same name.
+fn merge(mut catalog: Vec<ModelSpec>, external: Vec<ModelSpec>) -> Vec<ModelSpec> {
+    for spec in external {
+        match catalog.iter_mut().find(|model| model.name == spec.name) {
+            Some(existing) => *existing = spec,
+            None => catalog.push(spec),
+        }
+    }
+    catalog
+}
+
+fn load_catalog() -> Vec<ModelSpec> {
+    let builtin = builtin_catalog();
+    let Some(path) = external_catalog_path() else {
+        return builtin;
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return builtin,
+        Err(err) => {
+            eprintln!("⚠️  Cannot read model catalog {}: {}", path.display(), err);
+            return builtin;
+        }
+    };
+    match parse_catalog(&text) {
+        Ok(entries) => merge(
+            builtin,
+            entries.into_iter().map(CatalogEntry::into_spec).collect(),
+        ),
+        Err(err) => {
+            eprintln!(
+                "⚠️  Ignoring model catalog {}: {:#}; using the built-in models",
+                path.display(),
+                err
+            );
+            builtin
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRY: &str = r#"{
+        "name": "qwen2.5-coder-7b-q4",
+        "huggingface_repo": "Qwen/Qwen2.5-Coder-7B-Instruct-GGUF",
+        "filename": "qwen2.5-coder-7b-instruct-q5_k_m.gguf",
+        "size_gb": 5.4,
+        "ram_required_gb": 14,
+        "speed_rating": { "Fast": 100 },
+        "quality_rating": "VeryGood",
+        "context_sizes": [4096, 32768]
+    }"#;
+
+    #[test]
+    fn external_entries_override_by_name_or_are_added() {
+        let added = ENTRY.replace("qwen2.5-coder-7b-q4", "new-model");
+        let text = format!(r#"{{ "models": [{}, {}] }}"#, ENTRY, added);
+        let entries = parse_catalog(&text).unwrap();
+        let builtin = builtin_catalog();
+        let count = builtin.len();
+
+        let catalog = merge(
+            builtin,
+            entries.into_iter().map(CatalogEntry::into_spec).collect(),
+        );
+        assert_eq!(catalog.len(), count + 1);
+        let replaced = catalog
+            .iter()
+            .find(|spec| spec.name == "qwen2.5-coder-7b-q4")
+            .unwrap();
+        assert_eq!(replaced.filename, "qwen2.5-coder-7b-instruct-q5_k_m.gguf");
+        assert_eq!(catalog.last().unwrap().name, "new-model");
+    }
+
+    #[test]
+    fn invalid_catalogs_are_rejected() {
+        let with = |field: &str, value: &str| {
+            let entry = ENTRY.replace(field, value);
+            parse_catalog(&format!(r#"{{ "models": [{}] }}"#, entry))
+        };
+        assert!(with("qwen2.5-coder-7b-instruct-q5_k_m.gguf", "../../.bashrc").is_err());
+        assert!(with("Qwen/Qwen2.5-Coder-7B-Instruct-GGUF", "no-owner").is_err());
+        assert!(with("[4096, 32768]", "[]").is_err());
+        assert!(with("\"size_gb\"", "\"size\"").is_err(), "unknown field");
+        let twice = format!(r#"{{ "models": [{}, {}] }}"#, ENTRY, ENTRY);
+        let err = parse_catalog(&twice).unwrap_err();
+        assert!(err.to_string().contains("listed twice"));
+    }
+}
@@ -2,8 +2,14 @@
 //!
 //! Contains modules for handling local model specifications and catalogs.
 
+pub mod capabilities;
 pub mod catalog;
 pub mod registry;
+pub mod usage;
 
 #[allow(unused_imports)]
-pub use registry::{ModelProfile, ModelResources, ProviderKind, UniversalModelRegistry};
+pub use capabilities::Capabilities;
+#[allow(unused_imports)]
+pub use registry::{
+    ModelProfile, ModelResources, ProviderKind, SelectionWarning, UniversalModelRegistry,
+};
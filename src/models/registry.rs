@@ -4,10 +4,12 @@
 //! with user supplied entries. The registry acts as the single source of
 //! truth for routing decisions referenced throughout the enhance_model plan.
 
+use crate::models::capabilities::Capabilities;
 use crate::models::catalog::{ModelSpec, MODEL_CATALOG};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use std::sync::RwLock;
 
 lazy_static! {
@@ -36,6 +38,26 @@ impl ProviderKind {
             ProviderKind::Custom(value) => value.to_lowercase(),
         }
     }
+
+    /// Whether a configured provider name (`KANDIL_AI_PROVIDER`, `[ai].provider`)
+    /// can serve models of this kind.
+    pub fn accepts(&self, provider: &str) -> bool {
+        let provider = match provider.trim().to_lowercase().as_str() {
+            "claude" => "anthropic".to_string(),
+            "lmstudio" | "gpt4all" | "foundry" | "foundry_local" => "local".to_string(),
+            other => other.to_string(),
+        };
+        provider == self.alias_key()
+    }
+}
+
+impl fmt::Display for ProviderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProviderKind::Custom(name) => write!(f, "custom ({})", name),
+            other => f.write_str(&other.alias_key()),
+        }
+    }
 }
 
 /// Resource expectations for a model profile.
@@ -58,6 +80,64 @@ pub struct ModelProfile {
     pub default_endpoint: Option<String>,
     pub resources: ModelResources,
     pub tags: Vec<String>,
+    /// What the model itself supports; the serving provider may allow less.
+    #[serde(default)]
+    pub capabilities: Capabilities,
+}
+
+impl ModelProfile {
+    /// Describes resource or context values that cannot be right, such as a
+    /// zero-token context window or a model larger than the RAM it needs.
+    pub fn dimension_issues(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        if self.context_window == 0 {
+            issues.push("context window is 0 tokens".to_string());
+        }
+        if !self.resources.size_gb.is_finite() || self.resources.size_gb < 0.0 {
+            issues.push(format!("invalid size {} GB", self.resources.size_gb));
+        }
+        if self.is_local && self.resources.min_system_ram_gb == 0 {
+            issues.push("local model requires 0 GB of RAM".to_string());
+        }
+        if !self.is_local && self.default_endpoint.is_none() {
+            issues.push("remote model has no endpoint".to_string());
+        }
+        issues
+    }
+}
+
+/// Why a configured provider/model pair does not match the registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectionWarning {
+    /// The model has no profile, so routing falls back to it blindly.
+    Unregistered { model: String },
+    /// The model is registered for a different provider.
+    ProviderMismatch {
+        model: String,
+        configured: String,
+        registered: ProviderKind,
+    },
+}
+
+impl fmt::Display for SelectionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectionWarning::Unregistered { model } => write!(
+                f,
+                "Model '{}' is not in the model registry; prompts will use it without a profile. See `kandil local-model registry`",
+                model
+            ),
+            SelectionWarning::ProviderMismatch {
+                model,
+                configured,
+                registered,
+            } => write!(
+                f,
+                "Model '{}' is registered for provider {}, but the configured provider is {}",
+                model, registered, configured
+            ),
+        }
+    }
 }
 
 impl From<&ModelSpec> for ModelProfile {
@@ -76,6 +156,10 @@ impl From<&ModelSpec> for ModelProfile {
                 min_vram_gb: spec.gpu_vram_min,
             },
             tags: vec!["gguf".to_string(), "local".to_string()],
+            capabilities: Capabilities {
+                max_context: spec.context_sizes.iter().max().copied(),
+                ..Capabilities::guess(spec.name)
+            },
         }
     }
 }
@@ -163,8 +247,44 @@ impl UniversalModelRegistry {
         None
     }
 
+    /// Checks that `model` can be used with `provider`: a registered model
+    /// must be served by it, and an unregistered one must not look like a
+    /// catalog name. Returns the profile, if there is one.
+    pub fn validate_selection(
+        &self,
+        provider: &str,
+        model: &str,
+    ) -> Result<Option<ModelProfile>, SelectionWarning> {
+        let local_runtime = !crate::utils::offline::is_cloud_provider(provider);
+        let Some(profile) = self.get_profile(model) else {
+            // Cloud model ids, Ollama names with or without a tag, and the ids
+            // LM Studio or GPT4All report go to the provider as they are.
+            return if looks_like_catalog_name(model) {
+                Err(SelectionWarning::Unregistered {
+                    model: model.to_string(),
+                })
+            } else {
+                Ok(None)
+            };
+        };
+
+        // Catalog models are GGUF files any local runtime can serve.
+        if profile.provider.accepts(provider) || (profile.is_local && local_runtime) {
+            Ok(Some(profile))
+        } else {
+            Err(SelectionWarning::ProviderMismatch {
+                model: model.to_string(),
+                configured: provider.to_string(),
+                registered: profile.provider,
+            })
+        }
+    }
+
     /// Registers or updates a custom profile.
     pub fn register_custom(&self, profile: ModelProfile) -> ModelProfile {
+        for issue in profile.dimension_issues() {
+            log::warn!("Model profile '{}': {}", profile.name, issue);
+        }
         {
             let mut custom = self.custom.write().expect("custom registry poisoned");
             custom.insert(profile.name.clone(), profile.clone());
@@ -186,6 +306,24 @@ impl UniversalModelRegistry {
     }
 }
 
+/// Whether `model` follows the catalog's `<family>-<size>b-q<bits>` naming,
+/// e.g. `qwen2.5-coder-7b-q4`. Runtimes don't name models that way, so an
+/// unknown name of that shape is a typo rather than a runtime's own id.
+fn looks_like_catalog_name(model: &str) -> bool {
+    let Some((rest, bits)) = model.rsplit_once("-q") else {
+        return false;
+    };
+    let size = rest
+        .rsplit_once('-')
+        .and_then(|(_, size)| size.strip_suffix('b'));
+    !model.contains(['/', ':'])
+        && !bits.is_empty()
+        && bits.chars().all(|c| c.is_ascii_digit())
+        && size.is_some_and(|size| {
+            !size.is_empty() && size.chars().all(|c| c.is_ascii_digit() || c == '.')
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -215,6 +353,7 @@ mod tests {
                 min_vram_gb: Some(16),
             },
             tags: vec!["custom".to_string()],
+            capabilities: Capabilities::default(),
         };
 
         registry.register_custom(profile.clone());
@@ -223,5 +362,59 @@ mod tests {
             .expect("profile should exist");
         assert_eq!(fetched.name, profile.name);
         assert_eq!(fetched.context_window, 8192);
+        assert_eq!(fetched.provider.to_string(), "custom (acme)");
+        assert!(fetched.dimension_issues().is_empty());
+    }
+
+    #[test]
+    fn selection_is_validated_against_provider() {
+        let registry = UniversalModelRegistry::global();
+        assert!(registry
+            .validate_selection("ollama", "qwen2.5-coder-7b-q4")
+            .is_ok());
+        assert!(registry
+            .validate_selection("lmstudio", "qwen2.5-coder-7b-q4")
+            .is_ok());
+        assert!(matches!(
+            registry.validate_selection("openai", "qwen2.5-coder-7b-q4"),
+            Err(SelectionWarning::ProviderMismatch { .. })
+        ));
+        // A catalog-style name that isn't in the catalog is a typo.
+        assert!(matches!(
+            registry.validate_selection("ollama", "qwen2.5-coder-9b-q4"),
+            Err(SelectionWarning::Unregistered { .. })
+        ));
+        assert!(matches!(
+            registry.validate_selection("lmstudio", "qwen2.5-coder-7b-q5"),
+            Err(SelectionWarning::Unregistered { .. })
+        ));
+        // Provider-native names are accepted without a profile.
+        assert!(matches!(
+            registry.validate_selection("ollama", "llama3:70b"),
+            Ok(None)
+        ));
+        assert!(matches!(
+            registry.validate_selection("ollama", "llama3"),
+            Ok(None)
+        ));
+        assert!(matches!(
+            registry.validate_selection(
+                "lmstudio",
+                "lmstudio-community/Meta-Llama-3-8B-Instruct-GGUF"
+            ),
+            Ok(None)
+        ));
+        assert!(matches!(
+            registry.validate_selection("gpt4all", "Meta-Llama-3-8B-Instruct.Q4_0.gguf"),
+            Ok(None)
+        ));
+        assert!(matches!(
+            registry.validate_selection("openai", "gpt-4o"),
+            Ok(None)
+        ));
+        assert!(matches!(
+            registry.validate_selection("claude", "claude-3-5-sonnet-latest"),
+            Ok(None)
+        ));
     }
 }
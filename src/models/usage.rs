@@ -0,0 +1,350 @@
+//! Local model usage and pruning
+//!
+//! Every chat answered by a local model records when the model was last used
+//! and how many chats it has served, in `model_usage.json` next to the models
+//! directory. The update is a single atomic rewrite of a small file, made
+//! after the answer has been shown. `local-model list` and `status` show it,
+//! `strategy suggest` names the models relied on most, and
+//! `kandil local-model prune` combines it with the files in the models
+//! directory to find models worth removing: those not used for a number of
+//! days, or the largest ones until enough disk is free. Models that were
+//! never used count from when they were downloaded.
+//!
+//! Runtimes name models their own way (Ollama says `qwen2.5-coder:7b` where
+//! the catalog says `qwen2.5-coder-7b-q4`), so usage is keyed by
+//! [`catalog_name`] and the names it is looked up by go through it too.
+
+use crate::models::catalog::MODEL_CATALOG;
+use crate::utils::json_store::JsonStore;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use sysinfo::{DiskExt, RefreshKind, System, SystemExt};
+
+const USAGE_FILE: &str = "model_usage.json";
+
+/// How much one model has been used.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelUsage {
+    pub last_used: DateTime<Utc>,
+    /// Chats the model has answered.
+    pub uses: u64,
+}
+
+/// A model file in the models directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstalledModel {
+    /// Catalog name, or the file name for files the catalog doesn't know.
+    pub name: String,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub last_used: Option<DateTime<Utc>>,
+    pub uses: u64,
+    /// When the file was written, i.e. downloaded.
+    pub installed_at: DateTime<Utc>,
+}
+
+impl InstalledModel {
+    /// When the model was last used, or installed if it never was.
+    pub fn last_active(&self) -> DateTime<Utc> {
+        self.last_used.unwrap_or(self.installed_at)
+    }
+
+    /// e.g. "last used 3 days ago, 12 chats" or "never used (installed 5 days ago)".
+    pub fn describe_use(&self, now: DateTime<Utc>) -> String {
+        match self.last_used {
+            Some(at) => format!(
+                "last used {}, {} chat{}",
+                days_ago(now - at),
+                self.uses,
+                if self.uses == 1 { "" } else { "s" }
+            ),
+            None => format!(
+                "never used (installed {})",
+                days_ago(now - self.installed_at)
+            ),
+        }
+    }
+}
+
+fn days_ago(elapsed: Duration) -> String {
+    match elapsed.num_days() {
+        0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        days => format!("{} days ago", days),
+    }
+}
+
+/// The catalog name for a model as a runtime or config names it: a catalog
+/// name, a model file name or an Ollama tag. Other names are returned as is.
+pub fn catalog_name(model: &str) -> String {
+    let runtime_id = [model.to_string()];
+    MODEL_CATALOG
+        .iter()
+        .find(|spec| spec.name == model || spec.filename == model)
+        .or_else(|| {
+            MODEL_CATALOG.iter().find(|spec| {
+                model.contains(':')
+                    && crate::core::auto_config::installed_tag(spec.name, &runtime_id).is_some()
+            })
+        })
+        .map_or_else(|| model.to_string(), |spec| spec.name.to_string())
+}
+
+/// Notes that `model` just answered a chat. Failures are logged, not returned, so a
+/// read-only data directory never breaks a chat.
+pub fn record(model: &str) {
+    if let Err(err) = record_in(&JsonStore::in_data_dir(USAGE_FILE), model, Utc::now()) {
+        log::warn!("Cannot record use of {}: {:#}", model, err);
+    }
+}
+
+fn record_in(store: &JsonStore, model: &str, at: DateTime<Utc>) -> Result<()> {
+    store.update(|usage: &mut HashMap<String, ModelUsage>| {
+        let entry = usage.entry(catalog_name(model)).or_insert(ModelUsage {
+            last_used: at,
+            uses: 0,
+        });
+        entry.last_used = at;
+        entry.uses += 1;
+        Ok(())
+    })
+}
+
+/// Usage of each model, by name.
+pub fn load() -> Result<HashMap<String, ModelUsage>> {
+    JsonStore::in_data_dir(USAGE_FILE).load()
+}
+
+/// Up to `limit` model names, most chats first.
+pub fn most_used(usage: &HashMap<String, ModelUsage>, limit: usize) -> Vec<(&str, ModelUsage)> {
+    let mut models: Vec<(&str, ModelUsage)> = usage
+        .iter()
+        .map(|(name, usage)| (name.as_str(), *usage))
+        .collect();
+    models.sort_by(|a, b| {
+        b.1.uses
+            .cmp(&a.1.uses)
+            .then(b.1.last_used.cmp(&a.1.last_used))
+    });
+    models.truncate(limit);
+    models
+}
+
+/// The model files in `dir`, largest first. Usage recorded under the catalog
+/// name, the file name or an Ollama tag for the model counts.
+pub fn installed(dir: &Path, usage: &HashMap<String, ModelUsage>) -> Result<Vec<InstalledModel>> {
+    // Files written before usage was keyed by catalog name may hold tags
+    let mut by_name: HashMap<String, ModelUsage> = HashMap::new();
+    for (model, used) in usage {
+        let entry = by_name.entry(catalog_name(model)).or_insert(ModelUsage {
+            last_used: used.last_used,
+            uses: 0,
+        });
+        entry.last_used = entry.last_used.max(used.last_used);
+        entry.uses += used.uses;
+    }
+    let usage = &by_name;
+    let mut models = Vec::new();
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(models),
+        Err(err) => return Err(err.into()),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let name = MODEL_CATALOG
+            .iter()
+            .find(|spec| spec.filename == file_name)
+            .map(|spec| spec.name.to_string())
+            .unwrap_or_else(|| file_name.clone());
+        let used = usage.get(&name).or_else(|| usage.get(&file_name));
+        models.push(InstalledModel {
+            name,
+            path: entry.path(),
+            size_bytes: metadata.len(),
+            last_used: used.map(|used| used.last_used),
+            uses: used.map_or(0, |used| used.uses),
+            installed_at: metadata
+                .modified()
+                .map(DateTime::from)
+                .unwrap_or_else(|_| Utc::now()),
+        });
+    }
+    models.sort_by(|a, b| {
+        b.size_bytes
+            .cmp(&a.size_bytes)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(models)
+}
+
+/// Which models to remove. With `older_than_days`, only models idle for that
+/// long are candidates. With `target_free_bytes`, the largest candidates are
+/// taken until `free_bytes` would reach it; otherwise every candidate is.
+/// Models in `keep` are never chosen.
+pub fn select<'a>(
+    models: &'a [InstalledModel],
+    older_than_days: Option<u64>,
+    target_free_bytes: Option<u64>,
+    free_bytes: u64,
+    keep: &[&str],
+    now: DateTime<Utc>,
+) -> Vec<&'a InstalledModel> {
+    let candidates = models.iter().filter(|model| {
+        !keep.contains(&model.name.as_str())
+            && older_than_days.map_or(true, |days| {
+                now - model.last_active() >= Duration::days(days as i64)
+            })
+    });
+    let Some(target) = target_free_bytes else {
+        return candidates.collect();
+    };
+
+    // `models` is largest first, so this frees the target with the fewest removals
+    let mut free = free_bytes;
+    let mut chosen = Vec::new();
+    for model in candidates {
+        if free >= target {
+            break;
+        }
+        free += model.size_bytes;
+        chosen.push(model);
+    }
+    chosen
+}
+
+/// Free space on the disk holding `path`, in bytes.
+pub fn free_space(path: &Path) -> u64 {
+    let mut sys = System::new_with_specifics(RefreshKind::new().with_disks_list());
+    sys.refresh_disks();
+    sys.disks()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    fn model(name: &str, size_gb: u64, idle_days: i64, now: DateTime<Utc>) -> InstalledModel {
+        InstalledModel {
+            name: name.to_string(),
+            path: PathBuf::from(name),
+            size_bytes: size_gb * GB,
+            last_used: Some(now - Duration::days(idle_days)),
+            uses: 1,
+            installed_at: now - Duration::days(365),
+        }
+    }
+
+    #[test]
+    fn prune_selects_idle_models_or_the_largest_until_the_target() {
+        let now = Utc::now();
+        let models = vec![
+            model("big", 8, 40, now),
+            model("current", 5, 90, now),
+            model("medium", 4, 2, now),
+            model("small", 1, 60, now),
+        ];
+        let names = |chosen: Vec<&InstalledModel>| {
+            chosen
+                .iter()
+                .map(|model| model.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let idle = select(&models, Some(30), None, 0, &["current"], now);
+        assert_eq!(names(idle), ["big", "small"]);
+
+        let target = select(&models, None, Some(20 * GB), 10 * GB, &["current"], now);
+        assert_eq!(names(target), ["big", "medium"]);
+        assert!(select(&models, None, Some(5 * GB), 10 * GB, &[], now).is_empty());
+
+        let both = select(&models, Some(30), Some(20 * GB), 10 * GB, &["current"], now);
+        assert_eq!(
+            names(both),
+            ["big", "small"],
+            "the target may not be reachable"
+        );
+    }
+
+    #[test]
+    fn usage_is_recorded_and_never_used_models_count_from_install() {
+        let dir = std::env::temp_dir().join(format!("kandil_usage_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("models")).unwrap();
+        let spec = &MODEL_CATALOG[0];
+        std::fs::write(dir.join("models").join(spec.filename), b"gguf").unwrap();
+        std::fs::write(dir.join("models").join("custom.gguf"), b"gguf-bytes").unwrap();
+
+        let store = JsonStore::new(dir.join(USAGE_FILE));
+        let used_at: DateTime<Utc> = "2026-01-02T03:04:05Z".parse().unwrap();
+        record_in(&store, spec.name, used_at - Duration::days(1)).unwrap();
+        record_in(&store, spec.name, used_at).unwrap();
+
+        let usage = store.load().unwrap();
+        let models = installed(&dir.join("models"), &usage).unwrap();
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].name, "custom.gguf", "largest first");
+        assert_eq!(models[0].last_used, None);
+        assert_eq!(models[0].last_active(), models[0].installed_at);
+        assert_eq!(
+            models[0].describe_use(models[0].installed_at),
+            "never used (installed today)"
+        );
+        assert_eq!(models[1].name, spec.name);
+        assert_eq!(models[1].last_active(), used_at);
+        assert_eq!(models[1].uses, 2);
+        assert_eq!(
+            models[1].describe_use(used_at + Duration::days(3)),
+            "last used 3 days ago, 2 chats"
+        );
+        assert_eq!(most_used(&usage, 5)[0].0, spec.name);
+        assert!(installed(&dir.join("missing"), &usage).unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn chats_under_an_ollama_tag_keep_the_model_file() {
+        let dir = std::env::temp_dir().join(format!("kandil_usage_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("models")).unwrap();
+        let spec = MODEL_CATALOG
+            .iter()
+            .find(|spec| spec.name == "qwen2.5-coder-7b-q4")
+            .unwrap();
+        std::fs::write(dir.join("models").join(spec.filename), b"gguf").unwrap();
+        assert_eq!(catalog_name("qwen2.5-coder:7b"), spec.name);
+        assert_eq!(catalog_name(spec.filename), spec.name);
+        assert_eq!(catalog_name("mistral:7b"), "mistral:7b");
+
+        let store = JsonStore::new(dir.join(USAGE_FILE));
+        let now = Utc::now();
+        record_in(&store, "qwen2.5-coder:7b", now).unwrap();
+        record_in(&store, spec.name, now).unwrap();
+        let usage = store.load().unwrap();
+        assert_eq!(usage.len(), 1);
+
+        let models = installed(&dir.join("models"), &usage).unwrap();
+        assert_eq!(models[0].uses, 2);
+        assert!(select(&models, Some(30), None, 0, &[], now).is_empty());
+        let later = now + Duration::days(60);
+        let keep = catalog_name("qwen2.5-coder:7b");
+        assert!(select(&models, Some(30), None, 0, &[keep.as_str()], later).is_empty());
+        assert_eq!(select(&models, Some(30), None, 0, &[], later).len(), 1);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
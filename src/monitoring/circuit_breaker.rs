@@ -1,10 +1,21 @@
 //! Circuit breaker implementation for Kandil Code
 //!
 //! Provides resilience patterns for AI service calls.
-
+//!
+//! An open breaker can be reset by hand once the provider has recovered:
+//! `kandil config reset-breaker <provider>` records the reset in a state file
+//! that long-running sessions check while their breaker is open. Breakers
+//! live in memory, so each one-off CLI command starts with a closed one.
+
+use crate::utils::json_store::JsonStore;
+use anyhow::Result;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+/// Manual resets by provider, as the Unix time they were requested.
+const RESETS_FILE: &str = "breaker_resets.json";
+
 pub struct CircuitBreaker {
     failures: AtomicU64,
     successes: AtomicU64,
@@ -81,11 +92,27 @@ impl CircuitBreaker {
         self.failures.store(0, Ordering::Relaxed);
     }
 
+    /// Closes the breaker by hand so the next call goes through. It stays
+    /// half-open: one more failure opens it again, a success clears it.
     pub fn reset(&self) {
-        self.failures.store(0, Ordering::Relaxed);
-        self.successes.store(0, Ordering::Relaxed);
+        self.failures
+            .store(self.threshold.saturating_sub(1), Ordering::Relaxed);
         self.is_open.store(false, Ordering::Relaxed);
-        self.last_failure.store(0, Ordering::Relaxed);
+    }
+
+    /// Like [`Self::is_open`], but first applies a manual reset of `provider`
+    /// requested since the breaker opened.
+    pub fn is_open_for(&self, provider: &str) -> bool {
+        if !self.is_open() {
+            return false;
+        }
+        let opened_at = self.last_failure.load(Ordering::Relaxed);
+        if reset_requested(&JsonStore::in_data_dir(RESETS_FILE), provider, opened_at) {
+            log::info!("Circuit breaker for {} was reset manually", provider);
+            self.reset();
+            return false;
+        }
+        true
     }
 
     pub fn get_state(&self) -> CircuitState {
@@ -108,6 +135,39 @@ impl CircuitBreaker {
     }
 }
 
+/// Asks every session using `provider` to reset its open breaker on its next
+/// request.
+pub fn request_reset(provider: &str) -> Result<()> {
+    record_reset(&JsonStore::in_data_dir(RESETS_FILE), provider, unix_now())
+}
+
+fn record_reset(store: &JsonStore, provider: &str, at: u64) -> Result<()> {
+    store.update(|resets: &mut HashMap<String, u64>| {
+        resets.insert(provider.to_string(), at);
+        Ok(())
+    })
+}
+
+/// Whether a reset of `provider` was requested at or after `since`, in Unix
+/// seconds. Both are whole seconds, so a reset in the second the breaker
+/// opened still counts.
+fn reset_requested(store: &JsonStore, provider: &str, since: u64) -> bool {
+    match store.load::<HashMap<String, u64>>() {
+        Ok(resets) => resets.get(provider).is_some_and(|&at| at >= since),
+        Err(err) => {
+            log::warn!("Cannot read circuit breaker resets: {:#}", err);
+            false
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
 #[derive(PartialEq, Debug)]
 
 pub enum CircuitState {
@@ -202,4 +262,41 @@ mod tests {
         circuit_breaker.record_success();
         assert_eq!(circuit_breaker.get_state(), CircuitState::Closed);
     }
+
+    #[test]
+    fn a_manual_reset_allows_one_attempt() {
+        let circuit_breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        for _ in 0..3 {
+            circuit_breaker.record_failure();
+        }
+        assert!(circuit_breaker.is_open());
+
+        circuit_breaker.reset();
+        assert!(!circuit_breaker.is_open());
+        assert_eq!(circuit_breaker.get_state(), CircuitState::HalfOpen);
+
+        circuit_breaker.record_failure();
+        assert!(circuit_breaker.is_open());
+
+        circuit_breaker.reset();
+        circuit_breaker.record_success();
+        assert_eq!(circuit_breaker.get_state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn only_resets_since_the_breaker_opened_count() {
+        let store = JsonStore::new(
+            std::env::temp_dir().join(format!("kandil_breaker_{}.json", uuid::Uuid::new_v4())),
+        );
+        assert!(!reset_requested(&store, "ollama", 100));
+
+        record_reset(&store, "ollama", 150).unwrap();
+        assert!(reset_requested(&store, "ollama", 100));
+        assert!(reset_requested(&store, "ollama", 150));
+        assert!(!reset_requested(&store, "ollama", 151));
+        assert!(!reset_requested(&store, "claude", 100));
+
+        let _ = std::fs::remove_file(store.path());
+        let _ = std::fs::remove_file(store.path().with_extension("lock"));
+    }
 }
@@ -1,6 +1,8 @@
 use anyhow::Result;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 use tokio::time::sleep;
 
@@ -26,6 +28,33 @@ pub struct PerformanceTester {
     reports: Vec<PerformanceReport>,
 }
 
+/// A fresh directory under the system temp dir, removed with its contents
+/// when dropped, so a test that fails, panics or is cancelled halfway
+/// doesn't leave files behind. Names are unique per run, so concurrent runs
+/// never share one.
+struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    fn new(prefix: &str) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("{}_{}", prefix, uuid::Uuid::new_v4()));
+        // `create_dir`, not `create_dir_all`: fail rather than reuse a directory
+        std::fs::create_dir(&path)?;
+        Ok(Self { path })
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
 impl PerformanceTester {
     pub fn new() -> Self {
         Self {
@@ -51,39 +80,39 @@ impl PerformanceTester {
         // Test 2: Command execution speed
         let cmd_speed_result = self.test_command_execution_speed().await;
         if let Ok(report) = cmd_speed_result {
-            self.reports.push(report);
-            metrics.extend(report.metrics);
+            metrics.extend(report.metrics.clone());
             if matches!(report.status, TestStatus::Failed) {
                 status = TestStatus::Warning;
             }
+            self.reports.push(report);
         }
 
         // Test 3: Memory usage patterns
         let mem_usage_result = self.test_memory_usage().await;
         if let Ok(report) = mem_usage_result {
+            metrics.extend(report.metrics.clone());
             self.reports.push(report);
-            metrics.extend(report.metrics);
         }
 
         // Test 4: AI response latency
         let ai_latency_result = self.test_ai_response_latency().await;
         if let Ok(report) = ai_latency_result {
+            metrics.extend(report.metrics.clone());
             self.reports.push(report);
-            metrics.extend(report.metrics);
         }
 
         // Test 5: File I/O operations
         let io_result = self.test_file_io_performance().await;
         if let Ok(report) = io_result {
+            metrics.extend(report.metrics.clone());
             self.reports.push(report);
-            metrics.extend(report.metrics);
         }
 
         // Test 6: Terminal rendering performance
         let render_result = self.test_rendering_performance().await;
         if let Ok(report) = render_result {
+            metrics.extend(report.metrics.clone());
             self.reports.push(report);
-            metrics.extend(report.metrics);
         }
 
         let total_duration = start_time.elapsed();
@@ -158,7 +187,7 @@ impl PerformanceTester {
 
     /// Test memory usage
     async fn test_memory_usage(&self) -> Result<PerformanceReport> {
-        use sysinfo::{System, SystemExt, ProcessExt};
+        use sysinfo::{System, SystemExt};
         
         let mut sys = System::new_all();
         sys.refresh_all();
@@ -200,10 +229,11 @@ impl PerformanceTester {
     async fn test_ai_response_latency(&self) -> Result<PerformanceReport> {
         let start = Instant::now();
         
-        // Simulate AI responses
+        // Simulate AI responses; `--seed` makes the simulated timings repeatable
+        let mut rng = crate::utils::seed::rng();
         for _ in 0..10 {
             // Simulate AI processing time
-            sleep(Duration::from_millis(50 + (rand::random::<u64>() % 100))).await; // Vary response time
+            sleep(Duration::from_millis(50 + rng.gen_range(0..100))).await; // Vary response time
         }
         
         let duration = start.elapsed();
@@ -227,14 +257,13 @@ impl PerformanceTester {
 
     /// Test File I/O performance
     async fn test_file_io_performance(&self) -> Result<PerformanceReport> {
-        let temp_dir = std::env::temp_dir().join("kandil_perf_test");
-        std::fs::create_dir_all(&temp_dir)?;
+        let temp_dir = ScratchDir::new("kandil_perf_test")?;
         
         let start = Instant::now();
         
         // Write/read operations
         for i in 0..100 {
-            let file_path = temp_dir.join(format!("test_file_{}.txt", i));
+            let file_path = temp_dir.path().join(format!("test_file_{}.txt", i));
             let content = format!("Performance test content for file {}", i);
             
             std::fs::write(&file_path, &content)?;
@@ -243,9 +272,6 @@ impl PerformanceTester {
         
         let duration = start.elapsed();
         
-        // Cleanup
-        std::fs::remove_dir_all(&temp_dir)?;
-        
         Ok(PerformanceReport {
             test_name: "File I/O Performance Test".to_string(),
             timestamp: chrono::Utc::now(),
@@ -267,14 +293,14 @@ impl PerformanceTester {
     async fn test_rendering_performance(&self) -> Result<PerformanceReport> {
         use crate::enhanced_ui::terminal::KandilTerminal;
         
-        let terminal = KandilTerminal::new()?;
+        let _terminal = KandilTerminal::new()?;
         
         let start = Instant::now();
         
         // Render multiple frames to test performance
         for i in 0..50 {
             // Create dummy frame content
-            let frame_content = format!("Frame {} content with some text\nLine 2\nLine 3", i);
+            let _frame_content = format!("Frame {} content with some text\nLine 2\nLine 3", i);
             // The terminal's internal rendering would be tested here
             // For now, we'll simulate the rendering time
             sleep(Duration::from_millis(5)).await;
@@ -353,13 +379,15 @@ impl PerformanceTester {
         report
     }
 
-    /// Run a specific benchmark test
-    pub async fn run_benchmark(&self, benchmark_type: &str) -> Result<PerformanceReport> {
+    /// Run a specific benchmark test. Unknown types run the comprehensive
+    /// suite, whose reports are kept for [`Self::generate_report`].
+    pub async fn run_benchmark(&mut self, benchmark_type: &str) -> Result<PerformanceReport> {
         match benchmark_type {
             "cpu" => self.benchmark_cpu().await,
             "memory" => self.benchmark_memory().await,
             "io" => self.benchmark_io().await,
             "ai" => self.benchmark_ai_performance().await,
+            "context" => self.benchmark_context().await,
             _ => {
                 // Default to comprehensive test
                 let start = Instant::now();
@@ -382,8 +410,8 @@ impl PerformanceTester {
         let start = Instant::now();
         
         // CPU-intensive operation
-        let mut sum = 0;
-        for i in 0..1000000 {
+        let mut sum: u64 = 0;
+        for i in 0..1_000_000u64 {
             sum += i ^ (i << 2) ^ (i >> 3);
         }
         
@@ -441,14 +469,13 @@ impl PerformanceTester {
     }
 
     async fn benchmark_io(&self) -> Result<PerformanceReport> {
-        let temp_dir = std::env::temp_dir().join("kandil_io_test");
-        std::fs::create_dir_all(&temp_dir)?;
+        let temp_dir = ScratchDir::new("kandil_io_test")?;
         
         let start = Instant::now();
         
         // Random read/write test
         for i in 0..500 {
-            let file_path = temp_dir.join(format!("io_test_{}.dat", i));
+            let file_path = temp_dir.path().join(format!("io_test_{}.dat", i));
             let data = vec![i as u8; 1024]; // 1KB files
             std::fs::write(&file_path, &data)?;
             let _read_data = std::fs::read(&file_path)?;
@@ -456,9 +483,6 @@ impl PerformanceTester {
         
         let duration = start.elapsed();
         
-        // Cleanup
-        std::fs::remove_dir_all(&temp_dir)?;
-        
         Ok(PerformanceReport {
             test_name: "IO Benchmark".to_string(),
             timestamp: chrono::Utc::now(),
@@ -507,6 +531,77 @@ impl PerformanceTester {
     }
 }
 
+impl PerformanceTester {
+    /// Times gathering context from a 200-file project, first the way it
+    /// used to be done, reading every file on one thread, then with the
+    /// default readers and token budget.
+    async fn benchmark_context(&self) -> Result<PerformanceReport> {
+        use crate::core::context_manager::ContextManager;
+
+        const FILES: usize = 200;
+        let project = ScratchDir::new("kandil_context_bench")?;
+        let src = project.path().join("src");
+        std::fs::create_dir(&src)?;
+        for index in 0..FILES {
+            let body: String = (0..40)
+                .map(|item| {
+                    format!(
+                        "pub fn user_{}_{}(id: u64) -> u64 {{\n    let user = id * {};\n    user + {}\n}}\n\n",
+                        index, item, item, index
+                    )
+                })
+                .collect();
+            std::fs::write(src.join(format!("module_{:03}.rs", index)), body)?;
+        }
+        let workspace = project.path().to_string_lossy().to_string();
+        let task = "fix the user lookup";
+
+        let start = Instant::now();
+        let before = ContextManager::new()?
+            .with_readers(1)
+            .with_token_budget(usize::MAX)
+            .prepare_context(task, &workspace)?;
+        let sequential = start.elapsed();
+
+        let start = Instant::now();
+        let after = ContextManager::new()?.prepare_context(task, &workspace)?;
+        let parallel = start.elapsed();
+
+        let speedup = sequential.as_secs_f64() / parallel.as_secs_f64().max(f64::EPSILON);
+        let mut metrics = HashMap::new();
+        metrics.insert("files".to_string(), FILES.to_string());
+        metrics.insert(
+            "sequential_ms".to_string(),
+            sequential.as_millis().to_string(),
+        );
+        metrics.insert("parallel_ms".to_string(), parallel.as_millis().to_string());
+        metrics.insert("speedup".to_string(), format!("{:.1}x", speedup));
+        metrics.insert(
+            "files_kept".to_string(),
+            format!("{} / {}", after.files.len(), before.files.len()),
+        );
+
+        Ok(PerformanceReport {
+            test_name: "Context Benchmark".to_string(),
+            timestamp: chrono::Utc::now(),
+            duration: sequential + parallel,
+            metrics,
+            status: if parallel <= sequential {
+                TestStatus::Passed
+            } else {
+                TestStatus::Warning
+            },
+            details: format!(
+                "Gathering context from {} files: {} ms on one thread without a budget, {} ms with the defaults ({:.1}x)",
+                FILES,
+                sequential.as_millis(),
+                parallel.as_millis(),
+                speedup
+            ),
+        })
+    }
+}
+
 // Convenience function for running performance tests
 pub async fn run_performance_tests() -> Result<String> {
     let mut tester = PerformanceTester::new();
@@ -532,6 +627,14 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn unknown_benchmarks_keep_the_suite_reports() -> Result<()> {
+        let mut tester = PerformanceTester::new();
+        tester.run_benchmark("everything").await?;
+        assert!(!tester.reports.is_empty());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_individual_benchmarks() -> Result<()> {
         let tester = PerformanceTester::new();
@@ -544,7 +647,37 @@ mod tests {
         // Test memory benchmark
         let mem_report = tester.benchmark_memory().await?;
         assert_eq!(mem_report.test_name, "Memory Benchmark");
+
+        let context_report = tester.benchmark_context().await?;
+        assert_eq!(context_report.metrics["files"], "200");
+        assert!(context_report.metrics.contains_key("speedup"));
         
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn scratch_dirs_are_removed_however_the_test_exits() {
+        fn failing_io(seen: &mut Option<PathBuf>) -> Result<()> {
+            let dir = ScratchDir::new("kandil_perf_test")?;
+            *seen = Some(dir.path().to_path_buf());
+            std::fs::write(dir.path().join("test_file_0.txt"), "partial")?;
+            anyhow::bail!("simulated failure halfway through")
+        }
+        let mut seen = None;
+        assert!(failing_io(&mut seen).is_err());
+        assert!(!seen.unwrap().exists());
+
+        let mut seen = None;
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let dir = ScratchDir::new("kandil_io_test").unwrap();
+            seen = Some(dir.path().to_path_buf());
+            panic!("simulated panic");
+        }));
+        assert!(panicked.is_err());
+        assert!(!seen.unwrap().exists());
+
+        let first = ScratchDir::new("kandil_io_test").unwrap();
+        let second = ScratchDir::new("kandil_io_test").unwrap();
+        assert_ne!(first.path(), second.path());
+    }
+}
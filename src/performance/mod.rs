@@ -1,5 +1,12 @@
 pub mod benchmarks;
+mod profiler;
+pub mod watchdog;
 pub use benchmarks::{PerformanceTester, PerformanceReport, TestStatus, run_performance_tests};
+pub use profiler::{
+    emit_profile_report, profile_report, profiling_enabled, record, ProfileReport, Profiler,
+    SpanSummary,
+};
+pub use watchdog::Watchdog;
 
 // Additional performance testing and monitoring utilities
 use std::time::Duration;
@@ -11,7 +18,7 @@ pub async fn run_all_performance_tests() -> anyhow::Result<String> {
 
 /// Get system performance metrics
 pub fn get_system_metrics() -> SystemMetrics {
-    use sysinfo::{System, SystemExt, ProcessExt};
+    use sysinfo::{CpuExt, System, SystemExt};
     
     let mut sys = System::new_all();
     sys.refresh_all();
@@ -42,33 +49,3 @@ where
     let duration = start.elapsed();
     (result, duration)
 }
-
-/// Performance profiling decorator for functions
-pub struct Profiler {
-    start_time: std::time::Instant,
-    name: String,
-}
-
-impl Profiler {
-    pub fn start(name: &str) -> Self {
-        Self {
-            start_time: std::time::Instant::now(),
-            name: name.to_string(),
-        }
-    }
-    
-    pub fn stop(&mut self) -> Duration {
-        let duration = self.start_time.elapsed();
-        println!("⏱️  {} completed in {:?}", self.name, duration);
-        duration
-    }
-}
-
-impl Drop for Profiler {
-    fn drop(&mut self) {
-        if !std::thread::panicking() {
-            let duration = self.start_time.elapsed();
-            println!("⏱️  {} completed in {:?}", self.name, duration);
-        }
-    }
-}
\ No newline at end of file
@@ -0,0 +1,230 @@
+//! Timing spans collected into a process-wide registry
+//!
+//! A [`Profiler`] records how long its span took when it's stopped or
+//! dropped. Spans with the same name are aggregated, and [`profile_report`]
+//! summarizes them. The CLI prints the report on exit when `KANDIL_PROFILE=1`.
+//! `KANDIL_PROFILE=print` also prints every span as it completes, which is
+//! handy when debugging a single slow call.
+
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+static SPANS: Mutex<Vec<SpanStats>> = Mutex::new(Vec::new());
+
+struct SpanStats {
+    name: String,
+    calls: u64,
+    total: Duration,
+    min: Duration,
+    max: Duration,
+}
+
+/// Whether the profile report should be emitted (`KANDIL_PROFILE=1`).
+pub fn profiling_enabled() -> bool {
+    matches!(
+        std::env::var("KANDIL_PROFILE").as_deref(),
+        Ok("1") | Ok("true") | Ok("print")
+    )
+}
+
+fn print_spans() -> bool {
+    std::env::var("KANDIL_PROFILE").as_deref() == Ok("print")
+}
+
+/// Times a span from `start` until `stop` or drop.
+pub struct Profiler {
+    start_time: Instant,
+    name: String,
+    print: bool,
+    stopped: bool,
+}
+
+impl Profiler {
+    pub fn start(name: &str) -> Self {
+        Self {
+            start_time: Instant::now(),
+            name: name.to_string(),
+            print: print_spans(),
+            stopped: false,
+        }
+    }
+
+    /// Also prints the span's duration when it completes.
+    pub fn print_on_drop(mut self) -> Self {
+        self.print = true;
+        self
+    }
+
+    /// Ends the span early and returns its duration.
+    pub fn stop(&mut self) -> Duration {
+        let duration = self.start_time.elapsed();
+        if !self.stopped {
+            self.stopped = true;
+            record(&self.name, duration);
+            if self.print {
+                eprintln!("⏱️  {} completed in {:?}", self.name, duration);
+            }
+        }
+        duration
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            self.stop();
+        }
+    }
+}
+
+/// Adds one completed span to the registry.
+pub fn record(name: &str, duration: Duration) {
+    let mut spans = SPANS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    match spans.iter_mut().find(|span| span.name == name) {
+        Some(span) => {
+            span.calls += 1;
+            span.total += duration;
+            span.min = span.min.min(duration);
+            span.max = span.max.max(duration);
+        }
+        None => spans.push(SpanStats {
+            name: name.to_string(),
+            calls: 1,
+            total: duration,
+            min: duration,
+            max: duration,
+        }),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpanSummary {
+    pub name: String,
+    pub calls: u64,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProfileReport {
+    /// Slowest total first.
+    pub spans: Vec<SpanSummary>,
+}
+
+impl ProfileReport {
+    pub fn span(&self, name: &str) -> Option<&SpanSummary> {
+        self.spans.iter().find(|span| span.name == name)
+    }
+
+    pub fn render_text(&self) -> String {
+        if self.spans.is_empty() {
+            return "⏱️  Profile: no spans recorded\n".to_string();
+        }
+        let width = self
+            .spans
+            .iter()
+            .map(|span| span.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        let mut text = format!(
+            "⏱️  Profile\n  {:<width$} {:>6} {:>12} {:>12} {:>12}\n",
+            "span",
+            "calls",
+            "total",
+            "mean",
+            "max",
+            width = width
+        );
+        for span in &self.spans {
+            text.push_str(&format!(
+                "  {:<width$} {:>6} {:>10.1}ms {:>10.1}ms {:>10.1}ms\n",
+                span.name,
+                span.calls,
+                span.total_ms,
+                span.mean_ms,
+                span.max_ms,
+                width = width
+            ));
+        }
+        text
+    }
+}
+
+/// Everything recorded so far, aggregated by span name.
+pub fn profile_report() -> ProfileReport {
+    let spans = SPANS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let mut summaries: Vec<SpanSummary> = spans
+        .iter()
+        .map(|span| SpanSummary {
+            name: span.name.clone(),
+            calls: span.calls,
+            total_ms: ms(span.total),
+            mean_ms: ms(span.total) / span.calls as f64,
+            min_ms: ms(span.min),
+            max_ms: ms(span.max),
+        })
+        .collect();
+    summaries.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+    ProfileReport { spans: summaries }
+}
+
+/// Prints the report to stderr as `text` or `json` when profiling is enabled,
+/// keeping stdout clean for the command's own output.
+pub fn emit_profile_report(format: &str) {
+    if !profiling_enabled() {
+        return;
+    }
+    let report = profile_report();
+    match format {
+        "json" => match serde_json::to_string_pretty(&report) {
+            Ok(json) => eprintln!("{}", json),
+            Err(err) => log::warn!("Cannot serialize profile report: {}", err),
+        },
+        _ => eprint!("{}", report.render_text()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans_are_aggregated_by_name() {
+        record("test.aggregate", Duration::from_millis(30));
+        record("test.aggregate", Duration::from_millis(10));
+        {
+            let _span = Profiler::start("test.dropped");
+        }
+        let mut stopped = Profiler::start("test.stopped");
+        stopped.stop();
+        drop(stopped);
+
+        let report = profile_report();
+        let aggregate = report.span("test.aggregate").unwrap();
+        assert_eq!(aggregate.calls, 2);
+        assert_eq!(aggregate.total_ms, 40.0);
+        assert_eq!(aggregate.mean_ms, 20.0);
+        assert_eq!(aggregate.min_ms, 10.0);
+        assert_eq!(aggregate.max_ms, 30.0);
+        assert_eq!(report.span("test.dropped").unwrap().calls, 1);
+        // Stopping then dropping records the span once.
+        assert_eq!(report.span("test.stopped").unwrap().calls, 1);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert!(json["spans"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|span| span["name"] == "test.aggregate" && span["calls"] == 2));
+        assert!(report.render_text().contains("test.aggregate"));
+    }
+}
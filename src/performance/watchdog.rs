@@ -0,0 +1,271 @@
+//! Resource watchdog
+//!
+//! Samples system memory and CPU in the background while a command runs. It
+//! warns when memory use passes the [`AlertThresholds`] or the CPU stays
+//! pegged for several samples, both usual signs that the model is too large
+//! for the machine. Each kind of pressure is reported once until it clears.
+//! The sampling task is aborted when the [`Watchdog`] is dropped.
+
+use super::SystemMetrics;
+use crate::core::agents::maintenance::AlertThresholds;
+use crate::utils::config::WatchdogConfig;
+use std::time::Duration;
+use sysinfo::{CpuExt, System, SystemExt};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone)]
+pub struct WatchdogSettings {
+    pub interval: Duration,
+    pub thresholds: AlertThresholds,
+    /// Consecutive samples above the CPU threshold before warning.
+    pub cpu_sustained_samples: u32,
+}
+
+impl From<&WatchdogConfig> for WatchdogSettings {
+    fn from(config: &WatchdogConfig) -> Self {
+        Self {
+            interval: Duration::from_secs(config.interval_secs.max(1)),
+            thresholds: AlertThresholds {
+                memory_usage_percent: config.memory_usage_percent,
+                cpu_usage_percent: config.cpu_usage_percent,
+                ..AlertThresholds::default()
+            },
+            cpu_sustained_samples: config.cpu_sustained_samples.max(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pressure {
+    Memory { used_percent: f64, threshold: f64 },
+    Cpu { usage_percent: f64, threshold: f64 },
+}
+
+impl Pressure {
+    pub fn warning(&self) -> String {
+        match self {
+            Pressure::Memory {
+                used_percent,
+                threshold,
+            } => format!(
+                "Memory use is at {:.0}% (alert threshold {:.0}%). A smaller model would leave more headroom; see `kandil local-model list --compatible`.",
+                used_percent, threshold
+            ),
+            Pressure::Cpu {
+                usage_percent,
+                threshold,
+            } => format!(
+                "CPU has stayed above {:.0}% (now {:.0}%). A smaller model would respond faster on this machine; see `kandil local-model list --compatible`.",
+                threshold, usage_percent
+            ),
+        }
+    }
+
+    /// Short label for status bars, e.g. `MEM 91%`.
+    pub fn indicator(&self) -> String {
+        match self {
+            Pressure::Memory { used_percent, .. } => format!("MEM {:.0}%", used_percent),
+            Pressure::Cpu { usage_percent, .. } => format!("CPU {:.0}%", usage_percent),
+        }
+    }
+}
+
+/// Turns samples into pressure, remembering what was already reported.
+#[derive(Debug)]
+pub struct PressureTracker {
+    settings: WatchdogSettings,
+    cpu_streak: u32,
+    active: Vec<Pressure>,
+}
+
+impl PressureTracker {
+    pub fn new(settings: WatchdogSettings) -> Self {
+        Self {
+            settings,
+            cpu_streak: 0,
+            active: Vec::new(),
+        }
+    }
+
+    /// Records a sample and returns the pressure that began with it.
+    pub fn observe(&mut self, metrics: &SystemMetrics) -> Vec<Pressure> {
+        let thresholds = &self.settings.thresholds;
+        let mut current = Vec::new();
+
+        if metrics.total_memory > 0 {
+            let used_percent = metrics.used_memory as f64 * 100.0 / metrics.total_memory as f64;
+            if used_percent >= thresholds.memory_usage_percent {
+                current.push(Pressure::Memory {
+                    used_percent,
+                    threshold: thresholds.memory_usage_percent,
+                });
+            }
+        }
+
+        let usage_percent = metrics.cpu_usage as f64;
+        if usage_percent >= thresholds.cpu_usage_percent {
+            self.cpu_streak += 1;
+        } else {
+            self.cpu_streak = 0;
+        }
+        if self.cpu_streak >= self.settings.cpu_sustained_samples {
+            current.push(Pressure::Cpu {
+                usage_percent,
+                threshold: thresholds.cpu_usage_percent,
+            });
+        }
+
+        let started = current
+            .iter()
+            .filter(|pressure| {
+                !self.active.iter().any(|active| {
+                    std::mem::discriminant(active) == std::mem::discriminant(*pressure)
+                })
+            })
+            .copied()
+            .collect();
+        self.active = current;
+        started
+    }
+
+    /// Pressure seen in the latest sample.
+    pub fn active(&self) -> &[Pressure] {
+        &self.active
+    }
+}
+
+/// Background sampler; dropping it stops the sampling task.
+pub struct Watchdog {
+    state: watch::Receiver<Vec<Pressure>>,
+    task: JoinHandle<()>,
+}
+
+impl Watchdog {
+    /// Starts sampling on the current runtime. New pressure is printed to
+    /// stderr when `print_warnings` is set; full-screen UIs should read
+    /// [`pressure`](Self::pressure) instead.
+    pub fn spawn(settings: WatchdogSettings, print_warnings: bool) -> Self {
+        let (sender, state) = watch::channel(Vec::new());
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(settings.interval);
+            let mut tracker = PressureTracker::new(settings);
+            let mut system = System::new();
+            loop {
+                interval.tick().await;
+                system.refresh_cpu();
+                system.refresh_memory();
+                let metrics = SystemMetrics {
+                    cpu_usage: system.global_cpu_info().cpu_usage(),
+                    total_memory: system.total_memory(),
+                    used_memory: system.used_memory(),
+                    available_memory: system.available_memory(),
+                };
+                for pressure in tracker.observe(&metrics) {
+                    log::warn!("{}", pressure.warning());
+                    if print_warnings {
+                        eprintln!("⚠️  {}", pressure.warning());
+                    }
+                }
+                if sender.send(tracker.active().to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { state, task }
+    }
+
+    /// Starts a watchdog when `[watchdog]` is enabled in the config.
+    pub fn from_config(config: &WatchdogConfig, print_warnings: bool) -> Option<Self> {
+        config
+            .enabled
+            .then(|| Self::spawn(WatchdogSettings::from(config), print_warnings))
+    }
+
+    /// Pressure seen in the latest sample.
+    pub fn pressure(&self) -> Vec<Pressure> {
+        self.state.borrow().clone()
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(cpu_usage: f32, used_memory: u64) -> SystemMetrics {
+        SystemMetrics {
+            cpu_usage,
+            total_memory: 100,
+            used_memory,
+            available_memory: 100 - used_memory,
+        }
+    }
+
+    fn tracker() -> PressureTracker {
+        PressureTracker::new(WatchdogSettings::from(&WatchdogConfig {
+            enabled: true,
+            cpu_sustained_samples: 2,
+            ..WatchdogConfig::default()
+        }))
+    }
+
+    #[test]
+    fn memory_pressure_is_reported_once_until_it_clears() {
+        let mut tracker = tracker();
+        assert!(tracker.observe(&sample(10.0, 50)).is_empty());
+
+        let raised = tracker.observe(&sample(10.0, 90));
+        assert_eq!(
+            raised,
+            [Pressure::Memory {
+                used_percent: 90.0,
+                threshold: 85.0
+            }]
+        );
+        assert!(tracker.observe(&sample(10.0, 95)).is_empty());
+        assert_eq!(tracker.active()[0].indicator(), "MEM 95%");
+
+        assert!(tracker.observe(&sample(10.0, 40)).is_empty());
+        assert!(tracker.active().is_empty());
+        assert_eq!(tracker.observe(&sample(10.0, 90)).len(), 1);
+    }
+
+    #[test]
+    fn cpu_pressure_needs_sustained_samples() {
+        let mut tracker = tracker();
+        assert!(tracker.observe(&sample(99.0, 10)).is_empty());
+        assert!(tracker.observe(&sample(20.0, 10)).is_empty());
+        assert!(tracker.observe(&sample(99.0, 10)).is_empty());
+
+        let raised = tracker.observe(&sample(97.0, 10));
+        assert!(matches!(raised[..], [Pressure::Cpu { .. }]));
+        assert!(raised[0].warning().contains("smaller model"));
+    }
+
+    #[tokio::test]
+    async fn dropping_the_watchdog_stops_sampling() {
+        let watchdog = Watchdog::spawn(
+            WatchdogSettings {
+                interval: Duration::from_millis(10),
+                thresholds: AlertThresholds::default(),
+                cpu_sustained_samples: 1,
+            },
+            false,
+        );
+        let mut state = watchdog.state.clone();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        drop(watchdog);
+        // The channel closes once the aborted task has dropped its sender.
+        let closed = tokio::time::timeout(Duration::from_secs(1), async {
+            while state.changed().await.is_ok() {}
+        })
+        .await;
+        assert!(closed.is_ok());
+    }
+}
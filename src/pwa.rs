@@ -1,15 +1,300 @@
-use anyhow::Result;
+//! Installable companion app
+//!
+//! `write_assets` exports the app shell, a web app manifest, PNG icons and a
+//! service worker. The manifest is checked against the browsers' installability
+//! requirements before the export is reported as done.
+
+mod serve;
+
+pub use serve::serve;
+
+use anyhow::{bail, Context, Result};
 use std::fs;
 use std::path::Path;
 
 pub const INDEX_HTML: &str = include_str!("../pwa/index.html");
-pub const MANIFEST: &str = include_str!("../pwa/manifest.webmanifest");
 pub const SERVICE_WORKER: &str = include_str!("../pwa/service_worker.js");
 
-pub fn write_assets(dir: &Path) -> Result<()> {
-    fs::create_dir_all(dir)?;
-    fs::write(dir.join("index.html"), INDEX_HTML)?;
-    fs::write(dir.join("manifest.webmanifest"), MANIFEST)?;
-    fs::write(dir.join("sw.js"), SERVICE_WORKER)?;
+/// Icon sizes written to `icons/`; 192 and 512 are the ones browsers require.
+pub const ICON_SIZES: &[u32] = &[72, 96, 128, 144, 152, 192, 384, 512];
+
+const BACKGROUND_COLOR: &str = "#050505";
+
+/// What the exported app is called and how it looks.
+#[derive(Debug, Clone)]
+pub struct PwaOptions {
+    pub app_name: String,
+    /// `#rgb` or `#rrggbb`.
+    pub theme_color: String,
+}
+
+impl Default for PwaOptions {
+    fn default() -> Self {
+        Self {
+            app_name: "Kandil Companion".to_string(),
+            theme_color: "#06b6d4".to_string(),
+        }
+    }
+}
+
+pub fn write_assets(dir: &Path, options: &PwaOptions) -> Result<()> {
+    if options.app_name.trim().is_empty() {
+        bail!("The app name must not be empty");
+    }
+    let theme = parse_hex_color(&options.theme_color)?;
+    let background = parse_hex_color(BACKGROUND_COLOR)?;
+
+    let icons_dir = dir.join("icons");
+    fs::create_dir_all(&icons_dir)
+        .with_context(|| format!("Failed to create {}", icons_dir.display()))?;
+    for &size in ICON_SIZES {
+        render_icon(size, theme, background)
+            .save(icons_dir.join(format!("icon-{}.png", size)))
+            .with_context(|| format!("Failed to write the {}px icon", size))?;
+    }
+
+    let manifest = manifest(options);
+    validate_manifest(&manifest, dir)?;
+
+    let index = INDEX_HTML
+        .replace("__APP_NAME__", &escape_html(options.app_name.trim()))
+        .replace("__THEME_COLOR__", &options.theme_color);
+    fs::write(dir.join("index.html"), index)?;
+    fs::write(
+        dir.join("manifest.webmanifest"),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    fs::write(dir.join("sw.js"), service_worker())?;
+    Ok(())
+}
+
+/// The web app manifest for `options`.
+pub fn manifest(options: &PwaOptions) -> serde_json::Value {
+    let name = options.app_name.trim();
+    let short_name: String = name.chars().take(12).collect();
+    let icon = |size: u32, purpose: &str| {
+        serde_json::json!({
+            "src": format!("/icons/icon-{}.png", size),
+            "sizes": format!("{0}x{0}", size),
+            "type": "image/png",
+            "purpose": purpose,
+        })
+    };
+    // The artwork is full-bleed, so the largest icon doubles as the maskable one.
+    let mut icons: Vec<_> = ICON_SIZES.iter().map(|&size| icon(size, "any")).collect();
+    icons.push(icon(512, "maskable"));
+    serde_json::json!({
+        "name": name,
+        "short_name": short_name.trim_end(),
+        "description": "Kandil Code companion for chatting with your models on the go",
+        "id": "/",
+        "start_url": "/",
+        "scope": "/",
+        "display": "standalone",
+        "background_color": BACKGROUND_COLOR,
+        "theme_color": options.theme_color,
+        "icons": icons,
+    })
+}
+
+/// Checks the installability criteria browsers apply to a manifest: a name,
+/// a start URL, a standalone-like display mode, valid colours, and PNG icons
+/// of at least 192px and 512px that exist under `dir`.
+pub fn validate_manifest(manifest: &serde_json::Value, dir: &Path) -> Result<()> {
+    let text = |key: &str| manifest.get(key).and_then(|value| value.as_str());
+
+    let mut problems = Vec::new();
+    if text("name").or(text("short_name")).unwrap_or("").is_empty() {
+        problems.push("`name` or `short_name` is required".to_string());
+    }
+    if text("start_url").unwrap_or("").is_empty() {
+        problems.push("`start_url` is required".to_string());
+    }
+    match text("display") {
+        Some("standalone" | "fullscreen" | "minimal-ui") => {}
+        other => problems.push(format!(
+            "`display` must be standalone, fullscreen or minimal-ui, not {:?}",
+            other.unwrap_or("missing")
+        )),
+    }
+    for key in ["theme_color", "background_color"] {
+        if let Err(err) = parse_hex_color(text(key).unwrap_or("")) {
+            problems.push(format!("`{}`: {}", key, err));
+        }
+    }
+
+    let mut largest = 0;
+    let mut has_192 = false;
+    for icon in manifest
+        .get("icons")
+        .and_then(|icons| icons.as_array())
+        .into_iter()
+        .flatten()
+    {
+        let src = icon.get("src").and_then(|src| src.as_str()).unwrap_or("");
+        if icon.get("type").and_then(|kind| kind.as_str()) != Some("image/png") {
+            continue;
+        }
+        if !dir.join(src.trim_start_matches('/')).is_file() {
+            problems.push(format!("icon {} was not exported", src));
+            continue;
+        }
+        let sizes = icon
+            .get("sizes")
+            .and_then(|sizes| sizes.as_str())
+            .unwrap_or("");
+        for size in sizes.split_whitespace() {
+            if let Some((width, height)) = size.split_once('x') {
+                if let (Ok(width), Ok(height)) = (width.parse::<u32>(), height.parse::<u32>()) {
+                    if width == height {
+                        has_192 |= width == 192;
+                        largest = largest.max(width);
+                    }
+                }
+            }
+        }
+    }
+    if !has_192 {
+        problems.push("a 192x192 PNG icon is required".to_string());
+    }
+    if largest < 512 {
+        problems.push("a PNG icon of at least 512x512 is required".to_string());
+    }
+
+    if !problems.is_empty() {
+        bail!("Invalid web app manifest:\n  - {}", problems.join("\n  - "));
+    }
     Ok(())
 }
+
+/// The service worker with the app shell list and cache version filled in.
+fn service_worker() -> String {
+    let mut shell = vec![
+        "/".to_string(),
+        "/index.html".to_string(),
+        "/manifest.webmanifest".to_string(),
+    ];
+    shell.extend(
+        ICON_SIZES
+            .iter()
+            .map(|size| format!("/icons/icon-{}.png", size)),
+    );
+    SERVICE_WORKER
+        .replace("__CACHE_VERSION__", env!("CARGO_PKG_VERSION"))
+        .replace(
+            "__APP_SHELL__",
+            &serde_json::to_string(&shell).unwrap_or_else(|_| "[]".to_string()),
+        )
+}
+
+/// A square icon in the theme colour with a disc in the background colour,
+/// kept inside the maskable safe zone.
+fn render_icon(size: u32, theme: [u8; 3], background: [u8; 3]) -> image::RgbaImage {
+    let center = size as f32 / 2.0;
+    let outer = size as f32 * 0.3;
+    let inner = size as f32 * 0.14;
+    image::RgbaImage::from_fn(size, size, |x, y| {
+        let dx = x as f32 + 0.5 - center;
+        let dy = y as f32 + 0.5 - center;
+        let distance = (dx * dx + dy * dy).sqrt();
+        let [r, g, b] = if distance <= inner || distance > outer {
+            theme
+        } else {
+            background
+        };
+        image::Rgba([r, g, b, 255])
+    })
+}
+
+/// Parses `#rgb` or `#rrggbb`.
+pub fn parse_hex_color(color: &str) -> Result<[u8; 3]> {
+    let hex = color
+        .strip_prefix('#')
+        .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()));
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).unwrap_or(0);
+    match hex {
+        Some(hex) if hex.len() == 6 => Ok([
+            channel(&hex[0..2]),
+            channel(&hex[2..4]),
+            channel(&hex[4..6]),
+        ]),
+        Some(hex) if hex.len() == 3 => {
+            let double = |i: usize| channel(&hex[i..i + 1]) * 17;
+            Ok([double(0), double(1), double(2)])
+        }
+        _ => bail!("'{}' is not a hex colour like #06b6d4", color),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("kandil_pwa_export_{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn export_writes_installable_manifest_icons_and_worker() -> Result<()> {
+        let dir = temp_dir();
+        let options = PwaOptions {
+            app_name: "Team <Kandil>".to_string(),
+            theme_color: "#ff8800".to_string(),
+        };
+        write_assets(&dir, &options)?;
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("manifest.webmanifest"))?)?;
+        assert_eq!(manifest["name"], "Team <Kandil>");
+        assert_eq!(manifest["theme_color"], "#ff8800");
+        assert_eq!(manifest["display"], "standalone");
+        validate_manifest(&manifest, &dir)?;
+
+        assert_eq!(
+            image::image_dimensions(dir.join("icons/icon-512.png"))?,
+            (512, 512)
+        );
+
+        let index = fs::read_to_string(dir.join("index.html"))?;
+        assert!(index.contains("<title>Team &lt;Kandil&gt;</title>"));
+        assert!(!index.contains("__"));
+        let worker = fs::read_to_string(dir.join("sw.js"))?;
+        assert!(worker.contains("\"/icons/icon-192.png\""));
+        assert!(!worker.contains("__APP_SHELL__"));
+
+        let _ = fs::remove_dir_all(dir);
+        Ok(())
+    }
+
+    #[test]
+    fn manifests_missing_requirements_are_rejected() {
+        let dir = temp_dir();
+        let mut manifest = manifest(&PwaOptions::default());
+        manifest["display"] = "browser".into();
+        manifest["theme_color"] = "teal".into();
+        let err = validate_manifest(&manifest, &dir).unwrap_err().to_string();
+        assert!(err.contains("`display`"));
+        assert!(err.contains("`theme_color`"));
+        // Nothing was exported, so the icons don't count.
+        assert!(err.contains("512x512"));
+    }
+
+    #[test]
+    fn bad_options_fail_before_writing() {
+        let dir = temp_dir();
+        let options = PwaOptions {
+            theme_color: "06b6d4".to_string(),
+            ..PwaOptions::default()
+        };
+        assert!(write_assets(&dir, &options).is_err());
+        assert!(!dir.exists());
+        assert_eq!(parse_hex_color("#0fa").unwrap(), [0x00, 0xff, 0xaa]);
+    }
+}
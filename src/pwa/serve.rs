@@ -0,0 +1,319 @@
+//! Static server for the exported PWA
+//!
+//! Serves the export directory with proper MIME types. The service worker,
+//! manifest and app shell are sent with `no-cache` so installed apps pick up
+//! new versions, while other assets may be cached for a day. Paths without an
+//! extension fall back to `index.html` for client-side routing, and `/api/*`
+//! is proxied to the web companion server.
+
+use anyhow::{Context, Result};
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    routing::any,
+    Router,
+};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Largest request body forwarded to the companion server.
+const MAX_PROXY_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+/// Headers that describe a single connection and must not be forwarded.
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+    "content-length",
+];
+
+struct PwaServer {
+    dir: PathBuf,
+    api_base: String,
+    client: Arc<reqwest::Client>,
+}
+
+/// Routes for the exported assets in `dir`, proxying `/api/*` to `api_base`.
+pub fn router(dir: PathBuf, api_base: &str) -> Router {
+    let state = Arc::new(PwaServer {
+        dir,
+        api_base: api_base.trim_end_matches('/').to_string(),
+        client: crate::core::adapters::ai::http::shared_client(),
+    });
+    Router::new()
+        .route("/api", any(proxy))
+        .route("/api/*path", any(proxy))
+        .fallback(static_file)
+        .with_state(state)
+}
+
+/// Exports the assets into `dir` unless they're already there, then serves
+/// them on `address` until Ctrl+C.
+pub async fn serve(
+    dir: PathBuf,
+    address: &str,
+    api_base: &str,
+    options: &super::PwaOptions,
+) -> Result<()> {
+    if !dir.join("index.html").is_file() {
+        super::write_assets(&dir, options)?;
+        println!("📦 PWA assets exported to {}", dir.display());
+    }
+
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .with_context(|| format!("Failed to bind {}", address))?;
+    println!("Kandil PWA serving {} on http://{}", dir.display(), address);
+    println!("Proxying /api/* to {}", api_base);
+
+    let shutdown = crate::shutdown::ShutdownHandler::new();
+    shutdown
+        .setup_signal_handlers()
+        .await
+        .map_err(|err| anyhow::anyhow!("Cannot install Ctrl+C handler: {}", err))?;
+
+    axum::serve(listener, router(dir, api_base))
+        .with_graceful_shutdown(async move { shutdown.wait_for_shutdown().await })
+        .await?;
+    Ok(())
+}
+
+async fn static_file(State(state): State<Arc<PwaServer>>, uri: Uri) -> Response {
+    let Some(relative) = sanitize(uri.path()) else {
+        return (StatusCode::BAD_REQUEST, "Invalid path").into_response();
+    };
+
+    let mut path = state.dir.join(&relative);
+    if path.is_dir() {
+        path = path.join("index.html");
+    }
+    if !path.is_file() {
+        // Client-side routes have no extension; a missing asset stays a 404.
+        if relative.extension().is_some() {
+            return (StatusCode::NOT_FOUND, "Not found").into_response();
+        }
+        path = state.dir.join("index.html");
+    }
+
+    match tokio::fs::read(&path).await {
+        Ok(bytes) => {
+            let mut response = (
+                [
+                    (header::CONTENT_TYPE, content_type(&path)),
+                    (header::CACHE_CONTROL, cache_control(&path)),
+                ],
+                bytes,
+            )
+                .into_response();
+            if path.file_name().and_then(|name| name.to_str()) == Some("sw.js") {
+                // Lets the worker control the whole origin.
+                response.headers_mut().insert(
+                    "service-worker-allowed",
+                    header::HeaderValue::from_static("/"),
+                );
+            }
+            response
+        }
+        Err(_) => (StatusCode::NOT_FOUND, "Not found").into_response(),
+    }
+}
+
+async fn proxy(State(state): State<Arc<PwaServer>>, request: Request) -> Response {
+    let (parts, body) = request.into_parts();
+    let body = match to_bytes(body, MAX_PROXY_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(_) => return (StatusCode::PAYLOAD_TOO_LARGE, "Request body too large").into_response(),
+    };
+    let path = parts
+        .uri
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+    let url = format!("{}{}", state.api_base, path);
+
+    let Ok(method) = reqwest::Method::from_bytes(parts.method.as_str().as_bytes()) else {
+        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+    };
+    let mut upstream = state.client.request(method, &url).body(body.to_vec());
+    for (name, value) in parts.headers.iter() {
+        if !HOP_BY_HOP.contains(&name.as_str()) {
+            upstream = upstream.header(name.as_str(), value.as_bytes());
+        }
+    }
+
+    let upstream = match upstream.send().await {
+        Ok(response) => response,
+        Err(err) => {
+            return (
+                StatusCode::BAD_GATEWAY,
+                format!(
+                    "Companion server unavailable at {}: {}",
+                    state.api_base, err
+                ),
+            )
+                .into_response()
+        }
+    };
+
+    let mut response = Response::builder().status(upstream.status().as_u16());
+    for (name, value) in upstream.headers() {
+        if !HOP_BY_HOP.contains(&name.as_str()) {
+            response = response.header(name.as_str(), value.as_bytes());
+        }
+    }
+    match upstream.bytes().await {
+        Ok(bytes) => response
+            .body(Body::from(bytes))
+            .unwrap_or_else(|_| StatusCode::BAD_GATEWAY.into_response()),
+        Err(err) => (StatusCode::BAD_GATEWAY, err.to_string()).into_response(),
+    }
+}
+
+/// Turns a URL path into a path relative to the export directory, refusing
+/// anything that could escape it.
+fn sanitize(uri_path: &str) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for segment in uri_path.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => return None,
+            _ if segment.contains('\\') || segment.contains(':') => return None,
+            _ => relative.push(segment),
+        }
+    }
+    Some(relative)
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()).unwrap_or("") {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "webp" => "image/webp",
+        "woff2" => "font/woff2",
+        "wasm" => "application/wasm",
+        "txt" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// The worker, manifest and HTML are always revalidated so updates roll out;
+/// everything else may be cached by the browser for a day.
+fn cache_control(path: &Path) -> &'static str {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    if name == "sw.js" || matches!(ext, "html" | "htm" | "webmanifest") {
+        "no-cache"
+    } else {
+        "public, max-age=86400"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::ServiceExt;
+
+    fn exported() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kandil_pwa_{}", uuid::Uuid::new_v4()));
+        super::super::write_assets(&dir, &super::super::PwaOptions::default()).unwrap();
+        dir
+    }
+
+    async fn get(app: Router, path: &str) -> Response {
+        app.oneshot(axum::http::Request::get(path).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_assets_with_mime_types_and_cache_policy() {
+        let dir = exported();
+        let app = router(dir.clone(), "http://127.0.0.1:9");
+
+        let index = get(app.clone(), "/").await;
+        assert_eq!(index.status(), StatusCode::OK);
+        assert_eq!(
+            index.headers()[header::CONTENT_TYPE],
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(index.headers()[header::CACHE_CONTROL], "no-cache");
+
+        let manifest = get(app.clone(), "/manifest.webmanifest").await;
+        assert_eq!(
+            manifest.headers()[header::CONTENT_TYPE],
+            "application/manifest+json"
+        );
+
+        let worker = get(app, "/sw.js").await;
+        assert_eq!(worker.headers()[header::CACHE_CONTROL], "no-cache");
+        assert_eq!(worker.headers()["service-worker-allowed"], "/");
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn client_routes_fall_back_to_index_but_missing_assets_do_not() {
+        let dir = exported();
+        let app = router(dir.clone(), "http://127.0.0.1:9");
+
+        let route = get(app.clone(), "/chat/history").await;
+        assert_eq!(route.status(), StatusCode::OK);
+        let body = to_bytes(route.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, std::fs::read(dir.join("index.html")).unwrap());
+
+        assert_eq!(
+            get(app.clone(), "/missing.js").await.status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            get(app, "/../secret").await.status(),
+            StatusCode::BAD_REQUEST
+        );
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn api_requests_are_proxied_to_the_companion() {
+        let upstream = Router::new().route(
+            "/api/echo",
+            axum::routing::post(|body: String| async move { format!("echo: {}", body) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, upstream).await });
+
+        let dir = exported();
+        let response = router(dir.clone(), &format!("http://{}", address))
+            .oneshot(
+                axum::http::Request::post("/api/echo")
+                    .body(Body::from("hello"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, "echo: hello".as_bytes());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
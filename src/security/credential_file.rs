@@ -0,0 +1,222 @@
+//! Encrypted credential file
+//!
+//! Fallback secret storage for machines without a usable OS keyring, such as
+//! headless Linux servers and CI runners. Each secret is sealed with
+//! AES-256-GCM under a key derived (PBKDF2-HMAC-SHA256) from the master
+//! passphrase. Provider names are stored in the clear so presence checks
+//! don't need the passphrase; secrets never are.
+
+use crate::utils::json_store::JsonStore;
+use anyhow::{Context, Result};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::num::NonZeroU32;
+
+const FORMAT_VERSION: u32 = 1;
+#[cfg(not(test))]
+const PBKDF2_ITERATIONS: u32 = 600_000;
+// Keep unit tests fast; the format doesn't depend on the iteration count.
+#[cfg(test)]
+const PBKDF2_ITERATIONS: u32 = 1_000;
+const SALT_LEN: usize = 16;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CredentialFileData {
+    version: u32,
+    /// Hex-encoded PBKDF2 salt, generated when the first secret is stored.
+    salt: String,
+    entries: BTreeMap<String, SealedSecret>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SealedSecret {
+    nonce: String,
+    ciphertext: String,
+}
+
+pub struct EncryptedCredentialFile {
+    store: JsonStore,
+}
+
+impl EncryptedCredentialFile {
+    pub fn new(store: JsonStore) -> Self {
+        Self { store }
+    }
+
+    /// `credentials.enc.json` under the kandil data directory.
+    pub fn open_default() -> Self {
+        Self::new(JsonStore::in_data_dir("credentials.enc.json"))
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        self.store.path()
+    }
+
+    /// Whether a secret is stored for `provider`. Does not need the passphrase.
+    pub fn contains(&self, provider: &str) -> Result<bool> {
+        let data: CredentialFileData = self.store.load()?;
+        Ok(data.entries.contains_key(provider))
+    }
+
+    pub fn get(&self, provider: &str, passphrase: &SecretString) -> Result<Option<String>> {
+        let data: CredentialFileData = self.store.load()?;
+        let Some(sealed) = data.entries.get(provider) else {
+            return Ok(None);
+        };
+        let key = derive_key(passphrase, &decode_hex(&data.salt)?)?;
+        let nonce: [u8; NONCE_LEN] = decode_hex(&sealed.nonce)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Corrupt nonce for {} in credential file", provider))?;
+        let mut buffer = decode_hex(&sealed.ciphertext)?;
+        let plaintext = key
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(provider.as_bytes()),
+                &mut buffer,
+            )
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "Could not decrypt the {} credential. Check KANDIL_MASTER_KEY",
+                    provider
+                )
+            })?;
+        Ok(Some(String::from_utf8(plaintext.to_vec())?))
+    }
+
+    pub fn set(&self, provider: &str, secret: &str, passphrase: &SecretString) -> Result<()> {
+        let rng = SystemRandom::new();
+        self.store.update(|data: &mut CredentialFileData| {
+            if data.salt.is_empty() {
+                let mut salt = [0u8; SALT_LEN];
+                rng.fill(&mut salt)
+                    .map_err(|_| anyhow::anyhow!("System random generator unavailable"))?;
+                data.salt = encode_hex(&salt);
+                data.version = FORMAT_VERSION;
+            }
+            let key = derive_key(passphrase, &decode_hex(&data.salt)?)?;
+
+            // Refuse to mix passphrases in one file: an existing entry must
+            // still open with the passphrase being used now.
+            if let Some((name, existing)) = data.entries.iter().next() {
+                let mut probe = decode_hex(&existing.ciphertext)?;
+                let nonce: [u8; NONCE_LEN] = decode_hex(&existing.nonce)?
+                    .try_into()
+                    .map_err(|_| anyhow::anyhow!("Corrupt nonce in credential file"))?;
+                key.open_in_place(
+                    Nonce::assume_unique_for_key(nonce),
+                    Aad::from(name.as_bytes()),
+                    &mut probe,
+                )
+                .map_err(|_| {
+                    anyhow::anyhow!(
+                        "KANDIL_MASTER_KEY does not match the one used for existing credentials"
+                    )
+                })?;
+            }
+
+            let mut nonce = [0u8; NONCE_LEN];
+            rng.fill(&mut nonce)
+                .map_err(|_| anyhow::anyhow!("System random generator unavailable"))?;
+            let mut buffer = secret.as_bytes().to_vec();
+            key.seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(provider.as_bytes()),
+                &mut buffer,
+            )
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt credential"))?;
+
+            data.entries.insert(
+                provider.to_string(),
+                SealedSecret {
+                    nonce: encode_hex(&nonce),
+                    ciphertext: encode_hex(&buffer),
+                },
+            );
+            Ok(())
+        })?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ =
+                std::fs::set_permissions(self.store.path(), std::fs::Permissions::from_mode(0o600));
+        }
+        Ok(())
+    }
+
+    /// Drops the secret for `provider`, returning whether one was stored.
+    pub fn remove(&self, provider: &str) -> Result<bool> {
+        self.store
+            .update(|data: &mut CredentialFileData| Ok(data.entries.remove(provider).is_some()))
+    }
+}
+
+fn derive_key(passphrase: &SecretString, salt: &[u8]) -> Result<LessSafeKey> {
+    let mut key_bytes = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
+        salt,
+        passphrase.expose_secret().as_bytes(),
+        &mut key_bytes,
+    );
+    let key = UnboundKey::new(&AES_256_GCM, &key_bytes)
+        .map_err(|_| anyhow::anyhow!("Failed to derive credential key"))?;
+    key_bytes.fill(0);
+    Ok(LessSafeKey::new(key))
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(text: &str) -> Result<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        anyhow::bail!("Corrupt credential file: odd-length hex");
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&text[i..i + 2], 16).context("Corrupt credential file: invalid hex")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_round_trip_encrypted() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("kandil_creds_{}", uuid::Uuid::new_v4()));
+        let path = dir.join("credentials.enc.json");
+        let file = EncryptedCredentialFile::new(JsonStore::new(&path));
+        let passphrase = SecretString::new("correct horse".to_string());
+
+        assert!(!file.contains("openai")?);
+        file.set("openai", "sk-test-123", &passphrase)?;
+        assert!(file.contains("openai")?);
+        assert_eq!(
+            file.get("openai", &passphrase)?.as_deref(),
+            Some("sk-test-123")
+        );
+        assert_eq!(file.get("claude", &passphrase)?, None);
+
+        let on_disk = std::fs::read_to_string(&path)?;
+        assert!(!on_disk.contains("sk-test-123"));
+
+        let wrong = SecretString::new("battery staple".to_string());
+        assert!(file.get("openai", &wrong).is_err());
+        assert!(file.set("claude", "sk-other", &wrong).is_err());
+
+        assert!(file.remove("openai")?);
+        assert!(!file.contains("openai")?);
+
+        let _ = std::fs::remove_dir_all(dir);
+        Ok(())
+    }
+}
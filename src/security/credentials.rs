@@ -1,41 +1,85 @@
+use crate::security::credential_file::EncryptedCredentialFile;
 use crate::utils::config::SecureKey;
 use anyhow::{Context, Result};
 use keyring::Entry;
 use secrecy::{ExposeSecret, SecretString};
+use std::io::IsTerminal;
+use std::sync::OnceLock;
 
-/// Represents the presence of a credential in the OS keyring.
+/// Where provider secrets are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialBackend {
+    /// The OS keyring (Keychain, Credential Manager, Secret Service).
+    Keyring,
+    /// The encrypted file used when no keyring is available.
+    EncryptedFile,
+}
+
+/// Represents the presence of a credential and the backend holding it.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CredentialState {
-    Present,
+    Present(CredentialBackend),
     Missing,
 }
 
+/// Passphrase entered at the prompt, reused for the rest of the process.
+static PROMPTED_MASTER_KEY: OnceLock<SecretString> = OnceLock::new();
+
 /// Centralized credential manager.
 ///
 /// Wraps the lower-level `SecureKey` helper and provides richer diagnostics
 /// plus convenience helpers for storing and retrieving provider secrets.
+/// Secrets go to the OS keyring when one is usable and otherwise to an
+/// encrypted file protected by `KANDIL_MASTER_KEY`; never to plaintext.
 pub struct CredentialManager;
 
 impl CredentialManager {
-    /// Returns `CredentialState::Present` if the provider has a key in the keyring.
+    /// The backend new secrets are written to on this machine.
+    ///
+    /// `KANDIL_CREDENTIAL_BACKEND=file` forces the encrypted file, e.g. in CI
+    /// where a keyring daemon exists but is locked.
+    pub fn backend() -> CredentialBackend {
+        if std::env::var("KANDIL_CREDENTIAL_BACKEND").is_ok_and(|value| value == "file") {
+            return CredentialBackend::EncryptedFile;
+        }
+        let usable = Entry::new("kandil", "__kandil_probe__").is_ok_and(|entry| {
+            matches!(entry.get_password(), Ok(_) | Err(keyring::Error::NoEntry))
+        });
+        if usable {
+            CredentialBackend::Keyring
+        } else {
+            CredentialBackend::EncryptedFile
+        }
+    }
+
+    /// Reports whether the provider has a key, and where it is stored.
     pub fn ensure(provider: &str) -> Result<CredentialState> {
-        let entry = Entry::new("kandil", provider)?;
-        match entry.get_password() {
-            Ok(value) => {
-                if value.is_empty() {
-                    anyhow::bail!("Key for provider {provider} exists but is empty");
+        if Self::backend() == CredentialBackend::Keyring {
+            let entry = Entry::new("kandil", provider)?;
+            match entry.get_password() {
+                Ok(value) => {
+                    if value.is_empty() {
+                        anyhow::bail!("Key for provider {provider} exists but is empty");
+                    }
+                    return Ok(CredentialState::Present(CredentialBackend::Keyring));
                 }
-                Ok(CredentialState::Present)
+                Err(keyring::Error::NoEntry) => {}
+                Err(err) => return Err(err.into()),
             }
-            Err(keyring::Error::NoEntry) => Ok(CredentialState::Missing),
-            Err(err) => Err(err.into()),
+        }
+
+        // Keys saved while the keyring was unavailable stay in the file.
+        if EncryptedCredentialFile::open_default().contains(provider)? {
+            Ok(CredentialState::Present(CredentialBackend::EncryptedFile))
+        } else {
+            Ok(CredentialState::Missing)
         }
     }
 
-    /// Retrieves a provider secret from the OS keyring.
+    /// Retrieves a provider secret from whichever backend holds it.
     pub fn get(provider: &str) -> Result<SecretString> {
         let key = SecureKey::load(provider)
-            .with_context(|| format!("Missing API key for provider '{provider}' in keyring"))?;
+            .with_context(|| format!("Missing API key for provider '{provider}'"))?;
         Ok(SecretString::new(key.expose().to_string()))
     }
 
@@ -43,6 +87,144 @@ impl CredentialManager {
     pub fn set(provider: &str, secret: &SecretString) -> Result<()> {
         SecureKey::save(provider, secret.expose_secret())
     }
+
+    /// Reads a raw secret, trying the keyring first and the encrypted file second.
+    pub(crate) fn load_secret(provider: &str) -> Result<Option<String>> {
+        if Self::backend() == CredentialBackend::Keyring {
+            match Entry::new("kandil", provider)?.get_password() {
+                Ok(value) => return Ok(Some(value)),
+                Err(keyring::Error::NoEntry) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let file = EncryptedCredentialFile::open_default();
+        if !file.contains(provider)? {
+            return Ok(None);
+        }
+        file.get(provider, &master_key()?)
+    }
+
+    /// Writes a raw secret to the active backend and returns which one it used.
+    pub(crate) fn store_secret(provider: &str, secret: &str) -> Result<CredentialBackend> {
+        if Self::backend() == CredentialBackend::Keyring {
+            match Entry::new("kandil", provider)?.set_password(secret) {
+                Ok(()) => return Ok(CredentialBackend::Keyring),
+                Err(err) => log::warn!(
+                    "OS keyring rejected the {} key ({}); using the encrypted credential file",
+                    provider,
+                    err
+                ),
+            }
+        }
+
+        EncryptedCredentialFile::open_default().set(provider, secret, &master_key()?)?;
+        Ok(CredentialBackend::EncryptedFile)
+    }
+
+    /// Removes a secret from both backends, returning whether one existed.
+    pub fn delete(provider: &str) -> Result<bool> {
+        let mut removed = false;
+        if Self::backend() == CredentialBackend::Keyring {
+            match Entry::new("kandil", provider)?.delete_password() {
+                Ok(()) => removed = true,
+                Err(keyring::Error::NoEntry) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        let file = EncryptedCredentialFile::open_default();
+        if file.contains(provider)? {
+            removed |= file.remove(provider)?;
+        }
+        Ok(removed)
+    }
+
+    /// Replaces the provider's key, keeping the current one as a backup for a
+    /// single rotation. Returns whether there was a key to back up.
+    pub fn rotate(provider: &str, new_key: &SecretString) -> Result<bool> {
+        rotate_in(&ActiveBackend, provider, new_key)
+    }
+
+    /// Restores the key saved by the last [`CredentialManager::rotate`] and
+    /// discards the backup, so a rotation can only be undone once.
+    pub fn undo_rotation(provider: &str) -> Result<()> {
+        undo_rotation_in(&ActiveBackend, provider)
+    }
+}
+
+/// Raw secret storage that rotation works on.
+trait SecretStore {
+    fn load(&self, name: &str) -> Result<Option<String>>;
+    fn store(&self, name: &str, secret: &str) -> Result<()>;
+    fn delete(&self, name: &str) -> Result<bool>;
+}
+
+/// The keyring when usable, the encrypted file otherwise.
+struct ActiveBackend;
+
+impl SecretStore for ActiveBackend {
+    fn load(&self, name: &str) -> Result<Option<String>> {
+        CredentialManager::load_secret(name)
+    }
+
+    fn store(&self, name: &str, secret: &str) -> Result<()> {
+        CredentialManager::store_secret(name, secret).map(|_| ())
+    }
+
+    fn delete(&self, name: &str) -> Result<bool> {
+        CredentialManager::delete(name)
+    }
+}
+
+fn rotate_in(store: &dyn SecretStore, provider: &str, new_key: &SecretString) -> Result<bool> {
+    let previous = store.load(provider)?;
+    if let Some(old_key) = &previous {
+        store.store(&backup_name(provider), old_key)?;
+    }
+    store.store(provider, new_key.expose_secret())?;
+    Ok(previous.is_some())
+}
+
+fn undo_rotation_in(store: &dyn SecretStore, provider: &str) -> Result<()> {
+    let backup = backup_name(provider);
+    let Some(old_key) = store.load(&backup)? else {
+        anyhow::bail!("No previous key saved for {provider}; nothing to undo");
+    };
+    store.store(provider, &old_key)?;
+    store.delete(&backup)?;
+    Ok(())
+}
+
+/// Where `rotate` keeps the replaced key.
+fn backup_name(provider: &str) -> String {
+    format!("{provider}.previous")
+}
+
+/// The passphrase protecting the credential file: `KANDIL_MASTER_KEY`, or a
+/// prompt when running interactively.
+fn master_key() -> Result<SecretString> {
+    if let Ok(value) = std::env::var("KANDIL_MASTER_KEY") {
+        if !value.is_empty() {
+            return Ok(SecretString::new(value));
+        }
+    }
+    if let Some(prompted) = PROMPTED_MASTER_KEY.get() {
+        return Ok(prompted.clone());
+    }
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "The OS keyring is unavailable and KANDIL_MASTER_KEY is not set. \
+             Set KANDIL_MASTER_KEY to store credentials in an encrypted file."
+        );
+    }
+
+    let value = rpassword::prompt_password("Kandil master key (encrypts stored API keys): ")?;
+    if value.is_empty() {
+        anyhow::bail!("A master key is required to use the encrypted credential file");
+    }
+    Ok(PROMPTED_MASTER_KEY
+        .get_or_init(|| SecretString::new(value))
+        .clone())
 }
 
 #[cfg(test)]
@@ -56,4 +238,55 @@ mod tests {
         let state = CredentialManager::ensure(&provider).unwrap();
         assert_eq!(state, CredentialState::Missing);
     }
+
+    /// An encrypted credential file unlocked with a fixed passphrase.
+    struct FileStore {
+        file: EncryptedCredentialFile,
+        passphrase: SecretString,
+    }
+
+    impl SecretStore for FileStore {
+        fn load(&self, name: &str) -> Result<Option<String>> {
+            self.file.get(name, &self.passphrase)
+        }
+
+        fn store(&self, name: &str, secret: &str) -> Result<()> {
+            self.file.set(name, secret, &self.passphrase)
+        }
+
+        fn delete(&self, name: &str) -> Result<bool> {
+            self.file.remove(name)
+        }
+    }
+
+    #[test]
+    fn rotation_keeps_one_backup_and_undoes_once() -> Result<()> {
+        use crate::utils::json_store::JsonStore;
+
+        let dir = std::env::temp_dir().join(format!("kandil_rotate_{}", uuid::Uuid::new_v4()));
+        let store = FileStore {
+            file: EncryptedCredentialFile::new(JsonStore::new(dir.join("credentials.enc.json"))),
+            passphrase: SecretString::new("correct horse".to_string()),
+        };
+        let key = |value: &str| SecretString::new(value.to_string());
+
+        assert!(undo_rotation_in(&store, "openai").is_err());
+        assert!(!rotate_in(&store, "openai", &key("sk-one"))?);
+        assert_eq!(store.load("openai.previous")?, None);
+
+        assert!(rotate_in(&store, "openai", &key("sk-two"))?);
+        assert!(rotate_in(&store, "openai", &key("sk-three"))?);
+        assert_eq!(store.load("openai")?.as_deref(), Some("sk-three"));
+        assert_eq!(store.load("openai.previous")?.as_deref(), Some("sk-two"));
+
+        undo_rotation_in(&store, "openai")?;
+        assert_eq!(store.load("openai")?.as_deref(), Some("sk-two"));
+        assert!(!store.file.contains("openai.previous")?);
+        let err = undo_rotation_in(&store, "openai").unwrap_err();
+        assert!(err.to_string().contains("nothing to undo"));
+        assert_eq!(store.load("openai")?.as_deref(), Some("sk-two"));
+
+        let _ = std::fs::remove_dir_all(dir);
+        Ok(())
+    }
 }
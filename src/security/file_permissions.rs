@@ -0,0 +1,131 @@
+//! Permissions of files that hold secrets
+//!
+//! Config files can name proxy users and TLS client keys, and the encrypted
+//! credential file holds every API key stored without a keyring. Both should
+//! be readable by their owner only: mode 0600 on Unix, and on Windows an ACL
+//! that grants nothing to broad groups such as Everyone or Users.
+
+use crate::security::credential_file::EncryptedCredentialFile;
+use crate::utils::config::Config;
+use anyhow::Result;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Groups that must not appear in a secret file's ACL on Windows.
+#[cfg(windows)]
+const BROAD_GROUPS: &[&str] = &["Everyone", "BUILTIN\\Users", "Authenticated Users"];
+
+/// One secret-bearing file and what is wrong with its permissions, if anything.
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionCheck {
+    pub path: PathBuf,
+    pub kind: &'static str,
+    pub problem: Option<String>,
+}
+
+impl PermissionCheck {
+    pub fn is_secure(&self) -> bool {
+        self.problem.is_none()
+    }
+}
+
+/// Checks every config file that is loaded, and the encrypted credential
+/// file if it exists.
+pub fn check_secret_files() -> Result<Vec<PermissionCheck>> {
+    let mut files: Vec<(PathBuf, &'static str)> = Config::files()?
+        .into_iter()
+        .map(|path| (path, "config file"))
+        .collect();
+    let credentials = EncryptedCredentialFile::open_default().path().to_path_buf();
+    if credentials.exists() {
+        files.push((credentials, "encrypted credential file"));
+    }
+    files
+        .into_iter()
+        .map(|(path, kind)| {
+            Ok(PermissionCheck {
+                problem: check(&path)?,
+                path,
+                kind,
+            })
+        })
+        .collect()
+}
+
+/// Why `path` is readable by others, or `None` if only its owner can read it.
+#[cfg(unix)]
+pub fn check(path: &Path) -> Result<Option<String>> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = std::fs::metadata(path)?.permissions().mode() & 0o777;
+    Ok((mode & 0o077 != 0).then(|| {
+        format!(
+            "mode {:04o} lets other users access it; expected 0600",
+            mode
+        )
+    }))
+}
+
+#[cfg(windows)]
+pub fn check(path: &Path) -> Result<Option<String>> {
+    let output = std::process::Command::new("icacls").arg(path).output()?;
+    let acl = String::from_utf8_lossy(&output.stdout);
+    let broad: Vec<&str> = BROAD_GROUPS
+        .iter()
+        .copied()
+        .filter(|group| acl.contains(group))
+        .collect();
+    Ok((!broad.is_empty()).then(|| format!("its ACL grants access to {}", broad.join(", "))))
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn check(_path: &Path) -> Result<Option<String>> {
+    Ok(None)
+}
+
+/// Restricts `path` to its owner.
+#[cfg(unix)]
+pub fn fix(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn fix(path: &Path) -> Result<()> {
+    let user = std::env::var("USERNAME")?;
+    let status = std::process::Command::new("icacls")
+        .arg(path)
+        .args(["/inheritance:r", "/grant:r"])
+        .arg(format!("{}:F", user))
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("icacls could not restrict {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn fix(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn world_readable_files_are_reported_and_fixed() {
+        let path = std::env::temp_dir().join(format!("kandil_perms_{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "[ai]\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        let problem = check(&path).unwrap().unwrap();
+        assert!(problem.contains("0644"), "{}", problem);
+        fix(&path).unwrap();
+        assert_eq!(check(&path).unwrap(), None);
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600);
+        let _ = std::fs::remove_file(path);
+    }
+}
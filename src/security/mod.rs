@@ -2,12 +2,15 @@
 //!
 //! This module centralizes access to sensitive credentials so other parts of
 //! the codebase can rely on a hardened API rather than touching the OS
-//! keyring directly.
+//! keyring directly. Machines without a usable keyring fall back to an
+//! encrypted credential file.
 
+pub mod credential_file;
 pub mod credentials;
+pub mod file_permissions;
 pub mod mobile;
 pub mod model;
 pub mod platform;
 
 #[allow(unused_imports)]
-pub use credentials::{CredentialManager, CredentialState};
+pub use credentials::{CredentialBackend, CredentialManager, CredentialState};
@@ -73,7 +73,8 @@ impl ShutdownHandler {
             if tokio::signal::ctrl_c().await.is_ok() {
                 let mut manager = manager_clone.write().await;
                 manager.trigger_shutdown();
-                println!("Received shutdown signal (Ctrl+C), initiating graceful shutdown...");
+                // stderr, so a command's stdout stays clean for pipes.
+                eprintln!("Received shutdown signal (Ctrl+C), initiating graceful shutdown...");
             }
         });
 
@@ -115,9 +116,19 @@ impl ShutdownHandler {
     }
 
     /// Wait for shutdown signal
+    ///
+    /// The manager lock is released before waiting, since triggering a
+    /// shutdown needs the write lock.
     pub async fn wait_for_shutdown(&self) {
-        let manager = self.manager.read().await;
-        manager.wait_for_shutdown().await;
+        let notify = self.manager.read().await.subscribe();
+        let notified = notify.notified();
+        tokio::pin!(notified);
+        // Register before checking the flag so a trigger in between is not missed.
+        notified.as_mut().enable();
+        if self.shutdown_requested().await {
+            return;
+        }
+        notified.await;
     }
 
     /// Check if shutdown has been requested
@@ -188,4 +199,20 @@ mod tests {
         let result = handler.shutdown_gracefully(timeout).await;
         assert!(result.is_err()); // Should timeout since we didn't trigger shutdown
     }
+
+    #[tokio::test]
+    async fn wait_for_shutdown_sees_trigger() {
+        let handler = ShutdownHandler::new();
+        let manager = Arc::clone(&handler.manager);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            manager.write().await.trigger_shutdown();
+        });
+
+        timeout(Duration::from_secs(1), handler.wait_for_shutdown())
+            .await
+            .expect("waiter should wake once shutdown is triggered");
+        // Already triggered: returns immediately.
+        handler.wait_for_shutdown().await;
+    }
 }
@@ -4,6 +4,7 @@
 //! This implements Phase 3: TUI Studio & Code Understanding
 
 pub mod events;
+pub mod model_picker;
 pub mod studio;
 pub mod widgets;
 
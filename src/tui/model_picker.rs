@@ -0,0 +1,319 @@
+//! Interactive model picker for `kandil switch-model`
+//!
+//! Lists the registry's profiles, catalog models included, grouped by the
+//! provider that serves them, with how each fits this machine's RAM and
+//! whether it is installed locally. Arrow keys move, Enter picks and Esc or
+//! `q` cancels.
+
+use crate::core::hardware::HardwareProfile;
+use crate::models::{ModelProfile, ProviderKind};
+use anyhow::Result;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame, Terminal,
+};
+use std::io;
+
+/// How a model's RAM needs compare to this machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fit {
+    /// Fits in the RAM available now.
+    Fits,
+    /// Fits in total RAM, but not alongside what is running.
+    Tight,
+    TooLarge,
+    /// Served remotely, so local RAM doesn't matter.
+    Remote,
+}
+
+impl Fit {
+    fn label(&self) -> &'static str {
+        match self {
+            Fit::Fits => "✅ fits",
+            Fit::Tight => "⚠️  tight",
+            Fit::TooLarge => "❌ too large",
+            Fit::Remote => "☁️  remote",
+        }
+    }
+}
+
+/// One model the picker offers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PickerEntry {
+    /// Provider name as used in config, e.g. `ollama` or `claude`.
+    pub provider: String,
+    pub model: String,
+    pub size_gb: f64,
+    pub min_ram_gb: u64,
+    pub fit: Fit,
+    pub installed: bool,
+}
+
+enum Row {
+    Header(String),
+    Model(usize),
+}
+
+/// The config provider that serves `kind`, if Kandil has an adapter for it.
+fn config_provider(kind: &ProviderKind) -> Option<String> {
+    let provider = match kind {
+        ProviderKind::Ollama => "ollama",
+        ProviderKind::Anthropic => "claude",
+        ProviderKind::QwenCloud => "qwen",
+        ProviderKind::LocalBridge => "lmstudio",
+        ProviderKind::Gemini => return None,
+        ProviderKind::Custom(name) => name.as_str(),
+    };
+    let provider = provider.to_lowercase();
+    crate::core::adapters::ai::backend::is_registered(&provider).then_some(provider)
+}
+
+/// Entries for `profiles`, sorted by provider then name. `installed` holds
+/// the names of models present locally; providers Kandil can't talk to are
+/// left out.
+pub fn entries(
+    profiles: &[ModelProfile],
+    hardware: &HardwareProfile,
+    installed: &[String],
+) -> Vec<PickerEntry> {
+    let mut entries: Vec<PickerEntry> = profiles
+        .iter()
+        .filter_map(|profile| {
+            let fit = if !profile.is_local {
+                Fit::Remote
+            } else if profile.resources.min_system_ram_gb <= hardware.available_ram_gb {
+                Fit::Fits
+            } else if profile.resources.min_system_ram_gb <= hardware.total_ram_gb {
+                Fit::Tight
+            } else {
+                Fit::TooLarge
+            };
+            Some(PickerEntry {
+                provider: config_provider(&profile.provider)?,
+                model: profile.name.clone(),
+                size_gb: profile.resources.size_gb,
+                min_ram_gb: profile.resources.min_system_ram_gb,
+                fit,
+                installed: installed.contains(&profile.name),
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| (&a.provider, &a.model).cmp(&(&b.provider, &b.model)));
+    entries
+}
+
+pub struct ModelPicker {
+    entries: Vec<PickerEntry>,
+    rows: Vec<Row>,
+    /// Index into `rows`; always a model row when there are any.
+    selected: usize,
+}
+
+impl ModelPicker {
+    /// A picker over `entries`, which must be grouped by provider, starting
+    /// on `current` if it is among them.
+    pub fn new(entries: Vec<PickerEntry>, current: Option<(&str, &str)>) -> Self {
+        let mut rows = Vec::new();
+        for (index, entry) in entries.iter().enumerate() {
+            if index == 0 || entries[index - 1].provider != entry.provider {
+                rows.push(Row::Header(entry.provider.clone()));
+            }
+            rows.push(Row::Model(index));
+        }
+        let selected = rows
+            .iter()
+            .position(|row| match row {
+                Row::Model(index) => {
+                    let entry = &entries[*index];
+                    current == Some((entry.provider.as_str(), entry.model.as_str()))
+                }
+                Row::Header(_) => false,
+            })
+            .or_else(|| rows.iter().position(|row| matches!(row, Row::Model(_))))
+            .unwrap_or(0);
+        Self {
+            entries,
+            rows,
+            selected,
+        }
+    }
+
+    pub fn selected(&self) -> Option<&PickerEntry> {
+        match self.rows.get(self.selected)? {
+            Row::Model(index) => self.entries.get(*index),
+            Row::Header(_) => None,
+        }
+    }
+
+    /// Moves to the next model, skipping provider headers and wrapping.
+    pub fn next(&mut self) {
+        self.step(1);
+    }
+
+    pub fn previous(&mut self) {
+        self.step(self.rows.len().saturating_sub(1));
+    }
+
+    fn step(&mut self, by: usize) {
+        if self.entries.is_empty() {
+            return;
+        }
+        loop {
+            self.selected = (self.selected + by) % self.rows.len();
+            if matches!(self.rows[self.selected], Row::Model(_)) {
+                return;
+            }
+        }
+    }
+
+    /// Shows the picker full screen until a model is picked (`Some`) or the
+    /// user cancels (`None`).
+    pub fn run(mut self) -> Result<Option<PickerEntry>> {
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        let result = self.event_loop();
+        disable_raw_mode()?;
+        execute!(io::stdout(), LeaveAlternateScreen)?;
+        result
+    }
+
+    fn event_loop(&mut self) -> Result<Option<PickerEntry>> {
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+        loop {
+            terminal.draw(|f| self.ui(f))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => self.next(),
+                KeyCode::Up | KeyCode::Char('k') | KeyCode::BackTab => self.previous(),
+                KeyCode::Enter => return Ok(self.selected().cloned()),
+                KeyCode::Esc | KeyCode::Char('q') => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+
+    fn ui(&self, f: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(1)])
+            .split(f.size());
+
+        let items: Vec<ListItem> = self
+            .rows
+            .iter()
+            .map(|row| match row {
+                Row::Header(provider) => ListItem::new(provider.clone()).style(
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Row::Model(index) => {
+                    let entry = &self.entries[*index];
+                    ListItem::new(format!(
+                        "  {:<32} {:>5.1}GB  {:>3}GB RAM  {:<12} {}",
+                        entry.model,
+                        entry.size_gb,
+                        entry.min_ram_gb,
+                        entry.fit.label(),
+                        if entry.installed { "installed" } else { "" }
+                    ))
+                }
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Switch model"))
+            .highlight_style(
+                Style::default()
+                    .bg(Color::LightBlue)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("> ");
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        f.render_stateful_widget(list, chunks[0], &mut state);
+        f.render_widget(
+            Paragraph::new("↑/↓ move · Enter select · Esc cancel"),
+            chunks[1],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::hardware::detect_hardware;
+    use crate::models::ModelResources;
+
+    fn profile(name: &str, provider: ProviderKind, is_local: bool, ram: u64) -> ModelProfile {
+        ModelProfile {
+            name: name.to_string(),
+            provider,
+            description: String::new(),
+            is_local,
+            api_key_required: !is_local,
+            context_window: 8192,
+            default_endpoint: Some("http://localhost:11434".to_string()),
+            resources: ModelResources {
+                size_gb: 4.0,
+                min_system_ram_gb: ram,
+                min_vram_gb: None,
+            },
+            tags: Vec::new(),
+            capabilities: Default::default(),
+        }
+    }
+
+    #[test]
+    fn models_are_grouped_by_provider_and_headers_are_skipped() {
+        let hardware = HardwareProfile {
+            total_ram_gb: 16,
+            available_ram_gb: 8,
+            ..detect_hardware()
+        };
+        let profiles = vec![
+            profile("small", ProviderKind::Ollama, true, 4),
+            profile("claude-3-haiku", ProviderKind::Anthropic, false, 0),
+            profile("medium", ProviderKind::Ollama, true, 12),
+            profile("huge", ProviderKind::Ollama, true, 64),
+            profile("gemini-pro", ProviderKind::Gemini, false, 0),
+        ];
+        let entries = entries(&profiles, &hardware, &["small".to_string()]);
+        let names: Vec<(&str, Fit, bool)> = entries
+            .iter()
+            .map(|entry| (entry.model.as_str(), entry.fit, entry.installed))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("claude-3-haiku", Fit::Remote, false),
+                ("huge", Fit::TooLarge, false),
+                ("medium", Fit::Tight, false),
+                ("small", Fit::Fits, true),
+            ]
+        );
+
+        let mut picker = ModelPicker::new(entries, Some(("ollama", "medium")));
+        assert_eq!(picker.selected().unwrap().model, "medium");
+        picker.next();
+        assert_eq!(picker.selected().unwrap().model, "small");
+        picker.next();
+        assert_eq!(picker.selected().unwrap().model, "claude-3-haiku");
+        picker.previous();
+        assert_eq!(picker.selected().unwrap().model, "small");
+
+        let picker = ModelPicker::new(Vec::new(), None);
+        assert!(picker.selected().is_none());
+    }
+}
@@ -4,6 +4,7 @@
 
 use crate::enhanced_ui::gpu_render::{should_use_gpu, GpuRenderer};
 use crate::enhanced_ui::terminal::KandilTerminal;
+use crate::performance::Watchdog;
 use crate::tui::events::{AppEvent, EventHandler};
 use crate::tui::widgets::{AIChatWidget, CodeViewer, FileExplorer};
 use crate::utils::code_analysis::CodeAnalyzer;
@@ -14,7 +15,9 @@ use crossterm::{
 };
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    widgets::Paragraph,
     Frame, Terminal,
 };
 use std::io;
@@ -37,6 +40,7 @@ pub struct StudioApp {
     #[cfg(feature = "gpu-rendering")]
     gpu_renderer: Option<GpuRenderer>,
     terminal: Arc<KandilTerminal>,
+    watchdog: Option<Watchdog>,
 }
 
 impl StudioApp {
@@ -80,6 +84,7 @@ impl StudioApp {
             #[cfg(feature = "gpu-rendering")]
             gpu_renderer,
             terminal,
+            watchdog: None,
         })
     }
 
@@ -95,6 +100,11 @@ impl StudioApp {
         // Create event handler
         let events = EventHandler::new(250); // 250ms tick rate
 
+        // Resource pressure is shown in the corner rather than printed
+        if let Ok(config) = crate::utils::config::Config::load() {
+            self.watchdog = Watchdog::from_config(&config.watchdog, false);
+        }
+
         // Main loop
         loop {
             terminal.draw(|f| self.ui(f))?;
@@ -124,6 +134,8 @@ impl StudioApp {
             }
         }
 
+        self.watchdog = None;
+
         // Restore terminal
         disable_raw_mode()?;
         execute!(io::stdout(), LeaveAlternateScreen)?;
@@ -146,6 +158,42 @@ impl StudioApp {
         f.render_widget(self.file_explorer.clone(), chunks[0]);
         f.render_widget(self.code_viewer.clone(), chunks[1]);
         f.render_widget(self.ai_chat.clone(), chunks[2]);
+
+        self.render_pressure(f);
+    }
+
+    /// Draws the watchdog's pressure over the bottom-right border.
+    fn render_pressure(&self, f: &mut Frame) {
+        let Some(watchdog) = &self.watchdog else {
+            return;
+        };
+        let pressure = watchdog.pressure();
+        if pressure.is_empty() {
+            return;
+        }
+        let label = format!(
+            " ⚠ {} ",
+            pressure
+                .iter()
+                .map(|pressure| pressure.indicator())
+                .collect::<Vec<_>>()
+                .join(" · ")
+        );
+        let area = f.size();
+        let width = (label.chars().count() as u16).min(area.width);
+        if area.height == 0 || width == 0 {
+            return;
+        }
+        let corner = Rect {
+            x: area.right().saturating_sub(width + 1),
+            y: area.bottom() - 1,
+            width,
+            height: 1,
+        };
+        f.render_widget(
+            Paragraph::new(label).style(Style::default().fg(Color::Yellow)),
+            corner,
+        );
     }
 
     fn handle_key_events(&mut self, key_event: crossterm::event::KeyEvent) -> Result<()> {
@@ -0,0 +1,125 @@
+//! Per-invocation AI call budget
+//!
+//! Provider retries nest inside agent loops (TDD cycles, consensus rounds,
+//! pipeline stages), so one command can multiply into hundreds of requests.
+//! Every request to a model, retries included, spends one call from a
+//! budget set by `--call-budget` or `KANDIL_CALL_BUDGET`. Once it is spent,
+//! further requests fail with [`AiError::BudgetExhausted`] instead of
+//! reaching the provider. `0` means no limit.
+//!
+//! A one-shot command spends from one counter for the whole process.
+//! Long-running modes wrap each unit of work in [`scoped`] instead, so every
+//! web or IDE request and every REPL turn starts with the full budget rather
+//! than draining a shared one until the server refuses all AI calls.
+
+use crate::errors::AiError;
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::OnceLock;
+
+/// Calls allowed per command when neither the flag nor the env var is set.
+pub const DEFAULT_CALL_BUDGET: u32 = 100;
+
+static LIMIT: OnceLock<u32> = OnceLock::new();
+static USED: AtomicU32 = AtomicU32::new(0);
+
+tokio::task_local! {
+    /// Calls spent by the request or turn running in [`scoped`].
+    static SCOPED_USED: AtomicU32;
+}
+
+/// Runs `work` with a budget of its own. Calls made from tasks it spawns
+/// are still counted against the process-wide budget.
+pub async fn scoped<F: Future>(work: F) -> F::Output {
+    SCOPED_USED.scope(AtomicU32::new(0), work).await
+}
+
+/// Applies `count` to the counter of the current scope, or to the
+/// process-wide one outside [`scoped`].
+fn with_used<T>(count: impl Fn(&AtomicU32) -> T) -> T {
+    SCOPED_USED
+        .try_with(&count)
+        .unwrap_or_else(|_| count(&USED))
+}
+
+/// Fixes the budget for the rest of the process; later calls are ignored.
+pub fn set(limit: u32) {
+    let _ = LIMIT.set(limit);
+}
+
+/// The budget from `--call-budget`, falling back to `KANDIL_CALL_BUDGET` and
+/// then [`DEFAULT_CALL_BUDGET`]. `0` is unlimited.
+pub fn limit() -> u32 {
+    *LIMIT.get_or_init(|| {
+        std::env::var("KANDIL_CALL_BUDGET")
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(DEFAULT_CALL_BUDGET)
+    })
+}
+
+/// Calls left, or `None` when unlimited.
+pub fn remaining() -> Option<u32> {
+    remaining_of(limit(), with_used(|used| used.load(Ordering::SeqCst)))
+}
+
+/// Spends one call for a request to `provider`.
+pub fn spend(provider: &str) -> Result<(), AiError> {
+    let limit = limit();
+    let used = with_used(|used| spend_from(used, limit))?;
+    match remaining_of(limit, used) {
+        Some(left) => crate::utils::verbosity::note(format!(
+            "AI call {}/{} to {}; {} left in this command's budget",
+            used, limit, provider, left
+        )),
+        None => crate::utils::verbosity::note(format!("AI call {} to {}", used, provider)),
+    }
+    Ok(())
+}
+
+/// Counts one more call against `limit`, returning the calls used so far.
+fn spend_from(used: &AtomicU32, limit: u32) -> Result<u32, AiError> {
+    let previous = used
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+            (limit == 0 || used < limit).then_some(used + 1)
+        })
+        .map_err(|_| AiError::BudgetExhausted { limit })?;
+    Ok(previous + 1)
+}
+
+fn remaining_of(limit: u32, used: u32) -> Option<u32> {
+    (limit > 0).then(|| limit.saturating_sub(used))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calls_stop_once_the_budget_is_spent() {
+        let used = AtomicU32::new(0);
+        assert_eq!(spend_from(&used, 2).unwrap(), 1);
+        assert_eq!(spend_from(&used, 2).unwrap(), 2);
+        let err = spend_from(&used, 2).unwrap_err();
+        assert!(matches!(err, AiError::BudgetExhausted { limit: 2 }));
+        assert!(!err.is_retryable());
+        assert!(err.to_string().contains("--call-budget"));
+        assert_eq!(used.load(Ordering::SeqCst), 2);
+        assert_eq!(remaining_of(2, 2), Some(0));
+
+        let unlimited = AtomicU32::new(0);
+        for _ in 0..5 {
+            spend_from(&unlimited, 0).unwrap();
+        }
+        assert_eq!(remaining_of(0, 5), None);
+    }
+
+    #[tokio::test]
+    async fn each_scope_gets_its_own_count() {
+        let spent_in_scope = || scoped(async { with_used(|used| spend_from(used, 1)) });
+        assert_eq!(spent_in_scope().await.unwrap(), 1);
+        assert_eq!(spent_in_scope().await.unwrap(), 1);
+        let outside = USED.load(Ordering::SeqCst);
+        assert_eq!(with_used(|used| used.load(Ordering::SeqCst)), outside);
+    }
+}
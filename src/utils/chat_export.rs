@@ -0,0 +1,342 @@
+//! Chat transcript export
+//!
+//! Renders a project's stored memory turns as a Markdown or self-contained
+//! HTML transcript for sharing. Turns are grouped by session in the order the
+//! sessions started, and fenced code blocks in replies are kept intact.
+
+use crate::utils::db::{Memory, Project};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> Result<Self> {
+        match format.trim().to_lowercase().as_str() {
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            "html" => Ok(ExportFormat::Html),
+            other => bail!("Unsupported export format '{}'; use md or html", other),
+        }
+    }
+}
+
+/// One session's turns, oldest first.
+#[derive(Debug, Clone)]
+pub struct ChatSession {
+    pub id: String,
+    pub turns: Vec<Memory>,
+}
+
+/// Groups memory entries by session, oldest session first.
+pub fn group_sessions(mut memories: Vec<Memory>) -> Vec<ChatSession> {
+    memories.sort_by_key(|memory| memory.timestamp);
+    let mut sessions: Vec<ChatSession> = Vec::new();
+    for memory in memories {
+        match sessions
+            .iter_mut()
+            .find(|session| session.id == memory.session_id)
+        {
+            Some(session) => session.turns.push(memory),
+            None => sessions.push(ChatSession {
+                id: memory.session_id.clone(),
+                turns: vec![memory],
+            }),
+        }
+    }
+    sessions
+}
+
+/// Picks `session_id`, or the most recently started session.
+pub fn select_session(sessions: Vec<ChatSession>, session_id: Option<&str>) -> Result<ChatSession> {
+    match session_id {
+        Some(id) => match sessions.into_iter().find(|session| session.id == id) {
+            Some(session) => Ok(session),
+            None => bail!("Session {} has no stored turns in this project", id),
+        },
+        None => match sessions.into_iter().last() {
+            Some(session) => Ok(session),
+            None => bail!("This project has no stored chat sessions"),
+        },
+    }
+}
+
+pub fn render(project: &Project, sessions: &[ChatSession], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Markdown => render_markdown(project, sessions),
+        ExportFormat::Html => render_html(project, sessions),
+    }
+}
+
+fn role_label(role: &str) -> String {
+    match role {
+        "user" => "User".to_string(),
+        "ai" | "assistant" => "Assistant".to_string(),
+        other => {
+            let mut chars = other.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => "Unknown".to_string(),
+            }
+        }
+    }
+}
+
+fn format_time(timestamp: DateTime<Utc>) -> String {
+    timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
+
+pub fn render_markdown(project: &Project, sessions: &[ChatSession]) -> String {
+    let mut out = format!("# {}\n\n", project.name);
+    out.push_str(&format!(
+        "_Exported {} · {} ({})_\n",
+        format_time(Utc::now()),
+        project.ai_provider,
+        project.ai_model
+    ));
+    for session in sessions {
+        out.push_str(&format!("\n## Session {}\n", session.id));
+        for turn in &session.turns {
+            out.push_str(&format!(
+                "\n### {} · {}\n\n",
+                role_label(&turn.role),
+                format_time(turn.timestamp)
+            ));
+            out.push_str(turn.content.trim_end());
+            // Close a fence the model left open so later turns render normally
+            if fence_count(&turn.content) % 2 == 1 {
+                out.push_str("\n```");
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn fence_count(content: &str) -> usize {
+    content
+        .lines()
+        .filter(|line| line.trim_start().starts_with("```"))
+        .count()
+}
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>__TITLE__</title>
+<style>__STYLE__</style>
+</head>
+<body>
+<header>
+<h1>__TITLE__</h1>
+<p>Exported __EXPORTED__ · __PROVIDER__ (__MODEL__)</p>
+</header>
+__BODY__</body>
+</html>
+"#;
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:52rem;margin:2rem auto;\
+padding:0 1rem;color:#1f2328;line-height:1.5}\
+header p{color:#656d76}\
+h2{border-bottom:1px solid #d0d7de;padding-bottom:.3rem;margin-top:2.5rem}\
+.turn{border:1px solid #d0d7de;border-radius:6px;padding:.25rem 1rem;margin:1rem 0}\
+.turn.user{background:#f6f8fa}\
+.meta{font-size:.85rem;color:#656d76}\
+.meta strong{color:#1f2328}\
+pre{background:#0d1117;color:#e6edf3;padding:.75rem;border-radius:6px;overflow-x:auto}\
+code{font-family:ui-monospace,SFMono-Regular,Menlo,monospace;font-size:.9em}";
+
+pub fn render_html(project: &Project, sessions: &[ChatSession]) -> String {
+    let mut body = String::new();
+    for session in sessions {
+        body.push_str(&format!(
+            "<section>\n<h2>Session {}</h2>\n",
+            escape_html(&session.id)
+        ));
+        for turn in &session.turns {
+            let class = if turn.role == "user" {
+                "user"
+            } else {
+                "assistant"
+            };
+            body.push_str(&format!(
+                "<article class=\"turn {}\">\n<p class=\"meta\"><strong>{}</strong> · \
+                 <time datetime=\"{}\">{}</time></p>\n{}</article>\n",
+                class,
+                escape_html(&role_label(&turn.role)),
+                turn.timestamp.to_rfc3339(),
+                format_time(turn.timestamp),
+                content_to_html(&turn.content)
+            ));
+        }
+        body.push_str("</section>\n");
+    }
+
+    PAGE.replace("__STYLE__", STYLE)
+        .replace("__TITLE__", &escape_html(&project.name))
+        .replace("__EXPORTED__", &format_time(Utc::now()))
+        .replace("__PROVIDER__", &escape_html(&project.ai_provider))
+        .replace("__MODEL__", &escape_html(&project.ai_model))
+        .replace("__BODY__", &body)
+}
+
+/// Paragraphs for prose and `<pre><code>` for fenced blocks.
+fn content_to_html(content: &str) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut code: Option<(String, Vec<&str>)> = None;
+
+    let flush = |html: &mut String, paragraph: &mut Vec<&str>| {
+        if !paragraph.is_empty() {
+            let lines: Vec<String> = paragraph.iter().map(|line| escape_html(line)).collect();
+            html.push_str(&format!("<p>{}</p>\n", lines.join("<br>\n")));
+            paragraph.clear();
+        }
+    };
+
+    for line in content.lines() {
+        let fence = line.trim_start().strip_prefix("```");
+        match (&mut code, fence) {
+            (Some((language, lines)), Some(_)) => {
+                html.push_str(&code_block(language, lines));
+                code = None;
+            }
+            (Some((_, lines)), None) => lines.push(line),
+            (None, Some(language)) => {
+                flush(&mut html, &mut paragraph);
+                code = Some((language.trim().to_string(), Vec::new()));
+            }
+            (None, None) if line.trim().is_empty() => flush(&mut html, &mut paragraph),
+            (None, None) => paragraph.push(line),
+        }
+    }
+    flush(&mut html, &mut paragraph);
+    if let Some((language, lines)) = &code {
+        html.push_str(&code_block(language, lines));
+    }
+    html
+}
+
+fn code_block(language: &str, lines: &[&str]) -> String {
+    let class = if language.is_empty() {
+        String::new()
+    } else {
+        format!(" class=\"language-{}\"", escape_html(language))
+    };
+    format!(
+        "<pre><code{}>{}</code></pre>\n",
+        class,
+        escape_html(&lines.join("\n"))
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn project() -> Project {
+        Project {
+            id: "p1".to_string(),
+            name: "Shop <API>".to_string(),
+            root_path: "/tmp/shop".to_string(),
+            ai_provider: "ollama".to_string(),
+            ai_model: "llama3:8b".to_string(),
+            last_opened: None,
+            memory_enabled: true,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn turn(session: &str, role: &str, minute: u32, content: &str) -> Memory {
+        let timestamp = Utc.with_ymd_and_hms(2024, 5, 1, 9, minute, 0).unwrap();
+        Memory {
+            id: 0,
+            project_id: "p1".to_string(),
+            session_id: session.to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp,
+            tokens_used: None,
+            entry_id: format!("{}-{}", session, minute),
+            updated_at: timestamp,
+            synced_at: None,
+            cost_usd: None,
+            provider: None,
+            model: None,
+        }
+    }
+
+    fn history() -> Vec<Memory> {
+        vec![
+            turn(
+                "s2",
+                "ai",
+                31,
+                "Use a guard:\n```rust\nif x < 1 { return; }\n```",
+            ),
+            turn("s1", "user", 1, "Hello"),
+            turn("s2", "user", 30, "How do I exit early?"),
+            turn("s1", "ai", 2, "Hi there"),
+        ]
+    }
+
+    #[test]
+    fn sessions_are_grouped_in_order_and_latest_is_default() {
+        let sessions = group_sessions(history());
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].id, "s1");
+        assert_eq!(sessions[1].turns[0].role, "user");
+
+        assert_eq!(select_session(sessions.clone(), None).unwrap().id, "s2");
+        assert_eq!(
+            select_session(sessions.clone(), Some("s1")).unwrap().id,
+            "s1"
+        );
+        assert!(select_session(sessions, Some("s9")).is_err());
+        assert!(select_session(Vec::new(), None).is_err());
+    }
+
+    #[test]
+    fn markdown_has_labels_timestamps_and_fences() {
+        let sessions = group_sessions(history());
+        let markdown = render_markdown(&project(), &sessions[1..]);
+        assert!(markdown.starts_with("# Shop <API>\n"));
+        assert!(markdown.contains("### User · 2024-05-01 09:30:00 UTC"));
+        assert!(markdown.contains("### Assistant · 2024-05-01 09:31:00 UTC"));
+        assert!(markdown.contains("```rust\nif x < 1 { return; }\n```"));
+        assert!(!markdown.contains("Session s1"));
+
+        let open = render_markdown(
+            &project(),
+            &group_sessions(vec![turn("s", "ai", 0, "```sh\nls")]),
+        );
+        assert!(open.ends_with("ls\n```\n"));
+    }
+
+    #[test]
+    fn html_is_escaped_and_self_contained() {
+        let html = render_html(&project(), &group_sessions(history()));
+        assert!(html.contains("<title>Shop &lt;API&gt;</title>"));
+        assert!(html.contains("<style>"));
+        assert!(!html.contains("<script") && !html.contains("<link"));
+        assert!(html
+            .contains("<pre><code class=\"language-rust\">if x &lt; 1 { return; }</code></pre>"));
+        assert!(html.contains("<p>Use a guard:</p>"));
+        assert!(html.contains("<time datetime=\"2024-05-01T09:01:00+00:00\">"));
+        assert_eq!(ExportFormat::parse("MD").unwrap(), ExportFormat::Markdown);
+        assert!(ExportFormat::parse("pdf").is_err());
+    }
+}
@@ -1,10 +1,17 @@
 //! Cloud synchronization module
 //!
-//! Handles synchronization with Supabase cloud service
+//! Handles synchronization with Supabase cloud service, and reconciling
+//! project memory with a remote copy when both sides have changed.
 
-use crate::utils::db::{Database, SyncQueue};
-use anyhow::Result;
+use crate::utils::db::{Database, Memory, SyncQueue};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
 use std::sync::Arc;
+use uuid::Uuid;
 
 pub struct CloudSync {
     db: Arc<Database>,
@@ -28,6 +35,7 @@ impl CloudSync {
     }
 
     pub async fn sync_pending(&self) -> Result<()> {
+        crate::utils::offline::ensure_online("Cloud sync")?;
         let pending_items = self.db.get_unsynced_items()?;
 
         for item in pending_items {
@@ -102,3 +110,336 @@ impl CloudSync {
         Ok(())
     }
 }
+
+/// How `projects sync` settles an entry that changed on both sides since the
+/// last sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictStrategy {
+    LocalWins,
+    RemoteWins,
+    #[default]
+    NewestWins,
+    /// Keep both versions as separate entries, interleaved by timestamp.
+    Merge,
+}
+
+impl ConflictStrategy {
+    pub const ALL: [ConflictStrategy; 4] = [
+        ConflictStrategy::LocalWins,
+        ConflictStrategy::RemoteWins,
+        ConflictStrategy::NewestWins,
+        ConflictStrategy::Merge,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConflictStrategy::LocalWins => "local-wins",
+            ConflictStrategy::RemoteWins => "remote-wins",
+            ConflictStrategy::NewestWins => "newest-wins",
+            ConflictStrategy::Merge => "merge",
+        }
+    }
+}
+
+impl FromStr for ConflictStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        ConflictStrategy::ALL
+            .into_iter()
+            .find(|strategy| strategy.as_str() == s.to_lowercase())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown conflict strategy '{}'. Expected one of: local-wins, remote-wins, newest-wins, merge",
+                    s
+                )
+            })
+    }
+}
+
+/// An entry changed on both sides that the strategy couldn't settle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConflict {
+    pub entry_id: String,
+    pub local: Memory,
+    pub remote: Memory,
+}
+
+/// How many entries each rule settled.
+#[derive(Debug, Default, Serialize)]
+pub struct SyncReport {
+    pub unchanged: usize,
+    /// Fast-forwards: changed locally only.
+    pub pushed: usize,
+    /// Fast-forwards: changed remotely only.
+    pub pulled: usize,
+    pub local_wins: usize,
+    pub remote_wins: usize,
+    pub newest_wins: usize,
+    pub merged: usize,
+    /// Left as they are on each side for manual review.
+    pub unresolved: Vec<SyncConflict>,
+}
+
+/// The outcome of reconciling one project's memory with the remote.
+#[derive(Debug, Default)]
+pub struct SyncPlan {
+    /// Entries to write locally.
+    pub pull: Vec<Memory>,
+    /// The remote's memory after the sync, oldest first.
+    pub remote: Vec<Memory>,
+    /// Entries both sides agree on afterwards, to be stamped as synced.
+    pub agreed: Vec<String>,
+    pub report: SyncReport,
+}
+
+enum Winner {
+    Local,
+    Remote,
+    Both,
+    Undecided,
+}
+
+/// Reconciles local and remote memory entry by entry. An entry changed on
+/// only one side since its last sync is a fast-forward; one changed on both
+/// sides is a conflict and goes to `strategy`. Nothing is dropped: conflicts
+/// the strategy can't settle are reported and left untouched on both sides.
+pub fn reconcile(local: &[Memory], remote: &[Memory], strategy: ConflictStrategy) -> SyncPlan {
+    let mut plan = SyncPlan::default();
+    let remote_by_id: HashMap<&str, &Memory> = remote
+        .iter()
+        .map(|entry| (entry.entry_id.as_str(), entry))
+        .collect();
+    let local_ids: HashSet<&str> = local.iter().map(|entry| entry.entry_id.as_str()).collect();
+
+    for mine in local {
+        let Some(theirs) = remote_by_id.get(mine.entry_id.as_str()).copied() else {
+            plan.report.pushed += 1;
+            plan.remote.push(mine.clone());
+            plan.agreed.push(mine.entry_id.clone());
+            continue;
+        };
+        if same_content(mine, theirs) {
+            plan.report.unchanged += 1;
+            plan.remote.push(theirs.clone());
+            plan.agreed.push(mine.entry_id.clone());
+            continue;
+        }
+
+        let changed_since_sync = |entry: &Memory| {
+            mine.synced_at
+                .map_or(true, |synced| entry.updated_at > synced)
+        };
+        let winner = match (changed_since_sync(mine), changed_since_sync(theirs)) {
+            (true, false) => {
+                plan.report.pushed += 1;
+                Winner::Local
+            }
+            (false, true) => {
+                plan.report.pulled += 1;
+                Winner::Remote
+            }
+            _ => match strategy {
+                ConflictStrategy::LocalWins => {
+                    plan.report.local_wins += 1;
+                    Winner::Local
+                }
+                ConflictStrategy::RemoteWins => {
+                    plan.report.remote_wins += 1;
+                    Winner::Remote
+                }
+                ConflictStrategy::NewestWins => match mine.updated_at.cmp(&theirs.updated_at) {
+                    Ordering::Greater => {
+                        plan.report.newest_wins += 1;
+                        Winner::Local
+                    }
+                    Ordering::Less => {
+                        plan.report.newest_wins += 1;
+                        Winner::Remote
+                    }
+                    Ordering::Equal => Winner::Undecided,
+                },
+                ConflictStrategy::Merge => {
+                    plan.report.merged += 1;
+                    Winner::Both
+                }
+            },
+        };
+
+        match winner {
+            Winner::Local => {
+                plan.remote.push(mine.clone());
+                plan.agreed.push(mine.entry_id.clone());
+            }
+            Winner::Remote => {
+                plan.pull.push(theirs.clone());
+                plan.remote.push(theirs.clone());
+                plan.agreed.push(mine.entry_id.clone());
+            }
+            Winner::Both => {
+                // The remote version becomes its own entry on both sides.
+                let copy = Memory {
+                    entry_id: Uuid::new_v4().to_string(),
+                    ..theirs.clone()
+                };
+                plan.remote.push(mine.clone());
+                plan.remote.push(copy.clone());
+                plan.agreed.push(mine.entry_id.clone());
+                plan.agreed.push(copy.entry_id.clone());
+                plan.pull.push(copy);
+            }
+            Winner::Undecided => {
+                plan.remote.push(theirs.clone());
+                plan.report.unresolved.push(SyncConflict {
+                    entry_id: mine.entry_id.clone(),
+                    local: mine.clone(),
+                    remote: theirs.clone(),
+                });
+            }
+        }
+    }
+
+    for theirs in remote {
+        if !local_ids.contains(theirs.entry_id.as_str()) {
+            plan.report.pulled += 1;
+            plan.pull.push(theirs.clone());
+            plan.remote.push(theirs.clone());
+            plan.agreed.push(theirs.entry_id.clone());
+        }
+    }
+
+    plan.remote.sort_by_key(|entry| entry.timestamp);
+    plan
+}
+
+fn same_content(a: &Memory, b: &Memory) -> bool {
+    a.role == b.role && a.content == b.content && a.session_id == b.session_id
+}
+
+/// Reads a project's memory from a JSON sync file; a missing file is an empty remote.
+pub fn read_remote_file(path: &Path) -> Result<Vec<Memory>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("{} is not a Kandil memory sync file", path.display()))
+}
+
+pub fn write_remote_file(path: &Path, entries: &[Memory]) -> Result<()> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(entries)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration, Utc};
+
+    fn entry(id: &str, content: &str, updated_at: DateTime<Utc>) -> Memory {
+        Memory {
+            id: 0,
+            project_id: "p".to_string(),
+            session_id: "s".to_string(),
+            role: "user".to_string(),
+            content: content.to_string(),
+            timestamp: updated_at,
+            tokens_used: None,
+            entry_id: id.to_string(),
+            updated_at,
+            synced_at: None,
+            cost_usd: None,
+            provider: None,
+            model: None,
+        }
+    }
+
+    #[test]
+    fn one_sided_changes_fast_forward() {
+        let synced = Utc::now() - Duration::hours(1);
+        let mut edited_here = entry("a", "local edit", Utc::now());
+        edited_here.synced_at = Some(synced);
+        let mut edited_there = entry("b", "old", synced - Duration::minutes(5));
+        edited_there.synced_at = Some(synced);
+        let local = vec![
+            edited_here,
+            edited_there,
+            entry("c", "new here", Utc::now()),
+        ];
+        let remote = vec![
+            entry("a", "old", synced - Duration::minutes(5)),
+            entry("b", "remote edit", Utc::now()),
+            entry("d", "new there", Utc::now()),
+        ];
+
+        // local-wins would clobber "b" if it were treated as a conflict.
+        let plan = reconcile(&local, &remote, ConflictStrategy::LocalWins);
+        assert_eq!(plan.report.pushed, 2);
+        assert_eq!(plan.report.pulled, 2);
+        assert_eq!(plan.report.local_wins, 0);
+        let pulled: Vec<_> = plan.pull.iter().map(|e| e.content.as_str()).collect();
+        assert_eq!(pulled, ["remote edit", "new there"]);
+        assert_eq!(plan.remote.len(), 4);
+        assert!(plan.remote.iter().any(|e| e.content == "local edit"));
+    }
+
+    #[test]
+    fn conflicts_follow_the_strategy() {
+        let synced = Utc::now() - Duration::hours(1);
+        let mut mine = entry("a", "local", synced + Duration::minutes(10));
+        mine.synced_at = Some(synced);
+        let theirs = entry("a", "remote", synced + Duration::minutes(20));
+        let (local, remote) = (vec![mine], vec![theirs]);
+
+        let plan = reconcile(&local, &remote, ConflictStrategy::NewestWins);
+        assert_eq!(plan.report.newest_wins, 1);
+        assert_eq!(plan.pull[0].content, "remote");
+
+        let plan = reconcile(&local, &remote, ConflictStrategy::LocalWins);
+        assert_eq!(plan.report.local_wins, 1);
+        assert!(plan.pull.is_empty());
+        assert_eq!(plan.remote[0].content, "local");
+
+        let plan = reconcile(&local, &remote, ConflictStrategy::RemoteWins);
+        assert_eq!(plan.report.remote_wins, 1);
+        assert_eq!(plan.pull[0].content, "remote");
+
+        let plan = reconcile(&local, &remote, ConflictStrategy::Merge);
+        assert_eq!(plan.report.merged, 1);
+        let merged: Vec<_> = plan.remote.iter().map(|e| e.content.as_str()).collect();
+        assert_eq!(merged, ["local", "remote"]);
+        assert_ne!(plan.pull[0].entry_id, "a");
+        assert_eq!(plan.agreed.len(), 2);
+    }
+
+    #[test]
+    fn ties_are_left_for_manual_review() {
+        let at = Utc::now();
+        let local = vec![entry("a", "local", at)];
+        let remote = vec![entry("a", "remote", at)];
+
+        let plan = reconcile(&local, &remote, ConflictStrategy::NewestWins);
+        assert_eq!(plan.report.unresolved.len(), 1);
+        assert!(plan.pull.is_empty());
+        assert!(plan.agreed.is_empty());
+        assert_eq!(plan.remote[0].content, "remote");
+    }
+
+    #[test]
+    fn strategies_parse_from_cli_names() {
+        for strategy in ConflictStrategy::ALL {
+            assert_eq!(
+                strategy.as_str().parse::<ConflictStrategy>().unwrap(),
+                strategy
+            );
+        }
+        assert!("theirs".parse::<ConflictStrategy>().is_err());
+    }
+}
@@ -1,12 +1,22 @@
 //! Configuration management for Kandil Code
 //!
 //! Handles secure storage and retrieval of API keys and other configuration settings.
+//!
+//! `--config <path>` or `KANDIL_CONFIG` replaces the global, working-directory
+//! and project files with one explicit file, which then also receives saves.
 
+use crate::core::adapters::ai::backend;
+use crate::core::agents::maintenance::AlertThresholds;
+use crate::mobile::WebhookFormat;
+use crate::models::registry::{ModelProfile, SelectionWarning, UniversalModelRegistry};
+use crate::security::credentials::{CredentialBackend, CredentialManager};
 use anyhow::Context;
 use anyhow::Result;
-use keyring::Entry;
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 
 pub struct SecureKey {
     inner: Secret<String>,
@@ -14,9 +24,10 @@ pub struct SecureKey {
 }
 
 impl SecureKey {
+    /// Reads the key from the OS keyring, or from the encrypted credential
+    /// file on machines without one.
     pub fn load(provider: &str) -> Result<Self> {
-        let entry = Entry::new("kandil", provider)?;
-        let key = entry.get_password().map_err(|_| {
+        let key = CredentialManager::load_secret(provider)?.ok_or_else(|| {
             anyhow::anyhow!(
                 "No key for {}. Run: kandil config set-key {}",
                 provider,
@@ -39,9 +50,12 @@ impl SecureKey {
     }
 
     pub fn save(provider: &str, key: &str) -> Result<()> {
-        let entry = Entry::new("kandil", provider)?;
-        entry.set_password(key)?;
-        Ok(())
+        Self::save_with_backend(provider, key).map(|_| ())
+    }
+
+    /// Like [`SecureKey::save`], reporting which backend stored the key.
+    pub fn save_with_backend(provider: &str, key: &str) -> Result<CredentialBackend> {
+        CredentialManager::store_secret(provider, key)
     }
 }
 
@@ -49,50 +63,892 @@ impl SecureKey {
 pub struct Config {
     pub ai_provider: String,
     pub ai_model: String,
+    /// Per-intent model overrides keyed by intent name (e.g. `coding`).
+    #[serde(default)]
+    pub routing: HashMap<String, String>,
+    /// Instructions sent ahead of every prompt.
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// File name patterns (`*` wildcards) excluded from project context.
+    #[serde(default)]
+    pub ignore_patterns: Vec<String>,
+    /// Base URL overrides keyed by provider (e.g. `ollama`).
+    #[serde(default)]
+    pub endpoints: HashMap<String, String>,
+    /// Minimum trust score (0.0-1.0) for applying generated changes without `--force`.
+    #[serde(default = "default_trust_threshold")]
+    pub trust_threshold: f64,
+    /// Extra channels for mobile notifications, beyond the local log.
+    #[serde(default)]
+    pub push: PushConfig,
+    /// Default webhook pinged when long-running commands finish.
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    /// Limits for the web companion's API.
+    #[serde(default)]
+    pub web: WebConfig,
+    /// Caps on the resources a plugin may use.
+    #[serde(default)]
+    pub plugins: PluginPolicy,
+    /// Background warnings about memory and CPU pressure.
+    #[serde(default)]
+    pub watchdog: WatchdogConfig,
+    /// Outbound proxy for provider and download traffic.
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+    /// Extra trust roots and client certificates for HTTPS.
+    #[serde(default)]
+    pub tls: TlsConfig,
+    /// Circuit breaker, retry and timeout tuning for provider calls.
+    #[serde(default)]
+    pub resilience: ResilienceConfig,
+    /// Secret masking applied to AI responses and recorded memory.
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    /// Highest-precedence file this config was loaded from; `save` writes here.
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+    /// The merged values as [`Config::load`] returned them, so saving can
+    /// write only what the caller changed.
+    #[serde(skip)]
+    loaded: Option<serde_json::Value>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            ai_provider: "ollama".to_string(),
+            ai_model: "llama3:70b".to_string(),
+            routing: HashMap::new(),
+            system_prompt: None,
+            ignore_patterns: Vec::new(),
+            endpoints: HashMap::new(),
+            trust_threshold: default_trust_threshold(),
+            push: PushConfig::default(),
+            notify: NotifyConfig::default(),
+            web: WebConfig::default(),
+            plugins: PluginPolicy::default(),
+            watchdog: WatchdogConfig::default(),
+            proxy: ProxyConfig::default(),
+            tls: TlsConfig::default(),
+            resilience: ResilienceConfig::default(),
+            redaction: RedactionConfig::default(),
+            source: None,
+            loaded: None,
+        }
+    }
+}
+
+fn default_trust_threshold() -> f64 {
+    0.7
+}
+
+/// `[push]` section: where mobile notifications are delivered. Every
+/// notification is also appended to the local notification log.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PushConfig {
+    /// URL that receives each notification as a JSON POST.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// Firebase project id for FCM delivery.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fcm_project: Option<String>,
+    /// FCM registration token of the device to notify.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fcm_device_token: Option<String>,
+}
+
+impl PushConfig {
+    fn is_empty(&self) -> bool {
+        *self == PushConfig::default()
+    }
+}
+
+/// `[notify]` section: the webhook long-running commands such as
+/// `agent qa full-suite` POST to when they finish, unless
+/// `--notify-webhook` names another. `webhook_url` is only read from the
+/// global config.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NotifyConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// `json` (the default), `slack` or `discord`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<WebhookFormat>,
+}
+
+impl NotifyConfig {
+    fn is_empty(&self) -> bool {
+        *self == NotifyConfig::default()
+    }
+}
+
+/// `[web]` section: limits applied by `kandil web`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebConfig {
+    /// Chat requests each client IP may send per minute; 0 disables the limit.
+    pub chat_requests_per_minute: u32,
+    /// Largest accepted request body, in bytes.
+    pub max_body_bytes: usize,
+    /// Browser origins allowed to call the IDE server, e.g.
+    /// `vscode-webview://abc`; `*` allows any. Requests without an `Origin`
+    /// header (editors, curl) are allowed unless `ide_token` is set.
+    pub allowed_origins: Vec<String>,
+    /// Bearer token every IDE server request must carry. Without one the
+    /// server only listens on loopback addresses.
+    pub ide_token: Option<String>,
+}
+
+impl Default for WebConfig {
+    fn default() -> Self {
+        Self {
+            chat_requests_per_minute: 20,
+            max_body_bytes: 256 * 1024,
+            allowed_origins: Vec::new(),
+            ide_token: None,
+        }
+    }
+}
+
+/// `[plugins]` section: the most a plugin may use. A plugin's manifest can
+/// ask for less, never more.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginPolicy {
+    /// Wall-clock seconds before a plugin is killed.
+    pub max_timeout_secs: u64,
+    /// Memory cap in megabytes; 0 disables it.
+    pub max_memory_mb: u64,
+    /// Bytes kept from each of stdout and stderr; the rest is discarded.
+    pub max_output_bytes: usize,
+}
+
+impl Default for PluginPolicy {
+    fn default() -> Self {
+        Self {
+            max_timeout_secs: 60,
+            max_memory_mb: 512,
+            max_output_bytes: 1024 * 1024,
+        }
+    }
+}
+
+/// `[watchdog]` section: opt-in warnings when memory or CPU runs short during
+/// long operations.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchdogConfig {
+    pub enabled: bool,
+    /// Seconds between samples.
+    pub interval_secs: u64,
+    /// System memory use, in percent, that triggers a warning.
+    pub memory_usage_percent: f64,
+    /// CPU use, in percent, that triggers a warning once sustained.
+    pub cpu_usage_percent: f64,
+    /// Consecutive samples above `cpu_usage_percent` before warning.
+    pub cpu_sustained_samples: u32,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        let thresholds = AlertThresholds::default();
+        Self {
+            enabled: false,
+            interval_secs: 5,
+            memory_usage_percent: thresholds.memory_usage_percent,
+            cpu_usage_percent: thresholds.cpu_usage_percent,
+            cpu_sustained_samples: 3,
+        }
+    }
 }
 
+/// `[redaction]` section: what is masked in AI responses and recorded memory
+/// unless `--no-redact` is given. Known key formats are always covered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedactionConfig {
+    /// Extra regular expressions to mask. A named group `secret` limits the
+    /// mask to that part of the match. Patterns from every config layer apply.
+    pub patterns: Vec<String>,
+    /// Whether long random-looking strings are masked too.
+    pub high_entropy: bool,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            patterns: Vec::new(),
+            high_entropy: true,
+        }
+    }
+}
+
+/// `[proxy]` section: the proxy for outbound requests. When `url` is unset the
+/// standard `HTTPS_PROXY`/`HTTP_PROXY`/`NO_PROXY` variables are used.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    /// Proxy for both HTTP and HTTPS, e.g. `http://proxy.corp:3128`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Username for proxy authentication. The password is stored like an API
+    /// key, with `kandil config set-key proxy <password>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// Comma-separated hosts that skip the proxy; defaults to `NO_PROXY`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+}
+
+impl ProxyConfig {
+    fn is_empty(&self) -> bool {
+        *self == ProxyConfig::default()
+    }
+}
+
+/// `[tls]` section: certificates for gateways behind a private CA or that
+/// require mutual TLS.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// PEM files with extra root certificates to trust.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ca_certs: Vec<PathBuf>,
+    /// PEM client certificate (chain) presented for mutual TLS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert: Option<PathBuf>,
+    /// PKCS#8 PEM private key for `client_cert`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_key: Option<PathBuf>,
+    /// Turns off certificate verification. Anyone on the network path can
+    /// then read and alter requests, API keys included; for debugging only.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub danger_accept_invalid_certs: bool,
+}
+
+impl TlsConfig {
+    fn is_empty(&self) -> bool {
+        *self == TlsConfig::default()
+    }
+}
+
+/// `[resilience]` section: how provider calls cope with slow or failing
+/// backends. Each value can also be set with the environment variable listed
+/// in [`RESILIENCE_ENV`], which wins over every file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResilienceConfig {
+    /// Consecutive failures before the circuit breaker stops calling a provider.
+    pub circuit_threshold: u64,
+    /// Milliseconds the breaker stays open before letting a call through.
+    pub circuit_timeout_ms: u64,
+    /// Extra attempts after a transient failure (network, timeout, 429, 5xx).
+    pub retry_count: u32,
+    /// Delay before the first retry; it doubles on each further attempt.
+    pub retry_base_delay_ms: u64,
+    /// Whole-request timeout for non-streaming provider calls, in seconds.
+    pub request_timeout_secs: u64,
+    /// Connection timeout, in seconds.
+    pub connect_timeout_secs: u64,
+    /// How long a streamed reply may go without data, in seconds.
+    pub stream_idle_timeout_secs: u64,
+    /// How long hybrid mode waits for the local model before asking the
+    /// cloud provider instead, in milliseconds.
+    pub hybrid_latency_budget_ms: u64,
+}
+
+impl Default for ResilienceConfig {
+    fn default() -> Self {
+        Self {
+            circuit_threshold: 3,
+            circuit_timeout_ms: 2000,
+            retry_count: 2,
+            retry_base_delay_ms: 500,
+            // Generation can be slow on local hardware, so this is generous
+            request_timeout_secs: 600,
+            connect_timeout_secs: 10,
+            stream_idle_timeout_secs: 120,
+            hybrid_latency_budget_ms: 30_000,
+        }
+    }
+}
+
+impl ResilienceConfig {
+    fn apply(&mut self, section: &ResilienceSection) {
+        if let Some(threshold) = section.circuit_threshold {
+            self.circuit_threshold = threshold;
+        }
+        if let Some(ms) = section.circuit_timeout_ms {
+            self.circuit_timeout_ms = ms;
+        }
+        if let Some(count) = section.retry_count {
+            self.retry_count = count;
+        }
+        if let Some(ms) = section.retry_base_delay_ms {
+            self.retry_base_delay_ms = ms;
+        }
+        if let Some(secs) = section.request_timeout_secs {
+            self.request_timeout_secs = secs;
+        }
+        if let Some(secs) = section.connect_timeout_secs {
+            self.connect_timeout_secs = secs;
+        }
+        if let Some(secs) = section.stream_idle_timeout_secs {
+            self.stream_idle_timeout_secs = secs;
+        }
+        if let Some(ms) = section.hybrid_latency_budget_ms {
+            self.hybrid_latency_budget_ms = ms;
+        }
+    }
+}
+
+/// Environment variables overriding `[resilience]` keys.
+pub const RESILIENCE_ENV: &[(&str, &str)] = &[
+    ("circuit_threshold", "KANDIL_CIRCUIT_THRESHOLD"),
+    ("circuit_timeout_ms", "KANDIL_CIRCUIT_TIMEOUT_MS"),
+    ("retry_count", "KANDIL_RETRY_COUNT"),
+    ("retry_base_delay_ms", "KANDIL_RETRY_BASE_DELAY_MS"),
+    ("request_timeout_secs", "KANDIL_HTTP_TIMEOUT_SECS"),
+    ("connect_timeout_secs", "KANDIL_HTTP_CONNECT_TIMEOUT_SECS"),
+    ("stream_idle_timeout_secs", "KANDIL_HTTP_IDLE_TIMEOUT_SECS"),
+    ("hybrid_latency_budget_ms", "KANDIL_HYBRID_BUDGET_MS"),
+];
+
+/// An effective setting and the layer it came from.
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingSource {
+    pub key: String,
+    pub value: serde_json::Value,
+    /// `default`, a config file path, or `env KANDIL_...`
+    pub source: String,
+}
+
+/// On-disk config formats, detected by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ConfigFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "toml" => Some(ConfigFormat::Toml),
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            "json" => Some(ConfigFormat::Json),
+            _ => None,
+        }
+    }
+
+    fn parse(&self, contents: &str) -> Result<FileConfig> {
+        Ok(match self {
+            ConfigFormat::Toml => toml::from_str(contents)?,
+            ConfigFormat::Yaml => serde_yaml::from_str(contents)?,
+            ConfigFormat::Json => serde_json::from_str(contents)?,
+        })
+    }
+
+    fn render(&self, config: &FileConfig) -> Result<String> {
+        Ok(match self {
+            ConfigFormat::Toml => toml::to_string(config)?,
+            ConfigFormat::Yaml => serde_yaml::to_string(config)?,
+            ConfigFormat::Json => serde_json::to_string_pretty(config)?,
+        })
+    }
+}
+
+static CONFIG_PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Uses `path` as the only config file for the rest of the process; later
+/// calls are ignored.
+pub fn set_config_path(path: PathBuf) {
+    let _ = CONFIG_PATH.set(Some(path));
+}
+
+/// The config file from `--config`, falling back to `KANDIL_CONFIG`.
+pub fn config_path_override() -> Option<PathBuf> {
+    CONFIG_PATH
+        .get_or_init(|| {
+            std::env::var_os("KANDIL_CONFIG")
+                .filter(|value| !value.is_empty())
+                .map(PathBuf::from)
+        })
+        .clone()
+}
+
+/// Reads an explicitly chosen config file. A file that doesn't exist yet is
+/// an empty layer, so commands that save settings create it; unlike the
+/// default locations, an unknown format, unreadable or invalid file is an
+/// error.
+fn explicit_layer(path: &Path) -> Result<(PathBuf, FileConfig)> {
+    let format = ConfigFormat::from_path(path).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Config file {} must end in .toml, .yaml, .yml or .json",
+            path.display()
+        )
+    })?;
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok((path.to_path_buf(), FileConfig::default()));
+        }
+        Err(err) => {
+            return Err(err).with_context(|| format!("Failed to read {}", path.display()));
+        }
+    };
+    let fc = format
+        .parse(&contents)
+        .with_context(|| format!("Invalid config in {}", path.display()))?;
+    Ok((path.to_path_buf(), fc))
+}
+
+/// Extensions tried for each config location, in precedence order.
+const CONFIG_EXTENSIONS: [&str; 4] = ["toml", "yaml", "yml", "json"];
+
+/// Picks `<dir>/<stem>.<ext>` by precedence (toml > yaml > json), warning
+/// when more than one candidate exists.
+fn resolve_config_file(dir: &Path, stem: &str) -> Option<PathBuf> {
+    let found: Vec<PathBuf> = CONFIG_EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", stem, ext)))
+        .filter(|path| path.exists())
+        .collect();
+
+    if found.len() > 1 {
+        log::warn!(
+            "Multiple config files found in {}: {}; using {}",
+            dir.display(),
+            found
+                .iter()
+                .filter_map(|path| path.file_name())
+                .map(|name| name.to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            found[0].display()
+        );
+    }
+    found.into_iter().next()
+}
+
+/// Directory holding per-project settings, relative to the project root.
+pub const PROJECT_CONFIG_DIR: &str = ".kandil";
+
 impl Config {
+    /// Loads configuration with precedence (lowest first): built-in defaults,
+    /// the global user config, `kandil.*` in the working directory, the
+    /// nearest project `.kandil/config.*`, then `KANDIL_*` environment variables.
+    /// `[tls]`, `[proxy]`, `[notify] webhook_url` and `[web] ide_token` are
+    /// only read from the global config (or the file given with `--config`),
+    /// never from a project's files.
+    ///
+    /// Each location may use TOML, YAML or JSON. If several formats exist in
+    /// the same place, `.toml` wins over `.yaml`/`.yml`, which win over `.json`.
     pub fn load() -> Result<Self> {
-        let mut provider = "ollama".to_string();
-        let mut model = "llama3:70b".to_string();
-        let cfg_path = std::env::current_dir()?.join("kandil.toml");
-        if cfg_path.exists() {
-            let s = std::fs::read_to_string(&cfg_path)?;
-            if let Ok(fc) = toml::from_str::<FileConfig>(&s) {
-                if let Some(ai) = fc.ai {
-                    if !ai.provider.is_empty() {
-                        provider = ai.provider;
-                    }
-                    if !ai.model.is_empty() {
-                        model = ai.model;
-                    }
-                }
+        let mut config = Config::default();
+
+        let global = Self::global_path();
+        let explicit = config_path_override().is_some();
+        for (path, mut fc) in Self::file_layers()? {
+            if !explicit && global.as_ref() != Some(&path) {
+                fc.drop_user_only(&path);
             }
+            config.merge(fc);
+            config.source = Some(path);
         }
+
         if let Ok(p) = std::env::var("KANDIL_AI_PROVIDER") {
-            provider = p;
+            config.ai_provider = p;
         }
         if let Ok(m) = std::env::var("KANDIL_AI_MODEL") {
-            model = m;
+            config.ai_model = m;
         }
-        Ok(Config {
-            ai_provider: provider,
-            ai_model: model,
-        })
+        if let Some(limit) = std::env::var("KANDIL_WEB_CHAT_RATE_LIMIT")
+            .ok()
+            .and_then(|value| value.parse().ok())
+        {
+            config.web.chat_requests_per_minute = limit;
+        }
+        if let Some(bytes) = std::env::var("KANDIL_WEB_MAX_BODY_BYTES")
+            .ok()
+            .and_then(|value| value.parse().ok())
+        {
+            config.web.max_body_bytes = bytes;
+        }
+        if let Ok(url) = std::env::var("KANDIL_NOTIFY_WEBHOOK") {
+            config.notify.webhook_url = Some(url).filter(|url| !url.is_empty());
+        }
+        if let Ok(token) = std::env::var("KANDIL_IDE_TOKEN") {
+            config.web.ide_token = Some(token).filter(|token| !token.is_empty());
+        }
+        if let Ok(origins) = std::env::var("KANDIL_WEB_ALLOWED_ORIGINS") {
+            config.web.allowed_origins = origins
+                .split(',')
+                .map(|origin| origin.trim().to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect();
+        }
+        if let Some(secs) = std::env::var("KANDIL_PLUGIN_TIMEOUT_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+        {
+            config.plugins.max_timeout_secs = secs;
+        }
+        if let Some(mb) = std::env::var("KANDIL_PLUGIN_MEMORY_MB")
+            .ok()
+            .and_then(|value| value.parse().ok())
+        {
+            config.plugins.max_memory_mb = mb;
+        }
+        if let Some(bytes) = std::env::var("KANDIL_PLUGIN_MAX_OUTPUT_BYTES")
+            .ok()
+            .and_then(|value| value.parse().ok())
+        {
+            config.plugins.max_output_bytes = bytes;
+        }
+        if let Ok(value) = std::env::var("KANDIL_WATCHDOG") {
+            config.watchdog.enabled = matches!(value.as_str(), "1" | "true");
+        }
+        if let Some(secs) = std::env::var("KANDIL_WATCHDOG_INTERVAL_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+        {
+            config.watchdog.interval_secs = secs;
+        }
+        let resilience_env = ResilienceSection::from_env(|name| std::env::var(name).ok());
+        config.resilience.apply(&resilience_env);
+        config.loaded = Some(serde_json::to_value(FileConfig::from(&config))?);
+        if let Err(warning) = config.validate_model() {
+            warn_once(
+                format!("model {}/{}", config.ai_provider, config.ai_model),
+                warning,
+            );
+        }
+        Ok(config)
+    }
+
+    /// The config files that exist, lowest precedence first, with their
+    /// parsed contents. Files that fail to parse are skipped with a warning.
+    fn file_layers() -> Result<Vec<(PathBuf, FileConfig)>> {
+        if let Some(path) = config_path_override() {
+            return Ok(vec![explicit_layer(&path)?]);
+        }
+        let mut layers = Vec::new();
+        for path in Self::files()? {
+            let format = match ConfigFormat::from_path(&path) {
+                Some(format) => format,
+                None => continue,
+            };
+            let s = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            match format.parse(&s) {
+                Ok(fc) => layers.push((path, fc)),
+                Err(err) => {
+                    let message =
+                        format!("Ignoring invalid config in {}: {:#}", path.display(), err);
+                    warn_once(message.clone(), message);
+                }
+            }
+        }
+        Ok(layers)
+    }
+
+    /// The config files that exist and are read, lowest precedence first.
+    pub fn files() -> Result<Vec<PathBuf>> {
+        if let Some(path) = config_path_override() {
+            return Ok(path.is_file().then_some(path).into_iter().collect());
+        }
+        let mut paths = Vec::new();
+        if let Some(global) = Self::global_path() {
+            paths.push(global);
+        }
+        if let Some(local) = resolve_config_file(&std::env::current_dir()?, "kandil") {
+            paths.push(local);
+        }
+        if let Some(project) = Self::project_path()? {
+            paths.push(project);
+        }
+        Ok(paths)
+    }
+
+    /// Effective `[resilience]` values, each with the layer that set it.
+    pub fn resilience_sources(&self) -> Result<Vec<SettingSource>> {
+        let mut sources: HashMap<String, String> = HashMap::new();
+        for (path, fc) in Self::file_layers()? {
+            if let Some(section) = fc.resilience {
+                for key in section.keys() {
+                    sources.insert(key, path.display().to_string());
+                }
+            }
+        }
+        let env = ResilienceSection::from_env(|name| std::env::var(name).ok());
+        for key in env.keys() {
+            if let Some((_, var)) = RESILIENCE_ENV.iter().find(|(name, _)| *name == key) {
+                sources.insert(key, format!("env {}", var));
+            }
+        }
+
+        let values = serde_json::to_value(&self.resilience)?;
+        Ok(RESILIENCE_ENV
+            .iter()
+            .map(|(key, _)| SettingSource {
+                key: key.to_string(),
+                value: values[*key].clone(),
+                source: sources
+                    .remove(*key)
+                    .unwrap_or_else(|| "default".to_string()),
+            })
+            .collect())
+    }
+
+    /// Checks `ai_model` against the model registry, so a typo or a model
+    /// registered for another provider is reported instead of silently
+    /// routed as an unknown fallback.
+    pub fn validate_model(&self) -> std::result::Result<Option<ModelProfile>, SelectionWarning> {
+        UniversalModelRegistry::global().validate_selection(&self.ai_provider, &self.ai_model)
     }
 
+    /// Resolves a one-off provider and model choice against this config. The
+    /// provider must be supported, and a model named explicitly must pass
+    /// [`UniversalModelRegistry::validate_selection`] for that provider;
+    /// omitted parts come from the config.
+    pub fn resolve_selection(
+        &self,
+        provider: Option<&str>,
+        model: Option<&str>,
+    ) -> std::result::Result<(String, String), String> {
+        let provider = provider.unwrap_or(&self.ai_provider).to_string();
+        if !backend::is_registered(&provider) {
+            return Err(format!(
+                "Unknown provider '{}'. Supported: {}",
+                provider,
+                backend::names().join(", ")
+            ));
+        }
+
+        let model = match model {
+            Some(model) => {
+                UniversalModelRegistry::global()
+                    .validate_selection(&provider, model)
+                    .map_err(|warning| warning.to_string())?;
+                model.to_string()
+            }
+            None => self.ai_model.clone(),
+        };
+        Ok((provider, model))
+    }
+
+    /// User-wide configuration file, e.g. `~/.config/kandil/kandil.toml`.
+    pub fn global_path() -> Option<PathBuf> {
+        dirs::config_dir().and_then(|dir| resolve_config_file(&dir.join("kandil"), "kandil"))
+    }
+
+    /// Finds the nearest `.kandil/config.*` in the working directory or its parents.
+    pub fn project_path() -> Result<Option<PathBuf>> {
+        let cwd = std::env::current_dir()?;
+        Ok(cwd
+            .ancestors()
+            .find_map(|dir| resolve_config_file(&dir.join(PROJECT_CONFIG_DIR), "config")))
+    }
+
+    fn merge(&mut self, fc: FileConfig) {
+        if let Some(ai) = fc.ai {
+            if !ai.provider.is_empty() {
+                self.ai_provider = ai.provider;
+            }
+            if !ai.model.is_empty() {
+                self.ai_model = ai.model;
+            }
+        }
+        self.routing.extend(fc.routing);
+        if let Some(prompt) = fc.prompt {
+            if !prompt.system.trim().is_empty() {
+                self.system_prompt = Some(prompt.system);
+            }
+        }
+        if let Some(ignore) = fc.ignore {
+            self.ignore_patterns = ignore.patterns;
+        }
+        self.endpoints.extend(fc.endpoints);
+        if let Some(trust) = fc.trust {
+            self.trust_threshold = trust.threshold;
+        }
+        if let Some(push) = fc.push {
+            if push.webhook_url.is_some() {
+                self.push.webhook_url = push.webhook_url;
+            }
+            if push.fcm_project.is_some() {
+                self.push.fcm_project = push.fcm_project;
+            }
+            if push.fcm_device_token.is_some() {
+                self.push.fcm_device_token = push.fcm_device_token;
+            }
+        }
+        if let Some(notify) = fc.notify {
+            if notify.webhook_url.is_some() {
+                self.notify.webhook_url = notify.webhook_url;
+            }
+            if notify.format.is_some() {
+                self.notify.format = notify.format;
+            }
+        }
+        if let Some(web) = fc.web {
+            if let Some(limit) = web.chat_requests_per_minute {
+                self.web.chat_requests_per_minute = limit;
+            }
+            if let Some(bytes) = web.max_body_bytes {
+                self.web.max_body_bytes = bytes;
+            }
+            if let Some(origins) = web.allowed_origins {
+                self.web.allowed_origins = origins;
+            }
+            if web.ide_token.is_some() {
+                self.web.ide_token = web.ide_token;
+            }
+        }
+        if let Some(plugins) = fc.plugins {
+            if let Some(secs) = plugins.max_timeout_secs {
+                self.plugins.max_timeout_secs = secs;
+            }
+            if let Some(mb) = plugins.max_memory_mb {
+                self.plugins.max_memory_mb = mb;
+            }
+            if let Some(bytes) = plugins.max_output_bytes {
+                self.plugins.max_output_bytes = bytes;
+            }
+        }
+        if let Some(watchdog) = fc.watchdog {
+            if let Some(enabled) = watchdog.enabled {
+                self.watchdog.enabled = enabled;
+            }
+            if let Some(secs) = watchdog.interval_secs {
+                self.watchdog.interval_secs = secs;
+            }
+            if let Some(percent) = watchdog.memory_usage_percent {
+                self.watchdog.memory_usage_percent = percent;
+            }
+            if let Some(percent) = watchdog.cpu_usage_percent {
+                self.watchdog.cpu_usage_percent = percent;
+            }
+            if let Some(samples) = watchdog.cpu_sustained_samples {
+                self.watchdog.cpu_sustained_samples = samples;
+            }
+        }
+        if let Some(proxy) = fc.proxy {
+            if proxy.url.is_some() {
+                self.proxy.url = proxy.url;
+            }
+            if proxy.username.is_some() {
+                self.proxy.username = proxy.username;
+            }
+            if proxy.no_proxy.is_some() {
+                self.proxy.no_proxy = proxy.no_proxy;
+            }
+        }
+        if let Some(tls) = fc.tls {
+            if !tls.ca_certs.is_empty() {
+                self.tls.ca_certs = tls.ca_certs;
+            }
+            if tls.client_cert.is_some() {
+                self.tls.client_cert = tls.client_cert;
+            }
+            if tls.client_key.is_some() {
+                self.tls.client_key = tls.client_key;
+            }
+            if tls.danger_accept_invalid_certs {
+                self.tls.danger_accept_invalid_certs = true;
+            }
+        }
+        if let Some(resilience) = fc.resilience {
+            self.resilience.apply(&resilience);
+        }
+        if let Some(redaction) = fc.redaction {
+            self.redaction.patterns.extend(redaction.patterns);
+            if let Some(high_entropy) = redaction.high_entropy {
+                self.redaction.high_entropy = high_entropy;
+            }
+        }
+    }
+
+    /// Where a new user-wide config is created, e.g. `~/.config/kandil/kandil.toml`.
+    pub fn default_global_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("kandil").join("kandil.toml"))
+    }
+
+    /// Writes the config back to the file it was loaded from, in that file's
+    /// format, or else to the `--config` file or `kandil.toml` in the working
+    /// directory. See [`Config::save_to`] for what is written.
     pub fn save(&self) -> Result<()> {
-        let cfg_path = std::env::current_dir()?.join("kandil.toml");
-        let mut fc = FileConfig::default();
-        fc.ai = Some(AISection {
-            provider: self.ai_provider.clone(),
-            model: self.ai_model.clone(),
-        });
-        let s = toml::to_string(&fc)?;
-        std::fs::write(cfg_path, s)?;
+        let cfg_path = match self.source.clone().or_else(config_path_override) {
+            Some(path) => path,
+            None => std::env::current_dir()?.join("kandil.toml"),
+        };
+        self.save_to(&cfg_path)
+    }
+
+    /// Writes the config to `path` in the format its extension names.
+    ///
+    /// For a config from [`Config::load`], only the values changed since
+    /// loading are written over what `path` already holds, so settings from
+    /// other layers and `KANDIL_*` variables are not copied into it.
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.render_update(path)?)?;
         Ok(())
     }
 
+    /// Serializes the whole merged config in the format `path` names.
+    pub fn render(&self, path: &Path) -> Result<String> {
+        let format = ConfigFormat::from_path(path).unwrap_or(ConfigFormat::Toml);
+        format.render(&FileConfig::from(self))
+    }
+
+    /// The contents [`Config::save_to`] would write to `path`.
+    pub fn render_update(&self, path: &Path) -> Result<String> {
+        let Some(loaded) = &self.loaded else {
+            return self.render(path);
+        };
+        let format = ConfigFormat::from_path(path).unwrap_or(ConfigFormat::Toml);
+        let mut owned = if path.is_file() {
+            let contents = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let fc = format
+                .parse(&contents)
+                .with_context(|| format!("Invalid config in {}", path.display()))?;
+            serde_json::to_value(fc)?
+        } else {
+            serde_json::Value::Object(serde_json::Map::new())
+        };
+        copy_changes(
+            &mut owned,
+            loaded,
+            &serde_json::to_value(FileConfig::from(self))?,
+        );
+        format.render(&serde_json::from_value(owned)?)
+    }
+
+    /// Writes `.kandil/config.toml` under `root` and returns its path.
+    pub fn write_project(&self, root: &Path) -> Result<PathBuf> {
+        let dir = root.join(PROJECT_CONFIG_DIR);
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("config.toml");
+        let body = toml::to_string(&FileConfig::from(self))?;
+        std::fs::write(
+            &path,
+            format!(
+                "# Kandil project settings. These override your global config;\n# KANDIL_* environment variables still take precedence.\n\n{}",
+                body
+            ),
+        )?;
+        Ok(path)
+    }
+
+    /// Returns `true` if any path component matches an ignore pattern.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        path.components().any(|component| {
+            let name = component.as_os_str().to_string_lossy();
+            self.ignore_patterns
+                .iter()
+                .any(|pattern| wildcard_match(pattern.trim_end_matches('/'), &name))
+        })
+    }
+
     pub async fn validate_production(&self) -> Result<()> {
         if self.ai_model.trim().is_empty() {
             anyhow::bail!("AI model must be set for production");
@@ -176,22 +1032,327 @@ impl AiProvider {
     }
 }
 
+/// Copies into `target` every value that differs between `before` and
+/// `after`, descending into tables so unchanged keys keep their value.
+fn copy_changes(
+    target: &mut serde_json::Value,
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+) {
+    use serde_json::{Map, Value};
+
+    let Value::Object(after) = after else {
+        *target = after.clone();
+        return;
+    };
+    let empty = Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(target) = target else {
+        return;
+    };
+
+    let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    for key in keys {
+        let old = before.get(key).unwrap_or(&Value::Null);
+        let new = after.get(key).unwrap_or(&Value::Null);
+        if old == new {
+            continue;
+        }
+        if new.is_null() {
+            target.remove(key);
+        } else {
+            copy_changes(target.entry(key.clone()).or_insert(Value::Null), old, new);
+        }
+    }
+}
+
+/// Logs `message` the first time `key` is seen in this process. Commands
+/// load the config several times, which would otherwise repeat warnings.
+fn warn_once(key: String, message: impl std::fmt::Display) {
+    static SEEN: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    let first = SEEN
+        .get_or_init(Default::default)
+        .lock()
+        .map(|mut seen| seen.insert(key))
+        .unwrap_or(true);
+    if first {
+        log::warn!("{}", message);
+    }
+}
+
 fn is_placeholder(s: &str) -> bool {
     let lowered = s.to_lowercase();
     lowered.contains("your-") || lowered.contains("example") || lowered.contains("placeholder")
 }
 
+/// Matches `text` against a pattern where `*` stands for any run of characters.
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let mut rest = text;
+    for (i, part) in parts.iter().enumerate() {
+        if i == 0 {
+            match rest.strip_prefix(part) {
+                Some(tail) => rest = tail,
+                None => return false,
+            }
+        } else if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        } else if let Some(pos) = rest.find(part) {
+            rest = &rest[pos + part.len()..];
+        } else {
+            return false;
+        }
+    }
+    true
+}
+
 #[derive(Serialize, Deserialize, Default)]
 struct FileConfig {
     ai: Option<AISection>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    routing: HashMap<String, String>,
+    prompt: Option<PromptSection>,
+    ignore: Option<IgnoreSection>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    endpoints: HashMap<String, String>,
+    trust: Option<TrustSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    push: Option<PushConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    notify: Option<NotifyConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    web: Option<WebSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    plugins: Option<PluginsSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    watchdog: Option<WatchdogSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    proxy: Option<ProxyConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tls: Option<TlsConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    resilience: Option<ResilienceSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    redaction: Option<RedactionSection>,
+}
+
+impl FileConfig {
+    /// Removes the settings a project file may not set, warning about each.
+    /// `[tls]` and `[proxy]` decide which certificates are trusted and where
+    /// requests (API keys included) are sent, `[notify] webhook_url`
+    /// receives command output, and `[web] ide_token` lets the IDE server
+    /// listen beyond loopback, so a cloned repository must not be able to
+    /// change them.
+    fn drop_user_only(&mut self, path: &Path) {
+        let mut dropped = Vec::new();
+        if self.tls.take().is_some() {
+            dropped.push("[tls]");
+        }
+        if self.proxy.take().is_some() {
+            dropped.push("[proxy]");
+        }
+        if let Some(notify) = self.notify.as_mut() {
+            if notify.webhook_url.take().is_some() {
+                dropped.push("[notify] webhook_url");
+            }
+        }
+        if let Some(web) = self.web.as_mut() {
+            if web.ide_token.take().is_some() {
+                dropped.push("[web] ide_token");
+            }
+        }
+        if !dropped.is_empty() {
+            let message = format!(
+                "Ignoring {} in {}; set {} in the global config instead",
+                dropped.join(" and "),
+                path.display(),
+                if dropped.len() == 1 { "it" } else { "them" }
+            );
+            warn_once(message.clone(), message);
+        }
+    }
+}
+
+impl From<&Config> for FileConfig {
+    fn from(config: &Config) -> Self {
+        FileConfig {
+            ai: Some(AISection {
+                provider: config.ai_provider.clone(),
+                model: config.ai_model.clone(),
+            }),
+            routing: config.routing.clone(),
+            prompt: config.system_prompt.clone().map(|system| PromptSection { system }),
+            ignore: Some(IgnoreSection {
+                patterns: config.ignore_patterns.clone(),
+            }),
+            endpoints: config.endpoints.clone(),
+            trust: Some(TrustSection {
+                threshold: config.trust_threshold,
+            }),
+            push: Some(config.push.clone()).filter(|push| !push.is_empty()),
+            notify: Some(config.notify.clone()).filter(|notify| !notify.is_empty()),
+            web: (config.web != WebConfig::default()).then(|| WebSection {
+                chat_requests_per_minute: Some(config.web.chat_requests_per_minute),
+                max_body_bytes: Some(config.web.max_body_bytes),
+                allowed_origins: Some(config.web.allowed_origins.clone())
+                    .filter(|origins| !origins.is_empty()),
+                ide_token: config.web.ide_token.clone(),
+            }),
+            plugins: (config.plugins != PluginPolicy::default()).then(|| PluginsSection {
+                max_timeout_secs: Some(config.plugins.max_timeout_secs),
+                max_memory_mb: Some(config.plugins.max_memory_mb),
+                max_output_bytes: Some(config.plugins.max_output_bytes),
+            }),
+            watchdog: (config.watchdog != WatchdogConfig::default()).then(|| WatchdogSection {
+                enabled: Some(config.watchdog.enabled),
+                interval_secs: Some(config.watchdog.interval_secs),
+                memory_usage_percent: Some(config.watchdog.memory_usage_percent),
+                cpu_usage_percent: Some(config.watchdog.cpu_usage_percent),
+                cpu_sustained_samples: Some(config.watchdog.cpu_sustained_samples),
+            }),
+            proxy: Some(config.proxy.clone()).filter(|proxy| !proxy.is_empty()),
+            tls: Some(config.tls.clone()).filter(|tls| !tls.is_empty()),
+            resilience: (config.resilience != ResilienceConfig::default()).then(|| {
+                let resilience = &config.resilience;
+                ResilienceSection {
+                    circuit_threshold: Some(resilience.circuit_threshold),
+                    circuit_timeout_ms: Some(resilience.circuit_timeout_ms),
+                    retry_count: Some(resilience.retry_count),
+                    retry_base_delay_ms: Some(resilience.retry_base_delay_ms),
+                    request_timeout_secs: Some(resilience.request_timeout_secs),
+                    connect_timeout_secs: Some(resilience.connect_timeout_secs),
+                    stream_idle_timeout_secs: Some(resilience.stream_idle_timeout_secs),
+                    hybrid_latency_budget_ms: Some(resilience.hybrid_latency_budget_ms),
+                }
+            }),
+            redaction: (config.redaction != RedactionConfig::default()).then(|| {
+                RedactionSection {
+                    patterns: config.redaction.patterns.clone(),
+                    high_entropy: Some(config.redaction.high_entropy),
+                }
+            }),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Default)]
 struct AISection {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     provider: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     model: String,
 }
 
+#[derive(Serialize, Deserialize, Default)]
+struct PromptSection {
+    #[serde(default)]
+    system: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct IgnoreSection {
+    #[serde(default)]
+    patterns: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TrustSection {
+    #[serde(default = "default_trust_threshold")]
+    threshold: f64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct WebSection {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chat_requests_per_minute: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_body_bytes: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    allowed_origins: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ide_token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct PluginsSection {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_memory_mb: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_output_bytes: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct WatchdogSection {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    interval_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    memory_usage_percent: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cpu_usage_percent: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cpu_sustained_samples: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct RedactionSection {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    patterns: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    high_entropy: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct ResilienceSection {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    circuit_threshold: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    circuit_timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retry_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retry_base_delay_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    connect_timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stream_idle_timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hybrid_latency_budget_ms: Option<u64>,
+}
+
+impl ResilienceSection {
+    /// Values from [`RESILIENCE_ENV`]; unparsable values are ignored.
+    fn from_env(env: impl Fn(&str) -> Option<String>) -> Self {
+        let mut values = serde_json::Map::new();
+        for (key, var) in RESILIENCE_ENV {
+            if let Some(value) = env(var).and_then(|value| value.trim().parse::<u32>().ok()) {
+                values.insert(key.to_string(), value.into());
+            }
+        }
+        serde_json::from_value(serde_json::Value::Object(values)).unwrap_or_default()
+    }
+
+    /// Keys this section sets.
+    fn keys(&self) -> Vec<String> {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(values)) => values.keys().cloned().collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -201,6 +1362,7 @@ mod tests {
         let cfg = Config {
             ai_provider: "ollama".to_string(),
             ai_model: "llama3:8b".to_string(),
+            ..Default::default()
         };
         assert!(cfg.validate_production().await.is_ok());
     }
@@ -210,8 +1372,189 @@ mod tests {
         let cfg = Config {
             ai_provider: "unknown".to_string(),
             ai_model: "x".to_string(),
+            ..Default::default()
         };
         let err = cfg.validate_production().await.unwrap_err();
         assert!(format!("{}", err).contains("Unsupported AI provider"));
     }
+
+    #[test]
+    fn project_layer_overrides_model_and_adds_routing() {
+        let mut cfg = Config::default();
+        let fc: FileConfig = toml::from_str(
+            "[ai]\nmodel = \"qwen2.5-coder-7b-q4\"\n\n[routing]\ncoding = \"qwen2.5-coder-14b-q4\"\n\n[ignore]\npatterns = [\"target\", \"*.lock\"]\n",
+        )
+        .unwrap();
+        cfg.merge(fc);
+        assert_eq!(cfg.ai_provider, "ollama");
+        assert_eq!(cfg.ai_model, "qwen2.5-coder-7b-q4");
+        assert_eq!(cfg.routing.get("coding").unwrap(), "qwen2.5-coder-14b-q4");
+        assert!(cfg.is_ignored(Path::new("target/debug/main.rs")));
+        assert!(cfg.is_ignored(Path::new("Cargo.lock")));
+        assert!(!cfg.is_ignored(Path::new("src/main.rs")));
+    }
+
+    #[test]
+    fn project_files_cannot_set_tls_proxy_or_webhook() {
+        let mut fc: FileConfig = toml::from_str(
+            "[ai]\nmodel = \"llama3:8b\"\n\n[tls]\ndanger_accept_invalid_certs = true\n\n[proxy]\nurl = \"http://evil.example:3128\"\n\n[notify]\nwebhook_url = \"https://evil.example/hook\"\nformat = \"slack\"\n\n[web]\nide_token = \"known\"\nmax_body_bytes = 1024\n",
+        )
+        .unwrap();
+        fc.drop_user_only(Path::new(".kandil/config.toml"));
+        let mut cfg = Config::default();
+        cfg.merge(fc);
+        assert_eq!(cfg.ai_model, "llama3:8b");
+        assert!(!cfg.tls.danger_accept_invalid_certs);
+        assert_eq!(cfg.proxy, ProxyConfig::default());
+        assert_eq!(cfg.notify.webhook_url, None);
+        assert_eq!(cfg.notify.format, Some(WebhookFormat::Slack));
+        assert_eq!(cfg.web.ide_token, None);
+        assert_eq!(cfg.web.max_body_bytes, 1024);
+    }
+
+    #[test]
+    fn yaml_and_json_layers_parse() {
+        let yaml = ConfigFormat::Yaml
+            .parse("ai:\n  provider: openai\n  model: gpt-4o\n")
+            .unwrap();
+        let json = ConfigFormat::Json
+            .parse(r#"{"ai": {"model": "llama3:8b"}, "prompt": {"system": "Be brief"}}"#)
+            .unwrap();
+
+        let mut cfg = Config::default();
+        cfg.merge(yaml);
+        cfg.merge(json);
+        assert_eq!(cfg.ai_provider, "openai");
+        assert_eq!(cfg.ai_model, "llama3:8b");
+        assert_eq!(cfg.system_prompt.as_deref(), Some("Be brief"));
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config.yml")),
+            Some(ConfigFormat::Yaml)
+        );
+    }
+
+    #[test]
+    fn explicit_config_files_may_be_missing_but_must_parse() {
+        let dir = std::env::temp_dir().join(format!("kandil_config_{}", uuid::Uuid::new_v4()));
+        let path = dir.join("profile.yaml");
+        let (source, fc) = explicit_layer(&path).unwrap();
+        assert_eq!(source, path);
+        assert!(fc.ai.is_none());
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, "ai:\n  provider: openai\n  model: gpt-4o\n").unwrap();
+        let (source, fc) = explicit_layer(&path).unwrap();
+        assert_eq!(source, path);
+        assert_eq!(fc.ai.unwrap().model, "gpt-4o");
+
+        std::fs::write(&path, "ai: [").unwrap();
+        assert!(explicit_layer(&path).is_err());
+        let ini = dir.join("profile.ini");
+        std::fs::write(&ini, "").unwrap();
+        assert!(explicit_layer(&ini).unwrap_err().to_string().contains(".toml"));
+
+        let saved = dir.join("nested/profile.toml");
+        Config::default().save_to(&saved).unwrap();
+        assert!(saved.is_file());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn saving_writes_only_changed_values_into_the_target_file() {
+        let dir = std::env::temp_dir().join(format!("kandil_config_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let project = dir.join("config.toml");
+        let read = |path: &Path| {
+            let contents = std::fs::read_to_string(path).unwrap();
+            (ConfigFormat::Toml.parse(&contents).unwrap(), contents)
+        };
+        let global =
+            "[endpoints]\nollama = \"http://gpu-box:11434\"\n\n[resilience]\nretry_count = 5\n";
+        std::fs::write(
+            &project,
+            "[ai]\nmodel = \"qwen2.5-coder-7b-q4\"\n\n[routing]\ncoding = \"qwen2.5-coder-14b-q4\"\n",
+        )
+        .unwrap();
+
+        // A global layer, the project layer and a KANDIL_AI_PROVIDER override.
+        let mut cfg = Config::default();
+        cfg.merge(toml::from_str(global).unwrap());
+        cfg.merge(read(&project).0);
+        cfg.ai_provider = "lmstudio".to_string();
+        cfg.loaded = Some(serde_json::to_value(FileConfig::from(&cfg)).unwrap());
+
+        cfg.ai_model = "qwen2.5-coder-3b-q4".to_string();
+        let fast = "qwen2.5-coder-1.5b-q4".to_string();
+        cfg.routing.insert("testing".to_string(), fast);
+        cfg.save_to(&project).unwrap();
+
+        let (fc, contents) = read(&project);
+        let ai = fc.ai.unwrap();
+        assert_eq!(ai.model, "qwen2.5-coder-3b-q4");
+        assert_eq!(ai.provider, "");
+        assert_eq!(fc.routing.len(), 2);
+        assert_eq!(fc.routing["coding"], "qwen2.5-coder-14b-q4");
+        assert!(fc.endpoints.is_empty());
+        assert!(fc.resilience.is_none());
+        assert!(!contents.contains("lmstudio"));
+
+        // A config built in code has no baseline, so all of it is written.
+        Config::default().save_to(&project).unwrap();
+        assert_eq!(read(&project).0.ai.unwrap().provider, "ollama");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn proxy_section_round_trips() {
+        let mut cfg = Config::default();
+        cfg.merge(
+            toml::from_str("[proxy]\nurl = \"http://proxy.corp:3128\"\nusername = \"alice\"\n")
+                .unwrap(),
+        );
+        assert_eq!(cfg.proxy.url.as_deref(), Some("http://proxy.corp:3128"));
+        assert_eq!(cfg.proxy.username.as_deref(), Some("alice"));
+        assert_eq!(cfg.proxy.no_proxy, None);
+
+        let written = toml::to_string(&FileConfig::from(&cfg)).unwrap();
+        assert!(written.contains("[proxy]"));
+        assert!(!toml::to_string(&FileConfig::from(&Config::default()))
+            .unwrap()
+            .contains("[proxy]"));
+    }
+
+    #[test]
+    fn notify_section_round_trips() {
+        let mut cfg = Config::default();
+        let file = "[notify]\nwebhook_url = \"https://hooks.slack.com/x\"\nformat = \"slack\"\n";
+        cfg.merge(toml::from_str(file).unwrap());
+        assert_eq!(cfg.notify.webhook_url.as_deref(), Some("https://hooks.slack.com/x"));
+        assert_eq!(cfg.notify.format, Some(WebhookFormat::Slack));
+
+        let written = toml::to_string(&FileConfig::from(&cfg)).unwrap();
+        assert!(written.contains("[notify]"));
+        assert!(written.contains("format = \"slack\""));
+    }
+
+    #[test]
+    fn resilience_files_merge_and_env_wins() {
+        let mut cfg = Config::default();
+        let file = "[resilience]\nretry_count = 5\ncircuit_threshold = 9\n";
+        cfg.merge(toml::from_str(file).unwrap());
+        assert_eq!(cfg.resilience.retry_count, 5);
+        assert_eq!(cfg.resilience.circuit_threshold, 9);
+        assert_eq!(cfg.resilience.request_timeout_secs, 600);
+
+        let env = ResilienceSection::from_env(|name| match name {
+            "KANDIL_CIRCUIT_THRESHOLD" => Some("4".to_string()),
+            "KANDIL_RETRY_COUNT" => Some("lots".to_string()),
+            _ => None,
+        });
+        assert_eq!(env.keys(), ["circuit_threshold"]);
+        cfg.resilience.apply(&env);
+        assert_eq!(cfg.resilience.circuit_threshold, 4);
+        assert_eq!(cfg.resilience.retry_count, 5);
+
+        let written = toml::to_string(&FileConfig::from(&cfg)).unwrap();
+        assert!(written.contains("[resilience]"));
+    }
 }
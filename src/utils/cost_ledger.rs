@@ -0,0 +1,204 @@
+//! Cost reports
+//!
+//! The cost of each chat reply is stored with the turn in the project memory
+//! database (the `cost_usd` column), next to the provider, the model and the
+//! estimated token counts. `kandil config costs` reads those turns back as
+//! [`CostEntry`] rows and shows them as a table, JSON or CSV, per call or
+//! totalled by provider and month.
+
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// CSV header of [`write_csv`], in column order.
+pub const ENTRY_COLUMNS: [&str; 7] = [
+    "timestamp",
+    "provider",
+    "model",
+    "prompt_tokens",
+    "completion_tokens",
+    "cost_usd",
+    "project",
+];
+
+/// CSV header of [`write_summary_csv`], in column order.
+pub const SUMMARY_COLUMNS: [&str; 6] = [
+    "month",
+    "provider",
+    "calls",
+    "prompt_tokens",
+    "completion_tokens",
+    "cost_usd",
+];
+
+/// One recorded call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CostEntry {
+    pub timestamp: DateTime<Utc>,
+    pub provider: String,
+    pub model: String,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub cost_usd: f64,
+    /// Name of the project the call was made in.
+    pub project: String,
+}
+
+/// Calls and spend of one provider in one calendar month (UTC).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostSummary {
+    /// `YYYY-MM`
+    pub month: String,
+    pub provider: String,
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Totals per provider and month, ordered by month then provider.
+pub fn summarize(entries: &[CostEntry]) -> Vec<CostSummary> {
+    let mut summaries: Vec<CostSummary> = Vec::new();
+    for entry in entries {
+        let month = entry.timestamp.format("%Y-%m").to_string();
+        let index = match summaries
+            .iter()
+            .position(|summary| summary.month == month && summary.provider == entry.provider)
+        {
+            Some(index) => index,
+            None => {
+                summaries.push(CostSummary {
+                    month,
+                    provider: entry.provider.clone(),
+                    calls: 0,
+                    prompt_tokens: 0,
+                    completion_tokens: 0,
+                    cost_usd: 0.0,
+                });
+                summaries.len() - 1
+            }
+        };
+        let summary = &mut summaries[index];
+        summary.calls += 1;
+        summary.prompt_tokens += entry.prompt_tokens as u64;
+        summary.completion_tokens += entry.completion_tokens as u64;
+        summary.cost_usd += entry.cost_usd;
+    }
+    summaries.sort_by(|a, b| (&a.month, &a.provider).cmp(&(&b.month, &b.provider)));
+    summaries
+}
+
+/// Writes one row per call, with the [`ENTRY_COLUMNS`] header.
+pub fn write_csv(entries: &[CostEntry], mut out: impl Write) -> Result<()> {
+    write_row(&mut out, &ENTRY_COLUMNS.map(String::from))?;
+    for entry in entries {
+        write_row(
+            &mut out,
+            &[
+                entry.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+                entry.provider.clone(),
+                entry.model.clone(),
+                entry.prompt_tokens.to_string(),
+                entry.completion_tokens.to_string(),
+                format!("{:.6}", entry.cost_usd),
+                entry.project.clone(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes one row per provider and month, with the [`SUMMARY_COLUMNS`] header.
+pub fn write_summary_csv(summaries: &[CostSummary], mut out: impl Write) -> Result<()> {
+    write_row(&mut out, &SUMMARY_COLUMNS.map(String::from))?;
+    for summary in summaries {
+        write_row(
+            &mut out,
+            &[
+                summary.month.clone(),
+                summary.provider.clone(),
+                summary.calls.to_string(),
+                summary.prompt_tokens.to_string(),
+                summary.completion_tokens.to_string(),
+                format!("{:.6}", summary.cost_usd),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+fn write_row(out: &mut impl Write, fields: &[String]) -> Result<()> {
+    let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+    write!(out, "{}\r\n", row.join(","))?;
+    Ok(())
+}
+
+/// `field` quoted as RFC 4180 requires when it holds a comma, quote or line
+/// break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: &str, provider: &str, model: &str, cost_usd: f64) -> CostEntry {
+        CostEntry {
+            timestamp: timestamp.parse().unwrap(),
+            provider: provider.to_string(),
+            model: model.to_string(),
+            prompt_tokens: 100,
+            completion_tokens: 50,
+            cost_usd,
+            project: "kandil".to_string(),
+        }
+    }
+
+    #[test]
+    fn entries_export_as_csv_and_monthly_totals() {
+        let entries = vec![
+            entry(
+                "2026-01-15T10:30:00Z",
+                "claude",
+                "claude-3 \"opus\", v2",
+                0.25,
+            ),
+            entry("2026-01-20T08:00:00Z", "claude", "claude-3-haiku", 0.125),
+            entry("2026-02-01T09:00:00Z", "openai", "gpt-4", 0.5),
+        ];
+
+        let mut csv = Vec::new();
+        write_csv(&entries[..1], &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "timestamp,provider,model,prompt_tokens,completion_tokens,cost_usd,project\r\n\
+             2026-01-15T10:30:00Z,claude,\"claude-3 \"\"opus\"\", v2\",100,50,0.250000,kandil\r\n"
+        );
+
+        let summaries = summarize(&entries);
+        let rows: Vec<(&str, &str, u64, f64)> = summaries
+            .iter()
+            .map(|s| (s.month.as_str(), s.provider.as_str(), s.calls, s.cost_usd))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("2026-01", "claude", 2, 0.375),
+                ("2026-02", "openai", 1, 0.5),
+            ]
+        );
+        let mut csv = Vec::new();
+        write_summary_csv(&summaries, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(
+            csv.starts_with("month,provider,calls,prompt_tokens,completion_tokens,cost_usd\r\n")
+        );
+        assert!(csv.contains("2026-01,claude,2,200,100,0.375000\r\n"));
+    }
+}
@@ -55,14 +55,7 @@ impl CostTracker {
         input_tokens: u32,
         output_tokens: u32,
     ) -> f64 {
-        // Calculate approximate cost based on provider pricing
-        let cost = match provider {
-            "openai" => self.calculate_openai_cost(model, input_tokens, output_tokens),
-            "claude" => self.calculate_anthropic_cost(model, input_tokens, output_tokens),
-            "qwen" => self.calculate_qwen_cost(model, input_tokens, output_tokens),
-            "ollama" => 0.0, // Local models are free
-            _ => 0.0,
-        };
+        let cost = self.estimate_cost(provider, model, input_tokens, output_tokens);
 
         let record = UsageRecord {
             tokens_input: input_tokens,
@@ -90,6 +83,24 @@ impl CostTracker {
         cost
     }
 
+    /// Approximate cost of a call without recording it, for budget checks.
+    pub fn estimate_cost(
+        &self,
+        provider: &str,
+        model: &str,
+        input_tokens: u32,
+        output_tokens: u32,
+    ) -> f64 {
+        // Calculate approximate cost based on provider pricing
+        match provider {
+            "openai" => self.calculate_openai_cost(model, input_tokens, output_tokens),
+            "claude" => self.calculate_anthropic_cost(model, input_tokens, output_tokens),
+            "qwen" => self.calculate_qwen_cost(model, input_tokens, output_tokens),
+            "ollama" => 0.0, // Local models are free
+            _ => 0.0,
+        }
+    }
+
     fn calculate_openai_cost(&self, model: &str, input_tokens: u32, output_tokens: u32) -> f64 {
         // Approximate costs based on OpenAI pricing (as of 2023)
         let input_cost_per_m: f64 = match model {
@@ -0,0 +1,413 @@
+//! Measured test coverage
+//!
+//! Runs the real coverage tooling for a source/test pair (cargo-llvm-cov or
+//! cargo-tarpaulin for Rust, coverage.py for Python) and parses its LCOV output.
+//! The measured lines can be written back out as LCOV for Codecov or
+//! Coveralls; AI-estimated coverage has no line data and never is.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CoverageTool {
+    LlvmCov,
+    Tarpaulin,
+    CoveragePy,
+}
+
+impl CoverageTool {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CoverageTool::LlvmCov => "cargo-llvm-cov",
+            CoverageTool::Tarpaulin => "cargo-tarpaulin",
+            CoverageTool::CoveragePy => "coverage.py",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverageReport {
+    pub tool: CoverageTool,
+    pub source_file: String,
+    pub lines_found: u32,
+    pub lines_hit: u32,
+    pub branches_found: u32,
+    pub branches_hit: u32,
+    pub uncovered_lines: Vec<u32>,
+    /// Execution count of each instrumented line.
+    #[serde(default)]
+    pub line_hits: BTreeMap<u32, u64>,
+}
+
+impl CoverageReport {
+    pub fn line_percent(&self) -> f64 {
+        percent(self.lines_hit, self.lines_found)
+    }
+
+    /// Branch coverage, if the tool reported any branch data.
+    pub fn branch_percent(&self) -> Option<f64> {
+        if self.branches_found == 0 {
+            None
+        } else {
+            Some(percent(self.branches_hit, self.branches_found))
+        }
+    }
+
+    /// Collapses uncovered lines into ranges, e.g. `3-5, 9, 12-14`.
+    pub fn uncovered_ranges(&self) -> String {
+        let mut ranges: Vec<(u32, u32)> = Vec::new();
+        for &line in &self.uncovered_lines {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == line => *end = line,
+                _ => ranges.push((line, line)),
+            }
+        }
+        ranges
+            .iter()
+            .map(|(start, end)| {
+                if start == end {
+                    start.to_string()
+                } else {
+                    format!("{}-{}", start, end)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The report as an LCOV tracefile with one record for the source file.
+    pub fn to_lcov(&self) -> String {
+        let mut out = format!("TN:\nSF:{}\n", self.source_file);
+        for (line, hits) in &self.line_hits {
+            out.push_str(&format!("DA:{},{}\n", line, hits));
+        }
+        out.push_str(&format!("LF:{}\nLH:{}\n", self.lines_found, self.lines_hit));
+        if self.branches_found > 0 {
+            out.push_str(&format!(
+                "BRF:{}\nBRH:{}\n",
+                self.branches_found, self.branches_hit
+            ));
+        }
+        out.push_str("end_of_record\n");
+        out
+    }
+}
+
+fn percent(hit: u32, found: u32) -> f64 {
+    if found == 0 {
+        100.0
+    } else {
+        hit as f64 * 100.0 / found as f64
+    }
+}
+
+/// A single `SF:` section of an LCOV tracefile.
+#[derive(Debug, Clone, Default)]
+pub struct LcovRecord {
+    pub source_file: String,
+    pub line_hits: BTreeMap<u32, u64>,
+    pub branches_found: u32,
+    pub branches_hit: u32,
+}
+
+pub fn parse_lcov(contents: &str) -> Vec<LcovRecord> {
+    let mut records = Vec::new();
+    let mut current: Option<LcovRecord> = None;
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if let Some(path) = line.strip_prefix("SF:") {
+            current = Some(LcovRecord {
+                source_file: path.to_string(),
+                ..Default::default()
+            });
+        } else if let Some(data) = line.strip_prefix("DA:") {
+            if let Some(record) = current.as_mut() {
+                let mut parts = data.split(',');
+                let line_no = parts.next().and_then(|v| v.parse::<u32>().ok());
+                let hits = parts.next().and_then(|v| v.parse::<u64>().ok());
+                if let (Some(line_no), Some(hits)) = (line_no, hits) {
+                    *record.line_hits.entry(line_no).or_insert(0) += hits;
+                }
+            }
+        } else if let Some(data) = line.strip_prefix("BRDA:") {
+            if let Some(record) = current.as_mut() {
+                let taken = data.rsplit(',').next().unwrap_or("-");
+                record.branches_found += 1;
+                if taken != "-" && taken != "0" {
+                    record.branches_hit += 1;
+                }
+            }
+        } else if line == "end_of_record" {
+            if let Some(record) = current.take() {
+                records.push(record);
+            }
+        }
+    }
+
+    if let Some(record) = current.take() {
+        records.push(record);
+    }
+    records
+}
+
+pub struct CoverageRunner;
+
+impl CoverageRunner {
+    /// Picks the first installed coverage tool suitable for the source file.
+    pub async fn detect_tool(source_file: &Path) -> Option<CoverageTool> {
+        match source_file.extension().and_then(|ext| ext.to_str()) {
+            Some("rs") => {
+                if command_succeeds("cargo", &["llvm-cov", "--version"]).await {
+                    Some(CoverageTool::LlvmCov)
+                } else if command_succeeds("cargo", &["tarpaulin", "--version"]).await {
+                    Some(CoverageTool::Tarpaulin)
+                } else {
+                    None
+                }
+            }
+            Some("py") => {
+                if command_succeeds("coverage", &["--version"]).await {
+                    Some(CoverageTool::CoveragePy)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Measures coverage for `source_file` by running `test_file` under the
+    /// detected tool. Returns `Ok(None)` when no tool is available.
+    pub async fn measure(source_file: &str, test_file: &str) -> Result<Option<CoverageReport>> {
+        let source = Path::new(source_file);
+        let tool = match Self::detect_tool(source).await {
+            Some(tool) => tool,
+            None => return Ok(None),
+        };
+
+        let source = source
+            .canonicalize()
+            .with_context(|| format!("Source file not found: {}", source_file))?;
+        let test = Path::new(test_file)
+            .canonicalize()
+            .with_context(|| format!("Test file not found: {}", test_file))?;
+
+        let lcov_path =
+            std::env::temp_dir().join(format!("kandil-coverage-{}.info", uuid::Uuid::new_v4()));
+        let run = Self::run_tool(tool, &source, &test, &lcov_path).await;
+        let contents = run.and_then(|_| {
+            std::fs::read_to_string(&lcov_path)
+                .with_context(|| format!("{} did not produce an LCOV report", tool.as_str()))
+        });
+        let _ = std::fs::remove_file(&lcov_path);
+        let contents = contents?;
+
+        let records = parse_lcov(&contents);
+        let record = records
+            .into_iter()
+            .find(|record| same_file(&record.source_file, &source))
+            .with_context(|| {
+                format!(
+                    "{} report contains no data for {}",
+                    tool.as_str(),
+                    source_file
+                )
+            })?;
+
+        let uncovered_lines = record
+            .line_hits
+            .iter()
+            .filter(|(_, hits)| **hits == 0)
+            .map(|(line, _)| *line)
+            .collect();
+
+        Ok(Some(CoverageReport {
+            tool,
+            source_file: source_file.to_string(),
+            lines_found: record.line_hits.len() as u32,
+            lines_hit: record.line_hits.values().filter(|hits| **hits > 0).count() as u32,
+            branches_found: record.branches_found,
+            branches_hit: record.branches_hit,
+            uncovered_lines,
+            line_hits: record.line_hits,
+        }))
+    }
+
+    async fn run_tool(
+        tool: CoverageTool,
+        source: &Path,
+        test: &Path,
+        lcov_path: &Path,
+    ) -> Result<()> {
+        match tool {
+            CoverageTool::LlvmCov | CoverageTool::Tarpaulin => {
+                let root = cargo_root(source).with_context(|| {
+                    format!("No Cargo.toml found above {}", source.display())
+                })?;
+                let mut cmd = Command::new("cargo");
+                cmd.current_dir(&root).kill_on_drop(true);
+                if tool == CoverageTool::LlvmCov {
+                    cmd.args(["llvm-cov", "--lcov", "--output-path"])
+                        .arg(lcov_path);
+                } else {
+                    let out_dir = lcov_path.with_extension("d");
+                    cmd.args(["tarpaulin", "--out", "Lcov", "--output-dir"])
+                        .arg(&out_dir);
+                }
+                // Integration test files can be targeted directly; unit tests
+                // live next to the source and need the whole suite.
+                if let Some(name) = integration_test_name(&root, test) {
+                    cmd.arg("--test").arg(name);
+                }
+                run_checked(cmd, tool).await?;
+
+                if tool == CoverageTool::Tarpaulin {
+                    let out_dir = lcov_path.with_extension("d");
+                    let moved = std::fs::rename(out_dir.join("lcov.info"), lcov_path);
+                    let _ = std::fs::remove_dir_all(&out_dir);
+                    moved?;
+                }
+                Ok(())
+            }
+            CoverageTool::CoveragePy => {
+                let data_file = lcov_path.with_extension("coverage");
+                let mut run = Command::new("coverage");
+                run.kill_on_drop(true)
+                    .env("COVERAGE_FILE", &data_file)
+                    .args(["run", "--branch", "-m", "pytest"])
+                    .arg(test);
+                let result = run_checked(run, tool).await;
+
+                let mut export = Command::new("coverage");
+                export
+                    .kill_on_drop(true)
+                    .env("COVERAGE_FILE", &data_file)
+                    .args(["lcov", "-o"])
+                    .arg(lcov_path);
+                let exported = run_checked(export, tool).await;
+                let _ = std::fs::remove_file(&data_file);
+
+                // Failing tests still produce coverage data; only report the
+                // test failure if nothing could be exported.
+                match (result, exported) {
+                    (_, Ok(())) => Ok(()),
+                    (Err(err), Err(_)) | (Ok(()), Err(err)) => Err(err),
+                }
+            }
+        }
+    }
+}
+
+async fn command_succeeds(program: &str, args: &[&str]) -> bool {
+    Command::new(program)
+        .args(args)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+async fn run_checked(mut cmd: Command, tool: CoverageTool) -> Result<()> {
+    let output = cmd
+        .output()
+        .await
+        .with_context(|| format!("Failed to launch {}", tool.as_str()))?;
+    if !output.status.success() {
+        bail!(
+            "{} exited with {}: {}",
+            tool.as_str(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn cargo_root(source: &Path) -> Option<PathBuf> {
+    source
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.join("Cargo.toml").exists())
+        .map(Path::to_path_buf)
+}
+
+pub(crate) fn integration_test_name(root: &Path, test: &Path) -> Option<String> {
+    if test.parent()? == root.join("tests") {
+        test.file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(str::to_string)
+    } else {
+        None
+    }
+}
+
+fn same_file(reported: &str, source: &Path) -> bool {
+    let reported = Path::new(reported);
+    match reported.canonicalize() {
+        Ok(path) => path == source,
+        Err(_) => source.ends_with(reported),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "TN:\nSF:src/lib.rs\nDA:1,3\nDA:2,0\nDA:3,0\nDA:5,1\nDA:6,0\nBRDA:2,0,0,1\nBRDA:2,0,1,-\nLF:5\nLH:2\nend_of_record\nSF:src/other.rs\nDA:1,1\nend_of_record\n";
+
+    #[test]
+    fn parses_lcov_records() {
+        let records = parse_lcov(SAMPLE);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].source_file, "src/lib.rs");
+        assert_eq!(records[0].line_hits.len(), 5);
+        assert_eq!(records[0].branches_found, 2);
+        assert_eq!(records[0].branches_hit, 1);
+    }
+
+    #[test]
+    fn uncovered_lines_collapse_to_ranges() {
+        let report = CoverageReport {
+            tool: CoverageTool::LlvmCov,
+            source_file: "src/lib.rs".to_string(),
+            lines_found: 8,
+            lines_hit: 2,
+            branches_found: 0,
+            branches_hit: 0,
+            uncovered_lines: vec![2, 3, 4, 7, 9, 10],
+            line_hits: BTreeMap::new(),
+        };
+        assert_eq!(report.uncovered_ranges(), "2-4, 7, 9-10");
+        assert_eq!(report.line_percent(), 25.0);
+        assert!(report.branch_percent().is_none());
+    }
+
+    #[test]
+    fn measured_lines_export_as_lcov() {
+        let record = parse_lcov(SAMPLE).remove(0);
+        let report = CoverageReport {
+            tool: CoverageTool::LlvmCov,
+            source_file: "src/lib.rs".to_string(),
+            lines_found: 5,
+            lines_hit: 2,
+            branches_found: record.branches_found,
+            branches_hit: record.branches_hit,
+            uncovered_lines: vec![2, 3, 6],
+            line_hits: record.line_hits,
+        };
+        let lcov = report.to_lcov();
+        assert_eq!(
+            lcov,
+            "TN:\nSF:src/lib.rs\nDA:1,3\nDA:2,0\nDA:3,0\nDA:5,1\nDA:6,0\n\
+             LF:5\nLH:2\nBRF:2\nBRH:1\nend_of_record\n"
+        );
+        // What we write, we can read back.
+        let reparsed = parse_lcov(&lcov).remove(0);
+        assert_eq!(reparsed.line_hits, report.line_hits);
+    }
+}
@@ -0,0 +1,177 @@
+//! Where kandil writes its state
+//!
+//! Models, recordings, the project database and the small JSON stores all
+//! live under one data directory: `KANDIL_DATA_DIR` when set, otherwise
+//! `kandil` under the platform data directory. Each candidate is checked
+//! once per process by writing a probe file, so a read-only volume (common in
+//! containers) is found before a download starts rather than halfway through
+//! it. When no candidate is writable, state goes under the system temp
+//! directory with a warning that it won't survive a reboot. `kandil doctor`
+//! reports the directory in use and why.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The data directory in use.
+#[derive(Debug, Clone, Serialize)]
+pub struct DataDir {
+    pub path: PathBuf,
+    pub source: DataDirSource,
+    pub writable: bool,
+    /// Candidates that were passed over, with the reason.
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataDirSource {
+    /// `KANDIL_DATA_DIR`.
+    Env,
+    /// The platform data directory.
+    Default,
+    /// The system temp directory, because nothing else was writable.
+    Temp,
+}
+
+impl DataDirSource {
+    pub fn describe(self) -> &'static str {
+        match self {
+            DataDirSource::Env => "from KANDIL_DATA_DIR",
+            DataDirSource::Default => "default",
+            DataDirSource::Temp => "temporary fallback",
+        }
+    }
+}
+
+/// The data directory, resolved on first use.
+pub fn data_dir() -> &'static DataDir {
+    static DATA_DIR: OnceLock<DataDir> = OnceLock::new();
+    DATA_DIR.get_or_init(|| {
+        let env = std::env::var_os("KANDIL_DATA_DIR")
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from);
+        let platform = dirs::data_dir().map(|dir| dir.join("kandil"));
+        let dir = resolve(env, platform, std::env::temp_dir().join("kandil"));
+        for reason in &dir.skipped {
+            eprintln!("⚠️  {}", reason);
+        }
+        match dir.source {
+            DataDirSource::Temp if dir.writable => eprintln!(
+                "⚠️  Writing data to {}, which may be cleared on reboot; \
+                 set KANDIL_DATA_DIR to a writable directory to keep it",
+                dir.path.display()
+            ),
+            DataDirSource::Temp => eprintln!(
+                "⚠️  No writable data directory; set KANDIL_DATA_DIR to one. \
+                 Downloads and saved state will fail"
+            ),
+            _ => {}
+        }
+        dir
+    })
+}
+
+/// Path of the data directory.
+pub fn path() -> &'static Path {
+    &data_dir().path
+}
+
+/// `name` under the data directory, created if missing.
+pub fn subdir(name: &str) -> Result<PathBuf> {
+    let dir = path().join(name);
+    std::fs::create_dir_all(&dir).with_context(|| format!("Cannot create {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// The first writable of `env` (when set), `platform` (when the OS has a
+/// data directory) and `temp`. If none is, `temp` is returned anyway so
+/// callers fail with a path to report.
+fn resolve(env: Option<PathBuf>, platform: Option<PathBuf>, temp: PathBuf) -> DataDir {
+    let candidates = env
+        .map(|dir| (dir, DataDirSource::Env))
+        .into_iter()
+        .chain(platform.map(|dir| (dir, DataDirSource::Default)))
+        .chain([(temp.clone(), DataDirSource::Temp)]);
+    let mut skipped = Vec::new();
+    for (dir, source) in candidates {
+        match check_writable(&dir) {
+            Ok(()) => {
+                return DataDir {
+                    path: dir,
+                    source,
+                    writable: true,
+                    skipped,
+                }
+            }
+            Err(err) => skipped.push(format!(
+                "Data directory {} is not writable ({:#})",
+                dir.display(),
+                err
+            )),
+        }
+    }
+    DataDir {
+        path: temp,
+        source: DataDirSource::Temp,
+        writable: false,
+        skipped,
+    }
+}
+
+/// Creates `dir` if needed and writes and removes a probe file in it.
+fn check_writable(dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe = dir.join(format!(".kandil-write-test-{}", std::process::id()));
+    std::fs::write(&probe, b"")?;
+    let _ = std::fs::remove_file(probe);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unwritable_directories_fall_through_to_the_next() {
+        let root = std::env::temp_dir().join(format!("kandil_data_dir_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        // A directory can't be created under a regular file, even as root.
+        let file = root.join("read-only");
+        std::fs::write(&file, b"").unwrap();
+        let unwritable = file.join("kandil");
+
+        let dir = resolve(
+            Some(root.join("env")),
+            Some(unwritable.clone()),
+            root.join("tmp"),
+        );
+        assert_eq!(dir.source, DataDirSource::Env);
+        assert!(dir.skipped.is_empty());
+
+        let dir = resolve(None, Some(unwritable.clone()), root.join("tmp"));
+        assert_eq!(dir.source, DataDirSource::Temp);
+        assert_eq!(dir.path, root.join("tmp"));
+        assert!(dir.writable);
+        assert_eq!(dir.skipped.len(), 1);
+
+        let dir = resolve(
+            Some(unwritable.clone()),
+            Some(root.join("default")),
+            root.join("tmp"),
+        );
+        assert_eq!(dir.source, DataDirSource::Default);
+        assert!(dir.skipped[0].contains("not writable"));
+
+        // Without a platform data directory the temp directory is next.
+        let dir = resolve(None, None, root.join("tmp"));
+        assert_eq!(dir.source, DataDirSource::Temp);
+        assert!(dir.skipped.is_empty());
+
+        let dir = resolve(None, Some(unwritable.clone()), unwritable);
+        assert!(!dir.writable);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -2,12 +2,14 @@
 //!
 //! Contains SQLite schema, migrations, and data access layer
 
-use anyhow::Result;
+use crate::utils::cost_ledger::CostEntry;
+use anyhow::{bail, Result};
 use chrono::{DateTime, Utc};
 use rusqlite::OptionalExtension;
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, ErrorCode, Params};
 use rusqlite_migration::{Migrations, M};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct Project {
@@ -30,6 +32,22 @@ pub struct Memory {
     pub content: String,
     pub timestamp: DateTime<Utc>,
     pub tokens_used: Option<i64>,
+    /// Stable identity shared by every copy of this entry across machines.
+    pub entry_id: String,
+    /// When the content last changed.
+    pub updated_at: DateTime<Utc>,
+    /// When this machine last agreed with the remote on this entry.
+    #[serde(default)]
+    pub synced_at: Option<DateTime<Utc>>,
+    /// What producing this turn cost, when the provider's usage was known.
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+    /// Provider that produced an AI turn.
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Model that produced an AI turn.
+    #[serde(default)]
+    pub model: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -43,6 +61,27 @@ pub struct SyncQueue {
     pub created_at: DateTime<Utc>,
 }
 
+/// Aggregates over one project's stored memory.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryStats {
+    pub turns: u64,
+    /// Tokens recorded on user turns.
+    pub input_tokens: u64,
+    /// Tokens recorded on every other turn.
+    pub output_tokens: u64,
+    /// Bytes of stored content, which dominates the project's share of the database.
+    pub content_bytes: u64,
+    pub last_session_id: Option<String>,
+    /// Sum of recorded turn costs; `None` when no turn has one.
+    pub recorded_cost_usd: Option<f64>,
+}
+
+/// How long SQLite itself waits for another connection's lock.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(2);
+/// Further attempts after SQLite gives up, with doubling backoff.
+const LOCK_RETRIES: u32 = 3;
+const LOCK_BACKOFF: Duration = Duration::from_millis(100);
+
 pub struct Database {
     conn: Connection,
 }
@@ -50,61 +89,43 @@ pub struct Database {
 impl Database {
     pub fn new(db_path: &str) -> Result<Self> {
         let mut conn = Connection::open(db_path)?;
-
-        // Run migrations
-        let migrations = Migrations::new(vec![M::up(
-            r#"
-                CREATE TABLE projects (
-                    id TEXT PRIMARY KEY,
-                    name TEXT NOT NULL,
-                    root_path TEXT NOT NULL UNIQUE,
-                    ai_provider TEXT NOT NULL,
-                    ai_model TEXT NOT NULL,
-                    last_opened TIMESTAMP,
-                    memory_enabled BOOLEAN DEFAULT 1,
-                    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-                );
-
-                CREATE TABLE memory (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    project_id TEXT NOT NULL,
-                    session_id TEXT NOT NULL,
-                    role TEXT NOT NULL, -- 'user' or 'ai'
-                    content TEXT NOT NULL,
-                    timestamp TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-                    tokens_used INTEGER,
-                    FOREIGN KEY (project_id) REFERENCES projects(id)
-                );
-
-                CREATE TABLE sync_queue (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    operation TEXT NOT NULL, -- 'insert', 'update', 'delete'
-                    table_name TEXT NOT NULL,
-                    record_id TEXT NOT NULL,
-                    data TEXT NOT NULL,
-                    synced BOOLEAN DEFAULT 0,
-                    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-                );
-
-                CREATE INDEX idx_memory_project ON memory(project_id);
-                CREATE INDEX idx_sync_unsynced ON sync_queue(synced);
-                "#,
-        )
-        .down(
-            r#"
-                DROP TABLE IF EXISTS sync_queue;
-                DROP TABLE IF EXISTS memory;
-                DROP TABLE IF EXISTS projects;
-                "#,
-        )]);
-
-        migrations.to_latest(&mut conn)?;
+        // WAL lets readers proceed during a write, and the busy timeout makes
+        // writers from other processes (e.g. `kandil web`) queue instead of failing.
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+
+        let from = schema_version(&conn)?;
+        migrations().to_latest(&mut conn)?;
+        let to = schema_version(&conn)?;
+        if to != from {
+            log::info!("Migrated {} from schema v{} to v{}", db_path, from, to);
+        }
 
         Ok(Self { conn })
     }
 
+    /// Runs a write, retrying with backoff while another connection holds the
+    /// lock, and reports a lock that never frees up in plain words.
+    fn execute<P: Params + Copy>(&self, sql: &str, params: P) -> Result<usize> {
+        let mut attempt = 0;
+        loop {
+            match self.conn.execute(sql, params) {
+                Ok(rows) => return Ok(rows),
+                Err(err) if is_locked(&err) && attempt < LOCK_RETRIES => {
+                    std::thread::sleep(LOCK_BACKOFF * 2u32.pow(attempt));
+                    attempt += 1;
+                }
+                Err(err) if is_locked(&err) => bail!(
+                    "The Kandil database is busy with another process, such as `kandil web`; \
+                     try again once it has finished"
+                ),
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
     pub fn create_project(&self, project: &Project) -> Result<()> {
-        self.conn.execute(
+        self.execute(
             "INSERT INTO projects (id, name, root_path, ai_provider, ai_model, last_opened, memory_enabled, created_at) 
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
             params![
@@ -182,7 +203,7 @@ impl Database {
     }
 
     pub fn update_project_last_opened(&self, id: &str) -> Result<()> {
-        self.conn.execute(
+        self.execute(
             "UPDATE projects SET last_opened = ?1 WHERE id = ?2",
             params![Utc::now().to_rfc3339(), id],
         )?;
@@ -220,29 +241,77 @@ impl Database {
     }
 
     pub fn save_memory(&self, memory: &Memory) -> Result<()> {
-        self.conn.execute(
-            "INSERT INTO memory (project_id, session_id, role, content, timestamp, tokens_used) 
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        self.execute(
+            "INSERT INTO memory (project_id, session_id, role, content, timestamp, tokens_used, entry_id, updated_at, synced_at, cost_usd, provider, model) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
             params![
                 memory.project_id,
                 memory.session_id,
                 memory.role,
                 memory.content,
                 memory.timestamp.to_rfc3339(),
-                memory.tokens_used
+                memory.tokens_used,
+                memory.entry_id,
+                memory.updated_at.to_rfc3339(),
+                memory.synced_at.map(|t| t.to_rfc3339()),
+                memory.cost_usd,
+                memory.provider,
+                memory.model
             ],
         )?;
         Ok(())
     }
 
+    /// Inserts `memory`, or replaces the local copy with the same `entry_id`.
+    pub fn upsert_memory(&self, memory: &Memory) -> Result<()> {
+        self.execute(
+            "INSERT INTO memory (project_id, session_id, role, content, timestamp, tokens_used, entry_id, updated_at, synced_at, cost_usd, provider, model) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+             ON CONFLICT(entry_id) DO UPDATE SET
+                session_id = excluded.session_id,
+                role = excluded.role,
+                content = excluded.content,
+                timestamp = excluded.timestamp,
+                tokens_used = excluded.tokens_used,
+                updated_at = excluded.updated_at,
+                synced_at = excluded.synced_at,
+                cost_usd = excluded.cost_usd,
+                provider = excluded.provider,
+                model = excluded.model",
+            params![
+                memory.project_id,
+                memory.session_id,
+                memory.role,
+                memory.content,
+                memory.timestamp.to_rfc3339(),
+                memory.tokens_used,
+                memory.entry_id,
+                memory.updated_at.to_rfc3339(),
+                memory.synced_at.map(|t| t.to_rfc3339()),
+                memory.cost_usd,
+                memory.provider,
+                memory.model
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn mark_memory_synced(&self, entry_id: &str, at: DateTime<Utc>) -> Result<()> {
+        self.execute(
+            "UPDATE memory SET synced_at = ?1 WHERE entry_id = ?2",
+            params![at.to_rfc3339(), entry_id],
+        )?;
+        Ok(())
+    }
+
     pub fn get_memory_for_project(
         &self,
         project_id: &str,
         limit: Option<i32>,
     ) -> Result<Vec<Memory>> {
         let query = match limit {
-            Some(n) => format!("SELECT id, project_id, session_id, role, content, timestamp, tokens_used FROM memory WHERE project_id = ?1 ORDER BY timestamp DESC LIMIT {}", n),
-            None => "SELECT id, project_id, session_id, role, content, timestamp, tokens_used FROM memory WHERE project_id = ?1 ORDER BY timestamp DESC".to_string(),
+            Some(n) => format!("SELECT id, project_id, session_id, role, content, timestamp, tokens_used, entry_id, updated_at, synced_at, cost_usd, provider, model FROM memory WHERE project_id = ?1 ORDER BY timestamp DESC LIMIT {}", n),
+            None => "SELECT id, project_id, session_id, role, content, timestamp, tokens_used, entry_id, updated_at, synced_at, cost_usd, provider, model FROM memory WHERE project_id = ?1 ORDER BY timestamp DESC".to_string(),
         };
 
         let mut stmt = self.conn.prepare(&query)?;
@@ -259,6 +328,18 @@ impl Database {
                         .unwrap()
                         .with_timezone(&Utc),
                     tokens_used: row.get(6)?,
+                    entry_id: row.get(7)?,
+                    updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
+                        .unwrap()
+                        .with_timezone(&Utc),
+                    synced_at: row.get::<_, Option<String>>(9)?.map(|s| {
+                        DateTime::parse_from_rfc3339(&s)
+                            .unwrap()
+                            .with_timezone(&Utc)
+                    }),
+                    cost_usd: row.get(10)?,
+                    provider: row.get(11)?,
+                    model: row.get(12)?,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -266,6 +347,86 @@ impl Database {
         Ok(memories)
     }
 
+    pub fn memory_stats(&self, project_id: &str) -> Result<MemoryStats> {
+        let (turns, input_tokens, output_tokens, content_bytes, recorded_cost_usd) =
+            self.conn.query_row(
+                "SELECT COUNT(*),
+                        COALESCE(SUM(CASE WHEN role = 'user' THEN tokens_used END), 0),
+                        COALESCE(SUM(CASE WHEN role != 'user' THEN tokens_used END), 0),
+                        COALESCE(SUM(LENGTH(CAST(content AS BLOB))), 0),
+                        SUM(cost_usd)
+                 FROM memory WHERE project_id = ?1",
+                [project_id],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, i64>(3)?,
+                        row.get::<_, Option<f64>>(4)?,
+                    ))
+                },
+            )?;
+        let last_session_id = self
+            .conn
+            .query_row(
+                "SELECT session_id FROM memory WHERE project_id = ?1
+                 ORDER BY timestamp DESC, id DESC LIMIT 1",
+                [project_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(MemoryStats {
+            turns: turns as u64,
+            input_tokens: input_tokens as u64,
+            output_tokens: output_tokens as u64,
+            content_bytes: content_bytes as u64,
+            last_session_id,
+            recorded_cost_usd,
+        })
+    }
+
+    /// One entry per AI turn with a recorded cost, oldest first, limited to
+    /// `provider` if given. The prompt tokens are those of the user turn
+    /// before it in the same session.
+    pub fn cost_entries(&self, provider: Option<&str>) -> Result<Vec<CostEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT m.timestamp, m.provider, m.model,
+                    (SELECT u.tokens_used FROM memory u
+                     WHERE u.session_id = m.session_id AND u.role = 'user' AND u.id < m.id
+                     ORDER BY u.id DESC LIMIT 1),
+                    m.tokens_used, m.cost_usd, p.name
+             FROM memory m LEFT JOIN projects p ON p.id = m.project_id
+             WHERE m.role != 'user' AND m.cost_usd IS NOT NULL
+             ORDER BY m.timestamp, m.id",
+        )?;
+        let entries = stmt
+            .query_map([], |row| {
+                let tokens = |index: usize| -> rusqlite::Result<u32> {
+                    Ok(row.get::<_, Option<i64>>(index)?.unwrap_or(0) as u32)
+                };
+                Ok(CostEntry {
+                    timestamp: DateTime::parse_from_rfc3339(&row.get::<_, String>(0)?)
+                        .unwrap()
+                        .with_timezone(&Utc),
+                    provider: row
+                        .get::<_, Option<String>>(1)?
+                        .unwrap_or_else(|| "unknown".to_string()),
+                    model: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    prompt_tokens: tokens(3)?,
+                    completion_tokens: tokens(4)?,
+                    cost_usd: row.get(5)?,
+                    project: row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(entries
+            .into_iter()
+            .filter(|entry| provider.map_or(true, |p| entry.provider.eq_ignore_ascii_case(p)))
+            .collect())
+    }
+
     pub fn add_to_sync_queue(
         &self,
         operation: &str,
@@ -273,7 +434,7 @@ impl Database {
         record_id: &str,
         data: &str,
     ) -> Result<()> {
-        self.conn.execute(
+        self.execute(
             "INSERT INTO sync_queue (operation, table_name, record_id, data, synced, created_at) 
              VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
             params![
@@ -314,7 +475,7 @@ impl Database {
     }
 
     pub fn mark_synced(&self, id: i64) -> Result<()> {
-        self.conn.execute(
+        self.execute(
             "UPDATE sync_queue SET synced = 1 WHERE id = ?1",
             params![id],
         )?;
@@ -322,8 +483,254 @@ impl Database {
     }
 
     pub fn clear_sync_queue(&self) -> Result<()> {
-        self.conn
-            .execute("DELETE FROM sync_queue WHERE synced = 1", [])?;
+        self.execute("DELETE FROM sync_queue WHERE synced = 1", params![])?;
         Ok(())
     }
 }
+
+/// Schema steps in order. Each runs in a transaction and the applied version
+/// is kept in SQLite's `user_version`, so existing databases are upgraded in
+/// place at startup. Every step can be reverted with its `down`.
+fn migrations() -> Migrations<'static> {
+    Migrations::new(vec![
+        M::up(
+            r#"
+            CREATE TABLE projects (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                root_path TEXT NOT NULL UNIQUE,
+                ai_provider TEXT NOT NULL,
+                ai_model TEXT NOT NULL,
+                last_opened TIMESTAMP,
+                memory_enabled BOOLEAN DEFAULT 1,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE TABLE memory (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                project_id TEXT NOT NULL,
+                session_id TEXT NOT NULL,
+                role TEXT NOT NULL, -- 'user' or 'ai'
+                content TEXT NOT NULL,
+                timestamp TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                tokens_used INTEGER,
+                FOREIGN KEY (project_id) REFERENCES projects(id)
+            );
+
+            CREATE TABLE sync_queue (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                operation TEXT NOT NULL, -- 'insert', 'update', 'delete'
+                table_name TEXT NOT NULL,
+                record_id TEXT NOT NULL,
+                data TEXT NOT NULL,
+                synced BOOLEAN DEFAULT 0,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE INDEX idx_memory_project ON memory(project_id);
+            CREATE INDEX idx_sync_unsynced ON sync_queue(synced);
+            "#,
+        )
+        .down(
+            r#"
+            DROP TABLE IF EXISTS sync_queue;
+            DROP TABLE IF EXISTS memory;
+            DROP TABLE IF EXISTS projects;
+            "#,
+        ),
+        // Per-entry sync metadata, so sync can tell real conflicts from fast-forwards.
+        M::up(
+            r#"
+            ALTER TABLE memory ADD COLUMN entry_id TEXT;
+            ALTER TABLE memory ADD COLUMN updated_at TIMESTAMP;
+            ALTER TABLE memory ADD COLUMN synced_at TIMESTAMP;
+            UPDATE memory SET entry_id = lower(hex(randomblob(16))) WHERE entry_id IS NULL;
+            UPDATE memory SET updated_at = timestamp WHERE updated_at IS NULL;
+            CREATE UNIQUE INDEX idx_memory_entry ON memory(entry_id);
+            "#,
+        )
+        .down(
+            r#"
+            DROP INDEX idx_memory_entry;
+            ALTER TABLE memory DROP COLUMN synced_at;
+            ALTER TABLE memory DROP COLUMN updated_at;
+            ALTER TABLE memory DROP COLUMN entry_id;
+            "#,
+        ),
+        // What each turn cost, next to its token count.
+        M::up("ALTER TABLE memory ADD COLUMN cost_usd REAL;")
+            .down("ALTER TABLE memory DROP COLUMN cost_usd;"),
+        // Which provider and model produced each AI turn, for cost reports.
+        M::up(
+            r#"
+            ALTER TABLE memory ADD COLUMN provider TEXT;
+            ALTER TABLE memory ADD COLUMN model TEXT;
+            "#,
+        )
+        .down(
+            r#"
+            ALTER TABLE memory DROP COLUMN model;
+            ALTER TABLE memory DROP COLUMN provider;
+            "#,
+        ),
+    ])
+}
+
+fn schema_version(conn: &Connection) -> Result<i64> {
+    Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
+
+fn is_locked(err: &rusqlite::Error) -> bool {
+    matches!(
+        err,
+        rusqlite::Error::SqliteFailure(failure, _)
+            if matches!(failure.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(session: &str, role: &str, content: &str, tokens: Option<i64>) -> Memory {
+        let now = Utc::now();
+        Memory {
+            id: 0,
+            project_id: "p1".to_string(),
+            session_id: session.to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: now,
+            tokens_used: tokens,
+            entry_id: uuid::Uuid::new_v4().to_string(),
+            updated_at: now,
+            synced_at: None,
+            cost_usd: None,
+            provider: None,
+            model: None,
+        }
+    }
+
+    #[test]
+    fn memory_stats_sum_tokens_and_bytes() {
+        let db = Database::new(":memory:").unwrap();
+        assert_eq!(db.memory_stats("p1").unwrap(), MemoryStats::default());
+
+        db.save_memory(&memory("s1", "user", "héllo", Some(3)))
+            .unwrap();
+        db.save_memory(&memory("s1", "ai", "hi", Some(10))).unwrap();
+        db.save_memory(&memory("s2", "user", "again", None))
+            .unwrap();
+
+        let stats = db.memory_stats("p1").unwrap();
+        assert_eq!(stats.turns, 3);
+        assert_eq!((stats.input_tokens, stats.output_tokens), (3, 10));
+        assert_eq!(stats.content_bytes, 6 + 2 + 5);
+        assert_eq!(stats.last_session_id.as_deref(), Some("s2"));
+        assert_eq!(stats.recorded_cost_usd, None);
+        assert_eq!(db.memory_stats("other").unwrap().turns, 0);
+    }
+
+    #[test]
+    fn older_databases_are_upgraded_in_place_and_can_roll_back() {
+        let path = std::env::temp_dir().join(format!("kandil_db_{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap().to_string();
+        {
+            let mut conn = Connection::open(&path).unwrap();
+            migrations().to_version(&mut conn, 2).unwrap();
+            conn.execute(
+                "INSERT INTO memory (project_id, session_id, role, content, tokens_used, entry_id, updated_at)
+                 VALUES ('p1', 's1', 'ai', 'kept', 7, 'e1', '2024-05-01T09:00:00+00:00')",
+                [],
+            )
+            .unwrap();
+        }
+
+        let db = Database::new(&path).unwrap();
+        assert_eq!(schema_version(&db.conn).unwrap(), 4);
+        let mut turn = memory("s1", "ai", "priced", Some(5));
+        turn.cost_usd = Some(0.25);
+        db.save_memory(&turn).unwrap();
+        let stats = db.memory_stats("p1").unwrap();
+        assert_eq!((stats.turns, stats.output_tokens), (2, 12));
+        assert_eq!(stats.recorded_cost_usd, Some(0.25));
+
+        let mut conn = db.conn;
+        migrations().to_version(&mut conn, 2).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), 2);
+        let turns: i64 = conn
+            .query_row("SELECT COUNT(*) FROM memory", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(turns, 2);
+        drop(conn);
+
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+
+    #[test]
+    fn cost_entries_pair_replies_with_their_prompts() {
+        let db = Database::new(":memory:").unwrap();
+        db.save_memory(&memory("s1", "user", "question", Some(12)))
+            .unwrap();
+        let mut reply = memory("s1", "ai", "answer", Some(40));
+        reply.cost_usd = Some(0.002);
+        reply.provider = Some("openai".to_string());
+        reply.model = Some("gpt-4o".to_string());
+        db.save_memory(&reply).unwrap();
+        db.save_memory(&memory("s2", "ai", "unpriced", Some(5)))
+            .unwrap();
+
+        let entries = db.cost_entries(None).unwrap();
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(
+            (entry.provider.as_str(), entry.model.as_str()),
+            ("openai", "gpt-4o")
+        );
+        assert_eq!((entry.prompt_tokens, entry.completion_tokens), (12, 40));
+        assert_eq!(entry.cost_usd, 0.002);
+        assert_eq!(db.cost_entries(Some("OpenAI")).unwrap().len(), 1);
+        assert!(db.cost_entries(Some("claude")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn concurrent_writers_lose_nothing() {
+        let path = std::env::temp_dir().join(format!("kandil_db_{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap().to_string();
+        Database::new(&path).unwrap();
+
+        let writers: Vec<_> = (0..2)
+            .map(|writer| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let db = Database::new(&path).unwrap();
+                    for turn in 0..100 {
+                        let content = format!("writer {} turn {}", writer, turn);
+                        db.save_memory(&memory("s1", "user", &content, Some(1)))
+                            .unwrap();
+                        db.add_to_sync_queue("INSERT", "memory", &content, "{}")
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let db = Database::new(&path).unwrap();
+        assert_eq!(db.memory_stats("p1").unwrap().turns, 200);
+        assert_eq!(db.get_unsynced_items().unwrap().len(), 200);
+        let mode: String = db
+            .conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! File-backed JSON state
+//!
+//! Small state files shared between CLI invocations. Every read takes a shared
+//! lock and every update an exclusive one on a sidecar `.lock` file, so two
+//! processes updating the same store cannot interleave their writes.
+
+use anyhow::{Context, Result};
+use fs2::FileExt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct JsonStore {
+    path: PathBuf,
+}
+
+impl JsonStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// A store named `file_name` under the kandil data directory.
+    pub fn in_data_dir(file_name: &str) -> Self {
+        Self::new(crate::utils::data_dir::path().join(file_name))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the stored value, or `T::default()` when nothing has been saved yet.
+    pub fn load<T: DeserializeOwned + Default>(&self) -> Result<T> {
+        let lock = self.lock_file()?;
+        lock.lock_shared()
+            .with_context(|| format!("Failed to lock {}", self.path.display()))?;
+        self.read()
+    }
+
+    /// Re-reads the stored value under an exclusive lock, applies `f` and
+    /// writes the result back before releasing the lock.
+    pub fn update<T, R, F>(&self, f: F) -> Result<R>
+    where
+        T: Serialize + DeserializeOwned + Default,
+        F: FnOnce(&mut T) -> Result<R>,
+    {
+        let lock = self.lock_file()?;
+        lock.lock_exclusive()
+            .with_context(|| format!("Failed to lock {}", self.path.display()))?;
+
+        let mut value = self.read()?;
+        let result = f(&mut value)?;
+
+        // Write to a sibling file and rename so readers never see a partial file.
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(&value)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(result)
+    }
+
+    fn read<T: DeserializeOwned + Default>(&self) -> Result<T> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(contents) if contents.trim().is_empty() => Ok(T::default()),
+            Ok(contents) => serde_json::from_str(&contents)
+                .with_context(|| format!("Corrupt store file {}", self.path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// The lock is released when the returned file is dropped.
+    fn lock_file(&self) -> Result<File> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let lock_path = self.path.with_extension("lock");
+        OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("Failed to open {}", lock_path.display()))
+    }
+}
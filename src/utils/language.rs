@@ -0,0 +1,300 @@
+//! Language detection for code and test generation
+//!
+//! `--language auto` (or no language) infers the target language from, in
+//! order: the file extension, a shebang or the languages of fenced code blocks
+//! in the file, the project type, and finally the languages the file's prose
+//! names. Detection fails rather than guess when two languages are equally
+//! likely, so the user can pass one explicitly.
+
+use crate::enhanced_ui::context::ProjectType;
+use anyhow::{bail, Result};
+use regex::Regex;
+use std::path::Path;
+
+/// Known languages: canonical name, file extensions, code fence tags and
+/// shebang interpreters, and the words that name it in prose.
+const LANGUAGES: &[(&str, &[&str], &[&str], &[&str])] = &[
+    ("rust", &["rs"], &["rust", "rs"], &["rust"]),
+    (
+        "python",
+        &["py", "pyi"],
+        &["python", "py", "python3"],
+        &["python"],
+    ),
+    (
+        "javascript",
+        &["js", "jsx", "mjs", "cjs"],
+        &["javascript", "js", "jsx", "node"],
+        &["javascript", "node.js"],
+    ),
+    (
+        "typescript",
+        &["ts", "tsx"],
+        &["typescript", "ts", "tsx"],
+        &["typescript"],
+    ),
+    ("dart", &["dart"], &["dart"], &["dart", "flutter"]),
+    ("go", &["go"], &["go", "golang"], &["golang"]),
+    ("java", &["java"], &["java"], &["java"]),
+    ("kotlin", &["kt", "kts"], &["kotlin", "kt"], &["kotlin"]),
+    ("swift", &["swift"], &["swift"], &["swift"]),
+    ("ruby", &["rb"], &["ruby", "rb"], &["ruby"]),
+    (
+        "cpp",
+        &["cpp", "cc", "cxx", "hpp"],
+        &["cpp", "c++"],
+        &["c++"],
+    ),
+    ("csharp", &["cs"], &["csharp", "cs", "c#"], &["c#"]),
+];
+
+/// A detected language and how it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Detection {
+    pub language: &'static str,
+    pub reason: String,
+}
+
+/// Whether `language` asks for detection.
+pub fn is_auto(language: &str) -> bool {
+    let language = language.trim();
+    language.is_empty() || language.eq_ignore_ascii_case("auto")
+}
+
+/// The language of `path`, falling back to `project` when the file itself
+/// doesn't tell.
+pub fn detect(path: &Path, project: &ProjectType) -> Result<Detection> {
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        let extension = extension.to_lowercase();
+        if let Some((language, ..)) = LANGUAGES
+            .iter()
+            .find(|(_, extensions, ..)| extensions.contains(&extension.as_str()))
+        {
+            return Ok(Detection {
+                language: *language,
+                reason: format!("the .{} extension", extension),
+            });
+        }
+    }
+
+    let content = std::fs::read_to_string(path).unwrap_or_default();
+    detect_text(&content, &path.display().to_string(), project)
+}
+
+/// The language of `content` that has no file name, e.g. a design read from
+/// stdin, named `label` in errors.
+pub fn detect_text(content: &str, label: &str, project: &ProjectType) -> Result<Detection> {
+    if let Some(language) = shebang(content) {
+        return Ok(Detection {
+            language,
+            reason: "the shebang line".to_string(),
+        });
+    }
+    let fenced = fence_counts(content);
+    if let Some(language) = most_common(label, &fenced, "code blocks")? {
+        return Ok(Detection {
+            language,
+            reason: "its code blocks".to_string(),
+        });
+    }
+
+    let from_project = match project {
+        ProjectType::Rust => Some(("rust", "Cargo.toml")),
+        ProjectType::Node => Some(("javascript", "package.json")),
+        ProjectType::Python => Some(("python", "pyproject.toml")),
+        ProjectType::Unknown => None,
+    };
+    if let Some((language, marker)) = from_project {
+        return Ok(Detection {
+            language,
+            reason: format!("the project's {}", marker),
+        });
+    }
+
+    let mentioned = mention_counts(content);
+    if let Some(language) = most_common(label, &mentioned, "text")? {
+        return Ok(Detection {
+            language,
+            reason: "the languages its text names".to_string(),
+        });
+    }
+    bail!(
+        "Cannot detect the language of {}; pass one explicitly, e.g. `rust` or `python`",
+        label
+    )
+}
+
+/// The test framework for `language` in the project at `root`; `None` when
+/// there is no usual one.
+pub fn test_framework(language: &str, root: &Path) -> Option<&'static str> {
+    match language {
+        "rust" => Some("rust"),
+        "python" => Some("pytest"),
+        "javascript" | "typescript" => {
+            let manifest = std::fs::read_to_string(root.join("package.json")).unwrap_or_default();
+            if manifest.contains("\"vitest\"") {
+                Some("vitest")
+            } else if manifest.contains("\"mocha\"") {
+                Some("mocha")
+            } else {
+                Some("jest")
+            }
+        }
+        "dart" => Some("flutter"),
+        "go" => Some("go test"),
+        "java" | "kotlin" => Some("junit"),
+        _ => None,
+    }
+}
+
+fn shebang(content: &str) -> Option<&'static str> {
+    let first = content.lines().next()?.strip_prefix("#!")?;
+    let interpreter = first
+        .split_whitespace()
+        .filter(|word| *word != "-S")
+        .nth(if first.contains("/env") { 1 } else { 0 })?;
+    let interpreter = interpreter.rsplit('/').next()?;
+    let interpreter = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    LANGUAGES
+        .iter()
+        .find(|(_, _, tags, _)| tags.contains(&interpreter))
+        .map(|(language, ..)| *language)
+}
+
+/// Code blocks per language, from the tags after "```".
+fn fence_counts(content: &str) -> Vec<(&'static str, usize)> {
+    let fence = Regex::new(r"(?m)^\s*```\s*([A-Za-z0-9_+#-]+)").expect("valid regex");
+    let mut counts: Vec<(&'static str, usize)> = Vec::new();
+    for found in fence.captures_iter(content) {
+        let tag = found[1].to_lowercase();
+        if let Some((language, ..)) = LANGUAGES
+            .iter()
+            .find(|(_, _, tags, _)| tags.contains(&tag.as_str()))
+        {
+            count(&mut counts, *language);
+        }
+    }
+    counts
+}
+
+/// Times each language is named in `content`.
+fn mention_counts(content: &str) -> Vec<(&'static str, usize)> {
+    let content = content.to_lowercase();
+    let mut counts: Vec<(&'static str, usize)> = Vec::new();
+    for (language, _, _, words) in LANGUAGES {
+        for word in *words {
+            let pattern = format!(r"(?:^|[^\w.+#]){}(?:$|[^\w+#])", regex::escape(word));
+            let mentions = Regex::new(&pattern)
+                .expect("valid regex")
+                .find_iter(&content)
+                .count();
+            for _ in 0..mentions {
+                count(&mut counts, *language);
+            }
+        }
+    }
+    counts
+}
+
+fn count(counts: &mut Vec<(&'static str, usize)>, language: &'static str) {
+    match counts.iter_mut().find(|(known, _)| *known == language) {
+        Some((_, seen)) => *seen += 1,
+        None => counts.push((language, 1)),
+    }
+}
+
+/// The language seen most often, an error if several tie for first, or
+/// `None` if there were none.
+fn most_common(
+    label: &str,
+    counts: &[(&'static str, usize)],
+    evidence: &str,
+) -> Result<Option<&'static str>> {
+    let Some(top) = counts.iter().map(|(_, seen)| *seen).max() else {
+        return Ok(None);
+    };
+    let leaders: Vec<&'static str> = counts
+        .iter()
+        .filter(|(_, seen)| *seen == top)
+        .map(|(language, _)| *language)
+        .collect();
+    if leaders.len() > 1 {
+        bail!(
+            "The {} in {} point equally to {}; pass the language explicitly",
+            evidence,
+            label,
+            leaders.join(" and ")
+        );
+    }
+    Ok(Some(leaders[0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str, content: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("kandil_language_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn extensions_and_shebangs_decide_first() {
+        let detected = detect(Path::new("src/app.tsx"), &ProjectType::Rust).unwrap();
+        assert_eq!(detected.language, "typescript");
+        assert_eq!(detected.reason, "the .tsx extension");
+
+        let script = scratch("run", "#!/usr/bin/env python3\nprint('hi')\n");
+        assert_eq!(
+            detect(&script, &ProjectType::Unknown).unwrap().language,
+            "python"
+        );
+        let _ = std::fs::remove_dir_all(script.parent().unwrap());
+    }
+
+    #[test]
+    fn design_documents_use_code_blocks_then_the_project() {
+        let design = scratch(
+            "design.md",
+            "# API\n```rust\nstruct User;\n```\n```json\n{}\n```\n```rust\nfn main() {}\n```\n",
+        );
+        assert_eq!(
+            detect(&design, &ProjectType::Python).unwrap().language,
+            "rust"
+        );
+
+        std::fs::write(&design, "# API\nA REST service storing users.\n").unwrap();
+        let detected = detect(&design, &ProjectType::Python).unwrap();
+        assert_eq!(detected.language, "python");
+        assert_eq!(detected.reason, "the project's pyproject.toml");
+
+        std::fs::write(&design, "Written in Go, i.e. golang; Java clients.\n").unwrap();
+        let err = detect(&design, &ProjectType::Unknown).unwrap_err();
+        assert!(err.to_string().contains("point equally to go and java"));
+
+        std::fs::write(&design, "A service.\n").unwrap();
+        let err = detect(&design, &ProjectType::Unknown).unwrap_err();
+        assert!(err.to_string().contains("Cannot detect the language"));
+
+        let err = detect_text("A service.\n", "the design", &ProjectType::Unknown).unwrap_err();
+        assert!(err.to_string().contains("language of the design;"));
+        let inline = detect_text("```python\nx = 1\n```\n", "the design", &ProjectType::Rust);
+        assert_eq!(inline.unwrap().language, "python");
+        let _ = std::fs::remove_dir_all(design.parent().unwrap());
+    }
+
+    #[test]
+    fn frameworks_follow_the_language_and_project() {
+        let manifest = scratch("package.json", r#"{"devDependencies": {"vitest": "1"}}"#);
+        let root = manifest.parent().unwrap();
+        assert_eq!(test_framework("rust", root), Some("rust"));
+        assert_eq!(test_framework("python", root), Some("pytest"));
+        assert_eq!(test_framework("typescript", root), Some("vitest"));
+        assert_eq!(test_framework("ruby", root), None);
+        assert!(is_auto("") && is_auto("Auto") && !is_auto("rust"));
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -2,15 +2,30 @@
 //!
 //! Contains common utilities used across the application.
 
+pub mod budget;
+pub mod chat_export;
 pub mod cloud_sync;
 pub mod code_analysis;
 pub mod config;
+pub mod cost_ledger;
 pub mod cost_tracking;
+pub mod coverage;
+pub mod data_dir;
 pub mod db;
+pub mod json_store;
+pub mod language;
+pub mod offline;
 pub mod ollama;
+pub mod plugin_sandbox;
 pub mod plugins;
+pub mod preflight;
 pub mod project_manager;
+pub mod prompt_templates;
 pub mod rate_limit;
+pub mod redact;
 pub mod refactoring;
+pub mod seed;
 pub mod templates;
 pub mod test_generation;
+pub mod tokens;
+pub mod verbosity;
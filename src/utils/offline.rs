@@ -0,0 +1,109 @@
+//! Offline mode
+//!
+//! Process-wide switch, enabled by `--offline` or `KANDIL_OFFLINE=1`, that
+//! restricts Kandil to local runtimes and refuses anything needing the network.
+
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::core::auto_config::AutoConfig;
+use crate::core::hardware::detect_hardware;
+use crate::models::registry::UniversalModelRegistry;
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Turns offline mode on for the rest of the process.
+pub fn enable() {
+    OFFLINE.store(true, Ordering::SeqCst);
+}
+
+pub fn is_enabled() -> bool {
+    OFFLINE.load(Ordering::SeqCst)
+        || std::env::var("KANDIL_OFFLINE")
+            .map(|value| matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false)
+}
+
+/// Returns `true` for providers that are only reachable over the internet.
+pub fn is_cloud_provider(provider: &str) -> bool {
+    let provider = provider.to_lowercase();
+    matches!(
+        provider.as_str(),
+        "claude" | "anthropic" | "openai" | "qwen" | "gemini"
+    ) || crate::core::adapters::ai::backend::get(&provider)
+        .is_some_and(|backend| backend.is_cloud())
+}
+
+/// Fails fast with a clear message when `action` needs network access.
+pub fn ensure_online(action: &str) -> Result<()> {
+    refuse_when(is_enabled(), action)
+}
+
+fn refuse_when(offline: bool, action: &str) -> Result<()> {
+    if offline {
+        anyhow::bail!(
+            "{} requires network access, which is disabled in offline mode (--offline / KANDIL_OFFLINE)",
+            action
+        );
+    }
+    Ok(())
+}
+
+/// Maps a cloud provider/model pair onto the best local runtime.
+///
+/// Local pairs are returned unchanged. Cloud models are replaced with the
+/// highest quality catalog model that fits this machine, served by Ollama.
+pub fn local_route(provider: &str, model: &str) -> (String, String) {
+    if !is_cloud_provider(provider) {
+        return (provider.to_string(), model.to_string());
+    }
+
+    let local_model = UniversalModelRegistry::global()
+        .get_profile(model)
+        .filter(|profile| profile.is_local)
+        .map(|profile| profile.name)
+        .unwrap_or_else(|| AutoConfig::from_hardware(&detect_hardware()).model.name);
+
+    ("ollama".to_string(), local_model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_actions_are_refused_only_when_offline() {
+        assert!(refuse_when(false, "Cloud sync").is_ok());
+        let err = refuse_when(true, "Cloud sync").unwrap_err().to_string();
+        assert!(err.starts_with("Cloud sync requires network access"));
+        assert!(err.contains("--offline"));
+    }
+
+    #[test]
+    fn cloud_providers_are_remapped_to_ollama() {
+        for provider in ["claude", "anthropic", "OpenAI", "qwen", "gemini"] {
+            assert!(is_cloud_provider(provider), "{provider}");
+        }
+        for provider in ["ollama", "lmstudio", "gpt4all", "foundry"] {
+            assert!(!is_cloud_provider(provider), "{provider}");
+        }
+
+        let fallback = AutoConfig::from_hardware(&detect_hardware()).model.name;
+        assert_eq!(
+            local_route("openai", "gpt-4o"),
+            ("ollama".to_string(), fallback)
+        );
+        assert_eq!(
+            local_route("claude", "qwen2.5-coder-7b-q4"),
+            ("ollama".to_string(), "qwen2.5-coder-7b-q4".to_string())
+        );
+    }
+
+    #[test]
+    fn local_pairs_are_left_alone() {
+        assert_eq!(
+            local_route("lmstudio", "llama3:70b"),
+            ("lmstudio".to_string(), "llama3:70b".to_string())
+        );
+    }
+}
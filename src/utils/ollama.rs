@@ -1,5 +1,5 @@
+use crate::core::adapters::ai::http::{request_timeout, shared_client};
 use anyhow::Result;
-use reqwest::Client;
 use serde::Deserialize;
 
 #[derive(Deserialize)]
@@ -13,8 +13,12 @@ struct TagList {
 }
 
 pub async fn is_available() -> Result<bool> {
-    let client = Client::new();
-    let resp = client.get("http://localhost:11434/api/tags").send().await;
+    let client = shared_client();
+    let resp = client
+        .get("http://localhost:11434/api/tags")
+        .timeout(request_timeout())
+        .send()
+        .await;
     match resp {
         Ok(r) => Ok(r.status().is_success()),
         Err(_) => Ok(false),
@@ -22,8 +26,12 @@ pub async fn is_available() -> Result<bool> {
 }
 
 pub async fn list_models() -> Result<Vec<String>> {
-    let client = Client::new();
-    let resp = client.get("http://localhost:11434/api/tags").send().await?;
+    let client = shared_client();
+    let resp = client
+        .get("http://localhost:11434/api/tags")
+        .timeout(request_timeout())
+        .send()
+        .await?;
     if resp.status().is_success() {
         let tags: TagList = resp.json().await?;
         Ok(tags.models.into_iter().map(|t| t.name).collect())
@@ -33,16 +41,18 @@ pub async fn list_models() -> Result<Vec<String>> {
 }
 
 pub async fn pull_model(name: &str) -> Result<()> {
+    crate::utils::offline::ensure_online("Pulling Ollama models")?;
     #[derive(serde::Serialize)]
     struct PullReq {
         name: String,
     }
-    let client = Client::new();
+    let client = shared_client();
     let resp = client
         .post("http://localhost:11434/api/pull")
         .json(&PullReq {
             name: name.to_string(),
         })
+        .timeout(request_timeout())
         .send()
         .await?;
     if resp.status().is_success() {
@@ -57,12 +67,13 @@ pub async fn delete_model(name: &str) -> Result<()> {
     struct DelReq {
         name: String,
     }
-    let client = Client::new();
+    let client = shared_client();
     let resp = client
         .post("http://localhost:11434/api/delete")
         .json(&DelReq {
             name: name.to_string(),
         })
+        .timeout(request_timeout())
         .send()
         .await?;
     if resp.status().is_success() {
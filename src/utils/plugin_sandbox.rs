@@ -0,0 +1,487 @@
+//! Resource limits for plugin processes
+//!
+//! Runs a child process with a wall-clock timeout, a memory cap and bounded
+//! output capture. On timeout the whole process tree is killed: the child
+//! leads its own process group on Unix and sits in a job object on Windows.
+//! Memory is capped with a cgroup v2 child group on Linux when the current
+//! cgroup is delegated to us, with `RLIMIT_DATA` as the fallback on Unix, and
+//! with the job object's memory limit on Windows. `RLIMIT_AS` is avoided: it
+//! counts reserved address space, which Node, the JVM and Go reserve far
+//! beyond what they use, so they fail to start under a modest cap.
+
+use anyhow::{Context, Result};
+use std::fmt;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, Command};
+
+/// Limits applied to one run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceLimits {
+    pub timeout: Duration,
+    /// `None` leaves memory unbounded.
+    pub memory_bytes: Option<u64>,
+    /// Bytes kept from each of stdout and stderr.
+    pub max_output_bytes: usize,
+}
+
+/// Why a process was killed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    Timeout(Duration),
+    Memory(u64),
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitExceeded::Timeout(timeout) => {
+                write!(
+                    f,
+                    "killed after exceeding its {}s time limit",
+                    timeout.as_secs_f32()
+                )
+            }
+            LimitExceeded::Memory(bytes) => write!(
+                f,
+                "killed for exceeding its {} MB memory limit",
+                bytes / (1024 * 1024)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+#[derive(Debug)]
+pub struct LimitedOutput {
+    pub status: ExitStatus,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub stdout_truncated: bool,
+    pub stderr_truncated: bool,
+}
+
+/// Runs `command` with `input` on stdin under `limits`. Exceeding the time
+/// or (where detectable) memory limit fails with [`LimitExceeded`].
+pub async fn run(
+    mut command: Command,
+    input: &[u8],
+    limits: &ResourceLimits,
+) -> Result<LimitedOutput> {
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let mut containment = Containment::prepare(&mut command, limits);
+    let mut child = command.spawn()?;
+    containment.attach(&child);
+
+    let stdin = child.stdin.take();
+    let input = input.to_vec();
+    let mut stdout = child.stdout.take().context("stdout was not captured")?;
+    let mut stderr = child.stderr.take().context("stderr was not captured")?;
+
+    let mut feed = None;
+    let outcome = tokio::time::timeout(limits.timeout, async {
+        // Fed while the output is read: a child that writes before it has
+        // read all of its input would otherwise block on a full pipe while we
+        // block on its full stdin.
+        let writer = feed.insert(tokio::spawn(async move {
+            if let Some(mut stdin) = stdin {
+                // A process that ignores its input may exit before reading it.
+                let _ = stdin.write_all(&input).await;
+            }
+        }));
+        let (stdout, stderr) = tokio::try_join!(
+            read_limited(&mut stdout, limits.max_output_bytes),
+            read_limited(&mut stderr, limits.max_output_bytes)
+        )?;
+        let status = child.wait().await?;
+        let _ = writer.await;
+        Ok::<_, std::io::Error>((status, stdout, stderr))
+    })
+    .await;
+
+    let (status, (stdout, stdout_truncated), (stderr, stderr_truncated)) = match outcome {
+        Ok(result) => result?,
+        Err(_) => {
+            if let Some(writer) = feed {
+                writer.abort();
+            }
+            containment.kill(&child);
+            let _ = child.kill().await;
+            return Err(LimitExceeded::Timeout(limits.timeout).into());
+        }
+    };
+    if let Some(bytes) = limits.memory_bytes {
+        if containment.memory_exceeded() {
+            return Err(LimitExceeded::Memory(bytes).into());
+        }
+    }
+
+    Ok(LimitedOutput {
+        status,
+        stdout,
+        stderr,
+        stdout_truncated,
+        stderr_truncated,
+    })
+}
+
+/// Reads to the end, keeping at most `limit` bytes. The rest is drained so
+/// the writer never blocks on a full pipe.
+async fn read_limited<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    limit: usize,
+) -> std::io::Result<(Vec<u8>, bool)> {
+    let mut kept = Vec::new();
+    let mut truncated = false;
+    let mut buf = [0u8; 8192];
+    loop {
+        let read = reader.read(&mut buf).await?;
+        if read == 0 {
+            return Ok((kept, truncated));
+        }
+        let room = limit.saturating_sub(kept.len());
+        if read > room {
+            truncated = true;
+        }
+        kept.extend_from_slice(&buf[..read.min(room)]);
+    }
+}
+
+/// Platform handles that confine one child process.
+#[derive(Default)]
+struct Containment {
+    #[cfg(target_os = "linux")]
+    cgroup: Option<linux::Cgroup>,
+    #[cfg(windows)]
+    job: Option<windows::JobObject>,
+}
+
+impl Containment {
+    /// Configures `command` before it's spawned.
+    #[allow(unused_variables, unused_mut)]
+    fn prepare(command: &mut Command, limits: &ResourceLimits) -> Self {
+        let mut containment = Self::default();
+
+        #[cfg(unix)]
+        {
+            command.process_group(0);
+            let mut needs_rlimit = limits.memory_bytes;
+            #[cfg(target_os = "linux")]
+            if let Some(bytes) = limits.memory_bytes {
+                match linux::Cgroup::create(bytes) {
+                    Ok(cgroup) => {
+                        containment.cgroup = Some(cgroup);
+                        needs_rlimit = None;
+                    }
+                    Err(err) => log::debug!("No cgroup for plugin memory limit: {}", err),
+                }
+            }
+            if let Some(bytes) = needs_rlimit {
+                let limit = libc::rlimit {
+                    rlim_cur: bytes as libc::rlim_t,
+                    rlim_max: bytes as libc::rlim_t,
+                };
+                // SAFETY: setrlimit is async-signal-safe and touches only the child.
+                unsafe {
+                    command.pre_exec(move || {
+                        if libc::setrlimit(libc::RLIMIT_DATA, &limit) != 0 {
+                            return Err(std::io::Error::last_os_error());
+                        }
+                        Ok(())
+                    });
+                }
+            }
+        }
+
+        #[cfg(windows)]
+        match windows::JobObject::new(limits.memory_bytes) {
+            Ok(job) => containment.job = Some(job),
+            Err(err) => log::warn!("Plugin runs without a job object: {}", err),
+        }
+
+        containment
+    }
+
+    /// Moves the freshly spawned child into its cgroup or job object.
+    #[allow(unused_variables)]
+    fn attach(&self, child: &Child) {
+        #[cfg(target_os = "linux")]
+        if let (Some(cgroup), Some(pid)) = (&self.cgroup, child.id()) {
+            if let Err(err) = cgroup.add(pid) {
+                log::warn!("Plugin memory limit not applied: {}", err);
+            }
+        }
+        #[cfg(windows)]
+        if let (Some(job), Some(handle)) = (&self.job, child.raw_handle()) {
+            if let Err(err) = job.assign(handle) {
+                log::warn!("Plugin limits not applied: {}", err);
+            }
+        }
+    }
+
+    /// Kills the child and everything it started.
+    #[allow(unused_variables)]
+    fn kill(&mut self, child: &Child) {
+        #[cfg(unix)]
+        if let Some(pid) = child.id() {
+            // SAFETY: the child leads its own process group (see `prepare`).
+            unsafe {
+                libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+            }
+        }
+        #[cfg(windows)]
+        if let Some(job) = self.job.take() {
+            job.terminate();
+        }
+    }
+
+    fn memory_exceeded(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        if let Some(cgroup) = &self.cgroup {
+            return cgroup.oom_killed();
+        }
+        false
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+    /// A cgroup v2 child of our own cgroup. Dropping it kills whatever the
+    /// plugin left running in it and removes it.
+    pub struct Cgroup {
+        dir: PathBuf,
+    }
+
+    impl Cgroup {
+        pub fn create(memory_bytes: u64) -> io::Result<Self> {
+            let own = std::fs::read_to_string("/proc/self/cgroup")?;
+            let relative = own
+                .lines()
+                .find_map(|line| line.strip_prefix("0::"))
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::Unsupported, "cgroup v2 not in use")
+                })?;
+            let dir = Path::new("/sys/fs/cgroup")
+                .join(relative.trim_start_matches('/'))
+                .join(format!(
+                    "kandil-plugin-{}-{}",
+                    std::process::id(),
+                    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+                ));
+            std::fs::create_dir(&dir)?;
+            let cgroup = Self { dir };
+            std::fs::write(cgroup.dir.join("memory.max"), memory_bytes.to_string())?;
+            std::fs::write(cgroup.dir.join("memory.swap.max"), "0").ok();
+            Ok(cgroup)
+        }
+
+        pub fn add(&self, pid: u32) -> io::Result<()> {
+            std::fs::write(self.dir.join("cgroup.procs"), pid.to_string())
+        }
+
+        pub fn oom_killed(&self) -> bool {
+            std::fs::read_to_string(self.dir.join("memory.events"))
+                .ok()
+                .and_then(|events| {
+                    events.lines().find_map(|line| {
+                        line.strip_prefix("oom_kill ")
+                            .and_then(|count| count.trim().parse::<u64>().ok())
+                    })
+                })
+                .is_some_and(|count| count > 0)
+        }
+
+        fn pids(&self) -> Vec<libc::pid_t> {
+            std::fs::read_to_string(self.dir.join("cgroup.procs"))
+                .map(|procs| {
+                    procs
+                        .lines()
+                        .filter_map(|line| line.trim().parse().ok())
+                        .collect()
+                })
+                .unwrap_or_default()
+        }
+
+        fn kill_all(&self) {
+            if std::fs::write(self.dir.join("cgroup.kill"), "1").is_ok() {
+                return;
+            }
+            // `cgroup.kill` needs Linux 5.14; signal the members one by one.
+            for pid in self.pids() {
+                // SAFETY: kill only sends a signal.
+                unsafe {
+                    libc::kill(pid, libc::SIGKILL);
+                }
+            }
+        }
+    }
+
+    impl Drop for Cgroup {
+        fn drop(&mut self) {
+            self.kill_all();
+            // The directory can only be removed once its members are gone,
+            // which takes a moment after SIGKILL.
+            for _ in 0..100 {
+                if self.pids().is_empty() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            if let Err(err) = std::fs::remove_dir(&self.dir) {
+                log::warn!(
+                    "Could not remove plugin cgroup {}: {}",
+                    self.dir.display(),
+                    err
+                );
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::io;
+    use std::os::windows::io::RawHandle;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+        SetInformationJobObject, TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_LIMIT_JOB_MEMORY, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+
+    /// A job object that kills its processes when closed.
+    pub struct JobObject(HANDLE);
+
+    impl JobObject {
+        pub fn new(memory_bytes: Option<u64>) -> io::Result<Self> {
+            // SAFETY: plain Win32 calls on a handle we own.
+            unsafe {
+                let handle = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+                if handle == 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                let job = Self(handle);
+                let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+                info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+                if let Some(bytes) = memory_bytes {
+                    info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_MEMORY;
+                    info.JobMemoryLimit = bytes as usize;
+                }
+                let ok = SetInformationJobObject(
+                    handle,
+                    JobObjectExtendedLimitInformation,
+                    &info as *const _ as *const std::ffi::c_void,
+                    std::mem::size_of_val(&info) as u32,
+                );
+                if ok == 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(job)
+            }
+        }
+
+        pub fn assign(&self, process: RawHandle) -> io::Result<()> {
+            // SAFETY: `process` is the live child's handle.
+            if unsafe { AssignProcessToJobObject(self.0, process as HANDLE) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+
+        pub fn terminate(self) {
+            // SAFETY: the handle is valid until drop.
+            unsafe {
+                TerminateJobObject(self.0, 1);
+            }
+        }
+    }
+
+    impl Drop for JobObject {
+        fn drop(&mut self) {
+            // SAFETY: closing our own handle; KILL_ON_JOB_CLOSE ends leftovers.
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn limits(timeout: Duration, max_output_bytes: usize) -> ResourceLimits {
+        ResourceLimits {
+            timeout,
+            memory_bytes: None,
+            max_output_bytes,
+        }
+    }
+
+    fn sh(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        command
+    }
+
+    #[tokio::test]
+    async fn timeouts_kill_the_process_group() {
+        let started = Instant::now();
+        let err = run(
+            sh("sleep 30 & sleep 30"),
+            b"",
+            &limits(Duration::from_millis(200), 1024),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LimitExceeded>(),
+            Some(&LimitExceeded::Timeout(Duration::from_millis(200)))
+        );
+        // The backgrounded sleep holds stdout open; it must have been killed too.
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn output_past_the_limit_is_truncated() {
+        let output = run(
+            sh("cat; head -c 100000 /dev/zero"),
+            b"hello",
+            &limits(Duration::from_secs(10), 1000),
+        )
+        .await
+        .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout.len(), 1000);
+        assert!(output.stdout.starts_with(b"hello"));
+        assert!(output.stdout_truncated);
+        assert!(!output.stderr_truncated);
+    }
+
+    #[tokio::test]
+    async fn large_input_is_written_while_output_is_read() {
+        // More than a pipe holds in both directions: writing all of stdin
+        // before reading stdout would deadlock against `cat`.
+        let input = vec![b'x'; 4 * 1024 * 1024];
+        let output = run(sh("cat"), &input, &limits(Duration::from_secs(20), 1024))
+            .await
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout.len(), 1024);
+        assert!(output.stdout_truncated);
+    }
+}
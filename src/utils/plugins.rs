@@ -1,17 +1,158 @@
 //! Plugin system for Kandil Code
 //!
-//! Contains functionality for securely loading and executing plugins via IPC
+//! Contains functionality for securely loading and executing plugins via IPC.
+//!
+//! A plugin is a directory under `~/.config/kandil/plugins/` (or
+//! `KANDIL_PLUGINS_DIR`) with a `plugin.toml` naming an executable and the
+//! slash commands and intents it handles. Commands are namespaced as
+//! `/<plugin>:<command>`; the bare `/<command>` also works when it clashes
+//! with neither a built-in nor another plugin. To run a command the executable
+//! is started with the command name as its only argument, gets a JSON
+//! [`PluginRequest`] on stdin and must print a JSON [`PluginResponse`].
+//!
+//! A manifest may declare the most time, memory and output its plugin needs
+//! in a `[limits]` table. Each is capped by the user's `[plugins]` policy, and
+//! a plugin that exceeds its limit is killed along with its child processes.
 
-use anyhow::Result;
-use std::path::Path;
+use super::config::{Config, PluginPolicy};
+use super::plugin_sandbox::{self, ResourceLimits};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::Duration;
+
+pub const MANIFEST_FILE: &str = "plugin.toml";
+
+/// Version of the stdin/stdout protocol sent in every request.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginManifest {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Path relative to the plugin directory, or a program on `PATH`.
+    pub executable: String,
+    #[serde(default)]
+    pub commands: Vec<CommandSpec>,
+    #[serde(default)]
+    pub limits: PluginLimits,
+}
+
+/// `[limits]` table: the most a plugin asks for. Unset values fall back to
+/// the user's policy, and larger ones are capped by it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub struct PluginLimits {
+    pub timeout_secs: Option<u64>,
+    pub memory_mb: Option<u64>,
+    pub max_output_bytes: Option<usize>,
+}
+
+impl PluginLimits {
+    /// The limits actually enforced under `policy`.
+    pub fn capped_by(&self, policy: &PluginPolicy) -> ResourceLimits {
+        let cap = |declared: Option<u64>, max: u64| declared.map_or(max, |value| value.min(max));
+        let memory_mb = match policy.max_memory_mb {
+            0 => self.memory_mb.filter(|mb| *mb > 0),
+            max => Some(cap(self.memory_mb.filter(|mb| *mb > 0), max)),
+        };
+        ResourceLimits {
+            timeout: Duration::from_secs(cap(self.timeout_secs, policy.max_timeout_secs).max(1)),
+            memory_bytes: memory_mb.map(|mb| mb * 1024 * 1024),
+            max_output_bytes: self
+                .max_output_bytes
+                .map_or(policy.max_output_bytes, |bytes| {
+                    bytes.min(policy.max_output_bytes)
+                }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CommandSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Phrases that route natural-language input to this command.
+    #[serde(default)]
+    pub intents: Vec<String>,
+}
+
+/// A slash command provided by an installed plugin.
+#[derive(Debug, Clone)]
+pub struct PluginCommand {
+    pub plugin: String,
+    pub name: String,
+    pub description: String,
+    pub intents: Vec<String>,
+    pub executable: PathBuf,
+    /// `/<name>`, when it's free to use without the namespace.
+    pub alias: Option<String>,
+    pub limits: PluginLimits,
+}
+
+impl PluginCommand {
+    pub fn qualified(&self) -> String {
+        format!("/{}:{}", self.plugin, self.name)
+    }
+}
+
+/// What a plugin receives on stdin.
+#[derive(Debug, Serialize)]
+pub struct PluginRequest<'a> {
+    pub protocol: u32,
+    pub command: &'a str,
+    pub args: &'a [String],
+    /// The raw input that triggered the command.
+    pub input: &'a str,
+    pub cwd: String,
+}
+
+/// What a plugin prints on stdout.
+#[derive(Debug, Default, Deserialize)]
+pub struct PluginResponse {
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Set when the command failed.
+    #[serde(default)]
+    pub error: Option<String>,
+}
 
 #[derive(Debug)]
-pub struct PluginManager {}
+pub struct PluginManager {
+    dir: PathBuf,
+    policy: PluginPolicy,
+}
 
 impl PluginManager {
+    /// Plugins in the user's plugin directory, limited by the configured policy.
     pub fn new() -> Self {
-        Self {}
+        let policy = match Config::load() {
+            Ok(config) => config.plugins,
+            Err(err) => {
+                log::warn!("Using default plugin limits: {:#}", err);
+                PluginPolicy::default()
+            }
+        };
+        Self::with_dir(user_plugins_dir()).with_policy(policy)
+    }
+
+    pub fn with_dir(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            policy: PluginPolicy::default(),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: PluginPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
     }
 
     pub fn install_plugin(&self, plugin_source: &str) -> Result<()> {
@@ -51,7 +192,391 @@ impl PluginManager {
     }
 
     pub fn list_plugins(&self) -> Result<Vec<String>> {
-        // Return list of installed plugins
-        Ok(vec![])
+        Ok(self
+            .manifests()?
+            .into_iter()
+            .map(|(manifest, _)| manifest.name)
+            .collect())
+    }
+
+    /// Every plugin command, sorted by qualified name. `builtins` are the
+    /// triggers a bare alias must not shadow.
+    pub fn commands(&self, builtins: &[&str]) -> Result<Vec<PluginCommand>> {
+        let mut commands = Vec::new();
+        for (manifest, dir) in self.manifests()? {
+            let executable = resolve_executable(&dir, &manifest.executable);
+            for spec in manifest.commands {
+                if !is_valid_name(&spec.name) {
+                    log::warn!(
+                        "Ignoring command '{}' of plugin {}: use letters, digits, '-' or '_'",
+                        spec.name,
+                        manifest.name
+                    );
+                    continue;
+                }
+                commands.push(PluginCommand {
+                    plugin: manifest.name.clone(),
+                    name: spec.name,
+                    description: spec.description,
+                    intents: spec.intents,
+                    executable: executable.clone(),
+                    alias: None,
+                    limits: manifest.limits,
+                });
+            }
+        }
+
+        let mut uses: HashMap<String, usize> = HashMap::new();
+        for command in &commands {
+            *uses.entry(format!("/{}", command.name)).or_default() += 1;
+        }
+        for command in &mut commands {
+            let alias = format!("/{}", command.name);
+            if uses[&alias] == 1 && !builtins.contains(&alias.as_str()) {
+                command.alias = Some(alias);
+            }
+        }
+        commands.sort_by_key(|command| command.qualified());
+        Ok(commands)
+    }
+
+    /// Finds the command for a slash trigger such as `/jira:issue` or `/issue`.
+    pub fn resolve(&self, trigger: &str, builtins: &[&str]) -> Result<Option<PluginCommand>> {
+        Ok(self.commands(builtins)?.into_iter().find(|command| {
+            command.qualified() == trigger || command.alias.as_deref() == Some(trigger)
+        }))
+    }
+
+    /// Finds the command whose intent phrase appears in natural-language input.
+    pub fn match_intent(&self, input: &str, builtins: &[&str]) -> Result<Option<PluginCommand>> {
+        let input = input.to_lowercase();
+        Ok(self.commands(builtins)?.into_iter().find(|command| {
+            command
+                .intents
+                .iter()
+                .any(|intent| !intent.trim().is_empty() && input.contains(&intent.to_lowercase()))
+        }))
+    }
+
+    /// Runs `command`, sending the request on stdin and returning the
+    /// message from the response.
+    pub async fn invoke(
+        &self,
+        command: &PluginCommand,
+        args: &[String],
+        input: &str,
+    ) -> Result<String> {
+        let cwd = std::env::current_dir()?;
+        let request = serde_json::to_vec(&PluginRequest {
+            protocol: PROTOCOL_VERSION,
+            command: &command.name,
+            args,
+            input,
+            cwd: cwd.to_string_lossy().into_owned(),
+        })?;
+
+        let limits = command.limits.capped_by(&self.policy);
+        let mut process = tokio::process::Command::new(&command.executable);
+        process.arg(&command.name);
+        let output = match plugin_sandbox::run(process, &request, &limits).await {
+            Ok(output) => output,
+            Err(err) => match err.downcast_ref::<plugin_sandbox::LimitExceeded>() {
+                Some(exceeded) => bail!("{} was {}", command.qualified(), exceeded),
+                None => {
+                    return Err(err.context(format!(
+                        "Failed to run plugin {} ({})",
+                        command.plugin,
+                        command.executable.display()
+                    )))
+                }
+            },
+        };
+        if output.stderr_truncated {
+            log::warn!(
+                "{} wrote more than {} bytes to stderr; the rest was discarded",
+                command.qualified(),
+                limits.max_output_bytes
+            );
+        }
+        if !output.status.success() {
+            bail!(
+                "{} failed ({}): {}",
+                command.qualified(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        if output.stdout_truncated {
+            bail!(
+                "{} printed more than its {} byte output limit",
+                command.qualified(),
+                limits.max_output_bytes
+            );
+        }
+
+        let response: PluginResponse = serde_json::from_slice(&output.stdout)
+            .with_context(|| format!("{} printed an invalid response", command.qualified()))?;
+        if let Some(error) = response.error {
+            bail!("{}: {}", command.qualified(), error);
+        }
+        Ok(response.message.unwrap_or_default())
+    }
+
+    /// Valid plugin manifests with their directories, sorted by name. Broken
+    /// plugins are skipped with a warning so one can't disable the rest.
+    fn manifests(&self) -> Result<Vec<(PluginManifest, PathBuf)>> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut manifests = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read {}", self.dir.display()))?
+        {
+            let dir = entry?.path();
+            let path = dir.join(MANIFEST_FILE);
+            if !path.is_file() {
+                continue;
+            }
+            match load_manifest(&path) {
+                Ok(manifest) => manifests.push((manifest, dir)),
+                Err(err) => log::warn!("Ignoring plugin at {}: {:#}", dir.display(), err),
+            }
+        }
+        manifests.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+        Ok(manifests)
+    }
+}
+
+pub fn user_plugins_dir() -> PathBuf {
+    match std::env::var("KANDIL_PLUGINS_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("kandil")
+            .join("plugins"),
+    }
+}
+
+fn load_manifest(path: &Path) -> Result<PluginManifest> {
+    let manifest: PluginManifest = toml::from_str(&std::fs::read_to_string(path)?)
+        .with_context(|| format!("Invalid {}", path.display()))?;
+    if !is_valid_name(&manifest.name) {
+        bail!(
+            "Plugin name '{}' must use letters, digits, '-' or '_'",
+            manifest.name
+        );
+    }
+    Ok(manifest)
+}
+
+fn resolve_executable(dir: &Path, executable: &str) -> PathBuf {
+    let bundled = dir.join(executable);
+    if bundled.is_file() {
+        bundled
+    } else {
+        PathBuf::from(executable)
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugin(root: &Path, name: &str, manifest: &str) -> PathBuf {
+        let dir = root.join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(MANIFEST_FILE), manifest).unwrap();
+        dir
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("kandil_plugins_{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn commands_are_namespaced_and_aliased_only_when_free() {
+        let root = temp_dir();
+        plugin(
+            &root,
+            "jira",
+            r#"
+name = "jira"
+executable = "kandil-jira"
+[[commands]]
+name = "jira"
+intents = ["jira ticket"]
+[[commands]]
+name = "test"
+"#,
+        );
+        plugin(
+            &root,
+            "linear",
+            r#"
+name = "linear"
+executable = "kandil-linear"
+[[commands]]
+name = "issue"
+[[commands]]
+name = "jira"
+"#,
+        );
+        plugin(&root, "broken", "name = \"bro ken\"\nexecutable = \"x\"");
+
+        let manager = PluginManager::with_dir(&root);
+        assert_eq!(manager.list_plugins().unwrap(), ["jira", "linear"]);
+
+        let builtins = ["/test", "/help"];
+        let aliases: Vec<_> = manager
+            .commands(&builtins)
+            .unwrap()
+            .into_iter()
+            .map(|command| (command.qualified(), command.alias))
+            .collect();
+        assert_eq!(
+            aliases,
+            [
+                ("/jira:jira".to_string(), None),
+                ("/jira:test".to_string(), None),
+                ("/linear:issue".to_string(), Some("/issue".to_string())),
+                ("/linear:jira".to_string(), None),
+            ]
+        );
+
+        let issue = manager.resolve("/issue", &builtins).unwrap().unwrap();
+        assert_eq!(issue.plugin, "linear");
+        assert!(manager.resolve("/test", &builtins).unwrap().is_none());
+        let intent = manager
+            .match_intent("Open a JIRA ticket for the login bug", &builtins)
+            .unwrap()
+            .unwrap();
+        assert_eq!(intent.qualified(), "/jira:jira");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn manifest_limits_are_capped_by_policy() {
+        let policy = PluginPolicy {
+            max_timeout_secs: 30,
+            max_memory_mb: 256,
+            max_output_bytes: 4096,
+        };
+        let asked = PluginLimits {
+            timeout_secs: Some(600),
+            memory_mb: Some(64),
+            max_output_bytes: None,
+        };
+        assert_eq!(
+            asked.capped_by(&policy),
+            ResourceLimits {
+                timeout: Duration::from_secs(30),
+                memory_bytes: Some(64 * 1024 * 1024),
+                max_output_bytes: 4096,
+            }
+        );
+
+        let uncapped = PluginPolicy {
+            max_memory_mb: 0,
+            ..policy
+        };
+        assert_eq!(
+            PluginLimits::default().capped_by(&uncapped).memory_bytes,
+            None
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn invoke_sends_json_and_reads_the_response() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = temp_dir();
+        let dir = plugin(
+            &root,
+            "echo",
+            r#"
+name = "echo"
+executable = "run.sh"
+[[commands]]
+name = "say"
+[[commands]]
+name = "fail"
+"#,
+        );
+        let script = dir.join("run.sh");
+        std::fs::write(
+            &script,
+            r#"#!/bin/sh
+input=$(cat)
+if [ "$1" = fail ]; then
+  printf '{"error":"no such project"}'
+  exit 0
+fi
+case "$input" in
+  *'"args":["PROJ-1"]'*) printf '{"message":"%s got PROJ-1"}' "$1" ;;
+  *) printf '{"message":"missing args"}' ;;
+esac
+"#,
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let manager = PluginManager::with_dir(&root);
+        let say = manager.resolve("/echo:say", &[]).unwrap().unwrap();
+        let args = vec!["PROJ-1".to_string()];
+        assert_eq!(
+            manager
+                .invoke(&say, &args, "/echo:say PROJ-1")
+                .await
+                .unwrap(),
+            "say got PROJ-1"
+        );
+
+        let fail = manager.resolve("/fail", &[]).unwrap().unwrap();
+        let err = manager.invoke(&fail, &[], "/fail").await.unwrap_err();
+        assert!(err.to_string().contains("no such project"));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn plugins_exceeding_their_time_limit_are_killed() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = temp_dir();
+        let dir = plugin(
+            &root,
+            "slow",
+            r#"
+name = "slow"
+executable = "run.sh"
+[[commands]]
+name = "wait"
+[limits]
+timeout_secs = 1
+"#,
+        );
+        let script = dir.join("run.sh");
+        std::fs::write(&script, "#!/bin/sh\nsleep 30\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let manager = PluginManager::with_dir(&root);
+        let wait = manager.resolve("/wait", &[]).unwrap().unwrap();
+        let err = manager.invoke(&wait, &[], "/wait").await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "/slow:wait was killed after exceeding its 1s time limit"
+        );
+
+        let _ = std::fs::remove_dir_all(root);
     }
 }
@@ -0,0 +1,175 @@
+//! Startup preflight for AI commands
+//!
+//! Probes the configured provider before a command makes its first model call,
+//! so connectivity problems surface as actionable advice instead of a network
+//! error deep inside an agent. Probe results are cached briefly on disk.
+
+use crate::core::adapters::ai::http::shared_client;
+use crate::core::adapters::ai::KandilAI;
+use crate::utils::config::SecureKey;
+use crate::utils::offline;
+use crate::utils::verbosity;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+const CACHE_TTL_SECS: i64 = 30;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProbeCache {
+    entries: HashMap<String, CachedProbe>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedProbe {
+    reachable: bool,
+    checked_at: DateTime<Utc>,
+}
+
+/// Checks that `provider` can plausibly serve requests.
+///
+/// Returns `None` when everything looks fine, or a human readable explanation
+/// with next steps when the provider is unreachable or misconfigured.
+pub async fn check_provider(provider: &str, model: &str) -> Option<String> {
+    let ai = match KandilAI::new(provider.to_string(), model.to_string()) {
+        Ok(ai) => ai,
+        Err(err) => return Some(format!("{}. Switch provider with KANDIL_AI_PROVIDER.", err)),
+    };
+
+    if offline::is_cloud_provider(provider) && SecureKey::load(ai.provider_name()).is_err() {
+        return Some(format!(
+            "No API key stored for {}. Run `kandil config set-key {} <key>`, or switch to a local provider with KANDIL_AI_PROVIDER=ollama.",
+            provider,
+            ai.provider_name()
+        ));
+    }
+
+    let endpoint = ai.base_url().to_string();
+    if probe_cached(&endpoint).await {
+        return None;
+    }
+
+    Some(match ai.provider_name() {
+        "ollama" => format!(
+            "Ollama is not reachable at {}. Start it with `ollama serve`, or switch provider with KANDIL_AI_PROVIDER.",
+            endpoint
+        ),
+        _ if !offline::is_cloud_provider(provider) => format!(
+            "The {} server is not reachable at {}. Start its local API server, or switch provider with KANDIL_AI_PROVIDER.",
+            provider, endpoint
+        ),
+        _ => format!(
+            "The {} API at {} is unreachable. Check your internet connection or proxy, or run with --offline to use local runtimes.",
+            provider, endpoint
+        ),
+    })
+}
+
+impl ProbeCache {
+    /// The cached result for `endpoint`, unless it is older than the TTL.
+    fn lookup(&self, endpoint: &str, now: DateTime<Utc>) -> Option<bool> {
+        self.entries
+            .get(endpoint)
+            .filter(|entry| now - entry.checked_at < ChronoDuration::seconds(CACHE_TTL_SECS))
+            .map(|entry| entry.reachable)
+    }
+
+    fn record(&mut self, endpoint: &str, reachable: bool, now: DateTime<Utc>) {
+        self.entries.insert(
+            endpoint.to_string(),
+            CachedProbe {
+                reachable,
+                checked_at: now,
+            },
+        );
+    }
+}
+
+async fn probe_cached(endpoint: &str) -> bool {
+    let mut cache = load_cache();
+    if let Some(reachable) = cache.lookup(endpoint, Utc::now()) {
+        verbosity::note(format!("Preflight cache hit for {}", endpoint));
+        return reachable;
+    }
+
+    verbosity::note(format!("Preflight cache miss for {}; probing", endpoint));
+    let reachable = probe(endpoint).await;
+    cache.record(endpoint, reachable, Utc::now());
+    save_cache(&cache);
+    reachable
+}
+
+async fn probe(endpoint: &str) -> bool {
+    // Any HTTP response, even an error status, proves the endpoint is up.
+    // Probing through the shared client leaves a warm connection for the
+    // command's first real request.
+    shared_client()
+        .get(endpoint)
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+        .is_ok()
+}
+
+fn cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("kandil").join("preflight.json"))
+}
+
+fn load_cache() -> ProbeCache {
+    cache_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_cache(cache: &ProbeCache) {
+    if let Some(path) = cache_path() {
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(contents) = serde_json::to_string(cache) {
+            let _ = std::fs::write(path, contents);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLLAMA: &str = "http://localhost:11434";
+
+    #[test]
+    fn cached_probes_expire_after_the_ttl() {
+        let start = Utc::now();
+        let mut cache = ProbeCache::default();
+        assert_eq!(cache.lookup(OLLAMA, start), None);
+
+        cache.record(OLLAMA, true, start);
+        assert_eq!(cache.lookup(OLLAMA, start), Some(true));
+        let almost = start + ChronoDuration::seconds(CACHE_TTL_SECS - 1);
+        assert_eq!(cache.lookup(OLLAMA, almost), Some(true));
+        let expired = start + ChronoDuration::seconds(CACHE_TTL_SECS);
+        assert_eq!(cache.lookup(OLLAMA, expired), None);
+        assert_eq!(cache.lookup("https://api.openai.com", start), None);
+    }
+
+    #[test]
+    fn a_new_probe_replaces_the_cached_result() {
+        let start = Utc::now();
+        let mut cache = ProbeCache::default();
+        cache.record(OLLAMA, true, start);
+
+        let later = start + ChronoDuration::seconds(CACHE_TTL_SECS + 5);
+        cache.record(OLLAMA, false, later);
+        assert_eq!(cache.lookup(OLLAMA, later), Some(false));
+
+        let reloaded: ProbeCache =
+            serde_json::from_str(&serde_json::to_string(&cache).unwrap()).unwrap();
+        assert_eq!(reloaded.lookup(OLLAMA, later), Some(false));
+        assert_eq!(reloaded.entries.len(), 1);
+    }
+}
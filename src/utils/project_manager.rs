@@ -2,29 +2,73 @@
 //!
 //! Handles project creation, switching, and session management
 
+use crate::utils::cloud_sync::{SyncConflict, SyncPlan};
+use crate::utils::cost_tracking::CostTracker;
 use crate::utils::db::{Database, Project};
 use anyhow::Result;
 use chrono::Utc;
-use dirs::data_dir;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
+/// Usage derived from a project's stored memory.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectStats {
+    pub memory_turns: u64,
+    pub tokens_used: u64,
+    /// Recorded turn costs, or else an estimate from the project's provider
+    /// and model; zero for local models.
+    pub estimated_cost_usd: f64,
+    pub last_session_id: Option<String>,
+    pub memory_bytes: u64,
+}
+
+/// Who answered an AI turn and what the answer cost, stored with the reply so
+/// cost reports can be built from project memory.
+#[derive(Debug, Clone, Copy)]
+pub struct ReplyCost<'a> {
+    pub provider: &'a str,
+    pub model: &'a str,
+    pub cost_usd: f64,
+}
+
 pub struct ProjectManager {
     db: Database,
+    data_path: PathBuf,
+}
+
+/// Moves the database from where earlier versions kept it, `kandil_code`
+/// under the platform data directory, so existing projects carry over.
+fn adopt_legacy_db(db_path: &Path) {
+    let Some(legacy) = dirs::data_dir().map(|dir| dir.join("kandil_code").join("kandil.db")) else {
+        return;
+    };
+    if db_path.exists() || !legacy.exists() {
+        return;
+    }
+    let moved = std::fs::rename(&legacy, db_path).or_else(|_| {
+        std::fs::copy(&legacy, db_path)?;
+        std::fs::remove_file(&legacy)
+    });
+    if let Err(err) = moved {
+        log::warn!(
+            "Cannot move the project database from {} to {}: {}",
+            legacy.display(),
+            db_path.display(),
+            err
+        );
+    }
 }
 
 impl ProjectManager {
     pub fn new() -> Result<Self> {
-        // Create data directory if it doesn't exist
-        let data_path = data_dir()
-            .unwrap_or_else(|| std::env::current_dir().unwrap())
-            .join("kandil_code");
-
-        std::fs::create_dir_all(&data_path)?;
+        let data_path = crate::utils::data_dir::subdir("projects")?;
 
         let db_path = data_path.join("kandil.db");
-        let db = Database::new(db_path.to_str().unwrap())?;
+        adopt_legacy_db(&db_path);
+        let db = Database::new(&db_path.to_string_lossy())?;
 
-        Ok(Self { db })
+        Ok(Self { db, data_path })
     }
 
     pub fn create_project(
@@ -65,6 +109,44 @@ impl ProjectManager {
         self.db.list_projects()
     }
 
+    /// The project rooted at the working directory, or else the most recently
+    /// opened one; the flag is `true` when the project had to be inferred.
+    pub fn current_project(&self) -> Result<Option<(Project, bool)>> {
+        let cwd = std::env::current_dir()?.to_string_lossy().to_string();
+        if let Some(project) = self.db.get_project_by_path(&cwd)? {
+            return Ok(Some((project, false)));
+        }
+        Ok(self
+            .db
+            .list_projects()?
+            .into_iter()
+            .next()
+            .map(|project| (project, true)))
+    }
+
+    pub fn project_stats(&self, project: &Project) -> Result<ProjectStats> {
+        let stats = self.db.memory_stats(&project.id)?;
+        let estimated_cost_usd = if let Some(recorded) = stats.recorded_cost_usd {
+            recorded
+        } else if stats.input_tokens + stats.output_tokens == 0 {
+            0.0
+        } else {
+            CostTracker::new().estimate_cost(
+                &project.ai_provider,
+                &project.ai_model,
+                u32::try_from(stats.input_tokens).unwrap_or(u32::MAX),
+                u32::try_from(stats.output_tokens).unwrap_or(u32::MAX),
+            )
+        };
+        Ok(ProjectStats {
+            memory_turns: stats.turns,
+            tokens_used: stats.input_tokens + stats.output_tokens,
+            estimated_cost_usd,
+            last_session_id: stats.last_session_id,
+            memory_bytes: stats.content_bytes,
+        })
+    }
+
     pub fn switch_project(&self, id: &str) -> Result<Project> {
         // Update the last opened timestamp
         self.db.update_project_last_opened(id)?;
@@ -104,15 +186,23 @@ impl ProjectManager {
         role: &str,
         content: &str,
         tokens_used: Option<i64>,
+        reply_cost: Option<ReplyCost<'_>>,
     ) -> Result<()> {
+        let now = Utc::now();
         let memory = crate::utils::db::Memory {
             id: 0, // Will be auto-generated
             project_id: project_id.to_string(),
             session_id: session_id.to_string(),
             role: role.to_string(),
-            content: content.to_string(),
-            timestamp: Utc::now(),
+            content: crate::utils::redact::redact(content),
+            timestamp: now,
             tokens_used,
+            entry_id: Uuid::new_v4().to_string(),
+            updated_at: now,
+            synced_at: None,
+            cost_usd: reply_cost.map(|c| c.cost_usd),
+            provider: reply_cost.map(|c| c.provider.to_string()),
+            model: reply_cost.map(|c| c.model.to_string()),
         };
 
         self.db.save_memory(&memory)?;
@@ -120,7 +210,7 @@ impl ProjectManager {
         // Add to sync queue for cloud sync
         let data = serde_json::to_string(&memory)?;
         self.db
-            .add_to_sync_queue("INSERT", "memory", &memory.id.to_string(), &data)?;
+            .add_to_sync_queue("INSERT", "memory", &memory.entry_id, &data)?;
 
         Ok(())
     }
@@ -132,4 +222,41 @@ impl ProjectManager {
     ) -> Result<Vec<crate::utils::db::Memory>> {
         self.db.get_memory_for_project(project_id, limit)
     }
+
+    /// Recorded AI turn costs across all projects, oldest first.
+    pub fn cost_entries(
+        &self,
+        provider: Option<&str>,
+    ) -> Result<Vec<crate::utils::cost_ledger::CostEntry>> {
+        self.db.cost_entries(provider)
+    }
+
+    /// Writes the entries a sync pulled and stamps everything both sides now
+    /// agree on, so the next sync only sees later changes.
+    pub fn apply_sync(&self, project_id: &str, plan: &SyncPlan) -> Result<()> {
+        let now = Utc::now();
+        for entry in &plan.pull {
+            let mut entry = entry.clone();
+            entry.project_id = project_id.to_string();
+            entry.synced_at = Some(now);
+            self.db.upsert_memory(&entry)?;
+        }
+        for entry_id in &plan.agreed {
+            self.db.mark_memory_synced(entry_id, now)?;
+        }
+        Ok(())
+    }
+
+    /// Saves unresolved sync conflicts for manual review and returns the file.
+    pub fn save_sync_conflicts(
+        &self,
+        project_id: &str,
+        conflicts: &[SyncConflict],
+    ) -> Result<PathBuf> {
+        let dir = self.data_path.join("conflicts");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.json", project_id));
+        std::fs::write(&path, serde_json::to_string_pretty(conflicts)?)?;
+        Ok(path)
+    }
 }
@@ -0,0 +1,236 @@
+//! Agent prompt templates
+//!
+//! Each agent's main prompt is a Handlebars template. Defaults are compiled
+//! into the binary from `templates/prompts/`, and any of them can be replaced
+//! by dropping `<agent>.tmpl` into the user prompts directory
+//! (`~/.config/kandil/prompts` on Linux, or `KANDIL_PROMPTS_DIR`). Templates
+//! receive `language`, `context` and `constraints` as data.
+
+use anyhow::{Context, Result};
+use handlebars::Handlebars;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Built-in templates, by agent name.
+const BUILTIN: &[(&str, &str)] = &[
+    ("code", include_str!("../../templates/prompts/code.tmpl")),
+    (
+        "design",
+        include_str!("../../templates/prompts/design.tmpl"),
+    ),
+    (
+        "requirements",
+        include_str!("../../templates/prompts/requirements.tmpl"),
+    ),
+    (
+        "review",
+        include_str!("../../templates/prompts/review.tmpl"),
+    ),
+    ("test", include_str!("../../templates/prompts/test.tmpl")),
+];
+
+/// Values available to a prompt template.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PromptData {
+    /// Target language, e.g. `rust`; empty when not applicable.
+    pub language: String,
+    /// The material the agent works on: a design, source file, description...
+    pub context: String,
+    /// Extra requirements the output must respect.
+    pub constraints: Vec<String>,
+}
+
+impl PromptData {
+    pub fn new(context: impl Into<String>) -> Self {
+        Self {
+            context: context.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = language.into();
+        self
+    }
+}
+
+/// One agent's template as seen by `kandil config prompts list`.
+#[derive(Debug, Clone)]
+pub struct PromptInfo {
+    pub agent: &'static str,
+    pub path: PathBuf,
+    pub overridden: bool,
+}
+
+/// Resolves and renders agent prompts, preferring user overrides.
+#[derive(Debug, Clone)]
+pub struct PromptTemplates {
+    dir: PathBuf,
+}
+
+impl PromptTemplates {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Templates backed by the user's prompts directory.
+    pub fn user_default() -> Self {
+        let dir = match std::env::var("KANDIL_PROMPTS_DIR") {
+            Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => dirs::config_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join("kandil")
+                .join("prompts"),
+        };
+        Self::new(dir)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Agents that have a template.
+    pub fn agents() -> impl Iterator<Item = &'static str> {
+        BUILTIN.iter().map(|(agent, _)| *agent)
+    }
+
+    /// The template compiled into the binary for `agent`.
+    pub fn builtin(agent: &str) -> Option<&'static str> {
+        BUILTIN
+            .iter()
+            .find(|(name, _)| *name == agent)
+            .map(|(_, template)| *template)
+    }
+
+    pub fn override_path(&self, agent: &str) -> PathBuf {
+        self.dir.join(format!("{}.tmpl", agent))
+    }
+
+    pub fn list(&self) -> Vec<PromptInfo> {
+        Self::agents()
+            .map(|agent| {
+                let path = self.override_path(agent);
+                PromptInfo {
+                    agent,
+                    overridden: path.is_file(),
+                    path,
+                }
+            })
+            .collect()
+    }
+
+    /// Renders `agent`'s prompt. A user template that fails to render is
+    /// reported and the built-in one is used instead, so a typo in an
+    /// override never stops an agent from running.
+    pub fn render(&self, agent: &str, data: &PromptData) -> Result<String> {
+        let builtin = Self::builtin(agent)
+            .with_context(|| format!("No prompt template for agent '{}'", agent))?;
+
+        let path = self.override_path(agent);
+        if path.is_file() {
+            let custom = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            match render_template(&custom, data) {
+                Ok(prompt) => return Ok(prompt),
+                Err(err) => log::warn!(
+                    "Ignoring prompt template {}: {}; using the built-in one",
+                    path.display(),
+                    err
+                ),
+            }
+        }
+
+        render_template(builtin, data)
+    }
+
+    /// Copies the built-in template into the prompts directory unless an
+    /// override already exists, and returns the override's path.
+    pub fn materialize(&self, agent: &str) -> Result<PathBuf> {
+        let builtin = Self::builtin(agent).with_context(|| {
+            format!(
+                "Unknown agent '{}'. Available: {}",
+                agent,
+                Self::agents().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+        let path = self.override_path(agent);
+        if !path.exists() {
+            std::fs::create_dir_all(&self.dir)
+                .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+            std::fs::write(&path, builtin)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(path)
+    }
+}
+
+/// Renders a template string without HTML escaping; prompts are plain text.
+pub fn render_template(template: &str, data: &PromptData) -> Result<String> {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+    Ok(handlebars.render_template(template, data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_templates() -> PromptTemplates {
+        PromptTemplates::new(
+            std::env::temp_dir().join(format!("kandil_prompts_{}", uuid::Uuid::new_v4())),
+        )
+    }
+
+    #[test]
+    fn builtin_templates_render_all_variables() -> Result<()> {
+        let templates = temp_templates();
+        let data = PromptData {
+            language: "rust".to_string(),
+            context: "fn main() {}".to_string(),
+            constraints: vec!["no unsafe code".to_string()],
+        };
+        for agent in PromptTemplates::agents() {
+            let prompt = templates.render(agent, &data)?;
+            assert!(prompt.contains("fn main() {}"), "{agent} drops context");
+            assert!(
+                prompt.contains("- no unsafe code"),
+                "{agent} drops constraints"
+            );
+        }
+
+        let without = templates.render("code", &PromptData::new("design").with_language("go"))?;
+        assert!(without.contains("production-ready go code"));
+        assert!(!without.contains("Constraints:"));
+        Ok(())
+    }
+
+    #[test]
+    fn user_override_wins_and_bad_override_falls_back() -> Result<()> {
+        let templates = temp_templates();
+        let path = templates.materialize("review")?;
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            PromptTemplates::builtin("review").unwrap()
+        );
+
+        std::fs::write(&path, "Be terse. Review <{{language}}>: {{context}}")?;
+        let data = PromptData::new("x < y").with_language("c++");
+        assert_eq!(
+            templates.render("review", &data)?,
+            "Be terse. Review <c++>: x < y"
+        );
+
+        std::fs::write(&path, "{{#if context}}unclosed")?;
+        assert!(templates.render("review", &data)?.contains("OWASP"));
+
+        let _ = std::fs::remove_dir_all(templates.dir());
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_agents_are_rejected() {
+        let templates = temp_templates();
+        assert!(templates.render("poet", &PromptData::default()).is_err());
+        assert!(templates.materialize("poet").is_err());
+    }
+}
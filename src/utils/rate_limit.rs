@@ -24,3 +24,83 @@ pub fn check_limit(key: &str) -> Result<()> {
     entry.push(now);
     Ok(())
 }
+
+/// Buckets beyond this many keys trigger pruning of idle (full) buckets.
+const MAX_TRACKED_KEYS: usize = 4096;
+
+/// Token buckets keyed by client: each key can burst up to `per_minute`
+/// requests and regains one request every `60 / per_minute` seconds.
+pub struct TokenBucketLimiter {
+    per_minute: u32,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucketLimiter {
+    /// A limit of 0 lets every request through.
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a token for `key`, or returns how long until one is available.
+    pub fn try_acquire(&self, key: &str) -> std::result::Result<(), Duration> {
+        if self.per_minute == 0 {
+            return Ok(());
+        }
+        let capacity = f64::from(self.per_minute);
+        let refill_per_sec = capacity / 60.0;
+        let now = Instant::now();
+        let refilled = |bucket: &Bucket| {
+            (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * refill_per_sec)
+                .min(capacity)
+        };
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_KEYS {
+            buckets.retain(|_, bucket| refilled(bucket) < capacity);
+        }
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        bucket.tokens = refilled(bucket);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / refill_per_sec,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_allows_a_burst_then_limits_per_key() {
+        let limiter = TokenBucketLimiter::new(2);
+        assert!(limiter.try_acquire("10.0.0.1").is_ok());
+        assert!(limiter.try_acquire("10.0.0.1").is_ok());
+        let retry_after = limiter.try_acquire("10.0.0.1").unwrap_err();
+        assert!(retry_after > Duration::from_secs(25) && retry_after <= Duration::from_secs(30));
+        assert!(limiter.try_acquire("10.0.0.2").is_ok());
+    }
+
+    #[test]
+    fn zero_disables_the_limit() {
+        let limiter = TokenBucketLimiter::new(0);
+        assert!((0..100).all(|_| limiter.try_acquire("client").is_ok()));
+    }
+}
@@ -3,8 +3,10 @@
 //! Contains functionality for code refactoring with preview/apply workflow
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RefactorOperation {
     pub file_path: String,
     original_code: String,
@@ -38,6 +40,40 @@ impl RefactorEngine {
         }
     }
 
+    /// Where previews are kept between `refactor preview` and `refactor apply`.
+    pub fn pending_path() -> Result<PathBuf> {
+        Ok(std::env::current_dir()?
+            .join(crate::utils::config::PROJECT_CONFIG_DIR)
+            .join("pending_refactors.json"))
+    }
+
+    /// Creates an engine holding the operations saved by earlier previews.
+    pub fn load_pending() -> Result<Self> {
+        let path = Self::pending_path()?;
+        let operations = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(path)?)?
+        } else {
+            Vec::new()
+        };
+        Ok(Self { operations })
+    }
+
+    /// Persists pending operations, removing the file once none are left.
+    pub fn save_pending(&self) -> Result<()> {
+        let path = Self::pending_path()?;
+        if self.operations.is_empty() {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&self.operations)?)?;
+        Ok(())
+    }
+
     pub fn preview_refactor(
         &mut self,
         file_path: &str,
@@ -63,6 +99,25 @@ impl RefactorEngine {
         Ok(refactored_code)
     }
 
+    /// Queues a whole-file rewrite produced elsewhere, such as by an agent.
+    pub fn stage_rewrite(
+        &mut self,
+        file_path: &str,
+        refactored_code: String,
+        operation_type: &str,
+        description: &str,
+    ) -> Result<&RefactorOperation> {
+        let original_code = std::fs::read_to_string(file_path)?;
+        self.operations.push(RefactorOperation {
+            file_path: file_path.to_string(),
+            original_code,
+            refactored_code,
+            operation_type: operation_type.to_string(),
+            description: description.to_string(),
+        });
+        Ok(&self.operations[self.operations.len() - 1])
+    }
+
     fn apply_refactor(
         &self,
         code: &str,
@@ -0,0 +1,53 @@
+//! Deterministic seeding
+//!
+//! Process-wide seed, set by `--seed` or `KANDIL_SEED`. Providers that accept
+//! a sampling seed receive it with every request, and Kandil's own random
+//! choices draw from an RNG seeded with it, so a run can be reproduced.
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::sync::OnceLock;
+
+static SEED: OnceLock<u64> = OnceLock::new();
+
+/// Fixes the seed for the rest of the process; later calls are ignored.
+pub fn set(seed: u64) {
+    let _ = SEED.set(seed);
+}
+
+/// The seed from `--seed`, falling back to `KANDIL_SEED`.
+pub fn current() -> Option<u64> {
+    SEED.get().copied().or_else(|| {
+        std::env::var("KANDIL_SEED")
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+    })
+}
+
+/// An RNG seeded from [`current`], or from entropy when no seed is set.
+pub fn rng() -> StdRng {
+    rng_from(current())
+}
+
+fn rng_from(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn the_same_seed_gives_the_same_sequence() {
+        let draw = |seed| {
+            let mut rng = rng_from(Some(seed));
+            (0..8).map(|_| rng.gen_range(0..100)).collect::<Vec<u64>>()
+        };
+        assert_eq!(draw(42), draw(42));
+        assert_ne!(draw(42), draw(43));
+    }
+}
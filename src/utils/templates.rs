@@ -1,11 +1,28 @@
 //! Template engine for generating multi-language project structures
 //!
-//! Contains functionality for creating project templates from various languages
+//! Contains functionality for creating project templates from various languages.
+//! Besides the built-in templates, users can install their own into the user
+//! templates directory (`~/.config/kandil/templates` on Linux, or
+//! `KANDIL_TEMPLATES_DIR`): each is a directory of files plus an optional
+//! `template.toml` listing the variables to ask for and the setup commands
+//! `kandil create --setup` runs afterwards.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::Datelike;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use walkdir::WalkDir;
+
+/// Names of the templates compiled into the binary.
+pub const BUILTIN_TEMPLATES: &[&str] = &["flutter", "python", "javascript", "rust"];
+
+/// Manifest file at the root of a custom template.
+pub const MANIFEST_FILE: &str = "template.toml";
 
 #[derive(Debug, Clone)]
 pub struct TemplateEngine {
@@ -17,6 +34,10 @@ pub struct Template {
     name: String,
     description: String,
     pub files: Vec<TemplateFile>,
+    variables: Vec<TemplateVariable>,
+    post_create: Vec<String>,
+    /// Directory holding a custom template's files; `None` for built-ins.
+    root: Option<PathBuf>,
 }
 
 impl Template {
@@ -27,6 +48,45 @@ impl Template {
     pub fn description(&self) -> &str {
         &self.description
     }
+
+    /// Variables to ask for besides `project_name`.
+    pub fn variables(&self) -> &[TemplateVariable] {
+        &self.variables
+    }
+
+    /// Shell commands run in the new project with `--setup`.
+    pub fn post_create(&self) -> &[String] {
+        &self.post_create
+    }
+
+    pub fn is_custom(&self) -> bool {
+        self.root.is_some()
+    }
+}
+
+/// A value a custom template asks for when a project is created.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemplateVariable {
+    pub name: String,
+    /// Question shown to the user; the variable name when absent.
+    pub prompt: Option<String>,
+    #[serde(default)]
+    pub default: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TemplateManifest {
+    description: Option<String>,
+    #[serde(default)]
+    variables: Vec<TemplateVariable>,
+    #[serde(default)]
+    hooks: TemplateHooks,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TemplateHooks {
+    #[serde(default)]
+    post_create: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -76,6 +136,9 @@ impl TemplateEngine {
                     is_executable: false,
                 },
             ],
+            variables: Vec::new(),
+            post_create: vec!["git init".to_string(), "flutter pub get".to_string()],
+            root: None,
         };
         self.templates.insert("flutter".to_string(), template);
     }
@@ -96,6 +159,9 @@ impl TemplateEngine {
                     is_executable: false,
                 },
             ],
+            variables: Vec::new(),
+            post_create: vec!["git init".to_string()],
+            root: None,
         };
         self.templates.insert("python".to_string(), template);
     }
@@ -107,7 +173,7 @@ impl TemplateEngine {
             files: vec![
                 TemplateFile {
                     path: "package.json".to_string(),
-                    content: "{\n  \"name\": \"{{project_name}}\",\n  \"version\": \"0.1.0\",\n  \"private\": true,\n  \"scripts\": {\n    \"dev\": \"next dev\",\n    \"build\": \"next build\",\n    \"start\": \"next start\",\n    \"lint\": \"next lint\"\n  },\n  \"dependencies\": {\n    \"react\": \"^18\",\n    \"react-dom\": \"^18\",\n    \"next\": \"^14\"\n  },\n  \"devDependencies\": {\n    \"eslint\": \"^8\",\n    \"eslint-config-next\": \"^14\"\n  }\n}\n".to_string(),
+                    content: "{\n  \"name\": \"{{project_name}}\",\n  \"version\": \"0.1.0\",\n  \"author\": \"{{author}}\",\n  \"private\": true,\n  \"scripts\": {\n    \"dev\": \"next dev\",\n    \"build\": \"next build\",\n    \"start\": \"next start\",\n    \"lint\": \"next lint\"\n  },\n  \"dependencies\": {\n    \"react\": \"^18\",\n    \"react-dom\": \"^18\",\n    \"next\": \"^14\"\n  },\n  \"devDependencies\": {\n    \"eslint\": \"^8\",\n    \"eslint-config-next\": \"^14\"\n  }\n}\n".to_string(),
                     is_executable: false,
                 },
                 TemplateFile {
@@ -116,6 +182,9 @@ impl TemplateEngine {
                     is_executable: false,
                 },
             ],
+            variables: Vec::new(),
+            post_create: vec!["git init".to_string(), "npm install".to_string()],
+            root: None,
         };
         self.templates.insert("javascript".to_string(), template);
     }
@@ -127,7 +196,7 @@ impl TemplateEngine {
             files: vec![
                 TemplateFile {
                     path: "Cargo.toml".to_string(),
-                    content: "[package]\nname = \"{{project_name}}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\ntokio = { version = \"1.0\", features = [\"full\"] }\nclap = { version = \"4.0\", features = [\"derive\"] }\nanyhow = \"1.0\"\n".to_string(),
+                    content: "[package]\nname = \"{{project_name}}\"\nversion = \"0.1.0\"\nauthors = [\"{{author}}\"]\nedition = \"2021\"\n\n[dependencies]\ntokio = { version = \"1.0\", features = [\"full\"] }\nclap = { version = \"4.0\", features = [\"derive\"] }\nanyhow = \"1.0\"\n".to_string(),
                     is_executable: false,
                 },
                 TemplateFile {
@@ -136,10 +205,105 @@ impl TemplateEngine {
                     is_executable: false,
                 },
             ],
+            variables: Vec::new(),
+            post_create: vec!["git init".to_string(), "cargo build".to_string()],
+            root: None,
         };
         self.templates.insert("rust".to_string(), template);
     }
 
+    /// Built-in templates plus the ones installed in the user templates directory.
+    pub fn with_user_templates() -> Result<Self> {
+        let mut engine = Self::new();
+        engine.load_custom_templates(&user_templates_dir())?;
+        Ok(engine)
+    }
+
+    /// Loads each subdirectory of `dir` as a custom template. Custom templates
+    /// replace built-ins of the same name.
+    pub fn load_custom_templates(&mut self, dir: &Path) -> Result<()> {
+        if !dir.is_dir() {
+            return Ok(());
+        }
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if !path.is_dir() || name.starts_with('.') {
+                continue;
+            }
+            match load_custom_template(name, &path) {
+                Ok(template) => {
+                    self.templates.insert(name.to_string(), template);
+                }
+                Err(err) => log::warn!("Skipping template {}: {:#}", path.display(), err),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Template> {
+        self.templates.get(name)
+    }
+
+    /// Copies a template from a local directory or clones it from a git URL
+    /// into `dir/<name>`, returning the installed path. Names of built-in
+    /// or already installed templates are refused unless `replace` is set.
+    pub fn add_custom_template(
+        dir: &Path,
+        name: &str,
+        source: &str,
+        replace: bool,
+    ) -> Result<PathBuf> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            anyhow::bail!(
+                "Invalid template name '{}': use letters, digits, '-' or '_'",
+                name
+            );
+        }
+        if BUILTIN_TEMPLATES.contains(&name) && !replace {
+            anyhow::bail!(
+                "'{}' is a built-in template; pass --override to replace it",
+                name
+            );
+        }
+        let dest = dir.join(name);
+        if dest.exists() && !replace {
+            anyhow::bail!(
+                "Template '{}' is already installed; pass --override to replace it",
+                name
+            );
+        }
+
+        let staging = dir.join(format!(".{}.staging", name));
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        fs::create_dir_all(dir)?;
+
+        let fetched = if is_git_url(source) {
+            clone_repository(source, &staging)
+        } else {
+            copy_dir(Path::new(source), &staging)
+        }
+        .and_then(|_| load_custom_template(name, &staging).map(|_| ()));
+        if let Err(err) = fetched {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(err);
+        }
+
+        if dest.exists() {
+            fs::remove_dir_all(&dest)?;
+        }
+        fs::rename(&staging, &dest)?;
+        Ok(dest)
+    }
+
     pub fn list_templates(&self) -> Vec<(&String, &str)> {
         self.templates
             .iter()
@@ -153,34 +317,624 @@ impl TemplateEngine {
         project_path: &str,
         project_name: &str,
     ) -> Result<()> {
-        if let Some(template) = self.templates.get(template_name) {
-            let project_dir = Path::new(project_path);
-            fs::create_dir_all(project_dir)?;
+        let variables = standard_variables(project_name);
+        self.create_project_with(template_name, project_path, &variables, false)
+    }
 
-            for file in &template.files {
-                // Replace template variables
-                let content = file.content.replace("{{project_name}}", project_name);
+    /// The files a project would get, without touching disk. `{{name}}` is
+    /// replaced with each variable's value in file contents and, for custom
+    /// templates, in file paths; in `.toml` and `.json` files the value is
+    /// escaped as a string. The package name in `Cargo.toml`,
+    /// `package.json` and `pubspec.yaml` is set to the project name even when
+    /// the template hard-codes one.
+    pub fn plan_project(
+        &self,
+        template_name: &str,
+        project_path: &str,
+        variables: &HashMap<String, String>,
+    ) -> Result<Vec<PlannedFile>> {
+        let template = self
+            .templates
+            .get(template_name)
+            .ok_or_else(|| anyhow::anyhow!("Template '{}' not found", template_name))?;
+        let project_dir = Path::new(project_path);
+        if let Some(root) = &template.root {
+            return plan_custom_template(root, project_dir, variables);
+        }
 
-                let file_path = project_dir.join(&file.path);
-                if let Some(parent) = file_path.parent() {
-                    fs::create_dir_all(parent)?;
+        Ok(template
+            .files
+            .iter()
+            .map(|file| {
+                let content = substitute_file(&file.path, &file.content, variables);
+                PlannedFile {
+                    path: project_dir.join(&file.path),
+                    contents: rewrite_package_name(&file.path, &content, variables).into_bytes(),
+                    executable: file.is_executable,
                 }
+            })
+            .collect())
+    }
+
+    /// Creates a project from [`Self::plan_project`]. Refuses to write into a
+    /// non-empty directory unless `force` is set, in which case files the
+    /// template also has are overwritten.
+    pub fn create_project_with(
+        &self,
+        template_name: &str,
+        project_path: &str,
+        variables: &HashMap<String, String>,
+        force: bool,
+    ) -> Result<()> {
+        let plan = self.plan_project(template_name, project_path, variables)?;
+        let project_dir = Path::new(project_path);
+        if !force {
+            check_target(project_dir, &plan)?;
+        }
+        fs::create_dir_all(project_dir)?;
 
-                fs::write(&file_path, content)?;
+        for file in &plan {
+            if let Some(parent) = file.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&file.path, &file.contents)?;
 
-                // Set executable permissions if needed (Unix systems)
-                #[cfg(unix)]
-                if file.is_executable {
-                    use std::os::unix::fs::PermissionsExt;
-                    let mut perms = fs::metadata(&file_path)?.permissions();
-                    perms.set_mode(perms.mode() | 0o111); // Add execute permissions
-                    fs::set_permissions(&file_path, perms)?;
-                }
+            #[cfg(unix)]
+            if file.executable {
+                use std::os::unix::fs::PermissionsExt;
+                let mut perms = fs::metadata(&file.path)?.permissions();
+                perms.set_mode(perms.mode() | 0o111); // Add execute permissions
+                fs::set_permissions(&file.path, perms)?;
             }
+        }
+        Ok(())
+    }
+}
+
+/// A file a template would create.
+#[derive(Debug, Clone)]
+pub struct PlannedFile {
+    /// Target path, inside the project directory.
+    pub path: PathBuf,
+    pub contents: Vec<u8>,
+    pub executable: bool,
+}
+
+impl PlannedFile {
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+}
+
+/// Fails if `project_dir` already has anything in it, naming the files the
+/// plan would overwrite.
+pub fn check_target(project_dir: &Path, plan: &[PlannedFile]) -> Result<()> {
+    let occupied = fs::read_dir(project_dir)
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false);
+    if !occupied {
+        return Ok(());
+    }
+    let clashes: Vec<String> = plan
+        .iter()
+        .filter(|file| file.exists())
+        .map(|file| file.path.display().to_string())
+        .collect();
+    if clashes.is_empty() {
+        anyhow::bail!(
+            "{} is not empty. Pass --force to create the project in it anyway",
+            project_dir.display()
+        );
+    }
+    anyhow::bail!(
+        "{} is not empty and {} of the template's files already exist there: {}. \
+         Pass --force to overwrite them",
+        project_dir.display(),
+        clashes.len(),
+        clashes.join(", ")
+    )
+}
+
+/// Variables every template can use; manifests can't redeclare them.
+const RESERVED_VARIABLES: &[&str] = &["project_name", "author", "year"];
+
+/// `project_name`, `author` (git `user.name`, else the OS user) and `year`.
+pub fn standard_variables(project_name: &str) -> HashMap<String, String> {
+    let author = Command::new("git")
+        .args(["config", "user.name"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(whoami::realname);
+
+    HashMap::from([
+        ("project_name".to_string(), project_name.to_string()),
+        ("author".to_string(), author),
+        ("year".to_string(), chrono::Local::now().year().to_string()),
+    ])
+}
 
-            Ok(())
+/// Runs a template's post-create commands in `project_dir`, stopping at the
+/// first failure. Commands go through the platform shell after substitution.
+pub fn run_post_create(
+    template: &Template,
+    project_dir: &Path,
+    variables: &HashMap<String, String>,
+) -> Result<()> {
+    for command in template.post_create() {
+        let command = substitute(command, variables);
+        println!("$ {}", command);
+        let status = if cfg!(windows) {
+            Command::new("cmd")
+                .args(["/C", &command])
+                .current_dir(project_dir)
+                .status()
         } else {
-            Err(anyhow::anyhow!("Template '{}' not found", template_name))
+            Command::new("sh")
+                .args(["-c", &command])
+                .current_dir(project_dir)
+                .status()
         }
+        .with_context(|| format!("Failed to run '{}'", command))?;
+        if !status.success() {
+            anyhow::bail!("Setup step '{}' failed ({})", command, status);
+        }
+    }
+    Ok(())
+}
+
+/// Where `kandil template add` installs templates.
+pub fn user_templates_dir() -> PathBuf {
+    match std::env::var("KANDIL_TEMPLATES_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("kandil")
+            .join("templates"),
+    }
+}
+
+fn load_custom_template(name: &str, root: &Path) -> Result<Template> {
+    let manifest_path = root.join(MANIFEST_FILE);
+    let manifest: TemplateManifest = if manifest_path.is_file() {
+        toml::from_str(&fs::read_to_string(&manifest_path)?)
+            .with_context(|| format!("Invalid {}", manifest_path.display()))?
+    } else {
+        TemplateManifest::default()
+    };
+    if let Some(variable) = manifest
+        .variables
+        .iter()
+        .find(|variable| RESERVED_VARIABLES.contains(&variable.name.as_str()))
+    {
+        anyhow::bail!(
+            "Variable '{}' is provided by kandil and can't be redeclared",
+            variable.name
+        );
+    }
+
+    Ok(Template {
+        name: name.to_string(),
+        description: manifest
+            .description
+            .unwrap_or_else(|| "Custom template".to_string()),
+        files: Vec::new(),
+        variables: manifest.variables,
+        post_create: manifest.hooks.post_create,
+        root: Some(root.to_path_buf()),
+    })
+}
+
+fn plan_custom_template(
+    root: &Path,
+    project_dir: &Path,
+    variables: &HashMap<String, String>,
+) -> Result<Vec<PlannedFile>> {
+    let mut plan = Vec::new();
+    for entry in WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+    {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(root)?;
+        if !entry.file_type().is_file() || relative == Path::new(MANIFEST_FILE) {
+            continue;
+        }
+
+        let target = project_dir.join(substitute_path(relative, variables)?);
+        let bytes = fs::read(entry.path())?;
+        let contents = match String::from_utf8(bytes) {
+            Ok(text) => {
+                let text = substitute_file(&target, &text, variables);
+                rewrite_package_name(&target, &text, variables).into_bytes()
+            }
+            // Binary assets are copied as-is.
+            Err(err) => err.into_bytes(),
+        };
+
+        #[cfg(unix)]
+        let executable = {
+            use std::os::unix::fs::PermissionsExt;
+            entry.metadata()?.permissions().mode() & 0o111 != 0
+        };
+        #[cfg(not(unix))]
+        let executable = false;
+
+        plan.push(PlannedFile {
+            path: target,
+            contents,
+            executable,
+        });
+    }
+    Ok(plan)
+}
+
+/// Replaces each `{{name}}` with its variable in a single pass, so values
+/// are never expanded again. Unknown names are left as they are.
+fn substitute(text: &str, variables: &HashMap<String, String>) -> String {
+    substitute_with(text, variables, str::to_string)
+}
+
+/// [`substitute`] for a path inside a template. A value that would add a
+/// directory or climb out of the project, such as `../x` or `..`, is an
+/// error instead.
+fn substitute_path(relative: &Path, variables: &HashMap<String, String>) -> Result<PathBuf> {
+    let text = relative.to_string_lossy();
+    for (name, value) in variables {
+        let used = text.contains(&format!("{{{{{}}}}}", name));
+        if used && (value.contains(['/', '\\']) || value == "..") {
+            anyhow::bail!(
+                "Variable '{}' can't be used in a file name: '{}' is a path",
+                name,
+                value
+            );
+        }
+    }
+    let path = PathBuf::from(substitute(&text, variables));
+    if !path
+        .components()
+        .all(|part| matches!(part, Component::Normal(_) | Component::CurDir))
+    {
+        anyhow::bail!("{} would be written outside the project", path.display());
+    }
+    Ok(path)
+}
+
+/// [`substitute`] for the contents of `path`. Values going into TOML or JSON
+/// files are escaped for the quoted strings they land in, so an author such
+/// as `Jane "JJ" Doe` can't break `Cargo.toml` or `package.json`.
+fn substitute_file(
+    path: impl AsRef<Path>,
+    text: &str,
+    variables: &HashMap<String, String>,
+) -> String {
+    if is_toml_or_json(path.as_ref()) {
+        substitute_with(text, variables, escape_string)
+    } else {
+        substitute(text, variables)
+    }
+}
+
+fn substitute_with(
+    text: &str,
+    variables: &HashMap<String, String>,
+    escape: impl Fn(&str) -> String,
+) -> String {
+    lazy_static! {
+        static ref PLACEHOLDER: Regex = Regex::new(r"\{\{(\w+)\}\}").unwrap();
+    }
+    PLACEHOLDER
+        .replace_all(text, |caps: &regex::Captures| {
+            match variables.get(&caps[1]) {
+                Some(value) => escape(value),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+fn is_toml_or_json(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("toml") | Some("json")
+    )
+}
+
+/// Escapes `value` for a double-quoted JSON string. TOML basic strings take
+/// the same escapes, plus DEL, which JSON allows raw.
+fn escape_string(value: &str) -> String {
+    let quoted = serde_json::to_string(value).unwrap_or_default();
+    quoted[1..quoted.len() - 1].replace('\u{7f}', "\\u007F")
+}
+
+/// Points a package manifest's `name` at the project, in the form each
+/// ecosystem accepts. Other files are returned unchanged.
+fn rewrite_package_name(
+    path: impl AsRef<Path>,
+    content: &str,
+    variables: &HashMap<String, String>,
+) -> String {
+    let Some(project_name) = variables.get("project_name") else {
+        return content.to_string();
+    };
+    // Only the first match is replaced: the `name` under [package] in
+    // Cargo.toml and the top-level `name` in package.json come first.
+    let (pattern, name) = match path.as_ref().file_name().and_then(|name| name.to_str()) {
+        Some("Cargo.toml") => (
+            r#"(?m)^(name\s*=\s*)"[^"]*""#,
+            format!("\"{}\"", escape_string(&project_name.replace(' ', "-"))),
+        ),
+        Some("package.json") => (
+            r#"("name"\s*:\s*)"[^"]*""#,
+            format!(
+                "\"{}\"",
+                escape_string(&project_name.to_lowercase().replace([' ', '_'], "-"))
+            ),
+        ),
+        Some("pubspec.yaml") => (
+            r"(?m)^(name:\s*)\S+",
+            project_name.to_lowercase().replace([' ', '-'], "_"),
+        ),
+        _ => return content.to_string(),
+    };
+    let regex = Regex::new(pattern).expect("valid package name pattern");
+    regex
+        .replacen(content, 1, |caps: &regex::Captures| {
+            format!("{}{}", &caps[1], name)
+        })
+        .into_owned()
+}
+
+/// Sources that don't exist locally and look like a remote are cloned.
+fn is_git_url(source: &str) -> bool {
+    if Path::new(source).exists() {
+        return false;
+    }
+    ["https://", "http://", "ssh://", "git://", "git@"]
+        .iter()
+        .any(|prefix| source.starts_with(prefix))
+        || source.ends_with(".git")
+}
+
+fn clone_repository(url: &str, dest: &Path) -> Result<()> {
+    let status = Command::new("git")
+        .args(["clone", "--depth", "1", url])
+        .arg(dest)
+        .status()
+        .context("Failed to run git; is it installed?")?;
+    if !status.success() {
+        anyhow::bail!("git clone {} failed ({})", url, status);
+    }
+    fs::remove_dir_all(dest.join(".git")).ok();
+    Ok(())
+}
+
+fn copy_dir(source: &Path, dest: &Path) -> Result<()> {
+    if !source.is_dir() {
+        anyhow::bail!("Template source {} is not a directory", source.display());
+    }
+    for entry in WalkDir::new(source)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+    {
+        let entry = entry?;
+        let target = dest.join(entry.path().strip_prefix(source)?);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else if entry.file_type().is_file() {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(label: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("kandil_{}_{}", label, uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn custom_templates_substitute_paths_and_contents() -> Result<()> {
+        let source = temp_dir("template_src");
+        fs::create_dir_all(source.join("src"))?;
+        fs::write(
+            source.join(MANIFEST_FILE),
+            "description = \"Service\"\n\n[[variables]]\nname = \"owner\"\ndefault = \"anon\"\n\n[hooks]\npost_create = [\"git init\"]\n",
+        )?;
+        fs::write(
+            source.join("src/{{project_name}}.txt"),
+            "{{project_name}} by {{owner}}",
+        )?;
+
+        let templates_dir = temp_dir("templates");
+        TemplateEngine::add_custom_template(
+            &templates_dir,
+            "service",
+            &source.to_string_lossy(),
+            false,
+        )?;
+        let mut engine = TemplateEngine::new();
+        engine.load_custom_templates(&templates_dir)?;
+        let template = engine.get("service").unwrap();
+        assert!(template.is_custom());
+        assert_eq!(template.variables()[0].default, "anon");
+        assert_eq!(template.post_create(), ["git init".to_string()]);
+
+        let project = temp_dir("project");
+        let variables = HashMap::from([
+            ("project_name".to_string(), "demo".to_string()),
+            ("owner".to_string(), "ada".to_string()),
+        ]);
+        engine.create_project_with("service", &project.to_string_lossy(), &variables, false)?;
+        assert_eq!(
+            fs::read_to_string(project.join("src/demo.txt"))?,
+            "demo by ada"
+        );
+        assert!(!project.join(MANIFEST_FILE).exists());
+
+        for dir in [source, templates_dir, project] {
+            let _ = fs::remove_dir_all(dir);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn variables_cannot_move_files_out_of_the_project() {
+        let path = Path::new("src/{{project_name}}.rs");
+        let with = |value: &str| HashMap::from([("project_name".to_string(), value.to_string())]);
+        assert_eq!(
+            substitute_path(path, &with("demo")).unwrap(),
+            PathBuf::from("src/demo.rs")
+        );
+        for value in ["../../etc/demo", "nested/demo", "nested\\demo"] {
+            let err = substitute_path(path, &with(value)).unwrap_err();
+            assert!(err.to_string().contains("is a path"), "{}", value);
+        }
+        let err = substitute_path(Path::new("{{project_name}}/main.rs"), &with("..")).unwrap_err();
+        assert!(err.to_string().contains("is a path"));
+
+        // Only variables the path uses are checked
+        let variables = HashMap::from([("description".to_string(), "a/b".to_string())]);
+        assert!(substitute_path(path, &variables).is_ok());
+    }
+
+    #[test]
+    fn non_empty_directories_need_force() -> Result<()> {
+        let engine = TemplateEngine::new();
+        let project = temp_dir("project");
+        let path = project.to_string_lossy().to_string();
+        let variables = standard_variables("demo");
+
+        let plan = engine.plan_project("rust", &path, &variables)?;
+        assert!(plan
+            .iter()
+            .any(|file| file.path == project.join("Cargo.toml")));
+        assert!(!project.exists(), "planning writes nothing");
+
+        fs::create_dir_all(&project)?;
+        fs::write(project.join("notes.txt"), "keep")?;
+        let err = engine
+            .create_project_with("rust", &path, &variables, false)
+            .unwrap_err();
+        assert!(err.to_string().contains("is not empty. Pass --force"));
+
+        fs::write(project.join("Cargo.toml"), "mine")?;
+        let err = engine
+            .create_project_with("rust", &path, &variables, false)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("1 of the template's files already exist"));
+        assert_eq!(fs::read_to_string(project.join("Cargo.toml"))?, "mine");
+
+        engine.create_project_with("rust", &path, &variables, true)?;
+        assert!(fs::read_to_string(project.join("Cargo.toml"))?.contains("name = \"demo\""));
+        assert_eq!(fs::read_to_string(project.join("notes.txt"))?, "keep");
+
+        let _ = fs::remove_dir_all(project);
+        Ok(())
+    }
+
+    #[test]
+    fn builtin_names_need_override() -> Result<()> {
+        let source = temp_dir("template_src");
+        fs::create_dir_all(&source)?;
+        fs::write(source.join("README.md"), "{{project_name}}")?;
+        let templates_dir = temp_dir("templates");
+        let source_str = source.to_string_lossy().to_string();
+
+        assert!(
+            TemplateEngine::add_custom_template(&templates_dir, "rust", &source_str, false)
+                .is_err()
+        );
+        assert!(
+            TemplateEngine::add_custom_template(&templates_dir, "../evil", &source_str, true)
+                .is_err()
+        );
+        TemplateEngine::add_custom_template(&templates_dir, "rust", &source_str, true)?;
+
+        let mut engine = TemplateEngine::new();
+        engine.load_custom_templates(&templates_dir)?;
+        assert!(engine.get("rust").unwrap().is_custom());
+
+        for dir in [source, templates_dir] {
+            let _ = fs::remove_dir_all(dir);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn substitution_is_a_single_pass() {
+        let variables = HashMap::from([
+            ("a".to_string(), "{{b}}".to_string()),
+            ("b".to_string(), "x".to_string()),
+        ]);
+        assert_eq!(
+            substitute("{{a}} {{b}} {{missing}} {b}", &variables),
+            "{{b}} x {{missing}} {b}"
+        );
+    }
+
+    #[test]
+    fn values_are_escaped_for_toml_and_json_manifests() -> Result<()> {
+        let engine = TemplateEngine::new();
+        let mut variables = standard_variables("demo");
+        let author = "Jane \"JJ\" Doe \\ Co";
+        variables.insert("author".to_string(), author.to_string());
+
+        let plan = engine.plan_project("rust", "demo", &variables)?;
+        let cargo = plan
+            .iter()
+            .find(|file| file.path.ends_with("Cargo.toml"))
+            .unwrap();
+        let manifest: toml::Value = toml::from_str(std::str::from_utf8(&cargo.contents)?)?;
+        assert_eq!(manifest["package"]["authors"][0].as_str(), Some(author));
+
+        let plan = engine.plan_project("javascript", "demo", &variables)?;
+        let package = plan
+            .iter()
+            .find(|file| file.path.ends_with("package.json"))
+            .unwrap();
+        let manifest: serde_json::Value = serde_json::from_slice(&package.contents)?;
+        assert_eq!(manifest["author"], author);
+
+        assert_eq!(
+            substitute_file("README.md", "by {{author}}", &variables),
+            format!("by {}", author)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn package_names_follow_the_project() {
+        let variables = standard_variables("my app");
+        assert!(!variables["author"].is_empty());
+
+        let cargo = "[package]\nname = \"placeholder\"\n\n[[bin]]\nname = \"tool\"\n";
+        assert_eq!(
+            rewrite_package_name("Cargo.toml", cargo, &variables),
+            "[package]\nname = \"my-app\"\n\n[[bin]]\nname = \"tool\"\n"
+        );
+        assert_eq!(
+            rewrite_package_name("web/package.json", "{\n  \"name\": \"x\"\n}", &variables),
+            "{\n  \"name\": \"my-app\"\n}"
+        );
+        assert_eq!(
+            rewrite_package_name(
+                "pubspec.yaml",
+                "name: project_name_placeholder\n",
+                &variables
+            ),
+            "name: my_app\n"
+        );
+        assert_eq!(
+            rewrite_package_name("README.md", "name: x", &variables),
+            "name: x"
+        );
     }
 }
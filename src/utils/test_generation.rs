@@ -27,10 +27,17 @@ impl TestGenerator {
             .unwrap_or("");
 
         let (language, framework) = match (file_ext, test_framework) {
-            ("rs", "") | ("rs", "rust") => ("Rust", "Rust's built-in testing framework"),
-            ("py", "") | ("py", "pytest") => ("Python", "pytest"),
-            ("js", "") | ("js", "jest") => ("JavaScript", "Jest"),
-            ("dart", "") | ("dart", "flutter") => ("Dart", "Flutter's testing framework"),
+            ("rs", "") | (_, "rust") => ("Rust", "Rust's built-in testing framework"),
+            ("py", "") | (_, "pytest") => ("Python", "pytest"),
+            ("ts" | "tsx", "" | "jest") => ("TypeScript", "Jest"),
+            ("ts" | "tsx", "vitest") => ("TypeScript", "Vitest"),
+            ("js", "") | (_, "jest") => ("JavaScript", "Jest"),
+            (_, "vitest") => ("JavaScript", "Vitest"),
+            (_, "mocha") => ("JavaScript", "Mocha"),
+            ("dart", "") | (_, "flutter") => ("Dart", "Flutter's testing framework"),
+            ("go", "") | (_, "go test") => ("Go", "the testing package"),
+            ("kt" | "kts", "junit") => ("Kotlin", "JUnit"),
+            (_, "junit") => ("Java", "JUnit"),
             (_, _) => ("Generic", "appropriate testing framework"),
         };
 
@@ -0,0 +1,135 @@
+//! Token counting
+//!
+//! Counts text with the tokenizer closest to the provider's own. OpenAI models
+//! go through tiktoken's BPE for that model. Claude, Qwen and models tiktoken
+//! doesn't know have no public tokenizer here, so `cl100k_base` stands in and
+//! the count is marked approximate. Ollama counts with the model itself via
+//! `/api/embed`, which reports `prompt_eval_count` without generating text;
+//! when Ollama can't be reached the BPE count is used instead.
+
+use crate::core::adapters::ai::http::shared_client;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tiktoken_rs::CoreBPE;
+
+const OLLAMA_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A token count and how it was made.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenCount {
+    pub tokens: usize,
+    /// The tokenizer used, e.g. `gpt-4o (tiktoken)` or `cl100k_base`.
+    pub tokenizer: String,
+    /// Whether `tokenizer` is the one the provider bills with.
+    pub exact: bool,
+}
+
+/// Counts `text` with tiktoken's tokenizer for `model`, or `cl100k_base`
+/// when tiktoken doesn't know the model.
+pub fn bpe_count(model: &str, text: &str) -> TokenCount {
+    match bpe(model) {
+        Some(bpe) => TokenCount {
+            tokens: bpe.encode_with_special_tokens(text).len(),
+            tokenizer: format!("{} (tiktoken)", model),
+            exact: true,
+        },
+        None => approximate(text),
+    }
+}
+
+/// Counts `text` the way `provider` would for `model`. `ollama_endpoint` is
+/// only used for Ollama.
+pub async fn count(provider: &str, model: &str, ollama_endpoint: &str, text: &str) -> TokenCount {
+    match provider {
+        "openai" => bpe_count(model, text),
+        "ollama" => match ollama_count(ollama_endpoint, model, text).await {
+            Ok(tokens) => TokenCount {
+                tokens,
+                tokenizer: format!("{} (ollama)", model),
+                exact: true,
+            },
+            Err(err) => {
+                log::warn!("Cannot count tokens with Ollama: {:#}", err);
+                approximate(text)
+            }
+        },
+        _ => approximate(text),
+    }
+}
+
+/// `cl100k_base`, or a word count if the bundled tokenizer can't be loaded.
+fn approximate(text: &str) -> TokenCount {
+    match cl100k() {
+        Some(bpe) => TokenCount {
+            tokens: bpe.encode_with_special_tokens(text).len(),
+            tokenizer: "cl100k_base".to_string(),
+            exact: false,
+        },
+        None => TokenCount {
+            tokens: text.split_whitespace().count(),
+            tokenizer: "word count".to_string(),
+            exact: false,
+        },
+    }
+}
+
+#[derive(Deserialize)]
+struct EmbedResponse {
+    prompt_eval_count: Option<usize>,
+}
+
+async fn ollama_count(endpoint: &str, model: &str, text: &str) -> Result<usize> {
+    let response: EmbedResponse = shared_client()
+        .post(format!("{}/api/embed", endpoint))
+        .timeout(OLLAMA_TIMEOUT)
+        .json(&serde_json::json!({ "model": model, "input": text, "truncate": false }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    response
+        .prompt_eval_count
+        .context("Ollama did not report prompt_eval_count; it may be too old")
+}
+
+/// tiktoken's BPE for `model`, built once per model.
+fn bpe(model: &str) -> Option<Arc<CoreBPE>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Option<Arc<CoreBPE>>>>> = OnceLock::new();
+    let mut cache = CACHE.get_or_init(Default::default).lock().ok()?;
+    cache
+        .entry(model.to_string())
+        .or_insert_with(|| tiktoken_rs::get_bpe_from_model(model).ok().map(Arc::new))
+        .clone()
+}
+
+fn cl100k() -> Option<&'static CoreBPE> {
+    static CL100K: OnceLock<Option<CoreBPE>> = OnceLock::new();
+    CL100K
+        .get_or_init(|| tiktoken_rs::cl100k_base().ok())
+        .as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_models_count_exactly_and_others_approximately() {
+        let openai = bpe_count("gpt-4", "Hello, world!");
+        assert_eq!(openai.tokens, 4);
+        assert!(openai.exact);
+
+        let unknown = bpe_count("claude-3-5-sonnet", "Hello, world!");
+        assert_eq!(unknown.tokens, 4);
+        assert_eq!(unknown.tokenizer, "cl100k_base");
+        assert!(!unknown.exact);
+
+        // Unlike whitespace splitting, BPE counts the pieces of long words.
+        let long = bpe_count("gpt-4", "antidisestablishmentarianism");
+        assert!(long.tokens > 1);
+    }
+}
@@ -0,0 +1,32 @@
+//! Output verbosity
+//!
+//! Process-wide level from the global `-v` flag. At `-v` commands explain what
+//! they are doing on stderr: routing decisions, retries, cache use, context
+//! sizes and timings. `-vv` additionally enables debug logging. Without the
+//! flag these diagnostics stay quiet.
+
+use std::sync::OnceLock;
+
+static LEVEL: OnceLock<u8> = OnceLock::new();
+
+/// Fixes the level for the rest of the process; later calls are ignored.
+pub fn set(level: u8) {
+    let _ = LEVEL.set(level);
+}
+
+pub fn level() -> u8 {
+    LEVEL.get().copied().unwrap_or(0)
+}
+
+/// Whether `-v` was given.
+pub fn enabled() -> bool {
+    level() > 0
+}
+
+/// Prints a diagnostic line to stderr when `-v` was given, so it never mixes
+/// with a command's regular output.
+pub fn note(message: impl AsRef<str>) {
+    if enabled() {
+        eprintln!("· {}", message.as_ref());
+    }
+}
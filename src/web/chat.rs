@@ -0,0 +1,206 @@
+//! Chat API
+//!
+//! `POST /api/chat` sends one message to the configured (or requested)
+//! provider. The route is rate-limited per client IP with a token bucket so a
+//! runaway script can't flood the model; clients over the limit get 429 with
+//! `Retry-After`. Oversized bodies are refused with 413 and invalid requests
+//! with 400 before anything reaches a provider.
+
+use crate::core::adapters::ai::factory::AIProviderFactory;
+use crate::utils::config::Config;
+use crate::utils::rate_limit::TokenBucketLimiter;
+use axum::{
+    extract::{rejection::JsonRejection, ConnectInfo, DefaultBodyLimit, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+pub struct ChatRequest {
+    pub message: String,
+    /// Defaults to the configured provider.
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Defaults to the configured model.
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChatResponse {
+    pub provider: String,
+    pub model: String,
+    pub response: String,
+}
+
+pub struct ChatState {
+    config: Config,
+    limiter: TokenBucketLimiter,
+}
+
+impl ChatState {
+    pub fn new(config: Config) -> Self {
+        let limiter = TokenBucketLimiter::new(config.web.chat_requests_per_minute);
+        Self { config, limiter }
+    }
+}
+
+pub fn router(state: Arc<ChatState>) -> Router {
+    Router::new()
+        .route("/api/chat", post(chat))
+        .layer(DefaultBodyLimit::max(state.config.web.max_body_bytes))
+        .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit))
+        .route_layer(middleware::from_fn(super::per_request_budget))
+        .with_state(state)
+}
+
+async fn rate_limit(State(state): State<Arc<ChatState>>, request: Request, next: Next) -> Response {
+    // Without connection info (e.g. in-process tests) all callers share a bucket.
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    match state.limiter.try_acquire(&client) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            let secs = retry_after.as_secs() + 1;
+            let mut response = error(
+                StatusCode::TOO_MANY_REQUESTS,
+                format!("Rate limit exceeded; retry in {}s", secs),
+            );
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+            response
+        }
+    }
+}
+
+async fn chat(
+    State(state): State<Arc<ChatState>>,
+    payload: Result<Json<ChatRequest>, JsonRejection>,
+) -> Response {
+    let request = match payload {
+        Ok(Json(request)) => request,
+        Err(rejection) => {
+            let status = if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE {
+                StatusCode::PAYLOAD_TOO_LARGE
+            } else {
+                StatusCode::BAD_REQUEST
+            };
+            return error(status, rejection.body_text());
+        }
+    };
+    let (provider, model) = match validate(&state.config, &request) {
+        Ok(selection) => selection,
+        Err(message) => return error(StatusCode::BAD_REQUEST, message),
+    };
+
+    let factory = AIProviderFactory::new(state.config.clone());
+    let ai = match factory.create_ai(&provider, &model) {
+        Ok(ai) => ai,
+        Err(err) => return error(StatusCode::BAD_REQUEST, err.to_string()),
+    };
+    match ai.chat(&request.message).await {
+        Ok(response) => Json(ChatResponse {
+            provider,
+            model,
+            response,
+        })
+        .into_response(),
+        Err(err) => error(StatusCode::BAD_GATEWAY, format!("{:#}", err)),
+    }
+}
+
+/// Resolves the provider and model for a request, or explains what's wrong
+/// with it. A model named in the request must suit the provider (see
+/// [`Config::resolve_selection`]); the configured model was already checked
+/// when the config was loaded.
+fn validate(config: &Config, request: &ChatRequest) -> Result<(String, String), String> {
+    if request.message.trim().is_empty() {
+        return Err("`message` must not be empty".to_string());
+    }
+
+    config.resolve_selection(request.provider.as_deref(), request.model.as_deref())
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn over_limit_requests_get_retry_after() {
+        let mut config = Config::default();
+        config.web.chat_requests_per_minute = 1;
+        let app = router(Arc::new(ChatState::new(config)));
+        let request = || {
+            axum::http::Request::post("/api/chat")
+                .header(header::CONTENT_TYPE, "application/json")
+                // An unknown provider fails fast without a network call.
+                .body(Body::from(r#"{"message":"hi","provider":"nope"}"#))
+                .unwrap()
+        };
+
+        let first = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(first.status(), StatusCode::BAD_REQUEST);
+        let second = app.oneshot(request()).await.unwrap();
+        assert_eq!(second.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(second.headers().contains_key(header::RETRY_AFTER));
+    }
+
+    async fn post(config: Config, body: String) -> (StatusCode, String) {
+        let response = router(Arc::new(ChatState::new(config)))
+            .oneshot(
+                axum::http::Request::post("/api/chat")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    #[tokio::test]
+    async fn bad_requests_are_rejected_before_the_model() {
+        let (status, body) = post(Config::default(), r#"{"message":"  "}"#.to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("must not be empty"));
+
+        let (status, body) = post(Config::default(), "{not json".to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("error"));
+
+        let unregistered = r#"{"message":"hi","provider":"ollama","model":"qwen2.5-coder-9b-q4"}"#;
+        let (status, body) = post(Config::default(), unregistered.to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("not in the model registry"));
+    }
+
+    #[tokio::test]
+    async fn oversized_bodies_get_413() {
+        let mut config = Config::default();
+        config.web.max_body_bytes = 64;
+        let body = format!(r#"{{"message":"{}"}}"#, "x".repeat(1024));
+        let (status, _) = post(config, body).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -8,6 +8,7 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::broadcast;
 
 // Data structures for the web dashboard
@@ -79,24 +80,31 @@ impl Default for SystemStats {
 }
 
 pub struct WebCompanionDashboard {
-    port: u16,
-    state: WebAppState,
+    state: Arc<WebAppState>,
+}
+
+impl Default for WebCompanionDashboard {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl WebCompanionDashboard {
-    pub fn new(port: u16) -> Self {
+    pub fn new() -> Self {
         let (tx, _) = broadcast::channel(100);
         let state = WebAppState {
             session_state: tokio::sync::RwLock::new(CliSessionState::default()),
             tx,
         };
-        
-        Self { port, state }
+
+        Self {
+            state: Arc::new(state),
+        }
     }
 
-    pub async fn run(&self) -> Result<(), hyper::Error> {
-        let app_state = self.state.clone();
-        let app = Router::new()
+    /// Dashboard pages and session API, to be merged into the server's router.
+    pub fn router(&self) -> Router {
+        Router::new()
             .route("/", get(root))
             .route("/dashboard", get(dashboard))
             .route("/api/session", get(get_session_state))
@@ -105,13 +113,7 @@ impl WebCompanionDashboard {
             .route("/api/stats", get(get_system_stats))
             .route("/api/history", get(get_command_history))
             .route("/api/ai", get(get_ai_interactions))
-            .with_state(app_state);
-
-        let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", self.port))
-            .await
-            .unwrap();
-        
-        axum::serve(listener, app).await
+            .with_state(self.state.clone())
     }
     
     /// Update the session state from CLI events
@@ -160,20 +162,20 @@ impl WebCompanionDashboard {
 
 // Request handlers for the web API
 
-async fn root(State(_state): State<WebAppState>) -> Html<String> {
+async fn root(State(_state): State<Arc<WebAppState>>) -> Html<String> {
     Html(include_str!("../web/static/index.html").to_string())
 }
 
-async fn dashboard(State(_state): State<WebAppState>) -> Html<String> {
+async fn dashboard(State(_state): State<Arc<WebAppState>>) -> Html<String> {
     Html("<h1>Dashboard</h1>".to_string())
 }
 
-async fn get_session_state(State(state): State<WebAppState>) -> Json<CliSessionState> {
+async fn get_session_state(State(state): State<Arc<WebAppState>>) -> Json<CliSessionState> {
     let session_state = state.session_state.read().await;
     Json(session_state.clone())
 }
 
-async fn get_system_stats(State(_state): State<WebAppState>) -> Json<SystemStats> {
+async fn get_system_stats(State(_state): State<Arc<WebAppState>>) -> Json<SystemStats> {
     // In a real implementation, this would gather actual system stats
     Json(SystemStats {
         cpu_usage: 25.0, // Placeholder value
@@ -184,7 +186,7 @@ async fn get_system_stats(State(_state): State<WebAppState>) -> Json<SystemStats
 }
 
 async fn get_command_history(
-    State(state): State<WebAppState>,
+    State(state): State<Arc<WebAppState>>,
     Query(params): Query<HashMap<String, String>>,
 ) -> Json<Vec<CommandEntry>> {
     let session_state = state.session_state.read().await;
@@ -199,7 +201,7 @@ async fn get_command_history(
 }
 
 async fn get_ai_interactions(
-    State(state): State<WebAppState>,
+    State(state): State<Arc<WebAppState>>,
     Query(params): Query<HashMap<String, String>>,
 ) -> Json<Vec<AiInteraction>> {
     let session_state = state.session_state.read().await;
@@ -214,7 +216,7 @@ async fn get_ai_interactions(
 }
 
 async fn update_session_state(
-    State(state): State<WebAppState>,
+    State(state): State<Arc<WebAppState>>,
     Json(payload): Json<serde_json::Value>,
 ) -> Result<Json<CliSessionState>, StatusCode> {
     // In a real implementation, this would allow external updates to session state
@@ -232,14 +234,14 @@ async fn update_session_state(
 
 async fn websocket_handler(
     ws: WebSocketUpgrade,
-    State(state): State<WebAppState>,
+    State(state): State<Arc<WebAppState>>,
 ) -> impl IntoResponse {
     ws.on_upgrade(|websocket| handle_websocket(websocket, state))
 }
 
 async fn handle_websocket(
     mut websocket: axum::extract::ws::WebSocket,
-    state: WebAppState,
+    state: Arc<WebAppState>,
 ) {
     let mut rx = state.tx.subscribe();
     
@@ -279,8 +281,3 @@ async fn handle_websocket(
         }
     }
 }
-
-// Utility functions
-pub async fn launch_web_dashboard(port: u16) -> WebCompanionDashboard {
-    WebCompanionDashboard::new(port)
-}
\ No newline at end of file
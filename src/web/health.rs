@@ -0,0 +1,167 @@
+//! Liveness and readiness probes
+//!
+//! `/healthz` answers as long as the server is serving. `/readyz` checks that
+//! the configured AI provider can take requests: local runtimes are probed via
+//! [`CrossPlatformBenchmark::check_connectivity`], cloud providers via the
+//! startup preflight (API key present, API reachable). Results are cached
+//! briefly so frequent probes don't hammer the provider.
+
+use crate::benchmark::CrossPlatformBenchmark;
+use crate::core::adapters::ai::KandilAI;
+use crate::utils::config::Config;
+use crate::utils::{offline, preflight};
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+const READINESS_TTL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub provider: String,
+    pub model: String,
+    /// Checks that failed; empty when ready.
+    pub failing: Vec<FailedCheck>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedCheck {
+    pub name: String,
+    pub detail: String,
+}
+
+/// Readiness checker shared by the probe handlers.
+pub struct Readiness {
+    benchmark: CrossPlatformBenchmark,
+    last: Mutex<Option<(Instant, ReadinessReport)>>,
+}
+
+impl Readiness {
+    pub fn new() -> Self {
+        Self {
+            benchmark: CrossPlatformBenchmark::new(),
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Returns the cached report while it's fresh, checking again otherwise.
+    /// Concurrent probes wait for the check in flight instead of starting their own.
+    pub async fn report(&self) -> ReadinessReport {
+        let mut last = self.last.lock().await;
+        if let Some((checked_at, report)) = last.as_ref() {
+            if checked_at.elapsed() < READINESS_TTL {
+                return report.clone();
+            }
+        }
+        let report = self.check().await;
+        *last = Some((Instant::now(), report.clone()));
+        report
+    }
+
+    async fn check(&self) -> ReadinessReport {
+        let config = match Config::load() {
+            Ok(config) => config,
+            Err(err) => {
+                return ReadinessReport {
+                    ready: false,
+                    provider: String::new(),
+                    model: String::new(),
+                    failing: vec![FailedCheck {
+                        name: "config".to_string(),
+                        detail: format!("{:#}", err),
+                    }],
+                }
+            }
+        };
+
+        let mut failing = Vec::new();
+        if offline::is_cloud_provider(&config.ai_provider) {
+            if offline::is_enabled() {
+                failing.push(FailedCheck {
+                    name: "provider".to_string(),
+                    detail: format!("Offline mode is on; {} is unavailable", config.ai_provider),
+                });
+            } else if let Some(advice) =
+                preflight::check_provider(&config.ai_provider, &config.ai_model).await
+            {
+                failing.push(FailedCheck {
+                    name: "provider".to_string(),
+                    detail: advice,
+                });
+            }
+        } else {
+            match KandilAI::new(config.ai_provider.clone(), config.ai_model.clone()) {
+                Ok(ai) => {
+                    let connectivity = self.benchmark.check_connectivity().await;
+                    // In-process runtimes have no endpoint and are always ready.
+                    if let Some(endpoint) = connectivity
+                        .endpoints
+                        .iter()
+                        .find(|endpoint| endpoint.provider == ai.provider_name())
+                        .filter(|endpoint| !endpoint.reachable)
+                    {
+                        failing.push(FailedCheck {
+                            name: "provider".to_string(),
+                            detail: format!(
+                                "{} is not reachable at {}",
+                                endpoint.name, endpoint.endpoint
+                            ),
+                        });
+                    }
+                }
+                Err(err) => failing.push(FailedCheck {
+                    name: "provider".to_string(),
+                    detail: err.to_string(),
+                }),
+            }
+        }
+
+        ReadinessReport {
+            ready: failing.is_empty(),
+            provider: config.ai_provider,
+            model: config.ai_model,
+            failing,
+        }
+    }
+}
+
+pub fn router(readiness: Arc<Readiness>) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(readiness)
+}
+
+async fn healthz() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+async fn readyz(State(readiness): State<Arc<Readiness>>) -> (StatusCode, Json<ReadinessReport>) {
+    let report = readiness.report().await;
+    let status = if report.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn healthz_is_always_ok() {
+        let response = router(Arc::new(Readiness::new()))
+            .oneshot(Request::get("/healthz").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
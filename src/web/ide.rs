@@ -0,0 +1,518 @@
+//! IDE extension server
+//!
+//! `kandil agent advanced-features ide serve` exposes [`IdeExtension`] over
+//! HTTP so editor plugins can call it without spawning the CLI per request.
+//! Each method is a `POST` under `/api/ide/`, `GET /api/ide` describes the
+//! endpoints and their bodies, and `/api/ide/ws` is a WebSocket that sends
+//! each suggestion as its own message once the model has produced them.
+//! Browser-based clients (webviews) must send an `Origin` listed in
+//! `[web].allowed_origins`; other origins get 403, which also keeps arbitrary
+//! web pages from driving the model.
+//!
+//! With `[web].ide_token` set, every request must also carry it as
+//! `Authorization: Bearer <token>`. The server refuses to listen on anything
+//! but a loopback address without one.
+
+use crate::core::agents::ide_extension::{ExtensionContext, IdeExtension};
+use crate::utils::config::WebConfig;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{
+        rejection::JsonRejection,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        DefaultBodyLimit, Request, State,
+    },
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+pub struct CodeRequest {
+    pub code: String,
+    pub language: String,
+}
+
+pub struct IdeState {
+    extension: IdeExtension,
+    allowed_origins: Vec<String>,
+    token: Option<String>,
+}
+
+impl IdeState {
+    pub fn new(extension: IdeExtension, allowed_origins: Vec<String>) -> Self {
+        Self {
+            extension,
+            allowed_origins,
+            token: None,
+        }
+    }
+
+    /// Requires `token` as a bearer token on every request.
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    fn allows(&self, origin: &str) -> bool {
+        self.allowed_origins
+            .iter()
+            .any(|allowed| allowed == "*" || allowed.trim_end_matches('/') == origin)
+    }
+}
+
+pub fn router(state: Arc<IdeState>, max_body_bytes: usize) -> Router {
+    Router::new()
+        .route("/api/ide", get(describe))
+        .route("/api/ide/suggestions", post(suggestions))
+        .route("/api/ide/docs", post(docs))
+        .route("/api/ide/refactor", post(refactor))
+        .route("/api/ide/review", post(review))
+        .route("/api/ide/tests", post(test_suggestions))
+        .route("/api/ide/explain", post(explain))
+        .route("/api/ide/ws", get(websocket))
+        .layer(DefaultBodyLimit::max(max_body_bytes))
+        .layer(middleware::from_fn(super::per_request_budget))
+        .layer(middleware::from_fn_with_state(state.clone(), authorize))
+        // `layer`, not `route_layer`, so preflight requests reach it too.
+        .layer(middleware::from_fn_with_state(state.clone(), cors))
+        .with_state(state)
+}
+
+/// Binds `address` and serves the IDE API until Ctrl+C.
+pub async fn serve(address: &str, extension: IdeExtension, web: WebConfig) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .with_context(|| format!("Failed to bind {}", address))?;
+    let local = listener.local_addr()?;
+    if !local.ip().is_loopback() && web.ide_token.is_none() {
+        anyhow::bail!(
+            "Refusing to serve the IDE API on {} without a token, since anyone who can reach \
+             it could drive the model. Set ide_token under [web] in the global config or \
+             KANDIL_IDE_TOKEN, or keep --host 127.0.0.1",
+            local
+        );
+    }
+    println!("Kandil IDE server listening on http://{}", address);
+    println!("Endpoints: GET /api/ide describes the API; WebSocket at /api/ide/ws");
+    if web.allowed_origins.is_empty() {
+        println!("Browser origins: none allowed (add them under [web].allowed_origins)");
+    } else {
+        println!("Browser origins: {}", web.allowed_origins.join(", "));
+    }
+    if web.ide_token.is_some() {
+        println!("Requests must send Authorization: Bearer <ide_token>");
+    }
+
+    let shutdown = crate::shutdown::ShutdownHandler::new();
+    shutdown
+        .setup_signal_handlers()
+        .await
+        .map_err(|err| anyhow::anyhow!("Cannot install Ctrl+C handler: {}", err))?;
+
+    let state = Arc::new(IdeState::new(extension, web.allowed_origins).with_token(web.ide_token));
+    axum::serve(listener, router(state, web.max_body_bytes))
+        .with_graceful_shutdown(async move { shutdown.wait_for_shutdown().await })
+        .await?;
+    Ok(())
+}
+
+/// With a token configured, turns away requests that don't carry it.
+/// Preflight requests never do, so [`cors`] answers them first.
+async fn authorize(State(state): State<Arc<IdeState>>, request: Request, next: Next) -> Response {
+    let Some(token) = state.token.as_deref() else {
+        return next.run(request).await;
+    };
+    let given = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match given {
+        Some(given) if same_token(token, given) => next.run(request).await,
+        _ => error(
+            StatusCode::UNAUTHORIZED,
+            "Send the configured ide_token as Authorization: Bearer <token>".to_string(),
+        ),
+    }
+}
+
+/// Compares tokens without stopping at the first differing byte.
+fn same_token(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Requests without an `Origin` pass untouched. Allowed origins get CORS
+/// headers, and their preflight requests are answered here.
+async fn cors(State(state): State<Arc<IdeState>>, request: Request, next: Next) -> Response {
+    let Some(origin) = request.headers().get(header::ORIGIN).cloned() else {
+        return next.run(request).await;
+    };
+    let origin_text = origin.to_str().unwrap_or_default();
+    if !state.allows(origin_text) {
+        return error(
+            StatusCode::FORBIDDEN,
+            format!("Origin {} is not allowed", origin_text),
+        );
+    }
+
+    let preflight = request.method() == Method::OPTIONS;
+    let mut response = if preflight {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        next.run(request).await
+    };
+    let headers = response.headers_mut();
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    headers.insert(header::VARY, HeaderValue::from_static("Origin"));
+    if preflight {
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            HeaderValue::from_static("GET, POST, OPTIONS"),
+        );
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_HEADERS,
+            HeaderValue::from_static("authorization, content-type"),
+        );
+        headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from(600));
+    }
+    response
+}
+
+async fn describe(State(state): State<Arc<IdeState>>) -> Json<Value> {
+    let code = json!({ "code": "string", "language": "string" });
+    let context = json!({
+        "file_path": "string",
+        "language": "string",
+        "selected_code": "string",
+        "cursor_position": ["line", "column"],
+        "workspace_root": "string",
+    });
+    let endpoint = |path: &str, request: &Value, response: &str| {
+        let mut endpoint = json!({ "method": "POST", "path": path });
+        endpoint["request"] = request.clone();
+        endpoint["response"] = json!(response);
+        endpoint
+    };
+    Json(json!({
+        "name": state.extension.name,
+        "version": state.extension.version,
+        "features": state.extension.get_supported_features(),
+        "endpoints": [
+            endpoint("/api/ide/suggestions", &context, "array of suggestions"),
+            endpoint("/api/ide/docs", &code, "{ \"documentation\": string }"),
+            endpoint("/api/ide/refactor", &code, "{ \"options\": [string] }"),
+            endpoint("/api/ide/review", &code, "array of inline comments"),
+            endpoint("/api/ide/tests", &code, "{ \"tests\": string }"),
+            endpoint("/api/ide/explain", &code, "{ \"explanation\": string }"),
+            {
+                "method": "GET",
+                "path": "/api/ide/ws",
+                "protocol": "websocket",
+                "request": context,
+                "response": "once all are ready, one { \"suggestion\": ... } message per \
+                             suggestion, then { \"done\": true, \"count\": n }",
+            },
+        ],
+        "errors": "non-2xx responses carry { \"error\": string }",
+        "auth": "with [web].ide_token set, every request needs Authorization: Bearer <token>",
+    }))
+}
+
+async fn suggestions(
+    State(state): State<Arc<IdeState>>,
+    payload: Result<Json<ExtensionContext>, JsonRejection>,
+) -> Response {
+    let context = match parse(payload) {
+        Ok(context) => context,
+        Err(response) => return response,
+    };
+    match state.extension.get_code_suggestions(&context).await {
+        Ok(suggestions) => Json(suggestions).into_response(),
+        Err(err) => model_error(err),
+    }
+}
+
+async fn docs(
+    State(state): State<Arc<IdeState>>,
+    payload: Result<Json<CodeRequest>, JsonRejection>,
+) -> Response {
+    let request = match parse(payload) {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+    match state
+        .extension
+        .generate_documentation(&request.code, &request.language)
+        .await
+    {
+        Ok(documentation) => Json(json!({ "documentation": documentation })).into_response(),
+        Err(err) => model_error(err),
+    }
+}
+
+async fn refactor(
+    State(state): State<Arc<IdeState>>,
+    payload: Result<Json<CodeRequest>, JsonRejection>,
+) -> Response {
+    let request = match parse(payload) {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+    match state
+        .extension
+        .get_refactoring_options(&request.code, &request.language)
+        .await
+    {
+        Ok(options) => Json(json!({ "options": options })).into_response(),
+        Err(err) => model_error(err),
+    }
+}
+
+async fn review(
+    State(state): State<Arc<IdeState>>,
+    payload: Result<Json<CodeRequest>, JsonRejection>,
+) -> Response {
+    let request = match parse(payload) {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+    match state
+        .extension
+        .run_inline_code_review(&request.code, &request.language)
+        .await
+    {
+        Ok(comments) => Json(comments).into_response(),
+        Err(err) => model_error(err),
+    }
+}
+
+async fn test_suggestions(
+    State(state): State<Arc<IdeState>>,
+    payload: Result<Json<CodeRequest>, JsonRejection>,
+) -> Response {
+    let request = match parse(payload) {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+    match state
+        .extension
+        .get_test_suggestions(&request.code, &request.language)
+        .await
+    {
+        Ok(tests) => Json(json!({ "tests": tests })).into_response(),
+        Err(err) => model_error(err),
+    }
+}
+
+async fn explain(
+    State(state): State<Arc<IdeState>>,
+    payload: Result<Json<CodeRequest>, JsonRejection>,
+) -> Response {
+    let request = match parse(payload) {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+    match state
+        .extension
+        .explain_code(&request.code, &request.language)
+        .await
+    {
+        Ok(explanation) => Json(json!({ "explanation": explanation })).into_response(),
+        Err(err) => model_error(err),
+    }
+}
+
+async fn websocket(State(state): State<Arc<IdeState>>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| stream_suggestions(socket, state))
+}
+
+/// Answers each context the client sends with its suggestions, one message
+/// each. The model produces them in a single reply, so they are sent once it
+/// has finished; the socket saves reconnecting for every context.
+async fn stream_suggestions(mut socket: WebSocket, state: Arc<IdeState>) {
+    while let Some(Ok(message)) = socket.recv().await {
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        let replies = match serde_json::from_str::<ExtensionContext>(&text) {
+            Ok(context) => {
+                let suggestions = state.extension.get_code_suggestions(&context);
+                match crate::utils::budget::scoped(suggestions).await {
+                    Ok(suggestions) => {
+                        let count = suggestions.len();
+                        let mut replies: Vec<Value> = suggestions
+                            .into_iter()
+                            .map(|suggestion| json!({ "suggestion": suggestion }))
+                            .collect();
+                        replies.push(json!({ "done": true, "count": count }));
+                        replies
+                    }
+                    Err(err) => vec![json!({ "error": format!("{:#}", err) })],
+                }
+            }
+            Err(err) => vec![json!({ "error": format!("Invalid context: {}", err) })],
+        };
+        for reply in replies {
+            if socket.send(Message::Text(reply.to_string())).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn parse<T>(payload: Result<Json<T>, JsonRejection>) -> Result<T, Response> {
+    payload.map(|Json(value)| value).map_err(|rejection| {
+        let status = if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            StatusCode::PAYLOAD_TOO_LARGE
+        } else {
+            StatusCode::BAD_REQUEST
+        };
+        error(status, rejection.body_text())
+    })
+}
+
+fn model_error(err: anyhow::Error) -> Response {
+    error(StatusCode::BAD_GATEWAY, format!("{:#}", err))
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::adapters::ai::KandilAI;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    fn app(allowed_origins: &[&str]) -> Router {
+        let ai = KandilAI::new("ollama".to_string(), "llama3".to_string()).unwrap();
+        let origins = allowed_origins.iter().map(|o| o.to_string()).collect();
+        let state = IdeState::new(IdeExtension::new(Arc::new(ai)), origins);
+        router(Arc::new(state), 1024)
+    }
+
+    #[tokio::test]
+    async fn describes_every_endpoint() {
+        let response = app(&[])
+            .oneshot(
+                axum::http::Request::get("/api/ide")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let description: Value = serde_json::from_slice(&bytes).unwrap();
+        let paths: Vec<&str> = description["endpoints"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|endpoint| endpoint["path"].as_str().unwrap())
+            .collect();
+        assert!(paths.contains(&"/api/ide/suggestions"));
+        assert!(paths.contains(&"/api/ide/ws"));
+    }
+
+    #[tokio::test]
+    async fn only_configured_origins_may_call() {
+        let app = app(&["vscode-webview://kandil"]);
+        let preflight = |origin: &str| {
+            axum::http::Request::builder()
+                .method(Method::OPTIONS)
+                .uri("/api/ide/docs")
+                .header(header::ORIGIN, origin)
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let allowed = app
+            .clone()
+            .oneshot(preflight("vscode-webview://kandil"))
+            .await
+            .unwrap();
+        assert_eq!(allowed.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            allowed.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "vscode-webview://kandil"
+        );
+
+        let denied = app
+            .clone()
+            .oneshot(preflight("https://evil.example"))
+            .await
+            .unwrap();
+        assert_eq!(denied.status(), StatusCode::FORBIDDEN);
+
+        // Editors and curl send no Origin; bad bodies are refused before the model.
+        let no_origin = app
+            .oneshot(
+                axum::http::Request::post("/api/ide/docs")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from("{not json"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(no_origin.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn a_configured_token_is_required() {
+        let ai = KandilAI::new("ollama".to_string(), "llama3".to_string()).unwrap();
+        let state = IdeState::new(IdeExtension::new(Arc::new(ai)), Vec::new())
+            .with_token(Some("s3cret".to_string()));
+        let app = router(Arc::new(state), 1024);
+        let docs = |authorization: Option<&str>| {
+            let mut request = axum::http::Request::post("/api/ide/docs")
+                .header(header::CONTENT_TYPE, "application/json");
+            if let Some(authorization) = authorization {
+                request = request.header(header::AUTHORIZATION, authorization);
+            }
+            request.body(Body::from("{not json")).unwrap()
+        };
+
+        let missing = app.clone().oneshot(docs(None)).await.unwrap();
+        assert_eq!(missing.status(), StatusCode::UNAUTHORIZED);
+        let wrong = app
+            .clone()
+            .oneshot(docs(Some("Bearer guess")))
+            .await
+            .unwrap();
+        assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+        // The right token gets as far as body validation
+        let right = app.oneshot(docs(Some("Bearer s3cret"))).await.unwrap();
+        assert_eq!(right.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn public_addresses_need_a_token() {
+        let ai = KandilAI::new("ollama".to_string(), "llama3".to_string()).unwrap();
+        let err = serve(
+            "0.0.0.0:0",
+            IdeExtension::new(Arc::new(ai)),
+            WebConfig::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("without a token"));
+    }
+}
@@ -1 +1,64 @@
-pub mod dashboard;
\ No newline at end of file
+//! Web companion server
+//!
+//! Serves the dashboard and a chat API next to the CLI, plus liveness and
+//! readiness probes so the server can run behind Docker or Kubernetes health
+//! checks.
+
+mod chat;
+mod dashboard;
+mod health;
+pub mod ide;
+
+pub use dashboard::WebCompanionDashboard;
+
+use crate::utils::config::Config;
+use anyhow::{Context, Result};
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::Router;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// Builds the full application router. Rate limiting applies to the chat
+/// route only, so probes are never throttled.
+pub fn router(config: Config) -> Router {
+    WebCompanionDashboard::new()
+        .router()
+        .merge(chat::router(Arc::new(chat::ChatState::new(config))))
+        .merge(health::router(Arc::new(health::Readiness::new())))
+}
+
+/// Gives each request its own AI call budget, so a long-running server
+/// doesn't exhaust the process-wide one.
+async fn per_request_budget(request: Request, next: Next) -> Response {
+    crate::utils::budget::scoped(next.run(request)).await
+}
+
+/// Binds `address` and serves until Ctrl+C.
+pub async fn start(address: &str) -> Result<()> {
+    let config = Config::load()?;
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .with_context(|| format!("Failed to bind {}", address))?;
+    println!("Kandil web companion listening on http://{}", address);
+    println!("Probes: /healthz (liveness), /readyz (readiness)");
+    if config.web.chat_requests_per_minute > 0 {
+        println!(
+            "Chat API: POST /api/chat, limited to {} requests/min per client",
+            config.web.chat_requests_per_minute
+        );
+    }
+
+    let shutdown = crate::shutdown::ShutdownHandler::new();
+    shutdown
+        .setup_signal_handlers()
+        .await
+        .map_err(|err| anyhow::anyhow!("Cannot install Ctrl+C handler: {}", err))?;
+
+    let app = router(config).into_make_service_with_connect_info::<SocketAddr>();
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move { shutdown.wait_for_shutdown().await })
+        .await?;
+    Ok(())
+}
@@ -19,15 +19,43 @@ fn switch_model_rejects_invalid_provider() {
         .stderr(predicate::str::contains("Invalid provider"));
 }
 
+#[test]
+fn switch_model_needs_arguments_without_a_terminal() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.arg("switch-model");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Pass a provider and model"));
+}
+
 #[test]
 fn config_costs_shows_message() {
+    let data = std::env::temp_dir().join(format!("kandil_cli_costs_{}", uuid::Uuid::new_v4()));
     let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.env("HOME", &data).env("XDG_DATA_HOME", &data);
     cmd.args(["config", "costs"]);
     cmd.assert().success().stdout(predicate::str::contains(
-        "Cost tracking is available when using AI features",
+        "No AI calls have been recorded yet",
     ));
 }
 
+#[test]
+fn config_costs_exports_csv_with_a_stable_header() {
+    let data = std::env::temp_dir().join(format!("kandil_cli_costs_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&data).unwrap();
+    let output = data.join("monthly.csv");
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.env("HOME", &data).env("XDG_DATA_HOME", &data);
+    cmd.args(["config", "costs", "--summary", "--format", "csv", "--output"])
+        .arg(&output);
+    cmd.assert().success();
+    assert_eq!(
+        std::fs::read_to_string(&output).unwrap(),
+        "month,provider,calls,prompt_tokens,completion_tokens,cost_usd\r\n"
+    );
+    let _ = std::fs::remove_dir_all(data);
+}
+
 #[test]
 fn config_validate_ok_with_defaults() {
     let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
@@ -66,3 +94,618 @@ fn local_model_use_persists() {
         .success()
         .stdout(predicate::str::contains("Using local model"));
 }
+
+#[test]
+fn chat_rejects_message_with_file() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["chat", "hello", "--file", "prompt.txt"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn chat_reports_unreadable_file() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["--no-preflight", "chat", "--file", "does-not-exist.txt"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Cannot read does-not-exist.txt"));
+}
+
+#[test]
+fn chat_quiet_requires_output() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["chat", "hello", "--quiet"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--output"));
+}
+
+#[test]
+fn config_show_resilience_reports_env_overrides() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.env("KANDIL_RETRY_COUNT", "7");
+    cmd.args(["config", "show", "--section", "resilience"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"retry_count\s+7\s+env KANDIL_RETRY_COUNT").unwrap())
+        .stdout(predicate::str::contains("hybrid_latency_budget_ms"));
+}
+
+#[test]
+fn seed_must_be_an_integer() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["--seed", "lucky", "config", "costs"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--seed"));
+}
+
+#[test]
+fn self_improve_force_requires_apply() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["agent", "advanced", "self-improve", "src", "--force"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--apply"));
+}
+
+#[test]
+fn config_prompts_list_marks_overrides() {
+    let dir = std::env::temp_dir().join(format!("kandil_cli_prompts_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("review.tmpl"), "Review: {{context}}").unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.env("KANDIL_PROMPTS_DIR", &dir);
+    cmd.args(["config", "prompts", "list"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("review         custom"))
+        .stdout(predicate::str::contains("code           built-in"));
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn template_add_rejects_builtin_names() {
+    let dir = std::env::temp_dir().join(format!("kandil_cli_templates_{}", uuid::Uuid::new_v4()));
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.env("KANDIL_TEMPLATES_DIR", &dir);
+    cmd.args(["template", "add", "rust", "."]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--override"));
+}
+
+#[test]
+fn pwa_export_writes_custom_manifest() {
+    let dir = std::env::temp_dir().join(format!("kandil_cli_pwa_{}", uuid::Uuid::new_v4()));
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["pwa", "--output"]).arg(&dir);
+    cmd.args(["--app-name", "My Kandil", "--theme-color", "#123456"]);
+    cmd.assert().success();
+
+    let manifest = std::fs::read_to_string(dir.join("manifest.webmanifest")).unwrap();
+    assert!(manifest.contains("\"name\": \"My Kandil\""));
+    assert!(manifest.contains("\"theme_color\": \"#123456\""));
+    assert!(dir.join("icons/icon-512.png").is_file());
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn pwa_export_rejects_invalid_theme_color() {
+    let dir = std::env::temp_dir().join(format!("kandil_cli_pwa_{}", uuid::Uuid::new_v4()));
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["pwa", "--output"]).arg(&dir);
+    cmd.args(["--theme-color", "teal"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("hex colour"));
+}
+
+#[test]
+fn plugin_list_commands_shows_namespaced_triggers() {
+    let dir = std::env::temp_dir().join(format!("kandil_cli_plugins_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(dir.join("jira")).unwrap();
+    std::fs::write(
+        dir.join("jira").join("plugin.toml"),
+        "name = \"jira\"\nexecutable = \"kandil-jira\"\n\n[[commands]]\nname = \"issue\"\ndescription = \"Create an issue\"\n",
+    )
+    .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.env("KANDIL_PLUGINS_DIR", &dir);
+    cmd.args(["plugin", "list", "--commands"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("/jira:issue (/issue)"))
+        .stdout(predicate::str::contains("Create an issue"));
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn profile_report_goes_to_stderr_as_json() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.env("KANDIL_PROFILE", "1");
+    cmd.args(["config", "costs", "--profile-format", "json"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"spans\"").not())
+        .stderr(predicate::str::contains("\"spans\": []"));
+}
+
+#[test]
+fn local_model_list_remote_reports_unreachable_runtime() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.env("FOUNDRY_LOCAL_ENDPOINT", "http://127.0.0.1:9");
+    cmd.args(["local-model", "list-remote", "--runtime", "foundry"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Foundry Local is not reachable"));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["local-model", "list-remote", "--runtime", "mainframe"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown runtime 'mainframe'"));
+}
+
+#[test]
+fn verbose_reports_timing_on_stderr_only() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["-vv", "config", "costs"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Finished in").not())
+        .stderr(predicate::str::contains("Finished in"));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["config", "costs"]);
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Finished in").not());
+}
+
+#[test]
+fn chat_rejects_unknown_provider_override() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["--no-preflight", "chat", "hello", "--provider", "nope"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown provider 'nope'"));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["--no-preflight", "agent", "requirements", "x"]);
+    cmd.args(["--provider", "claude", "--model", "qwen2.5-coder-3b-q4"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("registered for provider"));
+}
+
+#[test]
+fn local_model_diagnostics_reports_configured_endpoints_as_json() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.env("FOUNDRY_LOCAL_ENDPOINT", "http://127.0.0.1:9");
+    cmd.args(["local-model", "diagnostics", "--format", "json"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("{"))
+        .stdout(predicate::str::contains(
+            "\"endpoint\": \"http://127.0.0.1:9\"",
+        ))
+        .stdout(predicate::str::contains("indicative only"));
+}
+
+#[test]
+fn windows_status_probes_configured_endpoints() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.env("FOUNDRY_LOCAL_ENDPOINT", "http://127.0.0.1:9");
+    cmd.args(["windows", "status"]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Foundry Local reachable at http://127.0.0.1:9/v1/models: ⚠️",
+    ));
+}
+
+#[test]
+fn qa_full_suite_writes_junit_xml() {
+    let path = std::env::temp_dir().join(format!("kandil_qa_{}.xml", uuid::Uuid::new_v4()));
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["--no-preflight", "agent", "qa", "full-suite", "."]);
+    cmd.args(["--format", "junit", "--output"]).arg(&path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("report written to"));
+
+    let xml = std::fs::read_to_string(&path).unwrap();
+    assert!(xml.starts_with("<?xml"));
+    assert!(xml.contains(r#"<testcase classname="cli::parser" name="test_cli_parsing""#));
+    let _ = std::fs::remove_file(path);
+
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args([
+        "--no-preflight",
+        "agent",
+        "qa",
+        "full-suite",
+        ".",
+        "--format",
+        "junit",
+    ]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--format junit needs --output"));
+}
+
+#[test]
+fn reset_breaker_rejects_unknown_providers() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["config", "reset-breaker", "nope"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown provider 'nope'"));
+}
+
+#[test]
+fn benchmark_history_without_runs_says_so() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["local-model", "benchmark", "history", "--model", "nope"]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "No benchmark history for nope yet",
+    ));
+}
+
+#[test]
+fn local_model_prune_dry_run_keeps_files() {
+    let data = std::env::temp_dir().join(format!("kandil_cli_prune_{}", uuid::Uuid::new_v4()));
+    let models = data.join("kandil").join("models");
+    std::fs::create_dir_all(&models).unwrap();
+    std::fs::write(models.join("old.gguf"), b"gguf").unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.env("HOME", &data).env("XDG_DATA_HOME", &data);
+    cmd.args(["local-model", "prune", "--older-than", "0", "--dry-run"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Would remove 1 model(s)"))
+        .stdout(predicate::str::contains("old.gguf"));
+    assert!(models.join("old.gguf").exists());
+    let _ = std::fs::remove_dir_all(data);
+}
+
+#[test]
+fn external_catalog_adds_models_and_bad_ones_are_ignored() {
+    let data = std::env::temp_dir().join(format!("kandil_cli_catalog_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&data).unwrap();
+    let catalog = data.join("models.json");
+    std::fs::write(
+        &catalog,
+        r#"{"models": [{
+            "name": "custom-coder-q4",
+            "huggingface_repo": "example/Custom-Coder-GGUF",
+            "filename": "custom-coder-q4_k_m.gguf",
+            "size_gb": 2.0,
+            "ram_required_gb": 6,
+            "speed_rating": {"Fast": 150},
+            "quality_rating": "Good",
+            "context_sizes": [8192]
+        }]}"#,
+    )
+    .unwrap();
+    let registry = |catalog: &std::path::Path| {
+        let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+        cmd.env("HOME", &data)
+            .env("XDG_DATA_HOME", &data)
+            .env("KANDIL_CATALOG", catalog);
+        cmd.args(["local-model", "registry", "--format", "json"]);
+        cmd.assert().success()
+    };
+    registry(&catalog).stdout(predicate::str::contains("custom-coder-q4"));
+
+    std::fs::write(&catalog, r#"{"models": [{"name": "broken"}]}"#).unwrap();
+    registry(&catalog)
+        .stdout(predicate::str::contains("qwen2.5-coder-7b-q4"))
+        .stderr(predicate::str::contains("Ignoring model catalog"));
+    let _ = std::fs::remove_dir_all(data);
+}
+
+#[test]
+fn tokens_count_reads_stdin_and_estimates_cost() {
+    let data = std::env::temp_dir().join(format!("kandil_cli_tokens_{}", uuid::Uuid::new_v4()));
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.env("HOME", &data).env("XDG_DATA_HOME", &data);
+    cmd.args(["tokens", "count", "--provider", "claude"])
+        .write_stdin("Hello, world!");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("4 tokens (cl100k_base, approximate)"))
+        .stdout(predicate::str::contains("Estimated input cost on claude:"));
+}
+
+#[test]
+fn create_dry_run_writes_nothing() {
+    let dir = std::env::temp_dir().join(format!("kandil_cli_create_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.current_dir(&dir);
+    cmd.args(["create", "rust", "demo", "--dry-run"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Cargo.toml"))
+        .stdout(predicate::str::contains("model:    llama3:70b"));
+    assert!(!dir.join("demo").exists());
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn maintenance_add_window_rejects_unknown_timezones() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["agent", "maintenance", "add-window", "0 2 * * Sun"]);
+    cmd.args(["--timezone", "Mars/Olympus"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown timezone 'Mars/Olympus'"));
+}
+
+#[test]
+fn deploy_rollback_of_a_missing_plan_fails() {
+    let plan = std::env::temp_dir().join(format!("kandil_cli_plan_{}.json", uuid::Uuid::new_v4()));
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["agent", "advanced", "deploy", "rollback"]).arg(&plan);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to read deployment plan"));
+}
+
+#[test]
+fn deploy_rollback_runs_once_per_execution() {
+    let data = std::env::temp_dir().join(format!("kandil_cli_deploy_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&data).unwrap();
+    let plan = data.join("plan.json");
+    std::fs::write(
+        &plan,
+        r#"{
+            "environment": "staging",
+            "steps": [{
+                "id": "deploy", "name": "Deploy", "description": "", "command": "true",
+                "dependencies": [], "timeout": 10, "success_conditions": []
+            }],
+            "dependencies": [],
+            "rollback_plan": {
+                "steps": [{"id": "rollback", "action": "Roll back", "command": "true", "description": ""}],
+                "conditions": [],
+                "notification_targets": []
+            },
+            "estimated_duration": "1 minute"
+        }"#,
+    )
+    .unwrap();
+    let deploy = |action: &str| {
+        let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+        cmd.env("HOME", &data).env("XDG_DATA_HOME", &data);
+        cmd.args(["agent", "advanced", "deploy", action]).arg(&plan);
+        cmd.assert()
+    };
+
+    deploy("rollback")
+        .success()
+        .stdout(predicate::str::contains("was never executed"));
+    deploy("execute").success();
+    deploy("rollback")
+        .success()
+        .stdout(predicate::str::contains("Rolled back staging"));
+    deploy("rollback")
+        .failure()
+        .stderr(predicate::str::contains("was already rolled back"));
+    let _ = std::fs::remove_dir_all(data);
+}
+
+#[test]
+fn agent_code_fails_when_the_design_cannot_be_read() {
+    let design = std::env::temp_dir().join(format!("kandil_cli_design_{}.md", uuid::Uuid::new_v4()));
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["agent", "code"]).arg(&design).args(["--language", "rust"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Cannot read design document"));
+}
+
+#[test]
+fn review_rejects_unknown_formats() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["agent", "advanced", "review", "src/main.rs", "--format", "xml"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value 'xml'"));
+}
+
+#[test]
+fn agent_timeout_bounds_a_stuck_provider() {
+    // Accepts connections but never answers, like a hung Ollama.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let config = std::env::temp_dir().join(format!("kandil_cli_config_{}.toml", uuid::Uuid::new_v4()));
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    std::fs::write(&config, format!("[endpoints]\nollama = \"{}\"\n", endpoint)).unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.arg("--no-preflight").arg("--config").arg(&config);
+    cmd.args(["agent", "--timeout", "2", "requirements", "a login page"]);
+    cmd.timeout(std::time::Duration::from_secs(60));
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("timed out after 2s"));
+    let _ = std::fs::remove_file(config);
+
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["agent", "--timeout", "0", "requirements", "x"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value '0'"));
+}
+
+#[test]
+fn lcov_export_refuses_estimated_coverage() {
+    // No coverage tool handles .txt files, so only an AI estimate is possible.
+    let output = std::env::temp_dir().join(format!("kandil_cli_lcov_{}.info", uuid::Uuid::new_v4()));
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["test", "coverage", "notes.txt", "notes_test.txt", "--format", "lcov"]);
+    cmd.arg("--output").arg(&output);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("no measured coverage to export as LCOV"));
+    assert!(!output.exists());
+}
+
+#[test]
+fn missing_config_file_is_an_empty_layer() {
+    let config = std::env::temp_dir().join(format!("kandil_cli_config_{}.toml", uuid::Uuid::new_v4()));
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.arg("--config").arg(&config).args(["config", "show"]);
+    cmd.assert().success();
+    assert!(!config.exists(), "showing the config must not create it");
+
+    std::fs::write(&config, "[ai\n").unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.arg("--config").arg(&config).args(["config", "show"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid config"));
+    let _ = std::fs::remove_file(config);
+}
+
+#[test]
+fn devops_pipeline_write_needs_a_ci_system() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.args(["agent", "advanced-features", "devops", "pipeline", "rust", "--write"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--ci"));
+}
+
+#[test]
+fn devops_pipeline_ci_flag_is_not_a_model_provider() {
+    let dir = std::env::temp_dir().join(format!("kandil_cli_ci_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(dir.join(".github/workflows")).unwrap();
+    std::fs::write(dir.join(".github/workflows/ci.yml"), "on: push\n").unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.current_dir(&dir).arg("--no-preflight");
+    cmd.args(["agent", "advanced-features", "devops", "pipeline", "rust"]);
+    cmd.args(["--ci", "github", "--write"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists. Pass --force"))
+        .stderr(predicate::str::contains("Unknown provider").not());
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn config_doctor_fixes_world_readable_config() {
+    use std::os::unix::fs::PermissionsExt;
+    let config = std::env::temp_dir().join(format!("kandil_cli_perms_{}.toml", uuid::Uuid::new_v4()));
+    std::fs::write(&config, "[ai]\nprovider = \"ollama\"\n").unwrap();
+    std::fs::set_permissions(&config, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.arg("--config").arg(&config).args(["config", "doctor"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("mode 0644"));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.arg("--config").arg(&config).args(["config", "doctor", "--fix"]);
+    cmd.assert().success();
+    let mode = std::fs::metadata(&config).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode, 0o600);
+    let _ = std::fs::remove_file(config);
+}
+
+#[test]
+fn chat_files_warns_when_a_glob_matches_nothing() {
+    let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+    cmd.env("KANDIL_CALL_BUDGET", "1");
+    cmd.args(["--no-preflight", "chat", "--files", "no_such_dir/**/*.zz", "hello"]);
+    cmd.assert()
+        .stderr(predicate::str::contains("--files no_such_dir/**/*.zz matched no files"));
+}
+
+/// Answers every request with `status` and an empty model list, like a
+/// provider accepting or rejecting a key. Returns its base URL.
+fn serve_status(status: &'static str) -> String {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                match stream.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => request.extend_from_slice(&buf[..read]),
+                }
+            }
+            let body = r#"{"data":[]}"#;
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+        }
+    });
+    address
+}
+
+#[test]
+fn config_rotate_key_verifies_before_storing_and_undoes_once() {
+    let data = std::env::temp_dir().join(format!("kandil_cli_rotate_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&data).unwrap();
+    let rejecting = data.join("rejecting.toml");
+    let accepting = data.join("accepting.toml");
+    for (config, status) in [(&rejecting, "401 Unauthorized"), (&accepting, "200 OK")] {
+        let endpoint = serve_status(status);
+        std::fs::write(config, format!("[endpoints]\nopenai = \"{}\"\n", endpoint)).unwrap();
+    }
+    let kandil = |config: &std::path::Path, args: &[&str], stdin: &str| {
+        let mut cmd = assert_cmd::Command::cargo_bin("kandil").unwrap();
+        cmd.env("HOME", &data)
+            .env("XDG_DATA_HOME", &data)
+            .env("KANDIL_CREDENTIAL_BACKEND", "file")
+            .env("KANDIL_MASTER_KEY", "correct horse");
+        cmd.arg("--no-preflight").arg("--config").arg(config);
+        cmd.args(["config", "rotate-key", "openai"]).args(args);
+        cmd.write_stdin(stdin);
+        cmd.assert()
+    };
+
+    kandil(&rejecting, &[], "sk-rejected\n")
+        .failure()
+        .stderr(predicate::str::contains(
+            "Verification failed, the stored key was not changed",
+        ));
+    kandil(&accepting, &["--undo"], "")
+        .failure()
+        .stderr(predicate::str::contains("nothing to undo"));
+    kandil(&accepting, &[], "sk-one\n")
+        .success()
+        .stdout(predicate::str::contains("no previous key to back up"));
+    kandil(&accepting, &[], "sk-two\n")
+        .success()
+        .stdout(predicate::str::contains("rotate-key openai --undo"));
+    kandil(&accepting, &["--undo"], "")
+        .success()
+        .stdout(predicate::str::contains(
+            "Restored the previous API key for openai",
+        ));
+    kandil(&accepting, &["--undo"], "")
+        .failure()
+        .stderr(predicate::str::contains("nothing to undo"));
+    let _ = std::fs::remove_dir_all(data);
+}